@@ -0,0 +1,103 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::time::{Duration, Instant};
+
+use wasmer::*;
+
+/// The number of functions of the modules, which fixes their relocations.
+const FUNCTIONS: usize = 64;
+
+/// The number of instructions in each function, making the code larger
+/// without adding relocations.
+const BODY_SIZES: [usize; 4] = [16, 64, 256, 1024];
+
+/// A module whose functions mix their parameter `body_size` times.
+fn module_wat(body_size: usize) -> String {
+    let mut wat = String::from("(module\n");
+    for function in 0..FUNCTIONS {
+        wat.push_str(&format!(
+            "  (func (export \"f{}\") (param i32) (result i32)\n",
+            function
+        ));
+        for step in 0..body_size {
+            wat.push_str(&format!(
+                "    (local.set 0 (i32.xor (i32.mul (local.get 0) (i32.const {})) (i32.const {})))\n",
+                step * 2 + 1,
+                function
+            ));
+        }
+        wat.push_str("    (local.get 0))\n");
+    }
+    wat.push(')');
+    wat
+}
+
+/// The time to load the module many times from `path`.
+fn load_time(store: &Store, path: &std::path::Path) -> Duration {
+    let start = Instant::now();
+    for _ in 0..20 {
+        black_box(unsafe { Module::deserialize_from_file(store, path) }.unwrap());
+    }
+    start.elapsed()
+}
+
+pub fn run_artifact_load(store: &Store, compiler_name: &str, c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let mut artifacts = Vec::new();
+    for &body_size in BODY_SIZES.iter() {
+        let module = Module::new(&store, module_wat(body_size)).unwrap();
+        let path = dir.path().join(format!("{}.wasmu", body_size));
+        module.serialize_to_file(&path).unwrap();
+        let size = std::fs::metadata(&path).unwrap().len();
+        artifacts.push((body_size, path, size));
+    }
+
+    // Loading a module copies its code once, from the mapped file to
+    // executable memory, and otherwise only deserializes its metadata.
+    // With the same relocations, 64 times more code must not take
+    // anywhere near 64 times longer to load.
+    let (_, smallest, _) = &artifacts[0];
+    let (_, largest, _) = &artifacts[artifacts.len() - 1];
+    load_time(store, smallest);
+    let ratio = load_time(store, largest).as_secs_f64() / load_time(store, smallest).as_secs_f64();
+    assert!(
+        ratio < 16.0,
+        "loading 64 times more code took {:.1} times longer with {}",
+        ratio,
+        compiler_name
+    );
+
+    let mut group = c.benchmark_group(format!("artifact load {}", compiler_name));
+    for (body_size, path, size) in &artifacts {
+        group.throughput(Throughput::Bytes(*size));
+        group.bench_with_input(BenchmarkId::from_parameter(body_size), path, |b, path| {
+            b.iter(|| black_box(unsafe { Module::deserialize_from_file(store, path) }.unwrap()))
+        });
+    }
+    group.finish();
+}
+
+fn run_artifact_load_benchmarks(_c: &mut Criterion) {
+    #[cfg(feature = "llvm")]
+    {
+        let store = Store::new(&Universal::new(wasmer_compiler_llvm::LLVM::new()).engine());
+        run_artifact_load(&store, "llvm", _c);
+    }
+
+    #[cfg(feature = "cranelift")]
+    {
+        let store =
+            Store::new(&Universal::new(wasmer_compiler_cranelift::Cranelift::new()).engine());
+        run_artifact_load(&store, "cranelift", _c);
+    }
+
+    #[cfg(feature = "singlepass")]
+    {
+        let store =
+            Store::new(&Universal::new(wasmer_compiler_singlepass::Singlepass::new()).engine());
+        run_artifact_load(&store, "singlepass", _c);
+    }
+}
+
+criterion_group!(benches, run_artifact_load_benchmarks);
+
+criterion_main!(benches);
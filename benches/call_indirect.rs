@@ -0,0 +1,79 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use wasmer::*;
+
+/// Objects dispatching through a table of methods, as interface tables
+/// do: most calls go to the method last called with the same signature.
+const WAT: &str = r#"
+(module
+  (type $method (func (param i32) (result i32)))
+  (table 4 funcref)
+  (elem (i32.const 0) $double $square $double $increment)
+  (func $double (type $method) (i32.shl (local.get 0) (i32.const 1)))
+  (func $square (type $method) (i32.mul (local.get 0) (local.get 0)))
+  (func $increment (type $method) (i32.add (local.get 0) (i32.const 1)))
+  (func (export "run") (param $n i32) (param $mask i32) (result i32)
+    (local $acc i32)
+    (loop $next
+      (local.set $acc
+        (call_indirect (type $method)
+          (local.get $acc)
+          (i32.and (local.get $n) (local.get $mask))))
+      (br_if $next
+        (local.tee $n (i32.sub (local.get $n) (i32.const 1)))))
+    (local.get $acc)))
+"#;
+
+/// The dispatches compared: always the same method, then a method
+/// changing every call.
+const DISPATCHES: &[(&str, i32)] = &[("monomorphic", 0), ("polymorphic", 3)];
+
+pub fn run_call_indirect(
+    compiler: Box<dyn CompilerConfig>,
+    compiler_name: &str,
+    c: &mut Criterion,
+) {
+    let mut group = c.benchmark_group(format!("call_indirect {}", compiler_name));
+    let store = Store::new(&Universal::new(compiler).engine());
+    let module = Module::new(&store, WAT).unwrap();
+    let instance = Instance::new(&module, &imports! {}).unwrap();
+    let run: NativeFunc<(i32, i32), i32> = instance.exports.get_native_function("run").unwrap();
+    for &(name, mask) in DISPATCHES {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &mask, |b, &mask| {
+            b.iter(|| black_box(run.call(black_box(10_000), mask).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+fn run_call_indirect_benchmarks(_c: &mut Criterion) {
+    #[cfg(feature = "llvm")]
+    {
+        run_call_indirect(Box::new(wasmer_compiler_llvm::LLVM::new()), "llvm", _c);
+    }
+
+    #[cfg(feature = "cranelift")]
+    {
+        run_call_indirect(
+            Box::new(wasmer_compiler_cranelift::Cranelift::new()),
+            "cranelift",
+            _c,
+        );
+    }
+
+    #[cfg(feature = "singlepass")]
+    {
+        run_call_indirect(
+            Box::new(wasmer_compiler_singlepass::Singlepass::new()),
+            "singlepass",
+            _c,
+        );
+        let mut cached = wasmer_compiler_singlepass::Singlepass::new();
+        cached.call_indirect_inline_cache(true);
+        run_call_indirect(Box::new(cached), "singlepass cached", _c);
+    }
+}
+
+criterion_group!(benches, run_call_indirect_benchmarks);
+
+criterion_main!(benches);
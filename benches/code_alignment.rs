@@ -0,0 +1,81 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use wasmer::*;
+
+/// Functions calling each other in a tight loop, whose speed depends on
+/// how their code falls in the instruction cache.
+const WAT: &str = r#"
+(module
+  (func $mix (param i32 i32) (result i32)
+    (i32.xor (i32.mul (local.get 0) (i32.const 31)) (local.get 1)))
+  (func (export "run") (param $n i32) (result i32)
+    (local $acc i32)
+    (loop $next
+      (local.set $acc (call $mix (local.get $acc) (local.get $n)))
+      (local.set $acc (i32.rotl (local.get $acc) (i32.const 5)))
+      (br_if $next
+        (local.tee $n (i32.sub (local.get $n) (i32.const 1)))))
+    (local.get $acc)))
+"#;
+
+/// The alignments compared: the default one, then functions and loops
+/// aligned to 32 and 64 bytes, then functions spread further apart.
+fn alignments() -> Vec<(&'static str, CodeAlignment)> {
+    let mut aligned_32 = CodeAlignment::new();
+    aligned_32.function(32).loop_header(32);
+    let mut aligned_64 = CodeAlignment::new();
+    aligned_64.function(64).loop_header(64);
+    let mut padded = CodeAlignment::new();
+    padded.function(64).loop_header(64).function_padding(64);
+    vec![
+        ("default", CodeAlignment::new()),
+        ("aligned 32", aligned_32),
+        ("aligned 64", aligned_64),
+        ("padded 64", padded),
+    ]
+}
+
+pub fn run_code_alignment<C>(compiler: C, compiler_name: &str, c: &mut Criterion)
+where
+    C: Into<Box<dyn CompilerConfig>> + Clone,
+{
+    let mut group = c.benchmark_group(format!("code alignment {}", compiler_name));
+    for (name, alignment) in alignments() {
+        let engine = Universal::new(compiler.clone())
+            .code_alignment(alignment)
+            .engine();
+        let store = Store::new(&engine);
+        let module = Module::new(&store, WAT).unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+        let run: NativeFunc<i32, i32> = instance.exports.get_native_function("run").unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(name), &run, |b, run| {
+            b.iter(|| black_box(run.call(black_box(10_000)).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+fn run_code_alignment_benchmarks(_c: &mut Criterion) {
+    #[cfg(feature = "llvm")]
+    {
+        run_code_alignment(wasmer_compiler_llvm::LLVM::new(), "llvm", _c);
+    }
+
+    #[cfg(feature = "cranelift")]
+    {
+        run_code_alignment(wasmer_compiler_cranelift::Cranelift::new(), "cranelift", _c);
+    }
+
+    #[cfg(feature = "singlepass")]
+    {
+        run_code_alignment(
+            wasmer_compiler_singlepass::Singlepass::new(),
+            "singlepass",
+            _c,
+        );
+    }
+}
+
+criterion_group!(benches, run_code_alignment_benchmarks);
+
+criterion_main!(benches);
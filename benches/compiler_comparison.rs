@@ -0,0 +1,221 @@
+//! Compares the enabled compilers on real-world modules, reporting for
+//! each of them the compile time, the size of the compiled artifact and
+//! the run time of every module.
+//!
+//! The modules are looked up in `benches/modules`, or in the directory
+//! given by `WASMER_BENCH_MODULES`, and the missing ones are skipped. See
+//! `benches/README.md` for how to get them.
+//!
+//! The report is printed, and written as JSON to
+//! `target/compiler-comparison.json`, or to the file given by
+//! `WASMER_BENCH_REPORT`, for CI to track regressions.
+
+use serde_json::{json, Value as JsonValue};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+
+use wasmer::*;
+use wasmer_wasi::{WasiError, WasiState};
+
+/// The number of times each module is run, the median and fastest runs
+/// being reported.
+const RUNS: usize = 5;
+
+/// How a module is run.
+enum Entry {
+    /// Calls `_start` with WASI, passing `args` to the program.
+    Wasi { args: &'static [&'static str] },
+    /// Calls the exported function `name` without arguments. The module
+    /// may import `env.clock_ms() -> i64` and the AssemblyScript
+    /// `env.abort`.
+    Export { name: &'static str },
+}
+
+struct BenchModule {
+    name: &'static str,
+    file: &'static str,
+    entry: Entry,
+}
+
+const MODULES: &[BenchModule] = &[
+    BenchModule {
+        name: "coremark",
+        file: "coremark-minimal.wasm",
+        entry: Entry::Export { name: "run" },
+    },
+    BenchModule {
+        name: "zstd",
+        file: "zstd.wasm",
+        entry: Entry::Wasi {
+            args: &["-b1", "-e3", "-i1"],
+        },
+    },
+    BenchModule {
+        name: "assemblyscript-mandelbrot",
+        file: "as-mandelbrot.wasm",
+        entry: Entry::Export { name: "run" },
+    },
+    BenchModule {
+        name: "assemblyscript-game-of-life",
+        file: "as-game-of-life.wasm",
+        entry: Entry::Export { name: "run" },
+    },
+    BenchModule {
+        name: "quickjs",
+        file: "qjs.wasm",
+        entry: Entry::Wasi {
+            args: &[
+                "--eval",
+                "let s = 0; for (let i = 0; i < 2000000; i++) { s = (s + i * i) % 65521; }",
+            ],
+        },
+    },
+];
+
+fn modules_dir() -> PathBuf {
+    env::var_os("WASMER_BENCH_MODULES")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/modules"))
+}
+
+/// Finds the file of a module, QuickJS being shipped with the C API
+/// examples.
+fn module_path(module: &BenchModule) -> Option<PathBuf> {
+    let path = modules_dir().join(module.file);
+    if path.exists() {
+        return Some(path);
+    }
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("lib/c-api/examples/assets")
+        .join(module.file);
+    if path.exists() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn clock_ms() -> i64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    now.as_millis() as i64
+}
+
+fn abort(_message: i32, _file: i32, _line: i32, _column: i32) -> Result<(), RuntimeError> {
+    Err(RuntimeError::new("abort"))
+}
+
+fn env_imports(store: &Store) -> ImportObject {
+    imports! {
+        "env" => {
+            "clock_ms" => Function::new_native(store, clock_ms),
+            "abort" => Function::new_native(store, abort),
+        },
+    }
+}
+
+/// Instantiates the module and times its entry.
+fn run_once(store: &Store, module: &Module, bench: &BenchModule) -> anyhow::Result<Duration> {
+    let (instance, name) = match &bench.entry {
+        Entry::Wasi { args } => {
+            let mut wasi_env = WasiState::new(bench.name).args(*args).finalize()?;
+            let import_object = wasi_env.import_object(module)?;
+            (Instance::new(module, &import_object)?, "_start")
+        }
+        Entry::Export { name } => (Instance::new(module, &env_imports(store))?, *name),
+    };
+    let function = instance.exports.get_function(name)?;
+    let start = Instant::now();
+    let result = function.call(&[]);
+    let elapsed = start.elapsed();
+    match result {
+        Ok(_) => Ok(elapsed),
+        Err(error) => match error.downcast::<WasiError>() {
+            Ok(WasiError::Exit(0)) => Ok(elapsed),
+            Ok(error) => Err(error.into()),
+            Err(error) => Err(error.into()),
+        },
+    }
+}
+
+fn bench_module(store: &Store, bench: &BenchModule, wasm: &[u8]) -> anyhow::Result<JsonValue> {
+    let start = Instant::now();
+    let module = Module::new(store, wasm)?;
+    let compile_time = start.elapsed();
+    let code_size = module.serialize()?.len();
+
+    let mut runs = (0..RUNS)
+        .map(|_| run_once(store, &module, bench))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    runs.sort();
+
+    Ok(json!({
+        "compile_time_ms": compile_time.as_secs_f64() * 1000.0,
+        "artifact_size": code_size,
+        "run_time_ms": {
+            "median": runs[RUNS / 2].as_secs_f64() * 1000.0,
+            "min": runs[0].as_secs_f64() * 1000.0,
+        },
+    }))
+}
+
+fn compilers() -> Vec<(&'static str, Box<dyn CompilerConfig>)> {
+    let mut compilers: Vec<(&'static str, Box<dyn CompilerConfig>)> = Vec::new();
+    #[cfg(feature = "llvm")]
+    compilers.push(("llvm", Box::new(wasmer_compiler_llvm::LLVM::new())));
+    #[cfg(feature = "cranelift")]
+    compilers.push((
+        "cranelift",
+        Box::new(wasmer_compiler_cranelift::Cranelift::new()),
+    ));
+    #[cfg(feature = "singlepass")]
+    compilers.push((
+        "singlepass",
+        Box::new(wasmer_compiler_singlepass::Singlepass::new()),
+    ));
+    compilers
+}
+
+fn main() -> anyhow::Result<()> {
+    // `cargo bench` passes `--bench`, and filters the benches by name.
+    let filter = env::args().skip(1).find(|arg| !arg.starts_with("--"));
+
+    let mut results = Vec::new();
+    for (compiler_name, compiler_config) in compilers() {
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        for bench in MODULES {
+            if let Some(filter) = &filter {
+                if !bench.name.contains(filter.as_str()) {
+                    continue;
+                }
+            }
+            let path = match module_path(bench) {
+                Some(path) => path,
+                None => {
+                    eprintln!("skipping {}: {} not found", bench.name, bench.file);
+                    continue;
+                }
+            };
+            let wasm = fs::read(&path)?;
+            let mut result = bench_module(&store, bench, &wasm)?;
+            println!("{} {}: {}", bench.name, compiler_name, result);
+            result["module"] = json!(bench.name);
+            result["compiler"] = json!(compiler_name);
+            results.push(result);
+        }
+    }
+
+    let report = json!({
+        "target": Triple::host().to_string(),
+        "runs": RUNS,
+        "results": results,
+    });
+    let report_path = env::var_os("WASMER_BENCH_REPORT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("target/compiler-comparison.json")
+        });
+    fs::write(&report_path, serde_json::to_string_pretty(&report)?)?;
+    eprintln!("report written to {}", report_path.display());
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use wasmer::*;
+
+/// The sizes of the bulk memory operations measured, in bytes.
+const SIZES: [u32; 4] = [64, 4096, 65536, 1 << 20];
+
+/// The number of 64 KiB active data segments of the data-heavy module.
+const DATA_SEGMENTS: usize = 64;
+
+const BULK_WAT: &str = r#"
+(module
+  (memory 64)
+  (data $segment "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef")
+  (func (export "fill") (param $len i32)
+    (memory.fill (i32.const 0) (i32.const 0x5a) (local.get $len)))
+  (func (export "copy") (param $len i32)
+    (memory.copy (i32.const 0x200000) (i32.const 0) (local.get $len)))
+  (func (export "init") (param $len i32)
+    (memory.init $segment (i32.const 0) (i32.const 0) (local.get $len))))
+"#;
+
+/// A module whose instantiation is dominated by copying its data
+/// segments to its memory.
+fn data_heavy_wat() -> String {
+    let segment = "x".repeat(65536);
+    let mut wat = format!("(module (memory {})", DATA_SEGMENTS);
+    for i in 0..DATA_SEGMENTS {
+        wat.push_str(&format!(
+            " (data (i32.const {}) \"{}\")",
+            i * 65536,
+            segment
+        ));
+    }
+    wat.push(')');
+    wat
+}
+
+pub fn run_memory_operations<C>(compiler: C, compiler_name: &str, c: &mut Criterion)
+where
+    C: Into<Box<dyn CompilerConfig>>,
+{
+    let store = Store::new(&Universal::new(compiler).engine());
+
+    let module = Module::new(&store, data_heavy_wat()).unwrap();
+    let mut group = c.benchmark_group(format!("data segments {}", compiler_name));
+    group.throughput(Throughput::Bytes((DATA_SEGMENTS * 65536) as u64));
+    group.bench_function("instantiate", |b| {
+        b.iter(|| black_box(Instance::new(&module, &imports! {}).unwrap()))
+    });
+    group.finish();
+
+    let module = Module::new(&store, BULK_WAT).unwrap();
+    let instance = Instance::new(&module, &imports! {}).unwrap();
+    for operation in ["fill", "copy"] {
+        let run: NativeFunc<u32, ()> = instance.exports.get_native_function(operation).unwrap();
+        let mut group = c.benchmark_group(format!("memory.{} {}", operation, compiler_name));
+        for size in SIZES {
+            group.throughput(Throughput::Bytes(size.into()));
+            group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+                b.iter(|| run.call(black_box(size)).unwrap())
+            });
+        }
+        group.finish();
+    }
+
+    // Passive segments are small in practice, so only the call itself is
+    // measured.
+    let init: NativeFunc<u32, ()> = instance.exports.get_native_function("init").unwrap();
+    c.bench_function(&format!("memory.init {}", compiler_name), |b| {
+        b.iter(|| init.call(black_box(64)).unwrap())
+    });
+}
+
+fn run_memory_operations_benchmarks(_c: &mut Criterion) {
+    #[cfg(feature = "llvm")]
+    {
+        run_memory_operations(wasmer_compiler_llvm::LLVM::new(), "llvm", _c);
+    }
+
+    #[cfg(feature = "cranelift")]
+    {
+        run_memory_operations(wasmer_compiler_cranelift::Cranelift::new(), "cranelift", _c);
+    }
+
+    #[cfg(feature = "singlepass")]
+    {
+        run_memory_operations(
+            wasmer_compiler_singlepass::Singlepass::new(),
+            "singlepass",
+            _c,
+        );
+    }
+}
+
+criterion_group!(benches, run_memory_operations_benchmarks);
+
+criterion_main!(benches);
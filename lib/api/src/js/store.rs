@@ -19,6 +19,12 @@ impl Store {
         Self
     }
 
+    /// Returns whether the store runs wasm code deterministically. The
+    /// execution is up to the JavaScript engine, so it never is.
+    pub fn is_deterministic(&self) -> bool {
+        false
+    }
+
     /// Checks whether two stores are identical. A store is considered
     /// equal to another store if both have the same engine. The
     /// tunables are excluded from the logic.
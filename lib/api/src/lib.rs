@@ -321,6 +321,10 @@
 #![cfg_attr(feature = "wat", doc = "(enabled),")]
 #![cfg_attr(not(feature = "wat"), doc = "(disabled),")]
 //!   enables `wasmer` to parse the WebAssembly text format,
+//! - `wasmprinter`
+#![cfg_attr(feature = "wasmprinter", doc = "(enabled),")]
+#![cfg_attr(not(feature = "wasmprinter"), doc = "(disabled),")]
+//!   enables printing modules in the WebAssembly text format,
 //! - `universal`
 #![cfg_attr(feature = "universal", doc = "(enabled),")]
 #![cfg_attr(not(feature = "universal"), doc = "(disabled),")]
@@ -0,0 +1,285 @@
+//! Batching of host calls through a ring buffer in linear memory.
+//!
+//! Chatty ABIs, like the ones of graphics or logging libraries, make many
+//! small host calls, each paying for crossing the boundary between
+//! WebAssembly and the host. With a command buffer, the guest queues its
+//! requests as commands in its linear memory, and makes a single host call
+//! to have them all handled at once:
+//!
+//! - the guest writes commands with a [`CommandWriter`], over the bytes of
+//!   the buffer in its own memory;
+//! - the host reads them from an import with a [`CommandBuffer`], over the
+//!   memory of the instance.
+//!
+//! This API is experimental: the layout of the buffer may change.
+//!
+//! # Layout
+//!
+//! The buffer starts with a header of three little-endian `u32`:
+//!
+//! | Offset | Field      | Written by | Meaning                                  |
+//! |--------|------------|------------|------------------------------------------|
+//! | 0      | `capacity` | the guest  | the size of the ring, a multiple of 4    |
+//! | 4      | `head`     | the host   | the offset of the next command to read   |
+//! | 8      | `tail`     | the guest  | the offset after the last command queued |
+//!
+//! The ring of `capacity` bytes follows. Each command is an `opcode` and
+//! the `length` of its payload, as little-endian `u32`, then the payload,
+//! padded to a multiple of 4 bytes. A command never wraps around the end of
+//! the ring: if it doesn't fit before the end, the [`WRAP`] opcode is
+//! written instead and the command starts over at offset 0. The ring is
+//! empty when `head == tail`, and 4 bytes are always left free so that a
+//! full ring can't look empty.
+//!
+//! ```
+//! # use wasmer::*;
+//! # fn main() -> anyhow::Result<()> {
+//! # let store = Store::default();
+//! let memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+//!
+//! // The guest side, usually in the guest itself.
+//! let mut buffer = vec![0; 64];
+//! let mut writer = CommandWriter::new(&mut buffer);
+//! writer.push(1, b"hello")?;
+//! writer.push(2, &[])?;
+//! memory.write(1024, &buffer)?;
+//!
+//! // The host side, in the import the guest calls to flush its commands.
+//! let commands = CommandBuffer::new(&memory, 1024);
+//! let mut received = vec![];
+//! commands.drain(|command| received.push((command.opcode, command.payload.to_vec())))?;
+//! assert_eq!(received, vec![(1, b"hello".to_vec()), (2, vec![])]);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::sys::{Memory, MemoryAccessError};
+use std::convert::TryInto;
+use thiserror::Error;
+
+/// The opcode marking the end of the commands before the end of the ring,
+/// the next command being at offset 0.
+pub const WRAP: u32 = u32::MAX;
+
+/// The size of the header of a command buffer, before its ring.
+pub const HEADER_SIZE: u32 = 12;
+
+/// The size of the opcode and length of a command, before its payload.
+const COMMAND_HEADER_SIZE: u32 = 8;
+
+const CAPACITY: usize = 0;
+const HEAD: usize = 4;
+const TAIL: usize = 8;
+
+/// A command read from a [`CommandBuffer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Command<'a> {
+    /// What the command does, as defined by the ABI.
+    pub opcode: u32,
+    /// The arguments of the command.
+    pub payload: &'a [u8],
+}
+
+/// An error while reading a [`CommandBuffer`].
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CommandBufferError {
+    /// The buffer is not entirely in the memory.
+    #[error(transparent)]
+    OutOfBounds(#[from] MemoryAccessError),
+
+    /// The header or a command of the buffer is invalid.
+    #[error("invalid command buffer: {0}")]
+    Invalid(String),
+}
+
+/// The ring is full, the command couldn't be queued.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("the command buffer is full")]
+pub struct CommandBufferFull;
+
+/// The host side of a command buffer: reads the commands queued by the
+/// guest in its memory.
+///
+/// See the [module documentation](self) for more information.
+#[derive(Debug, Clone)]
+pub struct CommandBuffer {
+    memory: Memory,
+    offset: u64,
+}
+
+impl CommandBuffer {
+    /// A command buffer at `offset` in `memory`.
+    pub fn new(memory: &Memory, offset: u32) -> Self {
+        Self {
+            memory: memory.clone(),
+            offset: offset.into(),
+        }
+    }
+
+    /// Calls `handler` on each command queued, in order, then marks them
+    /// as read. Returns how many commands there were.
+    ///
+    /// The payloads are copied out of the memory, so `handler` may call
+    /// back into the instance. Nothing is marked as read if the buffer
+    /// is invalid, but the commands before the invalid one were handled.
+    pub fn drain(&self, mut handler: impl FnMut(Command<'_>)) -> Result<usize, CommandBufferError> {
+        let mut header = [0; HEADER_SIZE as usize];
+        self.memory.read(self.offset, &mut header)?;
+        let capacity = read_u32(&header, CAPACITY);
+        let mut head = read_u32(&header, HEAD);
+        let tail = read_u32(&header, TAIL);
+        if capacity <= COMMAND_HEADER_SIZE
+            || capacity % 4 != 0
+            || head >= capacity
+            || head % 4 != 0
+            || tail >= capacity
+            || tail % 4 != 0
+        {
+            return Err(CommandBufferError::Invalid(format!(
+                "header capacity={} head={} tail={}",
+                capacity, head, tail
+            )));
+        }
+        let ring = self.offset + u64::from(HEADER_SIZE);
+        // Checks that the whole ring is in the memory.
+        let mut ring_end = [0; 4];
+        self.memory
+            .read(ring + u64::from(capacity - 4), &mut ring_end)?;
+
+        let mut payload = Vec::new();
+        let mut count = 0;
+        // The bytes read so far, which can't exceed the ring when it is
+        // valid.
+        let mut read = 0u64;
+        while head != tail {
+            let mut command = [0; COMMAND_HEADER_SIZE as usize];
+            let room = capacity - head;
+            self.memory.read(
+                ring + u64::from(head),
+                &mut command[..room.min(COMMAND_HEADER_SIZE) as usize],
+            )?;
+            let opcode = read_u32(&command, 0);
+            let length = read_u32(&command, 4);
+            let size = if opcode == WRAP {
+                Some(room)
+            } else {
+                command_size(length).filter(|&size| size <= room)
+            };
+            read += u64::from(size.unwrap_or(capacity));
+            let size = match size {
+                Some(size) if read < u64::from(capacity) => size,
+                _ => {
+                    return Err(CommandBufferError::Invalid(format!(
+                        "command at {} of length {} overflows the ring",
+                        head, length
+                    )))
+                }
+            };
+            if opcode != WRAP {
+                payload.resize(length as usize, 0);
+                self.memory
+                    .read(ring + u64::from(head + COMMAND_HEADER_SIZE), &mut payload)?;
+                handler(Command {
+                    opcode,
+                    payload: &payload,
+                });
+                count += 1;
+            }
+            head = (head + size) % capacity;
+        }
+        self.memory
+            .write(self.offset + HEAD as u64, &head.to_le_bytes())?;
+        Ok(count)
+    }
+}
+
+/// The guest side of a command buffer: queues commands in the bytes of
+/// the buffer.
+///
+/// It works on plain bytes, so guests written in Rust can use the same
+/// code over the buffer in their own memory. It reads the head written by
+/// the [`CommandBuffer`] to know how much room is left.
+///
+/// See the [module documentation](self) for more information.
+#[derive(Debug)]
+pub struct CommandWriter<'a> {
+    buffer: &'a mut [u8],
+}
+
+impl<'a> CommandWriter<'a> {
+    /// Makes an empty command buffer over `buffer`, header included.
+    ///
+    /// # Panics
+    ///
+    /// If `buffer` is too small to hold a command.
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        let capacity = (buffer.len() as u32).saturating_sub(HEADER_SIZE) & !3;
+        assert!(
+            capacity > COMMAND_HEADER_SIZE,
+            "the command buffer is too small"
+        );
+        write_u32(buffer, CAPACITY, capacity);
+        write_u32(buffer, HEAD, 0);
+        write_u32(buffer, TAIL, 0);
+        Self { buffer }
+    }
+
+    /// Resumes writing in a command buffer made with [`CommandWriter::new`].
+    pub fn resume(buffer: &'a mut [u8]) -> Self {
+        Self { buffer }
+    }
+
+    /// Queues a command, or returns [`CommandBufferFull`] if there's not
+    /// enough room left for it, leaving the buffer untouched.
+    pub fn push(&mut self, opcode: u32, payload: &[u8]) -> Result<(), CommandBufferFull> {
+        debug_assert_ne!(opcode, WRAP, "the wrap opcode is reserved");
+        let capacity = read_u32(self.buffer, CAPACITY);
+        let head = read_u32(self.buffer, HEAD);
+        let tail = read_u32(self.buffer, TAIL);
+        let size = command_size(payload.len() as u32).ok_or(CommandBufferFull)?;
+        // The free bytes after `tail`, before the end of the ring and in
+        // total, always keeping 4 bytes free.
+        let free = (head + capacity - tail - 4) % capacity;
+        let before_end = if head > tail { free } else { capacity - tail };
+        let start = if size <= before_end {
+            tail
+        } else if head <= tail && size <= head.saturating_sub(4) {
+            write_u32(self.buffer, (HEADER_SIZE + tail) as usize, WRAP);
+            0
+        } else {
+            return Err(CommandBufferFull);
+        };
+        if start == tail && size > free {
+            return Err(CommandBufferFull);
+        }
+
+        let at = (HEADER_SIZE + start) as usize;
+        write_u32(self.buffer, at, opcode);
+        write_u32(self.buffer, at + 4, payload.len() as u32);
+        let payload_at = at + COMMAND_HEADER_SIZE as usize;
+        self.buffer[payload_at..payload_at + payload.len()].copy_from_slice(payload);
+        write_u32(self.buffer, TAIL, (start + size) % capacity);
+        Ok(())
+    }
+
+    /// Whether all the commands queued were read.
+    pub fn is_empty(&self) -> bool {
+        read_u32(self.buffer, HEAD) == read_u32(self.buffer, TAIL)
+    }
+}
+
+/// The size of a command with a payload of `length` bytes, if it can be
+/// represented.
+fn command_size(length: u32) -> Option<u32> {
+    length
+        .checked_add(COMMAND_HEADER_SIZE + 3)
+        .map(|size| size & !3)
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn write_u32(bytes: &mut [u8], at: usize, value: u32) {
+    bytes[at..at + 4].copy_from_slice(&value.to_le_bytes());
+}
@@ -1,4 +1,4 @@
-use crate::sys::{ExportError, Instance};
+use crate::sys::{ExportError, Exports, Instance, NativeFunc, WasmTypeList};
 use thiserror::Error;
 
 /// An error while initializing the user supplied host env with the `WasmerEnv` trait.
@@ -222,3 +222,93 @@ impl<T> Default for LazyInit<T> {
 unsafe impl<T: Send> Send for LazyInit<T> {}
 // I thought we could opt out of sync..., look into this
 // unsafe impl<T> !Sync for InitWithInstance<T> {}
+
+/// A host environment wrapper that gives host functions access to the
+/// exports of the instance that is calling them.
+///
+/// Host functions frequently need to call back into the guest while they
+/// are running, for instance to ask the guest allocator (`malloc`) for a
+/// buffer before writing a result into it. `FrozenFuncEnv` captures the
+/// exports of the calling instance when the environment is initialized,
+/// holding them as weak references so that no reference cycle is created
+/// between the instance and its imports.
+///
+/// The exports are frozen at instantiation time: they are never mutated
+/// afterwards, so re-entering the guest from a host function does not
+/// require any exclusive borrow.
+///
+/// # Example
+///
+/// ```
+/// # use wasmer::{FrozenFuncEnv, WasmerEnv};
+/// #[derive(WasmerEnv, Clone)]
+/// struct Env {
+///     multiplier: i32,
+/// }
+///
+/// fn host_fn(env: &FrozenFuncEnv<Env>, x: i32) -> i32 {
+///     let double = env
+///         .get_native_function::<i32, i32>("double")
+///         .expect("the guest exports `double`");
+///     double.call(x).unwrap() * env.multiplier
+/// }
+///
+/// let env = FrozenFuncEnv::new(Env { multiplier: 3 });
+/// ```
+#[derive(Clone)]
+pub struct FrozenFuncEnv<Env> {
+    env: Env,
+    exports: LazyInit<Exports>,
+}
+
+impl<Env> FrozenFuncEnv<Env> {
+    /// Wraps a host environment.
+    pub fn new(env: Env) -> Self {
+        Self {
+            env,
+            exports: LazyInit::new(),
+        }
+    }
+
+    /// Returns a reference to the wrapped host environment.
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+
+    /// Returns the exports of the calling instance, or `None` if the
+    /// environment hasn't been initialized by an instantiation yet.
+    pub fn exports(&self) -> Option<&Exports> {
+        self.exports.get_ref()
+    }
+
+    /// Gets an export of the calling instance as a `NativeFunc`, so that it
+    /// can be called re-entrantly from the host function.
+    pub fn get_native_function<Args, Rets>(
+        &self,
+        name: &str,
+    ) -> Result<NativeFunc<Args, Rets>, ExportError>
+    where
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+    {
+        self.exports()
+            .ok_or_else(|| ExportError::Missing(name.to_string()))?
+            .get_native_function(name)
+    }
+}
+
+impl<Env> std::ops::Deref for FrozenFuncEnv<Env> {
+    type Target = Env;
+
+    fn deref(&self) -> &Self::Target {
+        &self.env
+    }
+}
+
+impl<Env: WasmerEnv> WasmerEnv for FrozenFuncEnv<Env> {
+    fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError> {
+        self.env.init_with_instance(instance)?;
+        self.exports.initialize(instance.exports.clone_weak());
+        Ok(())
+    }
+}
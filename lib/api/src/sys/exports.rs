@@ -1,7 +1,7 @@
 use crate::sys::externals::{Extern, Function, Global, Memory, Table};
 use crate::sys::import_object::LikeNamespace;
-use crate::sys::native::NativeFunc;
-use crate::sys::WasmTypeList;
+use crate::sys::native::{LazyNativeFunc, NativeFunc};
+use crate::sys::{ExternType, WasmTypeList};
 use indexmap::IndexMap;
 use loupe::MemoryUsage;
 use std::fmt;
@@ -150,6 +150,21 @@ impl Exports {
             .map_err(|_| ExportError::IncompatibleType)
     }
 
+    /// Get an export as a [`LazyNativeFunc`].
+    ///
+    /// Only the kind of the export is checked here: the function signature
+    /// is checked the first time [`LazyNativeFunc::get`] is called.
+    pub fn get_native_function_lazy<Args, Rets>(
+        &self,
+        name: &str,
+    ) -> Result<LazyNativeFunc<Args, Rets>, ExportError>
+    where
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+    {
+        Ok(LazyNativeFunc::new(self.get_function(name)?))
+    }
+
     /// Hack to get this working with nativefunc too
     pub fn get_with_generics<'a, T, Args, Rets>(&'a self, name: &str) -> Result<T, ExportError>
     where
@@ -189,6 +204,33 @@ impl Exports {
         self.map.contains_key(&name.into())
     }
 
+    /// Returns a copy of the `Exports` map where every extern holds a weak
+    /// reference to its instance.
+    ///
+    /// This is useful for storing the exports inside a type implementing
+    /// `WasmerEnv` without creating a reference cycle.
+    pub fn clone_weak(&self) -> Self {
+        self.map
+            .iter()
+            .map(|(name, extern_)| {
+                let mut extern_ = extern_.clone();
+                Exportable::into_weak_instance_ref(&mut extern_);
+                (name.clone(), extern_)
+            })
+            .collect()
+    }
+
+    /// Get an iterator over the names and types of the exports.
+    ///
+    /// Unlike [`Exports::iter`], no `Extern` is cloned or handed out,
+    /// which makes this the cheapest way to inspect what an instance
+    /// exports.
+    pub fn iter_typed(&self) -> impl Iterator<Item = (&str, ExternType)> + ExactSizeIterator {
+        self.map
+            .iter()
+            .map(|(name, extern_)| (name.as_str(), extern_.ty()))
+    }
+
     /// Get an iterator over the exports.
     pub fn iter(&self) -> ExportsIterator<impl Iterator<Item = (&String, &Extern)>> {
         ExportsIterator {
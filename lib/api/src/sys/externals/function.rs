@@ -4,6 +4,7 @@ use crate::sys::store::Store;
 use crate::sys::types::{Val, ValFuncRef};
 use crate::sys::FunctionType;
 use crate::sys::NativeFunc;
+use crate::sys::PatchPointKind;
 use crate::sys::RuntimeError;
 use crate::sys::WasmerEnv;
 pub use inner::{FromToNativeWasmType, HostFunction, WasmTypeList, WithEnv, WithoutEnv};
@@ -12,14 +13,50 @@ use loupe::MemoryUsage;
 use std::cmp::max;
 use std::ffi::c_void;
 use std::fmt;
+use std::ops::BitOr;
 use std::sync::Arc;
-use wasmer_engine::{Export, ExportFunction, ExportFunctionMetadata};
+use wasmer_engine::{Export, ExportFunction, ExportFunctionMetadata, HostFunctionInfo};
 use wasmer_vm::{
     on_host_stack, raise_user_trap, resume_panic, wasmer_call_trampoline, ImportInitializerFuncPtr,
     VMCallerCheckedAnyfunc, VMDynamicFunctionContext, VMFuncRef, VMFunction, VMFunctionBody,
     VMFunctionEnvironment, VMFunctionKind, VMTrampoline,
 };
 
+/// Flags telling what a host function may do, letting WebAssembly
+/// call it more cheaply when it does less.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FunctionFlags(u32);
+
+impl FunctionFlags {
+    /// The function doesn't trap or panic, call back into WebAssembly,
+    /// or grow a memory or table, and uses little stack, as math or
+    /// logging functions do. It's called right on the WebAssembly
+    /// stack, without switching to the host stack first.
+    ///
+    /// A leaf function trapping or panicking anyway is still handled,
+    /// but runs on the stack of the WebAssembly code, which may be
+    /// close to overflowing.
+    pub const LEAF: Self = Self(1);
+
+    /// No flags.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Whether all the flags of `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for FunctionFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
 /// A WebAssembly `function` instance.
 ///
 /// A function instance is the runtime representation of a function.
@@ -234,6 +271,7 @@ impl Function {
             store: store.clone(),
             exported: ExportFunction {
                 metadata: Some(Arc::new(metadata)),
+                info: None,
                 vm_function: VMFunction {
                     address,
                     kind: VMFunctionKind::Dynamic,
@@ -264,6 +302,35 @@ impl Function {
     /// let f = Function::new_native(&store, sum);
     /// ```
     pub fn new_native<F, Args, Rets, Env>(store: &Store, func: F) -> Self
+    where
+        F: HostFunction<Args, Rets, WithoutEnv, Env>,
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+        Env: Sized + 'static,
+    {
+        Self::new_native_with_flags(store, FunctionFlags::empty(), func)
+    }
+
+    /// Creates a new host `Function` from a native function, with
+    /// `flags` telling what it may do.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Store, Function, FunctionFlags};
+    /// # let store = Store::default();
+    /// #
+    /// fn sqrt(x: f64) -> f64 {
+    ///     x.sqrt()
+    /// }
+    ///
+    /// let f = Function::new_native_with_flags(&store, FunctionFlags::LEAF, sqrt);
+    /// ```
+    pub fn new_native_with_flags<F, Args, Rets, Env>(
+        store: &Store,
+        flags: FunctionFlags,
+        func: F,
+    ) -> Self
     where
         F: HostFunction<Args, Rets, WithoutEnv, Env>,
         Args: WasmTypeList,
@@ -273,7 +340,7 @@ impl Function {
         if std::mem::size_of::<F>() != 0 {
             Self::closures_unsupported_panic();
         }
-        let function = inner::Function::<Args, Rets>::new(func);
+        let function = inner::Function::<Args, Rets>::new_with_flags(func, flags);
         let address = function.address() as *const VMFunctionBody;
         let vmctx = VMFunctionEnvironment {
             host_env: std::ptr::null_mut() as *mut _,
@@ -286,6 +353,7 @@ impl Function {
                 // TODO: figure out what's going on in this function: it takes an `Env`
                 // param but also marks itself as not having an env
                 metadata: None,
+                info: None,
                 vm_function: VMFunction {
                     address,
                     vmctx,
@@ -322,6 +390,23 @@ impl Function {
     /// let f = Function::new_native_with_env(&store, env, sum_and_multiply);
     /// ```
     pub fn new_native_with_env<F, Args, Rets, Env>(store: &Store, env: Env, func: F) -> Self
+    where
+        F: HostFunction<Args, Rets, WithEnv, Env>,
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+        Env: Sized + WasmerEnv + 'static,
+    {
+        Self::new_native_with_env_and_flags(store, env, FunctionFlags::empty(), func)
+    }
+
+    /// Creates a new host `Function` from a native function and a
+    /// provided environment, with `flags` telling what it may do.
+    pub fn new_native_with_env_and_flags<F, Args, Rets, Env>(
+        store: &Store,
+        env: Env,
+        flags: FunctionFlags,
+        func: F,
+    ) -> Self
     where
         F: HostFunction<Args, Rets, WithEnv, Env>,
         Args: WasmTypeList,
@@ -331,7 +416,7 @@ impl Function {
         if std::mem::size_of::<F>() != 0 {
             Self::closures_unsupported_panic();
         }
-        let function = inner::Function::<Args, Rets>::new(func);
+        let function = inner::Function::<Args, Rets>::new_with_flags(func, flags);
         let address = function.address();
 
         let (host_env, metadata) =
@@ -344,6 +429,7 @@ impl Function {
             store: store.clone(),
             exported: ExportFunction {
                 metadata: Some(Arc::new(metadata)),
+                info: None,
                 vm_function: VMFunction {
                     address,
                     kind: VMFunctionKind::Static,
@@ -356,6 +442,38 @@ impl Function {
         }
     }
 
+    /// Attaches descriptive information to this host function. It can be
+    /// read back from the imports of the instances using the function,
+    /// see [`Instance::imports`](crate::Instance::imports).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Store, Function, HostFunctionInfo};
+    /// # let store = Store::default();
+    /// #
+    /// fn read_clock() -> i64 {
+    ///     0
+    /// }
+    ///
+    /// let f = Function::new_native(&store, read_clock).with_info(HostFunctionInfo {
+    ///     name: Some("read_clock".to_string()),
+    ///     docs: Some("Reads the host clock".to_string()),
+    ///     cost: Some(10),
+    /// });
+    ///
+    /// assert_eq!(f.info().unwrap().cost, Some(10));
+    /// ```
+    pub fn with_info(mut self, info: HostFunctionInfo) -> Self {
+        self.exported.info = Some(Arc::new(info));
+        self
+    }
+
+    /// Returns the information attached with [`Function::with_info`].
+    pub fn info(&self) -> Option<&HostFunctionInfo> {
+        self.exported.info.as_deref()
+    }
+
     /// Returns the [`FunctionType`] of the `Function`.
     ///
     /// # Example
@@ -382,6 +500,18 @@ impl Function {
         &self.store
     }
 
+    /// Enables or disables the probes of the patch points of `kind` in
+    /// this function, returning how many there are.
+    ///
+    /// Patch points are reserved by compilers configured for them, such
+    /// as Singlepass with `patch_points`. An enabled probe calls the
+    /// callback set with [`set_probe_callback`](crate::set_probe_callback)
+    /// each time it's hit. The code is patched in place, without
+    /// recompiling the module, so the function may be running meanwhile.
+    pub fn set_probes(&self, kind: PatchPointKind, enabled: bool) -> std::io::Result<usize> {
+        wasmer_engine::set_probes(self.exported.vm_function.address, kind, enabled)
+    }
+
     fn call_wasm(
         &self,
         trampoline: VMTrampoline,
@@ -429,6 +559,7 @@ impl Function {
         }
 
         // Call the trampoline.
+        let _domain = self.exported.vm_function.enter_protection_domain();
         if let Err(error) = unsafe {
             wasmer_call_trampoline(
                 &self.store,
@@ -641,33 +772,7 @@ impl Function {
         Args: WasmTypeList,
         Rets: WasmTypeList,
     {
-        // type check
-        {
-            let expected = self.exported.vm_function.signature.params();
-            let given = Args::wasm_types();
-
-            if expected != given {
-                return Err(RuntimeError::new(format!(
-                    "given types (`{:?}`) for the function arguments don't match the actual types (`{:?}`)",
-                    given,
-                    expected,
-                )));
-            }
-        }
-
-        {
-            let expected = self.exported.vm_function.signature.results();
-            let given = Rets::wasm_types();
-
-            if expected != given {
-                // todo: error result types don't match
-                return Err(RuntimeError::new(format!(
-                    "given types (`{:?}`) for the function results don't match the actual types (`{:?}`)",
-                    given,
-                    expected,
-                )));
-            }
-        }
+        check_native_signature::<Args, Rets>(&self.exported.vm_function.signature)?;
 
         Ok(NativeFunc::new(self.store.clone(), self.exported.clone()))
     }
@@ -690,6 +795,46 @@ impl Function {
     }
 }
 
+/// Checks that the `Args` and `Rets` of a [`NativeFunc`] match the given
+/// function signature.
+pub(crate) fn check_native_signature<Args, Rets>(
+    signature: &FunctionType,
+) -> Result<(), RuntimeError>
+where
+    Args: WasmTypeList,
+    Rets: WasmTypeList,
+{
+    // type check
+    {
+        let expected = signature.params();
+        let given = Args::wasm_types();
+
+        if expected != given {
+            return Err(RuntimeError::new(format!(
+                "given types (`{:?}`) for the function arguments don't match the actual types (`{:?}`)",
+                given,
+                expected,
+            )));
+        }
+    }
+
+    {
+        let expected = signature.results();
+        let given = Rets::wasm_types();
+
+        if expected != given {
+            // todo: error result types don't match
+            return Err(RuntimeError::new(format!(
+                "given types (`{:?}`) for the function results don't match the actual types (`{:?}`)",
+                given,
+                expected,
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 impl<'a> Exportable<'a> for Function {
     fn to_export(&self) -> Export {
         self.exported.clone().into()
@@ -834,15 +979,25 @@ impl<T: VMDynamicFunction> VMDynamicFunctionCall<T> for VMDynamicFunctionContext
 
         match result {
             Ok(Ok(())) => {}
-            Ok(Err(trap)) => raise_user_trap(Box::new(trap)),
+            Ok(Err(trap)) => raise_user_trap(Box::new(trap.with_caller_frames())),
             Err(panic) => resume_panic(panic),
         }
     }
 }
 
+/// Raises the error returned by a host function running on the host
+/// stack as a trap in the WebAssembly code which called it.
+unsafe fn raise_host_error(error: Box<dyn std::error::Error + Send + Sync>) -> ! {
+    match error.downcast::<RuntimeError>() {
+        Ok(error) => raise_user_trap(Box::new(error.with_caller_frames())),
+        Err(error) => raise_user_trap(error),
+    }
+}
+
 /// This private inner module contains the low-level implementation
 /// for `Function` and its siblings.
 mod inner {
+    use super::{raise_host_error, FunctionFlags};
     use std::array::TryFromSliceError;
     use std::convert::{Infallible, TryInto};
     use std::error::Error;
@@ -1128,6 +1283,13 @@ mod inner {
     {
         /// Get the pointer to the function body.
         fn function_body_ptr(self) -> *const VMFunctionBody;
+
+        /// Get the pointer to the function body, calling the function
+        /// without switching to the host stack, for
+        /// [`FunctionFlags::LEAF`] functions.
+        fn leaf_function_body_ptr(self) -> *const VMFunctionBody {
+            self.function_body_ptr()
+        }
     }
 
     /// Empty trait to specify the kind of `HostFunction`: With or
@@ -1181,6 +1343,24 @@ mod inner {
             }
         }
 
+        /// Creates a new `Function`, called as `flags` allow.
+        pub fn new_with_flags<F, T, E>(function: F, flags: FunctionFlags) -> Self
+        where
+            F: HostFunction<Args, Rets, T, E>,
+            T: HostFunctionKind,
+            E: Sized,
+        {
+            let address = if flags.contains(FunctionFlags::LEAF) {
+                function.leaf_function_body_ptr()
+            } else {
+                function.function_body_ptr()
+            };
+            Self {
+                address,
+                _phantom: PhantomData,
+            }
+        }
+
         /// Get the function type of this `Function`.
         pub fn ty(&self) -> FunctionType {
             FunctionType::new(Args::wasm_types(), Rets::wasm_types())
@@ -1320,13 +1500,39 @@ mod inner {
 
                         match result {
                             Ok(Ok(result)) => return result.into_c_struct(),
-                            Ok(Err(trap)) => unsafe { raise_user_trap(Box::new(trap)) },
+                            Ok(Err(trap)) => unsafe { raise_host_error(Box::new(trap)) },
                             Err(panic) => unsafe { resume_panic(panic) },
                         }
                     }
 
                     func_wrapper::< $( $x, )* Rets, RetsAsResult, Self > as *const VMFunctionBody
                 }
+
+                #[allow(non_snake_case)]
+                fn leaf_function_body_ptr(self) -> *const VMFunctionBody {
+                    /// This is a function that wraps the real host
+                    /// function, calling it on the WebAssembly stack.
+                    extern fn leaf_func_wrapper<$( $x, )* Rets, RetsAsResult, Func>( _: usize, $( $x: $x::Native, )* ) -> Rets::CStruct
+                    where
+                        $( $x: FromToNativeWasmType, )*
+                        Rets: WasmTypeList,
+                        RetsAsResult: IntoResult<Rets>,
+                        Func: Fn( $( $x ),* ) -> RetsAsResult + 'static
+                    {
+                        let func: &Func = unsafe { &*(&() as *const () as *const Func) };
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                            func( $( FromToNativeWasmType::from_native($x) ),* ).into_result()
+                        }));
+
+                        match result {
+                            Ok(Ok(result)) => return result.into_c_struct(),
+                            Ok(Err(trap)) => unsafe { raise_user_trap(Box::new(trap)) },
+                            Err(panic) => unsafe { resume_panic(panic) },
+                        }
+                    }
+
+                    leaf_func_wrapper::< $( $x, )* Rets, RetsAsResult, Self > as *const VMFunctionBody
+                }
             }
 
             // Implement `HostFunction` for a function that has the same arity than the tuple.
@@ -1366,13 +1572,40 @@ mod inner {
 
                         match result {
                             Ok(Ok(result)) => return result.into_c_struct(),
-                            Ok(Err(trap)) => unsafe { raise_user_trap(Box::new(trap)) },
+                            Ok(Err(trap)) => unsafe { raise_host_error(Box::new(trap)) },
                             Err(panic) => unsafe { resume_panic(panic) },
                         }
                     }
 
                     func_wrapper::< $( $x, )* Rets, RetsAsResult, Env, Self > as *const VMFunctionBody
                 }
+
+                #[allow(non_snake_case)]
+                fn leaf_function_body_ptr(self) -> *const VMFunctionBody {
+                    /// This is a function that wraps the real host
+                    /// function, calling it on the WebAssembly stack.
+                    extern fn leaf_func_wrapper<$( $x, )* Rets, RetsAsResult, Env, Func>( env: &Env, $( $x: $x::Native, )* ) -> Rets::CStruct
+                    where
+                        $( $x: FromToNativeWasmType, )*
+                        Rets: WasmTypeList,
+                        RetsAsResult: IntoResult<Rets>,
+                        Env: Sized,
+                        Func: Fn(&Env, $( $x ),* ) -> RetsAsResult + 'static
+                    {
+                        let func: &Func = unsafe { &*(&() as *const () as *const Func) };
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                            func(env, $( FromToNativeWasmType::from_native($x) ),* ).into_result()
+                        }));
+
+                        match result {
+                            Ok(Ok(result)) => return result.into_c_struct(),
+                            Ok(Err(trap)) => unsafe { raise_user_trap(Box::new(trap)) },
+                            Err(panic) => unsafe { resume_panic(panic) },
+                        }
+                    }
+
+                    leaf_func_wrapper::< $( $x, )* Rets, RetsAsResult, Env, Self > as *const VMFunctionBody
+                }
             }
         };
     }
@@ -4,10 +4,12 @@ use crate::sys::store::Store;
 use crate::sys::{MemoryType, MemoryView};
 use loupe::MemoryUsage;
 use std::convert::TryInto;
+use std::ops::Range;
+use std::ptr;
 use std::slice;
 use std::sync::Arc;
 use wasmer_engine::Export;
-use wasmer_types::{Pages, ValueType};
+use wasmer_types::{Bytes, MemoryAccessError, Pages, ValueType, WASM_PAGE_SIZE};
 use wasmer_vm::{MemoryError, VMMemory};
 
 /// A WebAssembly `memory` instance.
@@ -134,6 +136,123 @@ impl Memory {
         def.current_length.try_into().unwrap()
     }
 
+    /// Reads `buf.len()` bytes starting at `offset` into `buf`.
+    ///
+    /// Returns a [`MemoryAccessError`], leaving `buf` untouched, if the
+    /// read goes past the end of the memory rather than panicking.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryAccessError, MemoryType, Store};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// let mut buf = [0; 4];
+    ///
+    /// assert!(m.read(0, &mut buf).is_ok());
+    /// assert_eq!(
+    ///     m.read(65534, &mut buf),
+    ///     Err(MemoryAccessError { offset: 65534, length: 4 }),
+    /// );
+    /// ```
+    pub fn read(&self, offset: u64, buf: &mut [u8]) -> Result<(), MemoryAccessError> {
+        self.check_bounds(offset, buf.len() as u64)?;
+        unsafe {
+            ptr::copy(
+                self.data_ptr().add(offset as usize),
+                buf.as_mut_ptr(),
+                buf.len(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Writes the bytes of `data` starting at `offset`.
+    ///
+    /// Returns a [`MemoryAccessError`], leaving the memory untouched, if
+    /// the write goes past the end of the memory rather than panicking.
+    pub fn write(&self, offset: u64, data: &[u8]) -> Result<(), MemoryAccessError> {
+        self.check_bounds(offset, data.len() as u64)?;
+        unsafe {
+            ptr::copy(
+                data.as_ptr(),
+                self.data_ptr().add(offset as usize),
+                data.len(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Copies `len` bytes starting at `src` in this memory to `dst` in
+    /// `dst_memory`, without going through a host buffer. `dst_memory` may
+    /// be this memory, the ranges then being allowed to overlap.
+    ///
+    /// The bytes are copied a page at a time. Returns a
+    /// [`MemoryAccessError`] for the range that's out of bounds, leaving
+    /// both memories untouched, if either range goes past the end of its
+    /// memory.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryAccessError, MemoryType, Store};
+    /// # let store = Store::default();
+    /// #
+    /// let a = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// let b = Memory::new(&store, MemoryType::new(2, None, false)).unwrap();
+    /// a.write(0, b"hello").unwrap();
+    ///
+    /// a.copy_to(&b, 0, 65536, 5).unwrap();
+    /// let mut buf = [0; 5];
+    /// b.read(65536, &mut buf).unwrap();
+    /// assert_eq!(&buf, b"hello");
+    ///
+    /// assert_eq!(
+    ///     b.copy_to(&a, 65536, 65534, 5),
+    ///     Err(MemoryAccessError { offset: 65534, length: 5 }),
+    /// );
+    /// ```
+    pub fn copy_to(
+        &self,
+        dst_memory: &Self,
+        src: u64,
+        dst: u64,
+        len: u64,
+    ) -> Result<(), MemoryAccessError> {
+        self.check_bounds(src, len)?;
+        dst_memory.check_bounds(dst, len)?;
+        // Within the same memory, the pages are copied from the end when
+        // the destination is after the source, so that no page is
+        // overwritten before being copied.
+        let backwards = self.data_ptr() == dst_memory.data_ptr() && dst > src;
+        let mut copied = 0;
+        while copied < len {
+            let chunk = (len - copied).min(WASM_PAGE_SIZE as u64);
+            let offset = if backwards {
+                len - copied - chunk
+            } else {
+                copied
+            };
+            unsafe {
+                ptr::copy(
+                    self.data_ptr().add((src + offset) as usize),
+                    dst_memory.data_ptr().add((dst + offset) as usize),
+                    chunk as usize,
+                );
+            }
+            copied += chunk;
+        }
+        Ok(())
+    }
+
+    fn check_bounds(&self, offset: u64, length: u64) -> Result<(), MemoryAccessError> {
+        match offset.checked_add(length) {
+            Some(end) if end <= self.data_size() => Ok(()),
+            _ => Err(MemoryAccessError { offset, length }),
+        }
+    }
+
     /// Returns the size (in [`Pages`]) of the `Memory`.
     ///
     /// # Example
@@ -199,6 +318,10 @@ impl Memory {
     /// Therefore, if this memory is shared between multiple threads, a single memory
     /// location can be mutated concurrently without synchronization.
     ///
+    /// Growing the memory, including from a host function called by
+    /// WebAssembly, may move it: a view must be refreshed with
+    /// [`MemoryView::refresh`] before being used again.
+    ///
     /// # Usage:
     ///
     /// ```
@@ -219,11 +342,13 @@ impl Memory {
     /// # }
     /// ```
     pub fn view<T: ValueType>(&self) -> MemoryView<T> {
-        let base = self.data_ptr();
-
-        let length = self.size().bytes().0 / std::mem::size_of::<T>();
-
-        unsafe { MemoryView::new(base as _, length as u32) }
+        let definition = self.vm_memory.from.vmmemory().as_ptr();
+        unsafe {
+            MemoryView::new_refreshable(
+                ptr::addr_of!((*definition).base),
+                ptr::addr_of!((*definition).current_length),
+            )
+        }
     }
 
     /// A shortcut to [`Self::view::<u8>`][self::view].
@@ -241,6 +366,129 @@ impl Memory {
         }
     }
 
+    /// Starts recording which pages of the memory are written to, for
+    /// incremental snapshots. The pages recorded so far are forgotten.
+    ///
+    /// Tracking is supported for linear memories on Linux kernels
+    /// reporting soft-dirty pages. The soft-dirty bits are shared by the
+    /// whole process: pages written to while the dirty pages of any
+    /// memory are being taken may be missed, so take them while the
+    /// instances using tracked memories are not running.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the host can't track the pages of this memory.
+    pub fn start_tracking(&self) -> Result<(), MemoryError> {
+        self.vm_memory.from.start_tracking()
+    }
+
+    /// Returns the indices of the pages written to since the last call,
+    /// or since [`Memory::start_tracking`], in ascending order.
+    ///
+    /// Pages may be reported without having been written to, for example
+    /// after the memory grew, but a page written to is never missed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Pages, Store};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new(&store, MemoryType::new(4, None, false)).unwrap();
+    /// if m.start_tracking().is_ok() {
+    ///     m.write(Pages(2).bytes().0 as u64, &[1]).unwrap();
+    ///     assert!(m.take_dirty_pages().unwrap().contains(&Pages(2)));
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pages of the memory are not tracked.
+    pub fn take_dirty_pages(&self) -> Result<Vec<Pages>, MemoryError> {
+        self.vm_memory.from.take_dirty_pages()
+    }
+
+    /// Seals the memory: from now on, it can't grow, neither from the
+    /// host nor from WebAssembly, where `memory.grow` returns `-1`. This
+    /// fixes the footprint of the instances using it, for example after
+    /// a warm-up phase.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryError, MemoryType, Pages, Store};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// m.seal().unwrap();
+    ///
+    /// assert!(m.is_sealed());
+    /// assert_eq!(m.grow(1), Err(MemoryError::Sealed));
+    /// assert_eq!(m.size(), Pages(1));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the memory can't be sealed.
+    pub fn seal(&self) -> Result<(), MemoryError> {
+        self.vm_memory.from.seal()
+    }
+
+    /// Returns whether the memory is sealed.
+    pub fn is_sealed(&self) -> bool {
+        self.vm_memory.from.is_sealed()
+    }
+
+    /// Faults in the pages of `range`, in bytes, so that accessing them
+    /// later doesn't take page faults, for example before entering a
+    /// latency-sensitive section. Their contents are unchanged.
+    ///
+    /// The pages stay committed until the host reclaims them, for
+    /// example by swapping them out under memory pressure.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Pages, Store};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new(&store, MemoryType::new(4, None, false)).unwrap();
+    /// m.commit(0..Pages(2).bytes().0 as u64).unwrap();
+    ///
+    /// if let Ok(resident) = m.resident_size() {
+    ///     assert!(resident >= Pages(2).bytes());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `range` is out of bounds, or if the pages
+    /// can't be committed.
+    pub fn commit(&self, range: Range<u64>) -> Result<(), MemoryError> {
+        let out_of_bounds = || {
+            MemoryError::Generic(format!(
+                "the range {:?} is out of bounds of the memory",
+                range
+            ))
+        };
+        let start: usize = range.start.try_into().map_err(|_| out_of_bounds())?;
+        let len: usize = range
+            .end
+            .checked_sub(range.start)
+            .and_then(|len| len.try_into().ok())
+            .ok_or_else(out_of_bounds)?;
+        self.vm_memory.from.commit(start, len)
+    }
+
+    /// Returns how many bytes of the memory are resident in physical
+    /// memory, for attributing the resident set size of the process to
+    /// memories.
+    ///
+    /// This is supported for linear memories on Unix.
+    pub fn resident_size(&self) -> Result<Bytes, MemoryError> {
+        self.vm_memory.from.resident_size()
+    }
+
     /// Returns whether or not these two memories refer to the same data.
     ///
     /// # Example
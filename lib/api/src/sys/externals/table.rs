@@ -3,7 +3,7 @@ use crate::sys::externals::Extern;
 use crate::sys::store::Store;
 use crate::sys::types::{Val, ValFuncRef};
 use crate::sys::RuntimeError;
-use crate::sys::TableType;
+use crate::sys::{TableType, Type};
 use loupe::MemoryUsage;
 use std::sync::Arc;
 use wasmer_engine::Export;
@@ -12,7 +12,11 @@ use wasmer_vm::{Table as RuntimeTable, TableElement, VMTable};
 /// A WebAssembly `table` instance.
 ///
 /// The `Table` struct is an array-like structure representing a WebAssembly Table,
-/// which stores function references.
+/// which stores function references (`funcref`) or opaque host values
+/// (`externref`).
+///
+/// A table holds a reference to each of its `externref`s, which it
+/// releases when the element is replaced or the table is dropped.
 ///
 /// A table created by the host or in WebAssembly code will be accessible and
 /// mutable from both host and WebAssembly.
@@ -32,6 +36,18 @@ fn set_table_item(
     table.set(item_index, item).map_err(|e| e.into())
 }
 
+/// Checks that `val` can be stored in a table of type `ty`.
+fn check_table_value(ty: &TableType, val: &Val) -> Result<(), RuntimeError> {
+    if val.ty() != ty.ty {
+        return Err(RuntimeError::new(format!(
+            "cannot store a value of type {} in a table of {}",
+            val.ty(),
+            ty.ty
+        )));
+    }
+    Ok(())
+}
+
 impl Table {
     /// Creates a new `Table` with the provided [`TableType`] definition.
     ///
@@ -40,6 +56,7 @@ impl Table {
     /// This function will construct the `Table` using the store
     /// [`BaseTunables`][crate::sys::BaseTunables].
     pub fn new(store: &Store, ty: TableType, init: Val) -> Result<Self, RuntimeError> {
+        check_table_value(&ty, &init)?;
         let item = init.into_table_reference(store)?;
         let tunables = store.tunables();
         let style = tunables.table_style(&ty);
@@ -78,7 +95,15 @@ impl Table {
     }
 
     /// Sets an element `val` in the Table at the provided `index`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `index` is out of bounds, if the type of
+    /// `val` isn't the element type of the table, or if the table is
+    /// sealed.
     pub fn set(&self, index: u32, val: Val) -> Result<(), RuntimeError> {
+        self.check_not_sealed()?;
+        check_table_value(self.ty(), &val)?;
         let item = val.into_table_reference(&self.store)?;
         set_table_item(self.vm_table.from.as_ref(), index, item)
     }
@@ -96,8 +121,12 @@ impl Table {
     ///
     /// # Errors
     ///
-    /// Returns an error if the `delta` is out of bounds for the table.
+    /// Returns an error if the `delta` is out of bounds for the table, if
+    /// the type of `init` isn't the element type of the table, or if the
+    /// table is sealed.
     pub fn grow(&self, delta: u32, init: Val) -> Result<u32, RuntimeError> {
+        self.check_not_sealed()?;
+        check_table_value(self.ty(), &init)?;
         let item = init.into_table_reference(&self.store)?;
         self.vm_table
             .from
@@ -111,7 +140,9 @@ impl Table {
     /// # Errors
     ///
     /// Returns an error if the range is out of bounds of either the source or
-    /// destination tables.
+    /// destination tables, if their element types differ, or if the
+    /// destination table is sealed. Tables of functions must belong to
+    /// the same store.
     pub fn copy(
         dst_table: &Self,
         dst_index: u32,
@@ -119,7 +150,17 @@ impl Table {
         src_index: u32,
         len: u32,
     ) -> Result<(), RuntimeError> {
-        if !Store::same(&dst_table.store, &src_table.store) {
+        dst_table.check_not_sealed()?;
+        if dst_table.ty().ty != src_table.ty().ty {
+            return Err(RuntimeError::new(format!(
+                "cannot copy elements of a table of {} to a table of {}",
+                src_table.ty().ty,
+                dst_table.ty().ty
+            )));
+        }
+        // `externref`s aren't tied to a store, unlike functions.
+        if dst_table.ty().ty != Type::ExternRef && !Store::same(&dst_table.store, &src_table.store)
+        {
             return Err(RuntimeError::new(
                 "cross-`Store` table copies are not supported",
             ));
@@ -135,6 +176,35 @@ impl Table {
         Ok(())
     }
 
+    /// Seals the table: from now on, it can't grow, neither from the host
+    /// nor from WebAssembly, where `table.grow` returns `-1`, and the host
+    /// can't set its elements anymore. This fixes the footprint of the
+    /// instances using it, for example after a warm-up phase.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the table can't be sealed.
+    pub fn seal(&self) -> Result<(), RuntimeError> {
+        if self.vm_table.from.seal() {
+            Ok(())
+        } else {
+            Err(RuntimeError::new("sealing is not supported for this table"))
+        }
+    }
+
+    /// Returns whether the table is sealed.
+    pub fn is_sealed(&self) -> bool {
+        self.vm_table.from.is_sealed()
+    }
+
+    fn check_not_sealed(&self) -> Result<(), RuntimeError> {
+        if self.is_sealed() {
+            Err(RuntimeError::new("the table is sealed"))
+        } else {
+            Ok(())
+        }
+    }
+
     pub(crate) fn from_vm_export(store: &Store, vm_table: VMTable) -> Self {
         Self {
             store: store.clone(),
@@ -0,0 +1,259 @@
+//! Replacement of an instance by an instance of a new version of its
+//! module, carrying its state over.
+
+use crate::sys::exports::Exports;
+use crate::sys::externals::Function;
+use crate::sys::import_object::ImportObject;
+use crate::sys::instance::{Instance, InstantiationError};
+use crate::sys::module::Module;
+use crate::sys::types::{Val, ValType};
+use std::collections::HashMap;
+use thiserror::Error;
+use wasmer_engine::RuntimeError;
+use wasmer_types::ExternRef;
+use wasmer_vm::MemoryError;
+
+/// Describes the state carried over by [`Instance::hot_swap`] besides
+/// the exported memories and tables.
+///
+/// ```
+/// # use wasmer::Migration;
+/// let migration = Migration::new()
+///     .global("requests")
+///     .global_renamed("errors", "failed_requests");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Migration {
+    /// The globals to carry over: the name exported by the current
+    /// module, and by the new one.
+    globals: Vec<(String, String)>,
+}
+
+impl Migration {
+    /// Creates a migration carrying over no global.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Carries the value of the global exported as `name` over to the
+    /// global exported under the same name by the new module.
+    pub fn global(self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        self.global_renamed(name.clone(), name)
+    }
+
+    /// Carries the value of the global exported as `old` over to the
+    /// global exported as `new` by the new module.
+    pub fn global_renamed(mut self, old: impl Into<String>, new: impl Into<String>) -> Self {
+        self.globals.push((old.into(), new.into()));
+        self
+    }
+}
+
+/// An error while hot-swapping an instance with [`Instance::hot_swap`].
+#[derive(Error, Debug)]
+pub enum HotSwapError {
+    /// The new module couldn't be instantiated with the imports of the
+    /// current instance.
+    #[error(transparent)]
+    Instantiation(InstantiationError),
+
+    /// A global of the migration isn't exported by one of the modules.
+    #[error("the global `{name}` to migrate isn't exported by the {} module", if *.new { "new" } else { "current" })]
+    MissingGlobal {
+        /// The name of the global.
+        name: String,
+        /// Whether it's missing from the new module rather than the
+        /// current one.
+        new: bool,
+    },
+
+    /// A global of the migration has another type in the new module, or
+    /// isn't mutable there.
+    #[error("the global `{name}` can't be migrated: {error}")]
+    IncompatibleGlobal {
+        /// The name of the global in the new module.
+        name: String,
+        /// Why the value can't be set.
+        error: RuntimeError,
+    },
+
+    /// An exported memory of the new module couldn't be grown to the
+    /// size of the current one.
+    #[error("the memory `{name}` can't be migrated: {error}")]
+    Memory {
+        /// The name of the memory.
+        name: String,
+        /// The error growing it.
+        error: MemoryError,
+    },
+
+    /// An exported table of the new module couldn't be grown to the size
+    /// of the current one.
+    #[error("the table `{name}` can't be migrated: {error}")]
+    Table {
+        /// The name of the table.
+        name: String,
+        /// The error growing or filling it.
+        error: RuntimeError,
+    },
+}
+
+impl Instance {
+    /// Instantiates `module`, a new version of the module of this
+    /// instance, with the same imports, and carries the state of this
+    /// instance over to it.
+    ///
+    /// The state carried over is:
+    ///  * the contents of the memories exported under the same name by
+    ///    both modules, which replace the data segments of `module`,
+    ///  * the values of the globals listed in `migration`,
+    ///  * the entries of the tables exported under the same name by both
+    ///    modules. Functions of this instance are replaced by the
+    ///    functions `module` exports under the same name with the same
+    ///    signature, and other functions are kept. Entries referring to
+    ///    functions of this instance that can't be matched keep the
+    ///    value given by `module`.
+    ///
+    /// Imported memories, tables and globals are shared by both
+    /// instances, so they are carried over as they are. The start
+    /// function of `module` runs before the state is carried over.
+    ///
+    /// This instance is left untouched: it can keep running until the
+    /// new one takes over.
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let v1 = Module::new(&store, r#"(module
+    ///     (global $count (export "count") (mut i32) (i32.const 0))
+    ///     (func (export "hit") (result i32)
+    ///         (global.set $count (i32.add (global.get $count) (i32.const 1)))
+    ///         (global.get $count)))"#)?;
+    /// let v2 = Module::new(&store, r#"(module
+    ///     (global $count (export "count") (mut i32) (i32.const 0))
+    ///     (func (export "hit") (result i32)
+    ///         (global.set $count (i32.add (global.get $count) (i32.const 10)))
+    ///         (global.get $count)))"#)?;
+    ///
+    /// let instance = Instance::new(&v1, &imports! {})?;
+    /// assert_eq!(instance.exports.get_native_function::<(), i32>("hit")?.call()?, 1);
+    ///
+    /// let instance = instance.hot_swap(&v2, &Migration::new().global("count"))?;
+    /// assert_eq!(instance.exports.get_native_function::<(), i32>("hit")?.call()?, 11);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn hot_swap(&self, module: &Module, migration: &Migration) -> Result<Self, HotSwapError> {
+        // Provide the new version with the externs this instance imports
+        let mut namespaces: HashMap<&str, Exports> = HashMap::new();
+        for (import, extern_) in self.imports() {
+            namespaces
+                .entry(import.module())
+                .or_default()
+                .insert(import.name(), extern_.clone());
+        }
+        let mut import_object = ImportObject::new();
+        for (namespace, exports) in namespaces {
+            import_object.register(namespace, exports);
+        }
+        let instance = Self::new(module, &import_object).map_err(HotSwapError::Instantiation)?;
+
+        for (old, new) in &migration.globals {
+            let value = self
+                .exports
+                .get_global(old)
+                .map_err(|_| HotSwapError::MissingGlobal {
+                    name: old.clone(),
+                    new: false,
+                })?
+                .get();
+            instance
+                .exports
+                .get_global(new)
+                .map_err(|_| HotSwapError::MissingGlobal {
+                    name: new.clone(),
+                    new: true,
+                })?
+                .set(value)
+                .map_err(|error| HotSwapError::IncompatibleGlobal {
+                    name: new.clone(),
+                    error,
+                })?;
+        }
+
+        for (name, old) in self.exports.iter().memories() {
+            let new = match instance.exports.get_memory(name) {
+                Ok(new) if !new.same(old) => new,
+                _ => continue,
+            };
+            let (old_size, new_size) = (old.size(), new.size());
+            if new_size < old_size {
+                new.grow(old_size - new_size)
+                    .map_err(|error| HotSwapError::Memory {
+                        name: name.clone(),
+                        error,
+                    })?;
+            }
+            // Both memories belong to distinct instances, and nothing runs
+            // while the contents are copied
+            unsafe {
+                let data = old.data_unchecked();
+                new.data_unchecked_mut()[..data.len()].copy_from_slice(data);
+            }
+        }
+
+        for (name, old) in self.exports.iter().tables() {
+            let new = match instance.exports.get_table(name) {
+                Ok(new) if !new.same(old) && new.ty().ty == old.ty().ty => new,
+                _ => continue,
+            };
+            let table_error = |error| HotSwapError::Table {
+                name: name.clone(),
+                error,
+            };
+            if new.size() < old.size() {
+                let null = match new.ty().ty {
+                    ValType::FuncRef => Val::FuncRef(None),
+                    _ => Val::ExternRef(ExternRef::null()),
+                };
+                new.grow(old.size() - new.size(), null)
+                    .map_err(table_error)?;
+            }
+            for index in 0..old.size() {
+                let value = match old.get(index) {
+                    Some(Val::FuncRef(Some(function))) if self.owns(&function) => {
+                        match self.migrate_function(&function, &instance) {
+                            Some(function) => Val::FuncRef(Some(function)),
+                            None => continue,
+                        }
+                    }
+                    Some(value) => value,
+                    None => continue,
+                };
+                new.set(index, value).map_err(table_error)?;
+            }
+        }
+
+        Ok(instance)
+    }
+
+    /// Whether `function` is defined by this instance.
+    fn owns(&self, function: &Function) -> bool {
+        unsafe { function.exported.vm_function.vmctx.vmctx == self.vmctx_ptr() }
+    }
+
+    /// The function `instance` exports under the name this instance
+    /// exports `function`, if it has the same signature.
+    fn migrate_function(&self, function: &Function, instance: &Self) -> Option<Function> {
+        let (name, _) = self.exports.iter().functions().find(|(_, exported)| {
+            exported.exported.vm_function.address == function.exported.vm_function.address
+        })?;
+        let new = instance.exports.get_function(name).ok()?;
+        if new.ty() != function.ty() {
+            return None;
+        }
+        Some(new.clone())
+    }
+}
@@ -1,7 +1,9 @@
 use crate::sys::exports::Exports;
 use crate::sys::externals::Extern;
+use crate::sys::import_object::ImportObject;
 use crate::sys::module::Module;
 use crate::sys::store::Store;
+use crate::sys::types::ImportType;
 use crate::sys::{HostEnvInitError, LinkError, RuntimeError};
 use loupe::MemoryUsage;
 use std::fmt;
@@ -17,6 +19,19 @@ use wasmer_vm::{InstanceHandle, VMContext};
 /// functions, memories, tables and globals that allow
 /// interacting with WebAssembly.
 ///
+/// # Threads
+///
+/// An `Instance` is `Send` and `Sync`: it can be moved to another thread
+/// than the one that created it, or shared between threads, and its exports
+/// called from any of them. The host functions it imports are `Send + Sync`
+/// too, as required by [`WasmerEnv`](crate::WasmerEnv).
+///
+/// The runtime state of the instance is synchronized internally, but the
+/// WebAssembly state is not: concurrent calls into the same instance see
+/// each other's writes to its memories and globals in no particular
+/// order, as with a memory shared between threads. Exports are typically
+/// called from one thread at a time, or guarded by the host.
+///
 /// Spec: <https://webassembly.github.io/spec/core/exec/runtime.html#module-instances>
 #[derive(Clone, MemoryUsage)]
 pub struct Instance {
@@ -24,6 +39,8 @@ pub struct Instance {
     module: Module,
     /// The exports for an instance.
     pub exports: Exports,
+    #[loupe(skip)]
+    imports: Vec<(ImportType, Extern)>,
 }
 
 #[cfg(test)]
@@ -34,10 +51,23 @@ mod send_test {
         true
     }
 
+    fn is_sync<T: Sync>() -> bool {
+        true
+    }
+
     #[test]
     fn instance_is_send() {
         assert!(is_send::<Instance>());
     }
+
+    #[test]
+    fn instance_and_exports_are_sync() {
+        assert!(is_sync::<Instance>());
+        assert!(is_send::<Exports>() && is_sync::<Exports>());
+        assert!(is_send::<crate::Function>() && is_sync::<crate::Function>());
+        assert!(is_send::<crate::NativeFunc<i32, i32>>());
+        assert!(is_sync::<crate::NativeFunc<i32, i32>>());
+    }
 }
 
 /// An error while instantiating a module.
@@ -133,11 +163,20 @@ impl Instance {
                 (name, extern_)
             })
             .collect::<Exports>();
+        let imports = module
+            .imports()
+            .enumerate()
+            .filter_map(|(index, import)| {
+                let export = resolver.resolve(index as u32, import.module(), import.name())?;
+                Some((import, Extern::from_vm_export(store, export)))
+            })
+            .collect();
 
         let instance = Self {
             handle: Arc::new(Mutex::new(handle)),
             module: module.clone(),
             exports,
+            imports,
         };
 
         // # Safety
@@ -164,11 +203,82 @@ impl Instance {
         &self.module
     }
 
+    /// Returns the imports of the module, each with the extern that
+    /// satisfied it.
+    ///
+    /// This is what the instance was wired with, to audit the
+    /// capabilities it was given. The information attached to host
+    /// functions is available with [`Function::info`](crate::Function::info).
+    pub fn imports(&self) -> &[(ImportType, Extern)] {
+        &self.imports
+    }
+
     /// Returns the [`Store`] where the `Instance` belongs.
     pub fn store(&self) -> &Store {
         self.module.store()
     }
 
+    /// Returns an [`ImportObject`] providing the exports of this
+    /// instance under `namespace`, to instantiate a module importing
+    /// them.
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let a = Module::new(&store, r#"(module
+    ///     (func (export "answer") (result i32) (i32.const 42)))"#)?;
+    /// let b = Module::new(&store, r#"(module
+    ///     (import "a" "answer" (func $answer (result i32)))
+    ///     (func (export "twice") (result i32)
+    ///         (i32.add (call $answer) (call $answer))))"#)?;
+    ///
+    /// let a = Instance::new(&a, &imports! {})?;
+    /// let b = Instance::new(&b, &a.exports_as_imports("a"))?;
+    /// assert_eq!(b.exports.get_native_function::<(), i32>("twice")?.call()?, 84);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn exports_as_imports(&self, namespace: &str) -> ImportObject {
+        self.exports_as_imports_with(namespace, |name, _| Some(name.to_string()))
+    }
+
+    /// Like [`Instance::exports_as_imports`], but `rename` gives the
+    /// name under which each export is provided, or `None` to leave it
+    /// out.
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// # let module = Module::new(&store, r#"(module
+    /// #     (memory (export "memory") 1)
+    /// #     (func (export "lib_alloc") (result i32) (i32.const 0)))"#)?;
+    /// # let instance = Instance::new(&module, &imports! {})?;
+    /// // Provide the functions prefixed with `lib_`, without their prefix.
+    /// let imports = instance.exports_as_imports_with("lib", |name, extern_| match extern_ {
+    ///     Extern::Function(_) => name.strip_prefix("lib_").map(str::to_string),
+    ///     _ => None,
+    /// });
+    /// assert!(imports.get_export("lib", "alloc").is_some());
+    /// assert!(imports.get_export("lib", "memory").is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn exports_as_imports_with<F>(&self, namespace: &str, mut rename: F) -> ImportObject
+    where
+        F: FnMut(&str, &Extern) -> Option<String>,
+    {
+        let exports = self
+            .exports
+            .iter()
+            .filter_map(|(name, extern_)| Some((rename(name, extern_)?, extern_.clone())))
+            .collect::<Exports>();
+        let mut import_object = ImportObject::new();
+        import_object.register(namespace, exports);
+        import_object
+    }
+
     #[doc(hidden)]
     pub fn vmctx_ptr(&self) -> *mut VMContext {
         self.handle.lock().unwrap().vmctx_ptr()
@@ -0,0 +1,36 @@
+//! The memory taken by an instance, estimated before instantiating it.
+
+/// An estimate of the memory taken by an instance of a [`Module`],
+/// obtained with [`Module::estimated_instance_size`] to decide whether
+/// to instantiate it at all.
+///
+/// Only what the module defines is counted: imported memories and
+/// tables belong to other instances or to the host, and the memories
+/// and tables may grow later on.
+///
+/// [`Module`]: crate::Module
+/// [`Module::estimated_instance_size`]: crate::Module::estimated_instance_size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstanceSizeEstimate {
+    /// The bytes allocated for the instance and its `VMContext`.
+    pub vmctx: u64,
+    /// The bytes taken by the tables, at their minimum size.
+    pub tables: u64,
+    /// The bytes of the memories, at their minimum size.
+    pub memories: u64,
+    /// The bytes of address space reserved for the memories, including
+    /// the room static memories grow into and the guard pages.
+    pub memory_reservations: u64,
+    /// The native stack a call into the instance needs, in bytes: the
+    /// deepest stack of its exported functions when it's statically
+    /// known, the size of the stacks calls run on otherwise.
+    pub stack: u64,
+}
+
+impl InstanceSizeEstimate {
+    /// The bytes of memory the instance takes when it's created, and
+    /// while a call into it runs.
+    pub fn committed(&self) -> u64 {
+        self.vmctx + self.tables + self.memories + self.stack
+    }
+}
@@ -0,0 +1,374 @@
+//! Instantiation of modules importing from one another.
+
+use crate::sys::exports::Exports;
+use crate::sys::externals::{Memory, Table};
+use crate::sys::import_object::ImportObject;
+use crate::sys::instance::{Instance, InstantiationError};
+use crate::sys::module::Module;
+use crate::sys::store::Store;
+use crate::sys::types::{ExternType, MemoryType, TableType, Val, ValType};
+use std::collections::HashMap;
+use thiserror::Error;
+use wasmer_engine::{ChainableNamedResolver, RuntimeError};
+use wasmer_vm::MemoryError;
+
+/// The namespace of the memory and table shared by linked modules.
+const ENV: &str = "env";
+/// The name of the shared memory in [`ENV`].
+const MEMORY: &str = "memory";
+/// The name of the shared table in [`ENV`].
+const TABLE: &str = "__indirect_function_table";
+
+/// An error while linking modules with a [`Linker`].
+#[derive(Error, Debug)]
+pub enum LinkerError {
+    /// The modules import from one another in a cycle, so there's no
+    /// order to instantiate them in. The modules of the cycle are given,
+    /// each importing from the next one, and the last from the first.
+    #[error("modules import from one another in a cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+
+    /// The modules importing a shared memory or table can't agree on
+    /// its type.
+    #[error("the modules importing `env.{name}` disagree on its type")]
+    IncompatibleImports {
+        /// The name of the import, `memory` or `__indirect_function_table`.
+        name: String,
+    },
+
+    /// The shared memory couldn't be created.
+    #[error(transparent)]
+    Memory(MemoryError),
+
+    /// The shared table couldn't be created.
+    #[error(transparent)]
+    Table(RuntimeError),
+
+    /// A module couldn't be instantiated.
+    #[error("instantiating module `{module}`: {error}")]
+    Instantiation {
+        /// The name the module was added with.
+        module: String,
+        /// The error instantiating it.
+        error: InstantiationError,
+    },
+}
+
+/// Instantiates a set of modules importing from one another.
+///
+/// Each module is added under the name the others import it by. The
+/// modules are instantiated once the modules they import from are, and
+/// their imports are resolved with the exports of those instances first,
+/// then with the imports given by the host.
+///
+/// Following the conventions of dynamic linking, the modules importing
+/// `env.memory` or `env.__indirect_function_table` share the same memory
+/// or table, created large enough for all of them unless the host
+/// imports provide it.
+///
+/// ```
+/// # use wasmer::*;
+/// # fn main() -> anyhow::Result<()> {
+/// let store = Store::default();
+/// let math = Module::new(&store, r#"(module
+///     (import "env" "memory" (memory 1))
+///     (func (export "double") (param i32) (result i32)
+///         (i32.mul (local.get 0) (i32.const 2))))"#)?;
+/// let main = Module::new(&store, r#"(module
+///     (import "env" "memory" (memory 1))
+///     (import "math" "double" (func $double (param i32) (result i32)))
+///     (func (export "run") (result i32)
+///         (call $double (i32.const 21))))"#)?;
+///
+/// let instances = Linker::new(&store)
+///     .module("main", main)
+///     .module("math", math)
+///     .instantiate()?;
+///
+/// let run = instances.get("main").unwrap().exports.get_function("run")?;
+/// assert_eq!(run.call(&[])?[0], Value::I32(42));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Linker {
+    store: Store,
+    modules: Vec<(String, Module)>,
+    imports: ImportObject,
+}
+
+/// The instances of the modules linked by a [`Linker`].
+#[derive(Clone)]
+pub struct LinkedInstances {
+    instances: Vec<(String, Instance)>,
+    memory: Option<Memory>,
+    table: Option<Table>,
+}
+
+impl Linker {
+    /// Creates a linker without modules.
+    pub fn new(store: &Store) -> Self {
+        Self {
+            store: store.clone(),
+            modules: Vec::new(),
+            imports: ImportObject::new(),
+        }
+    }
+
+    /// Adds a module, imported by the others as `name`. It replaces the
+    /// module previously added with that name.
+    pub fn module(&mut self, name: impl Into<String>, module: Module) -> &mut Self {
+        let name = name.into();
+        match self.modules.iter_mut().find(|(added, _)| *added == name) {
+            Some(added) => added.1 = module,
+            None => self.modules.push((name, module)),
+        }
+        self
+    }
+
+    /// Sets the imports given by the host, resolving what the modules
+    /// don't import from one another.
+    pub fn imports(&mut self, imports: ImportObject) -> &mut Self {
+        self.imports = imports;
+        self
+    }
+
+    /// The names of the modules, in the order they are instantiated in.
+    ///
+    /// The modules are kept in the order they were added, unless a
+    /// module imports from one added after it.
+    pub fn instantiation_order(&self) -> Result<Vec<&str>, LinkerError> {
+        let index: HashMap<&str, usize> = self
+            .modules
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| (name.as_str(), i))
+            .collect();
+        let dependencies: Vec<Vec<usize>> = self
+            .modules
+            .iter()
+            .map(|(_, module)| {
+                let mut dependencies: Vec<usize> = module
+                    .imports()
+                    .filter_map(|import| index.get(import.module()).copied())
+                    .collect();
+                dependencies.sort_unstable();
+                dependencies.dedup();
+                dependencies
+            })
+            .collect();
+
+        // A depth first search, where a module still `Visiting` when it's
+        // reached again closes a cycle.
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            New,
+            Visiting,
+            Done,
+        }
+        let mut marks = vec![Mark::New; self.modules.len()];
+        let mut order = Vec::with_capacity(self.modules.len());
+        let mut path = Vec::new();
+        for root in 0..self.modules.len() {
+            if marks[root] != Mark::New {
+                continue;
+            }
+            marks[root] = Mark::Visiting;
+            path.push((root, 0));
+            while let Some((module, next)) = path.last_mut() {
+                let module = *module;
+                match dependencies[module].get(*next) {
+                    Some(&dependency) => {
+                        *next += 1;
+                        match marks[dependency] {
+                            Mark::New => {
+                                marks[dependency] = Mark::Visiting;
+                                path.push((dependency, 0));
+                            }
+                            Mark::Visiting => {
+                                let start = path
+                                    .iter()
+                                    .position(|&(module, _)| module == dependency)
+                                    .unwrap();
+                                return Err(LinkerError::Cycle(
+                                    path[start..]
+                                        .iter()
+                                        .map(|&(module, _)| self.modules[module].0.clone())
+                                        .collect(),
+                                ));
+                            }
+                            Mark::Done => (),
+                        }
+                    }
+                    None => {
+                        marks[module] = Mark::Done;
+                        order.push(self.modules[module].0.as_str());
+                        path.pop();
+                    }
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// Instantiates the modules, in their instantiation order.
+    pub fn instantiate(&self) -> Result<LinkedInstances, LinkerError> {
+        let order = self.instantiation_order()?;
+        let memory = match self.shared_memory_type()? {
+            Some(ty) if self.imports.get_export(ENV, MEMORY).is_none() => {
+                Some(Memory::new(&self.store, ty).map_err(LinkerError::Memory)?)
+            }
+            _ => None,
+        };
+        let table = match self.shared_table_type()? {
+            Some(ty) if self.imports.get_export(ENV, TABLE).is_none() => {
+                Some(Table::new(&self.store, ty, Val::FuncRef(None)).map_err(LinkerError::Table)?)
+            }
+            _ => None,
+        };
+
+        let mut linked = ImportObject::new();
+        let mut env = Exports::new();
+        if let Some(memory) = &memory {
+            env.insert(MEMORY, memory.clone());
+        }
+        if let Some(table) = &table {
+            env.insert(TABLE, table.clone());
+        }
+        linked.register(ENV, env);
+
+        let mut instances = Vec::with_capacity(order.len());
+        for name in order {
+            let (_, module) = self
+                .modules
+                .iter()
+                .find(|(added, _)| added == name)
+                .unwrap();
+            let resolver = (&linked).chain_back(&self.imports);
+            let instance =
+                Instance::new(module, &resolver).map_err(|error| LinkerError::Instantiation {
+                    module: name.to_string(),
+                    error,
+                })?;
+            linked.register(name, instance.exports.clone());
+            instances.push((name.to_string(), instance));
+        }
+        Ok(LinkedInstances {
+            instances,
+            memory,
+            table,
+        })
+    }
+
+    /// The imports the modules make of a shared memory or table.
+    fn shared_imports<'a>(&'a self, name: &'a str) -> impl Iterator<Item = ExternType> + 'a {
+        self.modules.iter().flat_map(move |(_, module)| {
+            module
+                .imports()
+                .filter(move |import| import.module() == ENV && import.name() == name)
+                .map(|import| import.ty().clone())
+        })
+    }
+
+    /// The type of a memory satisfying every import of `env.memory`.
+    fn shared_memory_type(&self) -> Result<Option<MemoryType>, LinkerError> {
+        let incompatible = || LinkerError::IncompatibleImports {
+            name: MEMORY.to_string(),
+        };
+        let mut shared: Option<MemoryType> = None;
+        for ty in self.shared_imports(MEMORY) {
+            let ty = match ty {
+                ExternType::Memory(ty) => ty,
+                _ => return Err(incompatible()),
+            };
+            shared = Some(match shared {
+                None => ty,
+                Some(shared) if shared.shared != ty.shared => return Err(incompatible()),
+                Some(shared) => MemoryType {
+                    minimum: shared.minimum.max(ty.minimum),
+                    maximum: min_maximum(shared.maximum, ty.maximum),
+                    shared: shared.shared,
+                },
+            });
+        }
+        match shared {
+            Some(MemoryType {
+                minimum,
+                maximum: Some(maximum),
+                ..
+            }) if maximum < minimum => Err(incompatible()),
+            shared => Ok(shared),
+        }
+    }
+
+    /// The type of a table satisfying every import of
+    /// `env.__indirect_function_table`.
+    fn shared_table_type(&self) -> Result<Option<TableType>, LinkerError> {
+        let incompatible = || LinkerError::IncompatibleImports {
+            name: TABLE.to_string(),
+        };
+        let mut shared: Option<TableType> = None;
+        for ty in self.shared_imports(TABLE) {
+            let ty = match ty {
+                ExternType::Table(ty) if ty.ty == ValType::FuncRef => ty,
+                _ => return Err(incompatible()),
+            };
+            shared = Some(match shared {
+                None => ty,
+                Some(shared) => TableType {
+                    ty: shared.ty,
+                    minimum: shared.minimum.max(ty.minimum),
+                    maximum: min_maximum(shared.maximum, ty.maximum),
+                },
+            });
+        }
+        match shared {
+            Some(TableType {
+                minimum,
+                maximum: Some(maximum),
+                ..
+            }) if maximum < minimum => Err(incompatible()),
+            shared => Ok(shared),
+        }
+    }
+}
+
+/// The smallest of two maximums, `None` being unbounded.
+fn min_maximum<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+impl LinkedInstances {
+    /// The instance of the module added as `name`.
+    pub fn get(&self, name: &str) -> Option<&Instance> {
+        self.instances
+            .iter()
+            .find(|(instantiated, _)| instantiated == name)
+            .map(|(_, instance)| instance)
+    }
+
+    /// The instances with the names of their modules, in the order they
+    /// were instantiated in.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Instance)> + '_ {
+        self.instances
+            .iter()
+            .map(|(name, instance)| (name.as_str(), instance))
+    }
+
+    /// The memory created for the modules importing `env.memory`, unless
+    /// the host imports provided it.
+    pub fn memory(&self) -> Option<&Memory> {
+        self.memory.as_ref()
+    }
+
+    /// The table created for the modules importing
+    /// `env.__indirect_function_table`, unless the host imports
+    /// provided it.
+    pub fn table(&self) -> Option<&Table> {
+        self.table.as_ref()
+    }
+}
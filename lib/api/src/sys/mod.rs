@@ -1,13 +1,24 @@
 mod cell;
+#[cfg(feature = "experimental-command-buffer")]
+pub mod command_buffer;
 mod env;
 mod exports;
 mod externals;
+mod hot_swap;
 mod import_object;
 mod instance;
+mod instance_size;
+mod linker;
 mod module;
+mod module_metadata;
 mod native;
+#[cfg(feature = "compiler")]
+mod optimize;
 mod ptr;
+mod raw_function;
+mod resource_limiter;
 mod store;
+mod trampoline_pool;
 mod tunables;
 mod types;
 
@@ -16,6 +27,28 @@ mod types;
 /// See the [`WasmerEnv`] trait for more information.
 pub use wasmer_derive::WasmerEnv;
 
+/// Generate host bindings for the functions described by a WIT interface.
+///
+/// The path is resolved relative to the `Cargo.toml` of the crate using
+/// the macro. A struct named after the file (`my-api.wit` gives
+/// `MyApi`) is generated, with a `new(&Instance)` constructor and one
+/// method per function. Strings and `list<u8>` values are copied in and
+/// out of the guest linear memory using the `canonical_abi_realloc` and
+/// `canonical_abi_free` exports of the guest, the same way `wit-bindgen`
+/// guests expect.
+///
+/// Only flat function interfaces are supported for now: scalar types,
+/// `string` and `list<u8>`.
+///
+/// ```ignore
+/// wasmer::import!("greeter.wit");
+///
+/// let greeter = Greeter::new(&instance)?;
+/// let greeting: String = greeter.greet("wasmer")?;
+/// ```
+#[cfg(feature = "wit")]
+pub use wasmer_derive::import;
+
 #[doc(hidden)]
 pub mod internals {
     //! We use the internals module for exporting types that are only
@@ -27,17 +60,35 @@ pub mod internals {
 }
 
 pub use crate::sys::cell::WasmCell;
-pub use crate::sys::env::{HostEnvInitError, LazyInit, WasmerEnv};
+#[cfg(feature = "experimental-command-buffer")]
+pub use crate::sys::command_buffer::{
+    Command, CommandBuffer, CommandBufferError, CommandBufferFull, CommandWriter,
+};
+pub use crate::sys::env::{FrozenFuncEnv, HostEnvInitError, LazyInit, WasmerEnv};
 pub use crate::sys::exports::{ExportError, Exportable, Exports, ExportsIterator};
 pub use crate::sys::externals::{
-    Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, Table, WasmTypeList,
+    Extern, FromToNativeWasmType, Function, FunctionFlags, Global, HostFunction, Memory, Table,
+    WasmTypeList,
 };
+pub use crate::sys::hot_swap::{HotSwapError, Migration};
 pub use crate::sys::import_object::{ImportObject, ImportObjectIterator, LikeNamespace};
 pub use crate::sys::instance::{Instance, InstantiationError};
-pub use crate::sys::module::Module;
-pub use crate::sys::native::NativeFunc;
-pub use crate::sys::ptr::{Array, Item, WasmPtr};
+pub use crate::sys::instance_size::InstanceSizeEstimate;
+pub use crate::sys::linker::{LinkedInstances, Linker, LinkerError};
+pub use crate::sys::module::{IoCompileError, Module};
+pub use crate::sys::module_metadata::ModuleMetadata;
+pub use crate::sys::native::{LazyNativeFunc, NativeFunc};
+#[cfg(feature = "compiler")]
+pub use crate::sys::optimize::OptimizeOptions;
+pub use crate::sys::ptr::{Array, Item, StringReadError, WasmPtr};
+pub use crate::sys::raw_function::RawFunction;
+pub use crate::sys::resource_limiter::ResourceLimiter;
+#[cfg(feature = "experimental-reference-types-extern-ref")]
+pub use crate::sys::store::ExternRefFinalizer;
+#[cfg(all(feature = "compiler", feature = "default-engine"))]
+pub use crate::sys::store::StoreBuilder;
 pub use crate::sys::store::{Store, StoreObject};
+pub use crate::sys::trampoline_pool::{PooledNativeFunc, TrampolinePool};
 pub use crate::sys::tunables::BaseTunables;
 pub use crate::sys::types::{
     ExportType, ExternType, FunctionType, GlobalType, ImportType, MemoryType, Mutability,
@@ -47,22 +98,26 @@ pub use crate::sys::types::{Val as Value, ValType as Type};
 pub use target_lexicon::{Architecture, CallingConvention, OperatingSystem, Triple, HOST};
 #[cfg(feature = "compiler")]
 pub use wasmer_compiler::{
-    wasmparser, CompilerConfig, FunctionMiddleware, MiddlewareError, MiddlewareReaderState,
-    ModuleMiddleware,
+    wasmparser, CompilerConfig, FunctionAnalysis, FunctionMiddleware, MiddlewareError,
+    MiddlewareReaderState, ModuleAnalysis, ModuleMiddleware,
 };
 pub use wasmer_compiler::{
-    CompileError, CpuFeature, Features, ParseCpuFeatureError, Target, WasmError, WasmResult,
+    CancellationToken, CompileError, CpuFeature, Features, LimitExceeded, ParseCpuFeatureError,
+    PatchPointKind, Target, ValidationLimit, ValidationLimits, WasmError, WasmResult,
 };
 pub use wasmer_engine::{
-    ChainableNamedResolver, DeserializeError, Engine, Export, FrameInfo, LinkError, NamedResolver,
-    NamedResolverChain, Resolver, RuntimeError, SerializeError, Tunables,
+    hot_functions, set_probe_callback, set_step_callback, start_sampling, stop_sampling,
+    ChainableNamedResolver, CustomTrap, DeserializeError, Engine, Export, FrameInfo, HostFrameInfo,
+    HostFunctionInfo, HotFunction, LinkError, NamedResolver, NamedResolverChain, Probe, Resolver,
+    RuntimeError, SerializeError, Step, TraceFrame, Tunables,
 };
 pub use wasmer_types::is_wasm;
 #[cfg(feature = "experimental-reference-types-extern-ref")]
 pub use wasmer_types::ExternRef;
 pub use wasmer_types::{
-    Atomically, Bytes, ExportIndex, GlobalInit, LocalFunctionIndex, MemoryView, Pages, ValueType,
-    WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
+    Atomically, Bytes, ExportIndex, FunctionIndex, GlobalInit, LocalFunctionIndex,
+    MemoryAccessError, MemoryView, Pages, TrapCode, ValueType, WASM_MAX_PAGES, WASM_MIN_PAGES,
+    WASM_PAGE_SIZE,
 };
 
 // TODO: should those be moved into wasmer::vm as well?
@@ -71,8 +126,8 @@ pub mod vm {
     //! The `vm` module re-exports wasmer-vm types.
 
     pub use wasmer_vm::{
-        Memory, MemoryError, MemoryStyle, Table, TableStyle, VMExtern, VMMemoryDefinition,
-        VMTableDefinition,
+        Memory, MemoryError, MemoryStyle, SignatureRegistry, Table, TableStyle, VMExtern,
+        VMFunctionBody, VMMemoryDefinition, VMTableDefinition, VMTrampoline,
     };
 }
 
@@ -109,7 +164,9 @@ pub use wasmer_compiler_cranelift::{Cranelift, CraneliftOptLevel};
 pub use wasmer_compiler_llvm::{LLVMOptLevel, LLVM};
 
 #[cfg(feature = "universal")]
-pub use wasmer_engine_universal::{Universal, UniversalArtifact, UniversalEngine};
+pub use wasmer_engine_universal::{
+    CodeAlignment, CodeDedupStats, Universal, UniversalArtifact, UniversalEngine,
+};
 
 #[cfg(feature = "dylib")]
 pub use wasmer_engine_dylib::{Dylib, DylibArtifact, DylibEngine};
@@ -1,3 +1,7 @@
+use crate::sys::instance_size::InstanceSizeEstimate;
+use crate::sys::module_metadata::ModuleMetadata;
+#[cfg(feature = "compiler")]
+use crate::sys::optimize::{self, OptimizeOptions};
 use crate::sys::store::Store;
 use crate::sys::types::{ExportType, ImportType};
 use crate::sys::InstantiationError;
@@ -7,13 +11,18 @@ use std::io;
 use std::path::Path;
 use std::sync::Arc;
 use thiserror::Error;
-use wasmer_compiler::CompileError;
-#[cfg(feature = "wat")]
+#[cfg(feature = "compiler")]
+use wasmer_compiler::ModuleAnalysis;
+#[cfg(any(feature = "wat", feature = "wasmprinter"))]
 use wasmer_compiler::WasmError;
+use wasmer_compiler::{CancellationToken, CompileError};
 use wasmer_engine::{Artifact, DeserializeError, Resolver, SerializeError};
-use wasmer_types::{ExportsIterator, ImportsIterator, ModuleInfo};
-use wasmer_vm::InstanceHandle;
+#[cfg(feature = "compiler")]
+use wasmer_types::{ExportIndex, FunctionIndex, ImportIndex};
+use wasmer_types::{ExportsIterator, ImportsIterator, MemoryStyle, ModuleInfo};
+use wasmer_vm::{CallStack, InstanceAllocator, InstanceHandle, LinearTable};
 
+/// An error while reading or compiling a WebAssembly module.
 #[derive(Error, Debug)]
 pub enum IoCompileError {
     /// An IO error
@@ -24,6 +33,28 @@ pub enum IoCompileError {
     Compile(#[from] CompileError),
 }
 
+/// A reader remembering the last IO error it encountered, so that it can be
+/// reported as such rather than as a compilation error.
+struct IoErrorCapture<R> {
+    inner: R,
+    error: Option<io::Error>,
+}
+
+impl<R: io::Read> io::Read for IoErrorCapture<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf).map_err(|e| {
+            if e.kind() == io::ErrorKind::Interrupted {
+                // The read is retried, this is not a failure.
+                return e;
+            }
+            // Keep the original error and hand back an equivalent one.
+            let copy = io::Error::new(e.kind(), e.to_string());
+            self.error = Some(e);
+            copy
+        })
+    }
+}
+
 /// A WebAssembly Module contains stateless WebAssembly
 /// code that has already been compiled and can be instantiated
 /// multiple times.
@@ -32,6 +63,15 @@ pub enum IoCompileError {
 ///
 /// Cloning a module is cheap: it does a shallow copy of the compiled
 /// contents rather than a deep copy.
+///
+/// ## Sharing a module between threads
+///
+/// A `Module` is `Send` and `Sync`. All its clones share the same
+/// compiled artifact, so the code memory is never duplicated, and they
+/// can be instantiated concurrently from as many threads as needed: the
+/// state shared between instantiations (the engine signature registry,
+/// the trampolines and the frame information) is synchronized
+/// internally.
 #[derive(Clone, MemoryUsage)]
 pub struct Module {
     // The field ordering here is actually significant because of the drop
@@ -50,6 +90,27 @@ pub struct Module {
     // ownership of the code and its metadata.
     artifact: Arc<dyn Artifact>,
     store: Store,
+    #[cfg(feature = "compiler")]
+    #[loupe(skip)]
+    analysis: Option<Arc<ModuleAnalysis>>,
+    /// The binary the module was compiled from, to print it.
+    #[cfg(feature = "wasmprinter")]
+    #[loupe(skip)]
+    binary: Option<Arc<[u8]>>,
+}
+
+#[cfg(test)]
+mod send_test {
+    use super::*;
+
+    fn is_send_and_sync<T: Send + Sync>() -> bool {
+        true
+    }
+
+    #[test]
+    fn module_is_send_and_sync() {
+        assert!(is_send_and_sync::<Module>());
+    }
 }
 
 impl Module {
@@ -125,6 +186,49 @@ impl Module {
         Self::from_binary(store, bytes.as_ref())
     }
 
+    /// Creates a new WebAssembly module like [`Module::new`], giving up
+    /// with [`CompileError::Cancelled`] once `cancellation` is cancelled
+    /// or its deadline has passed.
+    ///
+    /// This bounds the time spent compiling untrusted modules, whose
+    /// huge functions could otherwise stall the compiling thread. The
+    /// compilation is cancelled from another thread with
+    /// [`CancellationToken::cancel`].
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # use std::time::Duration;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let cancellation = CancellationToken::new().with_timeout(Duration::from_secs(10));
+    /// let module = Module::new_cancellable(&store, "(module)", &cancellation)?;
+    ///
+    /// cancellation.cancel();
+    /// assert!(matches!(
+    ///     Module::new_cancellable(&store, "(module)", &cancellation),
+    ///     Err(CompileError::Cancelled)
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[allow(unreachable_code)]
+    pub fn new_cancellable(
+        store: &Store,
+        bytes: impl AsRef<[u8]>,
+        cancellation: &CancellationToken,
+    ) -> Result<Self, CompileError> {
+        #[cfg(feature = "wat")]
+        let bytes = wat::parse_bytes(bytes.as_ref()).map_err(|e| {
+            CompileError::Wasm(WasmError::Generic(format!(
+                "Error when converting wat: {}",
+                e
+            )))
+        })?;
+
+        Self::validate(store, bytes.as_ref())?;
+        Self::compile(store, bytes.as_ref(), cancellation)
+    }
+
     /// Creates a new WebAssembly module from a file path.
     pub fn from_file(store: &Store, file: impl AsRef<Path>) -> Result<Self, IoCompileError> {
         let file_ref = file.as_ref();
@@ -138,6 +242,45 @@ impl Module {
         Ok(module)
     }
 
+    /// Creates a new WebAssembly module from a binary read from `reader`,
+    /// buffering it in full before compiling it.
+    ///
+    /// The module is validated while it is being read: each section and
+    /// function body is checked as soon as it has been received, so an
+    /// invalid module coming from a slow source (such as a network
+    /// connection) is rejected early, without waiting for the whole
+    /// download. Compilation doesn't overlap with the reading: it only
+    /// starts once all the bytes are received and valid.
+    ///
+    /// As with [`Module::from_binary`], the WebAssembly text format is
+    /// not accepted.
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let stream = std::net::TcpStream::connect("127.0.0.1:8080")?;
+    /// let module = Module::from_reader_buffered(&store, stream)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_reader_buffered(
+        store: &Store,
+        reader: impl io::Read,
+    ) -> Result<Self, IoCompileError> {
+        let mut reader = IoErrorCapture {
+            inner: reader,
+            error: None,
+        };
+        let binary = match store.engine().validate_streaming(&mut reader) {
+            Ok(binary) => binary,
+            Err(e) => return Err(reader.error.map_or(e.into(), Into::into)),
+        };
+        Ok(unsafe { Self::from_binary_unchecked(store, &binary)? })
+    }
+
     /// Creates a new WebAssembly module from a binary.
     ///
     /// Opposed to [`Module::new`], this function is not compatible with
@@ -159,7 +302,7 @@ impl Module {
         store: &Store,
         binary: &[u8],
     ) -> Result<Self, CompileError> {
-        let module = Self::compile(store, binary)?;
+        let module = Self::compile(store, binary, &CancellationToken::new())?;
         Ok(module)
     }
 
@@ -173,9 +316,66 @@ impl Module {
         store.engine().validate(binary)
     }
 
-    fn compile(store: &Store, binary: &[u8]) -> Result<Self, CompileError> {
-        let artifact = store.engine().compile(binary, store.tunables())?;
-        Ok(Self::from_artifact(store, artifact))
+    /// Strips the custom sections and the dead functions of a
+    /// WebAssembly binary, as set by `options`, so that it compiles
+    /// faster into a smaller artifact. The binary isn't validated.
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wasm = Module::optimize(
+    ///     r#"(module (func $unused) (func (export "used")))"#,
+    ///     &OptimizeOptions::default(),
+    /// )?;
+    /// let module = Module::new(&store, wasm)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "compiler")]
+    pub fn optimize(
+        bytes: impl AsRef<[u8]>,
+        options: &OptimizeOptions,
+    ) -> Result<Vec<u8>, CompileError> {
+        #[cfg(feature = "wat")]
+        let bytes = wat::parse_bytes(bytes.as_ref()).map_err(|e| {
+            CompileError::Wasm(WasmError::Generic(format!(
+                "Error when converting wat: {}",
+                e
+            )))
+        })?;
+
+        optimize::optimize(bytes.as_ref(), options)
+    }
+
+    fn compile(
+        store: &Store,
+        binary: &[u8],
+        cancellation: &CancellationToken,
+    ) -> Result<Self, CompileError> {
+        let artifact =
+            store
+                .engine()
+                .compile_cancellable(binary, store.tunables(), cancellation)?;
+        #[allow(unused_mut)]
+        let mut module = Self::from_artifact(store, artifact);
+        #[cfg(feature = "compiler")]
+        {
+            let mut analysis = ModuleAnalysis::new(binary)?;
+            if let Some(frame_infos) = module.artifact.frame_infos() {
+                for (index, frame_info) in frame_infos.iter() {
+                    if let Some(frame_size) = frame_info.frame_size {
+                        analysis.set_frame_size(index, frame_size);
+                    }
+                }
+            }
+            module.analysis = Some(Arc::new(analysis));
+        }
+        #[cfg(feature = "wasmprinter")]
+        {
+            module.binary = Some(Arc::from(binary));
+        }
+        Ok(module)
     }
 
     /// Serializes a module into a binary representation that the `Engine`
@@ -267,10 +467,54 @@ impl Module {
         Ok(Self::from_artifact(store, artifact))
     }
 
+    /// Serializes what the module declares — its name, imports, exports
+    /// and custom sections — but none of its code, which
+    /// [`Module::deserialize_metadata`] can later load without an
+    /// `Engine`.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = Module::new(&store, r#"(module $app
+    ///     (import "env" "log" (func (param i32)))
+    ///     (memory (export "memory") 1))"#)?;
+    /// let serialized = module.serialize_metadata()?;
+    ///
+    /// let metadata = unsafe { Module::deserialize_metadata(&serialized)? };
+    /// assert_eq!(metadata.name(), Some("app"));
+    /// assert_eq!(metadata.imports().len(), 1);
+    /// assert_eq!(metadata.exports().next().unwrap().name(), "memory");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn serialize_metadata(&self) -> Result<Vec<u8>, SerializeError> {
+        ModuleMetadata::new(self.info()).serialize()
+    }
+
+    /// Deserializes the metadata serialized with
+    /// [`Module::serialize_metadata`].
+    ///
+    /// # Safety
+    ///
+    /// Like [`Module::deserialize`], the bytes are deserialized directly
+    /// into Rust objects without being validated: they must come from
+    /// [`Module::serialize_metadata`], with the same version of Wasmer.
+    /// They contain no code, though.
+    pub unsafe fn deserialize_metadata(bytes: &[u8]) -> Result<ModuleMetadata, DeserializeError> {
+        ModuleMetadata::deserialize(bytes)
+    }
+
     fn from_artifact(store: &Store, artifact: Arc<dyn Artifact>) -> Self {
         Self {
             store: store.clone(),
             artifact,
+            #[cfg(feature = "compiler")]
+            analysis: None,
+            #[cfg(feature = "wasmprinter")]
+            binary: None,
         }
     }
 
@@ -415,11 +659,250 @@ impl Module {
         self.artifact.module_ref().custom_sections(name)
     }
 
+    /// Returns the static analyses of the module: its call graph, the
+    /// nesting of its loops and the native stack used by its functions,
+    /// which some compilers report.
+    ///
+    /// The analyses are made when the module is compiled, so it's `None`
+    /// for deserialized modules.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = r#"(module
+    ///     (func $leaf)
+    ///     (func $caller (call $leaf) (loop (br 0))))"#;
+    /// let module = Module::new(&store, wat)?;
+    /// let analysis = module.analysis().unwrap();
+    /// let caller = analysis.function(FunctionIndex::from_u32(1)).unwrap();
+    /// assert_eq!(caller.callees, vec![FunctionIndex::from_u32(0)]);
+    /// assert_eq!(caller.max_loop_depth, 1);
+    /// assert!(analysis.has_unbounded_loops());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "compiler")]
+    pub fn analysis(&self) -> Option<&ModuleAnalysis> {
+        self.analysis.as_deref()
+    }
+
+    /// Returns the imported functions a call to `function` may end up
+    /// calling, directly or through other functions, to audit the
+    /// capabilities it needs.
+    ///
+    /// It's `None` for deserialized modules, which aren't analyzed.
+    #[cfg(feature = "compiler")]
+    pub fn imports_used_by(&self, function: FunctionIndex) -> Option<Vec<ImportType>> {
+        let analysis = self.analysis.as_ref()?;
+        Some(self.function_imports(&analysis.imports_used_by(function)))
+    }
+
+    /// Returns the imported functions the module may ever call, from its
+    /// exported functions and its start function.
+    ///
+    /// The other imported functions can never be called, so a host may
+    /// refuse to provide them and give stubs instead. It's `None` for
+    /// deserialized modules, which aren't analyzed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = r#"(module
+    ///     (import "env" "read" (func $read))
+    ///     (import "env" "write" (func $write))
+    ///     (func $unused (call $write))
+    ///     (func (export "run") (call $read)))"#;
+    /// let module = Module::new(&store, wat)?;
+    /// let reachable = module.reachable_imports().unwrap();
+    /// assert_eq!(reachable.len(), 1);
+    /// assert_eq!(reachable[0].name(), "read");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "compiler")]
+    pub fn reachable_imports(&self) -> Option<Vec<ImportType>> {
+        let analysis = self.analysis.as_ref()?;
+        Some(self.function_imports(&analysis.reachable_imports()))
+    }
+
+    /// The types of the given imported functions, in the order of the
+    /// imports.
+    #[cfg(feature = "compiler")]
+    fn function_imports(&self, functions: &[FunctionIndex]) -> Vec<ImportType> {
+        let info = self.info();
+        info.imports
+            .values()
+            .zip(info.imports())
+            .filter_map(|(index, import)| match index {
+                ImportIndex::Function(index) if functions.contains(index) => Some(import),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Estimates the memory an instance of this module takes, from the
+    /// layout of its `VMContext` and the styles the tunables picked for
+    /// its memories and tables, for admission control before
+    /// instantiating it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = Module::new(&store, "(module (memory 2) (table 10 funcref))")?;
+    /// let estimate = module.estimated_instance_size();
+    /// assert_eq!(estimate.memories, 2 * WASM_PAGE_SIZE as u64);
+    /// assert!(estimate.memory_reservations >= estimate.memories);
+    /// if estimate.committed() > 64 * 1024 * 1024 {
+    ///     panic!("not enough memory left to instantiate the module");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn estimated_instance_size(&self) -> InstanceSizeEstimate {
+        let info = self.info();
+
+        let mut tables = 0;
+        for table in info.tables.values().skip(info.num_imported_tables) {
+            tables += u64::from(table.minimum) * LinearTable::ELEMENT_SIZE as u64;
+        }
+
+        let mut memories = 0;
+        let mut memory_reservations = 0;
+        let memory_styles = self.artifact.memory_styles();
+        for (index, memory) in info.memories.iter().skip(info.num_imported_memories) {
+            let minimum = memory.minimum.bytes().0 as u64;
+            memories += minimum;
+            memory_reservations += match memory_styles.get(index) {
+                Some(MemoryStyle::Static {
+                    bound,
+                    offset_guard_size,
+                }) => bound.bytes().0 as u64 + offset_guard_size,
+                Some(MemoryStyle::Dynamic { offset_guard_size }) => minimum + offset_guard_size,
+                None => minimum,
+            };
+        }
+
+        InstanceSizeEstimate {
+            vmctx: InstanceAllocator::instance_size(info) as u64,
+            tables,
+            memories,
+            memory_reservations,
+            stack: self.stack_usage().unwrap_or(CallStack::SIZE as u64),
+        }
+    }
+
+    /// The deepest native stack used by the exported functions and the
+    /// start function, when it's statically known.
+    #[cfg(feature = "compiler")]
+    fn stack_usage(&self) -> Option<u64> {
+        let analysis = self.analysis.as_ref()?;
+        let info = self.info();
+        let exported = info.exports.values().filter_map(|export| match export {
+            ExportIndex::Function(index) => Some(*index),
+            _ => None,
+        });
+        let mut stack = 0;
+        for index in exported.chain(info.start_function) {
+            if info.is_imported_function(index) {
+                continue;
+            }
+            stack = stack.max(analysis.max_stack_usage(index)?);
+        }
+        Some(stack)
+    }
+
+    #[cfg(not(feature = "compiler"))]
+    fn stack_usage(&self) -> Option<u64> {
+        None
+    }
+
     /// Returns the [`Store`] where the `Instance` belongs.
     pub fn store(&self) -> &Store {
         &self.store
     }
 
+    /// Prints the module in the WebAssembly text format.
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = Module::new(&store, "(module (func (export \"nop\")))")?;
+    /// assert!(module.to_wat()?.contains("(export \"nop\" (func 0))"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Only modules compiled from a binary or text can be printed, not
+    /// deserialized ones.
+    #[cfg(feature = "wasmprinter")]
+    pub fn to_wat(&self) -> Result<String, CompileError> {
+        let binary = self.binary.as_ref().ok_or_else(|| {
+            CompileError::Wasm(WasmError::Generic(
+                "the binary of a deserialized module can't be printed".to_string(),
+            ))
+        })?;
+        wasmprinter::print_bytes(binary).map_err(|e| {
+            CompileError::Wasm(WasmError::Generic(format!(
+                "Error when printing wat: {}",
+                e
+            )))
+        })
+    }
+
+    /// Prints the module in the WebAssembly text format like
+    /// [`Module::to_wat`], with a comment above each function giving
+    /// the size of the machine code it was compiled to, when the engine
+    /// keeps it.
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = Module::new(&store, "(module (func (export \"nop\")))")?;
+    /// println!("{}", module.to_wat_annotated()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "wasmprinter")]
+    pub fn to_wat_annotated(&self) -> Result<String, CompileError> {
+        let wat = self.to_wat()?;
+        let lengths = match self.artifact.finished_function_lengths() {
+            Some(lengths) => lengths,
+            None => return Ok(wat),
+        };
+        // The functions defined by the module are printed in order, each
+        // starting a line, while the imported ones are printed inside
+        // their import.
+        let mut lengths = lengths.values();
+        let mut annotated = String::with_capacity(wat.len());
+        for line in wat.lines() {
+            if line.trim_start().starts_with("(func") {
+                if let Some(length) = lengths.next() {
+                    let indent = &line[..line.len() - line.trim_start().len()];
+                    annotated.push_str(&format!(
+                        "{};; compiled to {} bytes of machine code\n",
+                        indent, length
+                    ));
+                }
+            }
+            annotated.push_str(line);
+            annotated.push('\n');
+        }
+        Ok(annotated)
+    }
+
     /// The ABI of the ModuleInfo is very unstable, we refactor it very often.
     /// This function is public because in some cases it can be useful to get some
     /// extra information from the module.
@@ -0,0 +1,138 @@
+//! The metadata of a module, without its code.
+
+use crate::sys::types::{ExportType, ImportType};
+use rkyv::{
+    archived_value, de::deserializers::SharedDeserializeMap, ser::serializers::AllocSerializer,
+    ser::Serializer as RkyvSerializer, AlignedVec, Deserialize as RkyvDeserialize,
+};
+use std::fmt;
+use std::sync::Arc;
+use wasmer_engine::{DeserializeError, SerializeError};
+use wasmer_types::{ExportsIterator, ImportsIterator, ModuleInfo};
+
+/// Magic number identifying serialized module metadata.
+const MAGIC_HEADER: &[u8; 16] = b"wasmer-metadata\x01";
+
+/// What a [`Module`] declares — its name, imports, exports and custom
+/// sections — without its code.
+///
+/// It's obtained with [`Module::serialize_metadata`] and
+/// [`Module::deserialize_metadata`], which let a service check how
+/// modules link together without compiling them, nor keeping their
+/// artifacts around.
+///
+/// [`Module`]: crate::Module
+/// [`Module::serialize_metadata`]: crate::Module::serialize_metadata
+/// [`Module::deserialize_metadata`]: crate::Module::deserialize_metadata
+#[derive(Clone)]
+pub struct ModuleMetadata {
+    info: ModuleInfo,
+}
+
+impl ModuleMetadata {
+    /// Keeps what a module declares from `info`, dropping its
+    /// initializers.
+    pub(crate) fn new(info: &ModuleInfo) -> Self {
+        let info = ModuleInfo {
+            id: Default::default(),
+            name: info.name.clone(),
+            imports: info.imports.clone(),
+            exports: info.exports.clone(),
+            start_function: info.start_function,
+            table_initializers: Vec::new(),
+            passive_elements: Default::default(),
+            passive_data: Default::default(),
+            global_initializers: Default::default(),
+            function_names: info.function_names.clone(),
+            signatures: info.signatures.clone(),
+            functions: info.functions.clone(),
+            tables: info.tables.clone(),
+            memories: info.memories.clone(),
+            globals: info.globals.clone(),
+            custom_sections: info.custom_sections.clone(),
+            custom_sections_data: info.custom_sections_data.clone(),
+            num_imported_functions: info.num_imported_functions,
+            num_imported_tables: info.num_imported_tables,
+            num_imported_memories: info.num_imported_memories,
+            num_imported_globals: info.num_imported_globals,
+        };
+        Self { info }
+    }
+
+    /// The serialized metadata: a magic header, followed by the rkyv
+    /// serialization of the `ModuleInfo` and its position (8 bytes).
+    pub(crate) fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
+        let mut serializer = AllocSerializer::<4096>::default();
+        let pos = serializer
+            .serialize_value(&self.info)
+            .map_err(|error| SerializeError::Generic(format!("{}", error)))?
+            as u64;
+        let serialized = serializer.into_serializer().into_inner();
+        let mut bytes = Vec::with_capacity(MAGIC_HEADER.len() + serialized.len() + 8);
+        bytes.extend_from_slice(MAGIC_HEADER);
+        bytes.extend_from_slice(&serialized);
+        bytes.extend_from_slice(&pos.to_le_bytes());
+        Ok(bytes)
+    }
+
+    /// # Safety
+    ///
+    /// See [`Module::deserialize_metadata`](crate::Module::deserialize_metadata).
+    pub(crate) unsafe fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let bytes = bytes.strip_prefix(&MAGIC_HEADER[..]).ok_or_else(|| {
+            DeserializeError::Incompatible(
+                "The provided bytes are not module metadata serialized by Wasmer".to_string(),
+            )
+        })?;
+        if bytes.len() < 8 {
+            return Err(DeserializeError::CorruptedBinary(
+                "invalid serialized metadata".to_string(),
+            ));
+        }
+        let (serialized, pos) = bytes.split_at(bytes.len() - 8);
+        let mut pos_bytes = [0; 8];
+        pos_bytes.copy_from_slice(pos);
+        let pos = u64::from_le_bytes(pos_bytes) as usize;
+        if pos >= serialized.len() {
+            return Err(DeserializeError::CorruptedBinary(
+                "invalid serialized metadata".to_string(),
+            ));
+        }
+        // The archive must be aligned, which a borrowed slice may not be.
+        let mut aligned = AlignedVec::with_capacity(serialized.len());
+        aligned.extend_from_slice(serialized);
+        let archived = archived_value::<ModuleInfo>(&aligned, pos);
+        let mut deserializer = SharedDeserializeMap::new();
+        let info = RkyvDeserialize::deserialize(archived, &mut deserializer)
+            .map_err(|error| DeserializeError::CorruptedBinary(format!("{:?}", error)))?;
+        Ok(Self { info })
+    }
+
+    /// Returns the name of the module, if any.
+    pub fn name(&self) -> Option<&str> {
+        self.info.name.as_deref()
+    }
+
+    /// Returns an iterator over the imported types of the module.
+    pub fn imports<'a>(&'a self) -> ImportsIterator<impl Iterator<Item = ImportType> + 'a> {
+        self.info.imports()
+    }
+
+    /// Returns an iterator over the exported types of the module.
+    pub fn exports<'a>(&'a self) -> ExportsIterator<impl Iterator<Item = ExportType> + 'a> {
+        self.info.exports()
+    }
+
+    /// Returns the custom sections of the module with the given `name`.
+    pub fn custom_sections<'a>(&'a self, name: &'a str) -> impl Iterator<Item = Arc<[u8]>> + 'a {
+        self.info.custom_sections(name)
+    }
+}
+
+impl fmt::Debug for ModuleMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ModuleMetadata")
+            .field("name", &self.name())
+            .finish()
+    }
+}
@@ -9,12 +9,16 @@
 //! ```
 use std::marker::PhantomData;
 
-use crate::sys::externals::function::{DynamicFunction, VMDynamicFunction};
+use crate::sys::externals::function::{check_native_signature, DynamicFunction, VMDynamicFunction};
 use crate::sys::{FromToNativeWasmType, Function, RuntimeError, Store, WasmTypeList};
 use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
 use wasmer_engine::ExportFunction;
 use wasmer_types::NativeWasmType;
-use wasmer_vm::{VMDynamicFunctionContext, VMFunctionBody, VMFunctionEnvironment, VMFunctionKind};
+use wasmer_vm::{
+    Trap, VMDynamicFunctionContext, VMFunctionBody, VMFunctionEnvironment, VMFunctionKind,
+    VMTrampoline,
+};
 
 /// A WebAssembly function that can be called natively
 /// (using the Native ABI).
@@ -39,6 +43,10 @@ where
         }
     }
 
+    pub(crate) fn store(&self) -> &Store {
+        &self.store
+    }
+
     pub(crate) fn is_host(&self) -> bool {
         self.exported.vm_function.instance_ref.is_none()
     }
@@ -123,6 +131,50 @@ where
     }
 }
 
+/// A [`NativeFunc`] whose signature is only checked the first time it
+/// is accessed.
+///
+/// Looking up thousands of typed exports eagerly means comparing as many
+/// signatures up front; a `LazyNativeFunc` defers that work until the
+/// function is actually used. It is obtained with
+/// [`Exports::get_native_function_lazy`](crate::Exports::get_native_function_lazy).
+pub struct LazyNativeFunc<Args = (), Rets = ()> {
+    func: NativeFunc<Args, Rets>,
+    checked: AtomicBool,
+}
+
+impl<Args, Rets> LazyNativeFunc<Args, Rets>
+where
+    Args: WasmTypeList,
+    Rets: WasmTypeList,
+{
+    pub(crate) fn new(function: &Function) -> Self {
+        Self {
+            func: NativeFunc::new(function.store.clone(), function.exported.clone()),
+            checked: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the typed function, checking its signature against `Args`
+    /// and `Rets` on first access.
+    pub fn get(&self) -> Result<&NativeFunc<Args, Rets>, RuntimeError> {
+        if !self.checked.load(Ordering::Acquire) {
+            check_native_signature::<Args, Rets>(&self.func.exported.vm_function.signature)?;
+            self.checked.store(true, Ordering::Release);
+        }
+        Ok(&self.func)
+    }
+}
+
+impl<Args: WasmTypeList, Rets: WasmTypeList> Clone for LazyNativeFunc<Args, Rets> {
+    fn clone(&self) -> Self {
+        Self {
+            func: self.func.clone(),
+            checked: AtomicBool::new(self.checked.load(Ordering::Acquire)),
+        }
+    }
+}
+
 macro_rules! impl_native_traits {
     (  $( $x:ident ),* ) => {
         #[allow(unused_parens, non_snake_case)]
@@ -133,6 +185,44 @@ macro_rules! impl_native_traits {
         {
             /// Call the typed func and return results.
             pub fn call(&self, $( $x: $x, )* ) -> Result<Rets, RuntimeError> {
+                self.call_with(
+                    |vmctx, trampoline, callee, values_vec| unsafe {
+                        wasmer_vm::wasmer_call_trampoline(&self.store, vmctx, trampoline, callee, values_vec)
+                    },
+                    $( $x, )*
+                )
+            }
+
+            /// Call the typed func on `stack`, a memory region owned by the
+            /// caller, and return results.
+            ///
+            /// The lowest page of `stack` is used as a guard page to catch
+            /// stack overflows, and the rest must be large enough for the
+            /// call, which [`Module::estimated_instance_size`] estimates. Host
+            /// functions called directly run on the stack of the caller.
+            ///
+            /// This is only supported on Unix.
+            ///
+            /// [`Module::estimated_instance_size`]: crate::Module::estimated_instance_size
+            #[cfg(unix)]
+            pub fn call_on_stack(&self, stack: &mut [u8], $( $x: $x, )* ) -> Result<Rets, RuntimeError> {
+                let mut region = wasmer_vm::StackRegion::new(stack)
+                    .map_err(|e| RuntimeError::new(format!("invalid stack region: {}", e)))?;
+                self.call_with(
+                    |vmctx, trampoline, callee, values_vec| unsafe {
+                        wasmer_vm::wasmer_call_trampoline_on_region(&self.store, &mut region, vmctx, trampoline, callee, values_vec)
+                    },
+                    $( $x, )*
+                )
+            }
+
+            /// Call the typed func, running the call trampoline of a
+            /// WebAssembly function with `call_trampoline`.
+            fn call_with(
+                &self,
+                call_trampoline: impl FnOnce(VMFunctionEnvironment, VMTrampoline, *const VMFunctionBody, *mut u8) -> Result<(), Trap>,
+                $( $x: $x, )*
+            ) -> Result<Rets, RuntimeError> {
                 if !self.is_host() {
                     // We assume the trampoline is always going to be present for
                     // Wasm functions
@@ -153,15 +243,13 @@ macro_rules! impl_native_traits {
                         }
                         rets_list.as_mut()
                     };
-                    unsafe {
-                        wasmer_vm::wasmer_call_trampoline(
-                            &self.store,
-                            self.vmctx(),
-                            trampoline,
-                            self.address(),
-                            args_rets.as_mut_ptr() as *mut u8,
-                        )
-                    }?;
+                    let _domain = self.exported.vm_function.enter_protection_domain();
+                    call_trampoline(
+                        self.vmctx(),
+                        trampoline,
+                        self.address(),
+                        args_rets.as_mut_ptr() as *mut u8,
+                    )?;
                     let num_rets = rets_list.len();
                     if !using_rets_array && num_rets > 0 {
                         let src_pointer = params_list.as_ptr();
@@ -0,0 +1,527 @@
+//! Preprocessing of WebAssembly binaries before they are compiled.
+
+use std::borrow::Cow;
+use std::ops::Range;
+use wasmer_compiler::wasmparser::{
+    BinaryReader, CodeSectionReader, ElementItem, ElementKind, ElementSectionReader,
+    ExportSectionReader, ExternalKind, FunctionBody, FunctionSectionReader, GlobalSectionReader,
+    ImportSectionEntryType, ImportSectionReader, InitExpr, Operator, Type,
+};
+use wasmer_compiler::{CompileError, WasmError};
+
+const CUSTOM_SECTION: u8 = 0;
+const IMPORT_SECTION: u8 = 2;
+const FUNCTION_SECTION: u8 = 3;
+const GLOBAL_SECTION: u8 = 6;
+const EXPORT_SECTION: u8 = 7;
+const START_SECTION: u8 = 8;
+const ELEMENT_SECTION: u8 = 9;
+const CODE_SECTION: u8 = 10;
+
+/// What [`Module::optimize`] removes from a WebAssembly binary.
+///
+/// [`Module::optimize`]: crate::Module::optimize
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptimizeOptions {
+    /// Removes the custom sections, such as the names, the producers and
+    /// the DWARF debug info, except the ones in `keep_custom_sections`.
+    pub strip_custom_sections: bool,
+    /// The custom sections kept when stripping them.
+    pub keep_custom_sections: Vec<String>,
+    /// Removes the functions which are neither exported, started, in an
+    /// element segment, referenced by a global, nor called or referenced
+    /// by such functions, then renumbers the remaining ones. The `name`
+    /// section is removed along with them, and other custom sections
+    /// referring to functions by index are left as they are.
+    pub remove_dead_functions: bool,
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        Self {
+            strip_custom_sections: true,
+            keep_custom_sections: Vec::new(),
+            remove_dead_functions: true,
+        }
+    }
+}
+
+/// A section of a binary.
+struct Section<'data> {
+    id: u8,
+    /// The offset of the content in the binary.
+    offset: usize,
+    content: &'data [u8],
+}
+
+/// The indices of the functions once the dead ones are removed.
+struct Renumbering {
+    /// The number of imported functions, which are all kept.
+    imported: u32,
+    /// The new index of each function, `None` if it's removed.
+    indices: Vec<Option<u32>>,
+}
+
+impl Renumbering {
+    /// Whether each function defined by the module is kept.
+    fn defined(&self) -> impl Iterator<Item = bool> + '_ {
+        self.indices[self.imported as usize..]
+            .iter()
+            .map(Option::is_some)
+    }
+
+    fn get(&self, index: u32) -> Result<u32, CompileError> {
+        self.indices
+            .get(index as usize)
+            .copied()
+            .flatten()
+            .ok_or_else(|| CompileError::Codegen(format!("function {} was removed", index)))
+    }
+}
+
+pub(crate) fn optimize(bytes: &[u8], options: &OptimizeOptions) -> Result<Vec<u8>, CompileError> {
+    let sections = sections(bytes)?;
+    let renumbering = if options.remove_dead_functions {
+        renumbering(&sections)?
+    } else {
+        None
+    };
+
+    let mut optimized = bytes[..8].to_vec();
+    for section in &sections {
+        let content = match (section.id, &renumbering) {
+            (CUSTOM_SECTION, _) => {
+                let name = custom_section_name(section)?;
+                let stripped = options.strip_custom_sections
+                    && !options.keep_custom_sections.iter().any(|kept| kept == name);
+                if stripped || (name == "name" && renumbering.is_some()) {
+                    continue;
+                }
+                Cow::Borrowed(section.content)
+            }
+            (FUNCTION_SECTION, Some(renumbering)) => {
+                Cow::Owned(rewrite_functions(section, renumbering)?)
+            }
+            (GLOBAL_SECTION, Some(renumbering)) => {
+                Cow::Owned(rewrite_globals(bytes, section, renumbering)?)
+            }
+            (EXPORT_SECTION, Some(renumbering)) => {
+                Cow::Owned(rewrite_exports(section, renumbering)?)
+            }
+            (START_SECTION, Some(renumbering)) => {
+                let mut content = Vec::new();
+                write_u32(&mut content, renumbering.get(start_function(section)?)?);
+                Cow::Owned(content)
+            }
+            (ELEMENT_SECTION, Some(renumbering)) => {
+                Cow::Owned(rewrite_elements(bytes, section, renumbering)?)
+            }
+            (CODE_SECTION, Some(renumbering)) => {
+                Cow::Owned(rewrite_code(bytes, section, renumbering)?)
+            }
+            _ => Cow::Borrowed(section.content),
+        };
+        optimized.push(section.id);
+        write_u32(&mut optimized, content.len() as u32);
+        optimized.extend_from_slice(&content);
+    }
+    Ok(optimized)
+}
+
+fn invalid(message: &str, offset: usize) -> CompileError {
+    CompileError::Wasm(WasmError::InvalidWebAssembly {
+        message: message.to_string(),
+        offset,
+    })
+}
+
+fn read_u32(bytes: &[u8], position: &mut usize) -> Result<u32, CompileError> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes
+            .get(*position)
+            .ok_or_else(|| invalid("unexpected end", *position))?;
+        *position += 1;
+        value |= u32::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("invalid LEB128 integer", *position))
+}
+
+fn write_u32(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn sections(bytes: &[u8]) -> Result<Vec<Section>, CompileError> {
+    if bytes.len() < 8 || &bytes[..4] != b"\0asm" {
+        return Err(invalid("not a WebAssembly binary", 0));
+    }
+    let mut sections = Vec::new();
+    let mut position = 8;
+    while position < bytes.len() {
+        let id = bytes[position];
+        position += 1;
+        let size = read_u32(bytes, &mut position)? as usize;
+        let content = bytes
+            .get(position..position + size)
+            .ok_or_else(|| invalid("section out of bounds", position))?;
+        sections.push(Section {
+            id,
+            offset: position,
+            content,
+        });
+        position += size;
+    }
+    Ok(sections)
+}
+
+fn custom_section_name<'data>(section: &Section<'data>) -> Result<&'data str, CompileError> {
+    let mut position = 0;
+    let length = read_u32(section.content, &mut position)? as usize;
+    section
+        .content
+        .get(position..position + length)
+        .and_then(|name| std::str::from_utf8(name).ok())
+        .ok_or_else(|| invalid("invalid custom section name", section.offset))
+}
+
+fn start_function(section: &Section) -> Result<u32, CompileError> {
+    read_u32(section.content, &mut 0)
+}
+
+/// The function indices of some code: the range of their LEB128
+/// integers, and the function.
+type References = Vec<(Range<usize>, u32)>;
+
+/// The function indices of an init expression, or of a function body
+/// read by `reader` until its end.
+fn references(reader: &mut BinaryReader) -> Result<References, CompileError> {
+    let mut references = Vec::new();
+    while !reader.eof() {
+        let offset = reader.original_position();
+        match reader.read_operator()? {
+            Operator::Call { function_index }
+            | Operator::ReturnCall { function_index }
+            | Operator::RefFunc { function_index } => {
+                references.push((offset + 1..reader.original_position(), function_index))
+            }
+            _ => (),
+        }
+    }
+    Ok(references)
+}
+
+/// The range of a function body, and its function indices.
+fn body_references(body: &FunctionBody) -> Result<(Range<usize>, References), CompileError> {
+    let mut reader = body.get_binary_reader();
+    let start = reader.original_position();
+    let range = start..start + reader.bytes_remaining();
+    for _ in 0..reader.read_var_u32()? {
+        reader.read_var_u32()?;
+        reader.read_type()?;
+    }
+    Ok((range, references(&mut reader)?))
+}
+
+fn init_expr_references(expr: &InitExpr) -> Result<References, CompileError> {
+    references(&mut expr.get_binary_reader())
+}
+
+/// Copies the code in `range` of `bytes`, renumbering the functions.
+fn write_code(
+    out: &mut Vec<u8>,
+    bytes: &[u8],
+    range: Range<usize>,
+    references: &[(Range<usize>, u32)],
+    renumbering: &Renumbering,
+) -> Result<(), CompileError> {
+    let mut copied = range.start;
+    for (index_range, index) in references {
+        out.extend_from_slice(&bytes[copied..index_range.start]);
+        write_u32(out, renumbering.get(*index)?);
+        copied = index_range.end;
+    }
+    out.extend_from_slice(&bytes[copied..range.end]);
+    Ok(())
+}
+
+fn write_init_expr(
+    out: &mut Vec<u8>,
+    bytes: &[u8],
+    expr: &InitExpr,
+    renumbering: &Renumbering,
+) -> Result<(), CompileError> {
+    let reader = expr.get_binary_reader();
+    let start = reader.original_position();
+    let range = start..start + reader.bytes_remaining();
+    write_code(out, bytes, range, &init_expr_references(expr)?, renumbering)
+}
+
+/// Finds the dead functions, or `None` when every function is alive.
+fn renumbering(sections: &[Section]) -> Result<Option<Renumbering>, CompileError> {
+    let mut imported = 0;
+    let mut defined = 0;
+    let mut roots = Vec::new();
+    let mut callees = Vec::new();
+    for section in sections {
+        match section.id {
+            IMPORT_SECTION => {
+                for import in ImportSectionReader::new(section.content, section.offset)? {
+                    if let ImportSectionEntryType::Function(_) = import?.ty {
+                        imported += 1;
+                    }
+                }
+            }
+            FUNCTION_SECTION => {
+                defined = FunctionSectionReader::new(section.content, section.offset)?.get_count();
+            }
+            GLOBAL_SECTION => {
+                for global in GlobalSectionReader::new(section.content, section.offset)? {
+                    let references = init_expr_references(&global?.init_expr)?;
+                    roots.extend(references.into_iter().map(|(_, index)| index));
+                }
+            }
+            EXPORT_SECTION => {
+                for export in ExportSectionReader::new(section.content, section.offset)? {
+                    let export = export?;
+                    if let ExternalKind::Function = export.kind {
+                        roots.push(export.index);
+                    }
+                }
+            }
+            START_SECTION => roots.push(start_function(section)?),
+            ELEMENT_SECTION => {
+                for element in ElementSectionReader::new(section.content, section.offset)? {
+                    for item in element?.items.get_items_reader()? {
+                        match item? {
+                            ElementItem::Func(index) => roots.push(index),
+                            ElementItem::Expr(expr) => {
+                                let references = init_expr_references(&expr)?;
+                                roots.extend(references.into_iter().map(|(_, index)| index));
+                            }
+                        }
+                    }
+                }
+            }
+            CODE_SECTION => {
+                for body in CodeSectionReader::new(section.content, section.offset)? {
+                    let (_, references) = body_references(&body?)?;
+                    callees.push(
+                        references
+                            .into_iter()
+                            .map(|(_, index)| index)
+                            .collect::<Vec<_>>(),
+                    );
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let total = (imported + defined) as usize;
+    let mut alive = vec![false; total];
+    alive[..imported as usize]
+        .iter_mut()
+        .for_each(|alive| *alive = true);
+    let mut pending = roots;
+    while let Some(index) = pending.pop() {
+        match alive.get_mut(index as usize) {
+            Some(alive) if !*alive => *alive = true,
+            Some(_) => continue,
+            None => return Err(invalid("invalid function index", 0)),
+        }
+        if let Some(callees) = index
+            .checked_sub(imported)
+            .and_then(|i| callees.get(i as usize))
+        {
+            pending.extend_from_slice(callees);
+        }
+    }
+    if alive.iter().all(|alive| *alive) {
+        return Ok(None);
+    }
+
+    let mut next = 0;
+    let indices = alive
+        .into_iter()
+        .map(|alive| {
+            alive.then(|| {
+                next += 1;
+                next - 1
+            })
+        })
+        .collect();
+    Ok(Some(Renumbering { imported, indices }))
+}
+
+fn value_type(ty: Type) -> Result<u8, CompileError> {
+    Ok(match ty {
+        Type::I32 => 0x7f,
+        Type::I64 => 0x7e,
+        Type::F32 => 0x7d,
+        Type::F64 => 0x7c,
+        Type::V128 => 0x7b,
+        Type::FuncRef => 0x70,
+        Type::ExternRef => 0x6f,
+        ty => {
+            return Err(CompileError::Wasm(WasmError::Unsupported(format!(
+                "value type {:?}",
+                ty
+            ))))
+        }
+    })
+}
+
+fn rewrite_functions(
+    section: &Section,
+    renumbering: &Renumbering,
+) -> Result<Vec<u8>, CompileError> {
+    let mut content = Vec::new();
+    write_u32(
+        &mut content,
+        renumbering.defined().filter(|kept| *kept).count() as u32,
+    );
+    let functions = FunctionSectionReader::new(section.content, section.offset)?;
+    for (signature, kept) in functions.into_iter().zip(renumbering.defined()) {
+        let signature = signature?;
+        if kept {
+            write_u32(&mut content, signature);
+        }
+    }
+    Ok(content)
+}
+
+fn rewrite_globals(
+    bytes: &[u8],
+    section: &Section,
+    renumbering: &Renumbering,
+) -> Result<Vec<u8>, CompileError> {
+    let globals = GlobalSectionReader::new(section.content, section.offset)?;
+    let mut content = Vec::new();
+    write_u32(&mut content, globals.get_count());
+    for global in globals {
+        let global = global?;
+        content.push(value_type(global.ty.content_type)?);
+        content.push(global.ty.mutable as u8);
+        write_init_expr(&mut content, bytes, &global.init_expr, renumbering)?;
+    }
+    Ok(content)
+}
+
+fn rewrite_exports(section: &Section, renumbering: &Renumbering) -> Result<Vec<u8>, CompileError> {
+    let exports = ExportSectionReader::new(section.content, section.offset)?;
+    let mut content = Vec::new();
+    write_u32(&mut content, exports.get_count());
+    for export in exports {
+        let export = export?;
+        let (kind, index) = match export.kind {
+            ExternalKind::Function => (0, renumbering.get(export.index)?),
+            ExternalKind::Table => (1, export.index),
+            ExternalKind::Memory => (2, export.index),
+            ExternalKind::Global => (3, export.index),
+            ExternalKind::Tag => (4, export.index),
+            ExternalKind::Type | ExternalKind::Module | ExternalKind::Instance => {
+                return Err(CompileError::Wasm(WasmError::Unsupported(
+                    "module linking".to_string(),
+                )))
+            }
+        };
+        write_u32(&mut content, export.field.len() as u32);
+        content.extend_from_slice(export.field.as_bytes());
+        content.push(kind);
+        write_u32(&mut content, index);
+    }
+    Ok(content)
+}
+
+fn rewrite_elements(
+    bytes: &[u8],
+    section: &Section,
+    renumbering: &Renumbering,
+) -> Result<Vec<u8>, CompileError> {
+    let elements = ElementSectionReader::new(section.content, section.offset)?;
+    let mut content = Vec::new();
+    write_u32(&mut content, elements.get_count());
+    for element in elements {
+        let element = element?;
+        let items = element
+            .items
+            .get_items_reader()?
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        let expressions = items
+            .iter()
+            .any(|item| matches!(item, ElementItem::Expr(_)));
+        // The low bits of the flags say whether the segment is passive or
+        // declared, and whether its table is given.
+        let (flags, table_index, offset) = match element.kind {
+            ElementKind::Active {
+                table_index: 0,
+                init_expr,
+            } if element.ty == Type::FuncRef => (0, None, Some(init_expr)),
+            ElementKind::Active {
+                table_index,
+                init_expr,
+            } => (2, Some(table_index), Some(init_expr)),
+            ElementKind::Passive => (1, None, None),
+            ElementKind::Declared => (3, None, None),
+        };
+        content.push(if expressions { flags | 4 } else { flags });
+        if let Some(table_index) = table_index {
+            write_u32(&mut content, table_index);
+        }
+        if let Some(offset) = &offset {
+            write_init_expr(&mut content, bytes, offset, renumbering)?;
+        }
+        if flags != 0 {
+            // The reference type, or the `funcref` element kind.
+            content.push(if expressions {
+                value_type(element.ty)?
+            } else {
+                0x00
+            });
+        }
+        write_u32(&mut content, items.len() as u32);
+        for item in &items {
+            match item {
+                ElementItem::Func(index) => write_u32(&mut content, renumbering.get(*index)?),
+                ElementItem::Expr(expr) => write_init_expr(&mut content, bytes, expr, renumbering)?,
+            }
+        }
+    }
+    Ok(content)
+}
+
+fn rewrite_code(
+    bytes: &[u8],
+    section: &Section,
+    renumbering: &Renumbering,
+) -> Result<Vec<u8>, CompileError> {
+    let mut content = Vec::new();
+    write_u32(
+        &mut content,
+        renumbering.defined().filter(|kept| *kept).count() as u32,
+    );
+    let bodies = CodeSectionReader::new(section.content, section.offset)?;
+    for (body, kept) in bodies.into_iter().zip(renumbering.defined()) {
+        let body = body?;
+        if kept {
+            let (range, references) = body_references(&body)?;
+            let mut code = Vec::with_capacity(range.len());
+            write_code(&mut code, bytes, range, &references, renumbering)?;
+            write_u32(&mut content, code.len() as u32);
+            content.extend_from_slice(&code);
+        }
+    }
+    Ok(content)
+}
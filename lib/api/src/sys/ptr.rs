@@ -7,8 +7,12 @@
 //! related bugs when implementing an ABI.
 
 use crate::sys::cell::WasmCell;
-use crate::sys::{externals::Memory, FromToNativeWasmType};
-use std::{cell::Cell, fmt, marker::PhantomData, mem};
+use crate::sys::{
+    externals::Memory, Exports, FromToNativeWasmType, MemoryAccessError, NativeFunc, RuntimeError,
+};
+use std::string::FromUtf8Error;
+use std::{cell::Cell, convert::TryFrom, fmt, marker::PhantomData, mem};
+use thiserror::Error;
 use wasmer_types::ValueType;
 
 /// The `Array` marker type. This type can be used like `WasmPtr<T, Array>`
@@ -238,6 +242,123 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
     }
 }
 
+/// An error while reading a UTF-8 string from Wasm linear memory.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum StringReadError {
+    /// The string is not entirely in the memory.
+    #[error(transparent)]
+    OutOfBounds(#[from] MemoryAccessError),
+
+    /// The bytes of the string are not valid UTF-8.
+    #[error(transparent)]
+    InvalidUtf8(#[from] FromUtf8Error),
+}
+
+/// Methods for copying whole slices of data between the host and Wasm
+/// linear memory.
+impl<T: Copy + ValueType> WasmPtr<T, Array> {
+    /// Copies `length` items starting at this pointer into a new `Vec`.
+    ///
+    /// Returns a [`MemoryAccessError`], in bytes, if the range is out of
+    /// bounds.
+    pub fn read_to_vec(self, memory: &Memory, length: u32) -> Result<Vec<T>, MemoryAccessError> {
+        let byte_len = mem::size_of::<T>() as u64 * u64::from(length);
+        if u64::from(self.offset) + byte_len > memory.data_size() {
+            return Err(MemoryAccessError {
+                offset: self.offset.into(),
+                length: byte_len,
+            });
+        }
+
+        let mut vec = Vec::<T>::with_capacity(length as usize);
+        unsafe {
+            // The items are copied byte by byte, so the pointer doesn't
+            // need to be aligned.
+            std::ptr::copy_nonoverlapping(
+                memory.data_ptr().add(self.offset as usize),
+                vec.as_mut_ptr() as *mut u8,
+                byte_len as usize,
+            );
+            vec.set_len(length as usize);
+        }
+        Ok(vec)
+    }
+
+    /// Writes the items of `data` to Wasm linear memory, starting at this
+    /// pointer.
+    ///
+    /// Returns a [`MemoryAccessError`], in bytes, if the range is out of
+    /// bounds, in which case the memory is left untouched.
+    pub fn write_slice(self, memory: &Memory, data: &[T]) -> Result<(), MemoryAccessError> {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(data.as_ptr() as *const u8, mem::size_of_val(data))
+        };
+        memory.write(self.offset.into(), bytes)
+    }
+}
+
+/// Methods for marshaling strings and byte buffers.
+impl WasmPtr<u8, Array> {
+    /// Reads a UTF-8 `String` of `str_len` bytes starting at this pointer.
+    ///
+    /// Unlike [`WasmPtr::get_utf8_string`], the bytes are copied out of
+    /// the memory in one go.
+    pub fn read_utf8_string(
+        self,
+        memory: &Memory,
+        str_len: u32,
+    ) -> Result<String, StringReadError> {
+        let bytes = self.read_to_vec(memory, str_len)?;
+        Ok(String::from_utf8(bytes)?)
+    }
+
+    /// Writes `string` to Wasm linear memory starting at this pointer.
+    ///
+    /// No nul terminator is added; returns a [`MemoryAccessError`] if the
+    /// string doesn't fit in the memory.
+    pub fn write_utf8_str(self, memory: &Memory, string: &str) -> Result<(), MemoryAccessError> {
+        self.write_slice(memory, string.as_bytes())
+    }
+
+    /// Allocates a buffer in the guest by calling its exported allocator
+    /// and copies `bytes` into it.
+    ///
+    /// `malloc` is the name of an exported function with the signature
+    /// `(i32) -> i32` which returns the address of a fresh allocation of
+    /// the given size in `memory`, like the `malloc` of most guest
+    /// toolchains.
+    ///
+    /// This is handy for passing strings or buffers to guest functions:
+    ///
+    /// ```
+    /// # use wasmer::{Array, Instance, WasmPtr};
+    /// # fn example(instance: &Instance) -> anyhow::Result<()> {
+    /// let memory = instance.exports.get_memory("memory")?;
+    /// let ptr = WasmPtr::<u8, Array>::allocate_and_copy(&instance.exports, memory, "malloc", b"hello")?;
+    /// let greet = instance.exports.get_native_function::<(WasmPtr<u8, Array>, u32), ()>("greet")?;
+    /// greet.call(ptr, 5)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn allocate_and_copy(
+        exports: &Exports,
+        memory: &Memory,
+        malloc: &str,
+        bytes: &[u8],
+    ) -> Result<Self, RuntimeError> {
+        let malloc: NativeFunc<i32, i32> = exports
+            .get_native_function(malloc)
+            .map_err(|e| RuntimeError::new(e.to_string()))?;
+        let len = i32::try_from(bytes.len())
+            .map_err(|_| RuntimeError::new("the buffer is too large for a 32-bit memory"))?;
+        let ptr = Self::new(malloc.call(len)? as u32);
+        ptr.write_slice(memory, bytes).map_err(|e| {
+            RuntimeError::new(format!("the guest allocator returned a bad buffer: {}", e))
+        })?;
+        Ok(ptr)
+    }
+}
+
 unsafe impl<T: Copy, Ty> FromToNativeWasmType for WasmPtr<T, Ty> {
     type Native = i32;
 
@@ -362,4 +483,47 @@ mod test {
             assert!(oob_end_array_ptr.deref(&memory, 1, 0).is_none());
         }
     }
+
+    #[test]
+    fn wasm_ptr_slice_roundtrip() {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, Some(1), false)).unwrap();
+
+        let ptr: WasmPtr<u32, Array> = WasmPtr::new(16);
+        assert!(ptr.write_slice(&memory, &[1, 2, 3]).is_ok());
+        assert_eq!(ptr.read_to_vec(&memory, 3), Ok(vec![1, 2, 3]));
+
+        let string_ptr: WasmPtr<u8, Array> = WasmPtr::new(64);
+        assert!(string_ptr.write_utf8_str(&memory, "héllo").is_ok());
+        assert_eq!(
+            string_ptr.read_utf8_string(&memory, 6),
+            Ok("héllo".to_string())
+        );
+        assert!(matches!(
+            string_ptr.read_utf8_string(&memory, 2),
+            Err(StringReadError::InvalidUtf8(_))
+        ));
+
+        // Out of bounds accesses are rejected and leave the memory untouched.
+        let last = (memory.size().bytes().0 - 2) as u32;
+        let end_ptr: WasmPtr<u8, Array> = WasmPtr::new(last);
+        assert_eq!(
+            end_ptr.write_slice(&memory, &[1, 2, 3]),
+            Err(MemoryAccessError {
+                offset: last.into(),
+                length: 3
+            })
+        );
+        assert_eq!(end_ptr.read_to_vec(&memory, 2), Ok(vec![0, 0]));
+        assert!(end_ptr.read_to_vec(&memory, 3).is_err());
+        assert!(matches!(
+            end_ptr.read_utf8_string(&memory, 3),
+            Err(StringReadError::OutOfBounds(_))
+        ));
+
+        // Misaligned pointers are fine, the items being copied bytewise.
+        let misaligned: WasmPtr<u32, Array> = WasmPtr::new(17);
+        assert!(misaligned.write_slice(&memory, &[0x0403_0201]).is_ok());
+        assert_eq!(misaligned.read_to_vec(&memory, 1), Ok(vec![0x0403_0201]));
+    }
 }
@@ -0,0 +1,127 @@
+//! Raw entry points of WebAssembly functions, for FFI layers.
+//!
+//! Language bindings usually come with their own FFI machinery, like
+//! libffi, which can call native code directly rather than going through
+//! arrays of [`Value`](crate::Value)s. A [`RawFunction`] gives them what
+//! they need to call a function defined in WebAssembly:
+//!
+//! - its [native entry point](RawFunction::address), to be called with
+//!   the C calling convention of the platform, taking the
+//!   [`vmctx`](RawFunction::vmctx) first and then the parameters of the
+//!   function (`i32`, `i64`, `f32` and `f64` as their C equivalents,
+//!   references as pointers), and returning its result if it has one;
+//! - its [call trampoline](RawFunction::call_trampoline), which takes the
+//!   `vmctx`, the entry point and an array of 16-byte slots, holding the
+//!   parameters on entry and the results on exit, for functions of any
+//!   signature, including the ones with several results.
+//!
+//! WebAssembly code must run on a stack set up to catch its traps: the
+//! entry point must be called from [`RawFunction::enter`], and the call
+//! trampoline through [`RawFunction::call_values`].
+//!
+//! ```
+//! # use wasmer::*;
+//! # fn main() -> anyhow::Result<()> {
+//! # let store = Store::default();
+//! let module = Module::new(&store, r#"(module
+//!     (func (export "add") (param i32 i32) (result i32)
+//!         (i32.add (local.get 0) (local.get 1))))"#)?;
+//! let instance = Instance::new(&module, &imports! {})?;
+//! let add = RawFunction::new(instance.exports.get_function("add")?).unwrap();
+//!
+//! // Through the native entry point, as an FFI layer would.
+//! let native: extern "C" fn(*mut std::ffi::c_void, i32, i32) -> i32 =
+//!     unsafe { std::mem::transmute(add.address()) };
+//! assert_eq!(unsafe { add.enter(|| native(add.vmctx(), 1, 2)) }?, 3);
+//!
+//! // Through the call trampoline.
+//! let mut values = [1u128, 2];
+//! unsafe { add.call_values(values.as_mut_ptr()) }?;
+//! assert_eq!(values[0] as i32, 3);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::sys::{Function, FunctionType, RuntimeError};
+use std::ffi::c_void;
+use wasmer_vm::{VMFunctionBody, VMTrampoline};
+
+/// The raw entry points of a function defined in WebAssembly.
+///
+/// It keeps the instance of the function alive. See the
+/// [module documentation](self) for more information.
+#[derive(Clone)]
+pub struct RawFunction {
+    function: Function,
+    call_trampoline: VMTrampoline,
+}
+
+impl RawFunction {
+    /// The entry points of `function`, or `None` if it's a host function,
+    /// whose code belongs to the host already.
+    pub fn new(function: &Function) -> Option<Self> {
+        let call_trampoline = function.exported.vm_function.call_trampoline?;
+        Some(Self {
+            function: function.clone(),
+            call_trampoline,
+        })
+    }
+
+    /// The signature of the function.
+    pub fn ty(&self) -> &FunctionType {
+        self.function.ty()
+    }
+
+    /// The native entry point of the function.
+    pub fn address(&self) -> *const VMFunctionBody {
+        self.function.exported.vm_function.address
+    }
+
+    /// The context to pass as the first argument of the entry point and
+    /// of the call trampoline.
+    pub fn vmctx(&self) -> *mut c_void {
+        unsafe { self.function.exported.vm_function.vmctx.host_env }
+    }
+
+    /// The call trampoline of the signature of the function.
+    ///
+    /// The array it takes has one slot per parameter or result, whichever
+    /// are more numerous.
+    pub fn call_trampoline(&self) -> VMTrampoline {
+        self.call_trampoline
+    }
+
+    /// Calls the function through its call trampoline, with the
+    /// parameters in `values`, and the results written back to it.
+    ///
+    /// # Safety
+    ///
+    /// `values` must point to an array of as many slots as the function
+    /// has parameters or results, the parameters being of the right
+    /// types.
+    pub unsafe fn call_values(&self, values: *mut u128) -> Result<(), RuntimeError> {
+        let vm_function = &self.function.exported.vm_function;
+        let _domain = vm_function.enter_protection_domain();
+        wasmer_vm::wasmer_call_trampoline(
+            self.function.store(),
+            vm_function.vmctx,
+            self.call_trampoline,
+            vm_function.address,
+            values as *mut u8,
+        )
+        .map_err(RuntimeError::from_trap)
+    }
+
+    /// Runs `f`, which calls the native entry point, on a stack which
+    /// catches the traps of WebAssembly code, returned as errors.
+    ///
+    /// # Safety
+    ///
+    /// The entry point must be called with the signature of the function.
+    /// Since a trap unwinds `f` without running destructors, `f` must not
+    /// own values which need to be dropped.
+    pub unsafe fn enter<R>(&self, f: impl FnOnce() -> R) -> Result<R, RuntimeError> {
+        let _domain = self.function.exported.vm_function.enter_protection_domain();
+        wasmer_vm::catch_traps(self.function.store(), f).map_err(RuntimeError::from_trap)
+    }
+}
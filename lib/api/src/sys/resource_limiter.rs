@@ -0,0 +1,390 @@
+//! Host control over the growth of memories and tables.
+
+use crate::sys::{Bytes, GlobalType, MemoryType, Pages, TableType};
+use loupe::MemoryUsage;
+use std::fmt;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use wasmer_engine::{LinkError, Tunables};
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::{
+    LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, ModuleInfo, TableIndex,
+};
+use wasmer_vm::{
+    Global, Memory, MemoryError, MemoryStyle, Table, TableElement, TableStyle, Trap,
+    VMMemoryDefinition, VMTableDefinition,
+};
+
+/// Decides whether the memories and tables of a [`Store`] may grow.
+///
+/// It's consulted before every growth, whether requested by the host
+/// with [`Memory::grow`] and [`Table::grow`] or by WebAssembly with
+/// `memory.grow` and `table.grow`. A refused growth fails the same way
+/// as one going past the maximum of the memory or table: the host gets
+/// an error, and WebAssembly gets `-1`.
+///
+/// Both methods allow every growth by default.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use wasmer::*;
+/// /// Caps every table at 1000 elements.
+/// struct TableCap;
+///
+/// impl ResourceLimiter for TableCap {
+///     fn table_growing(&self, _current: u32, desired: u32, _maximum: Option<u32>) -> bool {
+///         desired <= 1000
+///     }
+/// }
+///
+/// let store = Store::default();
+/// store.set_resource_limiter(Some(Arc::new(TableCap)));
+///
+/// let table = Table::new(&store, TableType::new(ValType::FuncRef, 0, None), Val::FuncRef(None)).unwrap();
+/// assert!(table.grow(1001, Val::FuncRef(None)).is_err());
+/// assert_eq!(table.grow(1000, Val::FuncRef(None)).unwrap(), 0);
+/// ```
+///
+/// [`Store`]: crate::Store
+/// [`Memory::grow`]: crate::Memory::grow
+/// [`Table::grow`]: crate::Table::grow
+pub trait ResourceLimiter: Send + Sync {
+    /// Whether a memory of `current` pages may grow to `desired` pages.
+    /// `maximum` is the maximum of the memory, if it has one.
+    fn memory_growing(&self, current: Pages, desired: Pages, maximum: Option<Pages>) -> bool {
+        let _ = (current, desired, maximum);
+        true
+    }
+
+    /// Whether a table of `current` elements may grow to `desired`
+    /// elements. `maximum` is the maximum of the table, if it has one.
+    fn table_growing(&self, current: u32, desired: u32, maximum: Option<u32>) -> bool {
+        let _ = (current, desired, maximum);
+        true
+    }
+}
+
+/// The limits of a store, shared with its memories and tables.
+pub(crate) type SharedResourceLimiter = Arc<StoreLimits>;
+
+/// The limits on the growth of the memories and tables of a store.
+pub(crate) struct StoreLimits {
+    limiter: RwLock<Option<Arc<dyn ResourceLimiter>>>,
+    /// The hard limit on the size of each memory, in bytes, or
+    /// `usize::MAX` if there's none.
+    memory_limit: AtomicUsize,
+}
+
+impl StoreLimits {
+    pub(crate) fn new() -> Self {
+        Self {
+            limiter: RwLock::new(None),
+            memory_limit: AtomicUsize::new(usize::MAX),
+        }
+    }
+
+    pub(crate) fn set_limiter(&self, limiter: Option<Arc<dyn ResourceLimiter>>) {
+        *self.limiter.write().unwrap() = limiter;
+    }
+
+    pub(crate) fn set_memory_limit(&self, limit: Option<Bytes>) {
+        let limit = limit.map_or(usize::MAX, |limit| limit.0);
+        self.memory_limit.store(limit, Ordering::Relaxed);
+    }
+
+    /// Whether a memory may grow, below the hard limit and as allowed by
+    /// the limiter.
+    fn memory_growing(&self, current: Pages, desired: Pages, maximum: Option<Pages>) -> bool {
+        Bytes::from(desired).0 <= self.memory_limit.load(Ordering::Relaxed)
+            && self
+                .limiter
+                .read()
+                .unwrap()
+                .as_ref()
+                .map_or(true, |limiter| {
+                    limiter.memory_growing(current, desired, maximum)
+                })
+    }
+
+    /// Whether a table may grow, as allowed by the limiter.
+    fn table_growing(&self, current: u32, desired: u32, maximum: Option<u32>) -> bool {
+        self.limiter
+            .read()
+            .unwrap()
+            .as_ref()
+            .map_or(true, |limiter| {
+                limiter.table_growing(current, desired, maximum)
+            })
+    }
+}
+
+/// Tunables making memories and tables which consult the limits of
+/// their store before growing.
+#[derive(MemoryUsage)]
+pub(crate) struct LimitingTunables {
+    tunables: Arc<dyn Tunables + Send + Sync>,
+    #[loupe(skip)]
+    limits: SharedResourceLimiter,
+}
+
+impl LimitingTunables {
+    pub(crate) fn new(
+        tunables: Arc<dyn Tunables + Send + Sync>,
+        limits: SharedResourceLimiter,
+    ) -> Self {
+        Self { tunables, limits }
+    }
+
+    fn limit_memory(&self, memory: Arc<dyn Memory>) -> Arc<dyn Memory> {
+        Arc::new(LimitedMemory {
+            memory,
+            limits: self.limits.clone(),
+        })
+    }
+
+    fn limit_table(&self, table: Arc<dyn Table>) -> Arc<dyn Table> {
+        Arc::new(LimitedTable {
+            table,
+            limits: self.limits.clone(),
+        })
+    }
+}
+
+impl Tunables for LimitingTunables {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        self.tunables.memory_style(memory)
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.tunables.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        Ok(self.limit_memory(self.tunables.create_host_memory(ty, style)?))
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        Ok(self.limit_memory(
+            self.tunables
+                .create_vm_memory(ty, style, vm_definition_location)?,
+        ))
+    }
+
+    fn create_host_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+    ) -> Result<Arc<dyn Table>, String> {
+        Ok(self.limit_table(self.tunables.create_host_table(ty, style)?))
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn Table>, String> {
+        Ok(self.limit_table(
+            self.tunables
+                .create_vm_table(ty, style, vm_definition_location)?,
+        ))
+    }
+
+    fn create_global(&self, ty: GlobalType) -> Result<Arc<Global>, String> {
+        self.tunables.create_global(ty)
+    }
+
+    unsafe fn create_memories(
+        &self,
+        module: &ModuleInfo,
+        memory_styles: &PrimaryMap<MemoryIndex, MemoryStyle>,
+        memory_definition_locations: &[NonNull<VMMemoryDefinition>],
+    ) -> Result<PrimaryMap<LocalMemoryIndex, Arc<dyn Memory>>, LinkError> {
+        let memories =
+            self.tunables
+                .create_memories(module, memory_styles, memory_definition_locations)?;
+        Ok(memories
+            .into_iter()
+            .map(|(_, memory)| self.limit_memory(memory))
+            .collect())
+    }
+
+    unsafe fn create_tables(
+        &self,
+        module: &ModuleInfo,
+        table_styles: &PrimaryMap<TableIndex, TableStyle>,
+        table_definition_locations: &[NonNull<VMTableDefinition>],
+    ) -> Result<PrimaryMap<LocalTableIndex, Arc<dyn Table>>, LinkError> {
+        let tables =
+            self.tunables
+                .create_tables(module, table_styles, table_definition_locations)?;
+        Ok(tables
+            .into_iter()
+            .map(|(_, table)| self.limit_table(table))
+            .collect())
+    }
+
+    fn create_globals(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<PrimaryMap<LocalGlobalIndex, Arc<Global>>, LinkError> {
+        self.tunables.create_globals(module)
+    }
+}
+
+/// A memory consulting a [`ResourceLimiter`] before growing.
+#[derive(MemoryUsage)]
+struct LimitedMemory {
+    memory: Arc<dyn Memory>,
+    #[loupe(skip)]
+    limits: SharedResourceLimiter,
+}
+
+impl fmt::Debug for LimitedMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.memory, f)
+    }
+}
+
+impl Memory for LimitedMemory {
+    fn ty(&self) -> MemoryType {
+        self.memory.ty()
+    }
+
+    fn style(&self) -> &MemoryStyle {
+        self.memory.style()
+    }
+
+    fn size(&self) -> Pages {
+        self.memory.size()
+    }
+
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        // A sealed memory doesn't ask the limiter.
+        if delta.0 > 0 && !self.memory.is_sealed() {
+            let current = self.memory.size();
+            let refused = current.checked_add(delta).map_or(false, |desired| {
+                !self
+                    .limits
+                    .memory_growing(current, desired, self.memory.ty().maximum)
+            });
+            if refused {
+                return Err(MemoryError::CouldNotGrow {
+                    current,
+                    attempted_delta: delta,
+                });
+            }
+        }
+        self.memory.grow(delta)
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.memory.vmmemory()
+    }
+
+    unsafe fn initialize_with_data(&self, start: usize, data: &[u8]) {
+        self.memory.initialize_with_data(start, data)
+    }
+
+    fn protection_key(&self) -> Option<u32> {
+        self.memory.protection_key()
+    }
+
+    fn start_tracking(&self) -> Result<(), MemoryError> {
+        self.memory.start_tracking()
+    }
+
+    fn take_dirty_pages(&self) -> Result<Vec<Pages>, MemoryError> {
+        self.memory.take_dirty_pages()
+    }
+
+    fn seal(&self) -> Result<(), MemoryError> {
+        self.memory.seal()
+    }
+
+    fn is_sealed(&self) -> bool {
+        self.memory.is_sealed()
+    }
+
+    fn commit(&self, start: usize, len: usize) -> Result<(), MemoryError> {
+        self.memory.commit(start, len)
+    }
+
+    fn resident_size(&self) -> Result<Bytes, MemoryError> {
+        self.memory.resident_size()
+    }
+}
+
+/// A table consulting a [`ResourceLimiter`] before growing.
+#[derive(MemoryUsage)]
+struct LimitedTable {
+    table: Arc<dyn Table>,
+    #[loupe(skip)]
+    limits: SharedResourceLimiter,
+}
+
+impl fmt::Debug for LimitedTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.table, f)
+    }
+}
+
+impl Table for LimitedTable {
+    fn style(&self) -> &TableStyle {
+        self.table.style()
+    }
+
+    fn ty(&self) -> &TableType {
+        self.table.ty()
+    }
+
+    fn size(&self) -> u32 {
+        self.table.size()
+    }
+
+    fn grow(&self, delta: u32, init_value: TableElement) -> Option<u32> {
+        // A sealed table doesn't ask the limiter.
+        if delta > 0 && !self.table.is_sealed() {
+            let current = self.table.size();
+            let refused = current.checked_add(delta).map_or(false, |desired| {
+                !self
+                    .limits
+                    .table_growing(current, desired, self.table.ty().maximum)
+            });
+            if refused {
+                return None;
+            }
+        }
+        self.table.grow(delta, init_value)
+    }
+
+    fn get(&self, index: u32) -> Option<TableElement> {
+        self.table.get(index)
+    }
+
+    fn set(&self, index: u32, reference: TableElement) -> Result<(), Trap> {
+        self.table.set(index, reference)
+    }
+
+    fn vmtable(&self) -> NonNull<VMTableDefinition> {
+        self.table.vmtable()
+    }
+
+    fn seal(&self) -> bool {
+        self.table.seal()
+    }
+
+    fn is_sealed(&self) -> bool {
+        self.table.is_sealed()
+    }
+}
@@ -1,11 +1,21 @@
+use crate::sys::resource_limiter::{
+    LimitingTunables, ResourceLimiter, SharedResourceLimiter, StoreLimits,
+};
 use crate::sys::tunables::BaseTunables;
+use crate::sys::Bytes;
 use loupe::MemoryUsage;
+#[cfg(feature = "experimental-reference-types-extern-ref")]
+use std::any::Any;
 use std::fmt;
 use std::sync::{Arc, RwLock};
 #[cfg(all(feature = "compiler", feature = "engine"))]
 use wasmer_compiler::CompilerConfig;
+#[cfg(all(feature = "compiler", feature = "default-engine"))]
+use wasmer_compiler::{Features, Target};
 use wasmer_engine::{Engine, Tunables};
-use wasmer_vm::{init_traps, TrapHandler, TrapHandlerFn};
+#[cfg(feature = "experimental-reference-types-extern-ref")]
+use wasmer_types::ExternRef;
+use wasmer_vm::{init_traps, SignatureRegistry, TrapHandler, TrapHandlerFn};
 
 /// The store represents all global state that can be manipulated by
 /// WebAssembly programs. It consists of the runtime representation
@@ -23,8 +33,19 @@ pub struct Store {
     tunables: Arc<dyn Tunables + Send + Sync>,
     #[loupe(skip)]
     trap_handler: Arc<RwLock<Option<Box<TrapHandlerFn>>>>,
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    #[loupe(skip)]
+    extern_ref_finalizer: Arc<RwLock<Option<Box<ExternRefFinalizer>>>>,
+    #[loupe(skip)]
+    resource_limiter: SharedResourceLimiter,
+    deterministic: bool,
 }
 
+/// A hook receiving the values of the [`ExternRef`]s made with
+/// [`Store::new_extern_ref`] once their last reference is dropped.
+#[cfg(feature = "experimental-reference-types-extern-ref")]
+pub type ExternRefFinalizer = dyn Fn(Box<dyn Any + Send + Sync>) + Send + Sync;
+
 impl Store {
     /// Creates a new `Store` with a specific [`Engine`].
     pub fn new<E>(engine: &E) -> Self
@@ -40,6 +61,84 @@ impl Store {
         *m = handler;
     }
 
+    /// Sets the [`ResourceLimiter`] consulted before the memories and
+    /// tables of this store grow, including the ones created before it
+    /// was set.
+    pub fn set_resource_limiter(&self, limiter: Option<Arc<dyn ResourceLimiter>>) {
+        self.resource_limiter.set_limiter(limiter);
+    }
+
+    /// Sets a hard limit on the size of each memory of this store,
+    /// including the ones created before it was set.
+    ///
+    /// Like a [`ResourceLimiter`], it applies to every growth, whether
+    /// requested by the host or by `memory.grow`, on top of the maximum
+    /// declared by the memory: a memory can't grow past it, and
+    /// `memory.grow` returns `-1` instead. It allows clamping the memories
+    /// of modules without recompiling them with lower maximums. Memories
+    /// already larger than the limit keep their size.
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// let store = Store::default();
+    /// store.set_memory_limit(Some(Pages(2).into()));
+    ///
+    /// let memory = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// assert!(memory.grow(2).is_err());
+    /// assert_eq!(memory.grow(1).unwrap(), Pages(1));
+    /// ```
+    pub fn set_memory_limit(&self, limit: Option<Bytes>) {
+        self.resource_limiter.set_memory_limit(limit);
+    }
+
+    /// Sets the hook receiving the values of the extern references made
+    /// with [`Store::new_extern_ref`] once the host and the VM dropped
+    /// their last reference to them, so that embedders bridging to a
+    /// garbage collected language can release the objects they stand
+    /// for.
+    ///
+    /// The hook runs on the thread dropping the last reference, which
+    /// may be running WebAssembly code: it must not panic. Values
+    /// released while no hook is set are dropped.
+    ///
+    /// ```
+    /// # use std::sync::{Arc, Mutex};
+    /// # use wasmer::*;
+    /// let store = Store::default();
+    /// let released = Arc::new(Mutex::new(vec![]));
+    /// let released2 = released.clone();
+    /// store.set_extern_ref_finalizer(Some(Box::new(move |value| {
+    ///     released2.lock().unwrap().push(*value.downcast::<u32>().unwrap());
+    /// })));
+    ///
+    /// let handle = store.new_extern_ref(42u32);
+    /// let other = handle.clone();
+    /// drop(handle);
+    /// assert!(released.lock().unwrap().is_empty());
+    /// drop(other);
+    /// assert_eq!(*released.lock().unwrap(), vec![42]);
+    /// ```
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    pub fn set_extern_ref_finalizer(&self, finalizer: Option<Box<ExternRefFinalizer>>) {
+        *self.extern_ref_finalizer.write().unwrap() = finalizer;
+    }
+
+    /// Makes a new extern reference whose value is given to the hook set
+    /// with [`Store::set_extern_ref_finalizer`] once its last reference
+    /// is dropped.
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    pub fn new_extern_ref<T>(&self, value: T) -> ExternRef
+    where
+        T: Any + Send + Sync + 'static,
+    {
+        let finalizer = self.extern_ref_finalizer.clone();
+        ExternRef::with_finalizer(value, move |value| {
+            if let Some(finalizer) = finalizer.read().unwrap().as_ref() {
+                finalizer(Box::new(value));
+            }
+        })
+    }
+
     /// Creates a new `Store` with a specific [`Engine`] and [`Tunables`].
     pub fn new_with_tunables<E>(engine: &E, tunables: impl Tunables + Send + Sync + 'static) -> Self
     where
@@ -49,10 +148,18 @@ impl Store {
         // This is required for handling traps.
         init_traps();
 
+        let resource_limiter: SharedResourceLimiter = Arc::new(StoreLimits::new());
         Self {
             engine: engine.cloned(),
-            tunables: Arc::new(tunables),
+            tunables: Arc::new(LimitingTunables::new(
+                Arc::new(tunables),
+                resource_limiter.clone(),
+            )),
             trap_handler: Arc::new(RwLock::new(None)),
+            #[cfg(feature = "experimental-reference-types-extern-ref")]
+            extern_ref_finalizer: Arc::new(RwLock::new(None)),
+            resource_limiter,
+            deterministic: false,
         }
     }
 
@@ -66,6 +173,21 @@ impl Store {
         &self.engine
     }
 
+    /// Returns whether the store was built with
+    /// `StoreBuilder::deterministic`, so that running the same module
+    /// with the same inputs always gives the same results.
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Returns the registry of the function signatures used by the modules
+    /// of this store.
+    ///
+    /// A signature is removed once the modules using it are dropped.
+    pub fn signature_registry(&self) -> Arc<SignatureRegistry> {
+        self.engine.signature_registry()
+    }
+
     /// Checks whether two stores are identical. A store is considered
     /// equal to another store if both have the same engine. The
     /// tunables are excluded from the logic.
@@ -95,50 +217,139 @@ unsafe impl TrapHandler for Store {
 unsafe impl Send for Store {}
 unsafe impl Sync for Store {}
 
-// We only implement default if we have assigned a default compiler and engine
+// We store them on a function that returns to make
+// sure this function doesn't emit a compile error even if
+// more than one compiler is enabled.
 #[cfg(all(feature = "default-compiler", feature = "default-engine"))]
-impl Default for Store {
-    fn default() -> Self {
-        // We store them on a function that returns to make
-        // sure this function doesn't emit a compile error even if
-        // more than one compiler is enabled.
-        #[allow(unreachable_code)]
-        fn get_config() -> impl CompilerConfig + 'static {
-            cfg_if::cfg_if! {
-                if #[cfg(feature = "default-cranelift")] {
-                    wasmer_compiler_cranelift::Cranelift::default()
-                } else if #[cfg(feature = "default-llvm")] {
-                    wasmer_compiler_llvm::LLVM::default()
-                } else if #[cfg(feature = "default-singlepass")] {
-                    wasmer_compiler_singlepass::Singlepass::default()
-                } else {
-                    compile_error!("No default compiler chosen")
-                }
-            }
+#[allow(unreachable_code)]
+fn get_config() -> impl CompilerConfig + 'static {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "default-cranelift")] {
+            wasmer_compiler_cranelift::Cranelift::default()
+        } else if #[cfg(feature = "default-llvm")] {
+            wasmer_compiler_llvm::LLVM::default()
+        } else if #[cfg(feature = "default-singlepass")] {
+            wasmer_compiler_singlepass::Singlepass::default()
+        } else {
+            compile_error!("No default compiler chosen")
         }
+    }
+}
 
-        #[allow(unreachable_code, unused_mut)]
-        fn get_engine(mut config: impl CompilerConfig + 'static) -> impl Engine + Send + Sync {
-            cfg_if::cfg_if! {
-                if #[cfg(feature = "default-universal")] {
-                    wasmer_engine_universal::Universal::new(config)
-                        .engine()
-                } else if #[cfg(feature = "default-dylib")] {
-                    wasmer_engine_dylib::Dylib::new(config)
-                        .engine()
-                } else {
-                    compile_error!("No default engine chosen")
-                }
+#[cfg(all(feature = "compiler", feature = "default-engine"))]
+#[allow(unreachable_code)]
+fn get_engine(
+    config: Box<dyn CompilerConfig>,
+    features: Option<Features>,
+) -> impl Engine + Send + Sync {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "default-universal")] {
+            let mut builder = wasmer_engine_universal::Universal::new(config);
+            if let Some(features) = features {
+                builder = builder.features(features);
+            }
+            builder.engine()
+        } else if #[cfg(feature = "default-dylib")] {
+            let mut builder = wasmer_engine_dylib::Dylib::new(config);
+            if let Some(features) = features {
+                builder = builder.features(features);
             }
+            builder.engine()
+        } else {
+            compile_error!("No default engine chosen")
         }
+    }
+}
 
-        let config = get_config();
-        let engine = get_engine(config);
+// We only implement default if we have assigned a default compiler and engine
+#[cfg(all(feature = "default-compiler", feature = "default-engine"))]
+impl Default for Store {
+    fn default() -> Self {
+        let engine = get_engine(Box::new(get_config()), None);
         let tunables = BaseTunables::for_target(engine.target());
         Self::new_with_tunables(&engine, tunables)
     }
 }
 
+/// A builder for a [`Store`] using the default engine.
+///
+/// ```ignore
+/// use wasmer::StoreBuilder;
+///
+/// let store = StoreBuilder::default().deterministic().build();
+/// assert!(store.is_deterministic());
+/// ```
+#[cfg(all(feature = "compiler", feature = "default-engine"))]
+pub struct StoreBuilder {
+    compiler_config: Box<dyn CompilerConfig>,
+    features: Option<Features>,
+    deterministic: bool,
+}
+
+#[cfg(all(feature = "compiler", feature = "default-engine"))]
+impl StoreBuilder {
+    /// Creates a builder compiling with `compiler_config`.
+    pub fn new<T>(compiler_config: T) -> Self
+    where
+        T: Into<Box<dyn CompilerConfig>>,
+    {
+        Self {
+            compiler_config: compiler_config.into(),
+            features: None,
+            deterministic: false,
+        }
+    }
+
+    /// Sets the features of the engine.
+    pub fn features(mut self, features: Features) -> Self {
+        self.features = Some(features);
+        self
+    }
+
+    /// Makes the execution of wasm code deterministic.
+    ///
+    /// This asserts every knob affecting it:
+    ///  * NaNs are canonicalized by the compiler,
+    ///  * relaxed SIMD and threads are disabled,
+    ///  * the memories are allocated with [`BaseTunables::deterministic`],
+    ///  * WASI programs run with virtual clocks and seeded randomness.
+    pub fn deterministic(mut self) -> Self {
+        self.deterministic = true;
+        self
+    }
+
+    /// Builds the `Store`.
+    pub fn build(self) -> Store {
+        let mut config = self.compiler_config;
+        let features = if self.deterministic {
+            config.canonicalize_nans(true);
+            let mut features = self
+                .features
+                .unwrap_or_else(|| config.default_features_for_target(&Target::default()));
+            features.relaxed_simd = false;
+            features.threads(false);
+            Some(features)
+        } else {
+            self.features
+        };
+        let engine = get_engine(config, features);
+        let mut store = if self.deterministic {
+            Store::new_with_tunables(&engine, BaseTunables::deterministic())
+        } else {
+            Store::new(&engine)
+        };
+        store.deterministic = self.deterministic;
+        store
+    }
+}
+
+#[cfg(all(feature = "default-compiler", feature = "default-engine"))]
+impl Default for StoreBuilder {
+    fn default() -> Self {
+        Self::new(get_config())
+    }
+}
+
 impl fmt::Debug for Store {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Store").finish()
@@ -0,0 +1,260 @@
+//! Calls into WebAssembly which neither lock nor allocate.
+//!
+//! A [`NativeFunc`] call takes a lock to borrow a stack, and functions
+//! read from tables have no call trampoline at all. A [`TrampolinePool`]
+//! makes the call trampolines of a set of signatures ahead of time, and
+//! hands out [`PooledNativeFunc`]s which own their stack, so that they
+//! can be called from threads which must not block, such as audio
+//! threads:
+//!
+//! ```
+//! # use wasmer::*;
+//! # fn main() -> anyhow::Result<()> {
+//! # let store = Store::default();
+//! let module = Module::new(&store, r#"(module
+//!     (func (export "gain") (param f32) (result f32)
+//!         (f32.mul (local.get 0) (f32.const 0.5))))"#)?;
+//! let instance = Instance::new(&module, &imports! {})?;
+//! let pool = TrampolinePool::new(&store, &[FunctionType::new([Type::F32], [Type::F32])])?;
+//! let mut gain = pool.prepare::<f32, f32>(instance.exports.get_function("gain")?)?;
+//!
+//! // On the real-time thread.
+//! TrampolinePool::init_thread()?;
+//! assert_eq!(gain.call(2.0)?, 1.0);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A call made with a [`PooledNativeFunc`] is real-time safe as long as
+//! the function it calls is: the code around it doesn't lock, doesn't
+//! allocate and doesn't make system calls, as long as
+//! [`TrampolinePool::init_thread`] was called on the thread beforehand.
+//! Only a trap allocates, to build its [`RuntimeError`].
+
+use crate::sys::{
+    CompileError, FromToNativeWasmType, Function, FunctionType, Module, NativeFunc, RuntimeError,
+    Store, Type, WasmTypeList,
+};
+use std::collections::HashMap;
+use wasmer_types::NativeWasmType;
+use wasmer_vm::{CallStack, VMFunctionKind, VMSharedSignatureIndex, VMTrampoline};
+
+/// Call trampolines made ahead of time for a set of signatures.
+///
+/// See the [module documentation](self) for more information.
+pub struct TrampolinePool {
+    store: Store,
+    trampolines: HashMap<VMSharedSignatureIndex, VMTrampoline>,
+    // Owns the code of the trampolines.
+    _module: Module,
+}
+
+impl TrampolinePool {
+    /// Makes the call trampolines of `signatures`, by compiling a
+    /// module declaring them.
+    pub fn new(store: &Store, signatures: &[FunctionType]) -> Result<Self, CompileError> {
+        let module = Module::from_binary(store, &signatures_module(signatures))?;
+        let artifact = module.artifact();
+        let trampolines = artifact
+            .signatures()
+            .values()
+            .copied()
+            .zip(
+                artifact
+                    .finished_function_call_trampolines()
+                    .values()
+                    .copied(),
+            )
+            .collect();
+        Ok(Self {
+            store: store.clone(),
+            trampolines,
+            _module: module,
+        })
+    }
+
+    /// Returns whether the pool has a trampoline for `signature`.
+    pub fn contains(&self, signature: &FunctionType) -> bool {
+        self.trampoline(signature).is_some()
+    }
+
+    /// Prepares `function` to be called without locking nor allocating.
+    ///
+    /// It fails if the signature of `function` doesn't match `Args` and
+    /// `Rets`, if it's missing from the pool, or if its stack can't be
+    /// allocated. Host functions made with [`Function::new`] are rejected
+    /// too, since their calls go through `Val`s, which allocates; use
+    /// [`Function::new_native`] instead.
+    pub fn prepare<Args, Rets>(
+        &self,
+        function: &Function,
+    ) -> Result<PooledNativeFunc<Args, Rets>, RuntimeError>
+    where
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+    {
+        if !Store::same(function.store(), &self.store) {
+            return Err(RuntimeError::new("cross-`Store` values are not supported"));
+        }
+        let func = function.native::<Args, Rets>()?.clone();
+        if let VMFunctionKind::Dynamic = func.arg_kind() {
+            return Err(RuntimeError::new(
+                "dynamic host functions can't be called from a trampoline pool",
+            ));
+        }
+        let trampoline = match unsafe { func.get_vm_function() }.call_trampoline {
+            Some(trampoline) => trampoline,
+            None => self.trampoline(function.ty()).ok_or_else(|| {
+                RuntimeError::new(format!(
+                    "the trampoline pool has no trampoline for {}",
+                    function.ty()
+                ))
+            })?,
+        };
+        let stack = CallStack::new().map_err(|error| {
+            RuntimeError::new(format!("failed to allocate a call stack: {}", error))
+        })?;
+        Ok(PooledNativeFunc {
+            func,
+            trampoline,
+            stack,
+        })
+    }
+
+    /// Prepares the current thread to call into WebAssembly. It's done
+    /// by the first call on each thread otherwise, which allocates.
+    pub fn init_thread() -> Result<(), RuntimeError> {
+        wasmer_vm::lazy_per_thread_init().map_err(RuntimeError::from_trap)
+    }
+
+    fn trampoline(&self, signature: &FunctionType) -> Option<VMTrampoline> {
+        let index = self.store.engine().register_signature(signature);
+        self.trampolines.get(&index).copied()
+    }
+}
+
+/// Encodes a module made of a type section declaring `signatures`.
+fn signatures_module(signatures: &[FunctionType]) -> Vec<u8> {
+    fn push_u32(bytes: &mut Vec<u8>, mut value: u32) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(byte);
+                return;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+
+    fn push_types(bytes: &mut Vec<u8>, types: &[Type]) {
+        push_u32(bytes, types.len() as u32);
+        bytes.extend(types.iter().map(|ty| match ty {
+            Type::I32 => 0x7f,
+            Type::I64 => 0x7e,
+            Type::F32 => 0x7d,
+            Type::F64 => 0x7c,
+            Type::V128 => 0x7b,
+            Type::FuncRef => 0x70,
+            Type::ExternRef => 0x6f,
+        }));
+    }
+
+    let mut section = Vec::new();
+    push_u32(&mut section, signatures.len() as u32);
+    for signature in signatures {
+        section.push(0x60);
+        push_types(&mut section, signature.params());
+        push_types(&mut section, signature.results());
+    }
+
+    let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+    bytes.push(1);
+    push_u32(&mut bytes, section.len() as u32);
+    bytes.extend(section);
+    bytes
+}
+
+/// A typed function which can be called without locking nor
+/// allocating, obtained with [`TrampolinePool::prepare`].
+///
+/// It owns the stack its calls run on, which is why calling it takes a
+/// mutable reference.
+pub struct PooledNativeFunc<Args = (), Rets = ()> {
+    func: NativeFunc<Args, Rets>,
+    trampoline: VMTrampoline,
+    stack: CallStack,
+}
+
+macro_rules! impl_pooled_call {
+    (  $( $x:ident ),* ) => {
+        #[allow(unused_parens, non_snake_case)]
+        impl<$( $x , )* Rets> PooledNativeFunc<( $( $x ),* ), Rets>
+        where
+            $( $x: FromToNativeWasmType, )*
+            Rets: WasmTypeList,
+        {
+            /// Call the typed func and return results.
+            pub fn call(&mut self, $( $x: $x, )* ) -> Result<Rets, RuntimeError> {
+                let mut params_list = [ $( $x.to_native().to_binary() ),* ];
+                let mut rets_list_array = Rets::empty_array();
+                let rets_list = rets_list_array.as_mut();
+                let using_rets_array;
+                let args_rets: &mut [i128] = if params_list.len() > rets_list.len() {
+                    using_rets_array = false;
+                    params_list.as_mut()
+                } else {
+                    using_rets_array = true;
+                    for (i, &arg) in params_list.iter().enumerate() {
+                        rets_list[i] = arg;
+                    }
+                    rets_list.as_mut()
+                };
+                let vm_function = unsafe { self.func.get_vm_function() };
+                let _domain = vm_function.enter_protection_domain();
+                unsafe {
+                    wasmer_vm::wasmer_call_trampoline_on_stack(
+                        self.func.store(),
+                        &mut self.stack,
+                        vm_function.vmctx,
+                        self.trampoline,
+                        vm_function.address,
+                        args_rets.as_mut_ptr() as *mut u8,
+                    )
+                }
+                .map_err(RuntimeError::from_trap)?;
+                let num_rets = rets_list.len();
+                if !using_rets_array && num_rets > 0 {
+                    rets_list_array.as_mut()[..num_rets].copy_from_slice(&params_list[..num_rets]);
+                }
+                Ok(Rets::from_array(rets_list_array))
+            }
+        }
+    };
+}
+
+impl_pooled_call!();
+impl_pooled_call!(A1);
+impl_pooled_call!(A1, A2);
+impl_pooled_call!(A1, A2, A3);
+impl_pooled_call!(A1, A2, A3, A4);
+impl_pooled_call!(A1, A2, A3, A4, A5);
+impl_pooled_call!(A1, A2, A3, A4, A5, A6);
+impl_pooled_call!(A1, A2, A3, A4, A5, A6, A7);
+impl_pooled_call!(A1, A2, A3, A4, A5, A6, A7, A8);
+impl_pooled_call!(A1, A2, A3, A4, A5, A6, A7, A8, A9);
+impl_pooled_call!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10);
+impl_pooled_call!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11);
+impl_pooled_call!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12);
+impl_pooled_call!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13);
+impl_pooled_call!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14);
+impl_pooled_call!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15);
+impl_pooled_call!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16);
+impl_pooled_call!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17);
+impl_pooled_call!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18);
+impl_pooled_call!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19
+);
+impl_pooled_call!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20
+);
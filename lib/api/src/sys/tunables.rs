@@ -5,10 +5,15 @@ use std::sync::Arc;
 use target_lexicon::PointerWidth;
 use wasmer_compiler::Target;
 use wasmer_engine::Tunables;
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+use wasmer_vm::LazyMemory;
 use wasmer_vm::MemoryError;
 use wasmer_vm::{
-    LinearMemory, LinearTable, Memory, MemoryStyle, Table, TableStyle, VMMemoryDefinition,
-    VMTableDefinition,
+    LinearMemory, LinearTable, Memory, MemoryStyle, ProtectedMemory, Table, TableStyle,
+    VMMemoryDefinition, VMTableDefinition,
 };
 
 /// Tunable parameters for WebAssembly compilation.
@@ -29,6 +34,22 @@ pub struct BaseTunables {
 
     /// The size in bytes of the offset guard for dynamic heaps.
     pub dynamic_memory_offset_guard_size: u64,
+
+    /// Whether static heaps populate their pages on first access, which
+    /// defers their zeroing and data segment copies, instead of at
+    /// instantiation.
+    ///
+    /// This is only supported on Linux, with `userfaultfd` available to
+    /// the process. It's ignored otherwise.
+    pub lazy_memory_initialization: bool,
+
+    /// Whether static heaps are tagged with a memory protection key, so
+    /// that the code of an instance cannot access the memories of the
+    /// other instances of the process.
+    ///
+    /// This is only supported on x86_64 Linux with PKU. It's ignored
+    /// otherwise.
+    pub memory_protection_keys: bool,
 }
 
 impl BaseTunables {
@@ -63,6 +84,32 @@ impl BaseTunables {
             static_memory_bound,
             static_memory_offset_guard_size,
             dynamic_memory_offset_guard_size,
+            lazy_memory_initialization: false,
+            memory_protection_keys: false,
+        }
+    }
+
+    /// Get the `BaseTunables` of deterministic stores.
+    ///
+    /// The memories are laid out the same way on every 64-bit host,
+    /// instead of depending on the target, and are always initialized
+    /// eagerly.
+    pub fn deterministic() -> Self {
+        Self {
+            static_memory_bound: 0x1_0000.into(),
+            static_memory_offset_guard_size: 0x8000_0000,
+            dynamic_memory_offset_guard_size: 0x1_0000,
+            lazy_memory_initialization: false,
+            memory_protection_keys: false,
+        }
+    }
+
+    /// Tags `memory` with a protection key if `memory_protection_keys` is set.
+    fn protect(&self, memory: impl Memory + 'static) -> Result<Arc<dyn Memory>, MemoryError> {
+        if self.memory_protection_keys && ProtectedMemory::is_supported(memory.style()) {
+            Ok(Arc::new(ProtectedMemory::new(Box::new(memory))?))
+        } else {
+            Ok(Arc::new(memory))
         }
     }
 }
@@ -99,7 +146,14 @@ impl Tunables for BaseTunables {
         ty: &MemoryType,
         style: &MemoryStyle,
     ) -> Result<Arc<dyn Memory>, MemoryError> {
-        Ok(Arc::new(LinearMemory::new(ty, style)?))
+        #[cfg(all(
+            target_os = "linux",
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        ))]
+        if self.lazy_memory_initialization && LazyMemory::is_supported(style) {
+            return self.protect(LazyMemory::new(ty, style)?);
+        }
+        self.protect(LinearMemory::new(ty, style)?)
     }
 
     /// Create a memory owned by the VM given a [`MemoryType`] and a [`MemoryStyle`].
@@ -113,11 +167,22 @@ impl Tunables for BaseTunables {
         style: &MemoryStyle,
         vm_definition_location: NonNull<VMMemoryDefinition>,
     ) -> Result<Arc<dyn Memory>, MemoryError> {
-        Ok(Arc::new(LinearMemory::from_definition(
+        #[cfg(all(
+            target_os = "linux",
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        ))]
+        if self.lazy_memory_initialization && LazyMemory::is_supported(style) {
+            return self.protect(LazyMemory::from_definition(
+                ty,
+                style,
+                vm_definition_location,
+            )?);
+        }
+        self.protect(LinearMemory::from_definition(
             ty,
             style,
             vm_definition_location,
-        )?))
+        )?)
     }
 
     /// Create a table owned by the host given a [`TableType`] and a [`TableStyle`].
@@ -158,6 +223,8 @@ mod tests {
             static_memory_bound: Pages(2048),
             static_memory_offset_guard_size: 128,
             dynamic_memory_offset_guard_size: 256,
+            lazy_memory_initialization: false,
+            memory_protection_keys: false,
         };
 
         // No maximum
@@ -74,6 +74,7 @@ impl ValFuncRef for Val {
             // TODO:
             // figure out if we ever need a value here: need testing with complicated import patterns
             metadata: None,
+            info: None,
             vm_function: wasmer_vm::VMFunction {
                 address: item.func_ptr,
                 signature,
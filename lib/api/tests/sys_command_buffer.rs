@@ -0,0 +1,122 @@
+#[cfg(all(feature = "sys", feature = "experimental-command-buffer"))]
+mod sys {
+    use anyhow::Result;
+    use std::sync::{Arc, Mutex};
+    use wasmer::*;
+
+    #[test]
+    fn guest_commands_are_drained_in_one_call() -> Result<()> {
+        let store = Store::default();
+        // The buffer is at 256, with a ring of 64 bytes. The guest queues
+        // `log("hi")` and `clear()`, then flushes them.
+        let module = Module::new(
+            &store,
+            r#"(module
+            (import "host" "flush" (func $flush (param i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 256) "\40\00\00\00\00\00\00\00\14\00\00\00")
+            (data (i32.const 268) "\01\00\00\00\02\00\00\00hi\00\00")
+            (data (i32.const 280) "\02\00\00\00\00\00\00\00")
+            (func (export "run") (result i32)
+                (call $flush (i32.const 256))))"#,
+        )?;
+
+        #[derive(Clone, Default, WasmerEnv)]
+        struct Env {
+            #[wasmer(export)]
+            memory: LazyInit<Memory>,
+            commands: Arc<Mutex<Vec<(u32, Vec<u8>)>>>,
+        }
+        fn flush(env: &Env, offset: u32) -> u32 {
+            let buffer = CommandBuffer::new(env.memory_ref().unwrap(), offset);
+            let mut commands = env.commands.lock().unwrap();
+            buffer
+                .drain(|command| commands.push((command.opcode, command.payload.to_vec())))
+                .unwrap() as u32
+        }
+        let env = Env::default();
+        let commands = env.commands.clone();
+        let imports = imports! {
+            "host" => { "flush" => Function::new_native_with_env(&store, env, flush) },
+        };
+        let instance = Instance::new(&module, &imports)?;
+        let run = instance.exports.get_native_function::<(), u32>("run")?;
+
+        assert_eq!(run.call()?, 2);
+        assert_eq!(
+            *commands.lock().unwrap(),
+            vec![(1, b"hi".to_vec()), (2, vec![])]
+        );
+        // The commands were marked as read.
+        assert_eq!(run.call()?, 0);
+        let memory = instance.exports.get_memory("memory")?;
+        let mut head = [0; 4];
+        memory.read(260, &mut head)?;
+        assert_eq!(u32::from_le_bytes(head), 20);
+        Ok(())
+    }
+
+    #[test]
+    fn commands_wrap_around_the_ring() -> Result<()> {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+        let mut buffer = vec![0; 12 + 48];
+        CommandWriter::new(&mut buffer);
+        let commands = CommandBuffer::new(&memory, 0);
+
+        for round in 0..20u32 {
+            let payload = vec![round as u8; (round % 7) as usize];
+            let mut writer = CommandWriter::resume(&mut buffer);
+            writer.push(round, &payload)?;
+            writer.push(round + 100, &[])?;
+            memory.write(0, &buffer)?;
+
+            let mut received = vec![];
+            let count = commands
+                .drain(|command| received.push((command.opcode, command.payload.to_vec())))?;
+            assert_eq!(count, 2);
+            assert_eq!(received, vec![(round, payload), (round + 100, vec![])]);
+            memory.read(0, &mut buffer)?;
+            assert!(CommandWriter::resume(&mut buffer).is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn full_rings_reject_commands() -> Result<()> {
+        let mut buffer = vec![0; 12 + 32];
+        let mut writer = CommandWriter::new(&mut buffer);
+        writer.push(1, &[0; 8])?;
+        writer.push(2, &[0; 4])?;
+        // 4 bytes are left, which are always kept free.
+        assert_eq!(writer.push(3, &[]), Err(CommandBufferFull));
+        assert!(!writer.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_buffers_are_reported() -> Result<()> {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+        let commands = CommandBuffer::new(&memory, 0);
+
+        // A command longer than the ring.
+        let mut buffer = vec![0; 12 + 32];
+        CommandWriter::new(&mut buffer).push(1, &[0; 4])?;
+        buffer[16..20].copy_from_slice(&1000u32.to_le_bytes());
+        memory.write(0, &buffer)?;
+        assert!(matches!(
+            commands.drain(|_| panic!("no command is valid")),
+            Err(CommandBufferError::Invalid(_))
+        ));
+
+        // A ring going past the end of the memory.
+        let commands = CommandBuffer::new(&memory, 65536 - 16);
+        memory.write(65536 - 16, &64u32.to_le_bytes())?;
+        assert!(matches!(
+            commands.drain(|_| ()),
+            Err(CommandBufferError::OutOfBounds(_))
+        ));
+        Ok(())
+    }
+}
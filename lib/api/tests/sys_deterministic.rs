@@ -0,0 +1,59 @@
+#[cfg(feature = "sys")]
+mod sys {
+    use anyhow::Result;
+    use wasmer::*;
+
+    // Stores NaNs produced by arithmetic, whose bits depend on the host
+    // unless they are canonicalized, and grows its memory.
+    const MODULE: &str = r#"
+    (module
+      (memory (export "memory") 1)
+      (func (export "run") (param $seed i32)
+        (f32.store (i32.const 0)
+          (f32.div (f32.const 0) (f32.const 0)))
+        (f64.store (i32.const 8)
+          (f64.sqrt (f64.const -1)))
+        (f32.store (i32.const 16)
+          (f32.mul (f32.reinterpret_i32 (i32.const 0xff800001)) (f32.const 1)))
+        (drop (memory.grow (i32.const 1)))
+        (i32.store (i32.const 0x10000) (local.get $seed))
+        (i32.store (i32.const 24) (memory.size))))
+    "#;
+
+    fn run_in_deterministic_store() -> Result<Vec<u8>> {
+        let store = StoreBuilder::default().deterministic().build();
+        assert!(store.is_deterministic());
+        let module = Module::new(&store, MODULE)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let run = instance.exports.get_function("run")?;
+        run.call(&[Value::I32(42)])?;
+        let memory = instance.exports.get_memory("memory")?;
+        Ok(unsafe { memory.data_unchecked() }.to_vec())
+    }
+
+    #[test]
+    fn deterministic_runs_have_the_same_memory() -> Result<()> {
+        let first = run_in_deterministic_store()?;
+        let second = run_in_deterministic_store()?;
+        assert_eq!(first.len(), 2 * 0x10000);
+        assert!(first == second);
+
+        // The NaNs are canonical.
+        assert_eq!(&first[0..4], &0x7fc0_0000u32.to_le_bytes());
+        assert_eq!(&first[8..16], &0x7ff8_0000_0000_0000u64.to_le_bytes());
+        assert_eq!(&first[16..20], &0x7fc0_0000u32.to_le_bytes());
+        assert_eq!(&first[24..28], &2u32.to_le_bytes());
+        assert_eq!(&first[0x10000..0x10004], &42u32.to_le_bytes());
+        Ok(())
+    }
+
+    #[test]
+    fn deterministic_store_disables_nondeterministic_features() -> Result<()> {
+        let store = StoreBuilder::default().deterministic().build();
+        let shared_memory = "(module (memory 1 1 shared))";
+        assert!(Module::new(&store, shared_memory).is_err());
+        assert!(!StoreBuilder::default().build().is_deterministic());
+        assert!(!Store::default().is_deterministic());
+        Ok(())
+    }
+}
@@ -129,6 +129,29 @@ mod sys {
         Ok(())
     }
 
+    #[test]
+    fn table_seal() -> Result<()> {
+        let store = Store::default();
+        let table_type = TableType {
+            ty: Type::FuncRef,
+            minimum: 2,
+            maximum: None,
+        };
+        let f = Function::new_native(&store, |num: i32| num + 1);
+        let table = Table::new(&store, table_type, Value::FuncRef(Some(f.clone())))?;
+        assert!(!table.is_sealed());
+        table.seal()?;
+        assert!(table.is_sealed());
+
+        assert!(table.grow(1, Value::FuncRef(None)).is_err());
+        assert!(table.set(0, Value::FuncRef(None)).is_err());
+        assert!(Table::copy(&table, 0, &table, 1, 1).is_err());
+        assert_eq!(table.size(), 2);
+        assert!(table.get(0).unwrap().funcref().unwrap().is_some());
+
+        Ok(())
+    }
+
     #[test]
     #[ignore]
     fn table_copy() -> Result<()> {
@@ -179,6 +202,134 @@ mod sys {
         Ok(())
     }
 
+    #[test]
+    fn memory_seal() -> Result<()> {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(Pages(1), None, false))?;
+        assert!(!memory.is_sealed());
+        memory.grow(Pages(1))?;
+        memory.seal()?;
+        assert!(memory.is_sealed());
+
+        assert_eq!(memory.grow(Pages(1)), Err(MemoryError::Sealed));
+        assert_eq!(memory.grow(Pages(0)), Ok(Pages(2)));
+        assert_eq!(memory.size(), Pages(2));
+        // The contents can still change.
+        memory.write(0, &[1])?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_commit() -> Result<()> {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(Pages(4), None, false))?;
+        memory.write(100, &[42])?;
+        let page_size = Pages(1).bytes().0 as u64;
+
+        memory.commit(0..2 * page_size)?;
+        let mut contents = [0];
+        memory.read(100, &mut contents)?;
+        assert_eq!(contents, [42]);
+        #[cfg(unix)]
+        assert!(memory.resident_size()? >= Pages(2).bytes());
+
+        assert!(memory.commit(3 * page_size..5 * page_size).is_err());
+        assert!(memory.commit(2..1).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_copy_to() -> Result<()> {
+        let store = Store::default();
+        let a = Memory::new(&store, MemoryType::new(Pages(3), None, false))?;
+        let b = Memory::new(&store, MemoryType::new(Pages(3), None, false))?;
+        let page_size = Pages(1).bytes().0 as u64;
+        let data = (0..2 * page_size + 10)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        a.write(5, &data)?;
+
+        a.copy_to(&b, 5, 7, data.len() as u64)?;
+        let mut contents = vec![0; data.len()];
+        b.read(7, &mut contents)?;
+        assert_eq!(contents, data);
+
+        // Overlapping copies within the same memory, in both directions.
+        b.copy_to(&b, 7, 9, data.len() as u64)?;
+        b.read(9, &mut contents)?;
+        assert_eq!(contents, data);
+        b.copy_to(&b, 9, 2, data.len() as u64)?;
+        b.read(2, &mut contents)?;
+        assert_eq!(contents, data);
+
+        assert!(a.copy_to(&b, 3 * page_size - 1, 0, 2).is_err());
+        assert!(a.copy_to(&b, 0, 3 * page_size - 1, 2).is_err());
+        assert!(a.copy_to(&b, 1, u64::MAX, 2).is_err());
+        assert!(a.copy_to(&b, 0, 0, 0).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn sealed_exports_cant_grow_from_wasm() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+    (memory (export "memory") 1)
+    (table (export "table") 1 funcref)
+    (func (export "grow_memory") (result i32)
+        (memory.grow (i32.const 1)))
+    (func (export "grow_table") (result i32)
+        (table.grow (ref.null func) (i32.const 1))))"#,
+        )?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let grow_memory: NativeFunc<(), i32> =
+            instance.exports.get_native_function("grow_memory")?;
+        let grow_table: NativeFunc<(), i32> = instance.exports.get_native_function("grow_table")?;
+        assert_eq!(grow_memory.call()?, 1);
+        assert_eq!(grow_table.call()?, 1);
+
+        let memory = instance.exports.get_memory("memory")?;
+        let table = instance.exports.get_table("table")?;
+        memory.seal()?;
+        table.seal()?;
+        assert_eq!(grow_memory.call()?, -1);
+        assert_eq!(grow_table.call()?, -1);
+        assert_eq!(memory.size(), Pages(2));
+        assert_eq!(table.size(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_dirty_pages() -> Result<()> {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(Pages(4), None, false))?;
+        assert!(memory.take_dirty_pages().is_err());
+        if memory.start_tracking().is_err() {
+            // The host doesn't report soft-dirty pages.
+            return Ok(());
+        }
+
+        memory.write(Pages(1).bytes().0 as u64, &[1])?;
+        memory.write(Pages(3).bytes().0 as u64 + 10, &[2])?;
+        let dirty = memory.take_dirty_pages()?;
+        assert!(dirty.contains(&Pages(1)));
+        assert!(dirty.contains(&Pages(3)));
+        assert!(memory.take_dirty_pages()?.is_empty());
+
+        // Tracking another memory doesn't lose the writes to this one.
+        let other = Memory::new(&store, MemoryType::new(Pages(1), None, false))?;
+        memory.write(0, &[3])?;
+        other.start_tracking()?;
+        assert_eq!(memory.take_dirty_pages()?, vec![Pages(0)]);
+
+        Ok(())
+    }
+
     #[test]
     fn function_new() -> Result<()> {
         let store = Store::default();
@@ -382,6 +533,49 @@ mod sys {
         Ok(())
     }
 
+    #[test]
+    fn leaf_functions_work() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+    (import "env" "double" (func $double (param i32) (result i32)))
+    (import "env" "offset" (func $offset (param i32) (result i32)))
+    (import "env" "fail" (func $fail))
+    (func (export "run") (param i32) (result i32) (local i32)
+        (loop $next
+            (local.set 1 (call $offset (call $double (local.get 1))))
+            (br_if $next (local.tee 0 (i32.sub (local.get 0) (i32.const 1)))))
+        (local.get 1))
+    (func (export "fail") (call $fail)))"#,
+        )?;
+        #[derive(Clone, WasmerEnv)]
+        struct Offset(i32);
+        let imports = imports! {
+            "env" => {
+                "double" => Function::new_native_with_flags(&store, FunctionFlags::LEAF, |x: i32| x * 2),
+                "offset" => Function::new_native_with_env_and_flags(
+                    &store,
+                    Offset(1),
+                    FunctionFlags::LEAF,
+                    |env: &Offset, x: i32| x + env.0,
+                ),
+                "fail" => Function::new_native_with_flags(&store, FunctionFlags::LEAF, || -> Result<(), RuntimeError> {
+                    Err(RuntimeError::new("leaf failure"))
+                }),
+            },
+        };
+        let instance = Instance::new(&module, &imports)?;
+
+        let run: NativeFunc<i32, i32> = instance.exports.get_native_function("run")?;
+        assert_eq!(run.call(4)?, 15);
+        let fail: NativeFunc<(), ()> = instance.exports.get_native_function("fail")?;
+        assert_eq!(fail.call().unwrap_err().message(), "leaf failure");
+        assert!(FunctionFlags::LEAF.contains(FunctionFlags::empty()));
+        assert!(!FunctionFlags::empty().contains(FunctionFlags::LEAF));
+        Ok(())
+    }
+
     #[test]
     fn function_outlives_instance() -> Result<()> {
         let store = Store::default();
@@ -67,4 +67,396 @@ mod sys {
 
         Ok(())
     }
+
+    #[test]
+    fn exports_iter_typed_and_lazy_native_functions() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+    (module
+      (func (export "sum") (param i32 i32) (result i32)
+        local.get 0
+        local.get 1
+        i32.add)
+      (memory (export "memory") 1)
+      (global (export "answer") i32 (i32.const 42)))
+"#,
+        )?;
+        let instance = Instance::new(&module, &imports! {})?;
+
+        let types = instance.exports.iter_typed().collect::<Vec<_>>();
+        assert_eq!(types.len(), 3);
+        assert_eq!(types[0].0, "sum");
+        assert!(matches!(types[0].1, ExternType::Function(_)));
+        assert!(matches!(types[1].1, ExternType::Memory(_)));
+        assert!(matches!(types[2].1, ExternType::Global(_)));
+
+        // The kind of the export is checked eagerly.
+        assert!(instance
+            .exports
+            .get_native_function_lazy::<(i32, i32), i32>("memory")
+            .is_err());
+
+        // The signature is only checked on first access.
+        let sum = instance
+            .exports
+            .get_native_function_lazy::<(i32, i32), i32>("sum")?;
+        assert_eq!(sum.get()?.call(1, 2)?, 3);
+        let bad_sum = instance
+            .exports
+            .get_native_function_lazy::<(i64, i64), i32>("sum")?;
+        assert!(bad_sum.get().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn imports_are_reported_with_their_host_function_info() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+    (module
+      (import "host" "clock" (func (result i64)))
+      (import "host" "memory" (memory 1)))
+"#,
+        )?;
+
+        fn clock() -> i64 {
+            0
+        }
+        let info = HostFunctionInfo {
+            name: Some("clock".to_string()),
+            docs: Some("Reads the host clock.".to_string()),
+            cost: Some(5),
+        };
+        let memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+        let import_object = imports! {
+            "host" => {
+                "clock" => Function::new_native(&store, clock).with_info(info.clone()),
+                "memory" => memory,
+            },
+        };
+        let instance = Instance::new(&module, &import_object)?;
+
+        let imports = instance.imports();
+        assert_eq!(imports.len(), 2);
+        assert_eq!(
+            (imports[0].0.module(), imports[0].0.name()),
+            ("host", "clock")
+        );
+        match &imports[0].1 {
+            Extern::Function(function) => assert_eq!(function.info(), Some(&info)),
+            other => panic!("unexpected import {:?}", other),
+        }
+        assert_eq!(imports[1].0.name(), "memory");
+        assert!(matches!(imports[1].1, Extern::Memory(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn exports_as_imports_chain_modules() -> Result<()> {
+        let store = Store::default();
+        let a = Module::new(
+            &store,
+            r#"(module
+            (memory (export "memory") 1)
+            (global (export "base") i32 (i32.const 8))
+            (func (export "store") (param i32 i32)
+                (i32.store (local.get 0) (local.get 1)))
+            (func (export "internal_load") (param i32) (result i32)
+                (i32.load (local.get 0))))"#,
+        )?;
+        let b = Module::new(
+            &store,
+            r#"(module
+            (import "a" "memory" (memory 1))
+            (import "a" "base" (global $base i32))
+            (import "a" "store" (func $store (param i32 i32)))
+            (func (export "run") (result i32)
+                (call $store (global.get $base) (i32.const 42))
+                (i32.load (global.get $base))))"#,
+        )?;
+        let a = Instance::new(&a, &imports! {})?;
+
+        let imports = a.exports_as_imports("a");
+        assert!(imports.contains_namespace("a"));
+        let b = Instance::new(&b, &imports)?;
+        assert_eq!(b.exports.get_native_function::<(), i32>("run")?.call()?, 42);
+        assert_eq!(
+            a.exports
+                .get_native_function::<i32, i32>("internal_load")?
+                .call(8)?,
+            42
+        );
+
+        let renamed = a.exports_as_imports_with("env", |name, _| {
+            if name.starts_with("internal_") {
+                None
+            } else {
+                Some(format!("a_{}", name))
+            }
+        });
+        assert!(renamed.get_export("env", "a_store").is_some());
+        assert!(renamed.get_export("env", "a_memory").is_some());
+        assert!(renamed.get_export("env", "store").is_none());
+        assert!(renamed.get_export("env", "a_internal_load").is_none());
+        assert!(renamed.get_export("env", "internal_load").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn incompatible_imports_report_their_limits() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+    (import "env" "memory" (memory 2 10))
+    (import "env" "table" (table 4 funcref))
+    (import "env" "global" (global (mut i32))))"#,
+        )?;
+        let memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+        let table = Table::new(
+            &store,
+            TableType::new(Type::FuncRef, 1, None),
+            Value::FuncRef(None),
+        )?;
+        let global = Global::new(&store, Value::I32(0));
+
+        let compatible_memory = Memory::new(&store, MemoryType::new(2, Some(10), false))?;
+        let compatible_table = Table::new(
+            &store,
+            TableType::new(Type::FuncRef, 4, None),
+            Value::FuncRef(None),
+        )?;
+        let compatible_global = Global::new_mut(&store, Value::I32(0));
+
+        let imports = imports! {
+            "env" => {
+                "memory" => memory,
+                "table" => compatible_table.clone(),
+                "global" => compatible_global.clone(),
+            },
+        };
+        match Instance::new(&module, &imports) {
+            Err(InstantiationError::Link(LinkError::IncompatibleMemoryLimits {
+                expected,
+                found,
+                import_name,
+            })) => {
+                assert_eq!(expected, MemoryType::new(2, Some(10), false));
+                assert_eq!(found, MemoryType::new(1, None, false));
+                assert_eq!(import_name, "env.memory");
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        let imports = imports! {
+            "env" => {
+                "memory" => compatible_memory.clone(),
+                "table" => table,
+                "global" => compatible_global,
+            },
+        };
+        match Instance::new(&module, &imports) {
+            Err(InstantiationError::Link(LinkError::IncompatibleTableLimits {
+                expected,
+                found,
+                import_name,
+            })) => {
+                assert_eq!(expected, TableType::new(Type::FuncRef, 4, None));
+                assert_eq!(found, TableType::new(Type::FuncRef, 1, None));
+                assert_eq!(import_name, "env.table");
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        let imports = imports! {
+            "env" => {
+                "memory" => compatible_memory,
+                "table" => compatible_table,
+                "global" => global,
+            },
+        };
+        match Instance::new(&module, &imports) {
+            Err(InstantiationError::Link(error @ LinkError::IncompatibleGlobalType { .. })) => {
+                assert!(error.to_string().contains("env.global"));
+                assert!(error.to_string().contains("incompatible import type"));
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn hot_swap_carries_state_over() -> Result<()> {
+        let store = Store::default();
+        let v1 = Module::new(
+            &store,
+            r#"(module
+            (memory (export "memory") 1)
+            (global (export "hits") (mut i32) (i32.const 0))
+            (table (export "table") 2 funcref)
+            (elem (i32.const 0) $handler)
+            (func $handler (export "handler") (result i32) (i32.const 1))
+            (func (export "hit") (result i32)
+                (global.set 0 (i32.add (global.get 0) (i32.const 1)))
+                (global.get 0)))"#,
+        )?;
+        let v2 = Module::new(
+            &store,
+            r#"(module
+            (memory (export "memory") 1)
+            (data (i32.const 0) "\ff")
+            (global (export "count") (mut i32) (i32.const 0))
+            (table (export "table") 1 funcref)
+            (func $handler (export "handler") (result i32) (i32.const 2))
+            (func (export "dispatch") (result i32)
+                (call_indirect (result i32) (i32.const 0))))"#,
+        )?;
+
+        let instance = Instance::new(&v1, &imports! {})?;
+        instance
+            .exports
+            .get_native_function::<(), i32>("hit")?
+            .call()?;
+        instance
+            .exports
+            .get_native_function::<(), i32>("hit")?
+            .call()?;
+        let memory = instance.exports.get_memory("memory")?;
+        memory.grow(1)?;
+        unsafe { memory.data_unchecked_mut()[0] = 42 };
+
+        match instance.hot_swap(&v2, &Migration::new().global("hits")) {
+            Err(HotSwapError::MissingGlobal { name, new: true }) => assert_eq!(name, "hits"),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        let swapped = instance.hot_swap(&v2, &Migration::new().global_renamed("hits", "count"))?;
+        assert_eq!(swapped.exports.get_global("count")?.get(), Value::I32(2));
+        let memory = swapped.exports.get_memory("memory")?;
+        assert_eq!(memory.size(), Pages(2));
+        assert_eq!(unsafe { memory.data_unchecked()[0] }, 42);
+        let table = swapped.exports.get_table("table")?;
+        assert_eq!(table.size(), 2);
+        // The handler of the old version is replaced by the new one
+        assert_eq!(
+            swapped
+                .exports
+                .get_native_function::<(), i32>("dispatch")?
+                .call()?,
+            2
+        );
+
+        // The old instance is left untouched
+        assert_eq!(
+            instance
+                .exports
+                .get_native_function::<(), i32>("hit")?
+                .call()?,
+            3
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn instances_are_used_from_other_threads() -> Result<()> {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use std::sync::Arc;
+        use std::thread;
+
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+            (import "host" "count" (func $count))
+            (memory (export "memory") 1)
+            (data $greeting "hello")
+            (func (export "greet") (param i32)
+                (call $count)
+                (memory.init $greeting (local.get 0) (i32.const 0) (i32.const 5)))
+            (func (export "forget") (data.drop $greeting)))"#,
+        )?;
+
+        #[derive(Clone, WasmerEnv)]
+        struct Env {
+            calls: Arc<AtomicU32>,
+        }
+        let calls = Arc::new(AtomicU32::new(0));
+        let count = Function::new_native_with_env(
+            &store,
+            Env {
+                calls: calls.clone(),
+            },
+            |env: &Env| {
+                env.calls.fetch_add(1, Ordering::SeqCst);
+            },
+        );
+        let instance = Instance::new(&module, &imports! { "host" => { "count" => count } })?;
+
+        // The instance is moved to, and called from, another thread.
+        let moved = instance.clone();
+        thread::spawn(move || -> Result<()> {
+            moved
+                .exports
+                .get_native_function::<i32, ()>("greet")?
+                .call(0)?;
+            Ok(())
+        })
+        .join()
+        .unwrap()?;
+
+        // Then shared between threads calling it concurrently.
+        let shared = Arc::new(instance);
+        let handles = (1..=4)
+            .map(|i| {
+                let shared = shared.clone();
+                thread::spawn(move || -> Result<()> {
+                    let greet = shared.exports.get_native_function::<i32, ()>("greet")?;
+                    for _ in 0..100 {
+                        greet.call(i * 8)?;
+                    }
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap()?;
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 401);
+
+        let memory = shared.exports.get_memory("memory")?;
+        for i in 0..=4 {
+            let offset = i * 8;
+            assert_eq!(
+                unsafe { &memory.data_unchecked()[offset..offset + 5] },
+                b"hello"
+            );
+        }
+
+        // Dropping the segment from one thread is seen by the others.
+        let forget = shared.clone();
+        thread::spawn(move || -> Result<()> {
+            forget
+                .exports
+                .get_native_function::<(), ()>("forget")?
+                .call()?;
+            Ok(())
+        })
+        .join()
+        .unwrap()?;
+        assert!(shared
+            .exports
+            .get_native_function::<i32, ()>("greet")?
+            .call(0)
+            .is_err());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,155 @@
+#[cfg(feature = "sys")]
+mod sys {
+    use anyhow::Result;
+    use wasmer::*;
+
+    #[test]
+    fn linker_instantiation_order() -> Result<()> {
+        let store = Store::default();
+        let app = Module::new(
+            &store,
+            r#"(module
+    (import "lib" "get" (func (result i32)))
+    (import "base" "get" (func (result i32))))"#,
+        )?;
+        let lib = Module::new(
+            &store,
+            r#"(module
+    (import "base" "get" (func $get (result i32)))
+    (func (export "get") (result i32) (call $get)))"#,
+        )?;
+        let base = Module::new(
+            &store,
+            r#"(module (func (export "get") (result i32) (i32.const 7)))"#,
+        )?;
+
+        let mut linker = Linker::new(&store);
+        linker
+            .module("app", app)
+            .module("lib", lib)
+            .module("base", base);
+        assert_eq!(linker.instantiation_order()?, vec!["base", "lib", "app"]);
+
+        let instances = linker.instantiate()?;
+        let names: Vec<&str> = instances.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["base", "lib", "app"]);
+        let get = instances.get("lib").unwrap().exports.get_function("get")?;
+        assert_eq!(get.call(&[])?.to_vec(), vec![Value::I32(7)]);
+        Ok(())
+    }
+
+    #[test]
+    fn linker_cycle() -> Result<()> {
+        let store = Store::default();
+        let a = Module::new(
+            &store,
+            r#"(module (import "b" "f" (func)) (func (export "f")))"#,
+        )?;
+        let b = Module::new(
+            &store,
+            r#"(module (import "c" "f" (func)) (func (export "f")))"#,
+        )?;
+        let c = Module::new(
+            &store,
+            r#"(module (import "a" "f" (func)) (func (export "f")))"#,
+        )?;
+
+        let mut linker = Linker::new(&store);
+        linker.module("a", a).module("b", b).module("c", c);
+        match linker.instantiation_order() {
+            Err(LinkerError::Cycle(cycle)) => assert_eq!(cycle, vec!["a", "b", "c"]),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(linker.instantiate(), Err(LinkerError::Cycle(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn linker_shares_memory_and_table() -> Result<()> {
+        let store = Store::default();
+        let writer = Module::new(
+            &store,
+            r#"(module
+    (import "env" "memory" (memory 1))
+    (import "env" "__indirect_function_table" (table 1 funcref))
+    (func $seven (result i32) (i32.const 7))
+    (elem (i32.const 0) $seven)
+    (func (export "write") (i32.store (i32.const 8) (i32.const 42))))"#,
+        )?;
+        let reader = Module::new(
+            &store,
+            r#"(module
+    (import "env" "memory" (memory 2 10))
+    (import "env" "__indirect_function_table" (table 2 funcref))
+    (import "writer" "write" (func $write))
+    (type $get (func (result i32)))
+    (func (export "read") (result i32)
+        (call $write)
+        (i32.add
+            (i32.load (i32.const 8))
+            (call_indirect (type $get) (i32.const 0)))))"#,
+        )?;
+
+        let instances = Linker::new(&store)
+            .module("reader", reader)
+            .module("writer", writer)
+            .instantiate()?;
+        let memory = instances.memory().unwrap();
+        assert_eq!(memory.ty(), MemoryType::new(2, Some(10), false));
+        assert_eq!(instances.table().unwrap().size(), 2);
+
+        let read = instances
+            .get("reader")
+            .unwrap()
+            .exports
+            .get_function("read")?;
+        assert_eq!(read.call(&[])?.to_vec(), vec![Value::I32(49)]);
+        assert_eq!(memory.view::<u8>()[8].get(), 42);
+        Ok(())
+    }
+
+    #[test]
+    fn linker_incompatible_memories() -> Result<()> {
+        let store = Store::default();
+        let small = Module::new(&store, r#"(module (import "env" "memory" (memory 1 2)))"#)?;
+        let large = Module::new(&store, r#"(module (import "env" "memory" (memory 3)))"#)?;
+
+        let result = Linker::new(&store)
+            .module("small", small)
+            .module("large", large)
+            .instantiate();
+        assert!(matches!(
+            result,
+            Err(LinkerError::IncompatibleImports { name }) if name == "memory"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn linker_host_imports() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+    (import "env" "memory" (memory 1))
+    (import "env" "answer" (func $answer (result i32)))
+    (func (export "run") (result i32) (call $answer)))"#,
+        )?;
+        let memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+        let imports = imports! {
+            "env" => {
+                "memory" => memory,
+                "answer" => Function::new_native(&store, || 42),
+            },
+        };
+
+        let instances = Linker::new(&store)
+            .module("main", module)
+            .imports(imports)
+            .instantiate()?;
+        assert!(instances.memory().is_none());
+        let run = instances.get("main").unwrap().exports.get_function("run")?;
+        assert_eq!(run.call(&[])?.to_vec(), vec![Value::I32(42)]);
+        Ok(())
+    }
+}
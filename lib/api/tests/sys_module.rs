@@ -1,6 +1,8 @@
 #[cfg(feature = "sys")]
 mod sys {
     use anyhow::Result;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
     use wasmer::*;
 
     #[test]
@@ -248,4 +250,503 @@ mod sys {
 
         Ok(())
     }
+
+    /// A reader handing out its bytes one at a time, then failing.
+    struct TrickleReader {
+        bytes: Vec<u8>,
+        position: usize,
+        fail_at_end: bool,
+    }
+
+    impl std::io::Read for TrickleReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.position == self.bytes.len() {
+                if self.fail_at_end {
+                    return Err(std::io::ErrorKind::ConnectionReset.into());
+                }
+                return Ok(0);
+            }
+            buf[0] = self.bytes[self.position];
+            self.position += 1;
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn module_from_reader_buffered() -> Result<()> {
+        let store = Store::default();
+        let wasm = wat::parse_str(
+            r#"(module
+    (func (export "add_one") (param i32) (result i32)
+        local.get 0
+        i32.const 1
+        i32.add))"#,
+        )?;
+        let reader = TrickleReader {
+            bytes: wasm,
+            position: 0,
+            fail_at_end: false,
+        };
+        let module = Module::from_reader_buffered(&store, reader)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let add_one: NativeFunc<i32, i32> = instance.exports.get_native_function("add_one")?;
+        assert_eq!(add_one.call(41)?, 42);
+
+        // A type section announcing five types but containing none is
+        // rejected before the reader gets to fail.
+        let reader = TrickleReader {
+            bytes: vec![
+                0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01, 0x05,
+            ],
+            position: 0,
+            fail_at_end: true,
+        };
+        assert!(matches!(
+            Module::from_reader_buffered(&store, reader),
+            Err(IoCompileError::Compile(CompileError::Validate(_)))
+        ));
+
+        // Read failures are reported as such.
+        let reader = TrickleReader {
+            bytes: vec![0x00, 0x61, 0x73, 0x6d],
+            position: 0,
+            fail_at_end: true,
+        };
+        match Module::from_reader_buffered(&store, reader) {
+            Err(IoCompileError::Io(e)) => {
+                assert_eq!(e.kind(), std::io::ErrorKind::ConnectionReset)
+            }
+            _ => panic!("expected an IO error"),
+        }
+
+        Ok(())
+    }
+
+    /// A reader using the engine of `store` while the module is read.
+    struct EngineReader {
+        store: Store,
+        bytes: std::io::Cursor<Vec<u8>>,
+    }
+
+    impl std::io::Read for EngineReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            Module::validate(&self.store, b"\0asm\x01\0\0\0").unwrap();
+            std::io::Read::read(&mut self.bytes, buf)
+        }
+    }
+
+    #[test]
+    fn module_from_reader_buffered_does_not_lock_the_engine() -> Result<()> {
+        let store = Store::default();
+        let reader = EngineReader {
+            store: store.clone(),
+            bytes: std::io::Cursor::new(wat::parse_str("(module)")?),
+        };
+        Module::from_reader_buffered(&store, reader)?;
+
+        Ok(())
+    }
+
+    #[derive(WasmerEnv, Clone)]
+    struct Offset {
+        value: i32,
+    }
+
+    #[test]
+    fn module_instantiated_from_many_threads() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+    (import "env" "offset" (func $offset (result i32)))
+    (func (export "add") (param i32) (result i32)
+        local.get 0
+        call $offset
+        i32.add))"#,
+        )?;
+
+        let handles = (0..8)
+            .map(|i| {
+                let module = module.clone();
+                std::thread::spawn(move || -> Result<i32> {
+                    assert!(Arc::ptr_eq(module.artifact(), module.clone().artifact()));
+                    let offset = Function::new_native_with_env(
+                        module.store(),
+                        Offset { value: i },
+                        |env: &Offset| env.value,
+                    );
+                    let instance = Instance::new(
+                        &module,
+                        &imports! {
+                            "env" => {
+                                "offset" => offset,
+                            },
+                        },
+                    )?;
+                    let add: NativeFunc<i32, i32> = instance.exports.get_native_function("add")?;
+                    Ok(add.call(100)?)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.join().unwrap()?, 100 + i as i32);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn module_signatures_are_released_on_drop() -> Result<()> {
+        let store = Store::default();
+        let registry = store.signature_registry();
+        let signatures = registry.len();
+        let wat = r#"(module
+    (type (func (param i64 f64) (result f32)))
+    (func (export "f") (param i64 f64) (result f32)
+        f32.const 0))"#;
+
+        let module = Module::new(&store, wat)?;
+        let other = Module::new(&store, wat)?;
+        assert_eq!(registry.len(), signatures + 1);
+        drop(module);
+        assert_eq!(registry.len(), signatures + 1);
+        drop(other);
+        assert_eq!(registry.len(), signatures);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "compiler")]
+    fn module_analysis() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+    (import "host" "log" (func $log (param i32)))
+    (table 1 funcref)
+    (func $leaf (param i32) (call $log (local.get 0)))
+    (func $fact (param i64) (result i64)
+        (if (result i64) (i64.eqz (local.get 0))
+            (then (i64.const 1))
+            (else (i64.mul (local.get 0) (call $fact (i64.sub (local.get 0) (i64.const 1)))))))
+    (func $loops
+        (loop (block (loop (br 1))))
+        (call $leaf (i32.const 0))
+        (call $leaf (i32.const 1)))
+    (func $indirect (call_indirect (i32.const 0))))"#;
+        let module = Module::new(&store, wat)?;
+        let analysis = module.analysis().expect("compiled modules are analyzed");
+        assert_eq!(analysis.functions().len(), 4);
+        assert!(analysis.function(FunctionIndex::from_u32(0)).is_none());
+
+        let leaf = analysis.function(FunctionIndex::from_u32(1)).unwrap();
+        assert_eq!(leaf.callees, vec![FunctionIndex::from_u32(0)]);
+        assert!(!leaf.recursive && !leaf.calls_indirect);
+        assert_eq!(leaf.max_loop_depth, 0);
+
+        let fact = analysis.function(FunctionIndex::from_u32(2)).unwrap();
+        assert!(fact.recursive);
+        assert_eq!(analysis.max_stack_usage(FunctionIndex::from_u32(2)), None);
+
+        let loops = analysis.function(FunctionIndex::from_u32(3)).unwrap();
+        assert_eq!(loops.callees, vec![FunctionIndex::from_u32(1)]);
+        assert_eq!(loops.max_loop_depth, 2);
+        assert!(!loops.recursive);
+
+        let indirect = analysis.function(FunctionIndex::from_u32(4)).unwrap();
+        assert!(indirect.calls_indirect);
+        assert_eq!(analysis.max_stack_usage(FunctionIndex::from_u32(4)), None);
+        assert!(analysis.has_unbounded_loops());
+
+        if leaf.frame_size.is_some() {
+            let leaf_usage = analysis
+                .max_stack_usage(FunctionIndex::from_u32(1))
+                .unwrap();
+            let loops_usage = analysis
+                .max_stack_usage(FunctionIndex::from_u32(3))
+                .unwrap();
+            assert!(loops_usage > leaf_usage);
+        }
+
+        let bounded = Module::new(&store, r#"(module (func (nop)))"#)?;
+        assert!(!bounded.analysis().unwrap().has_unbounded_loops());
+
+        let deserialized = unsafe { Module::deserialize(&store, &bounded.serialize()?)? };
+        assert!(deserialized.analysis().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "compiler")]
+    fn module_import_reachability() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+    (import "env" "read" (func $read))
+    (import "env" "write" (func $write))
+    (import "env" "exit" (func $exit))
+    (import "env" "spawn" (func $spawn))
+    (import "env" "memory" (memory 1))
+    (table 2 funcref)
+    (elem (i32.const 0) $callback)
+    (func $callback (call $write))
+    (func $dispatch (call_indirect (i32.const 0)))
+    (func $unused (call $spawn))
+    (func (export "run") (call $read) (call $dispatch))
+    (func $init (call $exit))
+    (start $init))"#;
+        let module = Module::new(&store, wat)?;
+        let names = |imports: Vec<ImportType>| {
+            imports
+                .iter()
+                .map(|import| import.name().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let reachable = module.reachable_imports().unwrap();
+        assert_eq!(names(reachable), vec!["read", "write", "exit"]);
+
+        let used_by = |index| {
+            names(
+                module
+                    .imports_used_by(FunctionIndex::from_u32(index))
+                    .unwrap(),
+            )
+        };
+        assert_eq!(used_by(4), vec!["write"]);
+        assert_eq!(used_by(5), vec!["write"]);
+        assert_eq!(used_by(6), vec!["spawn"]);
+        assert_eq!(used_by(8), vec!["exit"]);
+
+        // Functions stored in an exported table can be called by the host.
+        let shared = Module::new(
+            &store,
+            r#"(module
+    (import "env" "write" (func $write))
+    (table (export "table") 1 funcref)
+    (elem (i32.const 0) $callback)
+    (func $callback (call $write)))"#,
+        )?;
+        assert_eq!(names(shared.reachable_imports().unwrap()), vec!["write"]);
+
+        let deserialized = unsafe { Module::deserialize(&store, &module.serialize()?)? };
+        assert!(deserialized.reachable_imports().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "wasmprinter")]
+    fn module_to_wat() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+    (import "env" "log" (func $log (param i32)))
+    (func $double (export "double") (param i32) (result i32)
+        (i32.add (local.get 0) (local.get 0)))
+    (func $nop (export "nop")))"#,
+        )?;
+
+        let wat = module.to_wat()?;
+        assert!(wat.contains("(import \"env\" \"log\" (func $log"));
+        assert_eq!(Module::new(&store, &wat)?.exports().count(), 2);
+
+        // Only the two defined functions are annotated.
+        let annotated = module.to_wat_annotated()?;
+        let annotations = annotated
+            .lines()
+            .filter(|line| line.contains(";; compiled to "))
+            .count();
+        assert_eq!(annotations, 2);
+
+        let deserialized = unsafe { Module::deserialize(&store, &module.serialize()?)? };
+        assert!(deserialized.to_wat().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn module_metadata() -> Result<()> {
+        let store = Store::default();
+        let mut wasm = wat2wasm(
+            br#"(module $linked
+            (import "env" "log" (func (param i32)))
+            (import "env" "memory" (memory 1 2))
+            (global (export "answer") i32 (i32.const 42))
+            (func (export "run") (param i64) (result f32)
+                (f32.const 0))
+            (elem (i32.const 0) 0)
+            (table 1 funcref)
+            (data (i32.const 0) "hello"))"#,
+        )?
+        .into_owned();
+        // A custom section named `meta`.
+        wasm.extend_from_slice(&[0, 8, 4, b'm', b'e', b't', b'a', 1, 2, 3]);
+        let module = Module::new(&store, &wasm)?;
+
+        let serialized = module.serialize_metadata()?;
+        assert!(serialized.len() < module.serialize()?.len());
+        let metadata = unsafe { Module::deserialize_metadata(&serialized)? };
+
+        assert_eq!(metadata.name(), Some("linked"));
+        assert_eq!(
+            metadata.imports().collect::<Vec<_>>(),
+            module.imports().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            metadata.exports().collect::<Vec<_>>(),
+            module.exports().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            metadata
+                .custom_sections("meta")
+                .map(|data| data.to_vec())
+                .collect::<Vec<_>>(),
+            vec![vec![1, 2, 3]]
+        );
+
+        assert!(unsafe { Module::deserialize_metadata(&module.serialize()?) }.is_err());
+        assert!(unsafe { Module::deserialize_metadata(&serialized[..16]) }.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn estimated_instance_size() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+                (import "env" "table" (table 100 funcref))
+                (memory 3 10)
+                (table 4 funcref)
+                (global (mut i32) (i32.const 0))
+                (func (export "run")))"#,
+        )?;
+        let estimate = module.estimated_instance_size();
+
+        // Imported tables are not counted.
+        assert_eq!(estimate.memories, 3 * WASM_PAGE_SIZE as u64);
+        assert!(estimate.memory_reservations >= 10 * WASM_PAGE_SIZE as u64);
+        assert!(estimate.tables > 0);
+        assert!(estimate.tables < 100);
+        assert!(estimate.vmctx > 0);
+        assert!(estimate.stack > 0);
+        assert_eq!(
+            estimate.committed(),
+            estimate.vmctx + estimate.tables + estimate.memories + estimate.stack
+        );
+
+        let empty = Module::new(&store, "(module)")?.estimated_instance_size();
+        assert!(empty.vmctx < estimate.vmctx);
+        assert_eq!(empty.memories, 0);
+        assert_eq!(empty.memory_reservations, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn module_optimize() -> Result<()> {
+        let store = Store::default();
+        let mut wasm = wat2wasm(
+            br#"(module
+    (import "env" "log" (func $log (param i32)))
+    (table 1 funcref)
+    (elem (i32.const 0) $in_table)
+    (func $dead (call $also_dead))
+    (func $also_dead (call $log (i32.const 0)))
+    (func $in_table (result i32) (i32.const 40))
+    (func $add_two (param i32) (result i32) (i32.add (local.get 0) (i32.const 2)))
+    (func (export "run") (result i32)
+        (call $add_two (call_indirect (result i32) (i32.const 0))))
+    (func $init (call $log (i32.const 1)))
+    (start $init))"#,
+        )?
+        .into_owned();
+        // A `producers` custom section.
+        wasm.extend_from_slice(&[0, 11, 9]);
+        wasm.extend_from_slice(b"producers");
+        wasm.push(0);
+
+        let optimized = Module::optimize(&wasm, &OptimizeOptions::default())?;
+        assert!(optimized.len() < wasm.len());
+        let module = Module::new(&store, &optimized)?;
+        assert_eq!(module.info().functions.len(), 5);
+        assert_eq!(module.custom_sections("producers").count(), 0);
+
+        let import_object = imports! {
+            "env" => {
+                "log" => Function::new_native(&store, |_: i32| {}),
+            },
+        };
+        let instance = Instance::new(&module, &import_object)?;
+        let run = instance.exports.get_function("run")?;
+        assert_eq!(run.call(&[])?.to_vec(), vec![Value::I32(42)]);
+
+        let kept = Module::optimize(
+            &wasm,
+            &OptimizeOptions {
+                keep_custom_sections: vec!["producers".to_string()],
+                remove_dead_functions: false,
+                ..OptimizeOptions::default()
+            },
+        )?;
+        let module = Module::new(&store, &kept)?;
+        assert_eq!(module.info().functions.len(), 7);
+        assert_eq!(module.custom_sections("producers").count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn module_deserialize_from_file() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+    (func $double (param i32) (result i32) (i32.mul (local.get 0) (i32.const 2)))
+    (func (export "run") (param i32) (result i32) (call $double (local.get 0))))"#,
+        )?;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("module.wasmu");
+        module.serialize_to_file(&path)?;
+
+        let loaded = unsafe { Module::deserialize_from_file(&store, &path) }?;
+        let instance = Instance::new(&loaded, &imports! {})?;
+        let run = instance.exports.get_function("run")?;
+        assert_eq!(run.call(&[Value::I32(21)])?.to_vec(), vec![Value::I32(42)]);
+
+        // The code isn't kept by the loaded module, which serializes
+        // to the file it was loaded from.
+        assert_eq!(loaded.serialize()?, std::fs::read(&path)?);
+        Ok(())
+    }
+
+    #[test]
+    fn module_compilation_is_cancellable() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+    (func (export "run") (result i32) (i32.const 42)))"#;
+
+        let cancellation = CancellationToken::new().with_timeout(Duration::from_secs(3600));
+        let module = Module::new_cancellable(&store, wat, &cancellation)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let run = instance.exports.get_native_function::<(), i32>("run")?;
+        assert_eq!(run.call()?, 42);
+
+        let expired = CancellationToken::new().with_deadline(Instant::now());
+        assert!(matches!(
+            Module::new_cancellable(&store, wat, &expired),
+            Err(CompileError::Cancelled)
+        ));
+
+        // Cancelling a clone cancels the compilations using the token
+        cancellation.clone().cancel();
+        assert!(matches!(
+            Module::new_cancellable(&store, wat, &cancellation),
+            Err(CompileError::Cancelled)
+        ));
+        Ok(())
+    }
 }
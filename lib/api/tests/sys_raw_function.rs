@@ -0,0 +1,69 @@
+#[cfg(feature = "sys")]
+mod sys {
+    use anyhow::Result;
+    use std::ffi::c_void;
+    use std::mem;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+    (func (export "mix") (param i32 i64 f32 f64) (result f64)
+        (f64.add
+            (f64.add (f64.convert_i32_s (local.get 0)) (f64.convert_i64_s (local.get 1)))
+            (f64.add (f64.promote_f32 (local.get 2)) (local.get 3))))
+    (func (export "swap") (param i32 i64) (result i64 i32)
+        (local.get 1) (local.get 0))
+    (func (export "fail") (param i32) (result i32)
+        (unreachable)))"#;
+
+    fn raw(instance: &Instance, name: &str) -> Result<RawFunction> {
+        Ok(RawFunction::new(instance.exports.get_function(name)?).unwrap())
+    }
+
+    #[test]
+    fn native_entry_points_are_called_directly() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let mix = raw(&instance, "mix")?;
+        assert_eq!(
+            mix.ty(),
+            &FunctionType::new([Type::I32, Type::I64, Type::F32, Type::F64], [Type::F64])
+        );
+
+        let native: extern "C" fn(*mut c_void, i32, i64, f32, f64) -> f64 =
+            unsafe { mem::transmute(mix.address()) };
+        let result = unsafe { mix.enter(|| native(mix.vmctx(), 1, 2, 0.5, 0.25)) }?;
+        assert_eq!(result, 3.75);
+
+        // Traps are caught and reported as errors.
+        let fail = raw(&instance, "fail")?;
+        let native: extern "C" fn(*mut c_void, i32) -> i32 =
+            unsafe { mem::transmute(fail.address()) };
+        let error = unsafe { fail.enter(|| native(fail.vmctx(), 0)) }.unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::UnreachableCodeReached));
+        Ok(())
+    }
+
+    #[test]
+    fn call_trampolines_take_values_arrays() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let swap = raw(&instance, "swap")?;
+        drop(instance);
+
+        // The function keeps its instance alive.
+        let mut values = [7u128, 42];
+        unsafe { swap.call_values(values.as_mut_ptr()) }?;
+        assert_eq!(values[0] as i64, 42);
+        assert_eq!(values[1] as i32, 7);
+        Ok(())
+    }
+
+    #[test]
+    fn host_functions_have_no_raw_entry_points() {
+        let store = Store::default();
+        let function = Function::new_native(&store, |a: i32| a);
+        assert!(RawFunction::new(&function).is_none());
+    }
+}
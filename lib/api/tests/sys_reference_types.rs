@@ -497,4 +497,166 @@ mod sys {
 
         Ok(())
     }
+
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    #[test]
+    fn extern_ref_host_tables() -> Result<()> {
+        let store = Store::default();
+        let er1 = ExternRef::new(3usize);
+        let er2 = ExternRef::new(5usize);
+        let table_type = TableType::new(Type::ExternRef, 2, Some(8));
+
+        let table = Table::new(&store, table_type, Val::ExternRef(er1.clone()))?;
+        assert_eq!(*table.ty(), table_type);
+        assert_eq!(er1.strong_count(), 3);
+
+        assert_eq!(table.grow(4, Val::ExternRef(er2.clone()))?, 2);
+        assert_eq!(table.size(), 6);
+        assert_eq!(er2.strong_count(), 5);
+        assert!(table.grow(4, Val::ExternRef(er2.clone())).is_err());
+        assert_eq!(er2.strong_count(), 5);
+
+        let element = table.get(4).unwrap().unwrap_externref();
+        assert_eq!(*element.downcast::<usize>().unwrap(), 5);
+        drop(element);
+
+        table.set(0, Val::ExternRef(er2.clone()))?;
+        assert_eq!(er1.strong_count(), 2);
+        assert_eq!(er2.strong_count(), 6);
+
+        // Only `externref`s can be stored.
+        let f = Function::new_native(&store, || {});
+        assert!(table.set(1, Val::FuncRef(Some(f.clone()))).is_err());
+        assert!(table.grow(1, Val::FuncRef(None)).is_err());
+        assert!(Table::new(&store, table_type, Val::FuncRef(Some(f))).is_err());
+        assert_eq!(table.size(), 6);
+
+        // Dropping the table releases its elements.
+        drop(table);
+        assert_eq!(er1.strong_count(), 1);
+        assert_eq!(er2.strong_count(), 1);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    #[test]
+    fn extern_ref_tables_are_released_with_their_instance() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+    (table $table (export "table") 0 externref)
+    (func (export "grow") (param $er externref) (param $delta i32) (result i32)
+          (table.grow $table (local.get $er) (local.get $delta))))"#;
+        let module = Module::new(&store, wat)?;
+        let er = ExternRef::new("host value".to_string());
+        {
+            let instance = Instance::new(&module, &imports! {})?;
+            let grow: NativeFunc<(ExternRef, i32), i32> =
+                instance.exports.get_native_function("grow")?;
+            assert_eq!(grow.call(er.clone(), 3)?, 0);
+            assert_eq!(er.strong_count(), 4);
+
+            let table = instance.exports.get_table("table")?;
+            assert_eq!(table.grow(2, Val::ExternRef(er.clone()))?, 3);
+            assert_eq!(er.strong_count(), 6);
+        }
+        assert_eq!(er.strong_count(), 1);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    #[test]
+    fn extern_ref_tables_across_stores() -> Result<()> {
+        let store1 = Store::default();
+        let store2 = Store::default();
+        let er = ExternRef::new(7usize);
+        let table_type = TableType::new(Type::ExternRef, 4, None);
+        let table1 = Table::new(&store1, table_type, Val::ExternRef(er.clone()))?;
+        let table2 = Table::new(&store2, table_type, Val::ExternRef(ExternRef::null()))?;
+        assert_eq!(er.strong_count(), 5);
+
+        // `externref`s aren't tied to a store.
+        table2.set(3, table1.get(0).unwrap())?;
+        Table::copy(&table2, 0, &table1, 1, 2)?;
+        assert_eq!(er.strong_count(), 8);
+        assert!(table2.get(2).unwrap().unwrap_externref().is_null());
+        assert_eq!(
+            *table2
+                .get(3)
+                .unwrap()
+                .unwrap_externref()
+                .downcast::<usize>()
+                .unwrap(),
+            7
+        );
+
+        // Functions are.
+        let funcref_type = TableType::new(Type::FuncRef, 1, None);
+        let funcs1 = Table::new(&store1, funcref_type, Val::FuncRef(None))?;
+        let funcs2 = Table::new(&store2, funcref_type, Val::FuncRef(None))?;
+        assert!(Table::copy(&funcs2, 0, &funcs1, 0, 1).is_err());
+        assert!(Table::copy(&funcs2, 0, &table1, 0, 1).is_err());
+
+        drop(table1);
+        assert_eq!(er.strong_count(), 4);
+        drop(table2);
+        assert_eq!(er.strong_count(), 1);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    #[test]
+    fn extern_ref_finalizers() -> Result<()> {
+        use std::sync::atomic::AtomicUsize;
+        use std::sync::Mutex;
+
+        let store = Store::default();
+        let wat = r#"(module
+    (global $global (mut externref) (ref.null extern))
+    (func (export "keep") (param $er externref)
+          (global.set $global (local.get $er)))
+    (func (export "release")
+          (global.set $global (ref.null extern))))"#;
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let keep: NativeFunc<ExternRef, ()> = instance.exports.get_native_function("keep")?;
+        let release: NativeFunc<(), ()> = instance.exports.get_native_function("release")?;
+
+        // The VM drops the last reference.
+        let finalized = Arc::new(AtomicUsize::new(0));
+        let finalized2 = finalized.clone();
+        let er = ExternRef::with_finalizer(7usize, move |value| {
+            finalized2.store(value, Ordering::SeqCst);
+        });
+        keep.call(er.clone())?;
+        drop(er);
+        assert_eq!(finalized.load(Ordering::SeqCst), 0);
+        release.call()?;
+        assert_eq!(finalized.load(Ordering::SeqCst), 7);
+
+        // The store hook gets the values made through the store.
+        let released = Arc::new(Mutex::new(vec![]));
+        let released2 = released.clone();
+        store.set_extern_ref_finalizer(Some(Box::new(move |value| {
+            released2
+                .lock()
+                .unwrap()
+                .push(*value.downcast::<&'static str>().unwrap());
+        })));
+        let er = store.new_extern_ref("handle");
+        assert_eq!(*er.downcast::<&'static str>().unwrap(), "handle");
+        keep.call(er)?;
+        assert!(released.lock().unwrap().is_empty());
+        release.call()?;
+        assert_eq!(*released.lock().unwrap(), vec!["handle"]);
+
+        // Values released without a hook are dropped.
+        store.set_extern_ref_finalizer(None);
+        drop(store.new_extern_ref("dropped"));
+        assert_eq!(released.lock().unwrap().len(), 1);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,182 @@
+#[cfg(feature = "sys")]
+mod sys {
+    use anyhow::Result;
+    use std::sync::{Arc, Mutex};
+    use wasmer::*;
+
+    /// Allows growing up to `limit` elements or pages, and records what
+    /// it was asked.
+    #[derive(Default)]
+    struct Limiter {
+        limit: u32,
+        tables: Mutex<Vec<(u32, u32, Option<u32>)>>,
+        memories: Mutex<Vec<(Pages, Pages, Option<Pages>)>>,
+    }
+
+    impl Limiter {
+        fn new(limit: u32) -> Arc<Self> {
+            Arc::new(Self {
+                limit,
+                ..Self::default()
+            })
+        }
+    }
+
+    impl ResourceLimiter for Limiter {
+        fn memory_growing(&self, current: Pages, desired: Pages, maximum: Option<Pages>) -> bool {
+            self.memories
+                .lock()
+                .unwrap()
+                .push((current, desired, maximum));
+            desired.0 <= self.limit
+        }
+
+        fn table_growing(&self, current: u32, desired: u32, maximum: Option<u32>) -> bool {
+            self.tables
+                .lock()
+                .unwrap()
+                .push((current, desired, maximum));
+            desired <= self.limit
+        }
+    }
+
+    const WAT: &str = r#"(module
+    (table (export "table") 1 10 funcref)
+    (memory (export "memory") 1)
+    (func (export "table_grow") (param i32) (result i32)
+        (table.grow (ref.null func) (local.get 0)))
+    (func (export "memory_grow") (param i32) (result i32)
+        (memory.grow (local.get 0))))"#;
+
+    #[test]
+    fn limiter_vetoes_wasm_table_growth() -> Result<()> {
+        let store = Store::default();
+        let limiter = Limiter::new(4);
+        store.set_resource_limiter(Some(limiter.clone()));
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let table_grow = instance
+            .exports
+            .get_native_function::<i32, i32>("table_grow")?;
+        let table = instance.exports.get_table("table")?;
+
+        assert_eq!(table_grow.call(3)?, 1);
+        assert_eq!(table.size(), 4);
+        assert_eq!(table_grow.call(1)?, -1);
+        assert_eq!(table.size(), 4);
+        // Growing by nothing isn't a growth.
+        assert_eq!(table_grow.call(0)?, 4);
+        assert_eq!(
+            *limiter.tables.lock().unwrap(),
+            vec![(1, 4, Some(10)), (4, 5, Some(10))]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn limiter_vetoes_host_table_growth() -> Result<()> {
+        let store = Store::default();
+        let limiter = Limiter::new(2);
+        store.set_resource_limiter(Some(limiter.clone()));
+        let table = Table::new(
+            &store,
+            TableType::new(ValType::FuncRef, 0, None),
+            Value::FuncRef(None),
+        )?;
+
+        assert_eq!(table.grow(2, Value::FuncRef(None))?, 0);
+        assert!(table.grow(1, Value::FuncRef(None)).is_err());
+        assert_eq!(table.size(), 2);
+        assert_eq!(
+            *limiter.tables.lock().unwrap(),
+            vec![(0, 2, None), (2, 3, None)]
+        );
+
+        // Tables created before the limiter was removed follow it.
+        store.set_resource_limiter(None);
+        assert_eq!(table.grow(1, Value::FuncRef(None))?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn limiter_vetoes_memory_growth() -> Result<()> {
+        let store = Store::default();
+        let limiter = Limiter::new(2);
+        store.set_resource_limiter(Some(limiter.clone()));
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let memory_grow = instance
+            .exports
+            .get_native_function::<i32, i32>("memory_grow")?;
+        let memory = instance.exports.get_memory("memory")?;
+
+        assert_eq!(memory_grow.call(2)?, -1);
+        assert_eq!(memory_grow.call(1)?, 1);
+        assert!(memory.grow(1).is_err());
+        assert_eq!(memory.size(), Pages(2));
+        assert_eq!(
+            *limiter.memories.lock().unwrap(),
+            vec![
+                (Pages(1), Pages(3), None),
+                (Pages(1), Pages(2), None),
+                (Pages(2), Pages(3), None)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_limit_clamps_memory_growth() -> Result<()> {
+        let store = Store::default();
+        store.set_memory_limit(Some(Pages(3).into()));
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let memory_grow = instance
+            .exports
+            .get_native_function::<i32, i32>("memory_grow")?;
+        let memory = instance.exports.get_memory("memory")?;
+
+        assert_eq!(memory_grow.call(3)?, -1);
+        assert_eq!(memory_grow.call(2)?, 1);
+        assert!(memory.grow(1).is_err());
+        assert_eq!(memory.size(), Pages(3));
+
+        // The limiter is only consulted for growths below the limit.
+        let limiter = Limiter::new(2);
+        store.set_resource_limiter(Some(limiter.clone()));
+        store.set_memory_limit(Some(Bytes(4 * 65536 + 1)));
+        assert_eq!(memory_grow.call(2)?, -1);
+        assert_eq!(memory_grow.call(1)?, -1);
+        assert_eq!(
+            *limiter.memories.lock().unwrap(),
+            vec![(Pages(3), Pages(4), None)]
+        );
+
+        store.set_resource_limiter(None);
+        store.set_memory_limit(None);
+        assert_eq!(memory_grow.call(2)?, 3);
+        assert_eq!(memory.size(), Pages(5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn limited_memory_commits_pages() -> Result<()> {
+        let store = Store::default();
+        store.set_resource_limiter(Some(Limiter::new(2)));
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let memory = instance.exports.get_memory("memory")?;
+        let page_size = Pages(1).bytes().0 as u64;
+
+        memory.commit(0..page_size)?;
+        #[cfg(unix)]
+        assert!(memory.resident_size()? >= Pages(1).bytes());
+        assert!(memory.commit(page_size..2 * page_size).is_err());
+
+        Ok(())
+    }
+}
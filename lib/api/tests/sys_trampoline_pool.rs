@@ -0,0 +1,139 @@
+#[cfg(feature = "sys")]
+mod sys {
+    use anyhow::Result;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::thread;
+    use wasmer::*;
+
+    /// Counts the allocations made by the current thread.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            ALLOCATIONS.with(|count| count.set(count.get() + 1));
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn allocations() -> usize {
+        ALLOCATIONS.with(|count| count.get())
+    }
+
+    const WAT: &str = r#"(module
+    (table (export "table") 2 funcref)
+    (elem (i32.const 0) $mix $twice)
+    (func $mix (export "mix") (param i32 f64 i64) (result f64)
+        (f64.add
+            (f64.add (f64.convert_i32_s (local.get 0)) (local.get 1))
+            (f64.convert_i64_s (local.get 2))))
+    (func $twice (param i32) (result i32 i32)
+        (local.get 0) (local.get 0))
+    (func (export "trap") (unreachable)))"#;
+
+    #[test]
+    fn pooled_calls_dont_allocate() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let pool = TrampolinePool::new(
+            &store,
+            &[
+                FunctionType::new([Type::I32, Type::F64, Type::I64], [Type::F64]),
+                FunctionType::new([Type::I32], [Type::I32, Type::I32]),
+            ],
+        )?;
+        let mut mix =
+            pool.prepare::<(i32, f64, i64), f64>(instance.exports.get_function("mix")?)?;
+
+        thread::spawn(move || {
+            TrampolinePool::init_thread().unwrap();
+            let before = allocations();
+            for i in 0..100 {
+                assert_eq!(mix.call(i, 0.5, 1).unwrap(), i as f64 + 1.5);
+            }
+            assert_eq!(allocations(), before);
+        })
+        .join()
+        .unwrap();
+
+        Ok(())
+    }
+
+    #[test]
+    fn pooled_calls_of_table_elements() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let table = instance.exports.get_table("table")?;
+        let twice_type = FunctionType::new([Type::I32], [Type::I32, Type::I32]);
+        let pool = TrampolinePool::new(&store, &[twice_type.clone()])?;
+        assert!(pool.contains(&twice_type));
+        assert!(!pool.contains(&FunctionType::new([], [])));
+
+        let twice = match table.get(1) {
+            Some(Val::FuncRef(Some(function))) => function,
+            _ => panic!("expected a function"),
+        };
+        let mut twice = pool.prepare::<i32, (i32, i32)>(&twice)?;
+        assert_eq!(twice.call(7)?, (7, 7));
+
+        let mix = match table.get(0) {
+            Some(Val::FuncRef(Some(function))) => function,
+            _ => panic!("expected a function"),
+        };
+        assert!(pool.prepare::<(i32, f64, i64), f64>(&mix).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn pooled_calls_of_host_functions() -> Result<()> {
+        let store = Store::default();
+        let pool = TrampolinePool::new(&store, &[FunctionType::new([Type::I32], [Type::I32])])?;
+        let add_one = Function::new_native(&store, |x: i32| x + 1);
+        let mut add_one = pool.prepare::<i32, i32>(&add_one)?;
+        assert_eq!(add_one.call(41)?, 42);
+
+        // Dynamic host functions have no Wasm ABI address to call.
+        let add_one = Function::new(
+            &store,
+            FunctionType::new([Type::I32], [Type::I32]),
+            |args| Ok(vec![Val::I32(args[0].unwrap_i32() + 1)]),
+        );
+        assert!(pool.prepare::<i32, i32>(&add_one).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn pooled_calls_report_traps() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let pool = TrampolinePool::new(&store, &[])?;
+        let mut trap = pool.prepare::<(), ()>(instance.exports.get_function("trap")?)?;
+        let error = trap.call().unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::UnreachableCodeReached));
+        // The stack can be used again after a trap.
+        assert!(trap.call().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+#[cfg(all(feature = "sys", feature = "wit"))]
+mod sys {
+    use anyhow::Result;
+    use wasmer::*;
+
+    wasmer::import!("tests/wit/greeter.wit");
+
+    const GUEST: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 1024))
+  (func (export "canonical_abi_realloc")
+    (param $old_ptr i32) (param $old_size i32) (param $align i32) (param $new_size i32)
+    (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $heap))
+    (global.set $heap (i32.add (global.get $heap) (local.get $new_size)))
+    (local.get $ptr))
+  (func (export "canonical_abi_free") (param i32 i32 i32))
+  (func (export "add") (param $a i32) (param $b i32) (result i32)
+    (i32.add (local.get $a) (local.get $b)))
+  (func (export "byte-len") (param $ptr i32) (param $len i32) (result i32)
+    (local.get $len))
+  (func (export "echo") (param $ptr i32) (param $len i32) (result i32)
+    (i32.store (i32.const 16) (local.get $ptr))
+    (i32.store (i32.const 20) (local.get $len))
+    (i32.const 16)))
+"#;
+
+    #[test]
+    fn wit_bindings_lower_and_lift_values() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, GUEST)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let greeter = Greeter::new(&instance)?;
+
+        assert_eq!(greeter.add(40, 2)?, 42);
+        assert_eq!(greeter.byte_len("héllo")?, 6);
+        assert_eq!(greeter.echo("hello, wasmer")?, "hello, wasmer");
+
+        Ok(())
+    }
+}
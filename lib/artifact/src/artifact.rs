@@ -3,9 +3,9 @@ use enumset::EnumSet;
 use loupe::MemoryUsage;
 use std::any::Any;
 use std::convert::TryInto;
+use std::fs;
 use std::path::Path;
 use std::sync::Arc;
-use std::{fs, mem};
 use wasmer_compiler::{CpuFeature, Features};
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{
@@ -98,10 +98,14 @@ impl dyn ArtifactCreate + 'static {
 
 /// Metadata header which holds an ABI version and the length of the remaining
 /// metadata.
-#[repr(C)]
+///
+/// The header is always encoded in little-endian, whatever the byte order of
+/// the host, and records the byte order of the host that serialized the
+/// metadata, since the metadata itself is archived in native byte order.
 #[derive(Clone, Copy)]
 pub struct MetadataHeader {
-    magic: [u8; 8],
+    magic: [u8; 7],
+    byte_order: u8,
     version: u32,
     len: u32,
 }
@@ -109,10 +113,16 @@ pub struct MetadataHeader {
 impl MetadataHeader {
     /// Current ABI version. Increment this any time breaking changes are made
     /// to the format of the serialized data.
-    const CURRENT_VERSION: u32 = 1;
+    const CURRENT_VERSION: u32 = 2;
 
     /// Magic number to identify wasmer metadata.
-    const MAGIC: [u8; 8] = *b"WASMER\0\0";
+    const MAGIC: [u8; 7] = *b"WASMER\0";
+
+    /// Byte order of metadata serialized by a little-endian host.
+    const LITTLE_ENDIAN: u8 = 0;
+
+    /// Byte order of metadata serialized by a big-endian host.
+    const BIG_ENDIAN: u8 = 1;
 
     /// Length of the metadata header.
     pub const LEN: usize = 16;
@@ -120,14 +130,42 @@ impl MetadataHeader {
     /// Alignment of the metadata.
     pub const ALIGN: usize = 16;
 
+    /// Byte order of the host.
+    fn native_byte_order() -> u8 {
+        if cfg!(target_endian = "big") {
+            Self::BIG_ENDIAN
+        } else {
+            Self::LITTLE_ENDIAN
+        }
+    }
+
     /// Creates a new header for metadata of the given length.
     pub fn new(len: usize) -> [u8; 16] {
         let header = MetadataHeader {
             magic: Self::MAGIC,
+            byte_order: Self::native_byte_order(),
             version: Self::CURRENT_VERSION,
             len: len.try_into().expect("metadata exceeds maximum length"),
         };
-        unsafe { mem::transmute(header) }
+        header.to_bytes()
+    }
+
+    fn to_bytes(self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[..7].copy_from_slice(&self.magic);
+        bytes[7] = self.byte_order;
+        bytes[8..12].copy_from_slice(&self.version.to_le_bytes());
+        bytes[12..].copy_from_slice(&self.len.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: [u8; 16]) -> Self {
+        MetadataHeader {
+            magic: bytes[..7].try_into().unwrap(),
+            byte_order: bytes[7],
+            version: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            len: u32::from_le_bytes(bytes[12..].try_into().unwrap()),
+        }
     }
 
     /// Parses the header and returns the length of the metadata following it.
@@ -144,7 +182,7 @@ impl MetadataHeader {
             })?
             .try_into()
             .unwrap();
-        let header: MetadataHeader = unsafe { mem::transmute(bytes) };
+        let header = MetadataHeader::from_bytes(bytes);
         if header.magic != Self::MAGIC {
             return Err(DeserializeError::Incompatible(
                 "The provided bytes were not serialized by Wasmer".to_string(),
@@ -156,6 +194,43 @@ impl MetadataHeader {
                     .to_string(),
             ));
         }
+        if header.byte_order != Self::native_byte_order() {
+            return Err(DeserializeError::Incompatible(
+                "The provided bytes were serialized on a host with a different byte order"
+                    .to_string(),
+            ));
+        }
         Ok(header.len as usize)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(align(16))]
+    struct Aligned([u8; 16]);
+
+    #[test]
+    fn metadata_header_is_little_endian() {
+        let header = MetadataHeader::new(0x0102_0304);
+        assert_eq!(&header[..7], b"WASMER\0");
+        assert_eq!(header[7], MetadataHeader::native_byte_order());
+        assert_eq!(header[8..12], 2u32.to_le_bytes());
+        assert_eq!(header[12..], [0x04, 0x03, 0x02, 0x01]);
+        assert_eq!(
+            MetadataHeader::parse(&Aligned(header).0).unwrap(),
+            0x0102_0304
+        );
+    }
+
+    #[test]
+    fn metadata_of_another_byte_order_is_incompatible() {
+        let mut header = MetadataHeader::new(16);
+        header[7] ^= 1;
+        assert!(matches!(
+            MetadataHeader::parse(&Aligned(header).0),
+            Err(DeserializeError::Incompatible(_))
+        ));
+    }
+}
@@ -0,0 +1,257 @@
+//! Unstable non-standard Wasmer-specific API to call functions
+//! asynchronously.
+//!
+//! The calls are run by a pool of worker threads, shared by the whole
+//! process and sized after the available parallelism, so that an
+//! embedder doesn't need to dedicate a thread to each call that may
+//! take long.
+
+use super::super::externals::wasm_func_t;
+use super::super::trap::wasm_trap_t;
+use super::super::value::{wasm_val_t, wasm_val_vec_t};
+use lazy_static::lazy_static;
+use std::convert::TryInto;
+use std::ffi::c_void;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use wasmer_api::{RuntimeError, Val};
+
+/// The completion callback of [`wasm_func_call_async`].
+///
+/// It is called from a worker thread with the `env` given to
+/// `wasm_func_call_async`, the results of the call, which are only
+/// borrowed for the duration of the callback, and the trap the call
+/// ended with, if any, which the callback owns.
+#[allow(non_camel_case_types)]
+pub type wasm_func_async_callback_t = unsafe extern "C" fn(
+    env: *mut c_void,
+    results: &wasm_val_vec_t,
+    trap: Option<Box<wasm_trap_t>>,
+);
+
+/// Opaque type representing a cancellation token for asynchronous
+/// calls.
+///
+/// A call whose token is cancelled before it starts doesn't run, and
+/// completes with a trap. A call which already started runs to its end.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Default)]
+pub struct wasmer_cancellation_token_t {
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Creates a new cancellation token.
+///
+/// # Example
+///
+/// See [`wasm_func_call_async`].
+#[no_mangle]
+pub extern "C" fn wasmer_cancellation_token_new() -> Box<wasmer_cancellation_token_t> {
+    Box::new(wasmer_cancellation_token_t::default())
+}
+
+/// Cancels the calls using this token that didn't start yet.
+#[no_mangle]
+pub extern "C" fn wasmer_cancellation_token_cancel(token: Option<&wasmer_cancellation_token_t>) {
+    if let Some(token) = token {
+        token.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Returns whether the token has been cancelled.
+#[no_mangle]
+pub extern "C" fn wasmer_cancellation_token_is_cancelled(
+    token: Option<&wasmer_cancellation_token_t>,
+) -> bool {
+    token.map_or(false, |token| token.cancelled.load(Ordering::SeqCst))
+}
+
+/// Deletes a cancellation token. The calls using it are not affected.
+#[no_mangle]
+pub extern "C" fn wasmer_cancellation_token_delete(
+    _token: Option<Box<wasmer_cancellation_token_t>>,
+) {
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+lazy_static! {
+    static ref POOL: Mutex<Sender<Job>> = {
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = thread::available_parallelism().map_or(1, |n| n.get());
+        for index in 0..workers {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("wasmer-async-call-{}", index))
+                .spawn(move || work(&receiver))
+                .expect("failed to spawn an asynchronous call worker");
+        }
+        Mutex::new(sender)
+    };
+}
+
+fn work(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = match receiver.lock().unwrap_or_else(|e| e.into_inner()).recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        // Keep the worker alive if a completion callback panics.
+        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+    }
+}
+
+/// The environment of a completion callback, sent to the worker.
+struct CallbackEnv(*mut c_void);
+
+unsafe impl Send for CallbackEnv {}
+
+/// The arguments of a call, sent to the worker.
+struct Params(Vec<Val>);
+
+// The C API only converts numbers to `Val`s, references are not
+// supported, so the arguments are plain data.
+unsafe impl Send for Params {}
+
+/// Unstable non-standard Wasmer-specific API to call a function
+/// without blocking the current thread.
+///
+/// The call is queued to be run by a worker thread, which calls
+/// `callback` with `env` once it completes. If `token` is not null and
+/// it is cancelled before the call starts, the function isn't called
+/// and `callback` gets a trap. It also gets one if the call panics, or
+/// if its results can't be represented as `wasm_val_t`s.
+///
+/// Returns `false` if the call couldn't be queued, in which case
+/// `callback` is never called.
+///
+/// The function, the module it comes from and the arguments may be
+/// deleted as soon as this function returns. `env` must stay valid
+/// until `callback` is called.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// # #include <stdatomic.h>
+/// #
+/// void on_completion(void* env, const wasm_val_vec_t* results, wasm_trap_t* trap) {
+///     assert(trap == NULL);
+///     assert(results->size == 1);
+///     assert(results->data[0].of.i32 == 3);
+///     atomic_store((atomic_int*) env, 1);
+/// }
+///
+/// int main() {
+///     // Create the engine and the store.
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create a WebAssembly module from a WAT definition.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (func (export \"sum\") (param i32 i32) (result i32)\n"
+///         "    local.get 0 local.get 1 i32.add))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     // Instantiate the module and get the function.
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+///     assert(instance);
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     const wasm_func_t* sum = wasm_extern_as_func(exports.data[0]);
+///
+///     // Call it asynchronously.
+///     wasm_val_t arguments[2] = { WASM_I32_VAL(1), WASM_I32_VAL(2) };
+///     wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+///     wasmer_cancellation_token_t* token = wasmer_cancellation_token_new();
+///     atomic_int done = 0;
+///     assert(wasm_func_call_async(sum, &arguments_as_array, on_completion, &done, token));
+///
+///     // Wait for the call to complete.
+///     while (!atomic_load(&done)) {}
+///
+///     // Free everything.
+///     wasmer_cancellation_token_delete(token);
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasm_func_call_async(
+    func: Option<&wasm_func_t>,
+    args: Option<&wasm_val_vec_t>,
+    callback: Option<wasm_func_async_callback_t>,
+    env: *mut c_void,
+    token: Option<&wasmer_cancellation_token_t>,
+) -> bool {
+    let (func, args, callback) = match (func, args, callback) {
+        (Some(func), Some(args), Some(callback)) => (func, args, callback),
+        _ => return false,
+    };
+    let params = match args
+        .as_slice()
+        .iter()
+        .cloned()
+        .map(TryInto::try_into)
+        .collect::<Result<Vec<Val>, _>>()
+    {
+        Ok(params) => Params(params),
+        Err(_) => return false,
+    };
+    let function = (*func.inner).clone();
+    let cancelled = token.map(|token| token.cancelled.clone());
+    let env = CallbackEnv(env);
+
+    let job: Job = Box::new(move || {
+        // A panic must not leave the caller waiting for a callback that
+        // never comes, so it's reported as a trap.
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+            let values = match cancelled {
+                Some(cancelled) if cancelled.load(Ordering::SeqCst) => {
+                    return Err(RuntimeError::new("the call has been cancelled"))
+                }
+                _ => function.call(&params.0)?,
+            };
+            values
+                .iter()
+                .map(TryInto::try_into)
+                .collect::<Result<Vec<wasm_val_t>, _>>()
+                .map_err(|error| {
+                    RuntimeError::new(format!("the results can't be returned: {}", error))
+                })
+        }))
+        .unwrap_or_else(|_| Err(RuntimeError::new("the call panicked")));
+        let (results, trap) = match outcome {
+            Ok(results) => (results, None),
+            Err(error) => (Vec::new(), Some(Box::new(error.into()))),
+        };
+        callback(env.0, &results.into(), trap);
+    });
+
+    POOL.lock().unwrap().send(job).is_ok()
+}
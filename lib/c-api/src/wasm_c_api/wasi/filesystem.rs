@@ -0,0 +1,589 @@
+//! A WASI filesystem implemented by callbacks of the embedder.
+
+use super::super::externals::wasm_env_finalizer_t;
+use super::wasi_config_t;
+use std::ffi::{c_void, CStr, CString};
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use wasmer_vfs::{
+    DirEntry, FileOpener, FileSystem, FileType, FsError, Metadata, OpenOptions, OpenOptionsConfig,
+    ReadDir, Result, VirtualFile,
+};
+
+/// The file is opened for reading.
+pub const WASI_FILESYSTEM_OPEN_READ: u32 = 1 << 0;
+/// The file is opened for writing.
+pub const WASI_FILESYSTEM_OPEN_WRITE: u32 = 1 << 1;
+/// The file is created if it doesn't exist.
+pub const WASI_FILESYSTEM_OPEN_CREATE: u32 = 1 << 2;
+/// The file is created, and opening fails if it already exists.
+pub const WASI_FILESYSTEM_OPEN_CREATE_NEW: u32 = 1 << 3;
+/// Writes append to the end of the file.
+pub const WASI_FILESYSTEM_OPEN_APPEND: u32 = 1 << 4;
+/// The file is truncated to 0 bytes.
+pub const WASI_FILESYSTEM_OPEN_TRUNCATE: u32 = 1 << 5;
+
+/// The operation succeeded.
+pub const WASI_FILESYSTEM_OK: u32 = 0;
+/// The file or directory doesn't exist.
+pub const WASI_FILESYSTEM_NOT_FOUND: u32 = 1;
+/// The operation is not allowed.
+pub const WASI_FILESYSTEM_PERMISSION_DENIED: u32 = 2;
+/// The file or directory already exists.
+pub const WASI_FILESYSTEM_ALREADY_EXISTS: u32 = 3;
+/// A directory was expected.
+pub const WASI_FILESYSTEM_NOT_A_DIRECTORY: u32 = 4;
+/// A file was expected.
+pub const WASI_FILESYSTEM_NOT_A_FILE: u32 = 5;
+/// Any other failure.
+pub const WASI_FILESYSTEM_IO_ERROR: u32 = 6;
+
+/// What the filesystem callbacks tell about a file or a directory.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Default, Clone, Copy)]
+#[repr(C)]
+pub struct wasi_filesystem_stat_t {
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+/// Called by the `readdir` callback for each entry of the directory.
+#[allow(non_camel_case_types)]
+pub type wasi_filesystem_readdir_entry_t =
+    unsafe extern "C" fn(entries: *mut c_void, name: *const c_char, stat: &wasi_filesystem_stat_t);
+
+/// The callbacks implementing a WASI filesystem.
+///
+/// Each callback gets the `env` given to
+/// [`wasi_config_set_filesystem`], and returns one of the
+/// `WASI_FILESYSTEM_*` result codes, `WASI_FILESYSTEM_OK` on success.
+/// Paths are null-terminated UTF-8 strings, absolute from the root of
+/// the filesystem.
+///
+/// * `open` opens the file at `path` with the `WASI_FILESYSTEM_OPEN_*`
+///   `flags`, and writes a handle for it to `handle`.
+/// * `close` releases a handle. It's optional.
+/// * `read` reads up to `len` bytes at `offset` of the file into `buffer`,
+///   and writes how many bytes were read to `read`.
+/// * `write` writes `len` bytes of `buffer` at `offset` of the file, and
+///   writes how many bytes were written to `written`.
+/// * `stat` describes the file or directory at `path`.
+/// * `readdir` calls `entry` with `entries` for each entry of the
+///   directory at `path`.
+///
+/// Operations without a callback, like renaming, fail with
+/// `WASI_FILESYSTEM_PERMISSION_DENIED`.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct wasi_filesystem_callbacks_t {
+    pub open: Option<
+        unsafe extern "C" fn(
+            env: *mut c_void,
+            path: *const c_char,
+            flags: u32,
+            handle: &mut u64,
+        ) -> u32,
+    >,
+    pub close: Option<unsafe extern "C" fn(env: *mut c_void, handle: u64)>,
+    pub read: Option<
+        unsafe extern "C" fn(
+            env: *mut c_void,
+            handle: u64,
+            offset: u64,
+            buffer: *mut u8,
+            len: usize,
+            read: &mut usize,
+        ) -> u32,
+    >,
+    pub write: Option<
+        unsafe extern "C" fn(
+            env: *mut c_void,
+            handle: u64,
+            offset: u64,
+            buffer: *const u8,
+            len: usize,
+            written: &mut usize,
+        ) -> u32,
+    >,
+    pub stat: Option<
+        unsafe extern "C" fn(
+            env: *mut c_void,
+            path: *const c_char,
+            stat: &mut wasi_filesystem_stat_t,
+        ) -> u32,
+    >,
+    pub readdir: Option<
+        unsafe extern "C" fn(
+            env: *mut c_void,
+            path: *const c_char,
+            entry: wasi_filesystem_readdir_entry_t,
+            entries: *mut c_void,
+        ) -> u32,
+    >,
+}
+
+/// Makes the WASI program access files through `callbacks` instead of
+/// the host filesystem.
+///
+/// The directories of this filesystem the program can access are given
+/// with [`wasi_config_preopen_vfs_dir`]. `env` is given to every
+/// callback, from any thread, and `env_finalizer` is called with it once
+/// the filesystem is dropped.
+///
+/// Returns `false` if one of the `open`, `read`, `stat` or `readdir`
+/// callbacks is missing.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_config_set_filesystem(
+    config: &mut wasi_config_t,
+    callbacks: Option<&wasi_filesystem_callbacks_t>,
+    env: *mut c_void,
+    env_finalizer: Option<wasm_env_finalizer_t>,
+) -> bool {
+    let callbacks = match callbacks {
+        Some(callbacks) => *callbacks,
+        None => return false,
+    };
+    if callbacks.open.is_none()
+        || callbacks.read.is_none()
+        || callbacks.stat.is_none()
+        || callbacks.readdir.is_none()
+    {
+        return false;
+    }
+
+    config
+        .state_builder
+        .set_fs(Box::new(CallbackFileSystem(Arc::new(Callbacks {
+            callbacks,
+            env,
+            env_finalizer,
+        }))));
+
+    true
+}
+
+/// Gives the WASI program access to the directory `dir` of the
+/// filesystem set with [`wasi_config_set_filesystem`].
+#[no_mangle]
+pub unsafe extern "C" fn wasi_config_preopen_vfs_dir(
+    config: &mut wasi_config_t,
+    dir: *const c_char,
+) -> bool {
+    let dir_str = match CStr::from_ptr(dir).to_str() {
+        Ok(dir_str) => dir_str,
+        Err(e) => {
+            crate::error::update_last_error(e);
+            return false;
+        }
+    };
+
+    if let Err(e) = config
+        .state_builder
+        .preopen_vfs_dirs(std::iter::once(dir_str.to_string()))
+    {
+        crate::error::update_last_error(e);
+        return false;
+    }
+
+    true
+}
+
+struct Callbacks {
+    callbacks: wasi_filesystem_callbacks_t,
+    env: *mut c_void,
+    env_finalizer: Option<wasm_env_finalizer_t>,
+}
+
+// The embedder is responsible for making the callbacks thread-safe.
+unsafe impl Send for Callbacks {}
+unsafe impl Sync for Callbacks {}
+
+impl Drop for Callbacks {
+    fn drop(&mut self) {
+        if let Some(env_finalizer) = self.env_finalizer {
+            unsafe { env_finalizer(self.env) }
+        }
+    }
+}
+
+fn check(code: u32) -> Result<()> {
+    match code {
+        WASI_FILESYSTEM_OK => Ok(()),
+        WASI_FILESYSTEM_NOT_FOUND => Err(FsError::EntityNotFound),
+        WASI_FILESYSTEM_PERMISSION_DENIED => Err(FsError::PermissionDenied),
+        WASI_FILESYSTEM_ALREADY_EXISTS => Err(FsError::AlreadyExists),
+        WASI_FILESYSTEM_NOT_A_DIRECTORY => Err(FsError::BaseNotDirectory),
+        WASI_FILESYSTEM_NOT_A_FILE => Err(FsError::NotAFile),
+        _ => Err(FsError::IOError),
+    }
+}
+
+fn c_path(path: &Path) -> Result<CString> {
+    let path = path.to_str().ok_or(FsError::InvalidInput)?;
+    CString::new(path).map_err(|_| FsError::InvalidInput)
+}
+
+fn metadata(stat: &wasi_filesystem_stat_t) -> Metadata {
+    Metadata {
+        ft: FileType {
+            dir: stat.is_dir,
+            file: !stat.is_dir,
+            ..FileType::default()
+        },
+        len: stat.size,
+        ..Metadata::default()
+    }
+}
+
+impl Callbacks {
+    fn stat(&self, path: &Path) -> Result<wasi_filesystem_stat_t> {
+        let path = c_path(path)?;
+        let mut stat = wasi_filesystem_stat_t::default();
+        check(unsafe { self.callbacks.stat.unwrap()(self.env, path.as_ptr(), &mut stat) })?;
+        Ok(stat)
+    }
+}
+
+#[derive(Clone)]
+struct CallbackFileSystem(Arc<Callbacks>);
+
+impl fmt::Debug for CallbackFileSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallbackFileSystem").finish()
+    }
+}
+
+unsafe extern "C" fn push_entry(
+    entries: *mut c_void,
+    name: *const c_char,
+    stat: &wasi_filesystem_stat_t,
+) {
+    let entries = &mut *(entries as *mut (PathBuf, Vec<DirEntry>));
+    let name = CStr::from_ptr(name).to_string_lossy();
+    let path = entries.0.join(name.as_ref());
+    entries.1.push(DirEntry {
+        path,
+        metadata: Ok(metadata(stat)),
+    });
+}
+
+impl FileSystem for CallbackFileSystem {
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        let c_path = c_path(path)?;
+        let mut entries = (path.to_path_buf(), Vec::new());
+        check(unsafe {
+            self.0.callbacks.readdir.unwrap()(
+                self.0.env,
+                c_path.as_ptr(),
+                push_entry,
+                &mut entries as *mut _ as *mut c_void,
+            )
+        })?;
+        Ok(ReadDir::new(entries.1))
+    }
+
+    fn create_dir(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn remove_dir(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        Ok(metadata(&self.0.stat(path)?))
+    }
+
+    fn remove_file(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(self.clone()))
+    }
+}
+
+impl FileOpener for CallbackFileSystem {
+    fn open(&mut self, path: &Path, conf: &OpenOptionsConfig) -> Result<Box<dyn VirtualFile>> {
+        let flags = [
+            (conf.read(), WASI_FILESYSTEM_OPEN_READ),
+            (conf.write(), WASI_FILESYSTEM_OPEN_WRITE),
+            (conf.create(), WASI_FILESYSTEM_OPEN_CREATE),
+            (conf.create_new(), WASI_FILESYSTEM_OPEN_CREATE_NEW),
+            (conf.append(), WASI_FILESYSTEM_OPEN_APPEND),
+            (conf.truncate(), WASI_FILESYSTEM_OPEN_TRUNCATE),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |flags, (_, flag)| flags | flag);
+        let c_path = c_path(path)?;
+        let mut handle = 0;
+        check(unsafe {
+            self.0.callbacks.open.unwrap()(self.0.env, c_path.as_ptr(), flags, &mut handle)
+        })?;
+        let mut file = CallbackFile {
+            callbacks: self.0.clone(),
+            path: path.to_path_buf(),
+            handle,
+            position: 0,
+        };
+        if conf.append() {
+            file.position = file.size();
+        }
+        Ok(Box::new(file))
+    }
+}
+
+struct CallbackFile {
+    callbacks: Arc<Callbacks>,
+    path: PathBuf,
+    handle: u64,
+    position: u64,
+}
+
+impl fmt::Debug for CallbackFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallbackFile")
+            .field("path", &self.path)
+            .field("handle", &self.handle)
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+impl Drop for CallbackFile {
+    fn drop(&mut self) {
+        if let Some(close) = self.callbacks.callbacks.close {
+            unsafe { close(self.callbacks.env, self.handle) }
+        }
+    }
+}
+
+fn io_error(error: FsError) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, error)
+}
+
+impl Read for CallbackFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut read = 0;
+        check(unsafe {
+            self.callbacks.callbacks.read.unwrap()(
+                self.callbacks.env,
+                self.handle,
+                self.position,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut read,
+            )
+        })
+        .map_err(io_error)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Write for CallbackFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let write = self
+            .callbacks
+            .callbacks
+            .write
+            .ok_or_else(|| io_error(FsError::PermissionDenied))?;
+        let mut written = 0;
+        check(unsafe {
+            write(
+                self.callbacks.env,
+                self.handle,
+                self.position,
+                buf.as_ptr(),
+                buf.len(),
+                &mut written,
+            )
+        })
+        .map_err(io_error)?;
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for CallbackFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => {
+                self.position = offset;
+                return Ok(offset);
+            }
+            SeekFrom::End(offset) => (
+                self.callbacks.stat(&self.path).map_err(io_error)?.size,
+                offset,
+            ),
+            SeekFrom::Current(offset) => (self.position, offset),
+        };
+        let position = (base as i64)
+            .checked_add(offset)
+            .filter(|position| *position >= 0)
+            .ok_or_else(|| io_error(FsError::InvalidInput))?;
+        self.position = position as u64;
+        Ok(self.position)
+    }
+}
+
+impl VirtualFile for CallbackFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+
+    fn last_modified(&self) -> u64 {
+        0
+    }
+
+    fn created_time(&self) -> u64 {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        self.callbacks.stat(&self.path).map_or(0, |stat| stat.size)
+    }
+
+    fn set_len(&mut self, _new_size: u64) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn bytes_available(&self) -> Result<usize> {
+        Ok(self.size().saturating_sub(self.position) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &[u8] = b"hello";
+
+    unsafe extern "C" fn open(
+        _env: *mut c_void,
+        path: *const c_char,
+        flags: u32,
+        handle: &mut u64,
+    ) -> u32 {
+        if CStr::from_ptr(path).to_bytes() != b"/data/hello.txt" {
+            return WASI_FILESYSTEM_NOT_FOUND;
+        }
+        if flags & WASI_FILESYSTEM_OPEN_WRITE != 0 {
+            return WASI_FILESYSTEM_PERMISSION_DENIED;
+        }
+        *handle = 42;
+        WASI_FILESYSTEM_OK
+    }
+
+    unsafe extern "C" fn read(
+        _env: *mut c_void,
+        handle: u64,
+        offset: u64,
+        buffer: *mut u8,
+        len: usize,
+        read: &mut usize,
+    ) -> u32 {
+        assert_eq!(handle, 42);
+        let rest = &CONTENT[(offset as usize).min(CONTENT.len())..];
+        *read = rest.len().min(len);
+        std::ptr::copy_nonoverlapping(rest.as_ptr(), buffer, *read);
+        WASI_FILESYSTEM_OK
+    }
+
+    unsafe extern "C" fn stat(
+        _env: *mut c_void,
+        path: *const c_char,
+        stat: &mut wasi_filesystem_stat_t,
+    ) -> u32 {
+        match CStr::from_ptr(path).to_bytes() {
+            b"/data" => stat.is_dir = true,
+            b"/data/hello.txt" => stat.size = CONTENT.len() as u64,
+            _ => return WASI_FILESYSTEM_NOT_FOUND,
+        }
+        WASI_FILESYSTEM_OK
+    }
+
+    unsafe extern "C" fn readdir(
+        _env: *mut c_void,
+        path: *const c_char,
+        entry: wasi_filesystem_readdir_entry_t,
+        entries: *mut c_void,
+    ) -> u32 {
+        if CStr::from_ptr(path).to_bytes() != b"/data" {
+            return WASI_FILESYSTEM_NOT_A_DIRECTORY;
+        }
+        let stat = wasi_filesystem_stat_t {
+            is_dir: false,
+            size: CONTENT.len() as u64,
+        };
+        entry(entries, b"hello.txt\0".as_ptr() as *const c_char, &stat);
+        WASI_FILESYSTEM_OK
+    }
+
+    #[test]
+    fn callbacks_implement_the_filesystem() {
+        let fs = CallbackFileSystem(Arc::new(Callbacks {
+            callbacks: wasi_filesystem_callbacks_t {
+                open: Some(open),
+                close: None,
+                read: Some(read),
+                write: None,
+                stat: Some(stat),
+                readdir: Some(readdir),
+            },
+            env: std::ptr::null_mut(),
+            env_finalizer: None,
+        }));
+
+        let entries = fs
+            .read_dir(Path::new("/data"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(entries, vec![PathBuf::from("/data/hello.txt")]);
+        assert!(fs.metadata(Path::new("/data")).unwrap().is_dir());
+        assert_eq!(
+            fs.metadata(Path::new("/missing")).unwrap_err(),
+            FsError::EntityNotFound
+        );
+
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .open("/data/hello.txt")
+            .unwrap();
+        let mut content = String::new();
+        file.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "hello");
+        assert_eq!(file.seek(SeekFrom::End(-2)).unwrap(), 3);
+        content.clear();
+        file.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "lo");
+        assert!(file.write(b"!").is_err());
+
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .open("/data/hello.txt")
+                .unwrap_err(),
+            FsError::PermissionDenied
+        );
+    }
+}
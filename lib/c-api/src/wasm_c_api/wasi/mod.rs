@@ -3,7 +3,9 @@
 //! This API will be superseded by a standard WASI API when/if such a standard is created.
 
 mod capture_files;
+mod filesystem;
 
+pub use self::filesystem::*;
 pub use super::unstable::wasi::wasi_get_unordered_imports;
 use super::{
     externals::{wasm_extern_vec_t, wasm_func_t},
@@ -0,0 +1,266 @@
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+use wasmer::{DeserializeError, Module, SerializeError, Store};
+
+/// The magic header of a serialized [`ArtifactBundle`].
+const MAGIC_HEADER: &[u8; 16] = b"\0wasmer-bundle\0\0";
+
+/// The version of the serialized [`ArtifactBundle`] format.
+const VERSION: u32 = 1;
+
+/// A set of serialized modules, stored under a name, shipped together
+/// in a single file (conventionally with a `.wasmu-bundle` extension)
+/// along with some free-form metadata.
+///
+/// Bundles are useful to ship a runtime with a fixed set of
+/// precompiled plugins or libraries: they are compiled once, put in a
+/// bundle, and later loaded by name without any compilation.
+///
+/// As for any serialized module, the modules of a bundle can only be
+/// loaded by a [`Store`] whose engine is compatible with the one that
+/// produced them.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{DeserializeError, Module, SerializeError, Store};
+/// use wasmer_cache::ArtifactBundle;
+///
+/// fn make_bundle(plugins: &[(&str, Module)]) -> Result<Vec<u8>, SerializeError> {
+///     let mut bundle = ArtifactBundle::new();
+///     bundle.set_metadata("version", "1.0.0");
+///     for (name, module) in plugins {
+///         bundle.insert(*name, module)?;
+///     }
+///     Ok(bundle.serialize())
+/// }
+///
+/// fn load_plugin(store: &Store, bundle: &[u8], name: &str) -> Result<Module, DeserializeError> {
+///     let bundle = ArtifactBundle::deserialize(bundle)?;
+///     unsafe { bundle.load(store, name) }
+/// }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ArtifactBundle {
+    metadata: BTreeMap<String, String>,
+    artifacts: BTreeMap<String, Vec<u8>>,
+}
+
+impl ArtifactBundle {
+    /// Creates an empty bundle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether `bytes` look like a serialized bundle.
+    pub fn is_bundle(bytes: &[u8]) -> bool {
+        bytes.starts_with(MAGIC_HEADER)
+    }
+
+    /// Serializes `module` and adds it to the bundle under `name`,
+    /// replacing any module with the same name.
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        module: &Module,
+    ) -> Result<(), SerializeError> {
+        let artifact = module.serialize()?;
+        self.insert_serialized(name, artifact);
+        Ok(())
+    }
+
+    /// Adds an already serialized module to the bundle under `name`,
+    /// replacing any module with the same name.
+    pub fn insert_serialized(&mut self, name: impl Into<String>, artifact: Vec<u8>) {
+        self.artifacts.insert(name.into(), artifact);
+    }
+
+    /// Removes the module named `name` from the bundle, returning its
+    /// serialized form.
+    pub fn remove(&mut self, name: &str) -> Option<Vec<u8>> {
+        self.artifacts.remove(name)
+    }
+
+    /// Returns the names of the modules in the bundle, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.artifacts.keys().map(String::as_str)
+    }
+
+    /// Returns the serialized form of the module named `name`.
+    pub fn artifact(&self, name: &str) -> Option<&[u8]> {
+        self.artifacts.get(name).map(Vec::as_slice)
+    }
+
+    /// Sets a metadata entry of the bundle.
+    pub fn set_metadata(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.metadata.insert(key.into(), value.into());
+    }
+
+    /// Returns the metadata entry of the bundle for `key`.
+    pub fn metadata(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).map(String::as_str)
+    }
+
+    /// Returns all the metadata entries of the bundle, in order.
+    pub fn metadata_entries(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.metadata
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Loads the module named `name` from the bundle.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe for the same reasons as
+    /// [`Module::deserialize`]: the bundle could have been tampered with.
+    pub unsafe fn load(&self, store: &Store, name: &str) -> Result<Module, DeserializeError> {
+        let artifact = self.artifact(name).ok_or_else(|| {
+            DeserializeError::Generic(format!("module `{}` is not in the bundle", name))
+        })?;
+        let mut module = Module::deserialize(store, artifact)?;
+        module.set_name(name);
+        Ok(module)
+    }
+
+    /// Serializes the bundle into a binary representation that can later
+    /// be read with [`ArtifactBundle::deserialize`].
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC_HEADER);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.metadata.len() as u64).to_le_bytes());
+        for (key, value) in &self.metadata {
+            write_chunk(&mut bytes, key.as_bytes());
+            write_chunk(&mut bytes, value.as_bytes());
+        }
+        bytes.extend_from_slice(&(self.artifacts.len() as u64).to_le_bytes());
+        for (name, artifact) in &self.artifacts {
+            write_chunk(&mut bytes, name.as_bytes());
+            write_chunk(&mut bytes, artifact);
+        }
+        bytes
+    }
+
+    /// Serializes the bundle into a file.
+    pub fn serialize_to_file(&self, path: impl AsRef<Path>) -> Result<(), SerializeError> {
+        fs::write(path, self.serialize())?;
+        Ok(())
+    }
+
+    /// Reads a bundle serialized with [`ArtifactBundle::serialize`].
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if !Self::is_bundle(bytes) {
+            return Err(DeserializeError::Incompatible(
+                "the provided bytes are not an artifact bundle".to_string(),
+            ));
+        }
+        let mut reader = Reader {
+            bytes: &bytes[MAGIC_HEADER.len()..],
+        };
+        let version = u32::from_le_bytes(reader.read(4)?.try_into().unwrap());
+        if version != VERSION {
+            return Err(DeserializeError::Incompatible(format!(
+                "the artifact bundle version is {}, but only version {} is supported",
+                version, VERSION
+            )));
+        }
+
+        let mut bundle = Self::new();
+        for _ in 0..reader.read_u64()? {
+            let key = reader.read_string()?;
+            let value = reader.read_string()?;
+            bundle.metadata.insert(key, value);
+        }
+        for _ in 0..reader.read_u64()? {
+            let name = reader.read_string()?;
+            let artifact = reader.read_chunk()?.to_vec();
+            bundle.artifacts.insert(name, artifact);
+        }
+        if !reader.bytes.is_empty() {
+            return Err(DeserializeError::CorruptedBinary(
+                "trailing bytes after the artifact bundle".to_string(),
+            ));
+        }
+        Ok(bundle)
+    }
+
+    /// Reads a bundle from a file.
+    pub fn deserialize_from_file(path: impl AsRef<Path>) -> Result<Self, DeserializeError> {
+        Self::deserialize(&fs::read(path)?)
+    }
+}
+
+fn write_chunk(bytes: &mut Vec<u8>, chunk: &[u8]) {
+    bytes.extend_from_slice(&(chunk.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(chunk);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn read(&mut self, len: usize) -> Result<&'a [u8], DeserializeError> {
+        if self.bytes.len() < len {
+            return Err(DeserializeError::CorruptedBinary(
+                "the artifact bundle is truncated".to_string(),
+            ));
+        }
+        let (chunk, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(chunk)
+    }
+
+    fn read_u64(&mut self) -> Result<u64, DeserializeError> {
+        Ok(u64::from_le_bytes(self.read(8)?.try_into().unwrap()))
+    }
+
+    fn read_chunk(&mut self) -> Result<&'a [u8], DeserializeError> {
+        let len = self.read_u64()?;
+        let len = len.try_into().map_err(|_| {
+            DeserializeError::CorruptedBinary("the artifact bundle is truncated".to_string())
+        })?;
+        self.read(len)
+    }
+
+    fn read_string(&mut self) -> Result<String, DeserializeError> {
+        String::from_utf8(self.read_chunk()?.to_vec()).map_err(|_| {
+            DeserializeError::CorruptedBinary(
+                "the artifact bundle contains a name which is not valid UTF-8".to_string(),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let mut bundle = ArtifactBundle::new();
+        bundle.set_metadata("compiler", "singlepass");
+        bundle.insert_serialized("b", vec![4, 5]);
+        bundle.insert_serialized("a", vec![1, 2, 3]);
+
+        let bytes = bundle.serialize();
+        assert!(ArtifactBundle::is_bundle(&bytes));
+        let read = ArtifactBundle::deserialize(&bytes).unwrap();
+        assert_eq!(read, bundle);
+        assert_eq!(read.names().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(read.artifact("a"), Some(&[1, 2, 3][..]));
+        assert_eq!(read.metadata("compiler"), Some("singlepass"));
+
+        assert!(matches!(
+            ArtifactBundle::deserialize(&bytes[..bytes.len() - 1]),
+            Err(DeserializeError::CorruptedBinary(_))
+        ));
+        assert!(matches!(
+            ArtifactBundle::deserialize(b"\0asm"),
+            Err(DeserializeError::Incompatible(_))
+        ));
+    }
+}
@@ -18,14 +18,20 @@
     )
 )]
 
+mod bundle;
 mod cache;
 mod filesystem;
 mod hash;
+mod memory;
+mod tiered;
 
+pub use crate::bundle::ArtifactBundle;
 pub use crate::cache::Cache;
 #[cfg(feature = "filesystem")]
 pub use crate::filesystem::FileSystemCache;
 pub use crate::hash::Hash;
+pub use crate::memory::{CacheMetrics, InMemoryCache};
+pub use crate::tiered::{TieredCache, TieredCacheMetrics};
 
 // We re-export those for convinience of users
 pub use wasmer::{DeserializeError, SerializeError};
@@ -0,0 +1,230 @@
+use crate::cache::Cache;
+use crate::hash::Hash;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use wasmer::{DeserializeError, Module, SerializeError, Store};
+
+/// Hit and miss counters of a cache.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheMetrics {
+    /// How many loads found the requested module.
+    pub hits: u64,
+    /// How many loads did not find the requested module.
+    pub misses: u64,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CacheCounters {
+    pub(crate) fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A cache keeping serialized modules in memory, bounded by their total
+/// size in bytes.
+///
+/// When storing a module would go over the capacity, the least recently
+/// used modules are evicted first. A module whose serialized form is
+/// bigger than the capacity on its own is not kept at all.
+///
+/// The `InMemoryCache` type implements the [`Cache`] trait. It is mostly
+/// useful in front of a persistent cache, see [`TieredCache`].
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{DeserializeError, Module, SerializeError, Store};
+/// use wasmer_cache::{Cache, Hash, InMemoryCache};
+///
+/// fn store_and_load(store: &Store, module: &Module, bytes: &[u8]) -> Result<Module, DeserializeError> {
+///     // Keep at most 64MiB of modules in memory.
+///     let mut cache = InMemoryCache::new(64 * 1024 * 1024);
+///
+///     let key = Hash::generate(bytes);
+///     cache.store(key, module).unwrap();
+///
+///     unsafe { cache.load(store, key) }
+/// }
+/// ```
+///
+/// [`TieredCache`]: crate::TieredCache
+#[derive(Debug)]
+pub struct InMemoryCache {
+    capacity: usize,
+    inner: Mutex<LruEntries>,
+    counters: CacheCounters,
+}
+
+#[derive(Debug, Default)]
+struct LruEntries {
+    entries: HashMap<Hash, (Vec<u8>, u64)>,
+    // From the last use of each module to its key, the least recently
+    // used first.
+    recency: BTreeMap<u64, Hash>,
+    clock: u64,
+    size: usize,
+}
+
+impl LruEntries {
+    fn touch(&mut self, key: Hash) -> Option<&[u8]> {
+        self.clock += 1;
+        let (bytes, last_use) = self.entries.get_mut(&key)?;
+        self.recency.remove(&*last_use);
+        *last_use = self.clock;
+        self.recency.insert(self.clock, key);
+        Some(bytes.as_slice())
+    }
+
+    fn remove(&mut self, key: Hash) {
+        if let Some((bytes, last_use)) = self.entries.remove(&key) {
+            self.recency.remove(&last_use);
+            self.size -= bytes.len();
+        }
+    }
+
+    fn insert(&mut self, key: Hash, bytes: Vec<u8>, capacity: usize) {
+        self.remove(key);
+        if bytes.len() > capacity {
+            return;
+        }
+        while self.size + bytes.len() > capacity {
+            let oldest = *self.recency.values().next().unwrap();
+            self.remove(oldest);
+        }
+        self.clock += 1;
+        self.size += bytes.len();
+        self.recency.insert(self.clock, key);
+        self.entries.insert(key, (bytes, self.clock));
+    }
+}
+
+impl InMemoryCache {
+    /// Construct a new `InMemoryCache` keeping at most `capacity` bytes of
+    /// serialized modules.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(LruEntries::default()),
+            counters: CacheCounters::default(),
+        }
+    }
+
+    /// The maximum size in bytes of the modules kept by this cache.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The current size in bytes of the modules kept by this cache.
+    pub fn size(&self) -> usize {
+        self.inner.lock().unwrap().size
+    }
+
+    /// The number of modules kept by this cache.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// Whether the cache keeps no module at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether a module is kept for the given [`Hash`].
+    ///
+    /// This does not count as a use of the module.
+    pub fn contains(&self, key: Hash) -> bool {
+        self.inner.lock().unwrap().entries.contains_key(&key)
+    }
+
+    /// Returns the hit and miss counters of this cache.
+    pub fn metrics(&self) -> CacheMetrics {
+        self.counters.snapshot()
+    }
+}
+
+impl Cache for InMemoryCache {
+    type DeserializeError = DeserializeError;
+    type SerializeError = SerializeError;
+
+    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.touch(key) {
+            Some(bytes) => {
+                self.counters.hit();
+                Module::deserialize(store, bytes)
+            }
+            None => {
+                self.counters.miss();
+                Err(DeserializeError::Io(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("module {} is not in the cache", key.to_string()),
+                )))
+            }
+        }
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        let bytes = module.serialize()?;
+        self.inner
+            .get_mut()
+            .unwrap()
+            .insert(key, bytes, self.capacity);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer_compiler_singlepass::Singlepass;
+    use wasmer_engine_universal::Universal;
+
+    const EMPTY_MODULE: &[u8] = &[0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+    #[test]
+    fn evicts_least_recently_used_modules() {
+        let store = Store::new(&Universal::new(Singlepass::default()).engine());
+        let module = Module::new(&store, EMPTY_MODULE).unwrap();
+        let module_size = module.serialize().unwrap().len();
+
+        let mut cache = InMemoryCache::new(2 * module_size);
+        let (a, b, c) = (Hash::new([1; 32]), Hash::new([2; 32]), Hash::new([3; 32]));
+        cache.store(a, &module).unwrap();
+        cache.store(b, &module).unwrap();
+        assert_eq!(cache.size(), 2 * module_size);
+
+        // Using `a` makes `b` the least recently used module.
+        unsafe { cache.load(&store, a).unwrap() };
+        cache.store(c, &module).unwrap();
+        assert!(cache.contains(a));
+        assert!(!cache.contains(b));
+        assert!(cache.contains(c));
+        assert_eq!(cache.len(), 2);
+
+        assert!(unsafe { cache.load(&store, b) }.is_err());
+        assert_eq!(cache.metrics(), CacheMetrics { hits: 1, misses: 1 });
+
+        // A module bigger than the whole cache is not kept.
+        let mut tiny = InMemoryCache::new(module_size - 1);
+        tiny.store(a, &module).unwrap();
+        assert!(tiny.is_empty());
+    }
+}
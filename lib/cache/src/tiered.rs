@@ -0,0 +1,147 @@
+use crate::cache::Cache;
+use crate::hash::Hash;
+use crate::memory::{CacheCounters, CacheMetrics};
+use std::sync::Mutex;
+use wasmer::{Module, Store};
+
+/// Hit and miss counters of a [`TieredCache`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TieredCacheMetrics {
+    /// Loads served by the inner cache.
+    pub inner: CacheMetrics,
+    /// Loads served by the outer cache, after missing the inner cache.
+    pub outer: CacheMetrics,
+}
+
+/// A cache made of two caches: a fast `inner` one, usually an
+/// [`InMemoryCache`], backed by a slower `outer` one, usually a
+/// persistent cache such as a [`FileSystemCache`].
+///
+/// Loads are served by the inner cache when possible. Modules only found
+/// in the outer cache are then stored in the inner cache, so that the
+/// next loads hit it. Stores go to both caches.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{Module, SerializeError};
+/// use wasmer_cache::{Cache, FileSystemCache, Hash, InMemoryCache, TieredCache};
+///
+/// fn store_module(module: &Module, bytes: &[u8]) -> Result<(), SerializeError> {
+///     let mut cache = TieredCache::new(
+///         InMemoryCache::new(64 * 1024 * 1024),
+///         FileSystemCache::new("some/directory/goes/here")?,
+///     );
+///
+///     // The module is kept in memory and written to the directory.
+///     cache.store(Hash::generate(bytes), module)?;
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// [`InMemoryCache`]: crate::InMemoryCache
+/// [`FileSystemCache`]: crate::FileSystemCache
+#[derive(Debug)]
+pub struct TieredCache<I, O> {
+    inner: Mutex<I>,
+    outer: O,
+    inner_counters: CacheCounters,
+    outer_counters: CacheCounters,
+}
+
+impl<I, O> TieredCache<I, O> {
+    /// Construct a new `TieredCache` trying `inner` before `outer`.
+    pub fn new(inner: I, outer: O) -> Self {
+        Self {
+            inner: Mutex::new(inner),
+            outer,
+            inner_counters: CacheCounters::default(),
+            outer_counters: CacheCounters::default(),
+        }
+    }
+
+    /// Returns the hit and miss counters of both caches.
+    pub fn metrics(&self) -> TieredCacheMetrics {
+        TieredCacheMetrics {
+            inner: self.inner_counters.snapshot(),
+            outer: self.outer_counters.snapshot(),
+        }
+    }
+
+    /// Returns the two caches, inner first.
+    pub fn into_parts(self) -> (I, O) {
+        (self.inner.into_inner().unwrap(), self.outer)
+    }
+}
+
+impl<I, O> Cache for TieredCache<I, O>
+where
+    I: Cache,
+    O: Cache<SerializeError = I::SerializeError, DeserializeError = I::DeserializeError>,
+{
+    type DeserializeError = I::DeserializeError;
+    type SerializeError = I::SerializeError;
+
+    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+        if let Ok(module) = self.inner.lock().unwrap().load(store, key) {
+            self.inner_counters.hit();
+            return Ok(module);
+        }
+        self.inner_counters.miss();
+
+        match self.outer.load(store, key) {
+            Ok(module) => {
+                self.outer_counters.hit();
+                // Failing to keep the module in the inner cache only
+                // means the next load will hit the outer cache again.
+                let _ = self.inner.lock().unwrap().store(key, &module);
+                Ok(module)
+            }
+            Err(e) => {
+                self.outer_counters.miss();
+                Err(e)
+            }
+        }
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        self.outer.store(key, module)?;
+        self.inner.get_mut().unwrap().store(key, module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryCache;
+    use wasmer_compiler_singlepass::Singlepass;
+    use wasmer_engine_universal::Universal;
+
+    const EMPTY_MODULE: &[u8] = &[0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+    #[test]
+    fn promotes_modules_from_the_outer_cache() {
+        let store = Store::new(&Universal::new(Singlepass::default()).engine());
+        let module = Module::new(&store, EMPTY_MODULE).unwrap();
+        let key = Hash::generate(EMPTY_MODULE);
+
+        let mut outer = InMemoryCache::new(1024 * 1024);
+        outer.store(key, &module).unwrap();
+        let cache = TieredCache::new(InMemoryCache::new(1024 * 1024), outer);
+
+        unsafe { cache.load(&store, key).unwrap() };
+        unsafe { cache.load(&store, key).unwrap() };
+        assert!(unsafe { cache.load(&store, Hash::new([0; 32])) }.is_err());
+
+        assert_eq!(
+            cache.metrics(),
+            TieredCacheMetrics {
+                inner: CacheMetrics { hits: 1, misses: 2 },
+                outer: CacheMetrics { hits: 1, misses: 1 },
+            }
+        );
+        let (inner, _) = cache.into_parts();
+        assert!(inner.contains(key));
+    }
+}
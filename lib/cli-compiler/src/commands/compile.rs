@@ -3,7 +3,9 @@ use crate::warning;
 use anyhow::{Context, Result};
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
-use wasmer_compiler::{CompileError, CpuFeature, ModuleEnvironment, Target, Triple};
+use wasmer_compiler::{
+    CancellationToken, CompileError, CpuFeature, ModuleEnvironment, Target, Triple,
+};
 use wasmer_engine_universal_artifact::{ArtifactCreate, UniversalArtifactBuild};
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{MemoryIndex, MemoryStyle, TableIndex, TableStyle};
@@ -112,6 +114,7 @@ impl Compile {
             &target,
             memory_styles,
             table_styles,
+            &CancellationToken::new(),
         )?;
         artifact.serialize_to_file(self.output.as_ref())?;
         eprintln!(
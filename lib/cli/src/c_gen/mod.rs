@@ -1,6 +1,7 @@
 //! A convenient little abstraction for building up C expressions and generating
 //! simple C code.
 
+pub mod module_header;
 pub mod staticlib_header;
 
 /// An identifier in C.
@@ -38,6 +39,10 @@ pub enum CType {
     I64,
     /// C pointer sized signed integer type.
     ISize,
+    /// C 32 bit floating point type.
+    F32,
+    /// C 64 bit floating point type.
+    F64,
     /// A function or function pointer.
     Function {
         /// The arguments the function takes.
@@ -117,6 +122,12 @@ impl CType {
             Self::ISize => {
                 w.push_str("size_t");
             }
+            Self::F32 => {
+                w.push_str("float");
+            }
+            Self::F64 => {
+                w.push_str("double");
+            }
             Self::Function {
                 arguments,
                 return_value,
@@ -170,7 +181,9 @@ impl CType {
             | Self::I16
             | Self::I32
             | Self::I64
-            | Self::ISize => {
+            | Self::ISize
+            | Self::F32
+            | Self::F64 => {
                 self.generate_c(w);
                 w.push(' ');
                 w.push_str(name);
@@ -377,6 +390,8 @@ mod test {
         assert_c_type!(CType::I32, "int");
         assert_c_type!(CType::I64, "long long");
         assert_c_type!(CType::ISize, "size_t");
+        assert_c_type!(CType::F32, "float");
+        assert_c_type!(CType::F64, "double");
         assert_c_type!(CType::TypeDef("my_type".to_string()), "my_type");
         assert_c_type!(
             CType::Function {
@@ -429,6 +444,8 @@ mod test {
         assert_c_type!(CType::I32, "data", "int data");
         assert_c_type!(CType::I64, "data", "long long data");
         assert_c_type!(CType::ISize, "data", "size_t data");
+        assert_c_type!(CType::F32, "data", "float data");
+        assert_c_type!(CType::F64, "data", "double data");
         assert_c_type!(
             CType::TypeDef("my_type".to_string()),
             "data",
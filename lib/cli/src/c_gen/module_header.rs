@@ -0,0 +1,397 @@
+//! Generate a header declaring typed prototypes for the functions a module
+//! imports and exports, and a wrapper implementing them with the C API.
+//!
+//! An exported function `add` is called through
+//! `<prefix>_add(func, arguments..., results...)`, where `func` is the
+//! `wasm_func_t` of the export. An imported function `env.log` is
+//! implemented by the embedder as `<prefix>_env_log(arguments...,
+//! results...)`, and `<prefix>_env_log_callback` is the callback to give
+//! to `wasm_func_new` for it. All of them return the trap raised, if any.
+
+use super::{generate_c, CStatement, CType};
+use std::collections::HashSet;
+use wasmer_types::{ExportIndex, FunctionIndex, FunctionType, ImportIndex, ModuleInfo, Type};
+
+/// A function of the module given a prototype.
+struct Prototype {
+    /// The name of the C function.
+    name: String,
+    /// What the function is, for the comment above its declaration.
+    description: String,
+    ty: FunctionType,
+}
+
+/// The functions of the module to declare, and the comments about the
+/// ones which can't be.
+struct Prototypes {
+    exports: Vec<Prototype>,
+    imports: Vec<Prototype>,
+    skipped: Vec<String>,
+}
+
+/// The C type of a value of type `ty`, and the field and kind of its
+/// `wasm_val_t`. `v128` isn't supported by the C API.
+fn value_type(ty: Type) -> Option<(CType, &'static str, &'static str)> {
+    let ref_type = || CType::PointerTo {
+        is_const: false,
+        inner: Box::new(CType::TypeDef("wasm_ref_t".to_string())),
+    };
+    Some(match ty {
+        Type::I32 => (CType::I32, "i32", "WASM_I32"),
+        Type::I64 => (CType::I64, "i64", "WASM_I64"),
+        Type::F32 => (CType::F32, "f32", "WASM_F32"),
+        Type::F64 => (CType::F64, "f64", "WASM_F64"),
+        Type::ExternRef => (ref_type(), "ref", "WASM_ANYREF"),
+        Type::FuncRef => (ref_type(), "ref", "WASM_FUNCREF"),
+        Type::V128 => return None,
+    })
+}
+
+/// Turns a name into a C identifier, made unique among `used`.
+fn c_ident(name: &str, used: &mut HashSet<String>) -> String {
+    let ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let mut unique = ident.clone();
+    let mut suffix = 1;
+    while !used.insert(unique.clone()) {
+        suffix += 1;
+        unique = format!("{}_{}", ident, suffix);
+    }
+    unique
+}
+
+fn describe(module_info: &ModuleInfo, what: String, func_index: FunctionIndex) -> String {
+    let ty = &module_info.signatures[module_info.functions[func_index]];
+    match module_info.function_names.get(&func_index) {
+        Some(name) => format!("{}: {} (function `{}`)", what, ty, name),
+        None => format!("{}: {}", what, ty),
+    }
+}
+
+fn prototypes(module_info: &ModuleInfo, prefix: &str) -> Prototypes {
+    let mut used = HashSet::new();
+    let mut prototypes = Prototypes {
+        exports: vec![],
+        imports: vec![],
+        skipped: vec![],
+    };
+    let supported = |ty: &FunctionType| {
+        ty.params()
+            .iter()
+            .chain(ty.results())
+            .all(|ty| value_type(*ty).is_some())
+    };
+    for (name, export_index) in module_info.exports.iter() {
+        let func_index = match export_index {
+            ExportIndex::Function(func_index) => *func_index,
+            _ => continue,
+        };
+        let ty = &module_info.signatures[module_info.functions[func_index]];
+        let description = describe(module_info, format!("export `{}`", name), func_index);
+        if !supported(ty) {
+            prototypes.skipped.push(description);
+            continue;
+        }
+        prototypes.exports.push(Prototype {
+            name: c_ident(&format!("{}_{}", prefix, name), &mut used),
+            description,
+            ty: ty.clone(),
+        });
+    }
+    for ((module, field, _), import_index) in module_info.imports.iter() {
+        let func_index = match import_index {
+            ImportIndex::Function(func_index) => *func_index,
+            _ => continue,
+        };
+        let ty = &module_info.signatures[module_info.functions[func_index]];
+        let description = describe(
+            module_info,
+            format!("import `{}`.`{}`", module, field),
+            func_index,
+        );
+        if !supported(ty) {
+            prototypes.skipped.push(description);
+            continue;
+        }
+        let name = c_ident(&format!("{}_{}_{}", prefix, module, field), &mut used);
+        // Keeps the name of the callback from being taken too.
+        used.insert(format!("{}_callback", name));
+        prototypes.imports.push(Prototype {
+            name,
+            description,
+            ty: ty.clone(),
+        });
+    }
+    prototypes
+}
+
+/// The C types of the parameters of a prototype: the arguments, then a
+/// pointer for each result.
+fn parameter_types(ty: &FunctionType) -> Vec<CType> {
+    let params = ty.params().iter().map(|ty| value_type(*ty).unwrap().0);
+    let results = ty.results().iter().map(|ty| CType::PointerTo {
+        is_const: false,
+        inner: Box::new(value_type(*ty).unwrap().0),
+    });
+    params.chain(results).collect()
+}
+
+fn trap_ptr() -> CType {
+    CType::PointerTo {
+        is_const: false,
+        inner: Box::new(CType::TypeDef("wasm_trap_t".to_string())),
+    }
+}
+
+fn declaration(name: String, arguments: Vec<CType>) -> CStatement {
+    CStatement::Declaration {
+        name,
+        is_extern: false,
+        is_const: false,
+        ctype: CType::Function {
+            arguments,
+            return_value: Some(Box::new(trap_ptr())),
+        },
+        definition: None,
+    }
+}
+
+fn comment(text: &str) -> CStatement {
+    CStatement::LiteralConstant {
+        value: format!("\n// {}\n", text),
+    }
+}
+
+/// Generate the header declaring the functions the module imports and
+/// exports, prefixing their names with `prefix`.
+pub fn generate_module_header(module_info: &ModuleInfo, prefix: &str) -> String {
+    let prototypes = prototypes(module_info, prefix);
+    let guard = format!("{}_H", prefix.to_uppercase());
+    let mut c_statements = vec![
+        CStatement::LiteralConstant {
+            value: format!("#ifndef {0}\n#define {0}\n\n#include \"wasm.h\"\n\n", guard),
+        },
+        CStatement::LiteralConstant {
+            value: "#ifdef __cplusplus\nextern \"C\" {\n#endif\n".to_string(),
+        },
+    ];
+
+    for prototype in &prototypes.exports {
+        let mut arguments = vec![CType::PointerTo {
+            is_const: true,
+            inner: Box::new(CType::TypeDef("wasm_func_t".to_string())),
+        }];
+        arguments.extend(parameter_types(&prototype.ty));
+        c_statements.push(comment(&format!("Calls the {}.", prototype.description)));
+        c_statements.push(declaration(prototype.name.clone(), arguments));
+    }
+
+    for prototype in &prototypes.imports {
+        c_statements.push(comment(&format!(
+            "Implements the {}, to be defined by the embedder.",
+            prototype.description
+        )));
+        c_statements.push(declaration(
+            prototype.name.clone(),
+            parameter_types(&prototype.ty),
+        ));
+        c_statements.push(comment(&format!(
+            "The callback calling `{}`, to give to `wasm_func_new`.",
+            prototype.name
+        )));
+        c_statements.push(declaration(
+            format!("{}_callback", prototype.name),
+            vec![
+                CType::PointerTo {
+                    is_const: true,
+                    inner: Box::new(CType::TypeDef("wasm_val_vec_t".to_string())),
+                },
+                CType::PointerTo {
+                    is_const: false,
+                    inner: Box::new(CType::TypeDef("wasm_val_vec_t".to_string())),
+                },
+            ],
+        ));
+    }
+
+    for description in &prototypes.skipped {
+        c_statements.push(comment(&format!(
+            "Skipped the {}: `v128` isn't supported by the C API.",
+            description
+        )));
+    }
+
+    c_statements.push(CStatement::LiteralConstant {
+        value: format!("\n#ifdef __cplusplus\n}}\n#endif\n\n#endif // {}\n", guard),
+    });
+
+    generate_c(&c_statements)
+}
+
+/// Generate the C source implementing the calls to the exports and the
+/// callbacks of the imports declared by the header `header_name`.
+pub fn generate_module_wrapper(
+    module_info: &ModuleInfo,
+    prefix: &str,
+    header_name: &str,
+) -> String {
+    let prototypes = prototypes(module_info, prefix);
+    let mut out = format!("#include \"{}\"\n", header_name);
+
+    for prototype in &prototypes.exports {
+        let ty = &prototype.ty;
+        let mut parameters = vec!["const wasm_func_t* func".to_string()];
+        let mut values = vec![];
+        for (i, param) in ty.params().iter().enumerate() {
+            let (ctype, field, kind) = value_type(*param).unwrap();
+            let mut parameter = String::new();
+            ctype.generate_c_with_name(&format!("arg{}", i), &mut parameter);
+            parameters.push(parameter);
+            values.push(format!("{{ .kind = {}, .of.{} = arg{} }}", kind, field, i));
+        }
+        for (i, result) in ty.results().iter().enumerate() {
+            let ctype = CType::PointerTo {
+                is_const: false,
+                inner: Box::new(value_type(*result).unwrap().0),
+            };
+            let mut parameter = String::new();
+            ctype.generate_c_with_name(&format!("result{}", i), &mut parameter);
+            parameters.push(parameter);
+        }
+
+        out.push_str(&format!(
+            "\nwasm_trap_t* {}({}) {{\n",
+            prototype.name,
+            parameters.join(", ")
+        ));
+        if values.is_empty() {
+            out.push_str("        wasm_val_vec_t args = WASM_EMPTY_VEC;\n");
+        } else {
+            out.push_str(&format!(
+                "        wasm_val_t args_val[{}] = {{ {} }};\n        wasm_val_vec_t args = WASM_ARRAY_VEC(args_val);\n",
+                values.len(),
+                values.join(", ")
+            ));
+        }
+        if ty.results().is_empty() {
+            out.push_str("        wasm_val_vec_t results = WASM_EMPTY_VEC;\n");
+        } else {
+            let init = vec!["WASM_INIT_VAL"; ty.results().len()];
+            out.push_str(&format!(
+                "        wasm_val_t results_val[{}] = {{ {} }};\n        wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);\n",
+                init.len(),
+                init.join(", ")
+            ));
+        }
+        out.push_str("        wasm_trap_t* trap = wasm_func_call(func, &args, &results);\n");
+        if !ty.results().is_empty() {
+            out.push_str("        if (trap == NULL) {\n");
+            for (i, result) in ty.results().iter().enumerate() {
+                let (_, field, _) = value_type(*result).unwrap();
+                out.push_str(&format!(
+                    "                *result{0} = results_val[{0}].of.{1};\n",
+                    i, field
+                ));
+            }
+            out.push_str("        }\n");
+        }
+        out.push_str("        return trap;\n}\n");
+    }
+
+    for prototype in &prototypes.imports {
+        let ty = &prototype.ty;
+        out.push_str(&format!(
+            "\nwasm_trap_t* {}_callback(const wasm_val_vec_t* args, wasm_val_vec_t* results) {{\n",
+            prototype.name
+        ));
+        let mut arguments = vec![];
+        for (i, param) in ty.params().iter().enumerate() {
+            let (_, field, _) = value_type(*param).unwrap();
+            arguments.push(format!("args->data[{}].of.{}", i, field));
+        }
+        for (i, result) in ty.results().iter().enumerate() {
+            let (_, field, kind) = value_type(*result).unwrap();
+            out.push_str(&format!("        results->data[{}].kind = {};\n", i, kind));
+            arguments.push(format!("&results->data[{}].of.{}", i, field));
+        }
+        out.push_str(&format!(
+            "        return {}({});\n}}\n",
+            prototype.name,
+            arguments.join(", ")
+        ));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn module_info() -> ModuleInfo {
+        let mut module_info = ModuleInfo::new();
+        let binary = module_info
+            .signatures
+            .push(FunctionType::new([Type::I32, Type::I32], [Type::I32]));
+        let log = module_info
+            .signatures
+            .push(FunctionType::new([Type::F64], []));
+        let vector = module_info
+            .signatures
+            .push(FunctionType::new([Type::V128], []));
+        let import = module_info.functions.push(log);
+        module_info.num_imported_functions = 1;
+        module_info.imports.insert(
+            ("env".to_string(), "log".to_string(), 0),
+            ImportIndex::Function(import),
+        );
+        let add = module_info.functions.push(binary);
+        module_info
+            .function_names
+            .insert(add, "add_i32".to_string());
+        module_info
+            .exports
+            .insert("add".to_string(), ExportIndex::Function(add));
+        let splat = module_info.functions.push(vector);
+        module_info
+            .exports
+            .insert("splat".to_string(), ExportIndex::Function(splat));
+        module_info
+    }
+
+    #[test]
+    fn generate_module_header_works() {
+        let header = generate_module_header(&module_info(), "demo");
+        assert!(header.starts_with("#ifndef DEMO_H\n#define DEMO_H\n"));
+        assert!(header.contains(
+            "// Calls the export `add`: [I32, I32] -> [I32] (function `add_i32`).\n\
+             wasm_trap_t* demo_add(const wasm_func_t*, int, int, int*);\n"
+        ));
+        assert!(header.contains("wasm_trap_t* demo_env_log(double);\n"));
+        assert!(header.contains(
+            "wasm_trap_t* demo_env_log_callback(const wasm_val_vec_t*, wasm_val_vec_t*);\n"
+        ));
+        assert!(header.contains("// Skipped the export `splat`: [V128] -> []: "));
+        assert!(!header.contains("demo_splat"));
+    }
+
+    #[test]
+    fn generate_module_wrapper_works() {
+        let wrapper = generate_module_wrapper(&module_info(), "demo", "demo.h");
+        assert!(wrapper.starts_with("#include \"demo.h\"\n"));
+        assert!(wrapper.contains(
+            "wasm_trap_t* demo_add(const wasm_func_t* func, int arg0, int arg1, int* result0) {\n"
+        ));
+        assert!(wrapper.contains("*result0 = results_val[0].of.i32;\n"));
+        assert!(wrapper.contains("        return demo_env_log(args->data[0].of.f64);\n"));
+    }
+
+    #[test]
+    fn c_idents_are_unique() {
+        let mut used = HashSet::new();
+        assert_eq!(c_ident("demo_a-b", &mut used), "demo_a_b");
+        assert_eq!(c_ident("demo_a.b", &mut used), "demo_a_b_2");
+    }
+}
@@ -1,18 +1,27 @@
 //! The logic for the Wasmer CLI tool.
 
+#[cfg(all(feature = "compiler", feature = "engine"))]
+use crate::commands::Bench;
 #[cfg(target_os = "linux")]
 use crate::commands::Binfmt;
+#[cfg(all(feature = "cache", feature = "compiler"))]
+use crate::commands::Bundle;
 #[cfg(feature = "compiler")]
 use crate::commands::Compile;
 #[cfg(all(feature = "staticlib", feature = "compiler"))]
 use crate::commands::CreateExe;
 #[cfg(feature = "wast")]
 use crate::commands::Wast;
-use crate::commands::{Cache, Config, Inspect, Run, SelfUpdate, Validate};
+use crate::commands::{
+    Cache, Config, GenCHeader, GenerateCompletions, Inspect, Man, Run, SelfUpdate, Validate,
+};
 use crate::error::PrettyError;
 use anyhow::Result;
 
-use structopt::{clap::ErrorKind, StructOpt};
+use structopt::{
+    clap::{App, ErrorKind},
+    StructOpt,
+};
 
 #[derive(StructOpt)]
 #[cfg_attr(
@@ -51,6 +60,16 @@ enum WasmerCLIOptions {
     #[structopt(name = "create-exe")]
     CreateExe(CreateExe),
 
+    /// Generate a C header declaring the functions a WebAssembly file
+    /// imports and exports
+    #[structopt(name = "gen-c-header")]
+    GenCHeader(GenCHeader),
+
+    /// Compile several WebAssembly binaries into a single artifact bundle
+    #[cfg(all(feature = "cache", feature = "compiler"))]
+    #[structopt(name = "bundle")]
+    Bundle(Bundle),
+
     /// Get various configuration information needed
     /// to compile programs which use Wasmer
     #[structopt(name = "config")]
@@ -64,6 +83,11 @@ enum WasmerCLIOptions {
     #[structopt(name = "inspect")]
     Inspect(Inspect),
 
+    /// Benchmark a function exported by a WebAssembly file
+    #[cfg(all(feature = "compiler", feature = "engine"))]
+    #[structopt(name = "bench")]
+    Bench(Bench),
+
     /// Run spec testsuite
     #[cfg(feature = "wast")]
     #[structopt(name = "wast")]
@@ -73,6 +97,14 @@ enum WasmerCLIOptions {
     #[cfg(target_os = "linux")]
     #[structopt(name = "binfmt")]
     Binfmt(Binfmt),
+
+    /// Generate the shell completions of this binary
+    #[structopt(name = "generate-completions")]
+    GenerateCompletions(GenerateCompletions),
+
+    /// Generate the man page of this binary
+    #[structopt(name = "man")]
+    Man(Man),
 }
 
 impl WasmerCLIOptions {
@@ -86,16 +118,55 @@ impl WasmerCLIOptions {
             Self::Compile(compile) => compile.execute(),
             #[cfg(all(feature = "staticlib", feature = "compiler"))]
             Self::CreateExe(create_exe) => create_exe.execute(),
+            Self::GenCHeader(gen_c_header) => gen_c_header.execute(),
+            #[cfg(all(feature = "cache", feature = "compiler"))]
+            Self::Bundle(bundle) => bundle.execute(),
             Self::Config(config) => config.execute(),
             Self::Inspect(inspect) => inspect.execute(),
+            #[cfg(all(feature = "compiler", feature = "engine"))]
+            Self::Bench(bench) => bench.execute(),
             #[cfg(feature = "wast")]
             Self::Wast(wast) => wast.execute(),
             #[cfg(target_os = "linux")]
             Self::Binfmt(binfmt) => binfmt.execute(),
+            Self::GenerateCompletions(completions) => completions.execute(),
+            Self::Man(man) => man.execute(),
         }
     }
 }
 
+/// The definition of the arguments of this binary, with the commands
+/// and flags of the features it was built with.
+pub(crate) fn app() -> App<'static, 'static> {
+    WasmerCLIOptions::clap()
+}
+
+/// The names of the subcommands of this binary.
+pub(crate) fn subcommands() -> Vec<&'static str> {
+    vec![
+        "run",
+        "cache",
+        "validate",
+        #[cfg(feature = "compiler")]
+        "compile",
+        #[cfg(all(feature = "staticlib", feature = "compiler"))]
+        "create-exe",
+        #[cfg(all(feature = "cache", feature = "compiler"))]
+        "bundle",
+        "config",
+        "self-update",
+        "inspect",
+        #[cfg(all(feature = "compiler", feature = "engine"))]
+        "bench",
+        #[cfg(feature = "wast")]
+        "wast",
+        #[cfg(target_os = "linux")]
+        "binfmt",
+        "generate-completions",
+        "man",
+    ]
+}
+
 /// The main function for the Wasmer CLI tool.
 pub fn wasmer_main() {
     // We allow windows to print properly colors
@@ -115,9 +186,10 @@ pub fn wasmer_main() {
     let options = if cfg!(target_os = "linux") && binpath.ends_with("wasmer-binfmt-interpreter") {
         WasmerCLIOptions::Run(Run::from_binfmt_args())
     } else {
-        match command.unwrap_or(&"".to_string()).as_ref() {
-            "cache" | "compile" | "config" | "create-exe" | "help" | "inspect" | "run"
-            | "self-update" | "validate" | "wast" | "binfmt" => WasmerCLIOptions::from_args(),
+        match command.map(String::as_str) {
+            Some(command) if command == "help" || subcommands().contains(&command) => {
+                WasmerCLIOptions::from_args()
+            }
             _ => {
                 WasmerCLIOptions::from_iter_safe(args.iter()).unwrap_or_else(|e| {
                     match e.kind {
@@ -134,3 +206,18 @@ pub fn wasmer_main() {
 
     PrettyError::report(options.execute());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subcommands_are_defined() {
+        for subcommand in subcommands() {
+            let error = app()
+                .get_matches_from_safe(vec!["wasmer", subcommand, "--help"])
+                .unwrap_err();
+            assert_eq!(error.kind, ErrorKind::HelpDisplayed, "{}", subcommand);
+        }
+    }
+}
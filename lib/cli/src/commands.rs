@@ -1,25 +1,39 @@
 //! The commands available in the Wasmer binary.
+#[cfg(all(feature = "compiler", feature = "engine"))]
+mod bench;
 #[cfg(target_os = "linux")]
 mod binfmt;
+#[cfg(all(feature = "cache", feature = "compiler"))]
+mod bundle;
 mod cache;
 #[cfg(feature = "compiler")]
 mod compile;
 mod config;
 #[cfg(all(feature = "staticlib", feature = "compiler"))]
 mod create_exe;
+mod gen_c_header;
+mod generate_completions;
 mod inspect;
+mod man;
 mod run;
 mod self_update;
 mod validate;
 #[cfg(feature = "wast")]
 mod wast;
 
+#[cfg(all(feature = "compiler", feature = "engine"))]
+pub use bench::*;
 #[cfg(target_os = "linux")]
 pub use binfmt::*;
+#[cfg(all(feature = "cache", feature = "compiler"))]
+pub use bundle::*;
 #[cfg(feature = "compiler")]
 pub use compile::*;
 #[cfg(all(feature = "staticlib", feature = "compiler"))]
 pub use create_exe::*;
 #[cfg(feature = "wast")]
 pub use wast::*;
-pub use {cache::*, config::*, inspect::*, run::*, self_update::*, validate::*};
+pub use {
+    cache::*, config::*, gen_c_header::*, generate_completions::*, inspect::*, man::*, run::*,
+    self_update::*, validate::*,
+};
@@ -0,0 +1,217 @@
+//! Benchmarks a function exported by a WebAssembly module.
+
+use super::run::parse_invoke_args;
+#[cfg(feature = "wasi")]
+use super::run::Wasi;
+use crate::store::{CompilerType, StoreOptions};
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+use wasmer::wasmparser::Operator;
+use wasmer::*;
+use wasmer_middlewares::metering::{get_remaining_points, set_remaining_points, MeteringPoints};
+use wasmer_middlewares::Metering;
+
+#[derive(Debug, StructOpt)]
+/// The options for the `wasmer bench` subcommand
+pub struct Bench {
+    /// File holding the function to benchmark. Formats accepted: wasm, wat
+    #[structopt(name = "FILE", parse(from_os_str))]
+    path: PathBuf,
+
+    /// The exported function to benchmark
+    #[structopt(long = "invoke", short = "i")]
+    invoke: String,
+
+    /// Number of calls made before measuring
+    #[structopt(long = "warmup", default_value = "10")]
+    warmup: usize,
+
+    /// Number of calls measured
+    #[structopt(long = "iterations", short = "n", default_value = "1000")]
+    iterations: usize,
+
+    /// Compilers to compare, separated by commas (singlepass, cranelift,
+    /// llvm). The compiler selected by the store options is used
+    /// otherwise.
+    #[structopt(long = "compilers", use_delimiter = true)]
+    compilers: Vec<CompilerType>,
+
+    /// Count the WebAssembly operators executed by a call. The metering
+    /// instrumentation slows the calls down, so the latencies measured
+    /// with it are higher.
+    #[structopt(long = "metering")]
+    metering: bool,
+
+    #[structopt(flatten)]
+    store: StoreOptions,
+
+    #[cfg(feature = "wasi")]
+    #[structopt(flatten)]
+    wasi: Wasi,
+
+    /// Arguments of the function
+    #[structopt(value_name = "ARGS")]
+    args: Vec<String>,
+}
+
+/// What was measured with one compiler.
+struct Report {
+    compiler: CompilerType,
+    compile_time: Duration,
+    calls: Vec<Duration>,
+    operators: Option<u64>,
+}
+
+impl Bench {
+    /// Runs logic for the `bench` subcommand
+    pub fn execute(&self) -> Result<()> {
+        self.inner_execute()
+            .context(format!("failed to benchmark `{}`", self.path.display()))
+    }
+
+    fn inner_execute(&self) -> Result<()> {
+        if self.iterations == 0 {
+            bail!("at least one iteration is needed");
+        }
+        let contents = std::fs::read(&self.path)?;
+        let compilers = if self.compilers.is_empty() {
+            vec![None]
+        } else {
+            self.compilers
+                .iter()
+                .map(|compiler| Some(*compiler))
+                .collect()
+        };
+        for compiler in compilers {
+            let report = self.bench(&contents, compiler)?;
+            report.print();
+        }
+        Ok(())
+    }
+
+    fn bench(&self, contents: &[u8], compiler: Option<CompilerType>) -> Result<Report> {
+        let (mut compiler_config, compiler) = self.store.get_compiler_config(compiler)?;
+        if self.metering {
+            compiler_config.push_middleware(Arc::new(Metering::new(u64::MAX, |_: &Operator| 1)));
+        }
+        let (store, _engine_type) = self.store.get_store_with_compiler_config(compiler_config)?;
+
+        let start = Instant::now();
+        let module = Module::new(&store, contents)?;
+        let compile_time = start.elapsed();
+
+        let instance = self.instantiate(&module)?;
+        let function = instance
+            .exports
+            .get_function(&self.invoke)
+            .with_context(|| format!("no function `{}` is exported", self.invoke))?;
+        let params = function.ty().params();
+        if params.len() != self.args.len() {
+            bail!(
+                "Function expected {} arguments, but received {}: \"{}\"",
+                params.len(),
+                self.args.len(),
+                self.args.join(" ")
+            );
+        }
+        let args = parse_invoke_args(&self.args, params)?;
+
+        for _ in 0..self.warmup {
+            function.call(&args)?;
+        }
+        let mut calls = Vec::with_capacity(self.iterations);
+        for _ in 0..self.iterations {
+            let start = Instant::now();
+            function.call(&args)?;
+            calls.push(start.elapsed());
+        }
+
+        let operators = if self.metering {
+            set_remaining_points(&instance, u64::MAX);
+            function.call(&args)?;
+            match get_remaining_points(&instance) {
+                MeteringPoints::Remaining(points) => Some(u64::MAX - points),
+                MeteringPoints::Exhausted => None,
+            }
+        } else {
+            None
+        };
+
+        Ok(Report {
+            compiler,
+            compile_time,
+            calls,
+            operators,
+        })
+    }
+
+    fn instantiate(&self, module: &Module) -> Result<Instance> {
+        #[cfg(feature = "wasi")]
+        {
+            if Wasi::has_wasi_imports(module) {
+                let program_name = self
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                return self.wasi.instantiate(module, program_name, vec![]);
+            }
+        }
+        Ok(Instance::new(module, &imports! {})?)
+    }
+}
+
+impl Report {
+    fn print(&self) {
+        let mut calls = self.calls.clone();
+        calls.sort();
+        let total: Duration = calls.iter().sum();
+        println!("{}:", self.compiler.to_string());
+        println!("  compilation: {:?}", self.compile_time);
+        println!(
+            "  calls: {} in {:?}, mean {:?}",
+            calls.len(),
+            total,
+            total / calls.len() as u32
+        );
+        println!(
+            "  latency: min {:?}, p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+            calls[0],
+            percentile(&calls, 50),
+            percentile(&calls, 90),
+            percentile(&calls, 99),
+            calls[calls.len() - 1]
+        );
+        if let Some(operators) = self.operators {
+            println!("  operators per call: {}", operators);
+        }
+    }
+}
+
+/// The `p`th percentile of sorted, non-empty `values`, by the nearest
+/// rank.
+fn percentile(values: &[Duration], p: usize) -> Duration {
+    let rank = (values.len() * p + 99) / 100;
+    values[rank.max(1) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles() {
+        let values = (1..=10).map(Duration::from_millis).collect::<Vec<_>>();
+        assert_eq!(percentile(&values, 50), Duration::from_millis(5));
+        assert_eq!(percentile(&values, 90), Duration::from_millis(9));
+        assert_eq!(percentile(&values, 99), Duration::from_millis(10));
+        assert_eq!(percentile(&values, 0), Duration::from_millis(1));
+        assert_eq!(
+            percentile(&[Duration::from_secs(1)], 50),
+            Duration::from_secs(1)
+        );
+    }
+}
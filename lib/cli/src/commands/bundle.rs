@@ -0,0 +1,80 @@
+use crate::store::StoreOptions;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use wasmer::*;
+use wasmer_cache::ArtifactBundle;
+
+#[derive(Debug, StructOpt)]
+/// The options for the `wasmer bundle` subcommand
+pub struct Bundle {
+    /// Input files. Each module is named after its file name, without
+    /// the extension, unless given as `NAME=FILE`
+    #[structopt(name = "FILES", required = true, min_values = 1)]
+    inputs: Vec<String>,
+
+    /// Output file
+    #[structopt(name = "OUTPUT PATH", short = "o", parse(from_os_str))]
+    output: PathBuf,
+
+    #[structopt(flatten)]
+    store: StoreOptions,
+}
+
+impl Bundle {
+    /// Runs logic for the `bundle` subcommand
+    pub fn execute(&self) -> Result<()> {
+        self.inner_execute()
+            .context(format!("failed to bundle into `{}`", self.output.display()))
+    }
+
+    /// Splits each input into the module name and its file.
+    fn named_inputs(&self) -> Result<BTreeMap<String, PathBuf>> {
+        let mut named_inputs = BTreeMap::new();
+        for input in &self.inputs {
+            let (name, path) = match input.split_once('=') {
+                Some((name, path)) => (name.to_string(), PathBuf::from(path)),
+                None => {
+                    let path = PathBuf::from(input);
+                    let name = path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                        .with_context(|| format!("`{}` has no file name", input))?;
+                    (name, path)
+                }
+            };
+            if named_inputs.insert(name.clone(), path).is_some() {
+                bail!("several modules are named `{}`", name);
+            }
+        }
+        Ok(named_inputs)
+    }
+
+    fn inner_execute(&self) -> Result<()> {
+        let named_inputs = self.named_inputs()?;
+        let (store, engine_type, compiler_type) = self.store.get_store()?;
+        println!("Engine: {}", engine_type.to_string());
+        println!("Compiler: {}", compiler_type.to_string());
+
+        let mut bundle = ArtifactBundle::new();
+        bundle.set_metadata("wasmer-version", env!("CARGO_PKG_VERSION"));
+        bundle.set_metadata("engine", engine_type.to_string());
+        bundle.set_metadata("compiler", compiler_type.to_string());
+        bundle.set_metadata("target", Triple::host().to_string());
+        for (name, path) in &named_inputs {
+            let module = Module::from_file(&store, path)
+                .with_context(|| format!("failed to compile `{}`", path.display()))?;
+            bundle.insert(name.as_str(), &module)?;
+            println!("Module `{}`: {}", name, path.display());
+        }
+        bundle.serialize_to_file(&self.output)?;
+
+        eprintln!(
+            "✔ {} modules bundled successfully to `{}`.",
+            named_inputs.len(),
+            self.output.display(),
+        );
+        Ok(())
+    }
+}
@@ -36,6 +36,11 @@ pub struct CreateExe {
     /// This is useful for fixing linker errors that may occur on some systems.
     #[structopt(short = "l", multiple = true, number_of_values = 1)]
     libraries: Vec<String>,
+
+    /// Emit each function in its own section, and have the linker remove
+    /// the unused ones.
+    #[structopt(long = "function-sections")]
+    function_sections: bool,
 }
 
 impl CreateExe {
@@ -59,7 +64,7 @@ impl CreateExe {
         let engine_type = EngineType::Staticlib;
         let (store, compiler_type) = self
             .compiler
-            .get_store_for_target_and_engine(target.clone(), engine_type)?;
+            .get_staticlib_store_for_target(target.clone(), self.function_sections)?;
 
         println!("Engine: {}", engine_type.to_string());
         println!("Compiler: {}", compiler_type.to_string());
@@ -130,6 +135,7 @@ impl CreateExe {
             output_path,
             additional_libraries: self.libraries.clone(),
             target: self.target_triple.clone(),
+            gc_sections: self.function_sections,
             ..Default::default()
         }
         .run()
@@ -243,6 +249,8 @@ struct LinkCode {
     libwasmer_path: PathBuf,
     /// The target to link the executable for.
     target: Option<Triple>,
+    /// Whether to remove the unused sections.
+    gc_sections: bool,
 }
 
 impl Default for LinkCode {
@@ -259,6 +267,7 @@ impl Default for LinkCode {
             output_path: PathBuf::from("a.out"),
             libwasmer_path: get_libwasmer_path().unwrap(),
             target: None,
+            gc_sections: false,
         }
     }
 }
@@ -300,6 +309,19 @@ impl LinkCode {
             .iter()
             .map(|lib| format!("-l{}", lib));
         let command = command.args(link_aganist_extra_libs);
+        let command = if self.gc_sections {
+            let is_darwin = match &self.target {
+                Some(target) => target.operating_system == OperatingSystem::Darwin,
+                None => cfg!(target_os = "macos"),
+            };
+            if is_darwin {
+                command.arg("-Wl,-dead_strip")
+            } else {
+                command.arg("-Wl,--gc-sections")
+            }
+        } else {
+            command
+        };
         let output = command.arg("-o").arg(&self.output_path).output()?;
 
         if !output.status.success() {
@@ -0,0 +1,87 @@
+use crate::c_gen::module_header::{generate_module_header, generate_module_wrapper};
+use crate::store::StoreOptions;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use wasmer::*;
+
+#[derive(Debug, StructOpt)]
+/// The options for the `wasmer gen-c-header` subcommand
+pub struct GenCHeader {
+    /// Input file
+    #[structopt(name = "FILE", parse(from_os_str))]
+    path: PathBuf,
+
+    /// Output path for the header, printed if not given
+    #[structopt(name = "OUTPUT PATH", short = "o", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// Output path for a C file implementing the header with the C API
+    #[structopt(name = "WRAPPER PATH", long = "wrapper", parse(from_os_str))]
+    wrapper_path: Option<PathBuf>,
+
+    /// Prefix of the generated names, the name of the file by default
+    #[structopt(long = "prefix")]
+    prefix: Option<String>,
+
+    #[structopt(flatten)]
+    store: StoreOptions,
+}
+
+impl GenCHeader {
+    /// Runs logic for the `gen-c-header` subcommand
+    pub fn execute(&self) -> Result<()> {
+        self.inner_execute().context(format!(
+            "failed to generate a C header for `{}`",
+            self.path.display()
+        ))
+    }
+
+    fn inner_execute(&self) -> Result<()> {
+        let (store, _engine_type, _compiler_type) = self.store.get_store()?;
+        let module_contents = fs::read(&self.path)?;
+        let module = Module::new(&store, &module_contents)?;
+        let prefix = match &self.prefix {
+            Some(prefix) => prefix.clone(),
+            None => self
+                .path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "module".to_string()),
+        };
+        let prefix: String = prefix
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+
+        let header = generate_module_header(module.info(), &prefix);
+        match &self.output {
+            Some(output) => {
+                fs::write(output, header)?;
+                eprintln!(
+                    "✔ Header file generated successfully at `{}`.",
+                    output.display()
+                );
+            }
+            None => print!("{}", header),
+        }
+
+        if let Some(wrapper_path) = &self.wrapper_path {
+            let header_name = match &self.output {
+                Some(output) => output
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                None => format!("{}.h", prefix),
+            };
+            let wrapper = generate_module_wrapper(module.info(), &prefix, &header_name);
+            fs::write(wrapper_path, wrapper)?;
+            eprintln!(
+                "✔ Wrapper file generated successfully at `{}`.",
+                wrapper_path.display()
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,22 @@
+use crate::cli::app;
+use anyhow::Result;
+use std::io;
+use structopt::{clap::Shell, StructOpt};
+
+#[derive(Debug, StructOpt)]
+/// The options for the `wasmer generate-completions` subcommand
+pub struct GenerateCompletions {
+    /// The shell to generate the completions for
+    #[structopt(name = "SHELL", possible_values = &Shell::variants(), case_insensitive = true)]
+    shell: Shell,
+}
+
+impl GenerateCompletions {
+    /// Runs logic for the `generate-completions` subcommand
+    pub fn execute(&self) -> Result<()> {
+        let mut app = app();
+        let bin_name = app.get_name().to_string();
+        app.gen_completions_to(bin_name, self.shell, &mut io::stdout());
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use crate::cli::{app, subcommands};
+use anyhow::{bail, Result};
+use std::fmt::Write;
+use structopt::clap::ErrorKind;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+/// The options for the `wasmer man` subcommand
+pub struct Man {}
+
+impl Man {
+    /// Runs logic for the `man` subcommand
+    pub fn execute(&self) -> Result<()> {
+        print!("{}", man_page()?);
+        Ok(())
+    }
+}
+
+/// Writes the man page of this binary in roff, from the help of each
+/// of its subcommands.
+fn man_page() -> Result<String> {
+    let app = app();
+    let name = app.get_name().to_string();
+
+    let mut help = Vec::new();
+    app.clone().write_long_help(&mut help)?;
+    let help = String::from_utf8(help)?;
+    // The help starts with the name, the authors and the description,
+    // followed by an empty line.
+    let about = help
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .last()
+        .unwrap_or_default();
+
+    let mut page = String::new();
+    writeln!(
+        page,
+        ".TH {} 1 \"\" \"{} {}\"",
+        name.to_uppercase(),
+        name,
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(page, ".SH NAME\n{} \\- {}", name, escape(about))?;
+    writeln!(page, ".SH DESCRIPTION")?;
+    write_preformatted(&mut page, &help)?;
+    writeln!(page, ".SH COMMANDS")?;
+    for subcommand in subcommands() {
+        let error =
+            match app
+                .clone()
+                .get_matches_from_safe(vec![name.as_str(), subcommand, "--help"])
+            {
+                Ok(_) => bail!("no help for the `{}` subcommand", subcommand),
+                Err(error) => error,
+            };
+        if error.kind != ErrorKind::HelpDisplayed {
+            bail!("no help for the `{}` subcommand: {}", subcommand, error);
+        }
+        writeln!(page, ".SS {} {}", name, subcommand)?;
+        write_preformatted(&mut page, &error.message)?;
+    }
+    Ok(page)
+}
+
+/// Writes `text` as is, without filling nor adjusting it.
+fn write_preformatted(page: &mut String, text: &str) -> Result<()> {
+    writeln!(page, ".nf")?;
+    for line in text.trim_end().lines() {
+        writeln!(page, "{}", escape(line))?;
+    }
+    writeln!(page, ".fi")?;
+    Ok(())
+}
+
+/// Escapes the backslashes of a line, and the dots and quotes starting
+/// it, which roff would take for requests.
+fn escape(line: &str) -> String {
+    let line = line.replace('\\', "\\e");
+    if line.starts_with('.') || line.starts_with('\'') {
+        format!("\\&{}", line)
+    } else {
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn man_page_has_every_subcommand() {
+        let page = man_page().unwrap();
+        assert!(page.starts_with(".TH "));
+        for subcommand in subcommands() {
+            assert!(page.contains(&format!(".SS wasmer {}\n", subcommand)));
+        }
+    }
+
+    #[test]
+    fn escapes_roff() {
+        assert_eq!(escape(r"a\b"), r"a\eb");
+        assert_eq!(escape(".hidden"), r"\&.hidden");
+        assert_eq!(escape("plain"), "plain");
+    }
+}
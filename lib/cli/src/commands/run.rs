@@ -9,15 +9,18 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use wasmer::*;
 #[cfg(feature = "cache")]
-use wasmer_cache::{Cache, FileSystemCache, Hash};
+use wasmer_cache::{ArtifactBundle, Cache, FileSystemCache, Hash};
 
 use structopt::StructOpt;
 
+mod manifest;
 #[cfg(feature = "wasi")]
 mod wasi;
 
+use manifest::Manifest;
+
 #[cfg(feature = "wasi")]
-use wasi::Wasi;
+pub(crate) use wasi::Wasi;
 
 #[derive(Debug, StructOpt, Clone, Default)]
 /// The options for the `wasmer run` subcommand
@@ -27,10 +30,15 @@ pub struct Run {
     #[structopt(long = "disable-cache")]
     disable_cache: bool,
 
-    /// File to run
+    /// File to run, or directory holding a `wasmer.toml` manifest
     #[structopt(name = "FILE", parse(from_os_str))]
     path: PathBuf,
 
+    /// The command to run, when FILE is a package holding several
+    /// commands in its `wasmer.toml` manifest
+    #[structopt(long = "command")]
+    command: Option<String>,
+
     /// Invoke a specified function
     #[structopt(long = "invoke", short = "i")]
     invoke: Option<String>,
@@ -48,6 +56,12 @@ pub struct Run {
     #[structopt(long = "cache-key", hidden = true)]
     cache_key: Option<String>,
 
+    /// Name of the module to run, when FILE is an artifact bundle
+    /// holding several modules
+    #[cfg(feature = "cache")]
+    #[structopt(long = "bundle-module")]
+    bundle_module: Option<String>,
+
     #[structopt(flatten)]
     store: StoreOptions,
 
@@ -96,6 +110,13 @@ impl Run {
     }
 
     fn inner_execute(&self) -> Result<()> {
+        if let Some(manifest) = Manifest::find(&self.path)? {
+            return manifest.resolve(self)?.inner_execute_module();
+        }
+        self.inner_execute_module()
+    }
+
+    fn inner_execute_module(&self) -> Result<()> {
         let module = self.get_module()?;
         #[cfg(feature = "emscripten")]
         {
@@ -223,6 +244,12 @@ impl Run {
 
     fn get_module(&self) -> Result<Module> {
         let contents = std::fs::read(self.path.clone())?;
+        #[cfg(feature = "cache")]
+        {
+            if ArtifactBundle::is_bundle(&contents) {
+                return self.get_module_from_bundle(&contents);
+            }
+        }
         #[cfg(feature = "dylib")]
         {
             if wasmer_engine_dylib::DylibArtifact::is_deserializable(&contents) {
@@ -264,6 +291,49 @@ impl Run {
         Ok(module)
     }
 
+    #[cfg(feature = "cache")]
+    fn get_module_from_bundle(&self, contents: &[u8]) -> Result<Module> {
+        let bundle = ArtifactBundle::deserialize(contents)?;
+        let name = match &self.bundle_module {
+            Some(name) => name.clone(),
+            None => {
+                let names = bundle.names().collect::<Vec<_>>();
+                match names.as_slice() {
+                    [name] => name.to_string(),
+                    [] => bail!("the bundle is empty"),
+                    names => bail!(
+                        "the bundle holds several modules ({}), select one with `--bundle-module`",
+                        names.join(", ")
+                    ),
+                }
+            }
+        };
+        #[allow(unused_variables)]
+        let artifact = bundle
+            .artifact(&name)
+            .with_context(|| format!("module `{}` is not in the bundle", name))?;
+        #[cfg(feature = "dylib")]
+        {
+            if wasmer_engine_dylib::DylibArtifact::is_deserializable(artifact) {
+                let engine = wasmer_engine_dylib::Dylib::headless().engine();
+                let store = Store::new(&engine);
+                return Ok(unsafe { bundle.load(&store, &name)? });
+            }
+        }
+        #[cfg(feature = "universal")]
+        {
+            if wasmer_engine_universal::UniversalArtifact::is_deserializable(artifact) {
+                let engine = wasmer_engine_universal::Universal::headless().engine();
+                let store = Store::new(&engine);
+                return Ok(unsafe { bundle.load(&store, &name)? });
+            }
+        }
+        bail!(
+            "module `{}` of the bundle was produced by an engine which is not compiled in",
+            name
+        )
+    }
+
     #[cfg(feature = "cache")]
     fn get_module_from_cache(
         &self,
@@ -403,37 +473,7 @@ impl Run {
                 self.args.join(" ")
             );
         }
-        let invoke_args = args
-            .iter()
-            .zip(func_ty.params().iter())
-            .map(|(arg, param_type)| match param_type {
-                ValType::I32 => {
-                    Ok(Val::I32(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a i32", arg)
-                    })?))
-                }
-                ValType::I64 => {
-                    Ok(Val::I64(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a i64", arg)
-                    })?))
-                }
-                ValType::F32 => {
-                    Ok(Val::F32(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a f32", arg)
-                    })?))
-                }
-                ValType::F64 => {
-                    Ok(Val::F64(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a f64", arg)
-                    })?))
-                }
-                _ => Err(anyhow!(
-                    "Don't know how to convert {} into {:?}",
-                    arg,
-                    param_type
-                )),
-            })
-            .collect::<Result<Vec<_>>>()?;
+        let invoke_args = parse_invoke_args(args, func_ty.params())?;
         Ok(func.call(&invoke_args)?)
     }
 
@@ -492,3 +532,38 @@ impl Run {
         bail!("binfmt_misc is only available on linux.")
     }
 }
+
+/// Parses the arguments of an invoked function, given the types of its
+/// parameters.
+pub(crate) fn parse_invoke_args(args: &[String], params: &[Type]) -> Result<Vec<Val>> {
+    args.iter()
+        .zip(params.iter())
+        .map(|(arg, param_type)| match param_type {
+            ValType::I32 => {
+                Ok(Val::I32(arg.parse().map_err(|_| {
+                    anyhow!("Can't convert `{}` into a i32", arg)
+                })?))
+            }
+            ValType::I64 => {
+                Ok(Val::I64(arg.parse().map_err(|_| {
+                    anyhow!("Can't convert `{}` into a i64", arg)
+                })?))
+            }
+            ValType::F32 => {
+                Ok(Val::F32(arg.parse().map_err(|_| {
+                    anyhow!("Can't convert `{}` into a f32", arg)
+                })?))
+            }
+            ValType::F64 => {
+                Ok(Val::F64(arg.parse().map_err(|_| {
+                    anyhow!("Can't convert `{}` into a f64", arg)
+                })?))
+            }
+            _ => Err(anyhow!(
+                "Don't know how to convert {} into {:?}",
+                arg,
+                param_type
+            )),
+        })
+        .collect()
+}
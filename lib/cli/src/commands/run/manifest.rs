@@ -0,0 +1,253 @@
+//! Running the commands of a local package described by a `wasmer.toml`
+//! manifest, without going through any registry.
+//!
+//! Only the parts of the manifest which matter to run a command are read:
+//!
+//! ```toml
+//! [[module]]
+//! name = "hello"
+//! source = "target/wasm32-wasi/release/hello.wasm"
+//!
+//! [[command]]
+//! name = "hello"
+//! module = "hello"
+//! main_args = "--verbose"
+//!
+//! # Directories mapped for WASI modules, from the guest path to the
+//! # host path, relative to the manifest.
+//! [fs]
+//! "/data" = "data"
+//!
+//! # Environment variables set for WASI modules.
+//! [env]
+//! RUST_LOG = "info"
+//! ```
+use super::Run;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of the manifest file of a local package.
+pub const MANIFEST_FILE_NAME: &str = "wasmer.toml";
+
+/// A module of the package.
+#[derive(Debug, Deserialize)]
+struct ManifestModule {
+    name: String,
+    source: PathBuf,
+}
+
+/// A command of the package, running one of its modules.
+#[derive(Debug, Deserialize)]
+struct ManifestCommand {
+    name: String,
+    module: String,
+    main_args: Option<String>,
+}
+
+/// A `wasmer.toml` manifest.
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    #[serde(rename = "module", default)]
+    modules: Vec<ManifestModule>,
+    #[serde(rename = "command", default)]
+    commands: Vec<ManifestCommand>,
+    #[serde(default)]
+    fs: BTreeMap<String, PathBuf>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    /// The directory holding the manifest, relative paths are
+    /// resolved from it.
+    #[serde(skip)]
+    base_dir: PathBuf,
+}
+
+impl Manifest {
+    /// Reads the manifest of the package at `path`, if `path` is a
+    /// manifest or a directory holding one.
+    pub fn find(path: &Path) -> Result<Option<Self>> {
+        let manifest_path = if path.is_dir() {
+            path.join(MANIFEST_FILE_NAME)
+        } else {
+            path.to_path_buf()
+        };
+        if manifest_path.file_name() != Some(OsStr::new(MANIFEST_FILE_NAME))
+            || !manifest_path.is_file()
+        {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(&manifest_path)
+            .with_context(|| format!("failed to read `{}`", manifest_path.display()))?;
+        let mut manifest: Self = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse `{}`", manifest_path.display()))?;
+        manifest.base_dir = manifest_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        Ok(Some(manifest))
+    }
+
+    fn names<'a>(names: impl Iterator<Item = &'a String>) -> String {
+        names
+            .map(|name| format!("`{}`", name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn command(&self, name: Option<&str>) -> Result<Option<&ManifestCommand>> {
+        if let Some(name) = name {
+            return self
+                .commands
+                .iter()
+                .find(|command| command.name == name)
+                .map(Some)
+                .with_context(|| {
+                    format!(
+                        "the package has no command named `{}` (available commands: {})",
+                        name,
+                        Self::names(self.commands.iter().map(|command| &command.name))
+                    )
+                });
+        }
+        match self.commands.as_slice() {
+            [] => Ok(None),
+            [command] => Ok(Some(command)),
+            commands => bail!(
+                "the package has several commands ({}), select one with `--command`",
+                Self::names(commands.iter().map(|command| &command.name))
+            ),
+        }
+    }
+
+    fn module(&self, name: Option<&str>) -> Result<&ManifestModule> {
+        if let Some(name) = name {
+            return self
+                .modules
+                .iter()
+                .find(|module| module.name == name)
+                .with_context(|| format!("the package has no module named `{}`", name));
+        }
+        match self.modules.as_slice() {
+            [module] => Ok(module),
+            [] => bail!("the package has no module"),
+            modules => bail!(
+                "the package has several modules ({}) but no command to select one",
+                Self::names(modules.iter().map(|module| &module.name))
+            ),
+        }
+    }
+
+    /// Resolves the options of `run` for the command of the package it
+    /// selects, or its only command.
+    ///
+    /// The arguments of the command come before the ones of `run`, and
+    /// the mapped directories and environment variables of the manifest
+    /// before the ones given on the command line.
+    pub fn resolve(&self, run: &Run) -> Result<Run> {
+        let command = self.command(run.command.as_deref())?;
+        let module = self.module(command.map(|command| command.module.as_str()))?;
+
+        let mut resolved = run.clone();
+        resolved.path = self.base_dir.join(&module.source);
+        resolved.command = None;
+        resolved.args = command
+            .and_then(|command| command.main_args.as_deref())
+            .map(|main_args| main_args.split_whitespace().map(String::from).collect())
+            .unwrap_or_default();
+        resolved.args.extend(run.args.iter().cloned());
+        if resolved.command_name.is_none() {
+            resolved.command_name = Some(
+                command
+                    .map(|command| &command.name)
+                    .unwrap_or(&module.name)
+                    .clone(),
+            );
+        }
+
+        let mapped_dirs = self
+            .fs
+            .iter()
+            .map(|(guest, host)| (guest.clone(), self.base_dir.join(host)))
+            .collect::<Vec<_>>();
+        let env_vars = self
+            .env
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        #[cfg(feature = "wasi")]
+        {
+            resolved.wasi = run.wasi.with_defaults(mapped_dirs, env_vars);
+        }
+        #[cfg(not(feature = "wasi"))]
+        {
+            if !mapped_dirs.is_empty() || !env_vars.is_empty() {
+                crate::warning!(
+                    "the `fs` and `env` sections of the manifest are only used with WASI"
+                );
+            }
+        }
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+[package]
+name = "tools"
+version = "0.1.0"
+
+[[module]]
+name = "tools"
+source = "tools.wasm"
+abi = "wasi"
+
+[[command]]
+name = "greet"
+module = "tools"
+main_args = "greet --loud"
+
+[[command]]
+name = "count"
+module = "tools"
+
+[fs]
+"/data" = "data"
+
+[env]
+GREETING = "hello"
+"#;
+
+    #[test]
+    fn resolves_commands_from_a_local_manifest() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join(MANIFEST_FILE_NAME), MANIFEST)?;
+        let manifest = Manifest::find(dir.path())?.unwrap();
+
+        let run = Run {
+            path: dir.path().to_path_buf(),
+            command: Some("greet".to_string()),
+            args: vec!["world".to_string()],
+            ..Run::default()
+        };
+        let resolved = manifest.resolve(&run)?;
+        assert_eq!(resolved.path, dir.path().join("tools.wasm"));
+        assert_eq!(resolved.args, vec!["greet", "--loud", "world"]);
+        assert_eq!(resolved.command_name.as_deref(), Some("greet"));
+
+        // Several commands and none selected.
+        let run = Run {
+            path: dir.path().to_path_buf(),
+            ..Run::default()
+        };
+        assert!(manifest.resolve(&run).is_err());
+
+        assert!(Manifest::find(&dir.path().join("tools.wasm"))?.is_none());
+        Ok(())
+    }
+}
@@ -1,8 +1,11 @@
 use crate::utils::{parse_envvar, parse_mapdir};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::collections::BTreeSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
 use std::path::PathBuf;
 use wasmer::{Instance, Module, RuntimeError, Val};
+use wasmer_vfs::host_fs;
 use wasmer_wasi::{get_wasi_versions, WasiError, WasiState, WasiVersion};
 
 use structopt::StructOpt;
@@ -39,6 +42,15 @@ pub struct Wasi {
     )]
     env_vars: Vec<(String, String)>,
 
+    /// Read the standard input of the module from a file or a pipe
+    #[structopt(long = "stdin-file", name = "STDIN_FILE", parse(from_os_str))]
+    stdin_file: Option<PathBuf>,
+
+    /// Write the standard output of the module to a file or a pipe,
+    /// truncating it first
+    #[structopt(long = "stdout-file", name = "STDOUT_FILE", parse(from_os_str))]
+    stdout_file: Option<PathBuf>,
+
     /// Enable experimental IO devices
     #[cfg(feature = "experimental-io-devices")]
     #[structopt(long = "enable-experimental-io-devices")]
@@ -69,6 +81,22 @@ impl Wasi {
         get_wasi_versions(&module, false).is_some()
     }
 
+    /// Returns these options with the given mapped directories and
+    /// environment variables added before their own, so that the ones
+    /// given on the command line take precedence.
+    pub fn with_defaults(
+        &self,
+        mapped_dirs: Vec<(String, PathBuf)>,
+        env_vars: Vec<(String, String)>,
+    ) -> Self {
+        let mut wasi = self.clone();
+        wasi.mapped_dirs = mapped_dirs;
+        wasi.mapped_dirs.extend(self.mapped_dirs.iter().cloned());
+        wasi.env_vars = env_vars;
+        wasi.env_vars.extend(self.env_vars.iter().cloned());
+        wasi
+    }
+
     /// Helper function for instantiating a module with Wasi imports for the `Run` command.
     pub fn instantiate(
         &self,
@@ -85,6 +113,33 @@ impl Wasi {
             .preopen_dirs(self.pre_opened_directories.clone())?
             .map_dirs(self.mapped_dirs.clone())?;
 
+        if let Some(path) = &self.stdin_file {
+            let file = File::open(path)
+                .with_context(|| format!("failed to open `{}` for stdin", path.display()))?;
+            wasi_state_builder.stdin(Box::new(host_fs::File::new(
+                file,
+                path.clone(),
+                true,
+                false,
+                false,
+            )));
+        }
+        if let Some(path) = &self.stdout_file {
+            let file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(path)
+                .with_context(|| format!("failed to open `{}` for stdout", path.display()))?;
+            wasi_state_builder.stdout(Box::new(host_fs::File::new(
+                file,
+                path.clone(),
+                false,
+                true,
+                false,
+            )));
+        }
+
         #[cfg(feature = "experimental-io-devices")]
         {
             if self.enable_experimental_io_devices {
@@ -107,7 +162,7 @@ impl Wasi {
                 let err: anyhow::Error = match err.downcast::<WasiError>() {
                     Ok(WasiError::Exit(exit_code)) => {
                         // We should exit with the provided exit code
-                        std::process::exit(exit_code as _);
+                        exit(exit_code);
                     }
                     Ok(err) => err.into(),
                     Err(err) => err.into(),
@@ -130,3 +185,88 @@ impl Wasi {
         })
     }
 }
+
+/// How wasmer ends for an exit code given to `proc_exit`.
+#[derive(Debug, PartialEq)]
+enum Exit {
+    /// Exit with this status.
+    Status(i32),
+    /// Be killed by this signal, as a process whose shell status is
+    /// 128 + the signal.
+    #[cfg(unix)]
+    Signal(libc::c_int),
+}
+
+/// The signals ending a process without a core dump, which a module may
+/// ask to end with through their shell status.
+#[cfg(unix)]
+const EXIT_SIGNALS: &[libc::c_int] = &[libc::SIGHUP, libc::SIGINT, libc::SIGPIPE, libc::SIGTERM];
+
+impl Exit {
+    /// Unix only keeps the low 8 bits of a status, so codes above 255
+    /// are saturated rather than wrapped, which could make a failure
+    /// look like a success. Windows keeps 32-bit statuses as they are.
+    fn for_code(code: u32) -> Self {
+        #[cfg(unix)]
+        {
+            if let Some(signal) = code
+                .checked_sub(128)
+                .map(|signal| signal as libc::c_int)
+                .filter(|signal| EXIT_SIGNALS.contains(signal))
+            {
+                return Self::Signal(signal);
+            }
+            Self::Status(code.min(255) as i32)
+        }
+        #[cfg(not(unix))]
+        {
+            Self::Status(code as i32)
+        }
+    }
+}
+
+/// Ends wasmer as faithfully as possible to the exit code of a module.
+fn exit(code: u32) -> ! {
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+    match Exit::for_code(code) {
+        Exit::Status(status) => std::process::exit(status),
+        #[cfg(unix)]
+        Exit::Signal(signal) => {
+            unsafe {
+                libc::signal(signal, libc::SIG_DFL);
+                libc::raise(signal);
+            }
+            // The signal may be blocked, in which case the shell status
+            // is the closest.
+            std::process::exit(code as i32)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(Exit::for_code(0), Exit::Status(0));
+        assert_eq!(Exit::for_code(1), Exit::Status(1));
+        assert_eq!(Exit::for_code(126), Exit::Status(126));
+        assert_eq!(Exit::for_code(255), Exit::Status(255));
+        #[cfg(unix)]
+        {
+            assert_eq!(Exit::for_code(130), Exit::Signal(libc::SIGINT));
+            assert_eq!(Exit::for_code(141), Exit::Signal(libc::SIGPIPE));
+            // SIGSEGV would dump a core.
+            assert_eq!(Exit::for_code(139), Exit::Status(139));
+            assert_eq!(Exit::for_code(256), Exit::Status(255));
+            assert_eq!(Exit::for_code(u32::MAX), Exit::Status(255));
+        }
+        #[cfg(not(unix))]
+        {
+            assert_eq!(Exit::for_code(256), Exit::Status(256));
+            assert_eq!(Exit::for_code(u32::MAX), Exit::Status(-1));
+        }
+    }
+}
@@ -1,12 +1,13 @@
 //! Common module with common used structures across different
 //! commands.
 
-use anyhow::Result;
+use anyhow::{Error, Result};
 
 #[allow(unused_imports)]
 use crate::common::WasmFeatures;
 #[allow(unused_imports)]
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::string::ToString;
 #[allow(unused_imports)]
 use std::sync::Arc;
@@ -72,6 +73,13 @@ pub struct CompilerOptions {
     #[structopt(long, parse(from_os_str))]
     llvm_debug_dir: Option<PathBuf>,
 
+    /// Directory where the intermediate representations of each compiled
+    /// function will be written to: the LLVM IR and object files with LLVM,
+    /// the Cranelift IR and disassembly with Cranelift, and the annotated
+    /// machine code with Singlepass.
+    #[structopt(long, parse(from_os_str))]
+    dump_ir: Option<PathBuf>,
+
     #[structopt(flatten)]
     features: WasmFeatures,
 }
@@ -135,6 +143,26 @@ impl CompilerOptions {
         Ok((store, compiler_type))
     }
 
+    /// Gets the Store of the Staticlib engine for a given target,
+    /// emitting each function in its own section if `function_sections`
+    /// is set.
+    #[cfg(feature = "staticlib")]
+    pub fn get_staticlib_store_for_target(
+        &self,
+        target: Target,
+        function_sections: bool,
+    ) -> Result<(Store, CompilerType)> {
+        let (compiler_config, compiler_type) = self.get_compiler_config()?;
+        let features = self.get_features(compiler_config.default_features_for_target(&target))?;
+        let engine = wasmer_engine_staticlib::Staticlib::new(compiler_config)
+            .target(target)
+            .features(features)
+            .function_sections(function_sections)
+            .engine();
+        let store = Store::new(&engine);
+        Ok((store, compiler_type))
+    }
+
     fn get_engine_by_type(
         &self,
         target: Target,
@@ -175,9 +203,16 @@ impl CompilerOptions {
     }
 
     /// Get the Compiler Config for the current options
-    #[allow(unused_variables)]
     pub(crate) fn get_compiler_config(&self) -> Result<(Box<dyn CompilerConfig>, CompilerType)> {
-        let compiler = self.get_compiler()?;
+        self.get_compiler_config_for(self.get_compiler()?)
+    }
+
+    /// Get the Compiler Config of `compiler` for the current options
+    #[allow(unused_variables)]
+    pub(crate) fn get_compiler_config_for(
+        &self,
+        compiler: CompilerType,
+    ) -> Result<(Box<dyn CompilerConfig>, CompilerType)> {
         let compiler_config: Box<dyn CompilerConfig> = match compiler {
             CompilerType::Headless => bail!("The headless engine can't be chosen"),
             #[cfg(feature = "singlepass")]
@@ -186,6 +221,7 @@ impl CompilerOptions {
                 if self.enable_verifier {
                     config.enable_verifier();
                 }
+                config.dump_code(self.dump_ir.clone());
                 Box::new(config)
             }
             #[cfg(feature = "cranelift")]
@@ -194,6 +230,7 @@ impl CompilerOptions {
                 if self.enable_verifier {
                     config.enable_verifier();
                 }
+                config.dump_ir(self.dump_ir.clone());
                 Box::new(config)
             }
             #[cfg(feature = "llvm")]
@@ -291,8 +328,8 @@ impl CompilerOptions {
                     }
                 }
 
-                if let Some(ref llvm_debug_dir) = self.llvm_debug_dir {
-                    config.callbacks(Some(Arc::new(Callbacks::new(llvm_debug_dir.clone())?)));
+                if let Some(debug_dir) = self.dump_ir.as_ref().or(self.llvm_debug_dir.as_ref()) {
+                    config.callbacks(Some(Arc::new(Callbacks::new(debug_dir.clone())?)));
                 }
                 if self.enable_verifier {
                     config.enable_verifier();
@@ -314,7 +351,7 @@ impl CompilerOptions {
 }
 
 /// The compiler used for the store
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum CompilerType {
     /// Singlepass compiler
     Singlepass,
@@ -340,6 +377,18 @@ impl CompilerType {
     }
 }
 
+impl FromStr for CompilerType {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "singlepass" => Ok(Self::Singlepass),
+            "cranelift" => Ok(Self::Cranelift),
+            "llvm" => Ok(Self::LLVM),
+            _ => bail!("unknown compiler `{}`", s),
+        }
+    }
+}
+
 impl ToString for CompilerType {
     fn to_string(&self) -> String {
         match self {
@@ -391,6 +440,28 @@ impl StoreOptions {
         Ok((store, engine_type, compiler_type))
     }
 
+    /// Gets the compiler config of `compiler`, or of the compiler
+    /// selected by the options if it's `None`
+    pub fn get_compiler_config(
+        &self,
+        compiler: Option<CompilerType>,
+    ) -> Result<(Box<dyn CompilerConfig>, CompilerType)> {
+        match compiler {
+            Some(compiler) => self.compiler.get_compiler_config_for(compiler),
+            None => self.compiler.get_compiler_config(),
+        }
+    }
+
+    /// Gets the store for the host target, compiling with `compiler_config`
+    pub fn get_store_with_compiler_config(
+        &self,
+        compiler_config: Box<dyn CompilerConfig>,
+    ) -> Result<(Store, EngineType)> {
+        let (engine, engine_type) =
+            self.get_engine_with_compiler(Target::default(), compiler_config)?;
+        Ok((Store::new(&*engine), engine_type))
+    }
+
     fn get_engine_with_compiler(
         &self,
         target: Target,
@@ -21,17 +21,20 @@ use gimli::write::{Address, EhFrame, FrameTable};
 use loupe::MemoryUsage;
 #[cfg(feature = "rayon")]
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use std::fs;
+use std::path::Path;
 use std::sync::Arc;
 use wasmer_compiler::{
-    CallingConvention, ModuleTranslationState, RelocationTarget, Target, TrapInformation,
+    Architecture, CallingConvention, CpuFeature, ModuleTranslationState, RelocationTarget, Target,
+    TrapInformation,
 };
+use wasmer_compiler::{CancellationToken, CompileError, Relocation};
 use wasmer_compiler::{
     Compilation, CompileModuleInfo, CompiledFunction, CompiledFunctionFrameInfo,
     CompiledFunctionUnwindInfo, Compiler, Dwarf, FunctionBinaryReader, FunctionBody,
     FunctionBodyData, MiddlewareBinaryReader, ModuleMiddleware, ModuleMiddlewareChain,
     SectionIndex,
 };
-use wasmer_compiler::{CompileError, Relocation};
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::TrapCode;
 use wasmer_types::{FunctionIndex, LocalFunctionIndex, ModuleInfo, SignatureIndex};
@@ -70,11 +73,35 @@ impl Compiler for CraneliftCompiler {
         module_translation_state: &ModuleTranslationState,
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
     ) -> Result<Compilation, CompileError> {
+        self.compile_module_cancellable(
+            target,
+            compile_info,
+            module_translation_state,
+            function_body_inputs,
+            &CancellationToken::new(),
+        )
+    }
+
+    /// Compile the module using Cranelift, checking `cancellation` before
+    /// translating each function and before generating its code.
+    fn compile_module_cancellable(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation_state: &ModuleTranslationState,
+        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+        cancellation: &CancellationToken,
+    ) -> Result<Compilation, CompileError> {
+        cancellation.check()?;
         let isa = self
             .config()
             .isa(target)
             .map_err(|error| CompileError::Codegen(error.to_string()))?;
         let frontend_config = isa.frontend_config();
+        // Without SSE 4.1, Cranelift lowers float rounding to libcalls on
+        // x86, so the translation inlines it instead.
+        let native_float_rounding = target.triple().architecture != Architecture::X86_64
+            || target.cpu_features().contains(CpuFeature::SSE41);
         let memory_styles = &compile_info.memory_styles;
         let table_styles = &compile_info.table_styles;
         let module = &compile_info.module;
@@ -118,10 +145,12 @@ impl Compiler for CraneliftCompiler {
             .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
             .into_iter()
             .map(|(i, input)| {
+                cancellation.check()?;
                 let func_index = module.func_index(i);
                 let mut context = Context::new();
                 let mut func_env = FuncEnvironment::new(
                     isa.frontend_config(),
+                    native_float_rounding,
                     module,
                     &signatures,
                     &memory_styles,
@@ -147,11 +176,22 @@ impl Compiler for CraneliftCompiler {
                     &mut func_env,
                     i,
                 )?;
+                if let Some(dir) = &self.config.ir_dump_dir {
+                    dump_ir(dir, i, "clif", &context.func.display().to_string())?;
+                    context.want_disasm = true;
+                }
 
+                cancellation.check()?;
                 let mut code_buf: Vec<u8> = Vec::new();
                 context
                     .compile_and_emit(&*isa, &mut code_buf)
                     .map_err(|error| CompileError::Codegen(pretty_error(&context.func, error)))?;
+                if let Some(dir) = &self.config.ir_dump_dir {
+                    dump_ir(dir, i, "opt.clif", &context.func.display().to_string())?;
+                    if let Some(disasm) = &context.mach_compile_result.as_ref().unwrap().disasm {
+                        dump_ir(dir, i, "s", disasm)?;
+                    }
+                }
 
                 let result = context.mach_compile_result.as_ref().unwrap();
                 let func_relocs = result
@@ -205,7 +245,13 @@ impl Compiler for CraneliftCompiler {
                             unwind_info,
                         },
                         relocations: func_relocs,
-                        frame_info: CompiledFunctionFrameInfo { address_map, traps },
+                        frame_info: CompiledFunctionFrameInfo {
+                            address_map,
+                            traps,
+                            step_points: vec![],
+                            patch_points: vec![],
+                            frame_size: None,
+                        },
                     },
                     fde,
                 ))
@@ -219,10 +265,12 @@ impl Compiler for CraneliftCompiler {
             .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
             .par_iter()
             .map_init(FuncTranslator::new, |func_translator, (i, input)| {
+                cancellation.check()?;
                 let func_index = module.func_index(*i);
                 let mut context = Context::new();
                 let mut func_env = FuncEnvironment::new(
                     isa.frontend_config(),
+                    native_float_rounding,
                     module,
                     &signatures,
                     &memory_styles,
@@ -248,11 +296,22 @@ impl Compiler for CraneliftCompiler {
                     &mut func_env,
                     *i,
                 )?;
+                if let Some(dir) = &self.config.ir_dump_dir {
+                    dump_ir(dir, *i, "clif", &context.func.display().to_string())?;
+                    context.want_disasm = true;
+                }
 
+                cancellation.check()?;
                 let mut code_buf: Vec<u8> = Vec::new();
                 context
                     .compile_and_emit(&*isa, &mut code_buf)
                     .map_err(|error| CompileError::Codegen(pretty_error(&context.func, error)))?;
+                if let Some(dir) = &self.config.ir_dump_dir {
+                    dump_ir(dir, *i, "opt.clif", &context.func.display().to_string())?;
+                    if let Some(disasm) = &context.mach_compile_result.as_ref().unwrap().disasm {
+                        dump_ir(dir, *i, "s", disasm)?;
+                    }
+                }
 
                 let result = context.mach_compile_result.as_ref().unwrap();
                 let func_relocs = result
@@ -306,7 +365,13 @@ impl Compiler for CraneliftCompiler {
                             unwind_info,
                         },
                         relocations: func_relocs,
-                        frame_info: CompiledFunctionFrameInfo { address_map, traps },
+                        frame_info: CompiledFunctionFrameInfo {
+                            address_map,
+                            traps,
+                            step_points: vec![],
+                            patch_points: vec![],
+                            frame_size: None,
+                        },
                     },
                     fde,
                 ))
@@ -455,3 +520,17 @@ fn translate_ir_trapcode(trap: ir::TrapCode) -> TrapCode {
         // ir::TrapCode::User(user_code) => TrapCode::User(user_code),
     }
 }
+
+/// Writes `contents`, some IR of the local function `index`, to
+/// `function_<index>.<extension>` in `dir`.
+fn dump_ir(
+    dir: &Path,
+    index: LocalFunctionIndex,
+    extension: &str,
+    contents: &str,
+) -> Result<(), CompileError> {
+    let path = dir.join(format!("function_{}.{}", index.index(), extension));
+    fs::create_dir_all(dir)
+        .and_then(|()| fs::write(&path, contents))
+        .map_err(|e| CompileError::Codegen(format!("failed to write {}: {}", path.display(), e)))
+}
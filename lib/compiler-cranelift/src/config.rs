@@ -3,6 +3,7 @@ use cranelift_codegen::isa::{lookup, TargetIsa};
 use cranelift_codegen::settings::{self, Configurable};
 use cranelift_codegen::CodegenResult;
 use loupe::MemoryUsage;
+use std::path::PathBuf;
 use std::sync::Arc;
 use wasmer_compiler::{
     Architecture, Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, Target,
@@ -35,6 +36,9 @@ pub struct Cranelift {
     enable_verifier: bool,
     enable_pic: bool,
     opt_level: CraneliftOptLevel,
+    /// Where to write the IR of the compiled functions, if anywhere.
+    #[loupe(skip)]
+    pub(crate) ir_dump_dir: Option<PathBuf>,
     /// The middleware chain.
     pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
 }
@@ -48,6 +52,7 @@ impl Cranelift {
             enable_verifier: false,
             opt_level: CraneliftOptLevel::Speed,
             enable_pic: false,
+            ir_dump_dir: None,
             middlewares: vec![],
         }
     }
@@ -67,6 +72,17 @@ impl Cranelift {
         self
     }
 
+    /// Writes the Cranelift IR of each compiled function to `dir`, before
+    /// (`function_<index>.clif`) and after (`function_<index>.opt.clif`)
+    /// its optimization, along with its disassembly
+    /// (`function_<index>.s`), or stops writing it if `dir` is `None`.
+    ///
+    /// This is meant for debugging the code generation.
+    pub fn dump_ir(&mut self, dir: Option<PathBuf>) -> &mut Self {
+        self.ir_dump_dir = dir;
+        self
+    }
+
     /// Generates the ISA for the provided target
     pub fn isa(&self, target: &Target) -> CodegenResult<Box<dyn TargetIsa>> {
         let mut builder =
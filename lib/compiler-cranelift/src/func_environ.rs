@@ -40,6 +40,9 @@ pub struct FuncEnvironment<'module_environment> {
     /// Target-specified configuration.
     target_config: TargetFrontendConfig,
 
+    /// Whether the target rounds floats without libcalls.
+    native_float_rounding: bool,
+
     /// The module-level environment which this function-level environment belongs to.
     module: &'module_environment ModuleInfo,
 
@@ -122,6 +125,7 @@ pub struct FuncEnvironment<'module_environment> {
 impl<'module_environment> FuncEnvironment<'module_environment> {
     pub fn new(
         target_config: TargetFrontendConfig,
+        native_float_rounding: bool,
         module: &'module_environment ModuleInfo,
         signatures: &'module_environment PrimaryMap<SignatureIndex, ir::Signature>,
         memory_styles: &'module_environment PrimaryMap<MemoryIndex, MemoryStyle>,
@@ -129,6 +133,7 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
     ) -> Self {
         Self {
             target_config,
+            native_float_rounding,
             module,
             signatures,
             type_stack: vec![],
@@ -771,6 +776,10 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         index >= 1
     }
 
+    fn has_native_float_rounding(&self) -> bool {
+        self.native_float_rounding
+    }
+
     fn make_table(&mut self, func: &mut ir::Function, index: TableIndex) -> WasmResult<ir::Table> {
         let pointer_type = self.pointer_type();
 
@@ -1075,7 +1084,7 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         });
         Ok(func.create_heap(ir::HeapData {
             base: heap_base,
-            min_size: 0.into(),
+            min_size: Uimm64::new(self.module.memories[index].minimum.bytes().0 as u64),
             offset_guard_size,
             style: heap_style,
             index_type: I32,
@@ -82,7 +82,7 @@ use core::cmp;
 use core::convert::TryFrom;
 use core::{i32, u32};
 use cranelift_codegen::ir::condcodes::{FloatCC, IntCC};
-use cranelift_codegen::ir::immediates::Offset32;
+use cranelift_codegen::ir::immediates::{Ieee32, Ieee64, Offset32};
 use cranelift_codegen::ir::types::*;
 use cranelift_codegen::ir::{
     self, AtomicRmwOp, ConstantData, InstBuilder, JumpTableData, MemFlags, Value, ValueLabel,
@@ -94,7 +94,7 @@ use std::vec::Vec;
 
 use wasmer_compiler::wasmparser::{MemoryImmediate, Operator, Type as WPType};
 use wasmer_compiler::WasmResult;
-use wasmer_compiler::{wasm_unsupported, ModuleTranslationState};
+use wasmer_compiler::{is_constant_access_in_bounds, wasm_unsupported, ModuleTranslationState};
 use wasmer_types::{
     FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, Type as WasmerType,
 };
@@ -873,19 +873,35 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
         }
         Operator::F32Ceil | Operator::F64Ceil => {
             let (arg, _) = state.pop1();
-            state.push1(builder.ins().ceil(arg));
+            if environ.has_native_float_rounding() {
+                state.push1(builder.ins().ceil(arg));
+            } else {
+                state.push1(translate_inline_rounding(builder, arg, Rounding::Ceil));
+            }
         }
         Operator::F32Floor | Operator::F64Floor => {
             let (arg, _) = state.pop1();
-            state.push1(builder.ins().floor(arg));
+            if environ.has_native_float_rounding() {
+                state.push1(builder.ins().floor(arg));
+            } else {
+                state.push1(translate_inline_rounding(builder, arg, Rounding::Floor));
+            }
         }
         Operator::F32Trunc | Operator::F64Trunc => {
             let (arg, _) = state.pop1();
-            state.push1(builder.ins().trunc(arg));
+            if environ.has_native_float_rounding() {
+                state.push1(builder.ins().trunc(arg));
+            } else {
+                state.push1(translate_inline_rounding(builder, arg, Rounding::Trunc));
+            }
         }
         Operator::F32Nearest | Operator::F64Nearest => {
             let (arg, _) = state.pop1();
-            state.push1(builder.ins().nearest(arg));
+            if environ.has_native_float_rounding() {
+                state.push1(builder.ins().nearest(arg));
+            } else {
+                state.push1(translate_inline_rounding(builder, arg, Rounding::Nearest));
+            }
         }
         Operator::F32Abs | Operator::F64Abs => {
             let (val, _) = state.pop1();
@@ -2267,6 +2283,21 @@ fn get_heap_addr(
 ) -> (ir::Value, i32) {
     let offset_guard_size: u64 = builder.func.heaps[heap].offset_guard_size.into();
 
+    // Accesses at a constant address below the minimum size of the heap
+    // can't be out of bounds, so they skip `heap_addr` and its bounds check
+    // altogether.
+    if let Some(addr) = constant_address(addr32, builder) {
+        let heap_data = &builder.func.heaps[heap];
+        if is_constant_access_in_bounds(heap_data.min_size.into(), addr, offset.into(), width) {
+            let heap_base = heap_data.base;
+            let base = builder.ins().global_value(addr_ty, heap_base);
+            let base = builder
+                .ins()
+                .iadd_imm(base, i64::from(addr) + i64::from(offset));
+            return (base, 0);
+        }
+    }
+
     // How exactly the bounds check is performed here and what it's performed
     // on is a bit tricky. Generally we want to rely on access violations (e.g.
     // segfaults) to generate traps since that means we don't have to bounds
@@ -2338,6 +2369,18 @@ fn get_heap_addr(
     }
 }
 
+/// The value of `addr32`, if it is a constant.
+fn constant_address(addr32: ir::Value, builder: &FunctionBuilder) -> Option<u32> {
+    let inst = builder.func.dfg.value_def(addr32).inst()?;
+    match builder.func.dfg[inst] {
+        ir::InstructionData::UnaryImm {
+            opcode: ir::Opcode::Iconst,
+            imm,
+        } => Some(i64::from(imm) as u32),
+        _ => None,
+    }
+}
+
 /// Prepare for a load; factors out common functionality between load and load_extend operations.
 fn prepare_load<FE: FuncEnvironment + ?Sized>(
     memarg: &MemoryImmediate,
@@ -2428,6 +2471,72 @@ fn mem_op_size(opcode: ir::Opcode, ty: Type) -> u32 {
     }
 }
 
+/// How `translate_inline_rounding` rounds.
+#[derive(Clone, Copy)]
+enum Rounding {
+    Ceil,
+    Floor,
+    Trunc,
+    Nearest,
+}
+
+/// Rounds `arg` to an integer with plain float arithmetic, for targets
+/// where the rounding instructions would be lowered to libcalls.
+///
+/// Below 2^23 for `f32` and 2^52 for `f64`, adding then subtracting that
+/// power of two to the absolute value rounds it to the nearest integer,
+/// ties to even, which is then adjusted to `rounding`. Floats above it
+/// are integers already.
+fn translate_inline_rounding(
+    builder: &mut FunctionBuilder,
+    arg: Value,
+    rounding: Rounding,
+) -> Value {
+    let (magic, one, zero) = if builder.func.dfg.value_type(arg) == F32 {
+        (
+            builder.ins().f32const(Ieee32::with_float(8_388_608.0)),
+            builder.ins().f32const(Ieee32::with_float(1.0)),
+            builder.ins().f32const(Ieee32::with_float(0.0)),
+        )
+    } else {
+        (
+            builder
+                .ins()
+                .f64const(Ieee64::with_float(4_503_599_627_370_496.0)),
+            builder.ins().f64const(Ieee64::with_float(1.0)),
+            builder.ins().f64const(Ieee64::with_float(0.0)),
+        )
+    };
+    let abs = builder.ins().fabs(arg);
+    let biased = builder.ins().fadd(abs, magic);
+    let rounded_abs = builder.ins().fsub(biased, magic);
+    let rounded = builder.ins().fcopysign(rounded_abs, arg);
+    let adjusted = match rounding {
+        Rounding::Nearest => rounded,
+        Rounding::Ceil => {
+            let below = builder.ins().fcmp(FloatCC::LessThan, rounded, arg);
+            let above = builder.ins().fadd(rounded, one);
+            builder.ins().select(below, above, rounded)
+        }
+        Rounding::Floor => {
+            let above = builder.ins().fcmp(FloatCC::GreaterThan, rounded, arg);
+            let below = builder.ins().fsub(rounded, one);
+            builder.ins().select(above, below, rounded)
+        }
+        Rounding::Trunc => {
+            let away = builder.ins().fcmp(FloatCC::GreaterThan, rounded_abs, abs);
+            let toward_zero = builder.ins().fsub(rounded_abs, one);
+            builder.ins().select(away, toward_zero, rounded_abs)
+        }
+    };
+    // The result has the sign of `arg`, including when it's zero.
+    let small_result = builder.ins().fcopysign(adjusted, arg);
+    // Adding zero only quiets NaNs, which fail the comparison.
+    let large_result = builder.ins().fadd(arg, zero);
+    let small = builder.ins().fcmp(FloatCC::LessThan, abs, magic);
+    builder.ins().select(small, small_result, large_result)
+}
+
 fn translate_icmp(cc: IntCC, builder: &mut FunctionBuilder, state: &mut FuncTranslationState) {
     let ((arg0, _), (arg1, _)) = state.pop2();
     let val = builder.ins().icmp(cc, arg0, arg1);
@@ -94,6 +94,13 @@ pub trait FuncEnvironment: TargetEnvironment {
         signature.returns[index].purpose == ir::ArgumentPurpose::Normal
     }
 
+    /// Can the target round floats with `ceil`, `floor`, `trunc` and
+    /// `nearest` without lowering them to libcalls? If not, they're
+    /// translated to inline sequences of float arithmetic.
+    fn has_native_float_rounding(&self) -> bool {
+        true
+    }
+
     /// Should the code be structured to use a single `fallthrough_return` instruction at the end
     /// of the function body, rather than `return` instructions as needed? This is used by VMs
     /// to append custom epilogues.
@@ -337,6 +337,9 @@ where
             frame_info: CompiledFunctionFrameInfo {
                 address_map,
                 traps: vec![],
+                step_points: vec![],
+                patch_points: vec![],
+                frame_size: None,
             },
         },
         custom_sections,
@@ -0,0 +1,118 @@
+//! Listings of the machine code emitted per operator, written to files
+//! to debug the code generation.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use wasmer_compiler::wasmparser::Operator;
+use wasmer_types::entity::EntityRef;
+use wasmer_types::LocalFunctionIndex;
+
+/// How many bytes of code are listed per line.
+const BYTES_PER_LINE: usize = 16;
+
+/// The operators of a function and the code they emitted, recorded while
+/// compiling it.
+#[derive(Default)]
+pub(crate) struct FunctionListing {
+    operators: Vec<ListedOperator>,
+}
+
+struct ListedOperator {
+    module_offset: usize,
+    operator: String,
+    code_start: usize,
+    code_end: usize,
+}
+
+impl FunctionListing {
+    /// Records that `operator`, at `module_offset` in the module, emitted
+    /// the code from `code_start` to `code_end`.
+    pub(crate) fn record(
+        &mut self,
+        module_offset: usize,
+        operator: &Operator,
+        code_start: usize,
+        code_end: usize,
+    ) {
+        self.operators.push(ListedOperator {
+            module_offset,
+            operator: format!("{:?}", operator),
+            code_start,
+            code_end,
+        });
+    }
+
+    /// The listing of `body`, the finalized code of the function, as
+    /// assembly annotated with the operators.
+    pub(crate) fn render(&self, index: LocalFunctionIndex, body: &[u8]) -> String {
+        let mut listing = String::new();
+        writeln!(listing, "# local function {}", index.index()).unwrap();
+        let prologue_end = self.operators.first().map_or(0, |op| op.code_start);
+        write_code(&mut listing, "prologue", body, 0, prologue_end);
+        for op in &self.operators {
+            let header = format!("wasm {:#x}: {}", op.module_offset, op.operator);
+            write_code(&mut listing, &header, body, op.code_start, op.code_end);
+        }
+        let epilogue_start = self.operators.last().map_or(0, |op| op.code_end);
+        write_code(
+            &mut listing,
+            "trap handlers",
+            body,
+            epilogue_start,
+            body.len(),
+        );
+        listing
+    }
+
+    /// Writes the listing of `body` to `function_<index>.s` in `dir`.
+    pub(crate) fn write(
+        &self,
+        dir: &Path,
+        index: LocalFunctionIndex,
+        body: &[u8],
+    ) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("function_{}.s", index.index()));
+        fs::write(path, self.render(index, body))
+    }
+}
+
+/// Writes `body[start..end]` as `.byte` directives after a `header`
+/// comment.
+fn write_code(listing: &mut String, header: &str, body: &[u8], start: usize, end: usize) {
+    writeln!(listing, "# {:#06x}: {}", start, header).unwrap();
+    for line in body[start..end].chunks(BYTES_PER_LINE) {
+        let bytes: Vec<_> = line.iter().map(|byte| format!("{:#04x}", byte)).collect();
+        writeln!(listing, "\t.byte {}", bytes.join(", ")).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotates_code_with_operators() {
+        let mut listing = FunctionListing::default();
+        listing.record(0x20, &Operator::I32Const { value: 1 }, 2, 3);
+        listing.record(0x22, &Operator::Drop, 3, 3);
+        listing.record(0x23, &Operator::End, 3, 5);
+        let body = [0x55, 0x90, 0xc3, 0x01, 0x02, 0x0f, 0x0b];
+
+        assert_eq!(
+            listing.render(LocalFunctionIndex::new(1), &body),
+            "# local function 1\n\
+             # 0x0000: prologue\n\
+             \t.byte 0x55, 0x90\n\
+             # 0x0002: wasm 0x20: I32Const { value: 1 }\n\
+             \t.byte 0xc3\n\
+             # 0x0003: wasm 0x22: Drop\n\
+             # 0x0003: wasm 0x23: End\n\
+             \t.byte 0x01, 0x02\n\
+             # 0x0005: trap handlers\n\
+             \t.byte 0x0f, 0x0b\n"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Accounting of the machine code emitted per kind of operator.
+
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::collections::HashMap;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use wasmer_compiler::wasmparser::Operator;
+
+/// The machine code emitted for one kind of operator.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OperatorCodeSize {
+    /// How many operators of this kind were compiled.
+    pub count: u64,
+    /// How many bytes of machine code they emitted in total.
+    pub bytes: u64,
+}
+
+impl OperatorCodeSize {
+    /// The average number of bytes emitted per operator.
+    pub fn average(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.bytes as f64 / self.count as f64
+        }
+    }
+}
+
+/// A report of the machine code Singlepass emitted per kind of operator,
+/// aggregated over every function it compiled.
+///
+/// The report is shared between the clones of the handle, so it can be
+/// read after the configuration was given to an engine.
+#[derive(Debug, Default, Clone)]
+pub struct CodeSizeReport {
+    operators: Arc<Mutex<HashMap<String, OperatorCodeSize>>>,
+}
+
+impl CodeSizeReport {
+    /// Creates an empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The code size of each kind of operator, the most bloating first.
+    pub fn operators(&self) -> Vec<(String, OperatorCodeSize)> {
+        let mut operators: Vec<_> = self
+            .operators
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, size)| (name.clone(), *size))
+            .collect();
+        operators.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then(a.0.cmp(&b.0)));
+        operators
+    }
+
+    /// The code size of the operators named `name`, like `"I32Add"`.
+    pub fn operator(&self, name: &str) -> Option<OperatorCodeSize> {
+        self.operators.lock().unwrap().get(name).cloned()
+    }
+
+    /// The number of bytes emitted for all the operators.
+    pub fn total_bytes(&self) -> u64 {
+        self.operators
+            .lock()
+            .unwrap()
+            .values()
+            .map(|size| size.bytes)
+            .sum()
+    }
+
+    /// Empties the report.
+    pub fn clear(&self) {
+        self.operators.lock().unwrap().clear();
+    }
+
+    /// Adds the sizes recorded for one function to the report.
+    pub(crate) fn merge(&self, function: FunctionCodeSize) {
+        let mut operators = self.operators.lock().unwrap();
+        for (name, size) in function.operators {
+            let total = operators.entry(name).or_default();
+            total.count += size.count;
+            total.bytes += size.bytes;
+        }
+    }
+}
+
+impl MemoryUsage for CodeSizeReport {
+    fn size_of_val(&self, _tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + self
+                .operators
+                .lock()
+                .unwrap()
+                .keys()
+                .map(|name| mem::size_of::<(String, OperatorCodeSize)>() + name.capacity())
+                .sum::<usize>()
+    }
+}
+
+/// The sizes recorded while compiling one function, merged into the
+/// report once it is done so compiling in parallel doesn't contend on it.
+#[derive(Default)]
+pub(crate) struct FunctionCodeSize {
+    operators: HashMap<String, OperatorCodeSize>,
+}
+
+impl FunctionCodeSize {
+    /// Records that `bytes` bytes were emitted for `operator`.
+    pub(crate) fn record(&mut self, operator: &Operator, bytes: usize) {
+        let size = self.operators.entry(operator_name(operator)).or_default();
+        size.count += 1;
+        size.bytes += bytes as u64;
+    }
+}
+
+/// The name of the kind of an operator, its variant name.
+fn operator_name(operator: &Operator) -> String {
+    let mut name = format!("{:?}", operator);
+    let end = name
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(name.len());
+    name.truncate(end);
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregates_per_operator_kind() {
+        let report = CodeSizeReport::new();
+        let mut function = FunctionCodeSize::default();
+        function.record(&Operator::I32Const { value: 1 }, 5);
+        function.record(&Operator::I32Const { value: 2 }, 7);
+        function.record(&Operator::I32Add, 3);
+        report.merge(function);
+        let mut function = FunctionCodeSize::default();
+        function.record(&Operator::I32Add, 4);
+        report.merge(function);
+
+        assert_eq!(
+            report.operators(),
+            vec![
+                (
+                    "I32Const".to_owned(),
+                    OperatorCodeSize {
+                        count: 2,
+                        bytes: 12
+                    }
+                ),
+                ("I32Add".to_owned(), OperatorCodeSize { count: 2, bytes: 7 }),
+            ]
+        );
+        assert_eq!(report.operator("I32Add").unwrap().average(), 3.5);
+        assert_eq!(report.total_bytes(), 19);
+
+        report.clear();
+        assert!(report.operators().is_empty());
+    }
+}
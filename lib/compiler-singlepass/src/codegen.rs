@@ -1,8 +1,11 @@
 use crate::address_map::get_function_address_map;
 #[cfg(feature = "unwind")]
 use crate::dwarf::WriterRelocate;
+use crate::frame_layout::SLOT_SIZE;
 use crate::location::{Location, Reg};
-use crate::machine::{CodegenError, Label, Machine, MachineStackOffset, NATIVE_PAGE_SIZE};
+use crate::machine::{
+    CodegenError, IntegerComparison, Label, Machine, MachineStackOffset, NATIVE_PAGE_SIZE,
+};
 use crate::unwind::UnwindFrame;
 use crate::{common_decl::*, config::Singlepass};
 #[cfg(feature = "unwind")]
@@ -10,12 +13,15 @@ use gimli::write::Address;
 use smallvec::{smallvec, SmallVec};
 use std::cmp;
 use std::iter;
-use wasmer_compiler::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
+use wasmer_compiler::wasmparser::{
+    MemoryImmediate, Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType,
+};
 #[cfg(feature = "unwind")]
 use wasmer_compiler::CompiledFunctionUnwindInfo;
 use wasmer_compiler::{
-    CallingConvention, CompiledFunction, CompiledFunctionFrameInfo, FunctionBody, FunctionBodyData,
-    Relocation, RelocationTarget, SectionIndex,
+    is_constant_access_in_bounds, CallingConvention, CompiledFunction, CompiledFunctionFrameInfo,
+    FunctionBody, FunctionBodyData, PatchPoint, PatchPointKind, Relocation, RelocationTarget,
+    SectionIndex, SourceLoc, StepPoint, ValueLocation,
 };
 use wasmer_types::{
     entity::{EntityRef, PrimaryMap},
@@ -85,6 +91,26 @@ pub struct FuncGen<'a, M: Machine> {
 
     /// Calling convention to use.
     calling_convention: CallingConvention,
+
+    /// Offset of the Wasm instruction being compiled.
+    srcloc: u32,
+
+    /// The breakpoints emitted for single-stepping.
+    step_points: Vec<StepPoint>,
+
+    /// The nops reserved for breakpoints patched in at runtime.
+    patch_points: Vec<PatchPoint>,
+
+    /// Whether the patch point of the entry of the function is still to
+    /// be emitted, before its first operator.
+    entry_patch_point_pending: bool,
+
+    /// The deepest the stack pointer went below the frame pointer.
+    max_stack_usage: usize,
+
+    /// An integer comparison whose operands are still on the value stack,
+    /// so that a `br_if` or an `if` following it can branch on it directly.
+    pending_comparison: Option<PendingComparison>,
 }
 
 struct SpecialLabelSet {
@@ -250,6 +276,63 @@ struct I2O1<R: Reg, S: Reg> {
     ret: Location<R, S>,
 }
 
+/// An integer comparison operator which has not been emitted yet.
+#[derive(Copy, Clone, Debug)]
+struct PendingComparison {
+    size: Size,
+    comparison: IntegerComparison,
+    /// Whether this is an `eqz`, comparing a single operand to zero.
+    eqz: bool,
+}
+
+impl PendingComparison {
+    fn from_operator(op: &Operator) -> Option<Self> {
+        use IntegerComparison::*;
+        let (size, comparison, eqz) = match op {
+            Operator::I32Eqz => (Size::S32, Eq, true),
+            Operator::I32Eq => (Size::S32, Eq, false),
+            Operator::I32Ne => (Size::S32, Ne, false),
+            Operator::I32LtS => (Size::S32, LtS, false),
+            Operator::I32LeS => (Size::S32, LeS, false),
+            Operator::I32GtS => (Size::S32, GtS, false),
+            Operator::I32GeS => (Size::S32, GeS, false),
+            Operator::I32LtU => (Size::S32, LtU, false),
+            Operator::I32LeU => (Size::S32, LeU, false),
+            Operator::I32GtU => (Size::S32, GtU, false),
+            Operator::I32GeU => (Size::S32, GeU, false),
+            Operator::I64Eqz => (Size::S64, Eq, true),
+            Operator::I64Eq => (Size::S64, Eq, false),
+            Operator::I64Ne => (Size::S64, Ne, false),
+            Operator::I64LtS => (Size::S64, LtS, false),
+            Operator::I64LeS => (Size::S64, LeS, false),
+            Operator::I64GtS => (Size::S64, GtS, false),
+            Operator::I64GeS => (Size::S64, GeS, false),
+            Operator::I64LtU => (Size::S64, LtU, false),
+            Operator::I64LeU => (Size::S64, LeU, false),
+            Operator::I64GtU => (Size::S64, GtU, false),
+            Operator::I64GeU => (Size::S64, GeU, false),
+            _ => return None,
+        };
+        Some(Self {
+            size,
+            comparison,
+            eqz,
+        })
+    }
+}
+
+/// The condition of a `br_if` or an `if`.
+enum BranchCondition<R: Reg, S: Reg> {
+    /// An `i32` value, true if non-zero.
+    Value(Location<R, S>),
+    /// An integer comparison fused with the branch.
+    Comparison {
+        pending: PendingComparison,
+        loc_a: Location<R, S>,
+        loc_b: Location<R, S>,
+    },
+}
+
 impl<'a, M: Machine> FuncGen<'a, M> {
     fn get_stack_offset(&self) -> usize {
         self.stack_offset.0
@@ -259,11 +342,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
     ///
     /// If the returned locations are used for stack value, `release_location` needs to be called on them;
     /// Otherwise, if the returned locations are used for locals, `release_location` does not need to be called on them.
+    #[allow(clippy::type_complexity)]
     fn acquire_locations(
         &mut self,
         tys: &[(WpType, MachineValue)],
         zeroed: bool,
-    ) -> SmallVec<[Location<M::GPR, M::SIMD>; 1]> {
+    ) -> Result<SmallVec<[Location<M::GPR, M::SIMD>; 1]>, CodegenError> {
         let mut ret = smallvec![];
         let mut delta_stack_offset: usize = 0;
 
@@ -301,10 +385,10 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         }
         if zeroed {
             for i in 0..tys.len() {
-                self.machine.zero_location(Size::S64, ret[i]);
+                self.machine.zero_location(Size::S64, ret[i])?;
             }
         }
-        ret
+        Ok(ret)
     }
 
     /// Releases locations used for stack value.
@@ -471,38 +555,32 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         }
     }
 
+    #[allow(clippy::type_complexity)]
     fn init_locals(
         &mut self,
         n: usize,
         sig: FunctionType,
         calling_convention: CallingConvention,
-    ) -> Vec<Location<M::GPR, M::SIMD>> {
+    ) -> Result<Vec<Location<M::GPR, M::SIMD>>, CodegenError> {
         // How many machine stack slots will all the locals use?
         let num_mem_slots = (0..n)
             .filter(|&x| self.machine.is_local_on_stack(x))
             .count();
 
-        // Total size (in bytes) of the pre-allocated "static area" for this function's
-        // locals and callee-saved registers.
-        let mut static_area_size: usize = 0;
-
         // Callee-saved registers used for locals.
         // Keep this consistent with the "Save callee-saved registers" code below.
-        for i in 0..n {
-            // If a local is not stored on stack, then it is allocated to a callee-saved register.
-            if !self.machine.is_local_on_stack(i) {
-                static_area_size += 8;
-            }
-        }
+        let num_reg_locals = n - num_mem_slots;
 
-        // Callee-saved vmctx.
-        static_area_size += 8;
-
-        // Some ABI (like Windows) needs extrat reg save
-        static_area_size += 8 * self.machine.list_to_save(calling_convention).len();
+        // Total size of callee saved registers: the ones used for locals, the
+        // vmctx one, and the ones some ABI (like Windows) needs to save.
+        let callee_saved_regs_size = self.machine.frame_layout().save_area_size(
+            num_reg_locals,
+            self.machine.list_to_save(calling_convention).len(),
+        );
 
-        // Total size of callee saved registers.
-        let callee_saved_regs_size = static_area_size;
+        // Total size (in bytes) of the pre-allocated "static area" for this function's
+        // locals and callee-saved registers.
+        let mut static_area_size = callee_saved_regs_size;
 
         // Now we can determine concrete locations for locals.
         let locations: Vec<Location<M::GPR, M::SIMD>> = (0..n)
@@ -510,7 +588,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             .collect();
 
         // Add size of locals on stack.
-        static_area_size += num_mem_slots * 8;
+        static_area_size += num_mem_slots * SLOT_SIZE;
 
         // Allocate save area, without actually writing to it.
         static_area_size = self.machine.round_stack_adjust(static_area_size);
@@ -523,38 +601,31 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             .step_by(NATIVE_PAGE_SIZE / 8)
             .skip(1)
         {
-            self.machine.zero_location(Size::S64, locations[i]);
+            self.machine.zero_location(Size::S64, locations[i])?;
         }
 
         self.machine.adjust_stack(static_area_size as _);
 
-        // Save callee-saved registers.
+        // Save callee-saved registers, the one used for vmctx and the ones
+        // the calling convention needs, in consecutive slots so that the
+        // machine can pair the stores.
+        let mut saved = Vec::new();
         for loc in locations.iter() {
             if let Location::GPR(x) = *loc {
-                self.stack_offset.0 += 8;
-                self.machine.move_local(self.stack_offset.0 as i32, *loc);
+                saved.push(*loc);
                 self.state.stack_values.push(MachineValue::PreserveRegister(
                     self.machine.index_from_gpr(x),
                 ));
             }
         }
-
-        // Save the Reg use for vmctx.
-        self.stack_offset.0 += 8;
-        self.machine.move_local(
-            self.stack_offset.0 as i32,
-            Location::GPR(self.machine.get_vmctx_reg()),
-        );
+        saved.push(Location::GPR(self.machine.get_vmctx_reg()));
         self.state.stack_values.push(MachineValue::PreserveRegister(
             self.machine.index_from_gpr(self.machine.get_vmctx_reg()),
         ));
-
-        // Check if need to same some CallingConvention specific regs
-        let regs_to_save = self.machine.list_to_save(calling_convention);
-        for loc in regs_to_save.iter() {
-            self.stack_offset.0 += 8;
-            self.machine.move_local(self.stack_offset.0 as i32, *loc);
-        }
+        saved.extend(self.machine.list_to_save(calling_convention));
+        self.machine
+            .move_locals(self.stack_offset.0 as i32 + 8, &saved);
+        self.stack_offset.0 += 8 * saved.len();
 
         // Save the offset of register save area.
         self.save_area_offset = Some(MachineStackOffset(self.stack_offset.0));
@@ -582,7 +653,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 Type::I32 | Type::F32 => Size::S32,
                 Type::I64 | Type::F64 => Size::S64,
                 Type::ExternRef | Type::FuncRef => Size::S64,
-                _ => unimplemented!(),
+                _ => codegen_error!("singlepass init_locals unimplemented"),
             };
             let loc = self.machine.get_call_param_location(
                 i + 1,
@@ -591,7 +662,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 calling_convention,
             );
             self.machine
-                .move_location_extend(sz, false, loc, Size::S64, locations[i]);
+                .move_location_extend(sz, false, loc, Size::S64, locations[i])?;
         }
 
         // Load vmctx into it's GPR.
@@ -600,7 +671,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             self.machine
                 .get_simple_param_location(0, calling_convention),
             Location::GPR(self.machine.get_vmctx_reg()),
-        );
+        )?;
 
         // Initialize all normal locals to zero.
         let mut init_stack_loc_cnt = 0;
@@ -612,47 +683,91 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     last_stack_loc = cmp::min(last_stack_loc, locations[i]);
                 }
                 Location::GPR(_) => {
-                    self.machine.zero_location(Size::S64, locations[i]);
+                    self.machine.zero_location(Size::S64, locations[i])?;
                 }
                 _ => unreachable!(),
             }
         }
         if init_stack_loc_cnt > 0 {
             self.machine
-                .init_stack_loc(init_stack_loc_cnt, last_stack_loc);
+                .init_stack_loc(init_stack_loc_cnt, last_stack_loc)?;
         }
 
         // Add the size of all locals allocated to stack.
         self.stack_offset.0 += static_area_size - callee_saved_regs_size;
 
-        locations
+        Ok(locations)
     }
 
-    fn finalize_locals(&mut self, calling_convention: CallingConvention) {
+    fn finalize_locals(
+        &mut self,
+        calling_convention: CallingConvention,
+    ) -> Result<(), CodegenError> {
         // Unwind stack to the "save area".
         self.machine
-            .restore_saved_area(self.save_area_offset.as_ref().unwrap().0 as i32);
+            .restore_saved_area(self.save_area_offset.as_ref().unwrap().0 as i32)?;
+
+        // Restore the registers saved by `init_locals`, in reverse order.
+        let mut saved = self.machine.list_to_save(calling_convention);
+        saved.reverse();
+        saved.push(Location::GPR(self.machine.get_vmctx_reg()));
+        saved.extend(
+            self.locals
+                .iter()
+                .rev()
+                .filter(|loc| matches!(loc, Location::GPR(_))),
+        );
+        self.machine.pop_locations(&saved)
+    }
 
-        let regs_to_save = self.machine.list_to_save(calling_convention);
-        for loc in regs_to_save.iter().rev() {
-            self.machine.pop_location(*loc);
-        }
+    /// Set the source location of the Wasm to the given offset.
+    pub fn set_srcloc(&mut self, offset: u32) {
+        self.srcloc = offset;
+        self.machine.set_srcloc(offset);
+    }
 
-        // Restore register used by vmctx.
-        self.machine
-            .pop_location(Location::GPR(self.machine.get_vmctx_reg()));
+    /// Records the stack used by the value stack and the locals, plus
+    /// `extra` bytes pushed on top of them.
+    fn record_stack_usage(&mut self, extra: usize) {
+        let usage = self.machine.round_stack_adjust(self.get_stack_offset()) + extra;
+        self.max_stack_usage = cmp::max(self.max_stack_usage, usage);
+    }
 
-        // Restore callee-saved registers.
-        for loc in self.locals.iter().rev() {
-            if let Location::GPR(_) = *loc {
-                self.machine.pop_location(*loc);
-            }
+    /// The native stack used by the function, from the frame layout.
+    fn frame_size(&self) -> u32 {
+        let layout = self.machine.frame_layout();
+        (layout.frame_record_size + self.max_stack_usage) as u32
+    }
+
+    /// Emits a breakpoint after the instruction which has just been fed,
+    /// recording where the locals and the operand stack are.
+    pub fn emit_step_point(&mut self) {
+        if self.unreachable_depth > 0 || !self.has_control_frames() {
+            return;
         }
+        let code_offset = self.machine.assembler_get_offset().0 as u32;
+        self.machine.emit_debug_breakpoint();
+        self.step_points.push(StepPoint {
+            code_offset,
+            srcloc: SourceLoc::new(self.srcloc),
+            locals: self.locals.iter().map(|&loc| value_location(loc)).collect(),
+            stack: self
+                .value_stack
+                .iter()
+                .map(|&loc| value_location(loc))
+                .collect(),
+        });
     }
 
-    /// Set the source location of the Wasm to the given offset.
-    pub fn set_srcloc(&mut self, offset: u32) {
-        self.machine.set_srcloc(offset);
+    /// Reserves a patch point before the code of the operator being fed.
+    fn emit_patch_point(&mut self, kind: PatchPointKind) {
+        let code_offset = self.machine.assembler_get_offset().0 as u32;
+        self.machine.emit_patch_point();
+        self.patch_points.push(PatchPoint {
+            code_offset,
+            kind,
+            srcloc: SourceLoc::new(self.srcloc),
+        });
     }
 
     fn get_location_released(
@@ -672,15 +787,116 @@ impl<'a, M: Machine> FuncGen<'a, M> {
     }
 
     /// Prepare data for binary operator with 2 inputs and 1 output.
-    fn i2o1_prepare(&mut self, ty: WpType) -> I2O1<M::GPR, M::SIMD> {
+    fn i2o1_prepare(&mut self, ty: WpType) -> Result<I2O1<M::GPR, M::SIMD>, CodegenError> {
         let loc_b = self.pop_value_released();
         let loc_a = self.pop_value_released();
         let ret = self.acquire_locations(
             &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
             false,
-        )[0];
+        )?[0];
         self.value_stack.push(ret);
-        I2O1 { loc_a, loc_b, ret }
+        Ok(I2O1 { loc_a, loc_b, ret })
+    }
+
+    /// Emits the comparison deferred by `feed_operator`, for an operator
+    /// which is not a branch on its result.
+    fn emit_pending_comparison(&mut self, pending: PendingComparison) -> Result<(), CodegenError> {
+        let ty = match pending.size {
+            Size::S32 => WpType::I32,
+            _ => WpType::I64,
+        };
+        let (loc_a, loc_b, ret) = if pending.eqz {
+            let loc_a = self.pop_value_released();
+            let ret = self.acquire_locations(
+                &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
+                false,
+            )?[0];
+            self.value_stack.push(ret);
+            let zero = match pending.size {
+                Size::S32 => Location::Imm32(0),
+                _ => Location::Imm64(0),
+            };
+            (loc_a, zero, ret)
+        } else {
+            let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(ty)?;
+            (loc_a, loc_b, ret)
+        };
+        match (pending.size, pending.comparison) {
+            (Size::S32, IntegerComparison::Eq) => self.machine.i32_cmp_eq(loc_a, loc_b, ret),
+            (Size::S32, IntegerComparison::Ne) => self.machine.i32_cmp_ne(loc_a, loc_b, ret),
+            (Size::S32, IntegerComparison::LtS) => self.machine.i32_cmp_lt_s(loc_a, loc_b, ret),
+            (Size::S32, IntegerComparison::LeS) => self.machine.i32_cmp_le_s(loc_a, loc_b, ret),
+            (Size::S32, IntegerComparison::GtS) => self.machine.i32_cmp_gt_s(loc_a, loc_b, ret),
+            (Size::S32, IntegerComparison::GeS) => self.machine.i32_cmp_ge_s(loc_a, loc_b, ret),
+            (Size::S32, IntegerComparison::LtU) => self.machine.i32_cmp_lt_u(loc_a, loc_b, ret),
+            (Size::S32, IntegerComparison::LeU) => self.machine.i32_cmp_le_u(loc_a, loc_b, ret),
+            (Size::S32, IntegerComparison::GtU) => self.machine.i32_cmp_gt_u(loc_a, loc_b, ret),
+            (Size::S32, IntegerComparison::GeU) => self.machine.i32_cmp_ge_u(loc_a, loc_b, ret),
+            (_, IntegerComparison::Eq) => self.machine.i64_cmp_eq(loc_a, loc_b, ret),
+            (_, IntegerComparison::Ne) => self.machine.i64_cmp_ne(loc_a, loc_b, ret),
+            (_, IntegerComparison::LtS) => self.machine.i64_cmp_lt_s(loc_a, loc_b, ret),
+            (_, IntegerComparison::LeS) => self.machine.i64_cmp_le_s(loc_a, loc_b, ret),
+            (_, IntegerComparison::GtS) => self.machine.i64_cmp_gt_s(loc_a, loc_b, ret),
+            (_, IntegerComparison::GeS) => self.machine.i64_cmp_ge_s(loc_a, loc_b, ret),
+            (_, IntegerComparison::LtU) => self.machine.i64_cmp_lt_u(loc_a, loc_b, ret),
+            (_, IntegerComparison::LeU) => self.machine.i64_cmp_le_u(loc_a, loc_b, ret),
+            (_, IntegerComparison::GtU) => self.machine.i64_cmp_gt_u(loc_a, loc_b, ret),
+            (_, IntegerComparison::GeU) => self.machine.i64_cmp_ge_u(loc_a, loc_b, ret),
+        }
+    }
+
+    /// Pops the condition of a `br_if` or an `if`, which is the operands
+    /// of `fused` if the comparison was deferred for it.
+    fn pop_branch_condition(
+        &mut self,
+        fused: Option<PendingComparison>,
+    ) -> BranchCondition<M::GPR, M::SIMD> {
+        match fused {
+            Some(pending) => {
+                let loc_b = if pending.eqz {
+                    match pending.size {
+                        Size::S32 => Location::Imm32(0),
+                        _ => Location::Imm64(0),
+                    }
+                } else {
+                    self.pop_value_released()
+                };
+                let loc_a = self.pop_value_released();
+                BranchCondition::Comparison {
+                    pending,
+                    loc_a,
+                    loc_b,
+                }
+            }
+            None => BranchCondition::Value(self.pop_value_released()),
+        }
+    }
+
+    /// Jumps to `label` if the condition of a `br_if` or an `if` is false.
+    fn emit_jmp_unless(
+        &mut self,
+        cond: BranchCondition<M::GPR, M::SIMD>,
+        label: Label,
+    ) -> Result<(), CodegenError> {
+        match cond {
+            BranchCondition::Value(loc) => {
+                self.machine
+                    .emit_relaxed_cmp(Size::S32, Location::Imm32(0), loc)?;
+                self.machine.jmp_on_equal(label);
+            }
+            BranchCondition::Comparison {
+                pending,
+                loc_a,
+                loc_b,
+            } => self.machine.emit_compare_and_branch(
+                pending.size,
+                pending.comparison.inverse(),
+                loc_a,
+                loc_b,
+                label,
+            )?,
+        }
+        Ok(())
     }
 
     fn mark_trappable(&mut self) {
@@ -722,7 +938,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
     fn emit_call_native<
         I: Iterator<Item = Location<M::GPR, M::SIMD>>,
         J: Iterator<Item = WpType>,
-        F: FnOnce(&mut Self),
+        F: FnOnce(&mut Self) -> Result<(), CodegenError>,
     >(
         &mut self,
         cb: F,
@@ -733,17 +949,18 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         self.state.stack_values.push(MachineValue::ExplicitShadow);
 
         let params: Vec<_> = params.collect();
-        let params_size: Vec<_> = params_type
-            .map(|x| match x {
+        let mut params_size = Vec::new();
+        for x in params_type {
+            params_size.push(match x {
                 WpType::F32 | WpType::I32 => Size::S32,
-                WpType::V128 => unimplemented!(),
+                WpType::V128 => codegen_error!("singlepass emit_call_native unimplemented"),
                 _ => Size::S64,
-            })
-            .collect();
+            });
+        }
 
         // Save used GPRs. Preserve correct stack alignment
         let used_gprs = self.machine.get_used_gprs();
-        let mut used_stack = self.machine.push_used_gpr(&used_gprs);
+        let mut used_stack = self.machine.push_used_gpr(&used_gprs)?;
         for r in used_gprs.iter() {
             let content = self.state.register_values[self.machine.index_from_gpr(*r).0].clone();
             if content == MachineValue::Undefined {
@@ -757,7 +974,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         // Save used SIMD registers.
         let used_simds = self.machine.get_used_simd();
         if used_simds.len() > 0 {
-            used_stack += self.machine.push_used_simd(&used_simds);
+            used_stack += self.machine.push_used_simd(&used_simds)?;
 
             for r in used_simds.iter().rev() {
                 let content =
@@ -802,6 +1019,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             stack_offset += 16 - stack_unaligned;
         }
         self.machine.adjust_stack(stack_offset as u32);
+        self.record_stack_usage(used_stack + stack_offset + stack_padding);
 
         let mut call_movs: Vec<(Location<M::GPR, M::SIMD>, M::GPR)> = vec![];
         // Prepare register & stack parameters.
@@ -848,7 +1066,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         }
                     }
                     self.machine
-                        .move_location_for_native(params_size[i], *param, loc);
+                        .move_location_for_native(params_size[i], *param, loc)?;
                 }
                 _ => {
                     return Err(CodegenError {
@@ -865,7 +1083,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         for (loc, gpr) in call_movs {
             if loc != Location::GPR(gpr) {
                 self.machine
-                    .move_location(Size::S64, loc, Location::GPR(gpr));
+                    .move_location(Size::S64, loc, Location::GPR(gpr))?;
             }
         }
 
@@ -875,14 +1093,14 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             Location::GPR(self.machine.get_vmctx_reg()),
             self.machine
                 .get_simple_param_location(0, calling_convention),
-        ); // vmctx
+        )?; // vmctx
 
         if stack_padding > 0 {
             self.machine.adjust_stack(stack_padding as u32);
         }
         // release the GPR used for call
         self.machine.release_gpr(self.machine.get_grp_for_call());
-        cb(self);
+        cb(self)?;
 
         // Offset needs to be after the 'call' instruction.
         // TODO: Now the state information is also inserted for internal calls (e.g. MemoryGrow). Is this expected?
@@ -920,14 +1138,14 @@ impl<'a, M: Machine> FuncGen<'a, M> {
 
         // Restore SIMDs.
         if !used_simds.is_empty() {
-            self.machine.pop_used_simd(&used_simds);
+            self.machine.pop_used_simd(&used_simds)?;
             for _ in 0..used_simds.len() {
                 self.state.stack_values.pop().unwrap();
             }
         }
 
         // Restore GPRs.
-        self.machine.pop_used_gpr(&used_gprs);
+        self.machine.pop_used_gpr(&used_gprs)?;
         for _ in used_gprs.iter().rev() {
             self.state.stack_values.pop().unwrap();
         }
@@ -951,18 +1169,37 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         params_type: J,
     ) -> Result<(), CodegenError> {
         self.emit_call_native(
-            |this| this.machine.emit_call_label(label),
+            |this| {
+                this.machine.emit_call_label(label);
+                Ok(())
+            },
             params,
             params_type,
         )?;
         Ok(())
     }
 
-    /// Emits a memory operation.
-    fn op_memory<F: FnOnce(&mut Self, bool, bool, i32, Label)>(&mut self, cb: F) {
+    /// Emits a memory operation of `access_size` bytes at `addr`.
+    fn op_memory<F: FnOnce(&mut Self, bool, bool, i32, Label) -> Result<(), CodegenError>>(
+        &mut self,
+        addr: Location<M::GPR, M::SIMD>,
+        memarg: &MemoryImmediate,
+        access_size: u32,
+        cb: F,
+    ) -> Result<(), CodegenError> {
         let need_check = match self.memory_styles[MemoryIndex::new(0)] {
             MemoryStyle::Static { .. } => false,
-            MemoryStyle::Dynamic { .. } => true,
+            MemoryStyle::Dynamic { .. } => match addr {
+                // Constant addresses below the minimum size of the memory
+                // are always in bounds.
+                Location::Imm32(addr) => !is_constant_access_in_bounds(
+                    self.module.memories[MemoryIndex::new(0)].minimum.bytes().0 as u64,
+                    addr,
+                    memarg.offset,
+                    access_size,
+                ),
+                _ => true,
+            },
         };
 
         let offset = if self.module.num_imported_memories != 0 {
@@ -978,7 +1215,8 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             self.module.num_imported_memories != 0,
             offset as i32,
             self.special_labels.heap_access_oob,
-        );
+        )?;
+        Ok(())
     }
 
     pub fn get_state_diff(&mut self) -> usize {
@@ -996,14 +1234,14 @@ impl<'a, M: Machine> FuncGen<'a, M> {
     }
 
     fn emit_head(&mut self) -> Result<(), CodegenError> {
-        self.machine.emit_function_prolog();
+        self.machine.emit_function_prolog()?;
 
         // Initialize locals.
         self.locals = self.init_locals(
             self.local_types.len(),
             self.signature.clone(),
             self.calling_convention,
-        );
+        )?;
 
         // Mark vmctx register. The actual loading of the vmctx value is handled by init_local.
         self.state.register_values[self.machine.index_from_gpr(self.machine.get_vmctx_reg()).0] =
@@ -1110,17 +1348,34 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             relocations: vec![],
             special_labels,
             calling_convention,
+            srcloc: 0,
+            step_points: vec![],
+            patch_points: vec![],
+            entry_patch_point_pending: config.patch_function_entries,
+            max_stack_usage: 0,
+            pending_comparison: None,
         };
         fg.emit_head()?;
         Ok(fg)
     }
 
+    /// The size of the code emitted so far.
+    pub fn code_offset(&self) -> usize {
+        self.machine.assembler_get_offset().0
+    }
+
     pub fn has_control_frames(&self) -> bool {
         !self.control_stack.is_empty()
     }
 
     pub fn feed_operator(&mut self, op: Operator) -> Result<(), CodegenError> {
         assert!(self.fp_stack.len() <= self.value_stack.len());
+        self.record_stack_usage(0);
+
+        if self.entry_patch_point_pending {
+            self.entry_patch_point_pending = false;
+            self.emit_patch_point(PatchPointKind::FunctionEntry);
+        }
 
         self.state.wasm_inst_offset = self.state.wasm_inst_offset.wrapping_add(1);
 
@@ -1156,6 +1411,27 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             was_unreachable = false;
         }
 
+        // A comparison is only emitted once the operator using its result is
+        // known: a `br_if` or an `if` branches on the comparison itself.
+        let fused = match self.pending_comparison.take() {
+            Some(pending) => match op {
+                Operator::BrIf { .. } | Operator::If { .. } => Some(pending),
+                _ => {
+                    self.emit_pending_comparison(pending)?;
+                    None
+                }
+            },
+            None => None,
+        };
+        // Step points report the value stack after each operator, so it
+        // must hold the result of the comparison
+        if self.machine.arch_supports_compare_and_branch() && !self.config.single_step {
+            if let Some(pending) = PendingComparison::from_operator(&op) {
+                self.pending_comparison = Some(pending);
+                return Ok(());
+            }
+        }
+
         match op {
             Operator::GlobalGet { global_index } => {
                 let global_index = GlobalIndex::from_u32(global_index);
@@ -1167,10 +1443,15 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let loc = self.acquire_locations(
                     &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(loc);
 
-                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                let tmp = self
+                    .machine
+                    .acquire_temp_gpr()
+                    .ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
 
                 let src = if let Some(local_global_index) =
                     self.module.local_global_index(global_index)
@@ -1180,7 +1461,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         Size::S64,
                         Location::Memory(self.machine.get_vmctx_reg(), offset as i32),
                         Location::GPR(tmp),
-                    );
+                    )?;
                     Location::Memory(tmp, 0)
                 } else {
                     // Imported globals require one level of indirection.
@@ -1191,17 +1472,22 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         Size::S64,
                         Location::Memory(self.machine.get_vmctx_reg(), offset as i32),
                         Location::GPR(tmp),
-                    );
+                    )?;
                     Location::Memory(tmp, 0)
                 };
 
-                self.machine.emit_relaxed_mov(Size::S64, src, loc);
+                self.machine.emit_relaxed_mov(Size::S64, src, loc)?;
 
                 self.machine.release_gpr(tmp);
             }
             Operator::GlobalSet { global_index } => {
                 let global_index = GlobalIndex::from_u32(global_index);
-                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                let tmp = self
+                    .machine
+                    .acquire_temp_gpr()
+                    .ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
                 let dst = if let Some(local_global_index) =
                     self.module.local_global_index(global_index)
                 {
@@ -1210,7 +1496,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         Size::S64,
                         Location::Memory(self.machine.get_vmctx_reg(), offset as i32),
                         Location::GPR(tmp),
-                    );
+                    )?;
                     Location::Memory(tmp, 0)
                 } else {
                     // Imported globals require one level of indirection.
@@ -1221,7 +1507,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         Size::S64,
                         Location::Memory(self.machine.get_vmctx_reg(), offset as i32),
                         Location::GPR(tmp),
-                    );
+                    )?;
                     Location::Memory(tmp, 0)
                 };
                 let ty = type_to_wp_type(self.module.globals[global_index].ty);
@@ -1240,12 +1526,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             },
                             loc,
                             dst,
-                        );
+                        )?;
                     } else {
-                        self.machine.emit_relaxed_mov(Size::S64, loc, dst);
+                        self.machine.emit_relaxed_mov(Size::S64, loc, dst)?;
                     }
                 } else {
-                    self.machine.emit_relaxed_mov(Size::S64, loc, dst);
+                    self.machine.emit_relaxed_mov(Size::S64, loc, dst)?;
                 }
                 self.machine.release_gpr(tmp);
             }
@@ -1254,9 +1540,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.machine
-                    .emit_relaxed_mov(Size::S64, self.locals[local_index], ret);
+                    .emit_relaxed_mov(Size::S64, self.locals[local_index], ret)?;
                 self.value_stack.push(ret);
                 if self.local_types[local_index].is_float() {
                     self.fp_stack
@@ -1281,14 +1567,14 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             },
                             loc,
                             self.locals[local_index],
-                        );
+                        )?;
                     } else {
                         self.machine
-                            .emit_relaxed_mov(Size::S64, loc, self.locals[local_index]);
+                            .emit_relaxed_mov(Size::S64, loc, self.locals[local_index])?;
                     }
                 } else {
                     self.machine
-                        .emit_relaxed_mov(Size::S64, loc, self.locals[local_index]);
+                        .emit_relaxed_mov(Size::S64, loc, self.locals[local_index])?;
                 }
             }
             Operator::LocalTee { local_index } => {
@@ -1309,14 +1595,14 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             },
                             loc,
                             self.locals[local_index],
-                        );
+                        )?;
                     } else {
                         self.machine
-                            .emit_relaxed_mov(Size::S64, loc, self.locals[local_index]);
+                            .emit_relaxed_mov(Size::S64, loc, self.locals[local_index])?;
                     }
                 } else {
                     self.machine
-                        .emit_relaxed_mov(Size::S64, loc, self.locals[local_index]);
+                        .emit_relaxed_mov(Size::S64, loc, self.locals[local_index])?;
                 }
             }
             Operator::I32Const { value } => {
@@ -1326,88 +1612,88 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     .push(WasmAbstractValue::Const(value as u32 as u64));
             }
             Operator::I32Add => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.emit_binop_add32(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.emit_binop_add32(loc_a, loc_b, ret)?;
             }
             Operator::I32Sub => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.emit_binop_sub32(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.emit_binop_sub32(loc_a, loc_b, ret)?;
             }
             Operator::I32Mul => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.emit_binop_mul32(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.emit_binop_mul32(loc_a, loc_b, ret)?;
             }
             Operator::I32DivU => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
                 let offset = self.machine.emit_binop_udiv32(
                     loc_a,
                     loc_b,
                     ret,
                     self.special_labels.integer_division_by_zero,
                     self.special_labels.integer_overflow,
-                );
+                )?;
                 self.mark_offset_trappable(offset);
             }
             Operator::I32DivS => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
                 let offset = self.machine.emit_binop_sdiv32(
                     loc_a,
                     loc_b,
                     ret,
                     self.special_labels.integer_division_by_zero,
                     self.special_labels.integer_overflow,
-                );
+                )?;
                 self.mark_offset_trappable(offset);
             }
             Operator::I32RemU => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
                 let offset = self.machine.emit_binop_urem32(
                     loc_a,
                     loc_b,
                     ret,
                     self.special_labels.integer_division_by_zero,
                     self.special_labels.integer_overflow,
-                );
+                )?;
                 self.mark_offset_trappable(offset);
             }
             Operator::I32RemS => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
                 let offset = self.machine.emit_binop_srem32(
                     loc_a,
                     loc_b,
                     ret,
                     self.special_labels.integer_division_by_zero,
                     self.special_labels.integer_overflow,
-                );
+                )?;
                 self.mark_offset_trappable(offset);
             }
             Operator::I32And => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.emit_binop_and32(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.emit_binop_and32(loc_a, loc_b, ret)?;
             }
             Operator::I32Or => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.emit_binop_or32(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.emit_binop_or32(loc_a, loc_b, ret)?;
             }
             Operator::I32Xor => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.emit_binop_xor32(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.emit_binop_xor32(loc_a, loc_b, ret)?;
             }
             Operator::I32Eq => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_cmp_eq(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_cmp_eq(loc_a, loc_b, ret)?;
             }
             Operator::I32Ne => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_cmp_ne(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_cmp_ne(loc_a, loc_b, ret)?;
             }
             Operator::I32Eqz => {
                 let loc_a = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
-                self.machine.i32_cmp_eq(loc_a, Location::Imm32(0), ret);
+                )?[0];
+                self.machine.i32_cmp_eq(loc_a, Location::Imm32(0), ret)?;
                 self.value_stack.push(ret);
             }
             Operator::I32Clz => {
@@ -1415,79 +1701,79 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.i32_clz(loc, ret);
+                self.machine.i32_clz(loc, ret)?;
             }
             Operator::I32Ctz => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.i32_ctz(loc, ret);
+                self.machine.i32_ctz(loc, ret)?;
             }
             Operator::I32Popcnt => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.i32_popcnt(loc, ret);
+                self.machine.i32_popcnt(loc, ret)?;
             }
             Operator::I32Shl => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_shl(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_shl(loc_a, loc_b, ret)?;
             }
             Operator::I32ShrU => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_shr(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_shr(loc_a, loc_b, ret)?;
             }
             Operator::I32ShrS => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_sar(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_sar(loc_a, loc_b, ret)?;
             }
             Operator::I32Rotl => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_rol(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_rol(loc_a, loc_b, ret)?;
             }
             Operator::I32Rotr => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_ror(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_ror(loc_a, loc_b, ret)?;
             }
             Operator::I32LtU => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_cmp_lt_u(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_cmp_lt_u(loc_a, loc_b, ret)?;
             }
             Operator::I32LeU => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_cmp_le_u(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_cmp_le_u(loc_a, loc_b, ret)?;
             }
             Operator::I32GtU => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_cmp_gt_u(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_cmp_gt_u(loc_a, loc_b, ret)?;
             }
             Operator::I32GeU => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_cmp_ge_u(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_cmp_ge_u(loc_a, loc_b, ret)?;
             }
             Operator::I32LtS => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_cmp_lt_s(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_cmp_lt_s(loc_a, loc_b, ret)?;
             }
             Operator::I32LeS => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_cmp_le_s(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_cmp_le_s(loc_a, loc_b, ret)?;
             }
             Operator::I32GtS => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_cmp_gt_s(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_cmp_gt_s(loc_a, loc_b, ret)?;
             }
             Operator::I32GeS => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.i32_cmp_ge_s(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.i32_cmp_ge_s(loc_a, loc_b, ret)?;
             }
             Operator::I64Const { value } => {
                 let value = value as u64;
@@ -1495,88 +1781,88 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 self.state.wasm_stack.push(WasmAbstractValue::Const(value));
             }
             Operator::I64Add => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.emit_binop_add64(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.emit_binop_add64(loc_a, loc_b, ret)?;
             }
             Operator::I64Sub => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.emit_binop_sub64(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.emit_binop_sub64(loc_a, loc_b, ret)?;
             }
             Operator::I64Mul => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.emit_binop_mul64(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.emit_binop_mul64(loc_a, loc_b, ret)?;
             }
             Operator::I64DivU => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
                 let offset = self.machine.emit_binop_udiv64(
                     loc_a,
                     loc_b,
                     ret,
                     self.special_labels.integer_division_by_zero,
                     self.special_labels.integer_overflow,
-                );
+                )?;
                 self.mark_offset_trappable(offset);
             }
             Operator::I64DivS => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
                 let offset = self.machine.emit_binop_sdiv64(
                     loc_a,
                     loc_b,
                     ret,
                     self.special_labels.integer_division_by_zero,
                     self.special_labels.integer_overflow,
-                );
+                )?;
                 self.mark_offset_trappable(offset);
             }
             Operator::I64RemU => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
                 let offset = self.machine.emit_binop_urem64(
                     loc_a,
                     loc_b,
                     ret,
                     self.special_labels.integer_division_by_zero,
                     self.special_labels.integer_overflow,
-                );
+                )?;
                 self.mark_offset_trappable(offset);
             }
             Operator::I64RemS => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
                 let offset = self.machine.emit_binop_srem64(
                     loc_a,
                     loc_b,
                     ret,
                     self.special_labels.integer_division_by_zero,
                     self.special_labels.integer_overflow,
-                );
+                )?;
                 self.mark_offset_trappable(offset);
             }
             Operator::I64And => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.emit_binop_and64(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.emit_binop_and64(loc_a, loc_b, ret)?;
             }
             Operator::I64Or => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.emit_binop_or64(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.emit_binop_or64(loc_a, loc_b, ret)?;
             }
             Operator::I64Xor => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.emit_binop_xor64(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.emit_binop_xor64(loc_a, loc_b, ret)?;
             }
             Operator::I64Eq => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_cmp_eq(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_cmp_eq(loc_a, loc_b, ret)?;
             }
             Operator::I64Ne => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_cmp_ne(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_cmp_ne(loc_a, loc_b, ret)?;
             }
             Operator::I64Eqz => {
                 let loc_a = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
-                self.machine.i64_cmp_eq(loc_a, Location::Imm64(0), ret);
+                )?[0];
+                self.machine.i64_cmp_eq(loc_a, Location::Imm64(0), ret)?;
                 self.value_stack.push(ret);
             }
             Operator::I64Clz => {
@@ -1584,88 +1870,88 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.i64_clz(loc, ret);
+                self.machine.i64_clz(loc, ret)?;
             }
             Operator::I64Ctz => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.i64_ctz(loc, ret);
+                self.machine.i64_ctz(loc, ret)?;
             }
             Operator::I64Popcnt => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.i64_popcnt(loc, ret);
+                self.machine.i64_popcnt(loc, ret)?;
             }
             Operator::I64Shl => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_shl(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_shl(loc_a, loc_b, ret)?;
             }
             Operator::I64ShrU => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_shr(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_shr(loc_a, loc_b, ret)?;
             }
             Operator::I64ShrS => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_sar(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_sar(loc_a, loc_b, ret)?;
             }
             Operator::I64Rotl => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_rol(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_rol(loc_a, loc_b, ret)?;
             }
             Operator::I64Rotr => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_ror(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_ror(loc_a, loc_b, ret)?;
             }
             Operator::I64LtU => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_cmp_lt_u(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_cmp_lt_u(loc_a, loc_b, ret)?;
             }
             Operator::I64LeU => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_cmp_le_u(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_cmp_le_u(loc_a, loc_b, ret)?;
             }
             Operator::I64GtU => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_cmp_gt_u(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_cmp_gt_u(loc_a, loc_b, ret)?;
             }
             Operator::I64GeU => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_cmp_ge_u(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_cmp_ge_u(loc_a, loc_b, ret)?;
             }
             Operator::I64LtS => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_cmp_lt_s(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_cmp_lt_s(loc_a, loc_b, ret)?;
             }
             Operator::I64LeS => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_cmp_le_s(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_cmp_le_s(loc_a, loc_b, ret)?;
             }
             Operator::I64GtS => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_cmp_gt_s(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_cmp_gt_s(loc_a, loc_b, ret)?;
             }
             Operator::I64GeS => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64);
-                self.machine.i64_cmp_ge_s(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I64)?;
+                self.machine.i64_cmp_ge_s(loc_a, loc_b, ret)?;
             }
             Operator::I64ExtendI32U => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.emit_relaxed_mov(Size::S32, loc, ret);
+                self.machine.emit_relaxed_mov(Size::S32, loc, ret)?;
 
                 // A 32-bit memory write does not automatically clear the upper 32 bits of a 64-bit word.
                 // So, we need to explicitly write zero to the upper half here.
@@ -1674,7 +1960,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         Size::S32,
                         Location::Imm32(0),
                         Location::Memory(base, off + 4),
-                    );
+                    )?;
                 }
             }
             Operator::I64ExtendI32S => {
@@ -1682,74 +1968,74 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.machine
-                    .emit_relaxed_sign_extension(Size::S32, loc, Size::S64, ret);
+                    .emit_relaxed_sign_extension(Size::S32, loc, Size::S64, ret)?;
             }
             Operator::I32Extend8S => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
 
                 self.machine
-                    .emit_relaxed_sign_extension(Size::S8, loc, Size::S32, ret);
+                    .emit_relaxed_sign_extension(Size::S8, loc, Size::S32, ret)?;
             }
             Operator::I32Extend16S => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
 
                 self.machine
-                    .emit_relaxed_sign_extension(Size::S16, loc, Size::S32, ret);
+                    .emit_relaxed_sign_extension(Size::S16, loc, Size::S32, ret)?;
             }
             Operator::I64Extend8S => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
 
                 self.machine
-                    .emit_relaxed_sign_extension(Size::S8, loc, Size::S64, ret);
+                    .emit_relaxed_sign_extension(Size::S8, loc, Size::S64, ret)?;
             }
             Operator::I64Extend16S => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
 
                 self.machine
-                    .emit_relaxed_sign_extension(Size::S16, loc, Size::S64, ret);
+                    .emit_relaxed_sign_extension(Size::S16, loc, Size::S64, ret)?;
             }
             Operator::I64Extend32S => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
 
                 self.machine
-                    .emit_relaxed_sign_extension(Size::S32, loc, Size::S64, ret);
+                    .emit_relaxed_sign_extension(Size::S32, loc, Size::S64, ret)?;
             }
             Operator::I32WrapI64 => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.emit_relaxed_mov(Size::S32, loc, ret);
+                self.machine.emit_relaxed_mov(Size::S32, loc, ret)?;
             }
 
             Operator::F32Const { value } => {
@@ -1764,77 +2050,77 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 self.fp_stack.pop2()?;
                 self.fp_stack
                     .push(FloatValue::cncl_f32(self.value_stack.len() - 2));
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64)?;
 
-                self.machine.f32_add(loc_a, loc_b, ret);
+                self.machine.f32_add(loc_a, loc_b, ret)?;
             }
             Operator::F32Sub => {
                 self.fp_stack.pop2()?;
                 self.fp_stack
                     .push(FloatValue::cncl_f32(self.value_stack.len() - 2));
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64)?;
 
-                self.machine.f32_sub(loc_a, loc_b, ret);
+                self.machine.f32_sub(loc_a, loc_b, ret)?;
             }
             Operator::F32Mul => {
                 self.fp_stack.pop2()?;
                 self.fp_stack
                     .push(FloatValue::cncl_f32(self.value_stack.len() - 2));
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64)?;
 
-                self.machine.f32_mul(loc_a, loc_b, ret);
+                self.machine.f32_mul(loc_a, loc_b, ret)?;
             }
             Operator::F32Div => {
                 self.fp_stack.pop2()?;
                 self.fp_stack
                     .push(FloatValue::cncl_f32(self.value_stack.len() - 2));
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64)?;
 
-                self.machine.f32_div(loc_a, loc_b, ret);
+                self.machine.f32_div(loc_a, loc_b, ret)?;
             }
             Operator::F32Max => {
                 self.fp_stack.pop2()?;
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 2));
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
-                self.machine.f32_max(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64)?;
+                self.machine.f32_max(loc_a, loc_b, ret)?;
             }
             Operator::F32Min => {
                 self.fp_stack.pop2()?;
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 2));
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
-                self.machine.f32_min(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64)?;
+                self.machine.f32_min(loc_a, loc_b, ret)?;
             }
             Operator::F32Eq => {
                 self.fp_stack.pop2()?;
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.f32_cmp_eq(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.f32_cmp_eq(loc_a, loc_b, ret)?;
             }
             Operator::F32Ne => {
                 self.fp_stack.pop2()?;
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.f32_cmp_ne(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.f32_cmp_ne(loc_a, loc_b, ret)?;
             }
             Operator::F32Lt => {
                 self.fp_stack.pop2()?;
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.f32_cmp_lt(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.f32_cmp_lt(loc_a, loc_b, ret)?;
             }
             Operator::F32Le => {
                 self.fp_stack.pop2()?;
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.f32_cmp_le(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.f32_cmp_le(loc_a, loc_b, ret)?;
             }
             Operator::F32Gt => {
                 self.fp_stack.pop2()?;
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.f32_cmp_gt(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.f32_cmp_gt(loc_a, loc_b, ret)?;
             }
             Operator::F32Ge => {
                 self.fp_stack.pop2()?;
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.f32_cmp_ge(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.f32_cmp_ge(loc_a, loc_b, ret)?;
             }
             Operator::F32Nearest => {
                 self.fp_stack.pop1()?;
@@ -1844,9 +2130,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.f32_nearest(loc, ret);
+                self.machine.f32_nearest(loc, ret)?;
             }
             Operator::F32Floor => {
                 self.fp_stack.pop1()?;
@@ -1856,9 +2142,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.f32_floor(loc, ret);
+                self.machine.f32_floor(loc, ret)?;
             }
             Operator::F32Ceil => {
                 self.fp_stack.pop1()?;
@@ -1868,9 +2154,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.f32_ceil(loc, ret);
+                self.machine.f32_ceil(loc, ret)?;
             }
             Operator::F32Trunc => {
                 self.fp_stack.pop1()?;
@@ -1880,9 +2166,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.f32_trunc(loc, ret);
+                self.machine.f32_trunc(loc, ret)?;
             }
             Operator::F32Sqrt => {
                 self.fp_stack.pop1()?;
@@ -1892,20 +2178,30 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.f32_sqrt(loc, ret);
+                self.machine.f32_sqrt(loc, ret)?;
             }
 
             Operator::F32Copysign => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F32);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F32)?;
 
                 let (fp_src1, fp_src2) = self.fp_stack.pop2()?;
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1));
 
-                let tmp1 = self.machine.acquire_temp_gpr().unwrap();
-                let tmp2 = self.machine.acquire_temp_gpr().unwrap();
+                let tmp1 = self
+                    .machine
+                    .acquire_temp_gpr()
+                    .ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
+                let tmp2 = self
+                    .machine
+                    .acquire_temp_gpr()
+                    .ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
 
                 if self.machine.arch_supports_canonicalize_nan()
                     && self.config.enable_nan_canonicalization
@@ -1913,24 +2209,27 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     for (fp, loc, tmp) in [(fp_src1, loc_a, tmp1), (fp_src2, loc_b, tmp2)].iter() {
                         match fp.canonicalization {
                             Some(_) => {
-                                self.machine
-                                    .canonicalize_nan(Size::S32, *loc, Location::GPR(*tmp));
+                                self.machine.canonicalize_nan(
+                                    Size::S32,
+                                    *loc,
+                                    Location::GPR(*tmp),
+                                )?;
                             }
                             None => {
                                 self.machine
-                                    .move_location(Size::S32, *loc, Location::GPR(*tmp));
+                                    .move_location(Size::S32, *loc, Location::GPR(*tmp))?;
                             }
                         }
                     }
                 } else {
                     self.machine
-                        .move_location(Size::S32, loc_a, Location::GPR(tmp1));
+                        .move_location(Size::S32, loc_a, Location::GPR(tmp1))?;
                     self.machine
-                        .move_location(Size::S32, loc_b, Location::GPR(tmp2));
+                        .move_location(Size::S32, loc_b, Location::GPR(tmp2))?;
                 }
                 self.machine.emit_i32_copysign(tmp1, tmp2);
                 self.machine
-                    .move_location(Size::S32, Location::GPR(tmp1), ret);
+                    .move_location(Size::S32, Location::GPR(tmp1), ret)?;
                 self.machine.release_gpr(tmp2);
                 self.machine.release_gpr(tmp1);
             }
@@ -1942,10 +2241,10 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
 
-                self.machine.f32_abs(loc, ret);
+                self.machine.f32_abs(loc, ret)?;
             }
 
             Operator::F32Neg => {
@@ -1955,10 +2254,10 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
 
-                self.machine.f32_neg(loc, ret);
+                self.machine.f32_neg(loc, ret)?;
             }
 
             Operator::F64Const { value } => {
@@ -1973,77 +2272,77 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 self.fp_stack.pop2()?;
                 self.fp_stack
                     .push(FloatValue::cncl_f64(self.value_stack.len() - 2));
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64)?;
 
-                self.machine.f64_add(loc_a, loc_b, ret);
+                self.machine.f64_add(loc_a, loc_b, ret)?;
             }
             Operator::F64Sub => {
                 self.fp_stack.pop2()?;
                 self.fp_stack
                     .push(FloatValue::cncl_f64(self.value_stack.len() - 2));
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64)?;
 
-                self.machine.f64_sub(loc_a, loc_b, ret);
+                self.machine.f64_sub(loc_a, loc_b, ret)?;
             }
             Operator::F64Mul => {
                 self.fp_stack.pop2()?;
                 self.fp_stack
                     .push(FloatValue::cncl_f64(self.value_stack.len() - 2));
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64)?;
 
-                self.machine.f64_mul(loc_a, loc_b, ret);
+                self.machine.f64_mul(loc_a, loc_b, ret)?;
             }
             Operator::F64Div => {
                 self.fp_stack.pop2()?;
                 self.fp_stack
                     .push(FloatValue::cncl_f64(self.value_stack.len() - 2));
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64)?;
 
-                self.machine.f64_div(loc_a, loc_b, ret);
+                self.machine.f64_div(loc_a, loc_b, ret)?;
             }
             Operator::F64Max => {
                 self.fp_stack.pop2()?;
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 2));
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
-                self.machine.f64_max(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64)?;
+                self.machine.f64_max(loc_a, loc_b, ret)?;
             }
             Operator::F64Min => {
                 self.fp_stack.pop2()?;
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 2));
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
-                self.machine.f64_min(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64)?;
+                self.machine.f64_min(loc_a, loc_b, ret)?;
             }
             Operator::F64Eq => {
                 self.fp_stack.pop2()?;
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.f64_cmp_eq(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.f64_cmp_eq(loc_a, loc_b, ret)?;
             }
             Operator::F64Ne => {
                 self.fp_stack.pop2()?;
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.f64_cmp_ne(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.f64_cmp_ne(loc_a, loc_b, ret)?;
             }
             Operator::F64Lt => {
                 self.fp_stack.pop2()?;
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.f64_cmp_lt(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.f64_cmp_lt(loc_a, loc_b, ret)?;
             }
             Operator::F64Le => {
                 self.fp_stack.pop2()?;
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.f64_cmp_le(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.f64_cmp_le(loc_a, loc_b, ret)?;
             }
             Operator::F64Gt => {
                 self.fp_stack.pop2()?;
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.f64_cmp_gt(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.f64_cmp_gt(loc_a, loc_b, ret)?;
             }
             Operator::F64Ge => {
                 self.fp_stack.pop2()?;
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-                self.machine.f64_cmp_ge(loc_a, loc_b, ret);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32)?;
+                self.machine.f64_cmp_ge(loc_a, loc_b, ret)?;
             }
             Operator::F64Nearest => {
                 self.fp_stack.pop1()?;
@@ -2053,9 +2352,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.f64_nearest(loc, ret);
+                self.machine.f64_nearest(loc, ret)?;
             }
             Operator::F64Floor => {
                 self.fp_stack.pop1()?;
@@ -2065,9 +2364,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.f64_floor(loc, ret);
+                self.machine.f64_floor(loc, ret)?;
             }
             Operator::F64Ceil => {
                 self.fp_stack.pop1()?;
@@ -2077,9 +2376,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.f64_ceil(loc, ret);
+                self.machine.f64_ceil(loc, ret)?;
             }
             Operator::F64Trunc => {
                 self.fp_stack.pop1()?;
@@ -2089,9 +2388,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.f64_trunc(loc, ret);
+                self.machine.f64_trunc(loc, ret)?;
             }
             Operator::F64Sqrt => {
                 self.fp_stack.pop1()?;
@@ -2101,20 +2400,30 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.f64_sqrt(loc, ret);
+                self.machine.f64_sqrt(loc, ret)?;
             }
 
             Operator::F64Copysign => {
-                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
+                let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64)?;
 
                 let (fp_src1, fp_src2) = self.fp_stack.pop2()?;
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1));
 
-                let tmp1 = self.machine.acquire_temp_gpr().unwrap();
-                let tmp2 = self.machine.acquire_temp_gpr().unwrap();
+                let tmp1 = self
+                    .machine
+                    .acquire_temp_gpr()
+                    .ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
+                let tmp2 = self
+                    .machine
+                    .acquire_temp_gpr()
+                    .ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
 
                 if self.machine.arch_supports_canonicalize_nan()
                     && self.config.enable_nan_canonicalization
@@ -2122,24 +2431,27 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     for (fp, loc, tmp) in [(fp_src1, loc_a, tmp1), (fp_src2, loc_b, tmp2)].iter() {
                         match fp.canonicalization {
                             Some(_) => {
-                                self.machine
-                                    .canonicalize_nan(Size::S64, *loc, Location::GPR(*tmp));
+                                self.machine.canonicalize_nan(
+                                    Size::S64,
+                                    *loc,
+                                    Location::GPR(*tmp),
+                                )?;
                             }
                             None => {
                                 self.machine
-                                    .move_location(Size::S64, *loc, Location::GPR(*tmp));
+                                    .move_location(Size::S64, *loc, Location::GPR(*tmp))?;
                             }
                         }
                     }
                 } else {
                     self.machine
-                        .move_location(Size::S64, loc_a, Location::GPR(tmp1));
+                        .move_location(Size::S64, loc_a, Location::GPR(tmp1))?;
                     self.machine
-                        .move_location(Size::S64, loc_b, Location::GPR(tmp2));
+                        .move_location(Size::S64, loc_b, Location::GPR(tmp2))?;
                 }
-                self.machine.emit_i64_copysign(tmp1, tmp2);
+                self.machine.emit_i64_copysign(tmp1, tmp2)?;
                 self.machine
-                    .move_location(Size::S64, Location::GPR(tmp1), ret);
+                    .move_location(Size::S64, Location::GPR(tmp1), ret)?;
 
                 self.machine.release_gpr(tmp2);
                 self.machine.release_gpr(tmp1);
@@ -2152,10 +2464,10 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
 
-                self.machine.f64_abs(loc, ret);
+                self.machine.f64_abs(loc, ret)?;
             }
 
             Operator::F64Neg => {
@@ -2165,10 +2477,10 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
 
-                self.machine.f64_neg(loc, ret);
+                self.machine.f64_neg(loc, ret)?;
             }
 
             Operator::F64PromoteF32 => {
@@ -2178,9 +2490,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.convert_f64_f32(loc, ret);
+                self.machine.convert_f64_f32(loc, ret)?;
             }
             Operator::F32DemoteF64 => {
                 let fp = self.fp_stack.pop1()?;
@@ -2189,9 +2501,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
-                self.machine.convert_f32_f64(loc, ret);
+                self.machine.convert_f32_f64(loc, ret)?;
             }
 
             Operator::I32ReinterpretF32 => {
@@ -2199,7 +2511,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 let fp = self.fp_stack.pop1()?;
 
@@ -2208,10 +2520,10 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     || fp.canonicalization.is_none()
                 {
                     if loc != ret {
-                        self.machine.emit_relaxed_mov(Size::S32, loc, ret);
+                        self.machine.emit_relaxed_mov(Size::S32, loc, ret)?;
                     }
                 } else {
-                    self.machine.canonicalize_nan(Size::S32, loc, ret);
+                    self.machine.canonicalize_nan(Size::S32, loc, ret)?;
                 }
             }
             Operator::F32ReinterpretI32 => {
@@ -2219,13 +2531,13 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1));
 
                 if loc != ret {
-                    self.machine.emit_relaxed_mov(Size::S32, loc, ret);
+                    self.machine.emit_relaxed_mov(Size::S32, loc, ret)?;
                 }
             }
 
@@ -2234,7 +2546,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 let fp = self.fp_stack.pop1()?;
 
@@ -2243,10 +2555,10 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     || fp.canonicalization.is_none()
                 {
                     if loc != ret {
-                        self.machine.emit_relaxed_mov(Size::S64, loc, ret);
+                        self.machine.emit_relaxed_mov(Size::S64, loc, ret)?;
                     }
                 } else {
-                    self.machine.canonicalize_nan(Size::S64, loc, ret);
+                    self.machine.canonicalize_nan(Size::S64, loc, ret)?;
                 }
             }
             Operator::F64ReinterpretI64 => {
@@ -2254,13 +2566,13 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1));
 
                 if loc != ret {
-                    self.machine.emit_relaxed_mov(Size::S64, loc, ret);
+                    self.machine.emit_relaxed_mov(Size::S64, loc, ret)?;
                 }
             }
 
@@ -2269,11 +2581,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i32_f32(loc, ret, false, false);
+                self.machine.convert_i32_f32(loc, ret, false, false)?;
             }
 
             Operator::I32TruncSatF32U => {
@@ -2281,11 +2593,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i32_f32(loc, ret, false, true);
+                self.machine.convert_i32_f32(loc, ret, false, true)?;
             }
 
             Operator::I32TruncF32S => {
@@ -2293,22 +2605,22 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i32_f32(loc, ret, true, false);
+                self.machine.convert_i32_f32(loc, ret, true, false)?;
             }
             Operator::I32TruncSatF32S => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i32_f32(loc, ret, true, true);
+                self.machine.convert_i32_f32(loc, ret, true, true)?;
             }
 
             Operator::I64TruncF32S => {
@@ -2316,11 +2628,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i64_f32(loc, ret, true, false);
+                self.machine.convert_i64_f32(loc, ret, true, false)?;
             }
 
             Operator::I64TruncSatF32S => {
@@ -2328,11 +2640,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i64_f32(loc, ret, true, true);
+                self.machine.convert_i64_f32(loc, ret, true, true)?;
             }
 
             Operator::I64TruncF32U => {
@@ -2340,22 +2652,22 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i64_f32(loc, ret, false, false);
+                self.machine.convert_i64_f32(loc, ret, false, false)?;
             }
             Operator::I64TruncSatF32U => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i64_f32(loc, ret, false, true);
+                self.machine.convert_i64_f32(loc, ret, false, true)?;
             }
 
             Operator::I32TruncF64U => {
@@ -2363,11 +2675,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i32_f64(loc, ret, false, false);
+                self.machine.convert_i32_f64(loc, ret, false, false)?;
             }
 
             Operator::I32TruncSatF64U => {
@@ -2375,11 +2687,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i32_f64(loc, ret, false, true);
+                self.machine.convert_i32_f64(loc, ret, false, true)?;
             }
 
             Operator::I32TruncF64S => {
@@ -2387,11 +2699,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i32_f64(loc, ret, true, false);
+                self.machine.convert_i32_f64(loc, ret, true, false)?;
             }
 
             Operator::I32TruncSatF64S => {
@@ -2399,11 +2711,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i32_f64(loc, ret, true, true);
+                self.machine.convert_i32_f64(loc, ret, true, true)?;
             }
 
             Operator::I64TruncF64S => {
@@ -2411,11 +2723,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i64_f64(loc, ret, true, false);
+                self.machine.convert_i64_f64(loc, ret, true, false)?;
             }
 
             Operator::I64TruncSatF64S => {
@@ -2423,11 +2735,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i64_f64(loc, ret, true, true);
+                self.machine.convert_i64_f64(loc, ret, true, true)?;
             }
 
             Operator::I64TruncF64U => {
@@ -2435,11 +2747,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i64_f64(loc, ret, false, false);
+                self.machine.convert_i64_f64(loc, ret, false, false)?;
             }
 
             Operator::I64TruncSatF64U => {
@@ -2447,11 +2759,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack.pop1()?;
 
-                self.machine.convert_i64_f64(loc, ret, false, true);
+                self.machine.convert_i64_f64(loc, ret, false, true)?;
             }
 
             Operator::F32ConvertI32S => {
@@ -2459,48 +2771,48 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i32 to f32 never results in NaN.
 
-                self.machine.convert_f32_i32(loc, true, ret);
+                self.machine.convert_f32_i32(loc, true, ret)?;
             }
             Operator::F32ConvertI32U => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::F32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i32 to f32 never results in NaN.
 
-                self.machine.convert_f32_i32(loc, false, ret);
+                self.machine.convert_f32_i32(loc, false, ret)?;
             }
             Operator::F32ConvertI64S => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::F32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i64 to f32 never results in NaN.
 
-                self.machine.convert_f32_i64(loc, true, ret);
+                self.machine.convert_f32_i64(loc, true, ret)?;
             }
             Operator::F32ConvertI64U => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::F32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i64 to f32 never results in NaN.
 
-                self.machine.convert_f32_i64(loc, false, ret);
+                self.machine.convert_f32_i64(loc, false, ret)?;
             }
 
             Operator::F64ConvertI32S => {
@@ -2508,48 +2820,48 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i32 to f64 never results in NaN.
 
-                self.machine.convert_f64_i32(loc, true, ret);
+                self.machine.convert_f64_i32(loc, true, ret)?;
             }
             Operator::F64ConvertI32U => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i32 to f64 never results in NaN.
 
-                self.machine.convert_f64_i32(loc, false, ret);
+                self.machine.convert_f64_i32(loc, false, ret)?;
             }
             Operator::F64ConvertI64S => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i64 to f64 never results in NaN.
 
-                self.machine.convert_f64_i64(loc, true, ret);
+                self.machine.convert_f64_i64(loc, true, ret)?;
             }
             Operator::F64ConvertI64U => {
                 let loc = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i64 to f64 never results in NaN.
 
-                self.machine.convert_f64_i64(loc, false, ret);
+                self.machine.convert_f64_i64(loc, false, ret)?;
             }
 
             Operator::Call { function_index } => {
@@ -2587,7 +2899,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         {
                             let size = fp.canonicalization.unwrap().to_size();
                             self.machine
-                                .canonicalize_nan(size, params[index], params[index]);
+                                .canonicalize_nan(size, params[index], params[index])?;
                         }
                         self.fp_stack.pop().unwrap();
                     } else {
@@ -2615,6 +2927,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             .emit_call_with_reloc(calling_convention, reloc_target);
                         this.machine.mark_instruction_address_end(offset);
                         this.relocations.append(&mut relocations);
+                        Ok(())
                     },
                     params.iter().copied(),
                     param_types.iter().copied(),
@@ -2629,14 +2942,14 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             MachineValue::WasmStack(self.value_stack.len()),
                         )],
                         false,
-                    )[0];
+                    )?[0];
                     self.value_stack.push(ret);
                     if return_types[0].is_float() {
                         self.machine.move_location(
                             Size::S64,
                             Location::SIMD(self.machine.get_simd_for_ret()),
                             ret,
-                        );
+                        )?;
                         self.fp_stack
                             .push(FloatValue::new(self.value_stack.len() - 1));
                     } else {
@@ -2644,7 +2957,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             Size::S64,
                             Location::GPR(self.machine.get_gpr_for_ret()),
                             ret,
-                        );
+                        )?;
                     }
                 }
             }
@@ -2680,7 +2993,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         {
                             let size = fp.canonicalization.unwrap().to_size();
                             self.machine
-                                .canonicalize_nan(size, params[index], params[index]);
+                                .canonicalize_nan(size, params[index], params[index])?;
                         }
                         self.fp_stack.pop().unwrap();
                     } else {
@@ -2688,9 +3001,24 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     }
                 }
 
-                let table_base = self.machine.acquire_temp_gpr().unwrap();
-                let table_count = self.machine.acquire_temp_gpr().unwrap();
-                let sigidx = self.machine.acquire_temp_gpr().unwrap();
+                let table_base = self
+                    .machine
+                    .acquire_temp_gpr()
+                    .ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
+                let table_count = self
+                    .machine
+                    .acquire_temp_gpr()
+                    .ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
+                let sigidx = self
+                    .machine
+                    .acquire_temp_gpr()
+                    .ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
 
                 if let Some(local_table_index) = self.module.local_table_index(table_index) {
                     let (vmctx_offset_base, vmctx_offset_len) = (
@@ -2702,12 +3030,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         Size::S64,
                         Location::Memory(self.machine.get_vmctx_reg(), vmctx_offset_base as i32),
                         Location::GPR(table_base),
-                    );
+                    )?;
                     self.machine.move_location(
                         Size::S32,
                         Location::Memory(self.machine.get_vmctx_reg(), vmctx_offset_len as i32),
                         Location::GPR(table_count),
-                    );
+                    )?;
                 } else {
                     // Do an indirection.
                     let import_offset = self.vmoffsets.vmctx_vmtable_import(table_index);
@@ -2715,7 +3043,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         Size::S64,
                         Location::Memory(self.machine.get_vmctx_reg(), import_offset as i32),
                         Location::GPR(table_base),
-                    );
+                    )?;
 
                     // Load len.
                     self.machine.move_location(
@@ -2725,48 +3053,67 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             self.vmoffsets.vmtable_definition_current_elements() as _,
                         ),
                         Location::GPR(table_count),
-                    );
+                    )?;
 
                     // Load base.
                     self.machine.move_location(
                         Size::S64,
                         Location::Memory(table_base, self.vmoffsets.vmtable_definition_base() as _),
                         Location::GPR(table_base),
-                    );
+                    )?;
                 }
 
                 self.machine
-                    .location_cmp(Size::S32, func_index, Location::GPR(table_count));
+                    .location_cmp(Size::S32, func_index, Location::GPR(table_count))?;
                 self.machine
                     .jmp_on_belowequal(self.special_labels.table_access_oob);
                 self.machine
-                    .move_location(Size::S32, func_index, Location::GPR(table_count));
+                    .move_location(Size::S32, func_index, Location::GPR(table_count))?;
                 self.machine.emit_imul_imm32(
                     Size::S64,
                     self.vmoffsets.size_of_vm_funcref() as u32,
                     table_count,
-                );
+                )?;
                 self.machine.location_add(
                     Size::S64,
                     Location::GPR(table_base),
                     Location::GPR(table_count),
                     false,
-                );
+                )?;
 
                 // deref the table to get a VMFuncRef
                 self.machine.move_location(
                     Size::S64,
                     Location::Memory(table_count, self.vmoffsets.vm_funcref_anyfunc_ptr() as i32),
                     Location::GPR(table_count),
-                );
+                )?;
                 // Trap if the FuncRef is null
                 self.machine.location_cmp(
                     Size::S64,
                     Location::Imm32(0),
                     Location::GPR(table_count),
-                );
+                )?;
                 self.machine
                     .jmp_on_equal(self.special_labels.indirect_call_null);
+
+                // The function last called with this signature, if it's a
+                // function of this instance, passed the signature check.
+                let cache_hit = if self.config.call_indirect_inline_cache {
+                    let cache_hit = self.machine.get_label();
+                    self.machine.location_cmp(
+                        Size::S64,
+                        Location::Memory(
+                            self.machine.get_vmctx_reg(),
+                            self.vmoffsets.vmctx_call_indirect_cache(index) as i32,
+                        ),
+                        Location::GPR(table_count),
+                    )?;
+                    self.machine.jmp_on_equal(cache_hit);
+                    Some(cache_hit)
+                } else {
+                    None
+                };
+
                 self.machine.move_location(
                     Size::S64,
                     Location::Memory(
@@ -2774,7 +3121,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         self.vmoffsets.vmctx_vmshared_signature_id(index) as i32,
                     ),
                     Location::GPR(sigidx),
-                );
+                )?;
 
                 // Trap if signature mismatches.
                 self.machine.location_cmp(
@@ -2784,10 +3131,34 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         table_count,
                         (self.vmoffsets.vmcaller_checked_anyfunc_type_index() as usize) as i32,
                     ),
-                );
+                )?;
                 self.machine
                     .jmp_on_different(self.special_labels.bad_signature);
 
+                if let Some(cache_hit) = cache_hit {
+                    // Only the functions of this instance are cached: they
+                    // live as long as the cache, so their address can't be
+                    // reused by a function of another signature.
+                    self.machine.location_cmp(
+                        Size::S64,
+                        Location::GPR(self.machine.get_vmctx_reg()),
+                        Location::Memory(
+                            table_count,
+                            self.vmoffsets.vmcaller_checked_anyfunc_vmctx() as i32,
+                        ),
+                    )?;
+                    self.machine.jmp_on_different(cache_hit);
+                    self.machine.move_location(
+                        Size::S64,
+                        Location::GPR(table_count),
+                        Location::Memory(
+                            self.machine.get_vmctx_reg(),
+                            self.vmoffsets.vmctx_call_indirect_cache(index) as i32,
+                        ),
+                    )?;
+                    self.machine.emit_label(cache_hit);
+                }
+
                 self.machine.release_gpr(sigidx);
                 self.machine.release_gpr(table_count);
                 self.machine.release_gpr(table_base);
@@ -2798,7 +3169,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         Size::S64,
                         Location::GPR(table_count),
                         Location::GPR(gpr_for_call),
-                    );
+                    )?;
                 }
 
                 self.release_locations_only_osr_state(params.len());
@@ -2831,14 +3202,15 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                                 ),
                                 this.machine
                                     .get_simple_param_location(0, calling_convention),
-                            );
+                            )?;
 
                             this.machine.emit_call_location(Location::Memory(
                                 gpr_for_call,
                                 vmcaller_checked_anyfunc_func_ptr as i32,
-                            ));
+                            ))?;
                             this.machine.mark_instruction_address_end(offset);
                         }
+                        Ok(())
                     },
                     params.iter().copied(),
                     param_types.iter().copied(),
@@ -2853,14 +3225,14 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             MachineValue::WasmStack(self.value_stack.len()),
                         )],
                         false,
-                    )[0];
+                    )?[0];
                     self.value_stack.push(ret);
                     if return_types[0].is_float() {
                         self.machine.move_location(
                             Size::S64,
                             Location::SIMD(self.machine.get_simd_for_ret()),
                             ret,
-                        );
+                        )?;
                         self.fp_stack
                             .push(FloatValue::new(self.value_stack.len() - 1));
                     } else {
@@ -2868,7 +3240,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             Size::S64,
                             Location::GPR(self.machine.get_gpr_for_ret()),
                             ret,
-                        );
+                        )?;
                     }
                 }
             }
@@ -2876,7 +3248,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let label_end = self.machine.get_label();
                 let label_else = self.machine.get_label();
 
-                let cond = self.pop_value_released();
+                let cond = self.pop_branch_condition(fused);
 
                 let frame = ControlFrame {
                     label: label_end,
@@ -2897,9 +3269,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     state_diff_id: self.get_state_diff(),
                 };
                 self.control_stack.push(frame);
-                self.machine
-                    .emit_relaxed_cmp(Size::S32, Location::Imm32(0), cond);
-                self.machine.jmp_on_equal(label_else);
+                self.emit_jmp_unless(cond, label_else)?;
             }
             Operator::Else => {
                 let frame = self.control_stack.last_mut().unwrap();
@@ -2916,7 +3286,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         false
                     };
                     self.machine
-                        .emit_function_return_value(first_return, canonicalize, loc);
+                        .emit_function_return_value(first_return, canonicalize, loc)?;
                 }
 
                 let frame = &self.control_stack.last_mut().unwrap();
@@ -2961,25 +3331,25 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
 
                 let end_label = self.machine.get_label();
                 let zero_label = self.machine.get_label();
 
                 self.machine
-                    .emit_relaxed_cmp(Size::S32, Location::Imm32(0), cond);
+                    .emit_relaxed_cmp(Size::S32, Location::Imm32(0), cond)?;
                 self.machine.jmp_on_equal(zero_label);
                 match cncl {
                     Some((Some(fp), _))
                         if self.machine.arch_supports_canonicalize_nan()
                             && self.config.enable_nan_canonicalization =>
                     {
-                        self.machine.canonicalize_nan(fp.to_size(), v_a, ret);
+                        self.machine.canonicalize_nan(fp.to_size(), v_a, ret)?;
                     }
                     _ => {
                         if v_a != ret {
-                            self.machine.emit_relaxed_mov(Size::S64, v_a, ret);
+                            self.machine.emit_relaxed_mov(Size::S64, v_a, ret)?;
                         }
                     }
                 }
@@ -2990,11 +3360,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         if self.machine.arch_supports_canonicalize_nan()
                             && self.config.enable_nan_canonicalization =>
                     {
-                        self.machine.canonicalize_nan(fp.to_size(), v_b, ret);
+                        self.machine.canonicalize_nan(fp.to_size(), v_b, ret)?;
                     }
                     _ => {
                         if v_b != ret {
-                            self.machine.emit_relaxed_mov(Size::S64, v_b, ret);
+                            self.machine.emit_relaxed_mov(Size::S64, v_b, ret)?;
                         }
                     }
                 }
@@ -3023,7 +3393,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 self.control_stack.push(frame);
             }
             Operator::Loop { ty } => {
-                self.machine.align_for_loop();
+                self.machine.align_for_loop(self.config.loop_alignment);
                 let label = self.machine.get_label();
                 let state_diff_id = self.get_state_diff();
                 let _activate_offset = self.machine.assembler_get_offset().0;
@@ -3048,6 +3418,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     state_diff_id,
                 });
                 self.machine.emit_label(label);
+                if self.config.patch_loop_headers {
+                    self.emit_patch_point(PatchPointKind::LoopHeader);
+                }
 
                 // TODO: Re-enable interrupt signal check without branching
             }
@@ -3067,11 +3440,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         ) as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
                 self.emit_call_native(
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, memory_index]
                     iter::once(Location::Imm32(memory_index.index() as u32)),
@@ -3080,13 +3454,13 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.machine.move_location(
                     Size::S64,
                     Location::GPR(self.machine.get_gpr_for_ret()),
                     ret,
-                );
+                )?;
             }
             Operator::MemoryInit { segment, mem } => {
                 let len = self.value_stack.pop().unwrap();
@@ -3103,7 +3477,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
 
                 // TODO: should this be 3?
                 self.release_locations_only_osr_state(1);
@@ -3112,6 +3486,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, memory_index, segment_index, dst, src, len]
                     [
@@ -3145,12 +3520,13 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
 
                 self.emit_call_native(
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, segment_index]
                     iter::once(Location::Imm32(segment)),
@@ -3186,7 +3562,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         self.vmoffsets.vmctx_builtin_function(memory_copy_index) as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
 
                 // TODO: should this be 3?
                 self.release_locations_only_osr_state(1);
@@ -3195,6 +3571,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, memory_index, dst, src, len]
                     [
@@ -3238,7 +3615,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         self.vmoffsets.vmctx_builtin_function(memory_fill_index) as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
 
                 // TODO: should this be 3?
                 self.release_locations_only_osr_state(1);
@@ -3247,6 +3624,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, memory_index, dst, src, len]
                     [Location::Imm32(memory_index.index() as u32), dst, val, len]
@@ -3277,7 +3655,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         ) as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
 
                 self.release_locations_only_osr_state(1);
 
@@ -3285,6 +3663,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, val, memory_index]
                     iter::once(param_pages)
@@ -3297,22 +3676,25 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.machine.move_location(
                     Size::S64,
                     Location::GPR(self.machine.get_gpr_for_ret()),
                     ret,
-                );
+                )?;
             }
             Operator::I32Load { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_load(
                             target,
@@ -3322,20 +3704,23 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::F32Load { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::F32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1));
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.f32_load(
                             target,
@@ -3345,18 +3730,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32Load8U { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_load_8u(
                             target,
@@ -3366,18 +3754,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32Load8S { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_load_8s(
                             target,
@@ -3387,18 +3778,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32Load16U { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_load_16u(
                             target,
@@ -3408,18 +3802,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32Load16S { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_load_16s(
                             target,
@@ -3429,14 +3826,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32Store { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_save(
                             target_value,
@@ -3446,9 +3846,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::F32Store { ref memarg } => {
                 let target_value = self.pop_value_released();
@@ -3456,6 +3856,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let fp = self.fp_stack.pop1()?;
                 let config_nan_canonicalization = self.config.enable_nan_canonicalization;
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.f32_save(
                             target_value,
@@ -3466,14 +3869,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32Store8 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_save_8(
                             target_value,
@@ -3483,14 +3889,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32Store16 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_save_16(
                             target_value,
@@ -3500,18 +3909,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64Load { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    8,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_load(
                             target,
@@ -3521,20 +3933,23 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::F64Load { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1));
                 self.op_memory(
+                    target,
+                    memarg,
+                    8,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.f64_load(
                             target,
@@ -3544,18 +3959,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64Load8U { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_load_8u(
                             target,
@@ -3565,18 +3983,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64Load8S { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_load_8s(
                             target,
@@ -3586,18 +4007,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64Load16U { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_load_16u(
                             target,
@@ -3607,18 +4031,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64Load16S { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_load_16s(
                             target,
@@ -3628,18 +4055,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64Load32U { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_load_32u(
                             target,
@@ -3649,18 +4079,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64Load32S { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_load_32s(
                             target,
@@ -3670,15 +4103,18 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64Store { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
 
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    8,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_save(
                             target_value,
@@ -3688,9 +4124,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::F64Store { ref memarg } => {
                 let target_value = self.pop_value_released();
@@ -3698,6 +4134,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let fp = self.fp_stack.pop1()?;
                 let config_nan_canonicalization = self.config.enable_nan_canonicalization;
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    8,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.f64_save(
                             target_value,
@@ -3708,14 +4147,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64Store8 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_save_8(
                             target_value,
@@ -3725,14 +4167,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64Store16 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_save_16(
                             target_value,
@@ -3742,14 +4187,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64Store32 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_save_32(
                             target_value,
@@ -3759,9 +4207,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::Unreachable => {
                 self.mark_trappable();
@@ -3788,7 +4236,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         false
                     };
                     self.machine
-                        .emit_function_return_value(first_return, canonicalize, loc);
+                        .emit_function_return_value(first_return, canonicalize, loc)?;
                 }
                 let frame = &self.control_stack[0];
                 let frame_depth = frame.value_stack_depth.clone();
@@ -3817,7 +4265,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         false
                     };
                     self.machine
-                        .emit_function_return_value(first_return, canonicalize, loc);
+                        .emit_function_return_value(first_return, canonicalize, loc)?;
                 }
                 let stack_len = self.control_stack.len();
                 let frame = &mut self.control_stack[stack_len - 1 - (relative_depth as usize)];
@@ -3830,10 +4278,8 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             }
             Operator::BrIf { relative_depth } => {
                 let after = self.machine.get_label();
-                let cond = self.pop_value_released();
-                self.machine
-                    .emit_relaxed_cmp(Size::S32, Location::Imm32(0), cond);
-                self.machine.jmp_on_equal(after);
+                let cond = self.pop_branch_condition(fused);
+                self.emit_jmp_unless(cond, after)?;
 
                 let frame =
                     &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
@@ -3855,7 +4301,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         false
                     };
                     self.machine
-                        .emit_function_return_value(first_return, canonicalize, loc);
+                        .emit_function_return_value(first_return, canonicalize, loc)?;
                 }
                 let stack_len = self.control_stack.len();
                 let frame = &mut self.control_stack[stack_len - 1 - (relative_depth as usize)];
@@ -3882,10 +4328,10 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     Size::S32,
                     Location::Imm32(targets.len() as u32),
                     cond,
-                );
+                )?;
                 self.machine.jmp_on_aboveequal(default_br);
 
-                self.machine.emit_jmp_to_jumptable(table_label, cond);
+                self.machine.emit_jmp_to_jumptable(table_label, cond)?;
 
                 for target in targets.iter() {
                     let label = self.machine.get_label();
@@ -3914,7 +4360,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             false
                         };
                         self.machine
-                            .emit_function_return_value(first_return, canonicalize, loc);
+                            .emit_function_return_value(first_return, canonicalize, loc)?;
                     }
                     let frame = &self.control_stack
                         [self.control_stack.len().clone() - 1 - (*target as usize)];
@@ -3946,7 +4392,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             false
                         };
                         self.machine
-                            .emit_function_return_value(first_return, canonicalize, loc);
+                            .emit_function_return_value(first_return, canonicalize, loc)?;
                     }
                     let frame = &self.control_stack
                         [self.control_stack.len() - 1 - (default_target as usize)];
@@ -3984,18 +4430,18 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         false
                     };
                     self.machine
-                        .emit_function_return_value(frame.returns[0], canonicalize, loc);
+                        .emit_function_return_value(frame.returns[0], canonicalize, loc)?;
                 }
 
                 if self.control_stack.is_empty() {
                     self.machine.emit_label(frame.label);
-                    self.finalize_locals(self.calling_convention);
-                    self.machine.emit_function_epilog();
+                    self.finalize_locals(self.calling_convention)?;
+                    self.machine.emit_function_epilog()?;
 
                     // Make a copy of the return value in XMM0, as required by the SysV CC.
                     match self.signature.results() {
                         [x] if *x == Type::F32 || *x == Type::F64 => {
-                            self.machine.emit_function_return_float();
+                            self.machine.emit_function_return_float()?;
                         }
                         _ => {}
                     }
@@ -4026,12 +4472,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                                 MachineValue::WasmStack(self.value_stack.len()),
                             )],
                             false,
-                        )[0];
+                        )?[0];
                         self.machine.move_location(
                             Size::S64,
                             Location::GPR(self.machine.get_gpr_for_ret()),
                             loc,
-                        );
+                        )?;
                         self.value_stack.push(loc);
                         if frame.returns[0].is_float() {
                             self.fp_stack
@@ -4056,9 +4502,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_load(
                             target,
@@ -4068,18 +4517,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicLoad8U { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_load_8u(
                             target,
@@ -4089,18 +4541,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicLoad16U { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_load_16u(
                             target,
@@ -4110,14 +4565,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicStore { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_save(
                             target_value,
@@ -4127,14 +4585,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicStore8 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_save_8(
                             target_value,
@@ -4144,14 +4605,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicStore16 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_save_16(
                             target_value,
@@ -4161,18 +4625,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicLoad { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    8,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_load(
                             target,
@@ -4182,18 +4649,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicLoad8U { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_load_8u(
                             target,
@@ -4203,18 +4673,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicLoad16U { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_load_16u(
                             target,
@@ -4224,18 +4697,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicLoad32U { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_load_32u(
                             target,
@@ -4245,14 +4721,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicStore { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    8,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_save(
                             target_value,
@@ -4262,14 +4741,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicStore8 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_save_8(
                             target_value,
@@ -4279,14 +4761,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicStore16 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_save_16(
                             target_value,
@@ -4296,14 +4781,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicStore32 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    target_addr,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_save_32(
                             target_value,
@@ -4313,9 +4801,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmwAdd { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4323,9 +4811,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_add(
                             loc,
@@ -4336,9 +4827,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmwAdd { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4346,9 +4837,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    8,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_add(
                             loc,
@@ -4359,9 +4853,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmw8AddU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4369,9 +4863,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_add_8u(
                             loc,
@@ -4382,9 +4879,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmw16AddU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4392,9 +4889,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_add_16u(
                             loc,
@@ -4405,9 +4905,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw8AddU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4415,9 +4915,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_add_8u(
                             loc,
@@ -4428,9 +4931,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw16AddU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4438,9 +4941,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_add_16u(
                             loc,
@@ -4451,9 +4957,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw32AddU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4461,9 +4967,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_add_32u(
                             loc,
@@ -4474,9 +4983,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmwSub { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4484,9 +4993,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_sub(
                             loc,
@@ -4497,9 +5009,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmwSub { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4507,9 +5019,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    8,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_sub(
                             loc,
@@ -4520,9 +5035,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmw8SubU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4530,9 +5045,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_sub_8u(
                             loc,
@@ -4543,9 +5061,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmw16SubU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4553,9 +5071,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_sub_16u(
                             loc,
@@ -4566,9 +5087,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw8SubU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4576,9 +5097,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_sub_8u(
                             loc,
@@ -4589,9 +5113,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw16SubU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4599,9 +5123,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_sub_16u(
                             loc,
@@ -4612,9 +5139,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw32SubU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4622,9 +5149,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_sub_32u(
                             loc,
@@ -4635,9 +5165,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmwAnd { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4645,9 +5175,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_and(
                             loc,
@@ -4658,9 +5191,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmwAnd { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4668,9 +5201,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    8,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_and(
                             loc,
@@ -4681,9 +5217,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmw8AndU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4691,9 +5227,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_and_8u(
                             loc,
@@ -4704,9 +5243,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmw16AndU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4714,9 +5253,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_and_16u(
                             loc,
@@ -4727,9 +5269,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw8AndU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4737,9 +5279,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_and_8u(
                             loc,
@@ -4750,9 +5295,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw16AndU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4760,9 +5305,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_and_16u(
                             loc,
@@ -4773,9 +5321,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw32AndU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4783,9 +5331,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_and_32u(
                             loc,
@@ -4796,9 +5347,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmwOr { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4806,9 +5357,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_or(
                             loc,
@@ -4819,9 +5373,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmwOr { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4829,9 +5383,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    8,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_or(
                             loc,
@@ -4842,9 +5399,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmw8OrU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4852,9 +5409,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_or_8u(
                             loc,
@@ -4865,9 +5425,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmw16OrU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4875,9 +5435,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_or_16u(
                             loc,
@@ -4888,9 +5451,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw8OrU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4898,9 +5461,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_or_8u(
                             loc,
@@ -4911,9 +5477,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw16OrU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4921,9 +5487,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_or_16u(
                             loc,
@@ -4934,9 +5503,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw32OrU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4944,9 +5513,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_or_32u(
                             loc,
@@ -4957,9 +5529,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmwXor { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4967,9 +5539,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xor(
                             loc,
@@ -4980,9 +5555,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmwXor { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -4990,9 +5565,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    8,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xor(
                             loc,
@@ -5003,9 +5581,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmw8XorU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -5013,9 +5591,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xor_8u(
                             loc,
@@ -5026,9 +5607,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmw16XorU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -5036,9 +5617,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xor_16u(
                             loc,
@@ -5049,9 +5633,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw8XorU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -5059,9 +5643,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xor_8u(
                             loc,
@@ -5072,9 +5659,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw16XorU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -5082,9 +5669,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xor_16u(
                             loc,
@@ -5095,9 +5685,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw32XorU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -5105,9 +5695,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xor_32u(
                             loc,
@@ -5118,9 +5711,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmwXchg { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -5128,9 +5721,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xchg(
                             loc,
@@ -5141,9 +5737,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmwXchg { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -5151,9 +5747,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    8,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xchg(
                             loc,
@@ -5164,9 +5763,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmw8XchgU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -5174,9 +5773,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xchg_8u(
                             loc,
@@ -5187,9 +5789,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmw16XchgU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -5197,9 +5799,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xchg_16u(
                             loc,
@@ -5210,9 +5815,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw8XchgU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -5220,9 +5825,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xchg_8u(
                             loc,
@@ -5233,9 +5841,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw16XchgU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -5243,9 +5851,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xchg_16u(
                             loc,
@@ -5256,9 +5867,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw32XchgU { ref memarg } => {
                 let loc = self.pop_value_released();
@@ -5266,9 +5877,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xchg_32u(
                             loc,
@@ -5279,9 +5893,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmwCmpxchg { ref memarg } => {
                 let new = self.pop_value_released();
@@ -5290,9 +5904,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_cmpxchg(
                             new,
@@ -5304,9 +5921,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmwCmpxchg { ref memarg } => {
                 let new = self.pop_value_released();
@@ -5315,9 +5932,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    8,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_cmpxchg(
                             new,
@@ -5329,9 +5949,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmw8CmpxchgU { ref memarg } => {
                 let new = self.pop_value_released();
@@ -5340,9 +5960,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_cmpxchg_8u(
                             new,
@@ -5354,9 +5977,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I32AtomicRmw16CmpxchgU { ref memarg } => {
                 let new = self.pop_value_released();
@@ -5365,9 +5988,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_cmpxchg_16u(
                             new,
@@ -5379,9 +6005,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw8CmpxchgU { ref memarg } => {
                 let new = self.pop_value_released();
@@ -5390,9 +6016,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    1,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_cmpxchg_8u(
                             new,
@@ -5404,9 +6033,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw16CmpxchgU { ref memarg } => {
                 let new = self.pop_value_released();
@@ -5415,9 +6044,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    2,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_cmpxchg_16u(
                             new,
@@ -5429,9 +6061,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
             Operator::I64AtomicRmw32CmpxchgU { ref memarg } => {
                 let new = self.pop_value_released();
@@ -5440,9 +6072,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    target,
+                    memarg,
+                    4,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_cmpxchg_32u(
                             new,
@@ -5454,9 +6089,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             imported_memories,
                             offset,
                             heap_access_oob,
-                        );
+                        )
                     },
-                );
+                )?;
             }
 
             Operator::RefNull { .. } => {
@@ -5473,7 +6108,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
 
                 // TODO: unclear if we need this? check other new insts with no stack ops
                 //.machine.release_locations_only_osr_state(1);
@@ -5481,6 +6116,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, func_index] -> funcref
                     iter::once(Location::Imm32(function_index as u32)),
@@ -5493,21 +6129,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         MachineValue::WasmStack(self.value_stack.len()),
                     )],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.machine.move_location(
                     Size::S64,
                     Location::GPR(self.machine.get_gpr_for_ret()),
                     ret,
-                );
+                )?;
             }
             Operator::RefIsNull => {
                 let loc_a = self.pop_value_released();
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
-                self.machine.i64_cmp_eq(loc_a, Location::Imm64(0), ret);
+                )?[0];
+                self.machine.i64_cmp_eq(loc_a, Location::Imm64(0), ret)?;
                 self.value_stack.push(ret);
             }
             Operator::TableSet { table: index } => {
@@ -5530,7 +6166,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         ) as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
 
                 // TODO: should this be 2?
                 self.release_locations_only_osr_state(1);
@@ -5538,6 +6174,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, table_index, elem_index, reftype]
                     [Location::Imm32(table_index.index() as u32), index, value]
@@ -5566,13 +6203,14 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         ) as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
 
                 self.release_locations_only_osr_state(1);
                 self.emit_call_native(
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, table_index, elem_index] -> reftype
                     [Location::Imm32(table_index.index() as u32), index]
@@ -5589,13 +6227,13 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         MachineValue::WasmStack(self.value_stack.len()),
                     )],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.machine.move_location(
                     Size::S64,
                     Location::GPR(self.machine.get_gpr_for_ret()),
                     ret,
-                );
+                )?;
             }
             Operator::TableSize { table: index } => {
                 let table_index = TableIndex::new(index as _);
@@ -5613,12 +6251,13 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         ) as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
 
                 self.emit_call_native(
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, table_index] -> i32
                     iter::once(Location::Imm32(table_index.index() as u32)),
@@ -5628,13 +6267,13 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.machine.move_location(
                     Size::S32,
                     Location::GPR(self.machine.get_gpr_for_ret()),
                     ret,
-                );
+                )?;
             }
             Operator::TableGrow { table: index } => {
                 let table_index = TableIndex::new(index as _);
@@ -5655,7 +6294,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         ) as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
 
                 // TODO: should this be 2?
                 self.release_locations_only_osr_state(1);
@@ -5663,6 +6302,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, init_value, delta, table_index] -> u32
                     [
@@ -5680,13 +6320,13 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let ret = self.acquire_locations(
                     &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
-                )[0];
+                )?[0];
                 self.value_stack.push(ret);
                 self.machine.move_location(
                     Size::S32,
                     Location::GPR(self.machine.get_gpr_for_ret()),
                     ret,
-                );
+                )?;
             }
             Operator::TableCopy {
                 dst_table,
@@ -5706,7 +6346,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
 
                 // TODO: should this be 3?
                 self.release_locations_only_osr_state(1);
@@ -5714,6 +6354,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, dst_table_index, src_table_index, dst, src, len]
                     [
@@ -5754,7 +6395,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
 
                 // TODO: should this be 3?
                 self.release_locations_only_osr_state(1);
@@ -5762,6 +6403,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, table_index, start_idx, item, len]
                     [Location::Imm32(table), dest, val, len].iter().cloned(),
@@ -5787,7 +6429,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
 
                 // TODO: should this be 3?
                 self.release_locations_only_osr_state(1);
@@ -5795,6 +6437,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, table_index, elem_index, dst, src, len]
                     [
@@ -5829,7 +6472,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
-                );
+                )?;
 
                 // TODO: do we need this?
                 //.machine.release_locations_only_osr_state(1);
@@ -5837,6 +6480,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     |this| {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
+                        Ok(())
                     },
                     // [vmctx, elem_index]
                     [Location::Imm32(segment)].iter().cloned(),
@@ -5911,6 +6555,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         let address_map =
             get_function_address_map(self.machine.instructions_address_map(), data, body_len);
         let traps = self.machine.collect_trap_information();
+        let frame_size = self.frame_size();
         let body = self.machine.assembler_finalize();
 
         (
@@ -5923,6 +6568,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 frame_info: CompiledFunctionFrameInfo {
                     traps: traps,
                     address_map,
+                    frame_size: Some(frame_size),
+                    step_points: self.step_points,
+                    patch_points: self.patch_points,
                 },
             },
             fde,
@@ -5977,3 +6625,19 @@ impl<'a, M: Machine> FuncGen<'a, M> {
     }
     */
 }
+
+/// Describes a location of the code generator for single-stepping.
+fn value_location<R: Reg, S: Reg>(loc: Location<R, S>) -> ValueLocation {
+    match loc {
+        Location::GPR(reg) => ValueLocation::Register(reg.into_index() as u16),
+        Location::SIMD(reg) => ValueLocation::SimdRegister(reg.into_index() as u16),
+        Location::Memory(base, offset) => ValueLocation::Memory {
+            base: base.into_index() as u16,
+            offset,
+        },
+        Location::Imm8(value) => ValueLocation::Constant(value as u64),
+        Location::Imm32(value) => ValueLocation::Constant(value as u64),
+        Location::Imm64(value) => ValueLocation::Constant(value),
+        Location::Memory2(..) | Location::None => ValueLocation::Unknown,
+    }
+}
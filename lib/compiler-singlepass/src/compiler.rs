@@ -2,6 +2,8 @@
 // Allow unused imports while developing.
 #![allow(unused_imports, dead_code)]
 
+use crate::code_dump::FunctionListing;
+use crate::code_size::FunctionCodeSize;
 use crate::codegen::FuncGen;
 use crate::config::Singlepass;
 #[cfg(feature = "unwind")]
@@ -19,11 +21,14 @@ use gimli::write::{EhFrame, FrameTable};
 use loupe::MemoryUsage;
 #[cfg(feature = "rayon")]
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use std::io;
+use std::path::Path;
 use std::sync::Arc;
+use wasmer_compiler::wasmparser::Operator;
 use wasmer_compiler::{
-    Architecture, CallingConvention, Compilation, CompileError, CompileModuleInfo,
-    CompiledFunction, Compiler, CompilerConfig, CpuFeature, Dwarf, FunctionBinaryReader,
-    FunctionBody, FunctionBodyData, MiddlewareBinaryReader, ModuleMiddleware,
+    Architecture, CallingConvention, CancellationToken, Compilation, CompileError,
+    CompileModuleInfo, CompiledFunction, Compiler, CompilerConfig, CpuFeature, Dwarf,
+    FunctionBinaryReader, FunctionBody, FunctionBodyData, MiddlewareBinaryReader, ModuleMiddleware,
     ModuleMiddlewareChain, ModuleTranslationState, OperatingSystem, SectionIndex, Target,
     TrapInformation,
 };
@@ -61,15 +66,35 @@ impl Compiler for SinglepassCompiler {
     /// Compile the module using Singlepass, producing a compilation result with
     /// associated relocations.
     fn compile_module(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) -> Result<Compilation, CompileError> {
+        self.compile_module_cancellable(
+            target,
+            compile_info,
+            module_translation,
+            function_body_inputs,
+            &CancellationToken::new(),
+        )
+    }
+
+    /// Compile the module using Singlepass, checking `cancellation`
+    /// before each function and every few thousand operators.
+    fn compile_module_cancellable(
         &self,
         target: &Target,
         compile_info: &CompileModuleInfo,
         _module_translation: &ModuleTranslationState,
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+        cancellation: &CancellationToken,
     ) -> Result<Compilation, CompileError> {
+        cancellation.check()?;
         match target.triple().architecture {
             Architecture::X86_64 => {}
-            Architecture::Aarch64(_) => {}
+            Architecture::Aarch64(_) => self.config.arm64_registers.validate()?,
             _ => {
                 return Err(CompileError::UnsupportedTarget(
                     target.triple().architecture.to_string(),
@@ -139,6 +164,7 @@ impl Compiler for SinglepassCompiler {
                     &module.signatures[module.functions[i]],
                     target,
                     calling_convention,
+                    &self.config,
                 )
             })
             .collect::<Vec<_>>()
@@ -149,6 +175,7 @@ impl Compiler for SinglepassCompiler {
             .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
             .into_par_iter_if_rayon()
             .map(|(i, input)| {
+                cancellation.check()?;
                 let middleware_chain = self
                     .config
                     .middlewares
@@ -182,16 +209,57 @@ impl Compiler for SinglepassCompiler {
                             calling_convention,
                         )
                         .map_err(to_compile_error)?;
+                        let mut code_size = self
+                            .config
+                            .code_size_report
+                            .as_ref()
+                            .map(|_| FunctionCodeSize::default());
+                        let mut listing = self
+                            .config
+                            .code_dump_dir
+                            .as_ref()
+                            .map(|_| FunctionListing::default());
+                        let mut operators = 0usize;
                         while generator.has_control_frames() {
-                            generator.set_srcloc(reader.original_position() as u32);
+                            operators += 1;
+                            if operators % CANCELLATION_CHECK_INTERVAL == 0 {
+                                cancellation.check()?;
+                            }
+                            let offset = reader.original_position();
+                            generator.set_srcloc(offset as u32);
                             let op = reader.read_operator()?;
-                            generator.feed_operator(op).map_err(to_compile_error)?;
+                            let code_start = generator.code_offset();
+                            generator
+                                .feed_operator(op.clone())
+                                .map_err(|e| operator_error(e, &op, offset))
+                                .map_err(to_compile_error)?;
+                            if let Some(code_size) = code_size.as_mut() {
+                                code_size.record(&op, generator.code_offset() - code_start);
+                            }
+                            if let Some(listing) = listing.as_mut() {
+                                listing.record(offset, &op, code_start, generator.code_offset());
+                            }
+                            if self.config.single_step {
+                                generator.emit_step_point();
+                            }
+                        }
+                        if let (Some(report), Some(code_size)) =
+                            (&self.config.code_size_report, code_size)
+                        {
+                            report.merge(code_size);
                         }
 
-                        Ok(generator.finalize(&input))
+                        let (function, fde) = generator.finalize(&input);
+                        if let (Some(dir), Some(listing)) = (&self.config.code_dump_dir, listing) {
+                            listing
+                                .write(dir, i, &function.body.body)
+                                .map_err(|e| code_dump_error(dir, e))?;
+                        }
+                        Ok((function, fde))
                     }
                     Architecture::Aarch64(_) => {
-                        let machine = MachineARM64::new();
+                        let machine =
+                            MachineARM64::new_with_registers(&self.config.arm64_registers);
                         let mut generator = FuncGen::new(
                             module,
                             &self.config,
@@ -204,13 +272,53 @@ impl Compiler for SinglepassCompiler {
                             calling_convention,
                         )
                         .map_err(to_compile_error)?;
+                        let mut code_size = self
+                            .config
+                            .code_size_report
+                            .as_ref()
+                            .map(|_| FunctionCodeSize::default());
+                        let mut listing = self
+                            .config
+                            .code_dump_dir
+                            .as_ref()
+                            .map(|_| FunctionListing::default());
+                        let mut operators = 0usize;
                         while generator.has_control_frames() {
-                            generator.set_srcloc(reader.original_position() as u32);
+                            operators += 1;
+                            if operators % CANCELLATION_CHECK_INTERVAL == 0 {
+                                cancellation.check()?;
+                            }
+                            let offset = reader.original_position();
+                            generator.set_srcloc(offset as u32);
                             let op = reader.read_operator()?;
-                            generator.feed_operator(op).map_err(to_compile_error)?;
+                            let code_start = generator.code_offset();
+                            generator
+                                .feed_operator(op.clone())
+                                .map_err(|e| operator_error(e, &op, offset))
+                                .map_err(to_compile_error)?;
+                            if let Some(code_size) = code_size.as_mut() {
+                                code_size.record(&op, generator.code_offset() - code_start);
+                            }
+                            if let Some(listing) = listing.as_mut() {
+                                listing.record(offset, &op, code_start, generator.code_offset());
+                            }
+                            if self.config.single_step {
+                                generator.emit_step_point();
+                            }
+                        }
+                        if let (Some(report), Some(code_size)) =
+                            (&self.config.code_size_report, code_size)
+                        {
+                            report.merge(code_size);
                         }
 
-                        Ok(generator.finalize(&input))
+                        let (function, fde) = generator.finalize(&input);
+                        if let (Some(dir), Some(listing)) = (&self.config.code_dump_dir, listing) {
+                            listing
+                                .write(dir, i, &function.body.body)
+                                .map_err(|e| code_dump_error(dir, e))?;
+                        }
+                        Ok((function, fde))
                     }
                     _ => unimplemented!(),
                 }
@@ -224,7 +332,9 @@ impl Compiler for SinglepassCompiler {
             .values()
             .collect::<Vec<_>>()
             .into_par_iter_if_rayon()
-            .map(|func_type| gen_std_trampoline(&func_type, target, calling_convention))
+            .map(|func_type| {
+                gen_std_trampoline(&func_type, target, calling_convention, &self.config)
+            })
             .collect::<Vec<_>>()
             .into_iter()
             .collect::<PrimaryMap<_, _>>();
@@ -239,6 +349,7 @@ impl Compiler for SinglepassCompiler {
                     &func_type,
                     target,
                     calling_convention,
+                    &self.config,
                 )
             })
             .collect::<Vec<_>>()
@@ -276,6 +387,10 @@ impl Compiler for SinglepassCompiler {
     }
 }
 
+/// The number of operators compiled between two checks of the
+/// cancellation token within a function.
+const CANCELLATION_CHECK_INTERVAL: usize = 4096;
+
 trait ToCompileError {
     fn to_compile_error(self) -> CompileError;
 }
@@ -286,6 +401,24 @@ impl ToCompileError for CodegenError {
     }
 }
 
+/// Adds the operator being compiled and its offset to a codegen error.
+fn operator_error(e: CodegenError, op: &Operator, offset: usize) -> CodegenError {
+    CodegenError {
+        message: format!(
+            "{} (while compiling {:?} at offset {})",
+            e.message, op, offset
+        ),
+    }
+}
+
+fn code_dump_error(dir: &Path, e: io::Error) -> CompileError {
+    CompileError::Codegen(format!(
+        "failed to write the code listing in {}: {}",
+        dir.display(),
+        e
+    ))
+}
+
 fn to_compile_error<T: ToCompileError>(x: T) -> CompileError {
     x.to_compile_error()
 }
@@ -312,6 +445,7 @@ impl<T: Send> IntoParIterIfRayon for Vec<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::Arm64Registers;
     use std::str::FromStr;
     use target_lexicon::triple;
     use wasmer_compiler::{CpuFeature, Features, Triple};
@@ -355,4 +489,48 @@ mod tests {
             error => panic!("Unexpected error: {:?}", error),
         };
     }
+
+    #[test]
+    fn errors_for_invalid_arm64_registers() {
+        let aarch64 = Target::new(triple!("aarch64-unknown-linux-gnu"), CpuFeature::set());
+        let invalid = [
+            // x9 is caller-saved
+            Arm64Registers {
+                vmctx: 9,
+                ..Arm64Registers::default()
+            },
+            // x27 is used twice
+            Arm64Registers {
+                vmctx: 27,
+                ..Arm64Registers::default()
+            },
+            // too many registers for locals
+            Arm64Registers {
+                locals: vec![19, 20, 21, 22, 23, 24, 25, 26, 28],
+                call: 27,
+                vmctx: 28,
+            },
+        ];
+        for registers in invalid.iter() {
+            let mut config = Singlepass::default();
+            config.arm64_registers(registers.clone());
+            let compiler = SinglepassCompiler::new(config);
+            let (mut info, translation, inputs) = dummy_compilation_ingredients();
+            let result = compiler.compile_module(&aarch64, &mut info, &translation, inputs);
+            match result.unwrap_err() {
+                CompileError::Codegen(message) => {
+                    assert!(message.starts_with("invalid ARM64 register configuration"))
+                }
+                error => panic!("Unexpected error: {:?}", error),
+            };
+        }
+
+        let mut config = Singlepass::default();
+        config.arm64_registers(Arm64Registers {
+            vmctx: 19,
+            call: 20,
+            locals: vec![21, 22, 23],
+        });
+        assert!(config.arm64_registers.validate().is_ok());
+    }
 }
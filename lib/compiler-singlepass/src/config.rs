@@ -1,15 +1,96 @@
 // Allow unused imports while developing
 #![allow(unused_imports, dead_code)]
 
+use crate::code_size::CodeSizeReport;
 use crate::compiler::SinglepassCompiler;
 use loupe::MemoryUsage;
+use std::path::PathBuf;
 use std::sync::Arc;
-use wasmer_compiler::{Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, Target};
+use wasmer_compiler::{
+    Architecture, CompileError, Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, Target,
+};
 use wasmer_types::Features;
 
+/// The general purpose registers Singlepass claims for itself on ARM64.
+///
+/// Registers are given by number, so `28` stands for `x28`. All of them
+/// must be distinct callee-saved registers (`x19` to `x28`); callee-saved
+/// registers which are left out are never touched by the generated code.
+#[derive(Debug, Clone, PartialEq, Eq, MemoryUsage)]
+pub struct Arm64Registers {
+    /// The register holding the `VMContext` pointer. Defaults to `x28`.
+    pub vmctx: u8,
+    /// The register holding the target of calls. Defaults to `x27`.
+    pub call: u8,
+    /// The registers the first locals of a function live in, in order.
+    /// At most 8 registers, defaults to `x19` to `x26`.
+    pub locals: Vec<u8>,
+}
+
+impl Arm64Registers {
+    /// The maximum number of locals which can live in registers.
+    pub const MAX_LOCALS: usize = 8;
+
+    /// Checks that this register assignment can be used by Singlepass.
+    pub fn validate(&self) -> Result<(), CompileError> {
+        let invalid = |message: String| {
+            Err(CompileError::Codegen(format!(
+                "invalid ARM64 register configuration: {}",
+                message
+            )))
+        };
+        if self.locals.len() > Self::MAX_LOCALS {
+            return invalid(format!(
+                "{} local registers given, at most {} are supported",
+                self.locals.len(),
+                Self::MAX_LOCALS
+            ));
+        }
+        let mut seen = 0u32;
+        for &reg in [self.vmctx, self.call].iter().chain(self.locals.iter()) {
+            if !(19..=28).contains(&reg) {
+                return invalid(format!("x{} is not a callee-saved register", reg));
+            }
+            if seen & (1 << reg) != 0 {
+                return invalid(format!("x{} is assigned more than once", reg));
+            }
+            seen |= 1 << reg;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Arm64Registers {
+    fn default() -> Self {
+        Self {
+            vmctx: 28,
+            call: 27,
+            locals: (19..=26).collect(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, MemoryUsage)]
 pub struct Singlepass {
     pub(crate) enable_nan_canonicalization: bool,
+    /// The registers reserved by the ARM64 backend.
+    pub(crate) arm64_registers: Arm64Registers,
+    /// Where to account the code emitted per operator, if anywhere.
+    pub(crate) code_size_report: Option<CodeSizeReport>,
+    /// Whether to stop after every operator.
+    pub(crate) single_step: bool,
+    /// Whether to reserve a patch point at the entry of functions.
+    pub(crate) patch_function_entries: bool,
+    /// Whether to reserve a patch point at the header of loops.
+    pub(crate) patch_loop_headers: bool,
+    /// The alignment of loop headers, if not the default of the target.
+    pub(crate) loop_alignment: Option<usize>,
+    /// Whether `call_indirect` skips the signature check of the function
+    /// last called with the same signature.
+    pub(crate) call_indirect_inline_cache: bool,
+    /// Where to write the listings of the compiled functions, if anywhere.
+    #[loupe(skip)]
+    pub(crate) code_dump_dir: Option<PathBuf>,
     /// The middleware chain.
     pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
 }
@@ -20,6 +101,14 @@ impl Singlepass {
     pub fn new() -> Self {
         Self {
             enable_nan_canonicalization: true,
+            arm64_registers: Arm64Registers::default(),
+            code_size_report: None,
+            single_step: false,
+            patch_function_entries: false,
+            patch_loop_headers: false,
+            loop_alignment: None,
+            call_indirect_inline_cache: false,
+            code_dump_dir: None,
             middlewares: vec![],
         }
     }
@@ -32,6 +121,94 @@ impl Singlepass {
         self.enable_nan_canonicalization = enable;
         self
     }
+
+    /// Sets the registers the ARM64 backend uses for the `VMContext`,
+    /// call targets and locals.
+    ///
+    /// The assignment is validated when a module is compiled for ARM64.
+    pub fn arm64_registers(&mut self, registers: Arm64Registers) -> &mut Self {
+        self.arm64_registers = registers;
+        self
+    }
+
+    /// Enables or disables accounting the machine code emitted for each
+    /// kind of operator. It makes compilation slower.
+    pub fn collect_code_size(&mut self, enable: bool) -> &mut Self {
+        self.code_size_report = if enable {
+            Some(self.code_size_report.take().unwrap_or_default())
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Enables or disables the single-stepping mode, where a breakpoint
+    /// is placed after every operator. Each breakpoint reports the
+    /// locals and the operand stack to the step callback of the engine.
+    ///
+    /// This is meant for testing, the generated code is much slower.
+    pub fn single_step(&mut self, enable: bool) -> &mut Self {
+        self.single_step = enable;
+        self
+    }
+
+    /// Reserves a patch point at the entry of every function, and
+    /// optionally at the header of every loop: a nop the size of a
+    /// breakpoint instruction, which `Function::set_probes` replaces by a
+    /// breakpoint at runtime to call the probe callback of the engine,
+    /// without recompiling the module.
+    ///
+    /// The nops cost little, but probes are only supported on Linux, on
+    /// x86_64 and ARM64.
+    pub fn patch_points(&mut self, function_entries: bool, loop_headers: bool) -> &mut Self {
+        self.patch_function_entries = function_entries;
+        self.patch_loop_headers = loop_headers;
+        self
+    }
+
+    /// Sets the alignment in bytes of the first instruction of loops,
+    /// which is padded with NOPs. By default, loops are aligned to 16
+    /// bytes on x86_64 and not aligned on ARM64.
+    ///
+    /// # Panics
+    ///
+    /// If `alignment` isn't a power of two.
+    pub fn loop_alignment(&mut self, alignment: usize) -> &mut Self {
+        assert!(
+            alignment.is_power_of_two(),
+            "the loop alignment must be a power of two"
+        );
+        self.loop_alignment = Some(alignment);
+        self
+    }
+
+    /// Enables or disables caching the target of `call_indirect`. Each
+    /// instance remembers the function it last called indirectly with each
+    /// signature, and calling it again skips the signature check, which
+    /// helps code dispatching through tables such as interface tables.
+    ///
+    /// Only the functions of the calling instance are cached. The cache
+    /// adds a compare and a store to the calls which miss it.
+    pub fn call_indirect_inline_cache(&mut self, enable: bool) -> &mut Self {
+        self.call_indirect_inline_cache = enable;
+        self
+    }
+
+    /// Writes the machine code of each compiled function to
+    /// `function_<index>.s` in `dir`, as assembly annotated with the
+    /// operators which emitted it, or stops writing it if `dir` is `None`.
+    ///
+    /// This is meant for debugging the code generation.
+    pub fn dump_code(&mut self, dir: Option<PathBuf>) -> &mut Self {
+        self.code_dump_dir = dir;
+        self
+    }
+
+    /// The code size report filled by the compilations made with this
+    /// configuration, if `collect_code_size` is enabled.
+    pub fn code_size_report(&self) -> Option<CodeSizeReport> {
+        self.code_size_report.clone()
+    }
 }
 
 impl CompilerConfig for Singlepass {
@@ -40,6 +217,14 @@ impl CompilerConfig for Singlepass {
         // PIC code.
     }
 
+    fn canonicalize_nans(&mut self, enable: bool) {
+        self.enable_nan_canonicalization = enable;
+    }
+
+    fn loop_alignment(&mut self, alignment: usize) {
+        Singlepass::loop_alignment(self, alignment);
+    }
+
     /// Transform it into the compiler
     fn compiler(self: Box<Self>) -> Box<dyn Compiler> {
         Box::new(SinglepassCompiler::new(*self))
@@ -52,6 +237,17 @@ impl CompilerConfig for Singlepass {
         features
     }
 
+    /// Gets the features this compiler is able to compile in the given target
+    fn supported_features_for_target(&self, target: &Target) -> Features {
+        let mut features = Features::default();
+        features.multi_value(false).simd(false);
+        features.threads(matches!(
+            target.triple().architecture,
+            Architecture::X86_64 | Architecture::Aarch64(_)
+        ));
+        features
+    }
+
     /// Pushes a middleware onto the back of the middleware chain.
     fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>) {
         self.middlewares.push(middleware);
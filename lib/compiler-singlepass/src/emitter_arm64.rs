@@ -99,6 +99,8 @@ pub trait EmitterARM64 {
     fn emit_ldria(&mut self, sz: Size, reg: Location, addr: GPR, offset: u32);
     fn emit_stpdb(&mut self, sz: Size, reg1: Location, reg2: Location, addr: GPR, offset: u32);
     fn emit_ldpia(&mut self, sz: Size, reg1: Location, reg2: Location, addr: GPR, offset: u32);
+    fn emit_stp(&mut self, sz: Size, reg1: Location, reg2: Location, addr: GPR, offset: i32);
+    fn emit_ldp(&mut self, sz: Size, reg1: Location, reg2: Location, addr: GPR, offset: i32);
 
     fn emit_ldrb(&mut self, sz: Size, reg: Location, dst: Location);
     fn emit_ldrh(&mut self, sz: Size, reg: Location, dst: Location);
@@ -107,6 +109,10 @@ pub trait EmitterARM64 {
     fn emit_ldrsw(&mut self, sz: Size, reg: Location, dst: Location);
     fn emit_strb(&mut self, sz: Size, reg: Location, dst: Location);
     fn emit_strh(&mut self, sz: Size, reg: Location, dst: Location);
+    fn emit_ldar(&mut self, sz: Size, reg: Location, addr: GPR);
+    fn emit_stlr(&mut self, sz: Size, reg: Location, addr: GPR);
+    fn emit_ldaxr(&mut self, sz: Size, reg: Location, addr: GPR);
+    fn emit_stlxr(&mut self, sz: Size, status: Location, reg: Location, addr: GPR);
 
     fn emit_mov(&mut self, sz: Size, src: Location, dst: Location);
 
@@ -155,6 +161,8 @@ pub trait EmitterARM64 {
     fn emit_cinc(&mut self, sz: Size, src: Location, dst: Location, cond: Condition);
     fn emit_clz(&mut self, sz: Size, src: Location, dst: Location);
     fn emit_rbit(&mut self, sz: Size, src: Location, dst: Location);
+    fn emit_cnt(&mut self, src: Location, dst: Location);
+    fn emit_addv(&mut self, src: Location, dst: Location);
 
     fn emit_label(&mut self, label: Label);
     fn emit_load_label(&mut self, reg: GPR, label: Label);
@@ -173,6 +181,7 @@ pub trait EmitterARM64 {
     fn emit_udf(&mut self, payload: u16);
     fn emit_dmb(&mut self);
     fn emit_brk(&mut self);
+    fn emit_nop(&mut self);
 
     fn emit_fcmp(&mut self, sz: Size, src1: Location, src2: Location);
     fn emit_fneg(&mut self, sz: Size, src: Location, dst: Location);
@@ -290,10 +299,11 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; str X(reg), [X(addr)]),
                     1 => dynasm!(self ; str X(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; str X(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; str X(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             (Size::S32, Location::GPR(reg), Location::Memory2(addr, r2, mult, offs)) => {
@@ -302,10 +312,11 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; str W(reg), [X(addr)]),
                     1 => dynasm!(self ; str W(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; str W(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; str W(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             _ => panic!("singlepass can't emit STR {:?}, {:?}, {:?}", sz, reg, addr),
@@ -347,10 +358,11 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; ldr X(reg), [X(addr)]),
                     1 => dynasm!(self ; ldr X(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; ldr X(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; ldr X(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             (Size::S32, Location::GPR(reg), Location::Memory2(addr, r2, mult, offs)) => {
@@ -359,10 +371,11 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; ldr W(reg), [X(addr)]),
                     1 => dynasm!(self ; ldr W(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; ldr W(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; ldr W(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             (Size::S64, Location::SIMD(reg), Location::Memory(addr, disp)) => {
@@ -385,10 +398,11 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; ldr D(reg), [X(addr)]),
                     1 => dynasm!(self ; ldr D(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; ldr D(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; ldr D(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             (Size::S32, Location::SIMD(reg), Location::Memory2(addr, r2, mult, offs)) => {
@@ -397,10 +411,11 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; ldr S(reg), [X(addr)]),
                     1 => dynasm!(self ; ldr S(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; ldr S(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; ldr S(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             _ => panic!("singlepass can't emit LDR {:?}, {:?}, {:?}", sz, reg, addr),
@@ -538,6 +553,48 @@ impl EmitterARM64 for Assembler {
             _ => unreachable!(),
         }
     }
+    fn emit_stp(&mut self, sz: Size, reg1: Location, reg2: Location, addr: GPR, offset: i32) {
+        assert!((offset >= -512) && (offset <= 504) && (offset & 0x7) == 0);
+        match (sz, reg1, reg2) {
+            (Size::S64, Location::GPR(reg1), Location::GPR(reg2)) => {
+                let reg1 = reg1.into_index() as u32;
+                let reg2 = reg2.into_index() as u32;
+                let addr = addr.into_index() as u32;
+                dynasm!(self ; stp X(reg1), X(reg2), [X(addr), offset]);
+            }
+            (Size::S64, Location::SIMD(reg1), Location::SIMD(reg2)) => {
+                let reg1 = reg1.into_index() as u32;
+                let reg2 = reg2.into_index() as u32;
+                let addr = addr.into_index() as u32;
+                dynasm!(self ; stp D(reg1), D(reg2), [X(addr), offset]);
+            }
+            _ => panic!(
+                "singlepass can't emit STP {:?}, {:?}, {:?}, {:?}, {:?}",
+                sz, reg1, reg2, addr, offset
+            ),
+        }
+    }
+    fn emit_ldp(&mut self, sz: Size, reg1: Location, reg2: Location, addr: GPR, offset: i32) {
+        assert!((offset >= -512) && (offset <= 504) && (offset & 0x7) == 0);
+        match (sz, reg1, reg2) {
+            (Size::S64, Location::GPR(reg1), Location::GPR(reg2)) => {
+                let reg1 = reg1.into_index() as u32;
+                let reg2 = reg2.into_index() as u32;
+                let addr = addr.into_index() as u32;
+                dynasm!(self ; ldp X(reg1), X(reg2), [X(addr), offset]);
+            }
+            (Size::S64, Location::SIMD(reg1), Location::SIMD(reg2)) => {
+                let reg1 = reg1.into_index() as u32;
+                let reg2 = reg2.into_index() as u32;
+                let addr = addr.into_index() as u32;
+                dynasm!(self ; ldp D(reg1), D(reg2), [X(addr), offset]);
+            }
+            _ => panic!(
+                "singlepass can't emit LDP {:?}, {:?}, {:?}, {:?}, {:?}",
+                sz, reg1, reg2, addr, offset
+            ),
+        }
+    }
 
     fn emit_ldrb(&mut self, _sz: Size, reg: Location, dst: Location) {
         match (reg, dst) {
@@ -554,10 +611,11 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; ldrb W(reg), [X(addr)]),
                     1 => dynasm!(self ; ldrb W(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; ldrb W(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; ldrb W(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             _ => panic!("singlepass can't emit LDRB {:?}, {:?}", reg, dst),
@@ -578,15 +636,111 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; ldrh W(reg), [X(addr)]),
                     1 => dynasm!(self ; ldrh W(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; ldrh W(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; ldrh W(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             _ => panic!("singlepass can't emit LDRH {:?}, {:?}", reg, dst),
         }
     }
+    fn emit_ldar(&mut self, sz: Size, reg: Location, addr: GPR) {
+        let addr = addr.into_index() as u32;
+        match (sz, reg) {
+            (Size::S8, Location::GPR(reg)) => {
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; ldarb W(reg), [X(addr)]);
+            }
+            (Size::S16, Location::GPR(reg)) => {
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; ldarh W(reg), [X(addr)]);
+            }
+            (Size::S32, Location::GPR(reg)) => {
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; ldar W(reg), [X(addr)]);
+            }
+            (Size::S64, Location::GPR(reg)) => {
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; ldar X(reg), [X(addr)]);
+            }
+            _ => panic!("singlepass can't emit LDAR {:?}, {:?}", sz, reg),
+        }
+    }
+    fn emit_stlr(&mut self, sz: Size, reg: Location, addr: GPR) {
+        let addr = addr.into_index() as u32;
+        match (sz, reg) {
+            (Size::S8, Location::GPR(reg)) => {
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; stlrb W(reg), [X(addr)]);
+            }
+            (Size::S16, Location::GPR(reg)) => {
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; stlrh W(reg), [X(addr)]);
+            }
+            (Size::S32, Location::GPR(reg)) => {
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; stlr W(reg), [X(addr)]);
+            }
+            (Size::S64, Location::GPR(reg)) => {
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; stlr X(reg), [X(addr)]);
+            }
+            _ => panic!("singlepass can't emit STLR {:?}, {:?}", sz, reg),
+        }
+    }
+    fn emit_ldaxr(&mut self, sz: Size, reg: Location, addr: GPR) {
+        let addr = addr.into_index() as u32;
+        match (sz, reg) {
+            (Size::S8, Location::GPR(reg)) => {
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; ldaxrb W(reg), [X(addr)]);
+            }
+            (Size::S16, Location::GPR(reg)) => {
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; ldaxrh W(reg), [X(addr)]);
+            }
+            (Size::S32, Location::GPR(reg)) => {
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; ldaxr W(reg), [X(addr)]);
+            }
+            (Size::S64, Location::GPR(reg)) => {
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; ldaxr X(reg), [X(addr)]);
+            }
+            _ => panic!("singlepass can't emit LDAXR {:?}, {:?}", sz, reg),
+        }
+    }
+    fn emit_stlxr(&mut self, sz: Size, status: Location, reg: Location, addr: GPR) {
+        let addr = addr.into_index() as u32;
+        match (sz, status, reg) {
+            (Size::S8, Location::GPR(status), Location::GPR(reg)) => {
+                let status = status.into_index() as u32;
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; stlxrb W(status), W(reg), [X(addr)]);
+            }
+            (Size::S16, Location::GPR(status), Location::GPR(reg)) => {
+                let status = status.into_index() as u32;
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; stlxrh W(status), W(reg), [X(addr)]);
+            }
+            (Size::S32, Location::GPR(status), Location::GPR(reg)) => {
+                let status = status.into_index() as u32;
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; stlxr W(status), W(reg), [X(addr)]);
+            }
+            (Size::S64, Location::GPR(status), Location::GPR(reg)) => {
+                let status = status.into_index() as u32;
+                let reg = reg.into_index() as u32;
+                dynasm!(self ; stlxr W(status), X(reg), [X(addr)]);
+            }
+            _ => panic!(
+                "singlepass can't emit STLXR {:?}, {:?}, {:?}",
+                sz, status, reg
+            ),
+        }
+    }
     fn emit_ldrsb(&mut self, sz: Size, reg: Location, dst: Location) {
         match (sz, reg, dst) {
             (Size::S64, Location::GPR(reg), Location::Memory(addr, offset)) => {
@@ -609,10 +763,11 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; ldrsb X(reg), [X(addr)]),
                     1 => dynasm!(self ; ldrsb X(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; ldrsb X(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; ldrsb X(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             (Size::S32, Location::GPR(reg), Location::Memory2(addr, r2, mult, offs)) => {
@@ -621,10 +776,11 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; ldrsb W(reg), [X(addr)]),
                     1 => dynasm!(self ; ldrsb W(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; ldrsb W(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; ldrsb W(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             _ => panic!("singlepass can't emit LDRSB {:?}, {:?}, {:?}", sz, reg, dst),
@@ -652,10 +808,11 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; ldrsh X(reg), [X(addr)]),
                     1 => dynasm!(self ; ldrsh X(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; ldrsh X(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; ldrsh X(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             (Size::S32, Location::GPR(reg), Location::Memory2(addr, r2, mult, offs)) => {
@@ -664,10 +821,11 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; ldrsh W(reg), [X(addr)]),
                     1 => dynasm!(self ; ldrsh W(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; ldrsh W(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; ldrsh W(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             _ => panic!("singlepass can't emit LDRSH {:?}, {:?}, {:?}", sz, reg, dst),
@@ -688,10 +846,11 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; ldrsw X(reg), [X(addr)]),
                     1 => dynasm!(self ; ldrsw X(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; ldrsw X(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; ldrsw X(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             _ => panic!("singlepass can't emit LDRSW {:?}, {:?}, {:?}", sz, reg, dst),
@@ -712,10 +871,11 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; strb W(reg), [X(addr)]),
                     1 => dynasm!(self ; strb W(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; strb W(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; strb W(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             _ => panic!("singlepass can't emit STRB {:?}, {:?}", reg, dst),
@@ -736,10 +896,11 @@ impl EmitterARM64 for Assembler {
                 let r2 = r2.into_index() as u32;
                 assert!(offs == 0);
                 let mult = mult as u32;
+                let shift = mult.trailing_zeros();
                 match mult {
                     0 => dynasm!(self ; strh W(reg), [X(addr)]),
                     1 => dynasm!(self ; strh W(reg), [X(addr), X(r2)]),
-                    _ => dynasm!(self ; strh W(reg), [X(addr), X(r2), LSL mult]),
+                    _ => dynasm!(self ; strh W(reg), [X(addr), X(r2), LSL shift]),
                 };
             }
             _ => panic!("singlepass can't emit STRH {:?}, {:?}", reg, dst),
@@ -1890,6 +2051,26 @@ impl EmitterARM64 for Assembler {
             _ => panic!("singlepass can't emit CLS {:?} {:?} {:?}", sz, src, dst),
         }
     }
+    fn emit_cnt(&mut self, src: Location, dst: Location) {
+        match (src, dst) {
+            (Location::SIMD(src), Location::SIMD(dst)) => {
+                let src = src.into_index() as u32;
+                let dst = dst.into_index() as u32;
+                dynasm!(self ; cnt V(dst).B8, V(src).B8);
+            }
+            _ => panic!("singlepass can't emit CNT {:?} {:?}", src, dst),
+        }
+    }
+    fn emit_addv(&mut self, src: Location, dst: Location) {
+        match (src, dst) {
+            (Location::SIMD(src), Location::SIMD(dst)) => {
+                let src = src.into_index() as u32;
+                let dst = dst.into_index() as u32;
+                dynasm!(self ; addv B(dst), V(src).B8);
+            }
+            _ => panic!("singlepass can't emit ADDV {:?} {:?}", src, dst),
+        }
+    }
 
     fn emit_label(&mut self, label: Label) {
         dynasm!(self ; => label);
@@ -2024,6 +2205,9 @@ impl EmitterARM64 for Assembler {
     fn emit_brk(&mut self) {
         dynasm!(self ; brk 0);
     }
+    fn emit_nop(&mut self) {
+        dynasm!(self ; nop);
+    }
 
     fn emit_fcmp(&mut self, sz: Size, src1: Location, src2: Location) {
         match (sz, src1, src2) {
@@ -2402,15 +2586,16 @@ impl EmitterARM64 for Assembler {
     }
 }
 
+// Generates the entry trampoline for a function type.
+// `fptr` and `args` must be callee-saved registers, they are preserved by the trampoline.
 pub fn gen_std_trampoline_arm64(
     sig: &FunctionType,
     calling_convention: CallingConvention,
+    fptr: GPR,
+    args: GPR,
 ) -> FunctionBody {
     let mut a = Assembler::new(0);
 
-    let fptr = GPR::X27;
-    let args = GPR::X28;
-
     dynasm!(a
         ; sub sp, sp, 32
         ; stp x29, x30, [sp]
@@ -2525,19 +2710,21 @@ pub fn gen_std_trampoline_arm64(
     }
 }
 // Generates dynamic import function call trampoline for a function type.
+// `scratch` must not be an argument register, it is preserved by the trampoline.
 pub fn gen_std_dynamic_import_trampoline_arm64(
     vmoffsets: &VMOffsets,
     sig: &FunctionType,
     calling_convention: CallingConvention,
+    scratch: GPR,
 ) -> FunctionBody {
     let mut a = Assembler::new(0);
     // Allocate argument array.
     let stack_offset: usize = 16 * std::cmp::max(sig.params().len(), sig.results().len());
-    // Save LR and X26, as scratch register
+    // Save LR and the scratch register
     a.emit_stpdb(
         Size::S64,
         Location::GPR(GPR::X30),
-        Location::GPR(GPR::X26),
+        Location::GPR(scratch),
         GPR::XzrSp,
         16,
     );
@@ -2551,11 +2738,11 @@ pub fn gen_std_dynamic_import_trampoline_arm64(
                 Location::GPR(GPR::XzrSp),
             );
         } else {
-            a.emit_mov_imm(Location::GPR(GPR::X26), stack_offset as u64);
+            a.emit_mov_imm(Location::GPR(scratch), stack_offset as u64);
             a.emit_sub(
                 Size::S64,
                 Location::GPR(GPR::XzrSp),
-                Location::GPR(GPR::X26),
+                Location::GPR(scratch),
                 Location::GPR(GPR::XzrSp),
             );
         }
@@ -2587,7 +2774,7 @@ pub fn gen_std_dynamic_import_trampoline_arm64(
                     };
                     a.emit_ldr(
                         sz,
-                        Location::GPR(GPR::X26),
+                        Location::GPR(scratch),
                         Location::Memory(GPR::XzrSp, (stack_offset + 16 + stack_param_count) as _),
                     );
                     stack_param_count += match sz {
@@ -2595,7 +2782,7 @@ pub fn gen_std_dynamic_import_trampoline_arm64(
                         Size::S64 => 8,
                         _ => unreachable!(),
                     };
-                    Location::GPR(GPR::X26)
+                    Location::GPR(scratch)
                 }
             };
             a.emit_str(
@@ -2617,7 +2804,7 @@ pub fn gen_std_dynamic_import_trampoline_arm64(
         _ => {
             // Load target address.
             let offset = vmoffsets.vmdynamicfunction_import_context_address();
-            a.emit_ldur(Size::S64, Location::GPR(GPR::X26), GPR::X0, offset as i32);
+            a.emit_ldur(Size::S64, Location::GPR(scratch), GPR::X0, offset as i32);
             // Load values array.
             a.emit_add(
                 Size::S64,
@@ -2629,7 +2816,7 @@ pub fn gen_std_dynamic_import_trampoline_arm64(
     };
 
     // Call target.
-    a.emit_call_register(GPR::X26);
+    a.emit_call_register(scratch);
 
     // Fetch return value.
     if !sig.results().is_empty() {
@@ -2651,11 +2838,11 @@ pub fn gen_std_dynamic_import_trampoline_arm64(
                 Location::GPR(GPR::XzrSp),
             );
         } else {
-            a.emit_mov_imm(Location::GPR(GPR::X26), stack_offset as u64);
+            a.emit_mov_imm(Location::GPR(scratch), stack_offset as u64);
             a.emit_add(
                 Size::S64,
                 Location::GPR(GPR::XzrSp),
-                Location::GPR(GPR::X26),
+                Location::GPR(scratch),
                 Location::GPR(GPR::XzrSp),
             );
         }
@@ -2663,7 +2850,7 @@ pub fn gen_std_dynamic_import_trampoline_arm64(
     a.emit_ldpia(
         Size::S64,
         Location::GPR(GPR::X30),
-        Location::GPR(GPR::X26),
+        Location::GPR(scratch),
         GPR::XzrSp,
         16,
     );
@@ -0,0 +1,132 @@
+//! Stack frame layout shared by the singlepass machines.
+//!
+//! A function compiled by singlepass has the following frame, from high
+//! to low addresses:
+//!
+//! ```text
+//! | stack arguments          |
+//! | frame record             |
+//! +--------------------------+ <- frame pointer
+//! | callee-saved registers   |  \
+//! | vmctx register           |   > save area
+//! | ABI specific registers   |  /
+//! | locals living on stack   |
+//! | value stack              |
+//! +--------------------------+ <- stack pointer
+//! ```
+//!
+//! The frame record is what the prologue pushes before setting the frame
+//! pointer: the return address and the old frame pointer on x86_64, plus
+//! the call and vmctx registers on ARM64.
+
+/// Size of a stack slot, in bytes.
+pub const SLOT_SIZE: usize = 8;
+
+/// The frame layout rules of a target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameLayout {
+    /// Size of the frame record, in bytes. Stack arguments start this far
+    /// above the frame pointer.
+    pub frame_record_size: usize,
+    /// Alignment every adjustment of the stack pointer must keep, in bytes.
+    pub stack_alignment: usize,
+}
+
+impl FrameLayout {
+    /// The x86_64 frame: `push rbp` above the return address. The stack
+    /// pointer only needs to stay slot aligned, calls realign it.
+    pub const X86_64: Self = Self {
+        frame_record_size: 16,
+        stack_alignment: SLOT_SIZE,
+    };
+
+    /// The ARM64 frame: `x29`/`x30` and the call/vmctx register pair. The
+    /// stack pointer must stay 16 bytes aligned for memory accesses.
+    pub const AARCH64: Self = Self {
+        frame_record_size: 32,
+        stack_alignment: 16,
+    };
+
+    /// Rounds a stack adjustment up to the stack alignment.
+    pub fn round_stack_adjust(&self, value: usize) -> usize {
+        let mask = self.stack_alignment - 1;
+        (value + mask) & !mask
+    }
+
+    /// Size of the save area for `local_regs` locals living in callee-saved
+    /// registers, the vmctx register and `abi_saved_regs` registers required
+    /// by the calling convention.
+    pub fn save_area_size(&self, local_regs: usize, abi_saved_regs: usize) -> usize {
+        (local_regs + 1 + abi_saved_regs) * SLOT_SIZE
+    }
+
+    /// Offset from the frame pointer of the local `idx`, when the first
+    /// `local_regs` locals live in registers and the save area is
+    /// `save_area_size` bytes.
+    pub fn local_offset(&self, idx: usize, local_regs: usize, save_area_size: usize) -> i32 {
+        debug_assert!(idx >= local_regs);
+        -(((idx + 1 - local_regs) * SLOT_SIZE + save_area_size) as i32)
+    }
+
+    /// Offset from the frame pointer of the stack argument at byte `offset`
+    /// of the incoming argument area.
+    pub fn stack_argument_offset(&self, offset: usize) -> i32 {
+        (self.frame_record_size + offset) as i32
+    }
+
+    /// Stack space taken to spill `count` registers around a call.
+    pub fn spill_size(&self, count: usize) -> usize {
+        self.round_stack_adjust(count * SLOT_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_stack_adjust() {
+        let x64 = FrameLayout::X86_64;
+        assert_eq!(x64.round_stack_adjust(0), 0);
+        assert_eq!(x64.round_stack_adjust(8), 8);
+        assert_eq!(x64.round_stack_adjust(24), 24);
+
+        let arm64 = FrameLayout::AARCH64;
+        assert_eq!(arm64.round_stack_adjust(0), 0);
+        assert_eq!(arm64.round_stack_adjust(8), 16);
+        assert_eq!(arm64.round_stack_adjust(16), 16);
+        assert_eq!(arm64.round_stack_adjust(24), 32);
+    }
+
+    #[test]
+    fn save_area_and_locals() {
+        // 4 register locals on x86_64, plus rdi/rsi on Windows.
+        let x64 = FrameLayout::X86_64;
+        let save_area = x64.save_area_size(4, 2);
+        assert_eq!(save_area, 56);
+        assert_eq!(x64.local_offset(4, 4, save_area), -64);
+        assert_eq!(x64.local_offset(5, 4, save_area), -72);
+
+        // 8 register locals on ARM64.
+        let arm64 = FrameLayout::AARCH64;
+        let save_area = arm64.save_area_size(8, 0);
+        assert_eq!(save_area, 72);
+        assert_eq!(arm64.local_offset(8, 8, save_area), -80);
+        assert_eq!(arm64.round_stack_adjust(save_area + 2 * SLOT_SIZE), 96);
+    }
+
+    #[test]
+    fn stack_arguments() {
+        assert_eq!(FrameLayout::X86_64.stack_argument_offset(0), 16);
+        assert_eq!(FrameLayout::X86_64.stack_argument_offset(8), 24);
+        assert_eq!(FrameLayout::AARCH64.stack_argument_offset(0), 32);
+        assert_eq!(FrameLayout::AARCH64.stack_argument_offset(12), 44);
+    }
+
+    #[test]
+    fn spill_size() {
+        assert_eq!(FrameLayout::X86_64.spill_size(3), 24);
+        assert_eq!(FrameLayout::AARCH64.spill_size(3), 32);
+        assert_eq!(FrameLayout::AARCH64.spill_size(4), 32);
+    }
+}
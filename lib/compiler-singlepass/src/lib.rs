@@ -8,8 +8,20 @@
 //! Compared to Cranelift and LLVM, Singlepass compiles much faster but has worse
 //! runtime performance.
 
+/// Returns early from the enclosing function with a `CodegenError`
+/// built from the format arguments.
+macro_rules! codegen_error {
+    ($($arg:tt)*) => {
+        return Err(crate::machine::CodegenError {
+            message: format!($($arg)*),
+        })
+    };
+}
+
 mod address_map;
 mod arm64_decl;
+mod code_dump;
+mod code_size;
 mod codegen;
 mod common_decl;
 mod compiler;
@@ -18,6 +30,7 @@ mod config;
 mod dwarf;
 mod emitter_arm64;
 mod emitter_x64;
+mod frame_layout;
 mod location;
 mod machine;
 mod machine_arm64;
@@ -27,5 +40,6 @@ mod unwind;
 mod unwind_winx64;
 mod x64_decl;
 
+pub use crate::code_size::{CodeSizeReport, OperatorCodeSize};
 pub use crate::compiler::SinglepassCompiler;
-pub use crate::config::Singlepass;
+pub use crate::config::{Arm64Registers, Singlepass};
@@ -1,4 +1,6 @@
 use crate::common_decl::*;
+use crate::config::Singlepass;
+use crate::frame_layout::FrameLayout;
 use crate::location::{Location, Reg};
 use crate::machine_arm64::MachineARM64;
 use crate::machine_x64::MachineX86_64;
@@ -46,6 +48,68 @@ pub struct TrapTable {
     pub offset_to_code: BTreeMap<usize, TrapCode>,
 }
 
+/// The kind of integer division emitted by the `emit_binop_*div*` and
+/// `emit_binop_*rem*` helpers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegerDivision {
+    UnsignedDiv,
+    SignedDiv,
+    UnsignedRem,
+    SignedRem,
+}
+
+impl IntegerDivision {
+    /// The trap code to attach to the division instruction itself.
+    ///
+    /// Division by zero is always checked ahead of the instruction, which
+    /// branches to the `integer_division_by_zero` label. Of the remaining
+    /// cases, only a signed division can overflow (`INT_MIN / -1`): an
+    /// unsigned division never does and the signed remainder of that case
+    /// is defined to be 0.
+    pub fn trap_code(self) -> TrapCode {
+        match self {
+            Self::SignedDiv => TrapCode::IntegerOverflow,
+            Self::UnsignedDiv | Self::UnsignedRem | Self::SignedRem => {
+                TrapCode::IntegerDivisionByZero
+            }
+        }
+    }
+}
+
+/// An integer comparison, as done by the `i32` and `i64` comparison
+/// operators, which can be fused with a conditional branch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegerComparison {
+    Eq,
+    Ne,
+    LtS,
+    LeS,
+    GtS,
+    GeS,
+    LtU,
+    LeU,
+    GtU,
+    GeU,
+}
+
+impl IntegerComparison {
+    /// The comparison holding exactly when this one does not.
+    pub fn inverse(self) -> Self {
+        match self {
+            Self::Eq => Self::Ne,
+            Self::Ne => Self::Eq,
+            Self::LtS => Self::GeS,
+            Self::LeS => Self::GtS,
+            Self::GtS => Self::LeS,
+            Self::GeS => Self::LtS,
+            Self::LtU => Self::GeU,
+            Self::LeU => Self::GtU,
+            Self::GtU => Self::LeU,
+            Self::GeU => Self::LtU,
+        }
+    }
+}
+
 // all machine seems to have a page this size, so not per arch for now
 pub const NATIVE_PAGE_SIZE: usize = 4096;
 
@@ -83,9 +147,9 @@ pub trait Machine {
     /// reserve a GPR
     fn reserve_gpr(&mut self, gpr: Self::GPR);
     /// Push used gpr to the stack. Return the bytes taken on the stack
-    fn push_used_gpr(&mut self, grps: &Vec<Self::GPR>) -> usize;
+    fn push_used_gpr(&mut self, grps: &Vec<Self::GPR>) -> Result<usize, CodegenError>;
     /// Pop used gpr to the stack
-    fn pop_used_gpr(&mut self, grps: &Vec<Self::GPR>);
+    fn pop_used_gpr(&mut self, grps: &Vec<Self::GPR>) -> Result<(), CodegenError>;
     /// Picks an unused SIMD register.
     ///
     /// This method does not mark the register as used
@@ -101,11 +165,15 @@ pub trait Machine {
     /// Releases a temporary XMM register.
     fn release_simd(&mut self, simd: Self::SIMD);
     /// Push used simd regs to the stack. Return bytes taken on the stack
-    fn push_used_simd(&mut self, simds: &Vec<Self::SIMD>) -> usize;
+    fn push_used_simd(&mut self, simds: &Vec<Self::SIMD>) -> Result<usize, CodegenError>;
     /// Pop used simd regs to the stack
-    fn pop_used_simd(&mut self, simds: &Vec<Self::SIMD>);
+    fn pop_used_simd(&mut self, simds: &Vec<Self::SIMD>) -> Result<(), CodegenError>;
+    /// The frame layout rules of the target.
+    fn frame_layout(&self) -> FrameLayout;
     /// Return a rounded stack adjustement value (must be multiple of 16bytes on ARM64 for example)
-    fn round_stack_adjust(&self, value: usize) -> usize;
+    fn round_stack_adjust(&self, value: usize) -> usize {
+        self.frame_layout().round_stack_adjust(value)
+    }
     /// Set the source location of the Wasm to the given offset.
     fn set_srcloc(&mut self, offset: u32);
     /// Marks each address in the code range emitted by `f` with the trap code `code`.
@@ -136,7 +204,11 @@ pub trait Machine {
     /// Like assembler.emit_add(Size::S64, Location::Imm32(delta_stack_offset as u32), Location::GPR(GPR::RSP))
     fn pop_stack_locals(&mut self, delta_stack_offset: u32);
     /// Zero a location taht is 32bits
-    fn zero_location(&mut self, size: Size, location: Location<Self::GPR, Self::SIMD>);
+    fn zero_location(
+        &mut self,
+        size: Size,
+        location: Location<Self::GPR, Self::SIMD>,
+    ) -> Result<(), CodegenError>;
     /// GPR Reg used for local pointer on the stack
     fn local_pointer(&self) -> Self::GPR;
     /// push a value on the stack for a native call
@@ -145,7 +217,7 @@ pub trait Machine {
         size: Size,
         loc: Location<Self::GPR, Self::SIMD>,
         dest: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Determine whether a local should be allocated on the stack.
     fn is_local_on_stack(&self, idx: usize) -> bool;
     /// Determine a local's location.
@@ -157,6 +229,13 @@ pub trait Machine {
     /// Move a local to the stack
     /// Like emit_mov(Size::S64, location, Location::Memory(GPR::RBP, -(self.stack_offset.0 as i32)));
     fn move_local(&mut self, stack_offset: i32, location: Location<Self::GPR, Self::SIMD>);
+    /// Move consecutive locals to the stack, the first one at `stack_offset`
+    /// and each next one 8 bytes below, pairing the stores when the target can
+    fn move_locals(&mut self, stack_offset: i32, locations: &[Location<Self::GPR, Self::SIMD>]) {
+        for (i, location) in locations.iter().enumerate() {
+            self.move_local(stack_offset + 8 * i as i32, *location);
+        }
+    }
     /// List of register to save, depending on the CallingConvention
     fn list_to_save(
         &self,
@@ -190,7 +269,7 @@ pub trait Machine {
         size: Size,
         source: Location<Self::GPR, Self::SIMD>,
         dest: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// move a location to another, with zero or sign extension
     fn move_location_extend(
         &mut self,
@@ -199,7 +278,7 @@ pub trait Machine {
         source: Location<Self::GPR, Self::SIMD>,
         size_op: Size,
         dest: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Load a memory value to a register, zero extending to 64bits.
     /// Panic if gpr is not a Location::GPR or if mem is not a Memory(2)
     fn load_address(
@@ -207,17 +286,30 @@ pub trait Machine {
         size: Size,
         gpr: Location<Self::GPR, Self::SIMD>,
         mem: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Init the stack loc counter
     fn init_stack_loc(
         &mut self,
         init_stack_loc_cnt: u64,
         last_stack_loc: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Restore save_area
-    fn restore_saved_area(&mut self, saved_area_offset: i32);
+    fn restore_saved_area(&mut self, saved_area_offset: i32) -> Result<(), CodegenError>;
     /// Pop a location
-    fn pop_location(&mut self, location: Location<Self::GPR, Self::SIMD>);
+    fn pop_location(
+        &mut self,
+        location: Location<Self::GPR, Self::SIMD>,
+    ) -> Result<(), CodegenError>;
+    /// Pop locations in order, pairing the loads when the target can
+    fn pop_locations(
+        &mut self,
+        locations: &[Location<Self::GPR, Self::SIMD>],
+    ) -> Result<(), CodegenError> {
+        for location in locations {
+            self.pop_location(*location)?;
+        }
+        Ok(())
+    }
     /// Create a new `MachineState` with default values.
     fn new_machine_state(&self) -> MachineState;
 
@@ -231,18 +323,18 @@ pub trait Machine {
     fn finalize_function(&mut self);
 
     /// emit native function prolog (depending on the calling Convention, like "PUSH RBP / MOV RSP, RBP")
-    fn emit_function_prolog(&mut self);
+    fn emit_function_prolog(&mut self) -> Result<(), CodegenError>;
     /// emit native function epilog (depending on the calling Convention, like "MOV RBP, RSP / POP RBP")
-    fn emit_function_epilog(&mut self);
+    fn emit_function_epilog(&mut self) -> Result<(), CodegenError>;
     /// handle return value, with optionnal cannonicalization if wanted
     fn emit_function_return_value(
         &mut self,
         ty: WpType,
         cannonicalize: bool,
         loc: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Handle copy to SIMD register from ret value (if needed by the arch/calling convention)
-    fn emit_function_return_float(&mut self);
+    fn emit_function_return_float(&mut self) -> Result<(), CodegenError>;
     /// Is NaN canonicalization supported
     fn arch_supports_canonicalize_nan(&self) -> bool;
     /// Cannonicalize a NaN (or panic if not supported)
@@ -251,7 +343,7 @@ pub trait Machine {
         sz: Size,
         input: Location<Self::GPR, Self::SIMD>,
         output: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
 
     /// emit an Illegal Opcode, associated with a trapcode
     fn emit_illegal_op(&mut self, trp: TrapCode);
@@ -274,7 +366,10 @@ pub trait Machine {
         location: Location<Self::GPR, Self::SIMD>,
     );
     /// emit a call to a location
-    fn emit_call_location(&mut self, location: Location<Self::GPR, Self::SIMD>);
+    fn emit_call_location(
+        &mut self,
+        location: Location<Self::GPR, Self::SIMD>,
+    ) -> Result<(), CodegenError>;
     /// get the gpr for the return of generic values
     fn get_gpr_for_ret(&self) -> Self::GPR;
     /// get the simd for the return of float/double values
@@ -283,6 +378,10 @@ pub trait Machine {
     /// Emit a debug breakpoint
     fn emit_debug_breakpoint(&mut self);
 
+    /// Emit a nop of the size of a debug breakpoint, which may be
+    /// replaced by one at runtime
+    fn emit_patch_point(&mut self);
+
     /// load the address of a memory location (will panic if src is not a memory)
     /// like LEA opcode on x86_64
     fn location_address(
@@ -290,7 +389,7 @@ pub trait Machine {
         size: Size,
         source: Location<Self::GPR, Self::SIMD>,
         dest: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
 
     /// And src & dst -> dst (with or without flags)
     fn location_and(
@@ -299,7 +398,7 @@ pub trait Machine {
         source: Location<Self::GPR, Self::SIMD>,
         dest: Location<Self::GPR, Self::SIMD>,
         flags: bool,
-    );
+    ) -> Result<(), CodegenError>;
     /// Xor src & dst -> dst (with or without flags)
     fn location_xor(
         &mut self,
@@ -307,7 +406,7 @@ pub trait Machine {
         source: Location<Self::GPR, Self::SIMD>,
         dest: Location<Self::GPR, Self::SIMD>,
         flags: bool,
-    );
+    ) -> Result<(), CodegenError>;
     /// Or src & dst -> dst (with or without flags)
     fn location_or(
         &mut self,
@@ -315,7 +414,7 @@ pub trait Machine {
         source: Location<Self::GPR, Self::SIMD>,
         dest: Location<Self::GPR, Self::SIMD>,
         flags: bool,
-    );
+    ) -> Result<(), CodegenError>;
 
     /// Add src+dst -> dst (with or without flags)
     fn location_add(
@@ -324,7 +423,7 @@ pub trait Machine {
         source: Location<Self::GPR, Self::SIMD>,
         dest: Location<Self::GPR, Self::SIMD>,
         flags: bool,
-    );
+    ) -> Result<(), CodegenError>;
     /// Sub dst-src -> dst (with or without flags)
     fn location_sub(
         &mut self,
@@ -332,7 +431,7 @@ pub trait Machine {
         source: Location<Self::GPR, Self::SIMD>,
         dest: Location<Self::GPR, Self::SIMD>,
         flags: bool,
-    );
+    ) -> Result<(), CodegenError>;
     /// -src -> dst
     fn location_neg(
         &mut self,
@@ -341,7 +440,7 @@ pub trait Machine {
         source: Location<Self::GPR, Self::SIMD>,
         size_op: Size,
         dest: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
 
     /// Cmp src - dst and set flags
     fn location_cmp(
@@ -349,14 +448,14 @@ pub trait Machine {
         size: Size,
         source: Location<Self::GPR, Self::SIMD>,
         dest: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Test src & dst and set flags
     fn location_test(
         &mut self,
         size: Size,
         source: Location<Self::GPR, Self::SIMD>,
         dest: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
 
     /// jmp without condidtion
     fn jmp_unconditionnal(&mut self, label: Label);
@@ -378,34 +477,59 @@ pub trait Machine {
     /// jmp on overflow
     /// like Carry set on x86_64
     fn jmp_on_overflow(&mut self, label: Label);
+    /// Can a comparison be fused with the conditional branch using it
+    fn arch_supports_compare_and_branch(&self) -> bool;
+    /// jmp if `loc_a` compares to `loc_b` as per `comparison`, without
+    /// materializing the result of the comparison (or panic if not supported)
+    fn emit_compare_and_branch(
+        &mut self,
+        size: Size,
+        comparison: IntegerComparison,
+        loc_a: Location<Self::GPR, Self::SIMD>,
+        loc_b: Location<Self::GPR, Self::SIMD>,
+        label: Label,
+    ) -> Result<(), CodegenError>;
 
     /// jmp using a jump table at lable with cond as the indice
-    fn emit_jmp_to_jumptable(&mut self, label: Label, cond: Location<Self::GPR, Self::SIMD>);
+    fn emit_jmp_to_jumptable(
+        &mut self,
+        label: Label,
+        cond: Location<Self::GPR, Self::SIMD>,
+    ) -> Result<(), CodegenError>;
 
-    /// Align for Loop (may do nothing, depending on the arch)
-    fn align_for_loop(&mut self);
+    /// Align for Loop, to `alignment` bytes if set or else to the default
+    /// of the arch (may do nothing, depending on the arch)
+    fn align_for_loop(&mut self, alignment: Option<usize>);
 
     /// ret (from a Call)
     fn emit_ret(&mut self);
 
     /// Stack push of a location
-    fn emit_push(&mut self, size: Size, loc: Location<Self::GPR, Self::SIMD>);
+    fn emit_push(
+        &mut self,
+        size: Size,
+        loc: Location<Self::GPR, Self::SIMD>,
+    ) -> Result<(), CodegenError>;
     /// Stack pop of a location
-    fn emit_pop(&mut self, size: Size, loc: Location<Self::GPR, Self::SIMD>);
+    fn emit_pop(
+        &mut self,
+        size: Size,
+        loc: Location<Self::GPR, Self::SIMD>,
+    ) -> Result<(), CodegenError>;
     /// relaxed mov: move from anywhere to anywhere
     fn emit_relaxed_mov(
         &mut self,
         sz: Size,
         src: Location<Self::GPR, Self::SIMD>,
         dst: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// relaxed cmp: compare from anywhere and anywhere
     fn emit_relaxed_cmp(
         &mut self,
         sz: Size,
         src: Location<Self::GPR, Self::SIMD>,
         dst: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Emit a memory fence. Can be nothing for x86_64 or a DMB on ARM64 for example
     fn emit_memory_fence(&mut self);
     /// relaxed move with zero extension
@@ -415,7 +539,7 @@ pub trait Machine {
         src: Location<Self::GPR, Self::SIMD>,
         sz_dst: Size,
         dst: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// relaxed move with sign extension
     fn emit_relaxed_sign_extension(
         &mut self,
@@ -423,30 +547,35 @@ pub trait Machine {
         src: Location<Self::GPR, Self::SIMD>,
         sz_dst: Size,
         dst: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Multiply location with immediate
-    fn emit_imul_imm32(&mut self, size: Size, imm32: u32, gpr: Self::GPR);
+    fn emit_imul_imm32(
+        &mut self,
+        size: Size,
+        imm32: u32,
+        gpr: Self::GPR,
+    ) -> Result<(), CodegenError>;
     /// Add with location directly from the stack
     fn emit_binop_add32(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Sub with location directly from the stack
     fn emit_binop_sub32(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Multiply with location directly from the stack
     fn emit_binop_mul32(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Unsigned Division with location directly from the stack. return the offset of the DIV opcode, to mark as trappable.
     fn emit_binop_udiv32(
         &mut self,
@@ -455,7 +584,7 @@ pub trait Machine {
         ret: Location<Self::GPR, Self::SIMD>,
         integer_division_by_zero: Label,
         integer_overflow: Label,
-    ) -> usize;
+    ) -> Result<usize, CodegenError>;
     /// Signed Division with location directly from the stack. return the offset of the DIV opcode, to mark as trappable.
     fn emit_binop_sdiv32(
         &mut self,
@@ -464,7 +593,7 @@ pub trait Machine {
         ret: Location<Self::GPR, Self::SIMD>,
         integer_division_by_zero: Label,
         integer_overflow: Label,
-    ) -> usize;
+    ) -> Result<usize, CodegenError>;
     /// Unsigned Reminder (of a division) with location directly from the stack. return the offset of the DIV opcode, to mark as trappable.
     fn emit_binop_urem32(
         &mut self,
@@ -473,7 +602,7 @@ pub trait Machine {
         ret: Location<Self::GPR, Self::SIMD>,
         integer_division_by_zero: Label,
         integer_overflow: Label,
-    ) -> usize;
+    ) -> Result<usize, CodegenError>;
     /// Signed Reminder (of a Division) with location directly from the stack. return the offset of the DIV opcode, to mark as trappable.
     fn emit_binop_srem32(
         &mut self,
@@ -482,151 +611,151 @@ pub trait Machine {
         ret: Location<Self::GPR, Self::SIMD>,
         integer_division_by_zero: Label,
         integer_overflow: Label,
-    ) -> usize;
+    ) -> Result<usize, CodegenError>;
     /// And with location directly from the stack
     fn emit_binop_and32(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Or with location directly from the stack
     fn emit_binop_or32(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Xor with location directly from the stack
     fn emit_binop_xor32(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Signed Greater of Equal Compare 2 i32, result in a GPR
     fn i32_cmp_ge_s(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Signed Greater Than Compare 2 i32, result in a GPR
     fn i32_cmp_gt_s(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Signed Less of Equal Compare 2 i32, result in a GPR
     fn i32_cmp_le_s(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Signed Less Than Compare 2 i32, result in a GPR
     fn i32_cmp_lt_s(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Unsigned Greater of Equal Compare 2 i32, result in a GPR
     fn i32_cmp_ge_u(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Unsigned Greater Than Compare 2 i32, result in a GPR
     fn i32_cmp_gt_u(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Unsigned Less of Equal Compare 2 i32, result in a GPR
     fn i32_cmp_le_u(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Unsigned Less Than Compare 2 i32, result in a GPR
     fn i32_cmp_lt_u(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Not Equal Compare 2 i32, result in a GPR
     fn i32_cmp_ne(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Equal Compare 2 i32, result in a GPR
     fn i32_cmp_eq(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Count Leading 0 bit of an i32
     fn i32_clz(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Count Trailling 0 bit of an i32
     fn i32_ctz(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Count the number of 1 bit of an i32
     fn i32_popcnt(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 Logical Shift Left
     fn i32_shl(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 Logical Shift Right
     fn i32_shr(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 Arithmetic Shift Right
     fn i32_sar(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 Roll Left
     fn i32_rol(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 Roll Right
     fn i32_ror(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 load
     fn i32_load(
         &mut self,
@@ -637,7 +766,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 load of an unsigned 8bits
     fn i32_load_8u(
         &mut self,
@@ -648,7 +777,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 load of an signed 8bits
     fn i32_load_8s(
         &mut self,
@@ -659,7 +788,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 load of an unsigned 16bits
     fn i32_load_16u(
         &mut self,
@@ -670,7 +799,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 load of an signed 16bits
     fn i32_load_16s(
         &mut self,
@@ -681,7 +810,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic load
     fn i32_atomic_load(
         &mut self,
@@ -692,7 +821,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic load of an unsigned 8bits
     fn i32_atomic_load_8u(
         &mut self,
@@ -703,7 +832,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic load of an unsigned 16bits
     fn i32_atomic_load_16u(
         &mut self,
@@ -714,7 +843,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 save
     fn i32_save(
         &mut self,
@@ -725,7 +854,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 save of the lower 8bits
     fn i32_save_8(
         &mut self,
@@ -736,7 +865,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 save of the lower 16bits
     fn i32_save_16(
         &mut self,
@@ -747,7 +876,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic save
     fn i32_atomic_save(
         &mut self,
@@ -758,7 +887,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic save of a the lower 8bits
     fn i32_atomic_save_8(
         &mut self,
@@ -769,7 +898,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic save of a the lower 16bits
     fn i32_atomic_save_16(
         &mut self,
@@ -780,7 +909,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Add with i32
     fn i32_atomic_add(
         &mut self,
@@ -792,7 +921,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Add with unsigned 8bits
     fn i32_atomic_add_8u(
         &mut self,
@@ -804,7 +933,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Add with unsigned 16bits
     fn i32_atomic_add_16u(
         &mut self,
@@ -816,7 +945,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Sub with i32
     fn i32_atomic_sub(
         &mut self,
@@ -828,7 +957,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Sub with unsigned 8bits
     fn i32_atomic_sub_8u(
         &mut self,
@@ -840,7 +969,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Sub with unsigned 16bits
     fn i32_atomic_sub_16u(
         &mut self,
@@ -852,7 +981,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic And with i32
     fn i32_atomic_and(
         &mut self,
@@ -864,7 +993,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic And with unsigned 8bits
     fn i32_atomic_and_8u(
         &mut self,
@@ -876,7 +1005,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic And with unsigned 16bits
     fn i32_atomic_and_16u(
         &mut self,
@@ -888,7 +1017,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Or with i32
     fn i32_atomic_or(
         &mut self,
@@ -900,7 +1029,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Or with unsigned 8bits
     fn i32_atomic_or_8u(
         &mut self,
@@ -912,7 +1041,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Or with unsigned 16bits
     fn i32_atomic_or_16u(
         &mut self,
@@ -924,7 +1053,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Xor with i32
     fn i32_atomic_xor(
         &mut self,
@@ -936,7 +1065,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Xor with unsigned 8bits
     fn i32_atomic_xor_8u(
         &mut self,
@@ -948,7 +1077,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Xor with unsigned 16bits
     fn i32_atomic_xor_16u(
         &mut self,
@@ -960,7 +1089,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Exchange with i32
     fn i32_atomic_xchg(
         &mut self,
@@ -972,7 +1101,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Exchange with u8
     fn i32_atomic_xchg_8u(
         &mut self,
@@ -984,7 +1113,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Exchange with u16
     fn i32_atomic_xchg_16u(
         &mut self,
@@ -996,7 +1125,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Compare and Exchange with i32
     fn i32_atomic_cmpxchg(
         &mut self,
@@ -1009,7 +1138,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Compare and Exchange with u8
     fn i32_atomic_cmpxchg_8u(
         &mut self,
@@ -1022,7 +1151,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i32 atomic Compare and Exchange with u16
     fn i32_atomic_cmpxchg_16u(
         &mut self,
@@ -1035,7 +1164,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
 
     /// emit a move function address to GPR ready for call, using appropriate relocation
     fn emit_call_with_reloc(
@@ -1049,21 +1178,21 @@ pub trait Machine {
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Sub with location directly from the stack
     fn emit_binop_sub64(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Multiply with location directly from the stack
     fn emit_binop_mul64(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Unsigned Division with location directly from the stack. return the offset of the DIV opcode, to mark as trappable.
     fn emit_binop_udiv64(
         &mut self,
@@ -1072,7 +1201,7 @@ pub trait Machine {
         ret: Location<Self::GPR, Self::SIMD>,
         integer_division_by_zero: Label,
         integer_overflow: Label,
-    ) -> usize;
+    ) -> Result<usize, CodegenError>;
     /// Signed Division with location directly from the stack. return the offset of the DIV opcode, to mark as trappable.
     fn emit_binop_sdiv64(
         &mut self,
@@ -1081,7 +1210,7 @@ pub trait Machine {
         ret: Location<Self::GPR, Self::SIMD>,
         integer_division_by_zero: Label,
         integer_overflow: Label,
-    ) -> usize;
+    ) -> Result<usize, CodegenError>;
     /// Unsigned Reminder (of a division) with location directly from the stack. return the offset of the DIV opcode, to mark as trappable.
     fn emit_binop_urem64(
         &mut self,
@@ -1090,7 +1219,7 @@ pub trait Machine {
         ret: Location<Self::GPR, Self::SIMD>,
         integer_division_by_zero: Label,
         integer_overflow: Label,
-    ) -> usize;
+    ) -> Result<usize, CodegenError>;
     /// Signed Reminder (of a Division) with location directly from the stack. return the offset of the DIV opcode, to mark as trappable.
     fn emit_binop_srem64(
         &mut self,
@@ -1099,151 +1228,151 @@ pub trait Machine {
         ret: Location<Self::GPR, Self::SIMD>,
         integer_division_by_zero: Label,
         integer_overflow: Label,
-    ) -> usize;
+    ) -> Result<usize, CodegenError>;
     /// And with location directly from the stack
     fn emit_binop_and64(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Or with location directly from the stack
     fn emit_binop_or64(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Xor with location directly from the stack
     fn emit_binop_xor64(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Signed Greater of Equal Compare 2 i64, result in a GPR
     fn i64_cmp_ge_s(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Signed Greater Than Compare 2 i64, result in a GPR
     fn i64_cmp_gt_s(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Signed Less of Equal Compare 2 i64, result in a GPR
     fn i64_cmp_le_s(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Signed Less Than Compare 2 i64, result in a GPR
     fn i64_cmp_lt_s(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Unsigned Greater of Equal Compare 2 i64, result in a GPR
     fn i64_cmp_ge_u(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Unsigned Greater Than Compare 2 i64, result in a GPR
     fn i64_cmp_gt_u(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Unsigned Less of Equal Compare 2 i64, result in a GPR
     fn i64_cmp_le_u(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Unsigned Less Than Compare 2 i64, result in a GPR
     fn i64_cmp_lt_u(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Not Equal Compare 2 i64, result in a GPR
     fn i64_cmp_ne(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Equal Compare 2 i64, result in a GPR
     fn i64_cmp_eq(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Count Leading 0 bit of an i64
     fn i64_clz(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Count Trailling 0 bit of an i64
     fn i64_ctz(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Count the number of 1 bit of an i64
     fn i64_popcnt(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 Logical Shift Left
     fn i64_shl(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 Logical Shift Right
     fn i64_shr(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 Arithmetic Shift Right
     fn i64_sar(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 Roll Left
     fn i64_rol(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 Roll Right
     fn i64_ror(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 load
     fn i64_load(
         &mut self,
@@ -1254,7 +1383,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 load of an unsigned 8bits
     fn i64_load_8u(
         &mut self,
@@ -1265,7 +1394,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 load of an signed 8bits
     fn i64_load_8s(
         &mut self,
@@ -1276,7 +1405,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 load of an unsigned 32bits
     fn i64_load_32u(
         &mut self,
@@ -1287,7 +1416,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 load of an signed 32bits
     fn i64_load_32s(
         &mut self,
@@ -1298,7 +1427,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 load of an signed 16bits
     fn i64_load_16u(
         &mut self,
@@ -1309,7 +1438,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 load of an signed 16bits
     fn i64_load_16s(
         &mut self,
@@ -1320,7 +1449,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic load
     fn i64_atomic_load(
         &mut self,
@@ -1331,7 +1460,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic load from unsigned 8bits
     fn i64_atomic_load_8u(
         &mut self,
@@ -1342,7 +1471,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic load from unsigned 16bits
     fn i64_atomic_load_16u(
         &mut self,
@@ -1353,7 +1482,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic load from unsigned 32bits
     fn i64_atomic_load_32u(
         &mut self,
@@ -1364,7 +1493,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 save
     fn i64_save(
         &mut self,
@@ -1375,7 +1504,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 save of the lower 8bits
     fn i64_save_8(
         &mut self,
@@ -1386,7 +1515,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 save of the lower 16bits
     fn i64_save_16(
         &mut self,
@@ -1397,7 +1526,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 save of the lower 32bits
     fn i64_save_32(
         &mut self,
@@ -1408,7 +1537,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic save
     fn i64_atomic_save(
         &mut self,
@@ -1419,7 +1548,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic save of a the lower 8bits
     fn i64_atomic_save_8(
         &mut self,
@@ -1430,7 +1559,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic save of a the lower 16bits
     fn i64_atomic_save_16(
         &mut self,
@@ -1441,7 +1570,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic save of a the lower 32bits
     fn i64_atomic_save_32(
         &mut self,
@@ -1452,7 +1581,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Add with i64
     fn i64_atomic_add(
         &mut self,
@@ -1464,7 +1593,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Add with unsigned 8bits
     fn i64_atomic_add_8u(
         &mut self,
@@ -1476,7 +1605,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Add with unsigned 16bits
     fn i64_atomic_add_16u(
         &mut self,
@@ -1488,7 +1617,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Add with unsigned 32bits
     fn i64_atomic_add_32u(
         &mut self,
@@ -1500,7 +1629,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Sub with i64
     fn i64_atomic_sub(
         &mut self,
@@ -1512,7 +1641,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Sub with unsigned 8bits
     fn i64_atomic_sub_8u(
         &mut self,
@@ -1524,7 +1653,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Sub with unsigned 16bits
     fn i64_atomic_sub_16u(
         &mut self,
@@ -1536,7 +1665,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Sub with unsigned 32bits
     fn i64_atomic_sub_32u(
         &mut self,
@@ -1548,7 +1677,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic And with i64
     fn i64_atomic_and(
         &mut self,
@@ -1560,7 +1689,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic And with unsigned 8bits
     fn i64_atomic_and_8u(
         &mut self,
@@ -1572,7 +1701,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic And with unsigned 16bits
     fn i64_atomic_and_16u(
         &mut self,
@@ -1584,7 +1713,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic And with unsigned 32bits
     fn i64_atomic_and_32u(
         &mut self,
@@ -1596,7 +1725,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Or with i64
     fn i64_atomic_or(
         &mut self,
@@ -1608,7 +1737,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Or with unsigned 8bits
     fn i64_atomic_or_8u(
         &mut self,
@@ -1620,7 +1749,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Or with unsigned 16bits
     fn i64_atomic_or_16u(
         &mut self,
@@ -1632,7 +1761,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Or with unsigned 32bits
     fn i64_atomic_or_32u(
         &mut self,
@@ -1644,7 +1773,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Xor with i64
     fn i64_atomic_xor(
         &mut self,
@@ -1656,7 +1785,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Xor with unsigned 8bits
     fn i64_atomic_xor_8u(
         &mut self,
@@ -1668,7 +1797,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Xor with unsigned 16bits
     fn i64_atomic_xor_16u(
         &mut self,
@@ -1680,7 +1809,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Xor with unsigned 32bits
     fn i64_atomic_xor_32u(
         &mut self,
@@ -1692,7 +1821,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Exchange with i64
     fn i64_atomic_xchg(
         &mut self,
@@ -1704,7 +1833,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Exchange with u8
     fn i64_atomic_xchg_8u(
         &mut self,
@@ -1716,7 +1845,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Exchange with u16
     fn i64_atomic_xchg_16u(
         &mut self,
@@ -1728,7 +1857,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Exchange with u32
     fn i64_atomic_xchg_32u(
         &mut self,
@@ -1740,7 +1869,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Compare and Exchange with i32
     fn i64_atomic_cmpxchg(
         &mut self,
@@ -1753,7 +1882,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Compare and Exchange with u8
     fn i64_atomic_cmpxchg_8u(
         &mut self,
@@ -1766,7 +1895,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Compare and Exchange with u16
     fn i64_atomic_cmpxchg_16u(
         &mut self,
@@ -1779,7 +1908,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// i64 atomic Compare and Exchange with u32
     fn i64_atomic_cmpxchg_32u(
         &mut self,
@@ -1792,7 +1921,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
 
     /// load an F32
     fn f32_load(
@@ -1804,7 +1933,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// f32 save
     fn f32_save(
         &mut self,
@@ -1816,7 +1945,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// load an F64
     fn f64_load(
         &mut self,
@@ -1827,7 +1956,7 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// f64 save
     fn f64_save(
         &mut self,
@@ -1839,35 +1968,35 @@ pub trait Machine {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    );
+    ) -> Result<(), CodegenError>;
     /// Convert a F64 from I64, signed or unsigned
     fn convert_f64_i64(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         signed: bool,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Convert a F64 from I32, signed or unsigned
     fn convert_f64_i32(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         signed: bool,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Convert a F32 from I64, signed or unsigned
     fn convert_f32_i64(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         signed: bool,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Convert a F32 from I32, signed or unsigned
     fn convert_f32_i32(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         signed: bool,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Convert a F64 to I64, signed or unsigned, without or without saturation
     fn convert_i64_f64(
         &mut self,
@@ -1875,7 +2004,7 @@ pub trait Machine {
         ret: Location<Self::GPR, Self::SIMD>,
         signed: bool,
         sat: bool,
-    );
+    ) -> Result<(), CodegenError>;
     /// Convert a F64 to I32, signed or unsigned, without or without saturation
     fn convert_i32_f64(
         &mut self,
@@ -1883,7 +2012,7 @@ pub trait Machine {
         ret: Location<Self::GPR, Self::SIMD>,
         signed: bool,
         sat: bool,
-    );
+    ) -> Result<(), CodegenError>;
     /// Convert a F32 to I64, signed or unsigned, without or without saturation
     fn convert_i64_f32(
         &mut self,
@@ -1891,7 +2020,7 @@ pub trait Machine {
         ret: Location<Self::GPR, Self::SIMD>,
         signed: bool,
         sat: bool,
-    );
+    ) -> Result<(), CodegenError>;
     /// Convert a F32 to I32, signed or unsigned, without or without saturation
     fn convert_i32_f32(
         &mut self,
@@ -1899,159 +2028,159 @@ pub trait Machine {
         ret: Location<Self::GPR, Self::SIMD>,
         signed: bool,
         sat: bool,
-    );
+    ) -> Result<(), CodegenError>;
     /// Convert a F32 to F64
     fn convert_f64_f32(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Convert a F64 to F32
     fn convert_f32_f64(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Negate an F64
     fn f64_neg(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Get the Absolute Value of an F64
     fn f64_abs(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Copy sign from tmp1 Self::GPR to tmp2 Self::GPR
-    fn emit_i64_copysign(&mut self, tmp1: Self::GPR, tmp2: Self::GPR);
+    fn emit_i64_copysign(&mut self, tmp1: Self::GPR, tmp2: Self::GPR) -> Result<(), CodegenError>;
     /// Get the Square Root of an F64
     fn f64_sqrt(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Trunc of an F64
     fn f64_trunc(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Ceil of an F64
     fn f64_ceil(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Floor of an F64
     fn f64_floor(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Round at nearest int of an F64
     fn f64_nearest(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Greater of Equal Compare 2 F64, result in a GPR
     fn f64_cmp_ge(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Greater Than Compare 2 F64, result in a GPR
     fn f64_cmp_gt(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Less of Equal Compare 2 F64, result in a GPR
     fn f64_cmp_le(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Less Than Compare 2 F64, result in a GPR
     fn f64_cmp_lt(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Not Equal Compare 2 F64, result in a GPR
     fn f64_cmp_ne(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Equal Compare 2 F64, result in a GPR
     fn f64_cmp_eq(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// get Min for 2 F64 values
     fn f64_min(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// get Max for 2 F64 values
     fn f64_max(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Add 2 F64 values
     fn f64_add(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Sub 2 F64 values
     fn f64_sub(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Multiply 2 F64 values
     fn f64_mul(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Divide 2 F64 values
     fn f64_div(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Negate an F32
     fn f32_neg(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Get the Absolute Value of an F32
     fn f32_abs(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Copy sign from tmp1 Self::GPR to tmp2 Self::GPR
     fn emit_i32_copysign(&mut self, tmp1: Self::GPR, tmp2: Self::GPR);
     /// Get the Square Root of an F32
@@ -2059,115 +2188,115 @@ pub trait Machine {
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Trunc of an F32
     fn f32_trunc(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Ceil of an F32
     fn f32_ceil(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Floor of an F32
     fn f32_floor(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Round at nearest int of an F32
     fn f32_nearest(
         &mut self,
         loc: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Greater of Equal Compare 2 F32, result in a GPR
     fn f32_cmp_ge(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Greater Than Compare 2 F32, result in a GPR
     fn f32_cmp_gt(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Less of Equal Compare 2 F32, result in a GPR
     fn f32_cmp_le(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Less Than Compare 2 F32, result in a GPR
     fn f32_cmp_lt(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Not Equal Compare 2 F32, result in a GPR
     fn f32_cmp_ne(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Equal Compare 2 F32, result in a GPR
     fn f32_cmp_eq(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// get Min for 2 F32 values
     fn f32_min(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// get Max for 2 F32 values
     fn f32_max(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Add 2 F32 values
     fn f32_add(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Sub 2 F32 values
     fn f32_sub(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Multiply 2 F32 values
     fn f32_mul(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
     /// Divide 2 F32 values
     fn f32_div(
         &mut self,
         loc_a: Location<Self::GPR, Self::SIMD>,
         loc_b: Location<Self::GPR, Self::SIMD>,
         ret: Location<Self::GPR, Self::SIMD>,
-    );
+    ) -> Result<(), CodegenError>;
 
     /// Standard function Trampoline generation
     fn gen_std_trampoline(
@@ -2201,6 +2330,7 @@ pub fn gen_std_trampoline(
     sig: &FunctionType,
     target: &Target,
     calling_convention: CallingConvention,
+    config: &Singlepass,
 ) -> FunctionBody {
     match target.triple().architecture {
         Architecture::X86_64 => {
@@ -2214,7 +2344,7 @@ pub fn gen_std_trampoline(
             machine.gen_std_trampoline(sig, calling_convention)
         }
         Architecture::Aarch64(_) => {
-            let machine = MachineARM64::new();
+            let machine = MachineARM64::new_with_registers(&config.arm64_registers);
             machine.gen_std_trampoline(sig, calling_convention)
         }
         _ => unimplemented!(),
@@ -2227,6 +2357,7 @@ pub fn gen_std_dynamic_import_trampoline(
     sig: &FunctionType,
     target: &Target,
     calling_convention: CallingConvention,
+    config: &Singlepass,
 ) -> FunctionBody {
     match target.triple().architecture {
         Architecture::X86_64 => {
@@ -2240,7 +2371,7 @@ pub fn gen_std_dynamic_import_trampoline(
             machine.gen_std_dynamic_import_trampoline(vmoffsets, sig, calling_convention)
         }
         Architecture::Aarch64(_) => {
-            let machine = MachineARM64::new();
+            let machine = MachineARM64::new_with_registers(&config.arm64_registers);
             machine.gen_std_dynamic_import_trampoline(vmoffsets, sig, calling_convention)
         }
         _ => unimplemented!(),
@@ -2253,6 +2384,7 @@ pub fn gen_import_call_trampoline(
     sig: &FunctionType,
     target: &Target,
     calling_convention: CallingConvention,
+    config: &Singlepass,
 ) -> CustomSection {
     match target.triple().architecture {
         Architecture::X86_64 => {
@@ -2266,7 +2398,7 @@ pub fn gen_import_call_trampoline(
             machine.gen_import_call_trampoline(vmoffsets, index, sig, calling_convention)
         }
         Architecture::Aarch64(_) => {
-            let machine = MachineARM64::new();
+            let machine = MachineARM64::new_with_registers(&config.arm64_registers);
             machine.gen_import_call_trampoline(vmoffsets, index, sig, calling_convention)
         }
         _ => unimplemented!(),
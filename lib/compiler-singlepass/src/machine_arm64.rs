@@ -1,7 +1,9 @@
 use crate::arm64_decl::new_machine_state;
 use crate::arm64_decl::{GPR, NEON};
 use crate::common_decl::*;
+use crate::config::Arm64Registers;
 use crate::emitter_arm64::*;
+use crate::frame_layout::FrameLayout;
 use crate::location::Location as AbstractLocation;
 use crate::location::Reg;
 use crate::machine::*;
@@ -19,6 +21,11 @@ use wasmer_types::{FunctionIndex, FunctionType, TrapCode, VMOffsets};
 type Assembler = VecAssembler<Aarch64Relocation>;
 type Location = AbstractLocation<GPR, NEON>;
 
+/// The register caching the base of the linear memory between accesses.
+/// `x16` is otherwise only used by trampolines and may be clobbered by
+/// the veneers of calls, which invalidate the cache.
+const MEMORY_BASE_REG: GPR = GPR::X16;
+
 #[cfg(feature = "unwind")]
 fn dwarf_index(reg: u16) -> gimli::Register {
     static DWARF_GPR: [gimli::Register; 32] = [
@@ -111,6 +118,17 @@ pub struct MachineARM64 {
     pushed: bool,
     /// Vector of unwind operations with offset
     unwind_ops: Vec<(usize, UnwindOps)>,
+    /// The register holding the vmctx.
+    vmctx_reg: GPR,
+    /// The register used for calls.
+    call_reg: GPR,
+    /// The callee-saved registers used for the first locals.
+    local_regs: Vec<GPR>,
+    /// The vmctx offset of the memory whose base `MEMORY_BASE_REG` holds,
+    /// if it is still valid: the cache is invalidated by every label, as
+    /// other paths may join there, and by every call, which may grow the
+    /// memory.
+    cached_memory_base: Option<i32>,
 }
 
 #[allow(dead_code)]
@@ -133,9 +151,25 @@ enum ImmType {
     OffsetDWord,
 }
 
+/// The register an out of range memory offset was materialized in.
+enum OffsetRegister {
+    /// A register the caller already owns, like the destination of a load.
+    Scratch,
+    /// A temporary register, to release.
+    Temp(GPR),
+    /// A live register saved on the stack, to restore.
+    Spilled(GPR),
+}
+
 #[allow(dead_code)]
 impl MachineARM64 {
     pub fn new() -> Self {
+        Self::new_with_registers(&Arm64Registers::default())
+    }
+    /// Creates a machine using the given register assignment, which must
+    /// have been validated.
+    pub fn new_with_registers(registers: &Arm64Registers) -> Self {
+        let gpr = |reg: u8| GPR::from_index(reg as usize).unwrap();
         MachineARM64 {
             assembler: Assembler::new(0),
             used_gprs: 0,
@@ -145,6 +179,10 @@ impl MachineARM64 {
             src_loc: 0,
             pushed: false,
             unwind_ops: vec![],
+            vmctx_reg: gpr(registers.vmctx),
+            call_reg: gpr(registers.call),
+            local_regs: registers.locals.iter().map(|&reg| gpr(reg)).collect(),
+            cached_memory_base: None,
         }
     }
     fn compatible_imm(&self, imm: i64, ty: ImmType) -> bool {
@@ -175,8 +213,8 @@ impl MachineARM64 {
         allow_imm: ImmType,
         read_val: bool,
         wanted: Option<GPR>,
-    ) -> Location {
-        match src {
+    ) -> Result<Location, CodegenError> {
+        Ok(match src {
             Location::GPR(_) | Location::SIMD(_) => src,
             Location::Imm8(val) => {
                 if allow_imm == ImmType::NoneXzr && val == 0 {
@@ -188,7 +226,9 @@ impl MachineARM64 {
                         let tmp = if wanted.is_some() {
                             wanted.unwrap()
                         } else {
-                            let tmp = self.acquire_temp_gpr().unwrap();
+                            let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                                message: "singlepass cannot acquire temp gpr".to_owned(),
+                            })?;
                             temps.push(tmp.clone());
                             tmp
                         };
@@ -207,7 +247,9 @@ impl MachineARM64 {
                         let tmp = if wanted.is_some() {
                             wanted.unwrap()
                         } else {
-                            let tmp = self.acquire_temp_gpr().unwrap();
+                            let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                                message: "singlepass cannot acquire temp gpr".to_owned(),
+                            })?;
                             temps.push(tmp.clone());
                             tmp
                         };
@@ -227,7 +269,9 @@ impl MachineARM64 {
                         let tmp = if wanted.is_some() {
                             wanted.unwrap()
                         } else {
-                            let tmp = self.acquire_temp_gpr().unwrap();
+                            let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                                message: "singlepass cannot acquire temp gpr".to_owned(),
+                            })?;
                             temps.push(tmp.clone());
                             tmp
                         };
@@ -240,7 +284,9 @@ impl MachineARM64 {
                 let tmp = if wanted.is_some() {
                     wanted.unwrap()
                 } else {
-                    let tmp = self.acquire_temp_gpr().unwrap();
+                    let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
                     temps.push(tmp.clone());
                     tmp
                 };
@@ -312,8 +358,8 @@ impl MachineARM64 {
                 }
                 Location::GPR(tmp)
             }
-            _ => panic!("singlepass can't emit location_to_reg {:?} {:?}", sz, src),
-        }
+            _ => codegen_error!("singlepass can't emit location_to_reg {:?} {:?}", sz, src),
+        })
     }
     fn location_to_neon(
         &mut self,
@@ -322,11 +368,13 @@ impl MachineARM64 {
         temps: &mut Vec<NEON>,
         allow_imm: ImmType,
         read_val: bool,
-    ) -> Location {
-        match src {
+    ) -> Result<Location, CodegenError> {
+        Ok(match src {
             Location::SIMD(_) => src,
             Location::GPR(_) => {
-                let tmp = self.acquire_temp_simd().unwrap();
+                let tmp = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp simd".to_owned(),
+                })?;
                 temps.push(tmp.clone());
                 if read_val {
                     self.assembler.emit_mov(sz, src, Location::SIMD(tmp));
@@ -337,8 +385,12 @@ impl MachineARM64 {
                 if self.compatible_imm(val as i64, allow_imm) {
                     src
                 } else {
-                    let gpr = self.acquire_temp_gpr().unwrap();
-                    let tmp = self.acquire_temp_simd().unwrap();
+                    let gpr = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
+                    let tmp = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp simd".to_owned(),
+                    })?;
                     temps.push(tmp.clone());
                     self.assembler.emit_mov_imm(Location::GPR(gpr), val as u64);
                     self.assembler
@@ -351,8 +403,12 @@ impl MachineARM64 {
                 if self.compatible_imm(val as i64, allow_imm) {
                     src
                 } else {
-                    let gpr = self.acquire_temp_gpr().unwrap();
-                    let tmp = self.acquire_temp_simd().unwrap();
+                    let gpr = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
+                    let tmp = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp simd".to_owned(),
+                    })?;
                     temps.push(tmp.clone());
                     self.assembler
                         .emit_mov_imm(Location::GPR(gpr), (val as i64) as u64);
@@ -366,8 +422,12 @@ impl MachineARM64 {
                 if self.compatible_imm(val as i64, allow_imm) {
                     src
                 } else {
-                    let gpr = self.acquire_temp_gpr().unwrap();
-                    let tmp = self.acquire_temp_simd().unwrap();
+                    let gpr = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
+                    let tmp = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp simd".to_owned(),
+                    })?;
                     temps.push(tmp.clone());
                     self.assembler.emit_mov_imm(Location::GPR(gpr), val as u64);
                     self.assembler
@@ -377,7 +437,9 @@ impl MachineARM64 {
                 }
             }
             Location::Memory(reg, val) => {
-                let tmp = self.acquire_temp_simd().unwrap();
+                let tmp = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp simd".to_owned(),
+                })?;
                 temps.push(tmp.clone());
                 if read_val {
                     let offsize = if sz == Size::S32 {
@@ -394,21 +456,16 @@ impl MachineARM64 {
                     } else if self.compatible_imm(val as i64, ImmType::UnscaledOffset) {
                         self.assembler.emit_ldur(sz, Location::SIMD(tmp), reg, val);
                     } else {
-                        let gpr = self.acquire_temp_gpr().unwrap();
-                        self.assembler
-                            .emit_mov_imm(Location::GPR(gpr), (val as i64) as u64);
-                        self.assembler.emit_ldr(
-                            sz,
-                            Location::SIMD(tmp),
-                            Location::Memory2(reg, gpr, Multiplier::One, 0),
-                        );
-                        self.release_gpr(gpr);
+                        let (src, offset_reg) =
+                            self.register_offset_address(reg, val, sz, Location::SIMD(tmp), None)?;
+                        self.assembler.emit_ldr(sz, Location::SIMD(tmp), src);
+                        self.release_offset_register(offset_reg)?;
                     }
                 }
                 Location::SIMD(tmp)
             }
-            _ => panic!("singlepass can't emit location_to_neon {:?} {:?}", sz, src),
-        }
+            _ => codegen_error!("singlepass can't emit location_to_neon {:?} {:?}", sz, src),
+        })
     }
 
     fn emit_relaxed_binop(
@@ -418,22 +475,23 @@ impl MachineARM64 {
         src: Location,
         dst: Location,
         putback: bool,
-    ) {
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
         let src_imm = if putback {
             ImmType::None
         } else {
             ImmType::Bits12
         };
-        let src = self.location_to_reg(sz, src, &mut temps, src_imm, true, None);
-        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, !putback, None);
+        let src = self.location_to_reg(sz, src, &mut temps, src_imm, true, None)?;
+        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, !putback, None)?;
         op(&mut self.assembler, sz, src, dest);
         if dst != dest && putback {
-            self.move_location(sz, dest, dst);
+            self.move_location(sz, dest, dst)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
     fn emit_relaxed_binop_neon(
         &mut self,
@@ -442,17 +500,18 @@ impl MachineARM64 {
         src: Location,
         dst: Location,
         putback: bool,
-    ) {
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let src = self.location_to_neon(sz, src, &mut temps, ImmType::None, true);
-        let dest = self.location_to_neon(sz, dst, &mut temps, ImmType::None, !putback);
+        let src = self.location_to_neon(sz, src, &mut temps, ImmType::None, true)?;
+        let dest = self.location_to_neon(sz, dst, &mut temps, ImmType::None, !putback)?;
         op(&mut self.assembler, sz, src, dest);
         if dst != dest && putback {
-            self.move_location(sz, dest, dst);
+            self.move_location(sz, dest, dst)?;
         }
         for r in temps {
             self.release_simd(r);
         }
+        Ok(())
     }
     fn emit_relaxed_binop3(
         &mut self,
@@ -462,18 +521,19 @@ impl MachineARM64 {
         src2: Location,
         dst: Location,
         allow_imm: ImmType,
-    ) {
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let src1 = self.location_to_reg(sz, src1, &mut temps, ImmType::None, true, None);
-        let src2 = self.location_to_reg(sz, src2, &mut temps, allow_imm, true, None);
-        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None);
+        let src1 = self.location_to_reg(sz, src1, &mut temps, ImmType::None, true, None)?;
+        let src2 = self.location_to_reg(sz, src2, &mut temps, allow_imm, true, None)?;
+        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None)?;
         op(&mut self.assembler, sz, src1, src2, dest);
         if dst != dest {
-            self.move_location(sz, dest, dst);
+            self.move_location(sz, dest, dst)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
     fn emit_relaxed_binop3_neon(
         &mut self,
@@ -483,22 +543,97 @@ impl MachineARM64 {
         src2: Location,
         dst: Location,
         allow_imm: ImmType,
-    ) {
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let src1 = self.location_to_neon(sz, src1, &mut temps, ImmType::None, true);
-        let src2 = self.location_to_neon(sz, src2, &mut temps, allow_imm, true);
-        let dest = self.location_to_neon(sz, dst, &mut temps, ImmType::None, false);
+        let src1 = self.location_to_neon(sz, src1, &mut temps, ImmType::None, true)?;
+        let src2 = self.location_to_neon(sz, src2, &mut temps, allow_imm, true)?;
+        let dest = self.location_to_neon(sz, dst, &mut temps, ImmType::None, false)?;
         op(&mut self.assembler, sz, src1, src2, dest);
         if dst != dest {
-            self.move_location(sz, dest, dst);
+            self.move_location(sz, dest, dst)?;
         }
         for r in temps {
             self.release_simd(r);
         }
+        Ok(())
+    }
+    /// The register a load to `dest` can use to hold its offset from
+    /// `addr`, since it is overwritten by the load anyway.
+    fn load_scratch(dest: Location, addr: GPR) -> Option<GPR> {
+        match dest {
+            Location::GPR(reg) if reg != addr && reg != GPR::XzrSp => Some(reg),
+            _ => None,
+        }
+    }
+
+    /// Returns a register offset address for an access of `size` bytes at
+    /// `offset` from `addr`, when `offset` can't be encoded as an immediate.
+    ///
+    /// The offset is scaled by the access size when it is a multiple of
+    /// it, so that it is shorter to materialize. It is put in `scratch` if
+    /// given, else in a temporary register, else in a register other than
+    /// `addr` and `value` saved on the stack for the time of the access, so
+    /// that running out of temporary registers doesn't fail. The register
+    /// must be given back with `release_offset_register` after the access.
+    fn register_offset_address(
+        &mut self,
+        addr: GPR,
+        mut offset: i32,
+        size: Size,
+        value: Location,
+        scratch: Option<GPR>,
+    ) -> Result<(Location, OffsetRegister), CodegenError> {
+        let (reg, offset_reg) = if let Some(reg) = scratch {
+            (reg, OffsetRegister::Scratch)
+        } else if let Some(reg) = self.acquire_temp_gpr() {
+            (reg, OffsetRegister::Temp(reg))
+        } else {
+            use GPR::*;
+            let reg = [X8, X7, X6, X5, X4, X3, X2, X1]
+                .iter()
+                .cloned()
+                .find(|&reg| reg != addr && Location::GPR(reg) != value)
+                .unwrap();
+            if addr == GPR::XzrSp && !self.pushed {
+                // The push below moves the stack pointer.
+                offset += 16;
+            }
+            self.emit_push(Size::S64, Location::GPR(reg))?;
+            (reg, OffsetRegister::Spilled(reg))
+        };
+        let multiplier = match size {
+            Size::S8 => Multiplier::One,
+            Size::S16 => Multiplier::Two,
+            Size::S32 => Multiplier::Four,
+            Size::S64 => Multiplier::Height,
+        };
+        let (offset, multiplier) = if offset % (multiplier as i32) == 0 {
+            (offset / (multiplier as i32), multiplier)
+        } else {
+            (offset, Multiplier::One)
+        };
+        self.assembler
+            .emit_mov_imm(Location::GPR(reg), (offset as i64) as u64);
+        Ok((Location::Memory2(addr, reg, multiplier, 0), offset_reg))
+    }
+
+    fn release_offset_register(&mut self, offset_reg: OffsetRegister) -> Result<(), CodegenError> {
+        match offset_reg {
+            OffsetRegister::Scratch => {}
+            OffsetRegister::Temp(reg) => self.release_gpr(reg),
+            OffsetRegister::Spilled(reg) => self.emit_pop(Size::S64, Location::GPR(reg))?,
+        }
+        Ok(())
     }
-    fn emit_relaxed_ldr64(&mut self, sz: Size, dst: Location, src: Location) {
+
+    fn emit_relaxed_ldr64(
+        &mut self,
+        sz: Size,
+        dst: Location,
+        src: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None);
+        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None)?;
         match src {
             Location::Memory(addr, offset) => {
                 if self.compatible_imm(offset as i64, ImmType::OffsetDWord) {
@@ -506,29 +641,35 @@ impl MachineARM64 {
                 } else if self.compatible_imm(offset as i64, ImmType::UnscaledOffset) {
                     self.assembler.emit_ldur(Size::S64, dest, addr, offset);
                 } else {
-                    let tmp = self.acquire_temp_gpr().unwrap();
-                    self.assembler
-                        .emit_mov_imm(Location::GPR(tmp), (offset as i64) as u64);
-                    self.assembler.emit_ldr(
+                    let (src, offset_reg) = self.register_offset_address(
+                        addr,
+                        offset,
                         Size::S64,
                         dest,
-                        Location::Memory2(addr, tmp, Multiplier::One, 0),
-                    );
-                    temps.push(tmp);
+                        Self::load_scratch(dest, addr),
+                    )?;
+                    self.assembler.emit_ldr(Size::S64, dest, src);
+                    self.release_offset_register(offset_reg)?;
                 }
             }
             _ => unreachable!(),
         }
         if dst != dest {
-            self.move_location(sz, dest, dst);
+            self.move_location(sz, dest, dst)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn emit_relaxed_ldr32(&mut self, sz: Size, dst: Location, src: Location) {
+    fn emit_relaxed_ldr32(
+        &mut self,
+        sz: Size,
+        dst: Location,
+        src: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None);
+        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None)?;
         match src {
             Location::Memory(addr, offset) => {
                 if self.compatible_imm(offset as i64, ImmType::OffsetWord) {
@@ -536,169 +677,200 @@ impl MachineARM64 {
                 } else if self.compatible_imm(offset as i64, ImmType::UnscaledOffset) {
                     self.assembler.emit_ldur(Size::S32, dest, addr, offset);
                 } else {
-                    let tmp = self.acquire_temp_gpr().unwrap();
-                    self.assembler
-                        .emit_mov_imm(Location::GPR(tmp), (offset as i64) as u64);
-                    self.assembler.emit_ldr(
+                    let (src, offset_reg) = self.register_offset_address(
+                        addr,
+                        offset,
                         Size::S32,
                         dest,
-                        Location::Memory2(addr, tmp, Multiplier::One, 0),
-                    );
-                    temps.push(tmp);
+                        Self::load_scratch(dest, addr),
+                    )?;
+                    self.assembler.emit_ldr(Size::S32, dest, src);
+                    self.release_offset_register(offset_reg)?;
                 }
             }
             _ => unreachable!(),
         }
         if dst != dest {
-            self.move_location(sz, dest, dst);
+            self.move_location(sz, dest, dst)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn emit_relaxed_ldr32s(&mut self, sz: Size, dst: Location, src: Location) {
+    fn emit_relaxed_ldr32s(
+        &mut self,
+        sz: Size,
+        dst: Location,
+        src: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None);
+        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None)?;
         match src {
             Location::Memory(addr, offset) => {
                 if self.compatible_imm(offset as i64, ImmType::OffsetWord) {
                     self.assembler.emit_ldrsw(Size::S64, dest, src);
                 } else {
-                    let tmp = self.acquire_temp_gpr().unwrap();
-                    self.assembler
-                        .emit_mov_imm(Location::GPR(tmp), (offset as i64) as u64);
-                    self.assembler.emit_ldrsw(
-                        Size::S64,
+                    let (src, offset_reg) = self.register_offset_address(
+                        addr,
+                        offset,
+                        Size::S32,
                         dest,
-                        Location::Memory2(addr, tmp, Multiplier::One, 0),
-                    );
-                    temps.push(tmp);
+                        Self::load_scratch(dest, addr),
+                    )?;
+                    self.assembler.emit_ldrsw(Size::S64, dest, src);
+                    self.release_offset_register(offset_reg)?;
                 }
             }
             _ => unreachable!(),
         }
         if dst != dest {
-            self.move_location(sz, dest, dst);
+            self.move_location(sz, dest, dst)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn emit_relaxed_ldr16(&mut self, sz: Size, dst: Location, src: Location) {
+    fn emit_relaxed_ldr16(
+        &mut self,
+        sz: Size,
+        dst: Location,
+        src: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None);
+        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None)?;
         match src {
             Location::Memory(addr, offset) => {
                 if self.compatible_imm(offset as i64, ImmType::OffsetHWord) {
                     self.assembler.emit_ldrh(Size::S32, dest, src);
                 } else {
-                    let tmp = self.acquire_temp_gpr().unwrap();
-                    self.assembler
-                        .emit_mov_imm(Location::GPR(tmp), (offset as i64) as u64);
-                    self.assembler.emit_ldrh(
-                        Size::S32,
+                    let (src, offset_reg) = self.register_offset_address(
+                        addr,
+                        offset,
+                        Size::S16,
                         dest,
-                        Location::Memory2(addr, tmp, Multiplier::One, 0),
-                    );
-                    temps.push(tmp);
+                        Self::load_scratch(dest, addr),
+                    )?;
+                    self.assembler.emit_ldrh(Size::S32, dest, src);
+                    self.release_offset_register(offset_reg)?;
                 }
             }
             _ => unreachable!(),
         }
         if dst != dest {
-            self.move_location(sz, dest, dst);
+            self.move_location(sz, dest, dst)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn emit_relaxed_ldr16s(&mut self, sz: Size, dst: Location, src: Location) {
+    fn emit_relaxed_ldr16s(
+        &mut self,
+        sz: Size,
+        dst: Location,
+        src: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None);
+        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None)?;
         match src {
             Location::Memory(addr, offset) => {
                 if self.compatible_imm(offset as i64, ImmType::OffsetHWord) {
                     self.assembler.emit_ldrsh(sz, dest, src);
                 } else {
-                    let tmp = self.acquire_temp_gpr().unwrap();
-                    self.assembler
-                        .emit_mov_imm(Location::GPR(tmp), (offset as i64) as u64);
-                    self.assembler.emit_ldrsh(
-                        sz,
+                    let (src, offset_reg) = self.register_offset_address(
+                        addr,
+                        offset,
+                        Size::S16,
                         dest,
-                        Location::Memory2(addr, tmp, Multiplier::One, 0),
-                    );
-                    temps.push(tmp);
+                        Self::load_scratch(dest, addr),
+                    )?;
+                    self.assembler.emit_ldrsh(sz, dest, src);
+                    self.release_offset_register(offset_reg)?;
                 }
             }
             _ => unreachable!(),
         }
         if dst != dest {
-            self.move_location(sz, dest, dst);
+            self.move_location(sz, dest, dst)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn emit_relaxed_ldr8(&mut self, sz: Size, dst: Location, src: Location) {
+    fn emit_relaxed_ldr8(
+        &mut self,
+        sz: Size,
+        dst: Location,
+        src: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None);
+        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None)?;
         match src {
             Location::Memory(addr, offset) => {
                 if self.compatible_imm(offset as i64, ImmType::OffsetByte) {
                     self.assembler.emit_ldrb(Size::S32, dest, src);
                 } else {
-                    let tmp = self.acquire_temp_gpr().unwrap();
-                    self.assembler
-                        .emit_mov_imm(Location::GPR(tmp), (offset as i64) as u64);
-                    self.assembler.emit_ldrb(
-                        Size::S32,
+                    let (src, offset_reg) = self.register_offset_address(
+                        addr,
+                        offset,
+                        Size::S8,
                         dest,
-                        Location::Memory2(addr, tmp, Multiplier::One, 0),
-                    );
-                    temps.push(tmp);
+                        Self::load_scratch(dest, addr),
+                    )?;
+                    self.assembler.emit_ldrb(Size::S32, dest, src);
+                    self.release_offset_register(offset_reg)?;
                 }
             }
             _ => unreachable!(),
         }
         if dst != dest {
-            self.move_location(sz, dest, dst);
+            self.move_location(sz, dest, dst)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn emit_relaxed_ldr8s(&mut self, sz: Size, dst: Location, src: Location) {
+    fn emit_relaxed_ldr8s(
+        &mut self,
+        sz: Size,
+        dst: Location,
+        src: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None);
+        let dest = self.location_to_reg(sz, dst, &mut temps, ImmType::None, false, None)?;
         match src {
             Location::Memory(addr, offset) => {
                 if self.compatible_imm(offset as i64, ImmType::OffsetByte) {
                     self.assembler.emit_ldrsb(sz, dest, src);
                 } else {
-                    let tmp = self.acquire_temp_gpr().unwrap();
-                    self.assembler
-                        .emit_mov_imm(Location::GPR(tmp), (offset as i64) as u64);
-                    self.assembler.emit_ldrsb(
-                        sz,
+                    let (src, offset_reg) = self.register_offset_address(
+                        addr,
+                        offset,
+                        Size::S8,
                         dest,
-                        Location::Memory2(addr, tmp, Multiplier::One, 0),
-                    );
-                    temps.push(tmp);
+                        Self::load_scratch(dest, addr),
+                    )?;
+                    self.assembler.emit_ldrsb(sz, dest, src);
+                    self.release_offset_register(offset_reg)?;
                 }
             }
             _ => unreachable!(),
         }
         if dst != dest {
-            self.move_location(sz, dest, dst);
+            self.move_location(sz, dest, dst)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn emit_relaxed_str64(&mut self, dst: Location, src: Location) {
+    fn emit_relaxed_str64(&mut self, dst: Location, src: Location) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dst = self.location_to_reg(Size::S64, dst, &mut temps, ImmType::NoneXzr, true, None);
+        let dst = self.location_to_reg(Size::S64, dst, &mut temps, ImmType::NoneXzr, true, None)?;
         match src {
             Location::Memory(addr, offset) => {
                 if self.compatible_imm(offset as i64, ImmType::OffsetDWord) {
@@ -706,26 +878,22 @@ impl MachineARM64 {
                 } else if self.compatible_imm(offset as i64, ImmType::UnscaledOffset) {
                     self.assembler.emit_stur(Size::S64, dst, addr, offset);
                 } else {
-                    let tmp = self.acquire_temp_gpr().unwrap();
-                    self.assembler
-                        .emit_mov_imm(Location::GPR(tmp), (offset as i64) as u64);
-                    self.assembler.emit_str(
-                        Size::S64,
-                        dst,
-                        Location::Memory2(addr, tmp, Multiplier::One, 0),
-                    );
-                    temps.push(tmp);
+                    let (src, offset_reg) =
+                        self.register_offset_address(addr, offset, Size::S64, dst, None)?;
+                    self.assembler.emit_str(Size::S64, dst, src);
+                    self.release_offset_register(offset_reg)?;
                 }
             }
-            _ => panic!("singlepass can't emit str64 {:?} {:?}", dst, src),
+            _ => codegen_error!("singlepass can't emit str64 {:?} {:?}", dst, src),
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn emit_relaxed_str32(&mut self, dst: Location, src: Location) {
+    fn emit_relaxed_str32(&mut self, dst: Location, src: Location) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dst = self.location_to_reg(Size::S64, dst, &mut temps, ImmType::NoneXzr, true, None);
+        let dst = self.location_to_reg(Size::S64, dst, &mut temps, ImmType::NoneXzr, true, None)?;
         match src {
             Location::Memory(addr, offset) => {
                 if self.compatible_imm(offset as i64, ImmType::OffsetWord) {
@@ -733,15 +901,10 @@ impl MachineARM64 {
                 } else if self.compatible_imm(offset as i64, ImmType::UnscaledOffset) {
                     self.assembler.emit_stur(Size::S32, dst, addr, offset);
                 } else {
-                    let tmp = self.acquire_temp_gpr().unwrap();
-                    self.assembler
-                        .emit_mov_imm(Location::GPR(tmp), (offset as i64) as u64);
-                    self.assembler.emit_str(
-                        Size::S32,
-                        dst,
-                        Location::Memory2(addr, tmp, Multiplier::One, 0),
-                    );
-                    temps.push(tmp);
+                    let (src, offset_reg) =
+                        self.register_offset_address(addr, offset, Size::S32, dst, None)?;
+                    self.assembler.emit_str(Size::S32, dst, src);
+                    self.release_offset_register(offset_reg)?;
                 }
             }
             _ => unreachable!(),
@@ -749,24 +912,20 @@ impl MachineARM64 {
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn emit_relaxed_str16(&mut self, dst: Location, src: Location) {
+    fn emit_relaxed_str16(&mut self, dst: Location, src: Location) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dst = self.location_to_reg(Size::S64, dst, &mut temps, ImmType::NoneXzr, true, None);
+        let dst = self.location_to_reg(Size::S64, dst, &mut temps, ImmType::NoneXzr, true, None)?;
         match src {
             Location::Memory(addr, offset) => {
                 if self.compatible_imm(offset as i64, ImmType::OffsetHWord) {
                     self.assembler.emit_strh(Size::S32, dst, src);
                 } else {
-                    let tmp = self.acquire_temp_gpr().unwrap();
-                    self.assembler
-                        .emit_mov_imm(Location::GPR(tmp), (offset as i64) as u64);
-                    self.assembler.emit_strh(
-                        Size::S32,
-                        dst,
-                        Location::Memory2(addr, tmp, Multiplier::One, 0),
-                    );
-                    temps.push(tmp);
+                    let (src, offset_reg) =
+                        self.register_offset_address(addr, offset, Size::S16, dst, None)?;
+                    self.assembler.emit_strh(Size::S32, dst, src);
+                    self.release_offset_register(offset_reg)?;
                 }
             }
             _ => unreachable!(),
@@ -774,25 +933,21 @@ impl MachineARM64 {
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn emit_relaxed_str8(&mut self, dst: Location, src: Location) {
+    fn emit_relaxed_str8(&mut self, dst: Location, src: Location) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dst = self.location_to_reg(Size::S64, dst, &mut temps, ImmType::NoneXzr, true, None);
+        let dst = self.location_to_reg(Size::S64, dst, &mut temps, ImmType::NoneXzr, true, None)?;
         match src {
             Location::Memory(addr, offset) => {
                 if self.compatible_imm(offset as i64, ImmType::OffsetByte) {
                     self.assembler
                         .emit_strb(Size::S32, dst, Location::Memory(addr, offset));
                 } else {
-                    let tmp = self.acquire_temp_gpr().unwrap();
-                    self.assembler
-                        .emit_mov_imm(Location::GPR(tmp), (offset as i64) as u64);
-                    self.assembler.emit_strb(
-                        Size::S32,
-                        dst,
-                        Location::Memory2(addr, tmp, Multiplier::One, 0),
-                    );
-                    temps.push(tmp);
+                    let (src, offset_reg) =
+                        self.register_offset_address(addr, offset, Size::S8, dst, None)?;
+                    self.assembler.emit_strb(Size::S32, dst, src);
+                    self.release_offset_register(offset_reg)?;
                 }
             }
             _ => unreachable!(),
@@ -800,6 +955,163 @@ impl MachineARM64 {
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
+    }
+    /// Loads `sz` bytes with acquire semantics, zero-extended to a
+    /// `dst_sz` value.
+    fn emit_relaxed_ldar(
+        &mut self,
+        sz: Size,
+        dst_sz: Size,
+        dst: Location,
+        addr: GPR,
+    ) -> Result<(), CodegenError> {
+        let mut temps = vec![];
+        let dest = self.location_to_reg(dst_sz, dst, &mut temps, ImmType::None, false, None)?;
+        self.assembler.emit_ldar(sz, dest, addr);
+        if dst != dest {
+            self.move_location(dst_sz, dest, dst)?;
+        }
+        for r in temps {
+            self.release_gpr(r);
+        }
+        Ok(())
+    }
+    /// Stores the low `sz` bytes of `src` with release semantics.
+    fn emit_relaxed_stlr(
+        &mut self,
+        sz: Size,
+        src: Location,
+        addr: GPR,
+    ) -> Result<(), CodegenError> {
+        let mut temps = vec![];
+        let src = self.location_to_reg(Size::S64, src, &mut temps, ImmType::NoneXzr, true, None)?;
+        self.assembler.emit_stlr(sz, src, addr);
+        for r in temps {
+            self.release_gpr(r);
+        }
+        Ok(())
+    }
+    /// Replaces the `sz` bytes at `addr` by `op` applied to them and
+    /// `loc`, or by `loc` without `op`, retrying until the exclusive store
+    /// succeeds. The previous value is zero-extended to `ret`.
+    #[allow(clippy::type_complexity)]
+    fn emit_relaxed_atomic_rmw(
+        &mut self,
+        op: Option<fn(&mut Assembler, Size, Location, Location, Location)>,
+        sz: Size,
+        dst_sz: Size,
+        loc: Location,
+        ret: Location,
+        addr: GPR,
+    ) -> Result<(), CodegenError> {
+        let op_sz = match sz {
+            Size::S64 => Size::S64,
+            _ => Size::S32,
+        };
+        let mut temps = vec![];
+        // `ret` may be the location of `loc`, so it's only written once
+        // the loop is done.
+        let src = self.location_to_reg(op_sz, loc, &mut temps, ImmType::None, true, None)?;
+        let old = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let status = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        temps.push(old);
+        temps.push(status);
+        let new = match op {
+            Some(_) => {
+                let new = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                temps.push(new);
+                Location::GPR(new)
+            }
+            None => src,
+        };
+
+        let retry = self.assembler.get_label();
+        self.assembler.emit_label(retry);
+        self.assembler.emit_ldaxr(sz, Location::GPR(old), addr);
+        if let Some(op) = op {
+            op(&mut self.assembler, op_sz, Location::GPR(old), src, new);
+        }
+        self.assembler
+            .emit_stlxr(sz, Location::GPR(status), new, addr);
+        self.assembler
+            .emit_cbnz_label(Size::S32, Location::GPR(status), retry);
+
+        self.move_location(dst_sz, Location::GPR(old), ret)?;
+        for r in temps {
+            self.release_gpr(r);
+        }
+        Ok(())
+    }
+    /// Replaces the `sz` bytes at `addr` by `new` if they're equal to
+    /// `cmp` wrapped to `sz`. The previous value is zero-extended to `ret`.
+    fn emit_relaxed_atomic_cmpxchg(
+        &mut self,
+        sz: Size,
+        dst_sz: Size,
+        new: Location,
+        cmp: Location,
+        ret: Location,
+        addr: GPR,
+    ) -> Result<(), CodegenError> {
+        let op_sz = match sz {
+            Size::S64 => Size::S64,
+            _ => Size::S32,
+        };
+        let mut temps = vec![];
+        let new = self.location_to_reg(op_sz, new, &mut temps, ImmType::NoneXzr, true, None)?;
+        let cmp = self.location_to_reg(op_sz, cmp, &mut temps, ImmType::None, true, None)?;
+        let old = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let status = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        temps.push(old);
+        temps.push(status);
+        // The loaded value is zero-extended, so the expected one must be
+        // too. The 32 bits case is covered by comparing W registers.
+        let expected = match sz {
+            Size::S8 | Size::S16 => {
+                let expected = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                temps.push(expected);
+                if sz == Size::S8 {
+                    self.assembler
+                        .emit_uxtb(Size::S32, cmp, Location::GPR(expected));
+                } else {
+                    self.assembler
+                        .emit_uxth(Size::S32, cmp, Location::GPR(expected));
+                }
+                Location::GPR(expected)
+            }
+            _ => cmp,
+        };
+
+        let retry = self.assembler.get_label();
+        let done = self.assembler.get_label();
+        self.assembler.emit_label(retry);
+        self.assembler.emit_ldaxr(sz, Location::GPR(old), addr);
+        self.assembler.emit_cmp(op_sz, expected, Location::GPR(old));
+        self.assembler.emit_bcond_label(Condition::Ne, done);
+        self.assembler
+            .emit_stlxr(sz, Location::GPR(status), new, addr);
+        self.assembler
+            .emit_cbnz_label(Size::S32, Location::GPR(status), retry);
+        self.assembler.emit_label(done);
+
+        self.move_location(dst_sz, Location::GPR(old), ret)?;
+        for r in temps {
+            self.release_gpr(r);
+        }
+        Ok(())
     }
     /// I64 comparison with.
     fn emit_cmpop_i64_dynamic_b(
@@ -808,23 +1120,26 @@ impl MachineARM64 {
         loc_a: Location,
         loc_b: Location,
         ret: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         match ret {
             Location::GPR(_) => {
-                self.emit_relaxed_cmp(Size::S64, loc_b, loc_a);
+                self.emit_relaxed_cmp(Size::S64, loc_b, loc_a)?;
                 self.assembler.emit_cset(Size::S32, ret, c);
             }
             Location::Memory(_, _) => {
-                let tmp = self.acquire_temp_gpr().unwrap();
-                self.emit_relaxed_cmp(Size::S64, loc_b, loc_a);
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                self.emit_relaxed_cmp(Size::S64, loc_b, loc_a)?;
                 self.assembler.emit_cset(Size::S32, Location::GPR(tmp), c);
-                self.move_location(Size::S32, Location::GPR(tmp), ret);
+                self.move_location(Size::S32, Location::GPR(tmp), ret)?;
                 self.release_gpr(tmp);
             }
             _ => {
                 unreachable!();
             }
         }
+        Ok(())
     }
     /// I32 comparison with.
     fn emit_cmpop_i32_dynamic_b(
@@ -833,26 +1148,62 @@ impl MachineARM64 {
         loc_a: Location,
         loc_b: Location,
         ret: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         match ret {
             Location::GPR(_) => {
-                self.emit_relaxed_cmp(Size::S32, loc_b, loc_a);
+                self.emit_relaxed_cmp(Size::S32, loc_b, loc_a)?;
                 self.assembler.emit_cset(Size::S32, ret, c);
             }
             Location::Memory(_, _) => {
-                let tmp = self.acquire_temp_gpr().unwrap();
-                self.emit_relaxed_cmp(Size::S32, loc_b, loc_a);
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                self.emit_relaxed_cmp(Size::S32, loc_b, loc_a)?;
                 self.assembler.emit_cset(Size::S32, Location::GPR(tmp), c);
-                self.move_location(Size::S32, Location::GPR(tmp), ret);
+                self.move_location(Size::S32, Location::GPR(tmp), ret)?;
                 self.release_gpr(tmp);
             }
             _ => {
                 unreachable!();
             }
         }
+        Ok(())
+    }
+
+    /// Counts the bits set in `loc` with NEON: CNT counts the bits of
+    /// each byte, and ADDV sums the 8 bytes.
+    fn emit_popcnt(&mut self, sz: Size, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        let mut temps = vec![];
+        let src = self.location_to_reg(sz, loc, &mut temps, ImmType::None, true, None)?;
+        let dest = self.location_to_reg(sz, ret, &mut temps, ImmType::None, false, None)?;
+        let tmp = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
+        let src = if sz == Size::S32 {
+            // zero the upper half, so it isn't counted
+            self.assembler.emit_mov(Size::S32, src, dest);
+            dest
+        } else {
+            src
+        };
+        self.assembler.emit_mov(Size::S64, src, Location::SIMD(tmp));
+        self.assembler
+            .emit_cnt(Location::SIMD(tmp), Location::SIMD(tmp));
+        self.assembler
+            .emit_addv(Location::SIMD(tmp), Location::SIMD(tmp));
+        self.assembler
+            .emit_mov(Size::S32, Location::SIMD(tmp), dest);
+        self.release_simd(tmp);
+        if ret != dest {
+            self.move_location(sz, dest, ret)?;
+        }
+        for r in temps {
+            self.release_gpr(r);
+        }
+        Ok(())
     }
 
-    fn memory_op<F: FnOnce(&mut Self, GPR)>(
+    fn memory_op<F: FnOnce(&mut Self, GPR) -> Result<(), CodegenError>>(
         &mut self,
         addr: Location,
         memarg: &MemoryImmediate,
@@ -863,19 +1214,25 @@ impl MachineARM64 {
         offset: i32,
         heap_access_oob: Label,
         cb: F,
-    ) {
-        let tmp_addr = self.acquire_temp_gpr().unwrap();
+    ) -> Result<(), CodegenError> {
+        let tmp_addr = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+
+        let base_cached = self.cached_memory_base == Some(offset);
 
         // Reusing `tmp_addr` for temporary indirection here, since it's not used before the last reference to `{base,bound}_loc`.
         let (base_loc, bound_loc) = if imported_memories {
             // Imported memories require one level of indirection.
-            self.emit_relaxed_binop(
-                Assembler::emit_mov,
-                Size::S64,
-                Location::Memory(self.get_vmctx_reg(), offset),
-                Location::GPR(tmp_addr),
-                true,
-            );
+            if !base_cached || need_check {
+                self.emit_relaxed_binop(
+                    Assembler::emit_mov,
+                    Size::S64,
+                    Location::Memory(self.get_vmctx_reg(), offset),
+                    Location::GPR(tmp_addr),
+                    true,
+                )?;
+            }
             (Location::Memory(tmp_addr, 0), Location::Memory(tmp_addr, 8))
         } else {
             (
@@ -884,15 +1241,20 @@ impl MachineARM64 {
             )
         };
 
-        let tmp_base = self.acquire_temp_gpr().unwrap();
-        let tmp_bound = self.acquire_temp_gpr().unwrap();
+        let tmp_base = MEMORY_BASE_REG;
+        let tmp_bound = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
 
-        // Load base into temporary register.
-        self.emit_relaxed_ldr64(Size::S64, Location::GPR(tmp_base), base_loc);
+        // Load base into the cache register, unless a previous access did.
+        if !base_cached {
+            self.emit_relaxed_ldr64(Size::S64, Location::GPR(tmp_base), base_loc)?;
+            self.cached_memory_base = Some(offset);
+        }
 
         // Load bound into temporary register, if needed.
         if need_check {
-            self.emit_relaxed_ldr64(Size::S64, Location::GPR(tmp_bound), bound_loc);
+            self.emit_relaxed_ldr64(Size::S64, Location::GPR(tmp_bound), bound_loc)?;
 
             // Wasm -> Effective.
             // Assuming we never underflow - should always be true on Linux/macOS and Windows >=8,
@@ -907,11 +1269,13 @@ impl MachineARM64 {
                 self.assembler.emit_sub(
                     Size::S64,
                     Location::GPR(tmp_bound),
-                    Location::GPR(tmp_bound),
                     Location::Imm32(value_size as _),
+                    Location::GPR(tmp_bound),
                 );
             } else {
-                let tmp2 = self.acquire_temp_gpr().unwrap();
+                let tmp2 = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
                 self.assembler
                     .emit_mov_imm(Location::GPR(tmp2), value_size as u64);
                 self.assembler.emit_sub(
@@ -927,7 +1291,7 @@ impl MachineARM64 {
         // Load effective address.
         // `base_loc` and `bound_loc` becomes INVALID after this line, because `tmp_addr`
         // might be reused.
-        self.move_location(Size::S32, addr, Location::GPR(tmp_addr));
+        self.move_location(Size::S32, addr, Location::GPR(tmp_addr))?;
 
         // Add offset to memory address.
         if memarg.offset != 0 {
@@ -939,7 +1303,9 @@ impl MachineARM64 {
                     Location::GPR(tmp_addr),
                 );
             } else {
-                let tmp = self.acquire_temp_gpr().unwrap();
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
                 self.assembler
                     .emit_mov_imm(Location::GPR(tmp), memarg.offset as _);
                 self.assembler.emit_adds(
@@ -975,7 +1341,6 @@ impl MachineARM64 {
         }
 
         self.release_gpr(tmp_bound);
-        self.release_gpr(tmp_base);
 
         let align = memarg.align;
         if check_alignment && align != 1 {
@@ -988,11 +1353,12 @@ impl MachineARM64 {
                 .emit_bcond_label_far(Condition::Ne, heap_access_oob);
         }
         let begin = self.assembler.get_offset().0;
-        cb(self, tmp_addr);
+        cb(self, tmp_addr)?;
         let end = self.assembler.get_offset().0;
         self.mark_address_range_with_trap_code(TrapCode::HeapAccessOutOfBounds, begin, end);
 
         self.release_gpr(tmp_addr);
+        Ok(())
     }
 
     /*fn emit_compare_and_swap<F: FnOnce(&mut Self, GPR, GPR)>(
@@ -1032,7 +1398,7 @@ impl MachineARM64 {
         return true;
     }
 
-    fn emit_push(&mut self, sz: Size, src: Location) {
+    fn emit_push(&mut self, sz: Size, src: Location) -> Result<(), CodegenError> {
         match (sz, src) {
             (Size::S64, Location::GPR(_)) | (Size::S64, Location::SIMD(_)) => {
                 let offset = if self.pushed {
@@ -1051,7 +1417,7 @@ impl MachineARM64 {
             }
             (Size::S64, _) => {
                 let mut temps = vec![];
-                let src = self.location_to_reg(sz, src, &mut temps, ImmType::None, true, None);
+                let src = self.location_to_reg(sz, src, &mut temps, ImmType::None, true, None)?;
                 let offset = if self.pushed {
                     0
                 } else {
@@ -1069,10 +1435,16 @@ impl MachineARM64 {
                     self.release_gpr(r);
                 }
             }
-            _ => panic!("singlepass can't emit PUSH {:?} {:?}", sz, src),
+            _ => codegen_error!("singlepass can't emit PUSH {:?} {:?}", sz, src),
         }
+        Ok(())
     }
-    fn emit_double_push(&mut self, sz: Size, src1: Location, src2: Location) {
+    fn emit_double_push(
+        &mut self,
+        sz: Size,
+        src1: Location,
+        src2: Location,
+    ) -> Result<(), CodegenError> {
         if !self.pushed {
             match (sz, src1, src2) {
                 (Size::S64, Location::GPR(_), Location::GPR(_)) => {
@@ -1080,16 +1452,17 @@ impl MachineARM64 {
                         .emit_stpdb(Size::S64, src1, src2, GPR::XzrSp, 16);
                 }
                 _ => {
-                    self.emit_push(sz, src1);
-                    self.emit_push(sz, src2);
+                    self.emit_push(sz, src1)?;
+                    self.emit_push(sz, src2)?;
                 }
             }
         } else {
-            self.emit_push(sz, src1);
-            self.emit_push(sz, src2);
+            self.emit_push(sz, src1)?;
+            self.emit_push(sz, src2)?;
         }
+        Ok(())
     }
-    fn emit_pop(&mut self, sz: Size, dst: Location) {
+    fn emit_pop(&mut self, sz: Size, dst: Location) -> Result<(), CodegenError> {
         match (sz, dst) {
             (Size::S64, Location::GPR(_)) | (Size::S64, Location::SIMD(_)) => {
                 let offset = if self.pushed { 8 } else { 0 };
@@ -1104,10 +1477,16 @@ impl MachineARM64 {
                 }
                 self.pushed = !self.pushed;
             }
-            _ => panic!("singlepass can't emit PUSH {:?} {:?}", sz, dst),
+            _ => codegen_error!("singlepass can't emit PUSH {:?} {:?}", sz, dst),
         }
+        Ok(())
     }
-    fn emit_double_pop(&mut self, sz: Size, dst1: Location, dst2: Location) {
+    fn emit_double_pop(
+        &mut self,
+        sz: Size,
+        dst1: Location,
+        dst2: Location,
+    ) -> Result<(), CodegenError> {
         if !self.pushed {
             match (sz, dst1, dst2) {
                 (Size::S64, Location::GPR(_), Location::GPR(_)) => {
@@ -1115,24 +1494,31 @@ impl MachineARM64 {
                         .emit_ldpia(Size::S64, dst1, dst2, GPR::XzrSp, 16);
                 }
                 _ => {
-                    self.emit_pop(sz, dst2);
-                    self.emit_pop(sz, dst1);
+                    self.emit_pop(sz, dst2)?;
+                    self.emit_pop(sz, dst1)?;
                 }
             }
         } else {
-            self.emit_pop(sz, dst2);
-            self.emit_pop(sz, dst1);
+            self.emit_pop(sz, dst2)?;
+            self.emit_pop(sz, dst1)?;
         }
+        Ok(())
     }
 
-    fn set_default_nan(&mut self, temps: &mut Vec<GPR>) -> GPR {
+    fn set_default_nan(&mut self, temps: &mut Vec<GPR>) -> Result<GPR, CodegenError> {
         // temporarly set FPCR to DefaultNan
-        let old_fpcr = self.acquire_temp_gpr().unwrap();
+        let old_fpcr = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         temps.push(old_fpcr.clone());
         self.assembler.emit_read_fpcr(old_fpcr);
-        let new_fpcr = self.acquire_temp_gpr().unwrap();
+        let new_fpcr = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         temps.push(new_fpcr.clone());
-        let tmp = self.acquire_temp_gpr().unwrap();
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         temps.push(tmp.clone());
         self.assembler
             .emit_mov(Size::S32, Location::Imm32(1), Location::GPR(tmp));
@@ -1147,14 +1533,18 @@ impl MachineARM64 {
             Location::GPR(new_fpcr),
         );
         self.assembler.emit_write_fpcr(new_fpcr);
-        old_fpcr
+        Ok(old_fpcr)
     }
-    fn set_trap_enabled(&mut self, temps: &mut Vec<GPR>) -> GPR {
+    fn set_trap_enabled(&mut self, temps: &mut Vec<GPR>) -> Result<GPR, CodegenError> {
         // temporarly set FPCR to DefaultNan
-        let old_fpcr = self.acquire_temp_gpr().unwrap();
+        let old_fpcr = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         temps.push(old_fpcr.clone());
         self.assembler.emit_read_fpcr(old_fpcr);
-        let new_fpcr = self.acquire_temp_gpr().unwrap();
+        let new_fpcr = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         temps.push(new_fpcr.clone());
         self.assembler
             .emit_mov(Size::S64, Location::GPR(old_fpcr), Location::GPR(new_fpcr));
@@ -1162,26 +1552,31 @@ impl MachineARM64 {
         self.assembler
             .emit_bfc(Size::S64, 8, 1, Location::GPR(new_fpcr));
         self.assembler.emit_write_fpcr(new_fpcr);
-        old_fpcr
+        Ok(old_fpcr)
     }
     fn restore_fpcr(&mut self, old_fpcr: GPR) {
         self.assembler.emit_write_fpcr(old_fpcr);
     }
 
-    fn reset_exception_fpsr(&mut self) {
+    fn reset_exception_fpsr(&mut self) -> Result<(), CodegenError> {
         // reset exception count in FPSR
-        let fpsr = self.acquire_temp_gpr().unwrap();
+        let fpsr = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         self.assembler.emit_read_fpsr(fpsr);
         // IOC is 0
         self.assembler
             .emit_bfc(Size::S64, 0, 1, Location::GPR(fpsr));
         self.assembler.emit_write_fpsr(fpsr);
         self.release_gpr(fpsr);
+        Ok(())
     }
-    fn read_fpsr(&mut self) -> GPR {
-        let fpsr = self.acquire_temp_gpr().unwrap();
+    fn read_fpsr(&mut self) -> Result<GPR, CodegenError> {
+        let fpsr = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         self.assembler.emit_read_fpsr(fpsr);
-        fpsr
+        Ok(fpsr)
     }
 
     fn trap_float_convertion_errors(
@@ -1190,11 +1585,11 @@ impl MachineARM64 {
         sz: Size,
         f: Location,
         temps: &mut Vec<GPR>,
-    ) {
+    ) -> Result<(), CodegenError> {
         let trap_badconv = self.assembler.get_label();
         let end = self.assembler.get_label();
 
-        let fpsr = self.read_fpsr();
+        let fpsr = self.read_fpsr()?;
         temps.push(fpsr.clone());
         // no trap, than all good
         self.assembler
@@ -1213,6 +1608,7 @@ impl MachineARM64 {
 
         self.emit_label(end);
         self.restore_fpcr(old_fpcr);
+        Ok(())
     }
 
     fn used_gprs_contains(&self, r: &GPR) -> bool {
@@ -1240,6 +1636,17 @@ impl MachineARM64 {
     fn emit_unwind_op(&mut self, op: UnwindOps) {
         self.unwind_ops.push((self.get_offset().0, op));
     }
+    fn emit_unwind_save_register(&mut self, stack_offset: i32, location: Location) {
+        let reg = match location {
+            Location::GPR(x) => x.to_dwarf(),
+            Location::SIMD(x) => x.to_dwarf(),
+            _ => return,
+        };
+        self.emit_unwind_op(UnwindOps::SaveRegister {
+            reg,
+            bp_neg_offset: stack_offset,
+        });
+    }
     fn emit_illegal_op_internal(&mut self, trap: TrapCode) {
         self.assembler.emit_udf(0xc0 | (trap as u8) as u16);
     }
@@ -1259,7 +1666,7 @@ impl Machine for MachineARM64 {
     }
 
     fn get_vmctx_reg(&self) -> GPR {
-        GPR::X28
+        self.vmctx_reg
     }
 
     fn get_used_gprs(&self) -> Vec<GPR> {
@@ -1333,22 +1740,29 @@ impl Machine for MachineARM64 {
         self.used_gprs_insert(gpr);
     }
 
-    fn push_used_gpr(&mut self, used_gprs: &Vec<GPR>) -> usize {
-        if used_gprs.len() % 2 == 1 {
-            self.emit_push(Size::S64, Location::GPR(GPR::XzrSp));
+    fn push_used_gpr(&mut self, used_gprs: &Vec<GPR>) -> Result<usize, CodegenError> {
+        // An odd register is pushed alone with some padding, the others by
+        // pairs, keeping the order of single pushes.
+        let paired = used_gprs.len() % 2;
+        if paired == 1 {
+            self.emit_push(Size::S64, Location::GPR(GPR::XzrSp))?;
+            self.emit_push(Size::S64, Location::GPR(used_gprs[0]))?;
         }
-        for r in used_gprs.iter() {
-            self.emit_push(Size::S64, Location::GPR(*r));
+        for pair in used_gprs[paired..].chunks(2) {
+            self.emit_double_push(Size::S64, Location::GPR(pair[1]), Location::GPR(pair[0]))?;
         }
-        ((used_gprs.len() + 1) / 2) * 16
+        Ok(FrameLayout::AARCH64.spill_size(used_gprs.len()))
     }
-    fn pop_used_gpr(&mut self, used_gprs: &Vec<GPR>) {
-        for r in used_gprs.iter().rev() {
-            self.emit_pop(Size::S64, Location::GPR(*r));
+    fn pop_used_gpr(&mut self, used_gprs: &Vec<GPR>) -> Result<(), CodegenError> {
+        let paired = used_gprs.len() % 2;
+        for pair in used_gprs[paired..].chunks(2).rev() {
+            self.emit_double_pop(Size::S64, Location::GPR(pair[1]), Location::GPR(pair[0]))?;
         }
-        if used_gprs.len() % 2 == 1 {
-            self.emit_pop(Size::S64, Location::GPR(GPR::XzrSp));
+        if paired == 1 {
+            self.emit_pop(Size::S64, Location::GPR(used_gprs[0]))?;
+            self.emit_pop(Size::S64, Location::GPR(GPR::XzrSp))?;
         }
+        Ok(())
     }
 
     // Picks an unused NEON register.
@@ -1393,42 +1807,55 @@ impl Machine for MachineARM64 {
         assert_eq!(self.used_simd_remove(&simd), true);
     }
 
-    fn push_used_simd(&mut self, used_neons: &Vec<NEON>) -> usize {
-        let stack_adjust = if used_neons.len() & 1 == 1 {
-            (used_neons.len() * 8) as u32 + 8
-        } else {
-            (used_neons.len() * 8) as u32
-        };
+    fn push_used_simd(&mut self, used_neons: &Vec<NEON>) -> Result<usize, CodegenError> {
+        let stack_adjust = FrameLayout::AARCH64.spill_size(used_neons.len()) as u32;
         self.adjust_stack(stack_adjust);
 
-        for (i, r) in used_neons.iter().enumerate() {
-            self.assembler.emit_str(
-                Size::S64,
-                Location::SIMD(*r),
-                Location::Memory(GPR::XzrSp, (i * 8) as i32),
-            );
+        for (i, pair) in used_neons.chunks(2).enumerate() {
+            let offset = (i * 16) as i32;
+            match *pair {
+                [first, second] => self.assembler.emit_stp(
+                    Size::S64,
+                    Location::SIMD(first),
+                    Location::SIMD(second),
+                    GPR::XzrSp,
+                    offset,
+                ),
+                _ => self.assembler.emit_str(
+                    Size::S64,
+                    Location::SIMD(pair[0]),
+                    Location::Memory(GPR::XzrSp, offset),
+                ),
+            }
         }
-        stack_adjust as usize
+        Ok(stack_adjust as usize)
     }
-    fn pop_used_simd(&mut self, used_neons: &Vec<NEON>) {
-        for (i, r) in used_neons.iter().enumerate() {
-            self.assembler.emit_ldr(
-                Size::S64,
-                Location::SIMD(*r),
-                Location::Memory(GPR::XzrSp, (i * 8) as i32),
-            );
+    fn pop_used_simd(&mut self, used_neons: &Vec<NEON>) -> Result<(), CodegenError> {
+        for (i, pair) in used_neons.chunks(2).enumerate() {
+            let offset = (i * 16) as i32;
+            match *pair {
+                [first, second] => self.assembler.emit_ldp(
+                    Size::S64,
+                    Location::SIMD(first),
+                    Location::SIMD(second),
+                    GPR::XzrSp,
+                    offset,
+                ),
+                _ => self.assembler.emit_ldr(
+                    Size::S64,
+                    Location::SIMD(pair[0]),
+                    Location::Memory(GPR::XzrSp, offset),
+                ),
+            }
         }
-        let stack_adjust = if used_neons.len() & 1 == 1 {
-            (used_neons.len() * 8) as u32 + 8
-        } else {
-            (used_neons.len() * 8) as u32
-        };
+        let stack_adjust = FrameLayout::AARCH64.spill_size(used_neons.len()) as u32;
         self.assembler.emit_add(
             Size::S64,
             Location::GPR(GPR::XzrSp),
             Location::Imm32(stack_adjust as _),
             Location::GPR(GPR::XzrSp),
         );
+        Ok(())
     }
 
     /// Set the source location of the Wasm to the given offset.
@@ -1491,13 +1918,8 @@ impl Machine for MachineARM64 {
         self.instructions_address_map.clone()
     }
 
-    // Return a rounded stack adjustement value (must be multiple of 16bytes on ARM64 for example)
-    fn round_stack_adjust(&self, value: usize) -> usize {
-        if value & 0xf != 0 {
-            ((value >> 4) + 1) << 4
-        } else {
-            value
-        }
+    fn frame_layout(&self) -> FrameLayout {
+        FrameLayout::AARCH64
     }
 
     // Memory location for a local on the stack
@@ -1540,11 +1962,7 @@ impl Machine for MachineARM64 {
         );
     }
     fn pop_stack_locals(&mut self, delta_stack_offset: u32) {
-        let real_delta = if delta_stack_offset & 15 != 0 {
-            delta_stack_offset + 8
-        } else {
-            delta_stack_offset
-        };
+        let real_delta = self.round_stack_adjust(delta_stack_offset as usize) as u32;
         let delta = if self.compatible_imm(real_delta as i64, ImmType::Bits12) {
             Location::Imm32(real_delta as _)
         } else {
@@ -1561,23 +1979,29 @@ impl Machine for MachineARM64 {
         );
     }
     // push a value on the stack for a native call
-    fn move_location_for_native(&mut self, size: Size, loc: Location, dest: Location) {
+    fn move_location_for_native(
+        &mut self,
+        size: Size,
+        loc: Location,
+        dest: Location,
+    ) -> Result<(), CodegenError> {
         match loc {
             Location::Imm64(_)
             | Location::Imm32(_)
             | Location::Imm8(_)
             | Location::Memory(_, _)
             | Location::Memory2(_, _, _, _) => {
-                self.move_location(size, loc, Location::GPR(GPR::X17));
-                self.move_location(size, Location::GPR(GPR::X17), dest);
+                self.move_location(size, loc, Location::GPR(GPR::X17))?;
+                self.move_location(size, Location::GPR(GPR::X17), dest)?;
             }
-            _ => self.move_location(size, loc, dest),
+            _ => self.move_location(size, loc, dest)?,
         }
+        Ok(())
     }
 
     // Zero a location that is 32bits
-    fn zero_location(&mut self, size: Size, location: Location) {
-        self.move_location(size, Location::GPR(GPR::XzrSp), location);
+    fn zero_location(&mut self, size: Size, location: Location) -> Result<(), CodegenError> {
+        self.move_location(size, Location::GPR(GPR::XzrSp), location)
     }
 
     // GPR Reg used for local pointer on the stack
@@ -1587,22 +2011,20 @@ impl Machine for MachineARM64 {
 
     // Determine whether a local should be allocated on the stack.
     fn is_local_on_stack(&self, idx: usize) -> bool {
-        idx > 7
+        idx >= self.local_regs.len()
     }
 
     // Determine a local's location.
     fn get_local_location(&self, idx: usize, callee_saved_regs_size: usize) -> Location {
         // Use callee-saved registers for the first locals.
-        match idx {
-            0 => Location::GPR(GPR::X19),
-            1 => Location::GPR(GPR::X20),
-            2 => Location::GPR(GPR::X21),
-            3 => Location::GPR(GPR::X22),
-            4 => Location::GPR(GPR::X23),
-            5 => Location::GPR(GPR::X24),
-            6 => Location::GPR(GPR::X25),
-            7 => Location::GPR(GPR::X26),
-            _ => Location::Memory(GPR::X29, -(((idx - 7) * 8 + callee_saved_regs_size) as i32)),
+        let regs = self.local_regs.len();
+        if idx < regs {
+            Location::GPR(self.local_regs[idx])
+        } else {
+            Location::Memory(
+                GPR::X29,
+                FrameLayout::AARCH64.local_offset(idx, regs, callee_saved_regs_size),
+            )
         }
     }
     // Move a local to the stack
@@ -1623,20 +2045,33 @@ impl Machine for MachineARM64 {
             self.assembler
                 .emit_str(Size::S64, location, Location::GPR(tmp));
         }
-        match location {
-            Location::GPR(x) => {
-                self.emit_unwind_op(UnwindOps::SaveRegister {
-                    reg: x.to_dwarf(),
-                    bp_neg_offset: stack_offset,
-                });
-            }
-            Location::SIMD(x) => {
-                self.emit_unwind_op(UnwindOps::SaveRegister {
-                    reg: x.to_dwarf(),
-                    bp_neg_offset: stack_offset,
-                });
+        self.emit_unwind_save_register(stack_offset, location);
+    }
+    // Move consecutive locals to the stack, by pairs with `stp`
+    fn move_locals(&mut self, stack_offset: i32, locations: &[Location]) {
+        for (i, pair) in locations.chunks(2).enumerate() {
+            let stack_offset = stack_offset + 16 * i as i32;
+            match *pair {
+                // The second local is 8 bytes below the first one.
+                [first @ Location::GPR(_), second @ Location::GPR(_)]
+                    if stack_offset + 8 <= 512 =>
+                {
+                    self.assembler.emit_stp(
+                        Size::S64,
+                        second,
+                        first,
+                        GPR::X29,
+                        -(stack_offset + 8),
+                    );
+                    self.emit_unwind_save_register(stack_offset, first);
+                    self.emit_unwind_save_register(stack_offset + 8, second);
+                }
+                _ => {
+                    for (j, location) in pair.iter().enumerate() {
+                        self.move_local(stack_offset + 8 * j as i32, *location);
+                    }
+                }
             }
-            _ => (),
         }
     }
 
@@ -1729,7 +2164,10 @@ impl Machine for MachineARM64 {
                             *stack_args = (*stack_args + ((1 << sz) - 1)) & !((1 << sz) - 1);
                         }
                     }
-                    let loc = Location::Memory(GPR::X29, 16 * 2 + *stack_args as i32);
+                    let loc = Location::Memory(
+                        GPR::X29,
+                        FrameLayout::AARCH64.stack_argument_offset(*stack_args),
+                    );
                     *stack_args += 1 << sz;
                     loc
                 }
@@ -1744,7 +2182,10 @@ impl Machine for MachineARM64 {
                 6 => Location::GPR(GPR::X6),
                 7 => Location::GPR(GPR::X7),
                 _ => {
-                    let loc = Location::Memory(GPR::X29, 16 * 2 + *stack_args as i32);
+                    let loc = Location::Memory(
+                        GPR::X29,
+                        FrameLayout::AARCH64.stack_argument_offset(*stack_args),
+                    );
                     *stack_args += 8;
                     loc
                 }
@@ -1767,12 +2208,20 @@ impl Machine for MachineARM64 {
                 5 => Location::GPR(GPR::X5),
                 6 => Location::GPR(GPR::X6),
                 7 => Location::GPR(GPR::X7),
-                _ => Location::Memory(GPR::X29, (16 * 2 + (idx - 8) * 8) as i32),
+                _ => Location::Memory(
+                    GPR::X29,
+                    FrameLayout::AARCH64.stack_argument_offset((idx - 8) * 8),
+                ),
             },
         }
     }
     // move a location to another
-    fn move_location(&mut self, size: Size, source: Location, dest: Location) {
+    fn move_location(
+        &mut self,
+        size: Size,
+        source: Location,
+        dest: Location,
+    ) -> Result<(), CodegenError> {
         match source {
             Location::GPR(_) | Location::SIMD(_) => match dest {
                 Location::GPR(_) | Location::SIMD(_) => self.assembler.emit_mov(size, source, dest),
@@ -1805,48 +2254,56 @@ impl Machine for MachineARM64 {
                             .emit_str(size, source, Location::Memory(tmp, 0));
                     }
                 }
-                _ => panic!(
+                _ => codegen_error!(
                     "singlepass can't emit move_location {:?} {:?} => {:?}",
-                    size, source, dest
+                    size,
+                    source,
+                    dest
                 ),
             },
             Location::Imm8(_) => match dest {
                 Location::GPR(_) => self.assembler.emit_mov(size, source, dest),
                 Location::Memory(_, _) => match size {
-                    Size::S64 => self.emit_relaxed_str64(source, dest),
-                    Size::S32 => self.emit_relaxed_str32(source, dest),
-                    Size::S16 => self.emit_relaxed_str16(source, dest),
-                    Size::S8 => self.emit_relaxed_str8(source, dest),
+                    Size::S64 => self.emit_relaxed_str64(source, dest)?,
+                    Size::S32 => self.emit_relaxed_str32(source, dest)?,
+                    Size::S16 => self.emit_relaxed_str16(source, dest)?,
+                    Size::S8 => self.emit_relaxed_str8(source, dest)?,
                 },
-                _ => panic!(
+                _ => codegen_error!(
                     "singlepass can't emit move_location {:?} {:?} => {:?}",
-                    size, source, dest
+                    size,
+                    source,
+                    dest
                 ),
             },
             Location::Imm32(val) => match dest {
                 Location::GPR(_) => self.assembler.emit_mov_imm(dest, val as u64),
                 Location::Memory(_, _) => match size {
-                    Size::S64 => self.emit_relaxed_str64(source, dest),
-                    Size::S32 => self.emit_relaxed_str32(source, dest),
-                    Size::S16 => self.emit_relaxed_str16(source, dest),
-                    Size::S8 => self.emit_relaxed_str8(source, dest),
+                    Size::S64 => self.emit_relaxed_str64(source, dest)?,
+                    Size::S32 => self.emit_relaxed_str32(source, dest)?,
+                    Size::S16 => self.emit_relaxed_str16(source, dest)?,
+                    Size::S8 => self.emit_relaxed_str8(source, dest)?,
                 },
-                _ => panic!(
+                _ => codegen_error!(
                     "singlepass can't emit move_location {:?} {:?} => {:?}",
-                    size, source, dest
+                    size,
+                    source,
+                    dest
                 ),
             },
             Location::Imm64(val) => match dest {
                 Location::GPR(_) => self.assembler.emit_mov_imm(dest, val),
                 Location::Memory(_, _) => match size {
-                    Size::S64 => self.emit_relaxed_str64(source, dest),
-                    Size::S32 => self.emit_relaxed_str32(source, dest),
-                    Size::S16 => self.emit_relaxed_str16(source, dest),
-                    Size::S8 => self.emit_relaxed_str8(source, dest),
+                    Size::S64 => self.emit_relaxed_str64(source, dest)?,
+                    Size::S32 => self.emit_relaxed_str32(source, dest)?,
+                    Size::S16 => self.emit_relaxed_str16(source, dest)?,
+                    Size::S8 => self.emit_relaxed_str8(source, dest)?,
                 },
-                _ => panic!(
+                _ => codegen_error!(
                     "singlepass can't emit move_location {:?} {:?} => {:?}",
-                    size, source, dest
+                    size,
+                    source,
+                    dest
                 ),
             },
             Location::Memory(addr, offs) => match dest {
@@ -1882,18 +2339,21 @@ impl Machine for MachineARM64 {
                 _ => {
                     let mut temps = vec![];
                     let src =
-                        self.location_to_reg(size, source, &mut temps, ImmType::None, true, None);
-                    self.move_location(size, src, dest);
+                        self.location_to_reg(size, source, &mut temps, ImmType::None, true, None)?;
+                    self.move_location(size, src, dest)?;
                     for r in temps {
                         self.release_gpr(r);
                     }
                 }
             },
-            _ => panic!(
+            _ => codegen_error!(
                 "singlepass can't emit move_location {:?} {:?} => {:?}",
-                size, source, dest
+                size,
+                source,
+                dest
             ),
         }
+        Ok(())
     }
     // move a location to another
     fn move_location_extend(
@@ -1903,12 +2363,12 @@ impl Machine for MachineARM64 {
         source: Location,
         size_op: Size,
         dest: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         if size_op != Size::S64 {
             unreachable!();
         }
         let mut temps = vec![];
-        let dst = self.location_to_reg(size_op, dest, &mut temps, ImmType::None, false, None);
+        let dst = self.location_to_reg(size_op, dest, &mut temps, ImmType::None, false, None)?;
         let src = match (size_val, signed, source) {
             (Size::S64, _, _) => source,
             (Size::S32, false, Location::GPR(_)) => {
@@ -1920,36 +2380,52 @@ impl Machine for MachineARM64 {
                 dst
             }
             (Size::S32, false, Location::Memory(_, _)) => {
-                self.emit_relaxed_ldr32(size_op, dst, source);
+                self.emit_relaxed_ldr32(size_op, dst, source)?;
                 dst
             }
             (Size::S32, true, Location::Memory(_, _)) => {
-                self.emit_relaxed_ldr32s(size_op, dst, source);
+                self.emit_relaxed_ldr32s(size_op, dst, source)?;
                 dst
             }
-            _ => panic!(
+            _ => codegen_error!(
                 "singlepass can't emit move_location_extend {:?} {:?} {:?} => {:?} {:?}",
-                size_val, signed, source, size_op, dest
+                size_val,
+                signed,
+                source,
+                size_op,
+                dest
             ),
         };
         if src != dst {
-            self.move_location(size_op, src, dst);
+            self.move_location(size_op, src, dst)?;
         }
         if dst != dest {
-            self.move_location(size_op, dst, dest);
+            self.move_location(size_op, dst, dest)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn load_address(&mut self, _size: Size, _reg: Location, _mem: Location) {
-        unimplemented!();
+    fn load_address(
+        &mut self,
+        _size: Size,
+        _reg: Location,
+        _mem: Location,
+    ) -> Result<(), CodegenError> {
+        codegen_error!("singlepass load_address unimplemented");
     }
     // Init the stack loc counter
-    fn init_stack_loc(&mut self, init_stack_loc_cnt: u64, last_stack_loc: Location) {
+    fn init_stack_loc(
+        &mut self,
+        init_stack_loc_cnt: u64,
+        last_stack_loc: Location,
+    ) -> Result<(), CodegenError> {
         let label = self.assembler.get_label();
         let mut temps = vec![];
-        let dest = self.acquire_temp_gpr().unwrap();
+        let dest = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         temps.push(dest.clone());
         let cnt = self.location_to_reg(
             Size::S64,
@@ -1958,7 +2434,7 @@ impl Machine for MachineARM64 {
             ImmType::None,
             true,
             None,
-        );
+        )?;
         let dest = match last_stack_loc {
             Location::GPR(_) => unreachable!(),
             Location::SIMD(_) => unreachable!(),
@@ -1973,7 +2449,9 @@ impl Machine for MachineARM64 {
                             Location::GPR(dest),
                         );
                     } else {
-                        let tmp = self.acquire_temp_gpr().unwrap();
+                        let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                            message: "singlepass cannot acquire temp gpr".to_owned(),
+                        })?;
                         self.assembler
                             .emit_mov_imm(Location::GPR(tmp), (offset as i64) as u64);
                         self.assembler.emit_sub(
@@ -1995,7 +2473,9 @@ impl Machine for MachineARM64 {
                             Location::GPR(dest),
                         );
                     } else {
-                        let tmp = self.acquire_temp_gpr().unwrap();
+                        let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                            message: "singlepass cannot acquire temp gpr".to_owned(),
+                        })?;
                         self.assembler
                             .emit_mov_imm(Location::GPR(tmp), (offset as i64) as u64);
                         self.assembler.emit_add(
@@ -2009,7 +2489,7 @@ impl Machine for MachineARM64 {
                     dest
                 }
             }
-            _ => panic!("singlepass can't emit init_stack_loc {:?}", last_stack_loc),
+            _ => codegen_error!("singlepass can't emit init_stack_loc {:?}", last_stack_loc),
         };
         self.assembler.emit_label(label);
         self.assembler
@@ -2020,16 +2500,13 @@ impl Machine for MachineARM64 {
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
     // Restore save_area
-    fn restore_saved_area(&mut self, saved_area_offset: i32) {
-        let real_delta = if saved_area_offset & 15 != 0 {
-            self.pushed = true;
-            saved_area_offset + 8
-        } else {
-            self.pushed = false;
-            saved_area_offset
-        };
+    fn restore_saved_area(&mut self, saved_area_offset: i32) -> Result<(), CodegenError> {
+        let real_delta = self.round_stack_adjust(saved_area_offset as usize) as i32;
+        // The save area ends in the middle of a 16 bytes slot when it was rounded.
+        self.pushed = real_delta != saved_area_offset;
         if self.compatible_imm(real_delta as _, ImmType::Bits12) {
             self.assembler.emit_sub(
                 Size::S64,
@@ -2038,7 +2515,9 @@ impl Machine for MachineARM64 {
                 Location::GPR(GPR::XzrSp),
             );
         } else {
-            let tmp = self.acquire_temp_gpr().unwrap();
+            let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
             self.assembler
                 .emit_mov_imm(Location::GPR(tmp), real_delta as u64);
             self.assembler.emit_sub(
@@ -2049,10 +2528,28 @@ impl Machine for MachineARM64 {
             );
             self.release_gpr(tmp);
         }
+        Ok(())
     }
     // Pop a location
-    fn pop_location(&mut self, location: Location) {
-        self.emit_pop(Size::S64, location);
+    fn pop_location(&mut self, location: Location) -> Result<(), CodegenError> {
+        self.emit_pop(Size::S64, location)
+    }
+    // Pop locations, by pairs with `ldp` once the stack is 16 bytes aligned
+    fn pop_locations(&mut self, locations: &[Location]) -> Result<(), CodegenError> {
+        let mut locations = locations;
+        while let Some((&first, rest)) = locations.split_first() {
+            match (first, rest.first()) {
+                (Location::GPR(_), Some(&second @ Location::GPR(_))) if !self.pushed => {
+                    self.emit_double_pop(Size::S64, first, second)?;
+                    locations = &rest[1..];
+                }
+                _ => {
+                    self.emit_pop(Size::S64, first)?;
+                    locations = rest;
+                }
+            }
+        }
+        Ok(())
     }
     // Create a new `MachineState` with default values.
     fn new_machine_state(&self) -> MachineState {
@@ -2072,17 +2569,21 @@ impl Machine for MachineARM64 {
         self.assembler.finalize_function();
     }
 
-    fn emit_function_prolog(&mut self) {
-        self.emit_double_push(Size::S64, Location::GPR(GPR::X29), Location::GPR(GPR::X30)); // save LR too
+    fn emit_function_prolog(&mut self) -> Result<(), CodegenError> {
+        self.emit_double_push(Size::S64, Location::GPR(GPR::X29), Location::GPR(GPR::X30))?; // save LR too
         self.emit_unwind_op(UnwindOps::Push2Regs {
             reg1: GPR::X29.to_dwarf(),
             reg2: GPR::X30.to_dwarf(),
             up_to_sp: 16,
         });
-        self.emit_double_push(Size::S64, Location::GPR(GPR::X27), Location::GPR(GPR::X28));
+        self.emit_double_push(
+            Size::S64,
+            Location::GPR(self.call_reg),
+            Location::GPR(self.vmctx_reg),
+        )?;
         self.emit_unwind_op(UnwindOps::Push2Regs {
-            reg1: GPR::X27.to_dwarf(),
-            reg2: GPR::X28.to_dwarf(),
+            reg1: self.call_reg.to_dwarf(),
+            reg2: self.vmctx_reg.to_dwarf(),
             up_to_sp: 32,
         });
         // cannot use mov, because XSP is XZR there. Need to use ADD with #0
@@ -2093,9 +2594,10 @@ impl Machine for MachineARM64 {
             Location::GPR(GPR::X29),
         );
         self.emit_unwind_op(UnwindOps::DefineNewFrame);
+        Ok(())
     }
 
-    fn emit_function_epilog(&mut self) {
+    fn emit_function_epilog(&mut self) -> Result<(), CodegenError> {
         // cannot use mov, because XSP is XZR there. Need to use ADD with #0
         self.assembler.emit_add(
             Size::S64,
@@ -2104,11 +2606,21 @@ impl Machine for MachineARM64 {
             Location::GPR(GPR::XzrSp),
         );
         self.pushed = false; // SP is restored, consider it aligned
-        self.emit_double_pop(Size::S64, Location::GPR(GPR::X27), Location::GPR(GPR::X28));
-        self.emit_double_pop(Size::S64, Location::GPR(GPR::X29), Location::GPR(GPR::X30));
+        self.emit_double_pop(
+            Size::S64,
+            Location::GPR(self.call_reg),
+            Location::GPR(self.vmctx_reg),
+        )?;
+        self.emit_double_pop(Size::S64, Location::GPR(GPR::X29), Location::GPR(GPR::X30))?;
+        Ok(())
     }
 
-    fn emit_function_return_value(&mut self, ty: WpType, canonicalize: bool, loc: Location) {
+    fn emit_function_return_value(
+        &mut self,
+        ty: WpType,
+        canonicalize: bool,
+        loc: Location,
+    ) -> Result<(), CodegenError> {
         if canonicalize {
             self.canonicalize_nan(
                 match ty {
@@ -2118,24 +2630,31 @@ impl Machine for MachineARM64 {
                 },
                 loc,
                 Location::GPR(GPR::X0),
-            );
+            )?;
         } else {
-            self.emit_relaxed_mov(Size::S64, loc, Location::GPR(GPR::X0));
+            self.emit_relaxed_mov(Size::S64, loc, Location::GPR(GPR::X0))?;
         }
+        Ok(())
     }
 
-    fn emit_function_return_float(&mut self) {
+    fn emit_function_return_float(&mut self) -> Result<(), CodegenError> {
         self.assembler
             .emit_mov(Size::S64, Location::GPR(GPR::X0), Location::SIMD(NEON::V0));
+        Ok(())
     }
 
     fn arch_supports_canonicalize_nan(&self) -> bool {
         self.assembler.arch_supports_canonicalize_nan()
     }
-    fn canonicalize_nan(&mut self, sz: Size, input: Location, output: Location) {
+    fn canonicalize_nan(
+        &mut self,
+        sz: Size,
+        input: Location,
+        output: Location,
+    ) -> Result<(), CodegenError> {
         let mut tempn = vec![];
         let mut temps = vec![];
-        let old_fpcr = self.set_default_nan(&mut temps);
+        let old_fpcr = self.set_default_nan(&mut temps)?;
         // use FMAX (input, intput) => output to automaticaly normalize the NaN
         match (sz, input, output) {
             (Size::S32, Location::SIMD(_), Location::SIMD(_)) => {
@@ -2145,21 +2664,23 @@ impl Machine for MachineARM64 {
                 self.assembler.emit_fmax(sz, input, input, output);
             }
             (Size::S32, Location::SIMD(_), _) | (Size::S64, Location::SIMD(_), _) => {
-                let tmp = self.location_to_neon(sz, output, &mut tempn, ImmType::None, false);
+                let tmp = self.location_to_neon(sz, output, &mut tempn, ImmType::None, false)?;
                 self.assembler.emit_fmax(sz, input, input, tmp);
-                self.move_location(sz, tmp, output);
+                self.move_location(sz, tmp, output)?;
             }
             (Size::S32, Location::Memory(_, _), _) | (Size::S64, Location::Memory(_, _), _) => {
-                let src = self.location_to_neon(sz, input, &mut tempn, ImmType::None, true);
-                let tmp = self.location_to_neon(sz, output, &mut tempn, ImmType::None, false);
+                let src = self.location_to_neon(sz, input, &mut tempn, ImmType::None, true)?;
+                let tmp = self.location_to_neon(sz, output, &mut tempn, ImmType::None, false)?;
                 self.assembler.emit_fmax(sz, src, src, tmp);
                 if tmp != output {
-                    self.move_location(sz, tmp, output);
+                    self.move_location(sz, tmp, output)?;
                 }
             }
-            _ => panic!(
+            _ => codegen_error!(
                 "singlepass can't emit canonicalize_nan {:?} {:?} {:?}",
-                sz, input, output
+                sz,
+                input,
+                output
             ),
         }
 
@@ -2170,6 +2691,7 @@ impl Machine for MachineARM64 {
         for r in tempn {
             self.release_simd(r);
         }
+        Ok(())
     }
 
     fn emit_illegal_op(&mut self, trap: TrapCode) {
@@ -2181,15 +2703,18 @@ impl Machine for MachineARM64 {
         self.assembler.new_dynamic_label()
     }
     fn emit_label(&mut self, label: Label) {
+        self.cached_memory_base = None;
         self.assembler.emit_label(label);
     }
     fn get_grp_for_call(&self) -> GPR {
-        GPR::X27
+        self.call_reg
     }
     fn emit_call_register(&mut self, reg: GPR) {
+        self.cached_memory_base = None;
         self.assembler.emit_call_register(reg);
     }
     fn emit_call_label(&mut self, label: Label) {
+        self.cached_memory_base = None;
         self.assembler.emit_call_label(label);
     }
     fn get_gpr_for_ret(&self) -> GPR {
@@ -2204,15 +2729,24 @@ impl Machine for MachineARM64 {
     }
 
     fn arch_emit_indirect_call_with_trampoline(&mut self, location: Location) {
+        self.cached_memory_base = None;
         self.assembler
             .arch_emit_indirect_call_with_trampoline(location);
     }
 
     fn emit_debug_breakpoint(&mut self) {
+        // the debugger may change the memory while stopped
+        self.cached_memory_base = None;
         self.assembler.emit_brk();
     }
 
-    fn emit_call_location(&mut self, location: Location) {
+    fn emit_patch_point(&mut self) {
+        // Instructions are aligned, so replacing this one by `brk` is
+        // atomic.
+        self.assembler.emit_nop();
+    }
+
+    fn emit_call_location(&mut self, location: Location) -> Result<(), CodegenError> {
         let mut temps = vec![];
         let loc = self.location_to_reg(
             Size::S64,
@@ -2220,8 +2754,9 @@ impl Machine for MachineARM64 {
             &mut temps,
             ImmType::None,
             true,
-            Some(GPR::X27),
-        );
+            Some(self.call_reg),
+        )?;
+        self.cached_memory_base = None;
         match loc {
             Location::GPR(reg) => self.assembler.emit_call_register(reg),
             _ => unreachable!(),
@@ -2229,59 +2764,107 @@ impl Machine for MachineARM64 {
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
 
-    fn location_address(&mut self, _size: Size, _source: Location, _dest: Location) {
-        unimplemented!();
+    fn location_address(
+        &mut self,
+        _size: Size,
+        _source: Location,
+        _dest: Location,
+    ) -> Result<(), CodegenError> {
+        codegen_error!("singlepass location_address unimplemented");
     }
     // logic
-    fn location_and(&mut self, _size: Size, _source: Location, _dest: Location, _flags: bool) {
-        unimplemented!();
+    fn location_and(
+        &mut self,
+        _size: Size,
+        _source: Location,
+        _dest: Location,
+        _flags: bool,
+    ) -> Result<(), CodegenError> {
+        codegen_error!("singlepass location_and unimplemented");
     }
-    fn location_xor(&mut self, _size: Size, _source: Location, _dest: Location, _flags: bool) {
-        unimplemented!();
+    fn location_xor(
+        &mut self,
+        _size: Size,
+        _source: Location,
+        _dest: Location,
+        _flags: bool,
+    ) -> Result<(), CodegenError> {
+        codegen_error!("singlepass location_xor unimplemented");
     }
-    fn location_or(&mut self, _size: Size, _source: Location, _dest: Location, _flags: bool) {
-        unimplemented!();
+    fn location_or(
+        &mut self,
+        _size: Size,
+        _source: Location,
+        _dest: Location,
+        _flags: bool,
+    ) -> Result<(), CodegenError> {
+        codegen_error!("singlepass location_or unimplemented");
     }
-    fn location_test(&mut self, _size: Size, _source: Location, _dest: Location) {
-        unimplemented!();
+    fn location_test(
+        &mut self,
+        _size: Size,
+        _source: Location,
+        _dest: Location,
+    ) -> Result<(), CodegenError> {
+        codegen_error!("singlepass location_test unimplemented");
     }
     // math
-    fn location_add(&mut self, size: Size, source: Location, dest: Location, flags: bool) {
+    fn location_add(
+        &mut self,
+        size: Size,
+        source: Location,
+        dest: Location,
+        flags: bool,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let src = self.location_to_reg(size, source, &mut temps, ImmType::Bits12, true, None);
-        let dst = self.location_to_reg(size, dest, &mut temps, ImmType::None, true, None);
+        let src = self.location_to_reg(size, source, &mut temps, ImmType::Bits12, true, None)?;
+        let dst = self.location_to_reg(size, dest, &mut temps, ImmType::None, true, None)?;
         if flags {
             self.assembler.emit_adds(size, dst, src, dst);
         } else {
             self.assembler.emit_add(size, dst, src, dst);
         }
         if dst != dest {
-            self.move_location(size, dst, dest);
+            self.move_location(size, dst, dest)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn location_sub(&mut self, size: Size, source: Location, dest: Location, flags: bool) {
+    fn location_sub(
+        &mut self,
+        size: Size,
+        source: Location,
+        dest: Location,
+        flags: bool,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let src = self.location_to_reg(size, source, &mut temps, ImmType::Bits12, true, None);
-        let dst = self.location_to_reg(size, dest, &mut temps, ImmType::None, true, None);
+        let src = self.location_to_reg(size, source, &mut temps, ImmType::Bits12, true, None)?;
+        let dst = self.location_to_reg(size, dest, &mut temps, ImmType::None, true, None)?;
         if flags {
             self.assembler.emit_subs(size, dst, src, dst);
         } else {
             self.assembler.emit_sub(size, dst, src, dst);
         }
         if dst != dest {
-            self.move_location(size, dst, dest);
+            self.move_location(size, dst, dest)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn location_cmp(&mut self, size: Size, source: Location, dest: Location) {
-        self.emit_relaxed_binop(Assembler::emit_cmp, size, source, dest, false);
+    fn location_cmp(
+        &mut self,
+        size: Size,
+        source: Location,
+        dest: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop(Assembler::emit_cmp, size, source, dest, false)
     }
     fn jmp_unconditionnal(&mut self, label: Label) {
         self.assembler.emit_b_label(label);
@@ -2304,14 +2887,65 @@ impl Machine for MachineARM64 {
     fn jmp_on_overflow(&mut self, label: Label) {
         self.assembler.emit_bcond_label_far(Condition::Cs, label);
     }
+    fn arch_supports_compare_and_branch(&self) -> bool {
+        true
+    }
+    fn emit_compare_and_branch(
+        &mut self,
+        size: Size,
+        comparison: IntegerComparison,
+        loc_a: Location,
+        loc_b: Location,
+        label: Label,
+    ) -> Result<(), CodegenError> {
+        let condition = match comparison {
+            IntegerComparison::Eq => Condition::Eq,
+            IntegerComparison::Ne => Condition::Ne,
+            IntegerComparison::LtS => Condition::Lt,
+            IntegerComparison::LeS => Condition::Le,
+            IntegerComparison::GtS => Condition::Gt,
+            IntegerComparison::GeS => Condition::Ge,
+            IntegerComparison::LtU => Condition::Cc,
+            IntegerComparison::LeU => Condition::Ls,
+            IntegerComparison::GtU => Condition::Hi,
+            IntegerComparison::GeU => Condition::Cs,
+        };
+        match (comparison, loc_a, loc_b) {
+            // a test against zero needs no cmp, the far jump is taken
+            // unless the inverse test holds
+            (IntegerComparison::Eq, Location::GPR(_), Location::Imm32(0))
+            | (IntegerComparison::Eq, Location::GPR(_), Location::Imm64(0)) => {
+                let cont = self.assembler.get_label();
+                self.assembler.emit_cbnz_label(size, loc_a, cont);
+                self.assembler.emit_b_label(label);
+                self.assembler.emit_label(cont);
+            }
+            (IntegerComparison::Ne, Location::GPR(_), Location::Imm32(0))
+            | (IntegerComparison::Ne, Location::GPR(_), Location::Imm64(0)) => {
+                let cont = self.assembler.get_label();
+                self.assembler.emit_cbz_label(size, loc_a, cont);
+                self.assembler.emit_b_label(label);
+                self.assembler.emit_label(cont);
+            }
+            _ => {
+                self.emit_relaxed_cmp(size, loc_b, loc_a)?;
+                self.assembler.emit_bcond_label_far(condition, label);
+            }
+        }
+        Ok(())
+    }
 
     // jmp table
-    fn emit_jmp_to_jumptable(&mut self, label: Label, cond: Location) {
-        let tmp1 = self.acquire_temp_gpr().unwrap();
-        let tmp2 = self.acquire_temp_gpr().unwrap();
+    fn emit_jmp_to_jumptable(&mut self, label: Label, cond: Location) -> Result<(), CodegenError> {
+        let tmp1 = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let tmp2 = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
 
         self.assembler.emit_load_label(tmp1, label);
-        self.move_location(Size::S32, cond, Location::GPR(tmp2));
+        self.move_location(Size::S32, cond, Location::GPR(tmp2))?;
 
         self.assembler.emit_add_lsl(
             Size::S64,
@@ -2323,21 +2957,27 @@ impl Machine for MachineARM64 {
         self.assembler.emit_b_register(tmp2);
         self.release_gpr(tmp2);
         self.release_gpr(tmp1);
+        Ok(())
     }
 
-    fn align_for_loop(&mut self) {
-        // noting to do on ARM64
+    fn align_for_loop(&mut self, alignment: Option<usize>) {
+        // Not aligned by default on ARM64, where instructions are 4 bytes.
+        if let Some(alignment) = alignment {
+            while self.assembler.get_offset().0 % alignment != 0 {
+                self.assembler.emit_nop();
+            }
+        }
     }
 
     fn emit_ret(&mut self) {
         self.assembler.emit_ret();
     }
 
-    fn emit_push(&mut self, size: Size, loc: Location) {
-        self.emit_push(size, loc);
+    fn emit_push(&mut self, size: Size, loc: Location) -> Result<(), CodegenError> {
+        self.emit_push(size, loc)
     }
-    fn emit_pop(&mut self, size: Size, loc: Location) {
-        self.emit_pop(size, loc);
+    fn emit_pop(&mut self, size: Size, loc: Location) -> Result<(), CodegenError> {
+        self.emit_pop(size, loc)
     }
 
     fn emit_memory_fence(&mut self) {
@@ -2351,12 +2991,14 @@ impl Machine for MachineARM64 {
         _source: Location,
         _size_op: Size,
         _dest: Location,
-    ) {
-        unimplemented!();
+    ) -> Result<(), CodegenError> {
+        codegen_error!("singlepass location_neg unimplemented");
     }
 
-    fn emit_imul_imm32(&mut self, size: Size, imm32: u32, gpr: GPR) {
-        let tmp = self.acquire_temp_gpr().unwrap();
+    fn emit_imul_imm32(&mut self, size: Size, imm32: u32, gpr: GPR) -> Result<(), CodegenError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         self.assembler
             .emit_mov_imm(Location::GPR(tmp), imm32 as u64);
         self.assembler.emit_mul(
@@ -2366,14 +3008,25 @@ impl Machine for MachineARM64 {
             Location::GPR(gpr),
         );
         self.release_gpr(tmp);
+        Ok(())
     }
 
     // relaxed binop based...
-    fn emit_relaxed_mov(&mut self, sz: Size, src: Location, dst: Location) {
-        self.emit_relaxed_binop(Assembler::emit_mov, sz, src, dst, true);
+    fn emit_relaxed_mov(
+        &mut self,
+        sz: Size,
+        src: Location,
+        dst: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop(Assembler::emit_mov, sz, src, dst, true)
     }
-    fn emit_relaxed_cmp(&mut self, sz: Size, src: Location, dst: Location) {
-        self.emit_relaxed_binop(Assembler::emit_cmp, sz, src, dst, false);
+    fn emit_relaxed_cmp(
+        &mut self,
+        sz: Size,
+        src: Location,
+        dst: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop(Assembler::emit_cmp, sz, src, dst, false)
     }
     fn emit_relaxed_zero_extension(
         &mut self,
@@ -2381,8 +3034,8 @@ impl Machine for MachineARM64 {
         _src: Location,
         _sz_dst: Size,
         _dst: Location,
-    ) {
-        unimplemented!();
+    ) -> Result<(), CodegenError> {
+        codegen_error!("singlepass emit_relaxed_zero_extension unimplemented");
     }
     fn emit_relaxed_sign_extension(
         &mut self,
@@ -2390,19 +3043,20 @@ impl Machine for MachineARM64 {
         src: Location,
         sz_dst: Size,
         dst: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         match (src, dst) {
             (Location::Memory(_, _), Location::GPR(_)) => match sz_src {
-                Size::S8 => self.emit_relaxed_ldr8s(sz_dst, dst, src),
-                Size::S16 => self.emit_relaxed_ldr16s(sz_dst, dst, src),
-                Size::S32 => self.emit_relaxed_ldr32s(sz_dst, dst, src),
+                Size::S8 => self.emit_relaxed_ldr8s(sz_dst, dst, src)?,
+                Size::S16 => self.emit_relaxed_ldr16s(sz_dst, dst, src)?,
+                Size::S32 => self.emit_relaxed_ldr32s(sz_dst, dst, src)?,
                 _ => unreachable!(),
             },
             _ => {
                 let mut temps = vec![];
-                let src = self.location_to_reg(sz_src, src, &mut temps, ImmType::None, true, None);
+                let src =
+                    self.location_to_reg(sz_src, src, &mut temps, ImmType::None, true, None)?;
                 let dest =
-                    self.location_to_reg(sz_dst, dst, &mut temps, ImmType::None, false, None);
+                    self.location_to_reg(sz_dst, dst, &mut temps, ImmType::None, false, None)?;
                 match sz_src {
                     Size::S8 => self.assembler.emit_sxtb(sz_dst, src, dest),
                     Size::S16 => self.assembler.emit_sxth(sz_dst, src, dest),
@@ -2410,16 +3064,22 @@ impl Machine for MachineARM64 {
                     _ => unreachable!(),
                 };
                 if dst != dest {
-                    self.move_location(sz_dst, dest, dst);
+                    self.move_location(sz_dst, dest, dst)?;
                 }
                 for r in temps {
                     self.release_gpr(r);
                 }
             }
         }
+        Ok(())
     }
 
-    fn emit_binop_add32(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn emit_binop_add32(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_add,
             Size::S32,
@@ -2427,9 +3087,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Bits12,
-        );
+        )
     }
-    fn emit_binop_sub32(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn emit_binop_sub32(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_sub,
             Size::S32,
@@ -2437,9 +3102,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Bits12,
-        );
+        )
     }
-    fn emit_binop_mul32(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn emit_binop_mul32(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_mul,
             Size::S32,
@@ -2447,7 +3117,7 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::None,
-        );
+        )
     }
     fn emit_binop_udiv32(
         &mut self,
@@ -2456,23 +3126,23 @@ impl Machine for MachineARM64 {
         ret: Location,
         integer_division_by_zero: Label,
         _integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         let mut temps = vec![];
-        let src1 = self.location_to_reg(Size::S32, loc_a, &mut temps, ImmType::None, true, None);
-        let src2 = self.location_to_reg(Size::S32, loc_b, &mut temps, ImmType::None, true, None);
-        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None);
+        let src1 = self.location_to_reg(Size::S32, loc_a, &mut temps, ImmType::None, true, None)?;
+        let src2 = self.location_to_reg(Size::S32, loc_b, &mut temps, ImmType::None, true, None)?;
+        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None)?;
 
         self.assembler
             .emit_cbz_label(Size::S32, src2, integer_division_by_zero);
-        let offset = self.mark_instruction_with_trap_code(TrapCode::IntegerOverflow);
+        let offset = self.mark_instruction_with_trap_code(IntegerDivision::UnsignedDiv.trap_code());
         self.assembler.emit_udiv(Size::S32, src1, src2, dest);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
-        offset
+        Ok(offset)
     }
     fn emit_binop_sdiv32(
         &mut self,
@@ -2481,11 +3151,11 @@ impl Machine for MachineARM64 {
         ret: Location,
         integer_division_by_zero: Label,
         integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         let mut temps = vec![];
-        let src1 = self.location_to_reg(Size::S32, loc_a, &mut temps, ImmType::None, true, None);
-        let src2 = self.location_to_reg(Size::S32, loc_b, &mut temps, ImmType::None, true, None);
-        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None);
+        let src1 = self.location_to_reg(Size::S32, loc_a, &mut temps, ImmType::None, true, None)?;
+        let src2 = self.location_to_reg(Size::S32, loc_b, &mut temps, ImmType::None, true, None)?;
+        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None)?;
 
         self.assembler
             .emit_cbz_label(Size::S32, src2, integer_division_by_zero);
@@ -2497,7 +3167,7 @@ impl Machine for MachineARM64 {
             ImmType::None,
             true,
             None,
-        );
+        )?;
         self.assembler.emit_cmp(Size::S32, tmp, src1);
         self.assembler
             .emit_bcond_label(Condition::Ne, label_nooverflow);
@@ -2505,16 +3175,16 @@ impl Machine for MachineARM64 {
         self.assembler.emit_cmp(Size::S32, tmp, src2);
         self.assembler
             .emit_bcond_label_far(Condition::Eq, integer_overflow);
-        let offset = self.mark_instruction_with_trap_code(TrapCode::IntegerOverflow);
+        let offset = self.mark_instruction_with_trap_code(IntegerDivision::SignedDiv.trap_code());
         self.assembler.emit_label(label_nooverflow);
         self.assembler.emit_sdiv(Size::S32, src1, src2, dest);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
-        offset
+        Ok(offset)
     }
     fn emit_binop_urem32(
         &mut self,
@@ -2523,13 +3193,15 @@ impl Machine for MachineARM64 {
         ret: Location,
         integer_division_by_zero: Label,
         _integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         let mut temps = vec![];
-        let src1 = self.location_to_reg(Size::S32, loc_a, &mut temps, ImmType::None, true, None);
-        let src2 = self.location_to_reg(Size::S32, loc_b, &mut temps, ImmType::None, true, None);
-        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None);
+        let src1 = self.location_to_reg(Size::S32, loc_a, &mut temps, ImmType::None, true, None)?;
+        let src2 = self.location_to_reg(Size::S32, loc_b, &mut temps, ImmType::None, true, None)?;
+        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None)?;
         let dest = if dest == src1 || dest == src2 {
-            let tmp = self.acquire_temp_gpr().unwrap();
+            let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
             temps.push(tmp.clone());
             self.assembler.emit_mov(Size::S32, dest, Location::GPR(tmp));
             Location::GPR(tmp)
@@ -2538,17 +3210,17 @@ impl Machine for MachineARM64 {
         };
         self.assembler
             .emit_cbz_label(Size::S32, src2, integer_division_by_zero);
-        let offset = self.mark_instruction_with_trap_code(TrapCode::IntegerOverflow);
+        let offset = self.mark_instruction_with_trap_code(IntegerDivision::UnsignedRem.trap_code());
         self.assembler.emit_udiv(Size::S32, src1, src2, dest);
         // unsigned remainder : src1 - (src1/src2)*src2
         self.assembler.emit_msub(Size::S32, dest, src2, src1, dest);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
-        offset
+        Ok(offset)
     }
     fn emit_binop_srem32(
         &mut self,
@@ -2557,13 +3229,15 @@ impl Machine for MachineARM64 {
         ret: Location,
         integer_division_by_zero: Label,
         _integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         let mut temps = vec![];
-        let src1 = self.location_to_reg(Size::S32, loc_a, &mut temps, ImmType::None, true, None);
-        let src2 = self.location_to_reg(Size::S32, loc_b, &mut temps, ImmType::None, true, None);
-        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None);
+        let src1 = self.location_to_reg(Size::S32, loc_a, &mut temps, ImmType::None, true, None)?;
+        let src2 = self.location_to_reg(Size::S32, loc_b, &mut temps, ImmType::None, true, None)?;
+        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None)?;
         let dest = if dest == src1 || dest == src2 {
-            let tmp = self.acquire_temp_gpr().unwrap();
+            let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
             temps.push(tmp.clone());
             self.assembler.emit_mov(Size::S32, dest, Location::GPR(tmp));
             Location::GPR(tmp)
@@ -2572,19 +3246,24 @@ impl Machine for MachineARM64 {
         };
         self.assembler
             .emit_cbz_label(Size::S32, src2, integer_division_by_zero);
-        let offset = self.mark_instruction_with_trap_code(TrapCode::IntegerOverflow);
+        let offset = self.mark_instruction_with_trap_code(IntegerDivision::SignedRem.trap_code());
         self.assembler.emit_sdiv(Size::S32, src1, src2, dest);
         // unsigned remainder : src1 - (src1/src2)*src2
         self.assembler.emit_msub(Size::S32, dest, src2, src1, dest);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
-        offset
+        Ok(offset)
     }
-    fn emit_binop_and32(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn emit_binop_and32(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_and,
             Size::S32,
@@ -2592,9 +3271,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Logical32,
-        );
+        )
     }
-    fn emit_binop_or32(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn emit_binop_or32(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_or,
             Size::S32,
@@ -2602,9 +3286,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Logical32,
-        );
+        )
     }
-    fn emit_binop_xor32(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn emit_binop_xor32(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_eor,
             Size::S32,
@@ -2612,95 +3301,114 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Logical32,
-        );
+        )
     }
-    fn i32_cmp_ge_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Ge, loc_a, loc_b, ret);
+    fn i32_cmp_ge_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Ge, loc_a, loc_b, ret)
     }
-    fn i32_cmp_gt_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Gt, loc_a, loc_b, ret);
+    fn i32_cmp_gt_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Gt, loc_a, loc_b, ret)
     }
-    fn i32_cmp_le_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Le, loc_a, loc_b, ret);
+    fn i32_cmp_le_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Le, loc_a, loc_b, ret)
     }
-    fn i32_cmp_lt_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Lt, loc_a, loc_b, ret);
+    fn i32_cmp_lt_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Lt, loc_a, loc_b, ret)
     }
-    fn i32_cmp_ge_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Cs, loc_a, loc_b, ret);
+    fn i32_cmp_ge_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Cs, loc_a, loc_b, ret)
     }
-    fn i32_cmp_gt_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Hi, loc_a, loc_b, ret);
+    fn i32_cmp_gt_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Hi, loc_a, loc_b, ret)
     }
-    fn i32_cmp_le_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Ls, loc_a, loc_b, ret);
+    fn i32_cmp_le_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Ls, loc_a, loc_b, ret)
     }
-    fn i32_cmp_lt_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Cc, loc_a, loc_b, ret);
+    fn i32_cmp_lt_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Cc, loc_a, loc_b, ret)
     }
-    fn i32_cmp_ne(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Ne, loc_a, loc_b, ret);
+    fn i32_cmp_ne(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Ne, loc_a, loc_b, ret)
     }
-    fn i32_cmp_eq(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Eq, loc_a, loc_b, ret);
+    fn i32_cmp_eq(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Eq, loc_a, loc_b, ret)
     }
-    fn i32_clz(&mut self, src: Location, dst: Location) {
-        self.emit_relaxed_binop(Assembler::emit_clz, Size::S32, src, dst, true);
+    fn i32_clz(&mut self, src: Location, dst: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop(Assembler::emit_clz, Size::S32, src, dst, true)
     }
-    fn i32_ctz(&mut self, src: Location, dst: Location) {
+    fn i32_ctz(&mut self, src: Location, dst: Location) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let src = self.location_to_reg(Size::S32, src, &mut temps, ImmType::None, true, None);
-        let dest = self.location_to_reg(Size::S32, dst, &mut temps, ImmType::None, false, None);
+        let src = self.location_to_reg(Size::S32, src, &mut temps, ImmType::None, true, None)?;
+        let dest = self.location_to_reg(Size::S32, dst, &mut temps, ImmType::None, false, None)?;
         self.assembler.emit_rbit(Size::S32, src, dest);
         self.assembler.emit_clz(Size::S32, dest, dest);
         if dst != dest {
-            self.move_location(Size::S32, dest, dst);
+            self.move_location(Size::S32, dest, dst)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn i32_popcnt(&mut self, loc: Location, ret: Location) {
-        // no opcode for that.
-        // 2 solutions: using NEON CNT, that count bits per Byte, or using clz with some shift and loop
-        let mut temps = vec![];
-        let src = self.location_to_reg(Size::S32, loc, &mut temps, ImmType::None, true, None);
-        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None);
-        let src = if src == loc {
-            let tmp = self.acquire_temp_gpr().unwrap();
-            temps.push(tmp.clone());
-            self.assembler.emit_mov(Size::S32, src, Location::GPR(tmp));
-            Location::GPR(tmp)
-        } else {
-            src
-        };
-        let tmp = {
-            let tmp = self.acquire_temp_gpr().unwrap();
-            temps.push(tmp.clone());
-            Location::GPR(tmp)
-        };
-        let label_loop = self.assembler.get_label();
-        let label_exit = self.assembler.get_label();
-        self.assembler
-            .emit_mov(Size::S32, Location::GPR(GPR::XzrSp), dest); // 0 => dest
-        self.assembler.emit_cbz_label(Size::S32, src, label_exit); // src==0, exit
-        self.assembler.emit_label(label_loop); // loop:
-        self.assembler
-            .emit_add(Size::S32, dest, Location::Imm8(1), dest); // inc dest
-        self.assembler.emit_clz(Size::S32, src, tmp); // clz src => tmp
-        self.assembler
-            .emit_add(Size::S32, tmp, Location::Imm8(1), tmp); // inc tmp
-        self.assembler.emit_lsl(Size::S32, src, tmp, src); // src << tmp => src
-        self.assembler.emit_cbnz_label(Size::S32, src, label_loop); // if src!=0 goto loop
-        self.assembler.emit_label(label_exit);
-        if ret != dest {
-            self.move_location(Size::S32, dest, ret);
-        }
-        for r in temps {
-            self.release_gpr(r);
-        }
+    fn i32_popcnt(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_popcnt(Size::S32, loc, ret)
     }
-    fn i32_shl(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn i32_shl(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_lsl,
             Size::S32,
@@ -2708,9 +3416,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Shift32No0,
-        );
+        )
     }
-    fn i32_shr(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn i32_shr(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_lsr,
             Size::S32,
@@ -2718,9 +3431,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Shift32No0,
-        );
+        )
     }
-    fn i32_sar(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn i32_sar(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_asr,
             Size::S32,
@@ -2728,9 +3446,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Shift32No0,
-        );
+        )
     }
-    fn i32_rol(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn i32_rol(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
         let src2 = match loc_b {
             Location::Imm8(imm) => Location::Imm8(32 - (imm & 31)),
@@ -2744,9 +3467,9 @@ impl Machine for MachineARM64 {
                     ImmType::None,
                     true,
                     None,
-                );
+                )?;
                 let tmp2 =
-                    self.location_to_reg(Size::S32, loc_b, &mut temps, ImmType::None, true, None);
+                    self.location_to_reg(Size::S32, loc_b, &mut temps, ImmType::None, true, None)?;
                 self.assembler.emit_sub(Size::S32, tmp1, tmp2, tmp1);
                 tmp1
             }
@@ -2758,12 +3481,18 @@ impl Machine for MachineARM64 {
             src2,
             ret,
             ImmType::Shift32No0,
-        );
+        )?;
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn i32_ror(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn i32_ror(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_ror,
             Size::S32,
@@ -2771,7 +3500,7 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Shift32No0,
-        );
+        )
     }
     fn i32_load(
         &mut self,
@@ -2782,7 +3511,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -2792,10 +3521,8 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_ldr32(Size::S32, ret, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldr32(Size::S32, ret, Location::Memory(addr, 0)),
+        )
     }
     fn i32_load_8u(
         &mut self,
@@ -2806,7 +3533,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -2816,10 +3543,8 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_ldr8(Size::S32, ret, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldr8(Size::S32, ret, Location::Memory(addr, 0)),
+        )
     }
     fn i32_load_8s(
         &mut self,
@@ -2830,7 +3555,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -2840,10 +3565,8 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_ldr8s(Size::S32, ret, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldr8s(Size::S32, ret, Location::Memory(addr, 0)),
+        )
     }
     fn i32_load_16u(
         &mut self,
@@ -2854,7 +3577,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -2864,10 +3587,8 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_ldr16(Size::S32, ret, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldr16(Size::S32, ret, Location::Memory(addr, 0)),
+        )
     }
     fn i32_load_16s(
         &mut self,
@@ -2878,7 +3599,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -2888,83 +3609,87 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_ldr16s(Size::S32, ret, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldr16s(Size::S32, ret, Location::Memory(addr, 0)),
+        )
     }
     fn i32_atomic_load(
         &mut self,
-        _addr: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
-    }
-    fn i32_atomic_load_8u(
-        &mut self,
-        _addr: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
-    }
-    fn i32_atomic_load_16u(
-        &mut self,
-        _addr: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
-    }
-    fn i32_save(
-        &mut self,
-        target_value: Location,
+        addr: Location,
         memarg: &MemoryImmediate,
-        target_addr: Location,
+        ret: Location,
         need_check: bool,
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
-            target_addr,
+            addr,
             memarg,
-            false,
+            true,
             4,
             need_check,
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_str32(target_value, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldar(Size::S32, Size::S32, ret, addr),
+        )
     }
-    fn i32_save_8(
+    fn i32_atomic_load_8u(
         &mut self,
-        target_value: Location,
+        addr: Location,
         memarg: &MemoryImmediate,
-        target_addr: Location,
+        ret: Location,
         need_check: bool,
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
-            target_addr,
+            addr,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_ldar(Size::S8, Size::S32, ret, addr),
+        )
+    }
+    fn i32_atomic_load_16u(
+        &mut self,
+        addr: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            addr,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_ldar(Size::S16, Size::S32, ret, addr),
+        )
+    }
+    fn i32_save(
+        &mut self,
+        target_value: Location,
+        memarg: &MemoryImmediate,
+        target_addr: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target_addr,
             memarg,
             false,
             4,
@@ -2972,10 +3697,30 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_str8(target_value, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_str32(target_value, Location::Memory(addr, 0)),
+        )
+    }
+    fn i32_save_8(
+        &mut self,
+        target_value: Location,
+        memarg: &MemoryImmediate,
+        target_addr: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target_addr,
+            memarg,
+            false,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_str8(target_value, Location::Memory(addr, 0)),
+        )
     }
     fn i32_save_16(
         &mut self,
@@ -2986,7 +3731,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -2996,343 +3741,720 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_str16(target_value, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_str16(target_value, Location::Memory(addr, 0)),
+        )
     }
     fn i32_atomic_save(
         &mut self,
-        _value: Location,
-        _memarg: &MemoryImmediate,
-        _target_addr: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        value: Location,
+        memarg: &MemoryImmediate,
+        target_addr: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target_addr,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_stlr(Size::S32, value, addr),
+        )
     }
     fn i32_atomic_save_8(
         &mut self,
-        _value: Location,
-        _memarg: &MemoryImmediate,
-        _target_addr: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        value: Location,
+        memarg: &MemoryImmediate,
+        target_addr: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target_addr,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_stlr(Size::S8, value, addr),
+        )
     }
     fn i32_atomic_save_16(
         &mut self,
-        _value: Location,
-        _memarg: &MemoryImmediate,
-        _target_addr: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        value: Location,
+        memarg: &MemoryImmediate,
+        target_addr: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target_addr,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_stlr(Size::S16, value, addr),
+        )
     }
     // i32 atomic Add with i32
     fn i32_atomic_add(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_add),
+                    Size::S32,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i32 atomic Add with u8
     fn i32_atomic_add_8u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_add),
+                    Size::S8,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i32 atomic Add with u16
     fn i32_atomic_add_16u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_add),
+                    Size::S16,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i32 atomic Sub with i32
     fn i32_atomic_sub(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_sub),
+                    Size::S32,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i32 atomic Sub with u8
     fn i32_atomic_sub_8u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
-    }
-    // i32 atomic Sub with u16
-    fn i32_atomic_sub_16u(
-        &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_sub),
+                    Size::S8,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
+    }
+    // i32 atomic Sub with u16
+    fn i32_atomic_sub_16u(
+        &mut self,
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_sub),
+                    Size::S16,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i32 atomic And with i32
     fn i32_atomic_and(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_and),
+                    Size::S32,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i32 atomic And with u8
     fn i32_atomic_and_8u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_and),
+                    Size::S8,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i32 atomic And with u16
     fn i32_atomic_and_16u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_and),
+                    Size::S16,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i32 atomic Or with i32
     fn i32_atomic_or(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_or),
+                    Size::S32,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i32 atomic Or with u8
     fn i32_atomic_or_8u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_or),
+                    Size::S8,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i32 atomic Or with u16
     fn i32_atomic_or_16u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_or),
+                    Size::S16,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i32 atomic Xor with i32
     fn i32_atomic_xor(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_eor),
+                    Size::S32,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i32 atomic Xor with u8
     fn i32_atomic_xor_8u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_eor),
+                    Size::S8,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i32 atomic Xor with u16
     fn i32_atomic_xor_16u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_eor),
+                    Size::S16,
+                    Size::S32,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i32 atomic Exchange with i32
     fn i32_atomic_xchg(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_atomic_rmw(None, Size::S32, Size::S32, loc, ret, addr),
+        )
     }
     // i32 atomic Exchange with u8
     fn i32_atomic_xchg_8u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_atomic_rmw(None, Size::S8, Size::S32, loc, ret, addr),
+        )
     }
     // i32 atomic Exchange with u16
     fn i32_atomic_xchg_16u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_atomic_rmw(None, Size::S16, Size::S32, loc, ret, addr),
+        )
     }
     // i32 atomic Exchange with i32
     fn i32_atomic_cmpxchg(
         &mut self,
-        _new: Location,
-        _cmp: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        new: Location,
+        cmp: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_cmpxchg(Size::S32, Size::S32, new, cmp, ret, addr)
+            },
+        )
     }
     // i32 atomic Exchange with u8
     fn i32_atomic_cmpxchg_8u(
         &mut self,
-        _new: Location,
-        _cmp: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        new: Location,
+        cmp: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_atomic_cmpxchg(Size::S8, Size::S32, new, cmp, ret, addr),
+        )
     }
     // i32 atomic Exchange with u16
     fn i32_atomic_cmpxchg_16u(
         &mut self,
-        _new: Location,
-        _cmp: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        new: Location,
+        cmp: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_cmpxchg(Size::S16, Size::S32, new, cmp, ret, addr)
+            },
+        )
     }
 
     fn emit_call_with_reloc(
@@ -3343,6 +4465,7 @@ impl Machine for MachineARM64 {
         let mut relocations = vec![];
         let next = self.get_label();
         let reloc_at = self.assembler.get_offset().0;
+        self.cached_memory_base = None;
         self.emit_label(next); // this is to be sure the current imm26 value is 0
         self.assembler.emit_call_label(next);
         relocations.push(Relocation {
@@ -3354,7 +4477,12 @@ impl Machine for MachineARM64 {
         relocations
     }
 
-    fn emit_binop_add64(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn emit_binop_add64(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_add,
             Size::S64,
@@ -3362,9 +4490,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Bits12,
-        );
+        )
     }
-    fn emit_binop_sub64(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn emit_binop_sub64(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_sub,
             Size::S64,
@@ -3372,9 +4505,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Bits12,
-        );
+        )
     }
-    fn emit_binop_mul64(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn emit_binop_mul64(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_mul,
             Size::S64,
@@ -3382,7 +4520,7 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::None,
-        );
+        )
     }
     fn emit_binop_udiv64(
         &mut self,
@@ -3391,23 +4529,23 @@ impl Machine for MachineARM64 {
         ret: Location,
         integer_division_by_zero: Label,
         _integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         let mut temps = vec![];
-        let src1 = self.location_to_reg(Size::S64, loc_a, &mut temps, ImmType::None, true, None);
-        let src2 = self.location_to_reg(Size::S64, loc_b, &mut temps, ImmType::None, true, None);
-        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None);
+        let src1 = self.location_to_reg(Size::S64, loc_a, &mut temps, ImmType::None, true, None)?;
+        let src2 = self.location_to_reg(Size::S64, loc_b, &mut temps, ImmType::None, true, None)?;
+        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None)?;
 
         self.assembler
             .emit_cbz_label(Size::S64, src2, integer_division_by_zero);
-        let offset = self.mark_instruction_with_trap_code(TrapCode::IntegerOverflow);
+        let offset = self.mark_instruction_with_trap_code(IntegerDivision::UnsignedDiv.trap_code());
         self.assembler.emit_udiv(Size::S64, src1, src2, dest);
         if ret != dest {
-            self.move_location(Size::S64, dest, ret);
+            self.move_location(Size::S64, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
-        offset
+        Ok(offset)
     }
     fn emit_binop_sdiv64(
         &mut self,
@@ -3416,11 +4554,11 @@ impl Machine for MachineARM64 {
         ret: Location,
         integer_division_by_zero: Label,
         integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         let mut temps = vec![];
-        let src1 = self.location_to_reg(Size::S64, loc_a, &mut temps, ImmType::None, true, None);
-        let src2 = self.location_to_reg(Size::S64, loc_b, &mut temps, ImmType::None, true, None);
-        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None);
+        let src1 = self.location_to_reg(Size::S64, loc_a, &mut temps, ImmType::None, true, None)?;
+        let src2 = self.location_to_reg(Size::S64, loc_b, &mut temps, ImmType::None, true, None)?;
+        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None)?;
 
         self.assembler
             .emit_cbz_label(Size::S64, src2, integer_division_by_zero);
@@ -3432,7 +4570,7 @@ impl Machine for MachineARM64 {
             ImmType::None,
             true,
             None,
-        );
+        )?;
         self.assembler.emit_cmp(Size::S64, tmp, src1);
         self.assembler
             .emit_bcond_label(Condition::Ne, label_nooverflow);
@@ -3440,16 +4578,16 @@ impl Machine for MachineARM64 {
         self.assembler.emit_cmp(Size::S64, tmp, src2);
         self.assembler
             .emit_bcond_label_far(Condition::Eq, integer_overflow);
-        let offset = self.mark_instruction_with_trap_code(TrapCode::IntegerOverflow);
+        let offset = self.mark_instruction_with_trap_code(IntegerDivision::SignedDiv.trap_code());
         self.assembler.emit_label(label_nooverflow);
         self.assembler.emit_sdiv(Size::S64, src1, src2, dest);
         if ret != dest {
-            self.move_location(Size::S64, dest, ret);
+            self.move_location(Size::S64, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
-        offset
+        Ok(offset)
     }
     fn emit_binop_urem64(
         &mut self,
@@ -3458,13 +4596,15 @@ impl Machine for MachineARM64 {
         ret: Location,
         integer_division_by_zero: Label,
         _integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         let mut temps = vec![];
-        let src1 = self.location_to_reg(Size::S64, loc_a, &mut temps, ImmType::None, true, None);
-        let src2 = self.location_to_reg(Size::S64, loc_b, &mut temps, ImmType::None, true, None);
-        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None);
+        let src1 = self.location_to_reg(Size::S64, loc_a, &mut temps, ImmType::None, true, None)?;
+        let src2 = self.location_to_reg(Size::S64, loc_b, &mut temps, ImmType::None, true, None)?;
+        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None)?;
         let dest = if dest == src1 || dest == src2 {
-            let tmp = self.acquire_temp_gpr().unwrap();
+            let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
             temps.push(tmp.clone());
             self.assembler.emit_mov(Size::S32, dest, Location::GPR(tmp));
             Location::GPR(tmp)
@@ -3473,17 +4613,17 @@ impl Machine for MachineARM64 {
         };
         self.assembler
             .emit_cbz_label(Size::S64, src2, integer_division_by_zero);
-        let offset = self.mark_instruction_with_trap_code(TrapCode::IntegerOverflow);
+        let offset = self.mark_instruction_with_trap_code(IntegerDivision::UnsignedRem.trap_code());
         self.assembler.emit_udiv(Size::S64, src1, src2, dest);
         // unsigned remainder : src1 - (src1/src2)*src2
         self.assembler.emit_msub(Size::S64, dest, src2, src1, dest);
         if ret != dest {
-            self.move_location(Size::S64, dest, ret);
+            self.move_location(Size::S64, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
-        offset
+        Ok(offset)
     }
     fn emit_binop_srem64(
         &mut self,
@@ -3492,13 +4632,15 @@ impl Machine for MachineARM64 {
         ret: Location,
         integer_division_by_zero: Label,
         _integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         let mut temps = vec![];
-        let src1 = self.location_to_reg(Size::S64, loc_a, &mut temps, ImmType::None, true, None);
-        let src2 = self.location_to_reg(Size::S64, loc_b, &mut temps, ImmType::None, true, None);
-        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None);
+        let src1 = self.location_to_reg(Size::S64, loc_a, &mut temps, ImmType::None, true, None)?;
+        let src2 = self.location_to_reg(Size::S64, loc_b, &mut temps, ImmType::None, true, None)?;
+        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None)?;
         let dest = if dest == src1 || dest == src2 {
-            let tmp = self.acquire_temp_gpr().unwrap();
+            let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
             temps.push(tmp.clone());
             self.assembler.emit_mov(Size::S64, dest, Location::GPR(tmp));
             Location::GPR(tmp)
@@ -3507,19 +4649,24 @@ impl Machine for MachineARM64 {
         };
         self.assembler
             .emit_cbz_label(Size::S64, src2, integer_division_by_zero);
-        let offset = self.mark_instruction_with_trap_code(TrapCode::IntegerOverflow);
+        let offset = self.mark_instruction_with_trap_code(IntegerDivision::SignedRem.trap_code());
         self.assembler.emit_sdiv(Size::S64, src1, src2, dest);
         // unsigned remainder : src1 - (src1/src2)*src2
         self.assembler.emit_msub(Size::S64, dest, src2, src1, dest);
         if ret != dest {
-            self.move_location(Size::S64, dest, ret);
+            self.move_location(Size::S64, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
-        offset
+        Ok(offset)
     }
-    fn emit_binop_and64(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn emit_binop_and64(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_and,
             Size::S64,
@@ -3527,9 +4674,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Logical64,
-        );
+        )
     }
-    fn emit_binop_or64(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn emit_binop_or64(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_or,
             Size::S64,
@@ -3537,9 +4689,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Logical64,
-        );
+        )
     }
-    fn emit_binop_xor64(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn emit_binop_xor64(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_eor,
             Size::S64,
@@ -3547,93 +4704,114 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Logical64,
-        );
+        )
     }
-    fn i64_cmp_ge_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Ge, loc_a, loc_b, ret);
+    fn i64_cmp_ge_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Ge, loc_a, loc_b, ret)
     }
-    fn i64_cmp_gt_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Gt, loc_a, loc_b, ret);
+    fn i64_cmp_gt_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Gt, loc_a, loc_b, ret)
     }
-    fn i64_cmp_le_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Le, loc_a, loc_b, ret);
+    fn i64_cmp_le_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Le, loc_a, loc_b, ret)
     }
-    fn i64_cmp_lt_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Lt, loc_a, loc_b, ret);
+    fn i64_cmp_lt_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Lt, loc_a, loc_b, ret)
     }
-    fn i64_cmp_ge_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Cs, loc_a, loc_b, ret);
+    fn i64_cmp_ge_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Cs, loc_a, loc_b, ret)
     }
-    fn i64_cmp_gt_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Hi, loc_a, loc_b, ret);
+    fn i64_cmp_gt_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Hi, loc_a, loc_b, ret)
     }
-    fn i64_cmp_le_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Ls, loc_a, loc_b, ret);
+    fn i64_cmp_le_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Ls, loc_a, loc_b, ret)
     }
-    fn i64_cmp_lt_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Cc, loc_a, loc_b, ret);
+    fn i64_cmp_lt_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Cc, loc_a, loc_b, ret)
     }
-    fn i64_cmp_ne(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Ne, loc_a, loc_b, ret);
+    fn i64_cmp_ne(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Ne, loc_a, loc_b, ret)
     }
-    fn i64_cmp_eq(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Eq, loc_a, loc_b, ret);
+    fn i64_cmp_eq(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Eq, loc_a, loc_b, ret)
     }
-    fn i64_clz(&mut self, src: Location, dst: Location) {
-        self.emit_relaxed_binop(Assembler::emit_clz, Size::S64, src, dst, true);
+    fn i64_clz(&mut self, src: Location, dst: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop(Assembler::emit_clz, Size::S64, src, dst, true)
     }
-    fn i64_ctz(&mut self, src: Location, dst: Location) {
+    fn i64_ctz(&mut self, src: Location, dst: Location) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let src = self.location_to_reg(Size::S64, src, &mut temps, ImmType::None, true, None);
-        let dest = self.location_to_reg(Size::S64, dst, &mut temps, ImmType::None, false, None);
+        let src = self.location_to_reg(Size::S64, src, &mut temps, ImmType::None, true, None)?;
+        let dest = self.location_to_reg(Size::S64, dst, &mut temps, ImmType::None, false, None)?;
         self.assembler.emit_rbit(Size::S64, src, dest);
         self.assembler.emit_clz(Size::S64, dest, dest);
         if dst != dest {
-            self.move_location(Size::S64, dest, dst);
+            self.move_location(Size::S64, dest, dst)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn i64_popcnt(&mut self, loc: Location, ret: Location) {
-        let mut temps = vec![];
-        let src = self.location_to_reg(Size::S64, loc, &mut temps, ImmType::None, true, None);
-        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None);
-        let src = if src == loc {
-            let tmp = self.acquire_temp_gpr().unwrap();
-            temps.push(tmp.clone());
-            self.assembler.emit_mov(Size::S64, src, Location::GPR(tmp));
-            Location::GPR(tmp)
-        } else {
-            src
-        };
-        let tmp = {
-            let tmp = self.acquire_temp_gpr().unwrap();
-            temps.push(tmp.clone());
-            Location::GPR(tmp)
-        };
-        let label_loop = self.assembler.get_label();
-        let label_exit = self.assembler.get_label();
-        self.assembler
-            .emit_mov(Size::S32, Location::GPR(GPR::XzrSp), dest);
-        self.assembler.emit_cbz_label(Size::S64, src, label_exit);
-        self.assembler.emit_label(label_loop);
-        self.assembler
-            .emit_add(Size::S32, dest, Location::Imm8(1), dest);
-        self.assembler.emit_clz(Size::S64, src, tmp);
-        self.assembler
-            .emit_add(Size::S32, tmp, Location::Imm8(1), tmp);
-        self.assembler.emit_lsl(Size::S64, src, tmp, src);
-        self.assembler.emit_cbnz_label(Size::S64, src, label_loop);
-        self.assembler.emit_label(label_exit);
-        if ret != dest {
-            self.move_location(Size::S64, dest, ret);
-        }
-        for r in temps {
-            self.release_gpr(r);
-        }
+    fn i64_popcnt(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_popcnt(Size::S64, loc, ret)
     }
-    fn i64_shl(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn i64_shl(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_lsl,
             Size::S64,
@@ -3641,9 +4819,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Shift64No0,
-        );
+        )
     }
-    fn i64_shr(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn i64_shr(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_lsr,
             Size::S64,
@@ -3651,9 +4834,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Shift64No0,
-        );
+        )
     }
-    fn i64_sar(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn i64_sar(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_asr,
             Size::S64,
@@ -3661,9 +4849,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Shift64No0,
-        );
+        )
     }
-    fn i64_rol(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn i64_rol(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         // there is no ROL on ARM64. We use ROR with 64-value instead
         let mut temps = vec![];
         let src2 = match loc_b {
@@ -3678,9 +4871,9 @@ impl Machine for MachineARM64 {
                     ImmType::None,
                     true,
                     None,
-                );
+                )?;
                 let tmp2 =
-                    self.location_to_reg(Size::S64, loc_b, &mut temps, ImmType::None, true, None);
+                    self.location_to_reg(Size::S64, loc_b, &mut temps, ImmType::None, true, None)?;
                 self.assembler.emit_sub(Size::S64, tmp1, tmp2, tmp1);
                 tmp1
             }
@@ -3692,12 +4885,18 @@ impl Machine for MachineARM64 {
             src2,
             ret,
             ImmType::Shift64No0,
-        );
+        )?;
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn i64_ror(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn i64_ror(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3(
             Assembler::emit_ror,
             Size::S64,
@@ -3705,7 +4904,7 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::Shift64No0,
-        );
+        )
     }
     fn i64_load(
         &mut self,
@@ -3716,7 +4915,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -3726,10 +4925,8 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_ldr64(Size::S64, ret, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldr64(Size::S64, ret, Location::Memory(addr, 0)),
+        )
     }
     fn i64_load_8u(
         &mut self,
@@ -3740,7 +4937,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -3750,10 +4947,8 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_ldr8(Size::S64, ret, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldr8(Size::S64, ret, Location::Memory(addr, 0)),
+        )
     }
     fn i64_load_8s(
         &mut self,
@@ -3764,7 +4959,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -3774,10 +4969,8 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_ldr8s(Size::S64, ret, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldr8s(Size::S64, ret, Location::Memory(addr, 0)),
+        )
     }
     fn i64_load_16u(
         &mut self,
@@ -3788,7 +4981,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -3798,10 +4991,8 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_ldr16(Size::S64, ret, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldr16(Size::S64, ret, Location::Memory(addr, 0)),
+        )
     }
     fn i64_load_16s(
         &mut self,
@@ -3812,7 +5003,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -3822,10 +5013,8 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_ldr16s(Size::S64, ret, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldr16s(Size::S64, ret, Location::Memory(addr, 0)),
+        )
     }
     fn i64_load_32u(
         &mut self,
@@ -3836,7 +5025,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -3846,10 +5035,8 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_ldr32(Size::S64, ret, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldr32(Size::S64, ret, Location::Memory(addr, 0)),
+        )
     }
     fn i64_load_32s(
         &mut self,
@@ -3860,7 +5047,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -3870,598 +5057,1134 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_ldr32s(Size::S64, ret, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldr32s(Size::S64, ret, Location::Memory(addr, 0)),
+        )
     }
     fn i64_atomic_load(
         &mut self,
-        _addr: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        addr: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            addr,
+            memarg,
+            true,
+            8,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_ldar(Size::S64, Size::S64, ret, addr),
+        )
     }
     fn i64_atomic_load_8u(
         &mut self,
-        _addr: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        addr: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            addr,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_ldar(Size::S8, Size::S64, ret, addr),
+        )
     }
     fn i64_atomic_load_16u(
         &mut self,
-        _addr: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        addr: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            addr,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_ldar(Size::S16, Size::S64, ret, addr),
+        )
     }
     fn i64_atomic_load_32u(
         &mut self,
-        _addr: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        addr: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            addr,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_ldar(Size::S32, Size::S64, ret, addr),
+        )
+    }
+    fn i64_save(
+        &mut self,
+        target_value: Location,
+        memarg: &MemoryImmediate,
+        target_addr: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target_addr,
+            memarg,
+            false,
+            8,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_str64(target_value, Location::Memory(addr, 0)),
+        )
+    }
+    fn i64_save_8(
+        &mut self,
+        target_value: Location,
+        memarg: &MemoryImmediate,
+        target_addr: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target_addr,
+            memarg,
+            false,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_str8(target_value, Location::Memory(addr, 0)),
+        )
+    }
+    fn i64_save_16(
+        &mut self,
+        target_value: Location,
+        memarg: &MemoryImmediate,
+        target_addr: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target_addr,
+            memarg,
+            false,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_str16(target_value, Location::Memory(addr, 0)),
+        )
+    }
+    fn i64_save_32(
+        &mut self,
+        target_value: Location,
+        memarg: &MemoryImmediate,
+        target_addr: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target_addr,
+            memarg,
+            false,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_str32(target_value, Location::Memory(addr, 0)),
+        )
     }
-    fn i64_save(
+    fn i64_atomic_save(
         &mut self,
-        target_value: Location,
+        value: Location,
         memarg: &MemoryImmediate,
         target_addr: Location,
         need_check: bool,
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
-            false,
+            true,
             8,
             need_check,
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_str64(target_value, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_stlr(Size::S64, value, addr),
+        )
     }
-    fn i64_save_8(
+    fn i64_atomic_save_8(
         &mut self,
-        target_value: Location,
+        value: Location,
         memarg: &MemoryImmediate,
         target_addr: Location,
         need_check: bool,
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
-            false,
+            true,
             1,
             need_check,
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_str8(target_value, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_stlr(Size::S8, value, addr),
+        )
     }
-    fn i64_save_16(
+    fn i64_atomic_save_16(
         &mut self,
-        target_value: Location,
+        value: Location,
         memarg: &MemoryImmediate,
         target_addr: Location,
         need_check: bool,
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
-            false,
+            true,
             2,
             need_check,
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_str16(target_value, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_stlr(Size::S16, value, addr),
+        )
     }
-    fn i64_save_32(
+    fn i64_atomic_save_32(
         &mut self,
-        target_value: Location,
+        value: Location,
         memarg: &MemoryImmediate,
         target_addr: Location,
         need_check: bool,
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
-            false,
+            true,
             4,
             need_check,
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_str32(target_value, Location::Memory(addr, 0));
-            },
-        );
-    }
-    fn i64_atomic_save(
-        &mut self,
-        _value: Location,
-        _memarg: &MemoryImmediate,
-        _target_addr: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
-    }
-    fn i64_atomic_save_8(
-        &mut self,
-        _value: Location,
-        _memarg: &MemoryImmediate,
-        _target_addr: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
-    }
-    fn i64_atomic_save_16(
-        &mut self,
-        _value: Location,
-        _memarg: &MemoryImmediate,
-        _target_addr: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
-    }
-    fn i64_atomic_save_32(
-        &mut self,
-        _value: Location,
-        _memarg: &MemoryImmediate,
-        _target_addr: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+            |this, addr| this.emit_relaxed_stlr(Size::S32, value, addr),
+        )
     }
     // i64 atomic Add with i64
     fn i64_atomic_add(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            8,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_add),
+                    Size::S64,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic Add with u8
     fn i64_atomic_add_8u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_add),
+                    Size::S8,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic Add with u16
     fn i64_atomic_add_16u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_add),
+                    Size::S16,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic Add with u32
     fn i64_atomic_add_32u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_add),
+                    Size::S32,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic Sub with i64
     fn i64_atomic_sub(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            8,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_sub),
+                    Size::S64,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic Sub with u8
     fn i64_atomic_sub_8u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
-    }
-    // i64 atomic Sub with u16
-    fn i64_atomic_sub_16u(
-        &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_sub),
+                    Size::S8,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
+    }
+    // i64 atomic Sub with u16
+    fn i64_atomic_sub_16u(
+        &mut self,
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_sub),
+                    Size::S16,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic Sub with u32
     fn i64_atomic_sub_32u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_sub),
+                    Size::S32,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic And with i64
     fn i64_atomic_and(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            8,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_and),
+                    Size::S64,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic And with u8
     fn i64_atomic_and_8u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_and),
+                    Size::S8,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic And with u16
     fn i64_atomic_and_16u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_and),
+                    Size::S16,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic And with u32
     fn i64_atomic_and_32u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_and),
+                    Size::S32,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic Or with i64
     fn i64_atomic_or(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            8,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_or),
+                    Size::S64,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic Or with u8
     fn i64_atomic_or_8u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_or),
+                    Size::S8,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic Or with u16
     fn i64_atomic_or_16u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_or),
+                    Size::S16,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic Or with u32
     fn i64_atomic_or_32u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_or),
+                    Size::S32,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic xor with i64
     fn i64_atomic_xor(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            8,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_eor),
+                    Size::S64,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic xor with u8
     fn i64_atomic_xor_8u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_eor),
+                    Size::S8,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic xor with u16
     fn i64_atomic_xor_16u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_eor),
+                    Size::S16,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic xor with u32
     fn i64_atomic_xor_32u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_rmw(
+                    Some(Assembler::emit_eor),
+                    Size::S32,
+                    Size::S64,
+                    loc,
+                    ret,
+                    addr,
+                )
+            },
+        )
     }
     // i64 atomic Exchange with i64
     fn i64_atomic_xchg(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            8,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_atomic_rmw(None, Size::S64, Size::S64, loc, ret, addr),
+        )
     }
     // i64 atomic Exchange with u8
     fn i64_atomic_xchg_8u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_atomic_rmw(None, Size::S8, Size::S64, loc, ret, addr),
+        )
     }
     // i64 atomic Exchange with u16
     fn i64_atomic_xchg_16u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_atomic_rmw(None, Size::S16, Size::S64, loc, ret, addr),
+        )
     }
     // i64 atomic Exchange with u32
     fn i64_atomic_xchg_32u(
         &mut self,
-        _loc: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        loc: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_atomic_rmw(None, Size::S32, Size::S64, loc, ret, addr),
+        )
     }
     // i64 atomic Exchange with i64
     fn i64_atomic_cmpxchg(
         &mut self,
-        _new: Location,
-        _cmp: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
-    }
-    // i64 atomic Exchange with u8
-    fn i64_atomic_cmpxchg_8u(
-        &mut self,
-        _new: Location,
-        _cmp: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        new: Location,
+        cmp: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            8,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_cmpxchg(Size::S64, Size::S64, new, cmp, ret, addr)
+            },
+        )
+    }
+    // i64 atomic Exchange with u8
+    fn i64_atomic_cmpxchg_8u(
+        &mut self,
+        new: Location,
+        cmp: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            1,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| this.emit_relaxed_atomic_cmpxchg(Size::S8, Size::S64, new, cmp, ret, addr),
+        )
     }
     // i64 atomic Exchange with u16
     fn i64_atomic_cmpxchg_16u(
         &mut self,
-        _new: Location,
-        _cmp: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        new: Location,
+        cmp: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            2,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_cmpxchg(Size::S16, Size::S64, new, cmp, ret, addr)
+            },
+        )
     }
     // i64 atomic Exchange with u32
     fn i64_atomic_cmpxchg_32u(
         &mut self,
-        _new: Location,
-        _cmp: Location,
-        _target: Location,
-        _memarg: &MemoryImmediate,
-        _ret: Location,
-        _need_check: bool,
-        _imported_memories: bool,
-        _offset: i32,
-        _heap_access_oob: Label,
-    ) {
-        unimplemented!();
+        new: Location,
+        cmp: Location,
+        target: Location,
+        memarg: &MemoryImmediate,
+        ret: Location,
+        need_check: bool,
+        imported_memories: bool,
+        offset: i32,
+        heap_access_oob: Label,
+    ) -> Result<(), CodegenError> {
+        self.memory_op(
+            target,
+            memarg,
+            true,
+            4,
+            need_check,
+            imported_memories,
+            offset,
+            heap_access_oob,
+            |this, addr| {
+                this.emit_relaxed_atomic_cmpxchg(Size::S32, Size::S64, new, cmp, ret, addr)
+            },
+        )
     }
 
     fn f32_load(
@@ -4473,7 +6196,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -4483,10 +6206,8 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_ldr32(Size::S32, ret, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldr32(Size::S32, ret, Location::Memory(addr, 0)),
+        )
     }
     fn f32_save(
         &mut self,
@@ -4498,7 +6219,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         let canonicalize = canonicalize && self.arch_supports_canonicalize_nan();
         self.memory_op(
             target_addr,
@@ -4511,12 +6232,13 @@ impl Machine for MachineARM64 {
             heap_access_oob,
             |this, addr| {
                 if !canonicalize {
-                    this.emit_relaxed_str32(target_value, Location::Memory(addr, 0));
+                    this.emit_relaxed_str32(target_value, Location::Memory(addr, 0))?;
                 } else {
-                    this.canonicalize_nan(Size::S32, target_value, Location::Memory(addr, 0));
+                    this.canonicalize_nan(Size::S32, target_value, Location::Memory(addr, 0))?;
                 }
+                Ok(())
             },
-        );
+        )
     }
     fn f64_load(
         &mut self,
@@ -4527,7 +6249,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -4537,10 +6259,8 @@ impl Machine for MachineARM64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_ldr64(Size::S64, ret, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_ldr64(Size::S64, ret, Location::Memory(addr, 0)),
+        )
     }
     fn f64_save(
         &mut self,
@@ -4552,7 +6272,7 @@ impl Machine for MachineARM64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         let canonicalize = canonicalize && self.arch_supports_canonicalize_nan();
         self.memory_op(
             target_addr,
@@ -4565,26 +6285,32 @@ impl Machine for MachineARM64 {
             heap_access_oob,
             |this, addr| {
                 if !canonicalize {
-                    this.emit_relaxed_str64(target_value, Location::Memory(addr, 0));
+                    this.emit_relaxed_str64(target_value, Location::Memory(addr, 0))?;
                 } else {
-                    this.canonicalize_nan(Size::S64, target_value, Location::Memory(addr, 0));
+                    this.canonicalize_nan(Size::S64, target_value, Location::Memory(addr, 0))?;
                 }
+                Ok(())
             },
-        );
+        )
     }
 
-    fn convert_f64_i64(&mut self, loc: Location, signed: bool, ret: Location) {
+    fn convert_f64_i64(
+        &mut self,
+        loc: Location,
+        signed: bool,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut gprs = vec![];
         let mut neons = vec![];
-        let src = self.location_to_reg(Size::S64, loc, &mut gprs, ImmType::NoneXzr, true, None);
-        let dest = self.location_to_neon(Size::S64, ret, &mut neons, ImmType::None, false);
+        let src = self.location_to_reg(Size::S64, loc, &mut gprs, ImmType::NoneXzr, true, None)?;
+        let dest = self.location_to_neon(Size::S64, ret, &mut neons, ImmType::None, false)?;
         if signed {
             self.assembler.emit_scvtf(Size::S64, src, Size::S64, dest);
         } else {
             self.assembler.emit_ucvtf(Size::S64, src, Size::S64, dest);
         }
         if ret != dest {
-            self.move_location(Size::S64, dest, ret);
+            self.move_location(Size::S64, dest, ret)?;
         }
         for r in gprs {
             self.release_gpr(r);
@@ -4592,19 +6318,25 @@ impl Machine for MachineARM64 {
         for r in neons {
             self.release_simd(r);
         }
+        Ok(())
     }
-    fn convert_f64_i32(&mut self, loc: Location, signed: bool, ret: Location) {
+    fn convert_f64_i32(
+        &mut self,
+        loc: Location,
+        signed: bool,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut gprs = vec![];
         let mut neons = vec![];
-        let src = self.location_to_reg(Size::S32, loc, &mut gprs, ImmType::NoneXzr, true, None);
-        let dest = self.location_to_neon(Size::S64, ret, &mut neons, ImmType::None, false);
+        let src = self.location_to_reg(Size::S32, loc, &mut gprs, ImmType::NoneXzr, true, None)?;
+        let dest = self.location_to_neon(Size::S64, ret, &mut neons, ImmType::None, false)?;
         if signed {
             self.assembler.emit_scvtf(Size::S32, src, Size::S64, dest);
         } else {
             self.assembler.emit_ucvtf(Size::S32, src, Size::S64, dest);
         }
         if ret != dest {
-            self.move_location(Size::S64, dest, ret);
+            self.move_location(Size::S64, dest, ret)?;
         }
         for r in gprs {
             self.release_gpr(r);
@@ -4612,19 +6344,25 @@ impl Machine for MachineARM64 {
         for r in neons {
             self.release_simd(r);
         }
+        Ok(())
     }
-    fn convert_f32_i64(&mut self, loc: Location, signed: bool, ret: Location) {
+    fn convert_f32_i64(
+        &mut self,
+        loc: Location,
+        signed: bool,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut gprs = vec![];
         let mut neons = vec![];
-        let src = self.location_to_reg(Size::S64, loc, &mut gprs, ImmType::NoneXzr, true, None);
-        let dest = self.location_to_neon(Size::S32, ret, &mut neons, ImmType::None, false);
+        let src = self.location_to_reg(Size::S64, loc, &mut gprs, ImmType::NoneXzr, true, None)?;
+        let dest = self.location_to_neon(Size::S32, ret, &mut neons, ImmType::None, false)?;
         if signed {
             self.assembler.emit_scvtf(Size::S64, src, Size::S32, dest);
         } else {
             self.assembler.emit_ucvtf(Size::S64, src, Size::S32, dest);
         }
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in gprs {
             self.release_gpr(r);
@@ -4632,19 +6370,25 @@ impl Machine for MachineARM64 {
         for r in neons {
             self.release_simd(r);
         }
+        Ok(())
     }
-    fn convert_f32_i32(&mut self, loc: Location, signed: bool, ret: Location) {
+    fn convert_f32_i32(
+        &mut self,
+        loc: Location,
+        signed: bool,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut gprs = vec![];
         let mut neons = vec![];
-        let src = self.location_to_reg(Size::S32, loc, &mut gprs, ImmType::NoneXzr, true, None);
-        let dest = self.location_to_neon(Size::S32, ret, &mut neons, ImmType::None, false);
+        let src = self.location_to_reg(Size::S32, loc, &mut gprs, ImmType::NoneXzr, true, None)?;
+        let dest = self.location_to_neon(Size::S32, ret, &mut neons, ImmType::None, false)?;
         if signed {
             self.assembler.emit_scvtf(Size::S32, src, Size::S32, dest);
         } else {
             self.assembler.emit_ucvtf(Size::S32, src, Size::S32, dest);
         }
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in gprs {
             self.release_gpr(r);
@@ -4652,15 +6396,22 @@ impl Machine for MachineARM64 {
         for r in neons {
             self.release_simd(r);
         }
+        Ok(())
     }
-    fn convert_i64_f64(&mut self, loc: Location, ret: Location, signed: bool, sat: bool) {
+    fn convert_i64_f64(
+        &mut self,
+        loc: Location,
+        ret: Location,
+        signed: bool,
+        sat: bool,
+    ) -> Result<(), CodegenError> {
         let mut gprs = vec![];
         let mut neons = vec![];
-        let src = self.location_to_neon(Size::S64, loc, &mut neons, ImmType::None, true);
-        let dest = self.location_to_reg(Size::S64, ret, &mut gprs, ImmType::None, false, None);
+        let src = self.location_to_neon(Size::S64, loc, &mut neons, ImmType::None, true)?;
+        let dest = self.location_to_reg(Size::S64, ret, &mut gprs, ImmType::None, false, None)?;
         let old_fpcr = if !sat {
-            self.reset_exception_fpsr();
-            self.set_trap_enabled(&mut gprs)
+            self.reset_exception_fpsr()?;
+            self.set_trap_enabled(&mut gprs)?
         } else {
             GPR::XzrSp
         };
@@ -4670,10 +6421,10 @@ impl Machine for MachineARM64 {
             self.assembler.emit_fcvtzu(Size::S64, src, Size::S64, dest);
         }
         if !sat {
-            self.trap_float_convertion_errors(old_fpcr, Size::S64, src, &mut gprs);
+            self.trap_float_convertion_errors(old_fpcr, Size::S64, src, &mut gprs)?;
         }
         if ret != dest {
-            self.move_location(Size::S64, dest, ret);
+            self.move_location(Size::S64, dest, ret)?;
         }
         for r in gprs {
             self.release_gpr(r);
@@ -4681,15 +6432,22 @@ impl Machine for MachineARM64 {
         for r in neons {
             self.release_simd(r);
         }
+        Ok(())
     }
-    fn convert_i32_f64(&mut self, loc: Location, ret: Location, signed: bool, sat: bool) {
+    fn convert_i32_f64(
+        &mut self,
+        loc: Location,
+        ret: Location,
+        signed: bool,
+        sat: bool,
+    ) -> Result<(), CodegenError> {
         let mut gprs = vec![];
         let mut neons = vec![];
-        let src = self.location_to_neon(Size::S64, loc, &mut neons, ImmType::None, true);
-        let dest = self.location_to_reg(Size::S32, ret, &mut gprs, ImmType::None, false, None);
+        let src = self.location_to_neon(Size::S64, loc, &mut neons, ImmType::None, true)?;
+        let dest = self.location_to_reg(Size::S32, ret, &mut gprs, ImmType::None, false, None)?;
         let old_fpcr = if !sat {
-            self.reset_exception_fpsr();
-            self.set_trap_enabled(&mut gprs)
+            self.reset_exception_fpsr()?;
+            self.set_trap_enabled(&mut gprs)?
         } else {
             GPR::XzrSp
         };
@@ -4699,10 +6457,10 @@ impl Machine for MachineARM64 {
             self.assembler.emit_fcvtzu(Size::S64, src, Size::S32, dest);
         }
         if !sat {
-            self.trap_float_convertion_errors(old_fpcr, Size::S64, src, &mut gprs);
+            self.trap_float_convertion_errors(old_fpcr, Size::S64, src, &mut gprs)?;
         }
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in gprs {
             self.release_gpr(r);
@@ -4710,15 +6468,22 @@ impl Machine for MachineARM64 {
         for r in neons {
             self.release_simd(r);
         }
+        Ok(())
     }
-    fn convert_i64_f32(&mut self, loc: Location, ret: Location, signed: bool, sat: bool) {
+    fn convert_i64_f32(
+        &mut self,
+        loc: Location,
+        ret: Location,
+        signed: bool,
+        sat: bool,
+    ) -> Result<(), CodegenError> {
         let mut gprs = vec![];
         let mut neons = vec![];
-        let src = self.location_to_neon(Size::S32, loc, &mut neons, ImmType::None, true);
-        let dest = self.location_to_reg(Size::S64, ret, &mut gprs, ImmType::None, false, None);
+        let src = self.location_to_neon(Size::S32, loc, &mut neons, ImmType::None, true)?;
+        let dest = self.location_to_reg(Size::S64, ret, &mut gprs, ImmType::None, false, None)?;
         let old_fpcr = if !sat {
-            self.reset_exception_fpsr();
-            self.set_trap_enabled(&mut gprs)
+            self.reset_exception_fpsr()?;
+            self.set_trap_enabled(&mut gprs)?
         } else {
             GPR::XzrSp
         };
@@ -4728,10 +6493,10 @@ impl Machine for MachineARM64 {
             self.assembler.emit_fcvtzu(Size::S32, src, Size::S64, dest);
         }
         if !sat {
-            self.trap_float_convertion_errors(old_fpcr, Size::S32, src, &mut gprs);
+            self.trap_float_convertion_errors(old_fpcr, Size::S32, src, &mut gprs)?;
         }
         if ret != dest {
-            self.move_location(Size::S64, dest, ret);
+            self.move_location(Size::S64, dest, ret)?;
         }
         for r in gprs {
             self.release_gpr(r);
@@ -4739,15 +6504,22 @@ impl Machine for MachineARM64 {
         for r in neons {
             self.release_simd(r);
         }
+        Ok(())
     }
-    fn convert_i32_f32(&mut self, loc: Location, ret: Location, signed: bool, sat: bool) {
+    fn convert_i32_f32(
+        &mut self,
+        loc: Location,
+        ret: Location,
+        signed: bool,
+        sat: bool,
+    ) -> Result<(), CodegenError> {
         let mut gprs = vec![];
         let mut neons = vec![];
-        let src = self.location_to_neon(Size::S32, loc, &mut neons, ImmType::None, true);
-        let dest = self.location_to_reg(Size::S32, ret, &mut gprs, ImmType::None, false, None);
+        let src = self.location_to_neon(Size::S32, loc, &mut neons, ImmType::None, true)?;
+        let dest = self.location_to_reg(Size::S32, ret, &mut gprs, ImmType::None, false, None)?;
         let old_fpcr = if !sat {
-            self.reset_exception_fpsr();
-            self.set_trap_enabled(&mut gprs)
+            self.reset_exception_fpsr()?;
+            self.set_trap_enabled(&mut gprs)?
         } else {
             GPR::XzrSp
         };
@@ -4757,10 +6529,10 @@ impl Machine for MachineARM64 {
             self.assembler.emit_fcvtzu(Size::S32, src, Size::S32, dest);
         }
         if !sat {
-            self.trap_float_convertion_errors(old_fpcr, Size::S32, src, &mut gprs);
+            self.trap_float_convertion_errors(old_fpcr, Size::S32, src, &mut gprs)?;
         }
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in gprs {
             self.release_gpr(r);
@@ -4768,31 +6540,35 @@ impl Machine for MachineARM64 {
         for r in neons {
             self.release_simd(r);
         }
+        Ok(())
     }
-    fn convert_f64_f32(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_binop_neon(Assembler::emit_fcvt, Size::S32, loc, ret, true);
+    fn convert_f64_f32(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop_neon(Assembler::emit_fcvt, Size::S32, loc, ret, true)
     }
-    fn convert_f32_f64(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_binop_neon(Assembler::emit_fcvt, Size::S64, loc, ret, true);
+    fn convert_f32_f64(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop_neon(Assembler::emit_fcvt, Size::S64, loc, ret, true)
     }
-    fn f64_neg(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_binop_neon(Assembler::emit_fneg, Size::S64, loc, ret, true);
+    fn f64_neg(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop_neon(Assembler::emit_fneg, Size::S64, loc, ret, true)
     }
-    fn f64_abs(&mut self, loc: Location, ret: Location) {
-        let tmp = self.acquire_temp_gpr().unwrap();
+    fn f64_abs(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
 
-        self.move_location(Size::S64, loc, Location::GPR(tmp));
+        self.move_location(Size::S64, loc, Location::GPR(tmp))?;
         self.assembler.emit_and(
             Size::S64,
             Location::GPR(tmp),
             Location::Imm64(0x7fffffffffffffffu64),
             Location::GPR(tmp),
         );
-        self.move_location(Size::S64, Location::GPR(tmp), ret);
+        self.move_location(Size::S64, Location::GPR(tmp), ret)?;
 
         self.release_gpr(tmp);
+        Ok(())
     }
-    fn emit_i64_copysign(&mut self, tmp1: GPR, tmp2: GPR) {
+    fn emit_i64_copysign(&mut self, tmp1: GPR, tmp2: GPR) -> Result<(), CodegenError> {
         self.assembler.emit_and(
             Size::S64,
             Location::GPR(tmp1),
@@ -4813,97 +6589,139 @@ impl Machine for MachineARM64 {
             Location::GPR(tmp2),
             Location::GPR(tmp1),
         );
+        Ok(())
     }
-    fn f64_sqrt(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_binop_neon(Assembler::emit_fsqrt, Size::S64, loc, ret, true);
+    fn f64_sqrt(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop_neon(Assembler::emit_fsqrt, Size::S64, loc, ret, true)
     }
-    fn f64_trunc(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_binop_neon(Assembler::emit_frintz, Size::S64, loc, ret, true);
+    fn f64_trunc(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop_neon(Assembler::emit_frintz, Size::S64, loc, ret, true)
     }
-    fn f64_ceil(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_binop_neon(Assembler::emit_frintp, Size::S64, loc, ret, true);
+    fn f64_ceil(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop_neon(Assembler::emit_frintp, Size::S64, loc, ret, true)
     }
-    fn f64_floor(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_binop_neon(Assembler::emit_frintm, Size::S64, loc, ret, true);
+    fn f64_floor(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop_neon(Assembler::emit_frintm, Size::S64, loc, ret, true)
     }
-    fn f64_nearest(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_binop_neon(Assembler::emit_frintn, Size::S64, loc, ret, true);
+    fn f64_nearest(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop_neon(Assembler::emit_frintn, Size::S64, loc, ret, true)
     }
-    fn f64_cmp_ge(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f64_cmp_ge(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None);
-        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S64, loc_b, loc_a, false);
+        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None)?;
+        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S64, loc_b, loc_a, false)?;
         self.assembler.emit_cset(Size::S32, dest, Condition::Ls);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f64_cmp_gt(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f64_cmp_gt(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None);
-        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S64, loc_b, loc_a, false);
+        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None)?;
+        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S64, loc_b, loc_a, false)?;
         self.assembler.emit_cset(Size::S32, dest, Condition::Cc);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f64_cmp_le(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f64_cmp_le(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None);
-        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S64, loc_a, loc_b, false);
+        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None)?;
+        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S64, loc_a, loc_b, false)?;
         self.assembler.emit_cset(Size::S32, dest, Condition::Ls);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f64_cmp_lt(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f64_cmp_lt(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None);
-        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S64, loc_a, loc_b, false);
+        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None)?;
+        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S64, loc_a, loc_b, false)?;
         self.assembler.emit_cset(Size::S32, dest, Condition::Cc);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f64_cmp_ne(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f64_cmp_ne(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None);
-        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S64, loc_a, loc_b, false);
+        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None)?;
+        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S64, loc_a, loc_b, false)?;
         self.assembler.emit_cset(Size::S32, dest, Condition::Ne);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f64_cmp_eq(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f64_cmp_eq(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None);
-        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S64, loc_a, loc_b, false);
+        let dest = self.location_to_reg(Size::S64, ret, &mut temps, ImmType::None, false, None)?;
+        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S64, loc_a, loc_b, false)?;
         self.assembler.emit_cset(Size::S32, dest, Condition::Eq);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f64_min(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f64_min(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let old_fpcr = self.set_default_nan(&mut temps);
+        let old_fpcr = self.set_default_nan(&mut temps)?;
         self.emit_relaxed_binop3_neon(
             Assembler::emit_fmin,
             Size::S64,
@@ -4911,15 +6729,21 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::None,
-        );
+        )?;
         self.restore_fpcr(old_fpcr);
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f64_max(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f64_max(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let old_fpcr = self.set_default_nan(&mut temps);
+        let old_fpcr = self.set_default_nan(&mut temps)?;
         self.emit_relaxed_binop3_neon(
             Assembler::emit_fmax,
             Size::S64,
@@ -4927,13 +6751,19 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::None,
-        );
+        )?;
         self.restore_fpcr(old_fpcr);
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f64_add(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f64_add(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3_neon(
             Assembler::emit_fadd,
             Size::S64,
@@ -4941,9 +6771,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::None,
-        );
+        )
     }
-    fn f64_sub(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f64_sub(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3_neon(
             Assembler::emit_fsub,
             Size::S64,
@@ -4951,9 +6786,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::None,
-        );
+        )
     }
-    fn f64_mul(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f64_mul(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3_neon(
             Assembler::emit_fmul,
             Size::S64,
@@ -4961,9 +6801,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::None,
-        );
+        )
     }
-    fn f64_div(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f64_div(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3_neon(
             Assembler::emit_fdiv,
             Size::S64,
@@ -4971,22 +6816,25 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::None,
-        );
+        )
     }
-    fn f32_neg(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_binop_neon(Assembler::emit_fneg, Size::S32, loc, ret, true);
+    fn f32_neg(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop_neon(Assembler::emit_fneg, Size::S32, loc, ret, true)
     }
-    fn f32_abs(&mut self, loc: Location, ret: Location) {
-        let tmp = self.acquire_temp_gpr().unwrap();
-        self.move_location(Size::S32, loc, Location::GPR(tmp));
+    fn f32_abs(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.move_location(Size::S32, loc, Location::GPR(tmp))?;
         self.assembler.emit_and(
             Size::S32,
             Location::GPR(tmp),
             Location::Imm32(0x7fffffffu32),
             Location::GPR(tmp),
         );
-        self.move_location(Size::S32, Location::GPR(tmp), ret);
+        self.move_location(Size::S32, Location::GPR(tmp), ret)?;
         self.release_gpr(tmp);
+        Ok(())
     }
     fn emit_i32_copysign(&mut self, tmp1: GPR, tmp2: GPR) {
         self.assembler.emit_and(
@@ -5008,96 +6856,137 @@ impl Machine for MachineARM64 {
             Location::GPR(tmp1),
         );
     }
-    fn f32_sqrt(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_binop_neon(Assembler::emit_fsqrt, Size::S32, loc, ret, true);
+    fn f32_sqrt(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop_neon(Assembler::emit_fsqrt, Size::S32, loc, ret, true)
     }
-    fn f32_trunc(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_binop_neon(Assembler::emit_frintz, Size::S32, loc, ret, true);
+    fn f32_trunc(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop_neon(Assembler::emit_frintz, Size::S32, loc, ret, true)
     }
-    fn f32_ceil(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_binop_neon(Assembler::emit_frintp, Size::S32, loc, ret, true);
+    fn f32_ceil(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop_neon(Assembler::emit_frintp, Size::S32, loc, ret, true)
     }
-    fn f32_floor(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_binop_neon(Assembler::emit_frintm, Size::S32, loc, ret, true);
+    fn f32_floor(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop_neon(Assembler::emit_frintm, Size::S32, loc, ret, true)
     }
-    fn f32_nearest(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_binop_neon(Assembler::emit_frintn, Size::S32, loc, ret, true);
+    fn f32_nearest(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop_neon(Assembler::emit_frintn, Size::S32, loc, ret, true)
     }
-    fn f32_cmp_ge(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f32_cmp_ge(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None);
-        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S32, loc_b, loc_a, false);
+        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None)?;
+        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S32, loc_b, loc_a, false)?;
         self.assembler.emit_cset(Size::S32, dest, Condition::Ls);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f32_cmp_gt(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f32_cmp_gt(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None);
-        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S32, loc_b, loc_a, false);
+        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None)?;
+        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S32, loc_b, loc_a, false)?;
         self.assembler.emit_cset(Size::S32, dest, Condition::Cc);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f32_cmp_le(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f32_cmp_le(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None);
-        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S32, loc_a, loc_b, false);
+        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None)?;
+        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S32, loc_a, loc_b, false)?;
         self.assembler.emit_cset(Size::S32, dest, Condition::Ls);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f32_cmp_lt(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f32_cmp_lt(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None);
-        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S32, loc_a, loc_b, false);
+        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None)?;
+        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S32, loc_a, loc_b, false)?;
         self.assembler.emit_cset(Size::S32, dest, Condition::Cc);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f32_cmp_ne(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f32_cmp_ne(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None);
-        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S32, loc_a, loc_b, false);
+        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None)?;
+        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S32, loc_a, loc_b, false)?;
         self.assembler.emit_cset(Size::S32, dest, Condition::Ne);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f32_cmp_eq(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f32_cmp_eq(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None);
-        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S32, loc_a, loc_b, false);
+        let dest = self.location_to_reg(Size::S32, ret, &mut temps, ImmType::None, false, None)?;
+        self.emit_relaxed_binop_neon(Assembler::emit_fcmp, Size::S32, loc_a, loc_b, false)?;
         self.assembler.emit_cset(Size::S32, dest, Condition::Eq);
         if ret != dest {
-            self.move_location(Size::S32, dest, ret);
+            self.move_location(Size::S32, dest, ret)?;
         }
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f32_min(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f32_min(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let old_fpcr = self.set_default_nan(&mut temps);
+        let old_fpcr = self.set_default_nan(&mut temps)?;
         self.emit_relaxed_binop3_neon(
             Assembler::emit_fmin,
             Size::S32,
@@ -5105,15 +6994,21 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::None,
-        );
+        )?;
         self.restore_fpcr(old_fpcr);
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f32_max(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f32_max(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         let mut temps = vec![];
-        let old_fpcr = self.set_default_nan(&mut temps);
+        let old_fpcr = self.set_default_nan(&mut temps)?;
         self.emit_relaxed_binop3_neon(
             Assembler::emit_fmax,
             Size::S32,
@@ -5121,13 +7016,19 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::None,
-        );
+        )?;
         self.restore_fpcr(old_fpcr);
         for r in temps {
             self.release_gpr(r);
         }
+        Ok(())
     }
-    fn f32_add(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f32_add(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3_neon(
             Assembler::emit_fadd,
             Size::S32,
@@ -5135,9 +7036,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::None,
-        );
+        )
     }
-    fn f32_sub(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f32_sub(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3_neon(
             Assembler::emit_fsub,
             Size::S32,
@@ -5145,9 +7051,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::None,
-        );
+        )
     }
-    fn f32_mul(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f32_mul(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3_neon(
             Assembler::emit_fmul,
             Size::S32,
@@ -5155,9 +7066,14 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::None,
-        );
+        )
     }
-    fn f32_div(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f32_div(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_binop3_neon(
             Assembler::emit_fdiv,
             Size::S32,
@@ -5165,7 +7081,7 @@ impl Machine for MachineARM64 {
             loc_b,
             ret,
             ImmType::None,
-        );
+        )
     }
 
     fn gen_std_trampoline(
@@ -5173,7 +7089,7 @@ impl Machine for MachineARM64 {
         sig: &FunctionType,
         calling_convention: CallingConvention,
     ) -> FunctionBody {
-        gen_std_trampoline_arm64(sig, calling_convention)
+        gen_std_trampoline_arm64(sig, calling_convention, self.call_reg, self.vmctx_reg)
     }
     // Generates dynamic import function call trampoline for a function type.
     fn gen_std_dynamic_import_trampoline(
@@ -5182,7 +7098,7 @@ impl Machine for MachineARM64 {
         sig: &FunctionType,
         calling_convention: CallingConvention,
     ) -> FunctionBody {
-        gen_std_dynamic_import_trampoline_arm64(vmoffsets, sig, calling_convention)
+        gen_std_dynamic_import_trampoline_arm64(vmoffsets, sig, calling_convention, self.call_reg)
     }
     // Singlepass calls import functions through a trampoline.
     fn gen_import_call_trampoline(
@@ -5254,3 +7170,189 @@ impl Machine for MachineARM64 {
         None
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn division_trap_codes() {
+        let mut machine = MachineARM64::new();
+        let integer_division_by_zero = machine.get_label();
+        let integer_overflow = machine.get_label();
+        let (a, b, ret) = (
+            Location::GPR(GPR::X0),
+            Location::GPR(GPR::X1),
+            Location::GPR(GPR::X2),
+        );
+
+        let offsets = [
+            (
+                machine
+                    .emit_binop_udiv32(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerDivisionByZero,
+            ),
+            (
+                machine
+                    .emit_binop_sdiv32(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerOverflow,
+            ),
+            (
+                machine
+                    .emit_binop_urem32(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerDivisionByZero,
+            ),
+            (
+                machine
+                    .emit_binop_srem32(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerDivisionByZero,
+            ),
+            (
+                machine
+                    .emit_binop_udiv64(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerDivisionByZero,
+            ),
+            (
+                machine
+                    .emit_binop_sdiv64(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerOverflow,
+            ),
+            (
+                machine
+                    .emit_binop_urem64(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerDivisionByZero,
+            ),
+            (
+                machine
+                    .emit_binop_srem64(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerDivisionByZero,
+            ),
+        ];
+
+        for (offset, code) in offsets.iter() {
+            assert_eq!(machine.trap_table.offset_to_code.get(offset), Some(code));
+        }
+        assert_eq!(machine.trap_table.offset_to_code.len(), offsets.len());
+    }
+
+    /// The bytes of code emitted by `f`.
+    fn code_size(machine: &mut MachineARM64, f: impl FnOnce(&mut MachineARM64)) -> usize {
+        let start = machine.get_offset().0;
+        f(machine);
+        machine.get_offset().0 - start
+    }
+
+    #[test]
+    fn spills_are_paired() {
+        let mut machine = MachineARM64::new();
+        let gprs = vec![GPR::X19, GPR::X20, GPR::X21, GPR::X22, GPR::X23];
+        // One `stp` per pair, and three instructions for the odd register
+        // pushed with padding.
+        let push = code_size(&mut machine, |m| {
+            m.push_used_gpr(&gprs).unwrap();
+        });
+        assert_eq!(push, 5 * 4);
+        let pop = code_size(&mut machine, |m| m.pop_used_gpr(&gprs).unwrap());
+        assert_eq!(pop, 5 * 4);
+        assert!(!machine.pushed);
+
+        let neons = vec![NEON::V8, NEON::V9, NEON::V10];
+        let push = code_size(&mut machine, |m| {
+            m.push_used_simd(&neons).unwrap();
+        });
+        // The stack adjustment, one `stp` and one `str`.
+        assert_eq!(push, 3 * 4);
+
+        let locals = [
+            Location::GPR(GPR::X19),
+            Location::GPR(GPR::X20),
+            Location::GPR(GPR::X21),
+            Location::GPR(GPR::X22),
+        ];
+        let save = code_size(&mut machine, |m| m.move_locals(8, &locals));
+        assert_eq!(save, 2 * 4);
+        let restore = code_size(&mut machine, |m| m.pop_locations(&locals).unwrap());
+        assert_eq!(restore, 2 * 4);
+    }
+
+    #[test]
+    fn atomic_loads_and_stores_are_ordered() {
+        let mut machine = MachineARM64::new();
+        let heap_access_oob = machine.get_label();
+        let memarg = MemoryImmediate {
+            align: 2,
+            offset: 0,
+            memory: 0,
+        };
+        let (addr, value) = (Location::GPR(GPR::X0), Location::GPR(GPR::X1));
+        machine
+            .i32_atomic_load(addr, &memarg, value, true, false, 0, heap_access_oob)
+            .unwrap();
+        machine
+            .i64_atomic_save_8(value, &memarg, addr, true, false, 0, heap_access_oob)
+            .unwrap();
+        machine.emit_label(heap_access_oob);
+
+        let code = machine.assembler_finalize();
+        let words: Vec<u32> = code
+            .chunks(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        // `ldar w1, [xN]` and `stlrb w1, [xN]`.
+        assert!(words.iter().any(|word| word & 0xFFFF_FC1F == 0x88DF_FC01));
+        assert!(words.iter().any(|word| word & 0xFFFF_FC1F == 0x089F_FC01));
+    }
+
+    #[test]
+    fn atomic_rmw_uses_exclusive_loops() {
+        let mut machine = MachineARM64::new();
+        let heap_access_oob = machine.get_label();
+        let memarg = MemoryImmediate {
+            align: 1,
+            offset: 0,
+            memory: 0,
+        };
+        let (addr, value, ret) = (
+            Location::GPR(GPR::X0),
+            Location::GPR(GPR::X1),
+            Location::GPR(GPR::X1),
+        );
+        machine
+            .i32_atomic_add(value, addr, &memarg, ret, true, false, 0, heap_access_oob)
+            .unwrap();
+        machine
+            .i64_atomic_cmpxchg_8u(
+                value,
+                Location::Imm32(7),
+                addr,
+                &memarg,
+                ret,
+                true,
+                false,
+                0,
+                heap_access_oob,
+            )
+            .unwrap();
+        machine.emit_label(heap_access_oob);
+
+        let code = machine.assembler_finalize();
+        let words: Vec<u32> = code
+            .chunks(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        // `ldaxr wN, [xN]` and `stlxr wN, wN, [xN]`.
+        assert!(words.iter().any(|word| word & 0xFFFF_FC00 == 0x885F_FC00));
+        assert!(words.iter().any(|word| word & 0xFFE0_FC00 == 0x8800_FC00));
+        // `ldaxrb wN, [xN]` and `stlxrb wN, wN, [xN]`.
+        assert!(words.iter().any(|word| word & 0xFFFF_FC00 == 0x085F_FC00));
+        assert!(words.iter().any(|word| word & 0xFFE0_FC00 == 0x0800_FC00));
+    }
+}
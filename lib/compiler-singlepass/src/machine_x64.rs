@@ -1,5 +1,6 @@
 use crate::common_decl::*;
 use crate::emitter_x64::*;
+use crate::frame_layout::FrameLayout;
 use crate::location::Location as AbstractLocation;
 use crate::location::Reg;
 use crate::machine::*;
@@ -137,7 +138,7 @@ impl MachineX86_64 {
         sz: Size,
         src: Location,
         dst: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         enum RelaxMode {
             Direct,
             SrcToGPR,
@@ -169,22 +170,30 @@ impl MachineX86_64 {
 
         match mode {
             RelaxMode::SrcToGPR => {
-                let temp = self.acquire_temp_gpr().unwrap();
-                self.move_location(sz, src, Location::GPR(temp));
+                let temp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                self.move_location(sz, src, Location::GPR(temp))?;
                 op(&mut self.assembler, sz, Location::GPR(temp), dst);
                 self.release_gpr(temp);
             }
             RelaxMode::DstToGPR => {
-                let temp = self.acquire_temp_gpr().unwrap();
-                self.move_location(sz, dst, Location::GPR(temp));
+                let temp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                self.move_location(sz, dst, Location::GPR(temp))?;
                 op(&mut self.assembler, sz, src, Location::GPR(temp));
                 self.release_gpr(temp);
             }
             RelaxMode::BothToGPR => {
-                let temp_src = self.acquire_temp_gpr().unwrap();
-                let temp_dst = self.acquire_temp_gpr().unwrap();
-                self.move_location(sz, src, Location::GPR(temp_src));
-                self.move_location(sz, dst, Location::GPR(temp_dst));
+                let temp_src = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                let temp_dst = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                self.move_location(sz, src, Location::GPR(temp_src))?;
+                self.move_location(sz, dst, Location::GPR(temp_dst))?;
                 op(
                     &mut self.assembler,
                     sz,
@@ -193,7 +202,7 @@ impl MachineX86_64 {
                 );
                 match dst {
                     Location::Memory(_, _) | Location::GPR(_) => {
-                        self.move_location(sz, Location::GPR(temp_dst), dst);
+                        self.move_location(sz, Location::GPR(temp_dst), dst)?;
                     }
                     _ => {}
                 }
@@ -204,6 +213,7 @@ impl MachineX86_64 {
                 op(&mut self.assembler, sz, src, dst);
             }
         }
+        Ok(())
     }
     pub fn emit_relaxed_zx_sx(
         &mut self,
@@ -212,18 +222,24 @@ impl MachineX86_64 {
         src: Location,
         sz_dst: Size,
         dst: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         match src {
             Location::Imm32(_) | Location::Imm64(_) => {
-                let tmp_src = self.acquire_temp_gpr().unwrap();
+                let tmp_src = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
                 self.assembler
                     .emit_mov(Size::S64, src, Location::GPR(tmp_src));
                 let src = Location::GPR(tmp_src);
 
                 match dst {
-                    Location::Imm32(_) | Location::Imm64(_) => unreachable!(),
+                    Location::Imm32(_) | Location::Imm64(_) => {
+                        codegen_error!("singlepass emit_relaxed_zx_sx unreachable")
+                    }
                     Location::Memory(_, _) => {
-                        let tmp_dst = self.acquire_temp_gpr().unwrap();
+                        let tmp_dst = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                            message: "singlepass cannot acquire temp gpr".to_owned(),
+                        })?;
                         op(
                             &mut self.assembler,
                             sz_src,
@@ -231,7 +247,7 @@ impl MachineX86_64 {
                             sz_dst,
                             Location::GPR(tmp_dst),
                         );
-                        self.move_location(Size::S64, Location::GPR(tmp_dst), dst);
+                        self.move_location(Size::S64, Location::GPR(tmp_dst), dst)?;
 
                         self.release_gpr(tmp_dst);
                     }
@@ -239,7 +255,7 @@ impl MachineX86_64 {
                         op(&mut self.assembler, sz_src, src, sz_dst, dst);
                     }
                     _ => {
-                        unreachable!();
+                        codegen_error!("singlepass emit_relaxed_zx_sx unreachable");
                     }
                 };
 
@@ -247,9 +263,13 @@ impl MachineX86_64 {
             }
             Location::GPR(_) | Location::Memory(_, _) => {
                 match dst {
-                    Location::Imm32(_) | Location::Imm64(_) => unreachable!(),
+                    Location::Imm32(_) | Location::Imm64(_) => {
+                        codegen_error!("singlepass emit_relaxed_zx_sx unreachable")
+                    }
                     Location::Memory(_, _) => {
-                        let tmp_dst = self.acquire_temp_gpr().unwrap();
+                        let tmp_dst = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                            message: "singlepass cannot acquire temp gpr".to_owned(),
+                        })?;
                         op(
                             &mut self.assembler,
                             sz_src,
@@ -257,7 +277,7 @@ impl MachineX86_64 {
                             sz_dst,
                             Location::GPR(tmp_dst),
                         );
-                        self.move_location(Size::S64, Location::GPR(tmp_dst), dst);
+                        self.move_location(Size::S64, Location::GPR(tmp_dst), dst)?;
 
                         self.release_gpr(tmp_dst);
                     }
@@ -265,14 +285,15 @@ impl MachineX86_64 {
                         op(&mut self.assembler, sz_src, src, sz_dst, dst);
                     }
                     _ => {
-                        unreachable!();
+                        codegen_error!("singlepass emit_relaxed_zx_sx unreachable");
                     }
                 };
             }
             _ => {
-                unreachable!();
+                codegen_error!("singlepass emit_relaxed_zx_sx unreachable");
             }
         }
+        Ok(())
     }
     /// I32 binary operation with both operands popped from the virtual stack.
     fn emit_binop_i32(
@@ -281,16 +302,19 @@ impl MachineX86_64 {
         loc_a: Location,
         loc_b: Location,
         ret: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         if loc_a != ret {
-            let tmp = self.acquire_temp_gpr().unwrap();
-            self.emit_relaxed_mov(Size::S32, loc_a, Location::GPR(tmp));
-            self.emit_relaxed_binop(f, Size::S32, loc_b, Location::GPR(tmp));
-            self.emit_relaxed_mov(Size::S32, Location::GPR(tmp), ret);
+            let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S32, loc_a, Location::GPR(tmp))?;
+            self.emit_relaxed_binop(f, Size::S32, loc_b, Location::GPR(tmp))?;
+            self.emit_relaxed_mov(Size::S32, Location::GPR(tmp), ret)?;
             self.release_gpr(tmp);
         } else {
-            self.emit_relaxed_binop(f, Size::S32, loc_b, ret);
+            self.emit_relaxed_binop(f, Size::S32, loc_b, ret)?;
         }
+        Ok(())
     }
     /// I64 binary operation with both operands popped from the virtual stack.
     fn emit_binop_i64(
@@ -299,16 +323,19 @@ impl MachineX86_64 {
         loc_a: Location,
         loc_b: Location,
         ret: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         if loc_a != ret {
-            let tmp = self.acquire_temp_gpr().unwrap();
-            self.emit_relaxed_mov(Size::S64, loc_a, Location::GPR(tmp));
-            self.emit_relaxed_binop(f, Size::S64, loc_b, Location::GPR(tmp));
-            self.emit_relaxed_mov(Size::S64, Location::GPR(tmp), ret);
+            let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S64, loc_a, Location::GPR(tmp))?;
+            self.emit_relaxed_binop(f, Size::S64, loc_b, Location::GPR(tmp))?;
+            self.emit_relaxed_mov(Size::S64, Location::GPR(tmp), ret)?;
             self.release_gpr(tmp);
         } else {
-            self.emit_relaxed_binop(f, Size::S64, loc_b, ret);
+            self.emit_relaxed_binop(f, Size::S64, loc_b, ret)?;
         }
+        Ok(())
     }
     /// I64 comparison with.
     fn emit_cmpop_i64_dynamic_b(
@@ -317,27 +344,30 @@ impl MachineX86_64 {
         loc_a: Location,
         loc_b: Location,
         ret: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         match ret {
             Location::GPR(x) => {
-                self.emit_relaxed_cmp(Size::S64, loc_b, loc_a);
+                self.emit_relaxed_cmp(Size::S64, loc_b, loc_a)?;
                 self.assembler.emit_set(c, x);
                 self.assembler
                     .emit_and(Size::S32, Location::Imm32(0xff), Location::GPR(x));
             }
             Location::Memory(_, _) => {
-                let tmp = self.acquire_temp_gpr().unwrap();
-                self.emit_relaxed_cmp(Size::S64, loc_b, loc_a);
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                self.emit_relaxed_cmp(Size::S64, loc_b, loc_a)?;
                 self.assembler.emit_set(c, tmp);
                 self.assembler
                     .emit_and(Size::S32, Location::Imm32(0xff), Location::GPR(tmp));
-                self.move_location(Size::S32, Location::GPR(tmp), ret);
+                self.move_location(Size::S32, Location::GPR(tmp), ret)?;
                 self.release_gpr(tmp);
             }
             _ => {
-                unreachable!();
+                codegen_error!("singlepass emit_cmpop_i64_dynamic_b unreachable");
             }
         }
+        Ok(())
     }
     /// I64 shift with both operands popped from the virtual stack.
     fn emit_shift_i64(
@@ -346,15 +376,16 @@ impl MachineX86_64 {
         loc_a: Location,
         loc_b: Location,
         ret: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.assembler
             .emit_mov(Size::S64, loc_b, Location::GPR(GPR::RCX));
 
         if loc_a != ret {
-            self.emit_relaxed_mov(Size::S64, loc_a, ret);
+            self.emit_relaxed_mov(Size::S64, loc_a, ret)?;
         }
 
         f(&mut self.assembler, Size::S64, Location::GPR(GPR::RCX), ret);
+        Ok(())
     }
     /// Moves `loc` to a valid location for `div`/`idiv`.
     fn emit_relaxed_xdiv(
@@ -362,27 +393,28 @@ impl MachineX86_64 {
         op: fn(&mut AssemblerX64, Size, Location),
         sz: Size,
         loc: Location,
+        division: IntegerDivision,
         integer_division_by_zero: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         self.assembler.emit_cmp(sz, Location::Imm32(0), loc);
         self.assembler
             .emit_jmp(Condition::Equal, integer_division_by_zero);
 
-        match loc {
+        Ok(match loc {
             Location::Imm64(_) | Location::Imm32(_) => {
-                self.move_location(sz, loc, Location::GPR(GPR::RCX)); // must not be used during div (rax, rdx)
-                let offset = self.mark_instruction_with_trap_code(TrapCode::IntegerOverflow);
+                self.move_location(sz, loc, Location::GPR(GPR::RCX))?; // must not be used during div (rax, rdx)
+                let offset = self.mark_instruction_with_trap_code(division.trap_code());
                 op(&mut self.assembler, sz, Location::GPR(GPR::RCX));
                 self.mark_instruction_address_end(offset);
                 offset
             }
             _ => {
-                let offset = self.mark_instruction_with_trap_code(TrapCode::IntegerOverflow);
+                let offset = self.mark_instruction_with_trap_code(division.trap_code());
                 op(&mut self.assembler, sz, loc);
                 self.mark_instruction_address_end(offset);
                 offset
             }
-        }
+        })
     }
     /// I32 comparison with.
     fn emit_cmpop_i32_dynamic_b(
@@ -391,27 +423,30 @@ impl MachineX86_64 {
         loc_a: Location,
         loc_b: Location,
         ret: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         match ret {
             Location::GPR(x) => {
-                self.emit_relaxed_cmp(Size::S32, loc_b, loc_a);
+                self.emit_relaxed_cmp(Size::S32, loc_b, loc_a)?;
                 self.assembler.emit_set(c, x);
                 self.assembler
                     .emit_and(Size::S32, Location::Imm32(0xff), Location::GPR(x));
             }
             Location::Memory(_, _) => {
-                let tmp = self.acquire_temp_gpr().unwrap();
-                self.emit_relaxed_cmp(Size::S32, loc_b, loc_a);
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                self.emit_relaxed_cmp(Size::S32, loc_b, loc_a)?;
                 self.assembler.emit_set(c, tmp);
                 self.assembler
                     .emit_and(Size::S32, Location::Imm32(0xff), Location::GPR(tmp));
-                self.move_location(Size::S32, Location::GPR(tmp), ret);
+                self.move_location(Size::S32, Location::GPR(tmp), ret)?;
                 self.release_gpr(tmp);
             }
             _ => {
-                unreachable!();
+                codegen_error!("singlepass emit_cmpop_i32_dynamic_b unreachable");
             }
         }
+        Ok(())
     }
     /// I32 shift with both operands popped from the virtual stack.
     fn emit_shift_i32(
@@ -420,18 +455,19 @@ impl MachineX86_64 {
         loc_a: Location,
         loc_b: Location,
         ret: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.assembler
             .emit_mov(Size::S32, loc_b, Location::GPR(GPR::RCX));
 
         if loc_a != ret {
-            self.emit_relaxed_mov(Size::S32, loc_a, ret);
+            self.emit_relaxed_mov(Size::S32, loc_a, ret)?;
         }
 
         f(&mut self.assembler, Size::S32, Location::GPR(GPR::RCX), ret);
+        Ok(())
     }
 
-    fn memory_op<F: FnOnce(&mut Self, GPR)>(
+    fn memory_op<F: FnOnce(&mut Self, GPR) -> Result<(), CodegenError>>(
         &mut self,
         addr: Location,
         memarg: &MemoryImmediate,
@@ -442,8 +478,10 @@ impl MachineX86_64 {
         offset: i32,
         heap_access_oob: Label,
         cb: F,
-    ) {
-        let tmp_addr = self.acquire_temp_gpr().unwrap();
+    ) -> Result<(), CodegenError> {
+        let tmp_addr = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
 
         // Reusing `tmp_addr` for temporary indirection here, since it's not used before the last reference to `{base,bound}_loc`.
         let (base_loc, bound_loc) = if imported_memories {
@@ -453,7 +491,7 @@ impl MachineX86_64 {
                 Size::S64,
                 Location::Memory(self.get_vmctx_reg(), offset),
                 Location::GPR(tmp_addr),
-            );
+            )?;
             (Location::Memory(tmp_addr, 0), Location::Memory(tmp_addr, 8))
         } else {
             (
@@ -462,8 +500,12 @@ impl MachineX86_64 {
             )
         };
 
-        let tmp_base = self.acquire_temp_gpr().unwrap();
-        let tmp_bound = self.acquire_temp_gpr().unwrap();
+        let tmp_base = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let tmp_bound = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
 
         // Load base into temporary register.
         self.assembler
@@ -525,7 +567,9 @@ impl MachineX86_64 {
 
         let align = memarg.align;
         if check_alignment && align != 1 {
-            let tmp_aligncheck = self.acquire_temp_gpr().unwrap();
+            let tmp_aligncheck = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
             self.assembler.emit_mov(
                 Size::S32,
                 Location::GPR(tmp_addr),
@@ -541,14 +585,15 @@ impl MachineX86_64 {
             self.release_gpr(tmp_aligncheck);
         }
         let begin = self.assembler.get_offset().0;
-        cb(self, tmp_addr);
+        cb(self, tmp_addr)?;
         let end = self.assembler.get_offset().0;
         self.mark_address_range_with_trap_code(TrapCode::HeapAccessOutOfBounds, begin, end);
 
         self.release_gpr(tmp_addr);
+        Ok(())
     }
 
-    fn emit_compare_and_swap<F: FnOnce(&mut Self, GPR, GPR)>(
+    fn emit_compare_and_swap<F: FnOnce(&mut Self, GPR, GPR) -> Result<(), CodegenError>>(
         &mut self,
         loc: Location,
         target: Location,
@@ -562,9 +607,9 @@ impl MachineX86_64 {
         offset: i32,
         heap_access_oob: Label,
         cb: F,
-    ) {
+    ) -> Result<(), CodegenError> {
         if memory_sz > stack_sz {
-            unreachable!();
+            codegen_error!("singlepass emit_compare_and_swap unreachable");
         }
 
         let compare = self.reserve_unused_temp_gpr(GPR::RAX);
@@ -575,7 +620,7 @@ impl MachineX86_64 {
         };
         self.assembler.emit_push(Size::S64, Location::GPR(value));
 
-        self.move_location(stack_sz, loc, Location::GPR(value));
+        self.move_location(stack_sz, loc, Location::GPR(value))?;
 
         let retry = self.assembler.get_label();
         self.emit_label(retry);
@@ -590,21 +635,23 @@ impl MachineX86_64 {
             offset,
             heap_access_oob,
             |this, addr| {
-                this.load_address(memory_sz, Location::GPR(compare), Location::Memory(addr, 0));
-                this.move_location(stack_sz, Location::GPR(compare), ret);
-                cb(this, compare, value);
+                this.load_address(memory_sz, Location::GPR(compare), Location::Memory(addr, 0))?;
+                this.move_location(stack_sz, Location::GPR(compare), ret)?;
+                cb(this, compare, value)?;
                 this.assembler.emit_lock_cmpxchg(
                     memory_sz,
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
+        )?;
 
         self.jmp_on_different(retry);
 
         self.assembler.emit_pop(Size::S64, Location::GPR(value));
         self.release_gpr(compare);
+        Ok(())
     }
 
     // Checks for underflow/overflow/nan.
@@ -617,30 +664,34 @@ impl MachineX86_64 {
         overflow_label: Label,
         nan_label: Label,
         succeed_label: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         let lower_bound = f32::to_bits(lower_bound);
         let upper_bound = f32::to_bits(upper_bound);
 
-        let tmp = self.acquire_temp_gpr().unwrap();
-        let tmp_x = self.acquire_temp_simd().unwrap();
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let tmp_x = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
 
         // Underflow.
-        self.move_location(Size::S32, Location::Imm32(lower_bound), Location::GPR(tmp));
-        self.move_location(Size::S32, Location::GPR(tmp), Location::SIMD(tmp_x));
+        self.move_location(Size::S32, Location::Imm32(lower_bound), Location::GPR(tmp))?;
+        self.move_location(Size::S32, Location::GPR(tmp), Location::SIMD(tmp_x))?;
         self.assembler
             .emit_vcmpless(reg, XMMOrMemory::XMM(tmp_x), tmp_x);
-        self.move_location(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp));
+        self.move_location(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp))?;
         self.assembler
             .emit_cmp(Size::S32, Location::Imm32(0), Location::GPR(tmp));
         self.assembler
             .emit_jmp(Condition::NotEqual, underflow_label);
 
         // Overflow.
-        self.move_location(Size::S32, Location::Imm32(upper_bound), Location::GPR(tmp));
-        self.move_location(Size::S32, Location::GPR(tmp), Location::SIMD(tmp_x));
+        self.move_location(Size::S32, Location::Imm32(upper_bound), Location::GPR(tmp))?;
+        self.move_location(Size::S32, Location::GPR(tmp), Location::SIMD(tmp_x))?;
         self.assembler
             .emit_vcmpgess(reg, XMMOrMemory::XMM(tmp_x), tmp_x);
-        self.move_location(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp));
+        self.move_location(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp))?;
         self.assembler
             .emit_cmp(Size::S32, Location::Imm32(0), Location::GPR(tmp));
         self.assembler.emit_jmp(Condition::NotEqual, overflow_label);
@@ -648,7 +699,7 @@ impl MachineX86_64 {
         // NaN.
         self.assembler
             .emit_vcmpeqss(reg, XMMOrMemory::XMM(reg), tmp_x);
-        self.move_location(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp));
+        self.move_location(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp))?;
         self.assembler
             .emit_cmp(Size::S32, Location::Imm32(0), Location::GPR(tmp));
         self.assembler.emit_jmp(Condition::Equal, nan_label);
@@ -657,10 +708,16 @@ impl MachineX86_64 {
 
         self.release_simd(tmp_x);
         self.release_gpr(tmp);
+        Ok(())
     }
 
     // Checks for underflow/overflow/nan before IxxTrunc{U/S}F32.
-    fn emit_f32_int_conv_check_trap(&mut self, reg: XMM, lower_bound: f32, upper_bound: f32) {
+    fn emit_f32_int_conv_check_trap(
+        &mut self,
+        reg: XMM,
+        lower_bound: f32,
+        upper_bound: f32,
+    ) -> Result<(), CodegenError> {
         let trap_overflow = self.assembler.get_label();
         let trap_badconv = self.assembler.get_label();
         let end = self.assembler.get_label();
@@ -673,7 +730,7 @@ impl MachineX86_64 {
             trap_overflow,
             trap_badconv,
             end,
-        );
+        )?;
 
         self.emit_label(trap_overflow);
 
@@ -684,12 +741,13 @@ impl MachineX86_64 {
         self.emit_illegal_op_internal(TrapCode::BadConversionToInteger);
 
         self.emit_label(end);
+        Ok(())
     }
     fn emit_f32_int_conv_check_sat<
-        F1: FnOnce(&mut Self),
-        F2: FnOnce(&mut Self),
-        F3: FnOnce(&mut Self),
-        F4: FnOnce(&mut Self),
+        F1: FnOnce(&mut Self) -> Result<(), CodegenError>,
+        F2: FnOnce(&mut Self) -> Result<(), CodegenError>,
+        F3: FnOnce(&mut Self) -> Result<(), CodegenError>,
+        F4: FnOnce(&mut Self) -> Result<(), CodegenError>,
     >(
         &mut self,
         reg: XMM,
@@ -699,7 +757,7 @@ impl MachineX86_64 {
         overflow_cb: F2,
         nan_cb: Option<F3>,
         convert_cb: F4,
-    ) {
+    ) -> Result<(), CodegenError> {
         // As an optimization nan_cb is optional, and when set to None we turn
         // use 'underflow' as the 'nan' label. This is useful for callers who
         // set the return value to zero for both underflow and nan.
@@ -722,25 +780,26 @@ impl MachineX86_64 {
             overflow,
             nan,
             convert,
-        );
+        )?;
 
         self.emit_label(underflow);
-        underflow_cb(self);
+        underflow_cb(self)?;
         self.assembler.emit_jmp(Condition::None, end);
 
         self.emit_label(overflow);
-        overflow_cb(self);
+        overflow_cb(self)?;
         self.assembler.emit_jmp(Condition::None, end);
 
         if let Some(cb) = nan_cb {
             self.emit_label(nan);
-            cb(self);
+            cb(self)?;
             self.assembler.emit_jmp(Condition::None, end);
         }
 
         self.emit_label(convert);
-        convert_cb(self);
+        convert_cb(self)?;
         self.emit_label(end);
+        Ok(())
     }
     // Checks for underflow/overflow/nan.
     fn emit_f64_int_conv_check(
@@ -752,30 +811,34 @@ impl MachineX86_64 {
         overflow_label: Label,
         nan_label: Label,
         succeed_label: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         let lower_bound = f64::to_bits(lower_bound);
         let upper_bound = f64::to_bits(upper_bound);
 
-        let tmp = self.acquire_temp_gpr().unwrap();
-        let tmp_x = self.acquire_temp_simd().unwrap();
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let tmp_x = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
 
         // Underflow.
-        self.move_location(Size::S64, Location::Imm64(lower_bound), Location::GPR(tmp));
-        self.move_location(Size::S64, Location::GPR(tmp), Location::SIMD(tmp_x));
+        self.move_location(Size::S64, Location::Imm64(lower_bound), Location::GPR(tmp))?;
+        self.move_location(Size::S64, Location::GPR(tmp), Location::SIMD(tmp_x))?;
         self.assembler
             .emit_vcmplesd(reg, XMMOrMemory::XMM(tmp_x), tmp_x);
-        self.move_location(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp));
+        self.move_location(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp))?;
         self.assembler
             .emit_cmp(Size::S32, Location::Imm32(0), Location::GPR(tmp));
         self.assembler
             .emit_jmp(Condition::NotEqual, underflow_label);
 
         // Overflow.
-        self.move_location(Size::S64, Location::Imm64(upper_bound), Location::GPR(tmp));
-        self.move_location(Size::S64, Location::GPR(tmp), Location::SIMD(tmp_x));
+        self.move_location(Size::S64, Location::Imm64(upper_bound), Location::GPR(tmp))?;
+        self.move_location(Size::S64, Location::GPR(tmp), Location::SIMD(tmp_x))?;
         self.assembler
             .emit_vcmpgesd(reg, XMMOrMemory::XMM(tmp_x), tmp_x);
-        self.move_location(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp));
+        self.move_location(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp))?;
         self.assembler
             .emit_cmp(Size::S32, Location::Imm32(0), Location::GPR(tmp));
         self.assembler.emit_jmp(Condition::NotEqual, overflow_label);
@@ -783,7 +846,7 @@ impl MachineX86_64 {
         // NaN.
         self.assembler
             .emit_vcmpeqsd(reg, XMMOrMemory::XMM(reg), tmp_x);
-        self.move_location(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp));
+        self.move_location(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp))?;
         self.assembler
             .emit_cmp(Size::S32, Location::Imm32(0), Location::GPR(tmp));
         self.assembler.emit_jmp(Condition::Equal, nan_label);
@@ -792,9 +855,15 @@ impl MachineX86_64 {
 
         self.release_simd(tmp_x);
         self.release_gpr(tmp);
+        Ok(())
     }
     // Checks for underflow/overflow/nan before IxxTrunc{U/S}F64.. return offset/len for trap_overflow and trap_badconv
-    fn emit_f64_int_conv_check_trap(&mut self, reg: XMM, lower_bound: f64, upper_bound: f64) {
+    fn emit_f64_int_conv_check_trap(
+        &mut self,
+        reg: XMM,
+        lower_bound: f64,
+        upper_bound: f64,
+    ) -> Result<(), CodegenError> {
         let trap_overflow = self.assembler.get_label();
         let trap_badconv = self.assembler.get_label();
         let end = self.assembler.get_label();
@@ -807,7 +876,7 @@ impl MachineX86_64 {
             trap_overflow,
             trap_badconv,
             end,
-        );
+        )?;
 
         self.emit_label(trap_overflow);
         self.emit_illegal_op_internal(TrapCode::IntegerOverflow);
@@ -816,12 +885,13 @@ impl MachineX86_64 {
         self.emit_illegal_op_internal(TrapCode::BadConversionToInteger);
 
         self.emit_label(end);
+        Ok(())
     }
     fn emit_f64_int_conv_check_sat<
-        F1: FnOnce(&mut Self),
-        F2: FnOnce(&mut Self),
-        F3: FnOnce(&mut Self),
-        F4: FnOnce(&mut Self),
+        F1: FnOnce(&mut Self) -> Result<(), CodegenError>,
+        F2: FnOnce(&mut Self) -> Result<(), CodegenError>,
+        F3: FnOnce(&mut Self) -> Result<(), CodegenError>,
+        F4: FnOnce(&mut Self) -> Result<(), CodegenError>,
     >(
         &mut self,
         reg: XMM,
@@ -831,7 +901,7 @@ impl MachineX86_64 {
         overflow_cb: F2,
         nan_cb: Option<F3>,
         convert_cb: F4,
-    ) {
+    ) -> Result<(), CodegenError> {
         // As an optimization nan_cb is optional, and when set to None we turn
         // use 'underflow' as the 'nan' label. This is useful for callers who
         // set the return value to zero for both underflow and nan.
@@ -854,25 +924,26 @@ impl MachineX86_64 {
             overflow,
             nan,
             convert,
-        );
+        )?;
 
         self.emit_label(underflow);
-        underflow_cb(self);
+        underflow_cb(self)?;
         self.assembler.emit_jmp(Condition::None, end);
 
         self.emit_label(overflow);
-        overflow_cb(self);
+        overflow_cb(self)?;
         self.assembler.emit_jmp(Condition::None, end);
 
         if let Some(cb) = nan_cb {
             self.emit_label(nan);
-            cb(self);
+            cb(self)?;
             self.assembler.emit_jmp(Condition::None, end);
         }
 
         self.emit_label(convert);
-        convert_cb(self);
+        convert_cb(self)?;
         self.emit_label(end);
+        Ok(())
     }
     /// Moves `src1` and `src2` to valid locations and possibly adds a layer of indirection for `dst` for AVX instructions.
     fn emit_relaxed_avx(
@@ -881,27 +952,40 @@ impl MachineX86_64 {
         src1: Location,
         src2: Location,
         dst: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_relaxed_avx_base(
-            |this, src1, src2, dst| op(&mut this.assembler, src1, src2, dst),
+            |this, src1, src2, dst| {
+                op(&mut this.assembler, src1, src2, dst);
+                Ok(())
+            },
             src1,
             src2,
             dst,
-        );
+        )
     }
 
     /// Moves `src1` and `src2` to valid locations and possibly adds a layer of indirection for `dst` for AVX instructions.
-    fn emit_relaxed_avx_base<F: FnOnce(&mut Self, XMM, XMMOrMemory, XMM)>(
+    fn emit_relaxed_avx_base<
+        F: FnOnce(&mut Self, XMM, XMMOrMemory, XMM) -> Result<(), CodegenError>,
+    >(
         &mut self,
         op: F,
         src1: Location,
         src2: Location,
         dst: Location,
-    ) {
-        let tmp1 = self.acquire_temp_simd().unwrap();
-        let tmp2 = self.acquire_temp_simd().unwrap();
-        let tmp3 = self.acquire_temp_simd().unwrap();
-        let tmpg = self.acquire_temp_gpr().unwrap();
+    ) -> Result<(), CodegenError> {
+        let tmp1 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
+        let tmp2 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
+        let tmp3 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
+        let tmpg = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
 
         let src1 = match src1 {
             Location::SIMD(x) => x,
@@ -913,17 +997,17 @@ impl MachineX86_64 {
             Location::Imm32(_) => {
                 self.assembler
                     .emit_mov(Size::S32, src1, Location::GPR(tmpg));
-                self.move_location(Size::S32, Location::GPR(tmpg), Location::SIMD(tmp1));
+                self.move_location(Size::S32, Location::GPR(tmpg), Location::SIMD(tmp1))?;
                 tmp1
             }
             Location::Imm64(_) => {
                 self.assembler
                     .emit_mov(Size::S64, src1, Location::GPR(tmpg));
-                self.move_location(Size::S64, Location::GPR(tmpg), Location::SIMD(tmp1));
+                self.move_location(Size::S64, Location::GPR(tmpg), Location::SIMD(tmp1))?;
                 tmp1
             }
             _ => {
-                unreachable!()
+                codegen_error!("singlepass emit_relaxed_avx_base unreachable")
             }
         };
 
@@ -938,31 +1022,31 @@ impl MachineX86_64 {
             Location::Imm32(_) => {
                 self.assembler
                     .emit_mov(Size::S32, src2, Location::GPR(tmpg));
-                self.move_location(Size::S32, Location::GPR(tmpg), Location::SIMD(tmp2));
+                self.move_location(Size::S32, Location::GPR(tmpg), Location::SIMD(tmp2))?;
                 XMMOrMemory::XMM(tmp2)
             }
             Location::Imm64(_) => {
                 self.assembler
                     .emit_mov(Size::S64, src2, Location::GPR(tmpg));
-                self.move_location(Size::S64, Location::GPR(tmpg), Location::SIMD(tmp2));
+                self.move_location(Size::S64, Location::GPR(tmpg), Location::SIMD(tmp2))?;
                 XMMOrMemory::XMM(tmp2)
             }
             _ => {
-                unreachable!()
+                codegen_error!("singlepass emit_relaxed_avx_base unreachable")
             }
         };
 
         match dst {
             Location::SIMD(x) => {
-                op(self, src1, src2, x);
+                op(self, src1, src2, x)?;
             }
             Location::Memory(_, _) | Location::GPR(_) => {
-                op(self, src1, src2, tmp3);
+                op(self, src1, src2, tmp3)?;
                 self.assembler
                     .emit_mov(Size::S64, Location::SIMD(tmp3), dst);
             }
             _ => {
-                unreachable!()
+                codegen_error!("singlepass emit_relaxed_avx_base unreachable")
             }
         }
 
@@ -970,13 +1054,18 @@ impl MachineX86_64 {
         self.release_simd(tmp3);
         self.release_simd(tmp2);
         self.release_simd(tmp1);
+        Ok(())
     }
 
-    fn convert_i64_f64_u_s(&mut self, loc: Location, ret: Location) {
-        let tmp_out = self.acquire_temp_gpr().unwrap();
-        let tmp_in = self.acquire_temp_simd().unwrap();
+    fn convert_i64_f64_u_s(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
 
-        self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in));
+        self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in))?;
         self.emit_f64_int_conv_check_sat(
             tmp_in,
             GEF64_LT_U64_MIN,
@@ -984,6 +1073,7 @@ impl MachineX86_64 {
             |this| {
                 this.assembler
                     .emit_mov(Size::S64, Location::Imm64(0), Location::GPR(tmp_out));
+                Ok(())
             },
             |this| {
                 this.assembler.emit_mov(
@@ -991,15 +1081,22 @@ impl MachineX86_64 {
                     Location::Imm64(std::u64::MAX),
                     Location::GPR(tmp_out),
                 );
+                Ok(())
             },
-            None::<fn(this: &mut Self)>,
+            None::<fn(this: &mut Self) -> Result<(), CodegenError>>,
             |this| {
                 if this.assembler.arch_has_itruncf() {
                     this.assembler.arch_emit_i64_trunc_uf64(tmp_in, tmp_out);
                 } else {
-                    let tmp = this.acquire_temp_gpr().unwrap();
-                    let tmp_x1 = this.acquire_temp_simd().unwrap();
-                    let tmp_x2 = this.acquire_temp_simd().unwrap();
+                    let tmp = this.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
+                    let tmp_x1 = this.acquire_temp_simd().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp simd".to_owned(),
+                    })?;
+                    let tmp_x2 = this.acquire_temp_simd().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp simd".to_owned(),
+                    })?;
 
                     this.assembler.emit_mov(
                         Size::S64,
@@ -1034,41 +1131,57 @@ impl MachineX86_64 {
                     this.release_simd(tmp_x1);
                     this.release_gpr(tmp);
                 }
+                Ok(())
             },
-        );
+        )?;
 
         self.assembler
             .emit_mov(Size::S64, Location::GPR(tmp_out), ret);
         self.release_simd(tmp_in);
         self.release_gpr(tmp_out);
+        Ok(())
     }
-    fn convert_i64_f64_u_u(&mut self, loc: Location, ret: Location) {
+    fn convert_i64_f64_u_u(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         if self.assembler.arch_has_itruncf() {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap();
-            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in));
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in))?;
             self.assembler.arch_emit_i64_trunc_uf64(tmp_in, tmp_out);
-            self.emit_relaxed_mov(Size::S64, Location::GPR(tmp_out), ret);
+            self.emit_relaxed_mov(Size::S64, Location::GPR(tmp_out), ret)?;
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         } else {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap(); // xmm2
-
-            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in));
-            self.emit_f64_int_conv_check_trap(tmp_in, GEF64_LT_U64_MIN, LEF64_GT_U64_MAX);
-
-            let tmp = self.acquire_temp_gpr().unwrap(); // r15
-            let tmp_x1 = self.acquire_temp_simd().unwrap(); // xmm1
-            let tmp_x2 = self.acquire_temp_simd().unwrap(); // xmm3
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?; // xmm2
+
+            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in))?;
+            self.emit_f64_int_conv_check_trap(tmp_in, GEF64_LT_U64_MIN, LEF64_GT_U64_MAX)?;
+
+            let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?; // r15
+            let tmp_x1 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?; // xmm1
+            let tmp_x2 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?; // xmm3
 
             self.move_location(
                 Size::S64,
                 Location::Imm64(4890909195324358656u64),
                 Location::GPR(tmp),
-            ); //double 9.2233720368547758E+18
-            self.move_location(Size::S64, Location::GPR(tmp), Location::SIMD(tmp_x1));
-            self.move_location(Size::S64, Location::SIMD(tmp_in), Location::SIMD(tmp_x2));
+            )?; //double 9.2233720368547758E+18
+            self.move_location(Size::S64, Location::GPR(tmp), Location::SIMD(tmp_x1))?;
+            self.move_location(Size::S64, Location::SIMD(tmp_in), Location::SIMD(tmp_x2))?;
             self.assembler
                 .emit_vsubsd(tmp_in, XMMOrMemory::XMM(tmp_x1), tmp_in);
             self.assembler
@@ -1077,7 +1190,7 @@ impl MachineX86_64 {
                 Size::S64,
                 Location::Imm64(0x8000000000000000u64),
                 Location::GPR(tmp),
-            );
+            )?;
             self.assembler
                 .emit_xor(Size::S64, Location::GPR(tmp_out), Location::GPR(tmp));
             self.assembler
@@ -1085,7 +1198,7 @@ impl MachineX86_64 {
             self.assembler
                 .emit_ucomisd(XMMOrMemory::XMM(tmp_x1), tmp_x2);
             self.assembler.emit_cmovae_gpr_64(tmp, tmp_out);
-            self.move_location(Size::S64, Location::GPR(tmp_out), ret);
+            self.move_location(Size::S64, Location::GPR(tmp_out), ret)?;
 
             self.release_simd(tmp_x2);
             self.release_simd(tmp_x1);
@@ -1093,12 +1206,17 @@ impl MachineX86_64 {
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         }
+        Ok(())
     }
-    fn convert_i64_f64_s_s(&mut self, loc: Location, ret: Location) {
-        let tmp_out = self.acquire_temp_gpr().unwrap();
-        let tmp_in = self.acquire_temp_simd().unwrap();
+    fn convert_i64_f64_s_s(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
 
-        self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in));
+        self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in))?;
         self.emit_f64_int_conv_check_sat(
             tmp_in,
             GEF64_LT_I64_MIN,
@@ -1109,6 +1227,7 @@ impl MachineX86_64 {
                     Location::Imm64(std::i64::MIN as u64),
                     Location::GPR(tmp_out),
                 );
+                Ok(())
             },
             |this| {
                 this.assembler.emit_mov(
@@ -1116,10 +1235,12 @@ impl MachineX86_64 {
                     Location::Imm64(std::i64::MAX as u64),
                     Location::GPR(tmp_out),
                 );
+                Ok(())
             },
             Some(|this: &mut Self| {
                 this.assembler
                     .emit_mov(Size::S64, Location::Imm64(0), Location::GPR(tmp_out));
+                Ok(())
             }),
             |this| {
                 if this.assembler.arch_has_itruncf() {
@@ -1128,51 +1249,66 @@ impl MachineX86_64 {
                     this.assembler
                         .emit_cvttsd2si_64(XMMOrMemory::XMM(tmp_in), tmp_out);
                 }
+                Ok(())
             },
-        );
+        )?;
 
         self.assembler
             .emit_mov(Size::S64, Location::GPR(tmp_out), ret);
         self.release_simd(tmp_in);
         self.release_gpr(tmp_out);
+        Ok(())
     }
-    fn convert_i64_f64_s_u(&mut self, loc: Location, ret: Location) {
+    fn convert_i64_f64_s_u(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         if self.assembler.arch_has_itruncf() {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap();
-            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in));
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in))?;
             self.assembler.arch_emit_i64_trunc_sf64(tmp_in, tmp_out);
-            self.emit_relaxed_mov(Size::S64, Location::GPR(tmp_out), ret);
+            self.emit_relaxed_mov(Size::S64, Location::GPR(tmp_out), ret)?;
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         } else {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap();
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
 
-            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in));
-            self.emit_f64_int_conv_check_trap(tmp_in, GEF64_LT_I64_MIN, LEF64_GT_I64_MAX);
+            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in))?;
+            self.emit_f64_int_conv_check_trap(tmp_in, GEF64_LT_I64_MIN, LEF64_GT_I64_MAX)?;
 
             self.assembler
                 .emit_cvttsd2si_64(XMMOrMemory::XMM(tmp_in), tmp_out);
-            self.move_location(Size::S64, Location::GPR(tmp_out), ret);
+            self.move_location(Size::S64, Location::GPR(tmp_out), ret)?;
 
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         }
+        Ok(())
     }
-    fn convert_i32_f64_s_s(&mut self, loc: Location, ret: Location) {
-        let tmp_out = self.acquire_temp_gpr().unwrap();
-        let tmp_in = self.acquire_temp_simd().unwrap();
+    fn convert_i32_f64_s_s(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
 
         let real_in = match loc {
             Location::Imm32(_) | Location::Imm64(_) => {
-                self.move_location(Size::S64, loc, Location::GPR(tmp_out));
-                self.move_location(Size::S64, Location::GPR(tmp_out), Location::SIMD(tmp_in));
+                self.move_location(Size::S64, loc, Location::GPR(tmp_out))?;
+                self.move_location(Size::S64, Location::GPR(tmp_out), Location::SIMD(tmp_in))?;
                 tmp_in
             }
             Location::SIMD(x) => x,
             _ => {
-                self.move_location(Size::S64, loc, Location::SIMD(tmp_in));
+                self.move_location(Size::S64, loc, Location::SIMD(tmp_in))?;
                 tmp_in
             }
         };
@@ -1187,6 +1323,7 @@ impl MachineX86_64 {
                     Location::Imm32(std::i32::MIN as u32),
                     Location::GPR(tmp_out),
                 );
+                Ok(())
             },
             |this| {
                 this.assembler.emit_mov(
@@ -1194,10 +1331,12 @@ impl MachineX86_64 {
                     Location::Imm32(std::i32::MAX as u32),
                     Location::GPR(tmp_out),
                 );
+                Ok(())
             },
             Some(|this: &mut Self| {
                 this.assembler
                     .emit_mov(Size::S32, Location::Imm32(0), Location::GPR(tmp_out));
+                Ok(())
             }),
             |this| {
                 if this.assembler.arch_has_itruncf() {
@@ -1206,55 +1345,70 @@ impl MachineX86_64 {
                     this.assembler
                         .emit_cvttsd2si_32(XMMOrMemory::XMM(real_in), tmp_out);
                 }
+                Ok(())
             },
-        );
+        )?;
 
         self.assembler
             .emit_mov(Size::S32, Location::GPR(tmp_out), ret);
         self.release_simd(tmp_in);
         self.release_gpr(tmp_out);
+        Ok(())
     }
-    fn convert_i32_f64_s_u(&mut self, loc: Location, ret: Location) {
+    fn convert_i32_f64_s_u(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         if self.assembler.arch_has_itruncf() {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap();
-            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in));
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in))?;
             self.assembler.arch_emit_i32_trunc_sf64(tmp_in, tmp_out);
-            self.emit_relaxed_mov(Size::S32, Location::GPR(tmp_out), ret);
+            self.emit_relaxed_mov(Size::S32, Location::GPR(tmp_out), ret)?;
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         } else {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap();
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
 
             let real_in = match loc {
                 Location::Imm32(_) | Location::Imm64(_) => {
-                    self.move_location(Size::S64, loc, Location::GPR(tmp_out));
-                    self.move_location(Size::S64, Location::GPR(tmp_out), Location::SIMD(tmp_in));
+                    self.move_location(Size::S64, loc, Location::GPR(tmp_out))?;
+                    self.move_location(Size::S64, Location::GPR(tmp_out), Location::SIMD(tmp_in))?;
                     tmp_in
                 }
                 Location::SIMD(x) => x,
                 _ => {
-                    self.move_location(Size::S64, loc, Location::SIMD(tmp_in));
+                    self.move_location(Size::S64, loc, Location::SIMD(tmp_in))?;
                     tmp_in
                 }
             };
 
-            self.emit_f64_int_conv_check_trap(real_in, GEF64_LT_I32_MIN, LEF64_GT_I32_MAX);
+            self.emit_f64_int_conv_check_trap(real_in, GEF64_LT_I32_MIN, LEF64_GT_I32_MAX)?;
 
             self.assembler
                 .emit_cvttsd2si_32(XMMOrMemory::XMM(real_in), tmp_out);
-            self.move_location(Size::S32, Location::GPR(tmp_out), ret);
+            self.move_location(Size::S32, Location::GPR(tmp_out), ret)?;
 
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         }
+        Ok(())
     }
-    fn convert_i32_f64_u_s(&mut self, loc: Location, ret: Location) {
-        let tmp_out = self.acquire_temp_gpr().unwrap();
-        let tmp_in = self.acquire_temp_simd().unwrap();
+    fn convert_i32_f64_u_s(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
 
-        self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in));
+        self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in))?;
         self.emit_f64_int_conv_check_sat(
             tmp_in,
             GEF64_LT_U32_MIN,
@@ -1262,6 +1416,7 @@ impl MachineX86_64 {
             |this| {
                 this.assembler
                     .emit_mov(Size::S32, Location::Imm32(0), Location::GPR(tmp_out));
+                Ok(())
             },
             |this| {
                 this.assembler.emit_mov(
@@ -1269,8 +1424,9 @@ impl MachineX86_64 {
                     Location::Imm32(std::u32::MAX),
                     Location::GPR(tmp_out),
                 );
+                Ok(())
             },
-            None::<fn(this: &mut Self)>,
+            None::<fn(this: &mut Self) -> Result<(), CodegenError>>,
             |this| {
                 if this.assembler.arch_has_itruncf() {
                     this.assembler.arch_emit_i32_trunc_uf64(tmp_in, tmp_out);
@@ -1278,43 +1434,58 @@ impl MachineX86_64 {
                     this.assembler
                         .emit_cvttsd2si_64(XMMOrMemory::XMM(tmp_in), tmp_out);
                 }
+                Ok(())
             },
-        );
+        )?;
 
         self.assembler
             .emit_mov(Size::S32, Location::GPR(tmp_out), ret);
         self.release_simd(tmp_in);
         self.release_gpr(tmp_out);
+        Ok(())
     }
-    fn convert_i32_f64_u_u(&mut self, loc: Location, ret: Location) {
+    fn convert_i32_f64_u_u(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         if self.assembler.arch_has_itruncf() {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap();
-            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in));
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in))?;
             self.assembler.arch_emit_i32_trunc_uf64(tmp_in, tmp_out);
-            self.emit_relaxed_mov(Size::S32, Location::GPR(tmp_out), ret);
+            self.emit_relaxed_mov(Size::S32, Location::GPR(tmp_out), ret)?;
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         } else {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap();
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
 
-            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in));
-            self.emit_f64_int_conv_check_trap(tmp_in, GEF64_LT_U32_MIN, LEF64_GT_U32_MAX);
+            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp_in))?;
+            self.emit_f64_int_conv_check_trap(tmp_in, GEF64_LT_U32_MIN, LEF64_GT_U32_MAX)?;
 
             self.assembler
                 .emit_cvttsd2si_64(XMMOrMemory::XMM(tmp_in), tmp_out);
-            self.move_location(Size::S32, Location::GPR(tmp_out), ret);
+            self.move_location(Size::S32, Location::GPR(tmp_out), ret)?;
 
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         }
+        Ok(())
     }
-    fn convert_i64_f32_u_s(&mut self, loc: Location, ret: Location) {
-        let tmp_out = self.acquire_temp_gpr().unwrap();
-        let tmp_in = self.acquire_temp_simd().unwrap();
+    fn convert_i64_f32_u_s(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
 
-        self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in));
+        self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in))?;
         self.emit_f32_int_conv_check_sat(
             tmp_in,
             GEF32_LT_U64_MIN,
@@ -1322,6 +1493,7 @@ impl MachineX86_64 {
             |this| {
                 this.assembler
                     .emit_mov(Size::S64, Location::Imm64(0), Location::GPR(tmp_out));
+                Ok(())
             },
             |this| {
                 this.assembler.emit_mov(
@@ -1329,15 +1501,22 @@ impl MachineX86_64 {
                     Location::Imm64(std::u64::MAX),
                     Location::GPR(tmp_out),
                 );
+                Ok(())
             },
-            None::<fn(this: &mut Self)>,
+            None::<fn(this: &mut Self) -> Result<(), CodegenError>>,
             |this| {
                 if this.assembler.arch_has_itruncf() {
                     this.assembler.arch_emit_i64_trunc_uf32(tmp_in, tmp_out);
                 } else {
-                    let tmp = this.acquire_temp_gpr().unwrap();
-                    let tmp_x1 = this.acquire_temp_simd().unwrap();
-                    let tmp_x2 = this.acquire_temp_simd().unwrap();
+                    let tmp = this.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
+                    let tmp_x1 = this.acquire_temp_simd().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp simd".to_owned(),
+                    })?;
+                    let tmp_x2 = this.acquire_temp_simd().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp simd".to_owned(),
+                    })?;
 
                     this.assembler.emit_mov(
                         Size::S32,
@@ -1372,41 +1551,57 @@ impl MachineX86_64 {
                     this.release_simd(tmp_x1);
                     this.release_gpr(tmp);
                 }
+                Ok(())
             },
-        );
+        )?;
 
         self.assembler
             .emit_mov(Size::S64, Location::GPR(tmp_out), ret);
         self.release_simd(tmp_in);
         self.release_gpr(tmp_out);
+        Ok(())
     }
-    fn convert_i64_f32_u_u(&mut self, loc: Location, ret: Location) {
+    fn convert_i64_f32_u_u(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         if self.assembler.arch_has_itruncf() {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap();
-            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in));
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in))?;
             self.assembler.arch_emit_i64_trunc_uf32(tmp_in, tmp_out);
-            self.emit_relaxed_mov(Size::S64, Location::GPR(tmp_out), ret);
+            self.emit_relaxed_mov(Size::S64, Location::GPR(tmp_out), ret)?;
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         } else {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap(); // xmm2
-
-            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in));
-            self.emit_f32_int_conv_check_trap(tmp_in, GEF32_LT_U64_MIN, LEF32_GT_U64_MAX);
-
-            let tmp = self.acquire_temp_gpr().unwrap(); // r15
-            let tmp_x1 = self.acquire_temp_simd().unwrap(); // xmm1
-            let tmp_x2 = self.acquire_temp_simd().unwrap(); // xmm3
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?; // xmm2
+
+            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in))?;
+            self.emit_f32_int_conv_check_trap(tmp_in, GEF32_LT_U64_MIN, LEF32_GT_U64_MAX)?;
+
+            let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?; // r15
+            let tmp_x1 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?; // xmm1
+            let tmp_x2 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?; // xmm3
 
             self.move_location(
                 Size::S32,
                 Location::Imm32(1593835520u32),
                 Location::GPR(tmp),
-            ); //float 9.22337203E+18
-            self.move_location(Size::S32, Location::GPR(tmp), Location::SIMD(tmp_x1));
-            self.move_location(Size::S32, Location::SIMD(tmp_in), Location::SIMD(tmp_x2));
+            )?; //float 9.22337203E+18
+            self.move_location(Size::S32, Location::GPR(tmp), Location::SIMD(tmp_x1))?;
+            self.move_location(Size::S32, Location::SIMD(tmp_in), Location::SIMD(tmp_x2))?;
             self.assembler
                 .emit_vsubss(tmp_in, XMMOrMemory::XMM(tmp_x1), tmp_in);
             self.assembler
@@ -1415,7 +1610,7 @@ impl MachineX86_64 {
                 Size::S64,
                 Location::Imm64(0x8000000000000000u64),
                 Location::GPR(tmp),
-            );
+            )?;
             self.assembler
                 .emit_xor(Size::S64, Location::GPR(tmp_out), Location::GPR(tmp));
             self.assembler
@@ -1423,7 +1618,7 @@ impl MachineX86_64 {
             self.assembler
                 .emit_ucomiss(XMMOrMemory::XMM(tmp_x1), tmp_x2);
             self.assembler.emit_cmovae_gpr_64(tmp, tmp_out);
-            self.move_location(Size::S64, Location::GPR(tmp_out), ret);
+            self.move_location(Size::S64, Location::GPR(tmp_out), ret)?;
 
             self.release_simd(tmp_x2);
             self.release_simd(tmp_x1);
@@ -1431,12 +1626,17 @@ impl MachineX86_64 {
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         }
+        Ok(())
     }
-    fn convert_i64_f32_s_s(&mut self, loc: Location, ret: Location) {
-        let tmp_out = self.acquire_temp_gpr().unwrap();
-        let tmp_in = self.acquire_temp_simd().unwrap();
+    fn convert_i64_f32_s_s(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
 
-        self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in));
+        self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in))?;
         self.emit_f32_int_conv_check_sat(
             tmp_in,
             GEF32_LT_I64_MIN,
@@ -1447,6 +1647,7 @@ impl MachineX86_64 {
                     Location::Imm64(std::i64::MIN as u64),
                     Location::GPR(tmp_out),
                 );
+                Ok(())
             },
             |this| {
                 this.assembler.emit_mov(
@@ -1454,10 +1655,12 @@ impl MachineX86_64 {
                     Location::Imm64(std::i64::MAX as u64),
                     Location::GPR(tmp_out),
                 );
+                Ok(())
             },
             Some(|this: &mut Self| {
                 this.assembler
                     .emit_mov(Size::S64, Location::Imm64(0), Location::GPR(tmp_out));
+                Ok(())
             }),
             |this| {
                 if this.assembler.arch_has_itruncf() {
@@ -1466,42 +1669,57 @@ impl MachineX86_64 {
                     this.assembler
                         .emit_cvttss2si_64(XMMOrMemory::XMM(tmp_in), tmp_out);
                 }
+                Ok(())
             },
-        );
+        )?;
 
         self.assembler
             .emit_mov(Size::S64, Location::GPR(tmp_out), ret);
         self.release_simd(tmp_in);
         self.release_gpr(tmp_out);
+        Ok(())
     }
-    fn convert_i64_f32_s_u(&mut self, loc: Location, ret: Location) {
+    fn convert_i64_f32_s_u(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         if self.assembler.arch_has_itruncf() {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap();
-            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in));
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in))?;
             self.assembler.arch_emit_i64_trunc_sf32(tmp_in, tmp_out);
-            self.emit_relaxed_mov(Size::S64, Location::GPR(tmp_out), ret);
+            self.emit_relaxed_mov(Size::S64, Location::GPR(tmp_out), ret)?;
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         } else {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap();
-
-            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in));
-            self.emit_f32_int_conv_check_trap(tmp_in, GEF32_LT_I64_MIN, LEF32_GT_I64_MAX);
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+
+            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in))?;
+            self.emit_f32_int_conv_check_trap(tmp_in, GEF32_LT_I64_MIN, LEF32_GT_I64_MAX)?;
             self.assembler
                 .emit_cvttss2si_64(XMMOrMemory::XMM(tmp_in), tmp_out);
-            self.move_location(Size::S64, Location::GPR(tmp_out), ret);
+            self.move_location(Size::S64, Location::GPR(tmp_out), ret)?;
 
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         }
+        Ok(())
     }
-    fn convert_i32_f32_s_s(&mut self, loc: Location, ret: Location) {
-        let tmp_out = self.acquire_temp_gpr().unwrap();
-        let tmp_in = self.acquire_temp_simd().unwrap();
+    fn convert_i32_f32_s_s(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
 
-        self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in));
+        self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in))?;
         self.emit_f32_int_conv_check_sat(
             tmp_in,
             GEF32_LT_I32_MIN,
@@ -1512,6 +1730,7 @@ impl MachineX86_64 {
                     Location::Imm32(std::i32::MIN as u32),
                     Location::GPR(tmp_out),
                 );
+                Ok(())
             },
             |this| {
                 this.assembler.emit_mov(
@@ -1519,10 +1738,12 @@ impl MachineX86_64 {
                     Location::Imm32(std::i32::MAX as u32),
                     Location::GPR(tmp_out),
                 );
+                Ok(())
             },
             Some(|this: &mut Self| {
                 this.assembler
                     .emit_mov(Size::S32, Location::Imm32(0), Location::GPR(tmp_out));
+                Ok(())
             }),
             |this| {
                 if this.assembler.arch_has_itruncf() {
@@ -1531,42 +1752,57 @@ impl MachineX86_64 {
                     this.assembler
                         .emit_cvttss2si_32(XMMOrMemory::XMM(tmp_in), tmp_out);
                 }
+                Ok(())
             },
-        );
+        )?;
 
         self.assembler
             .emit_mov(Size::S32, Location::GPR(tmp_out), ret);
         self.release_simd(tmp_in);
         self.release_gpr(tmp_out);
+        Ok(())
     }
-    fn convert_i32_f32_s_u(&mut self, loc: Location, ret: Location) {
+    fn convert_i32_f32_s_u(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         if self.assembler.arch_has_itruncf() {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap();
-            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in));
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in))?;
             self.assembler.arch_emit_i32_trunc_sf32(tmp_in, tmp_out);
-            self.emit_relaxed_mov(Size::S32, Location::GPR(tmp_out), ret);
+            self.emit_relaxed_mov(Size::S32, Location::GPR(tmp_out), ret)?;
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         } else {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap();
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
 
-            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in));
-            self.emit_f32_int_conv_check_trap(tmp_in, GEF32_LT_I32_MIN, LEF32_GT_I32_MAX);
+            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in))?;
+            self.emit_f32_int_conv_check_trap(tmp_in, GEF32_LT_I32_MIN, LEF32_GT_I32_MAX)?;
 
             self.assembler
                 .emit_cvttss2si_32(XMMOrMemory::XMM(tmp_in), tmp_out);
-            self.move_location(Size::S32, Location::GPR(tmp_out), ret);
+            self.move_location(Size::S32, Location::GPR(tmp_out), ret)?;
 
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         }
-    }
-    fn convert_i32_f32_u_s(&mut self, loc: Location, ret: Location) {
-        let tmp_out = self.acquire_temp_gpr().unwrap();
-        let tmp_in = self.acquire_temp_simd().unwrap();
-        self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in));
+        Ok(())
+    }
+    fn convert_i32_f32_u_s(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
+        self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in))?;
         self.emit_f32_int_conv_check_sat(
             tmp_in,
             GEF32_LT_U32_MIN,
@@ -1574,6 +1810,7 @@ impl MachineX86_64 {
             |this| {
                 this.assembler
                     .emit_mov(Size::S32, Location::Imm32(0), Location::GPR(tmp_out));
+                Ok(())
             },
             |this| {
                 this.assembler.emit_mov(
@@ -1581,8 +1818,9 @@ impl MachineX86_64 {
                     Location::Imm32(std::u32::MAX),
                     Location::GPR(tmp_out),
                 );
+                Ok(())
             },
-            None::<fn(this: &mut Self)>,
+            None::<fn(this: &mut Self) -> Result<(), CodegenError>>,
             |this| {
                 if this.assembler.arch_has_itruncf() {
                     this.assembler.arch_emit_i32_trunc_uf32(tmp_in, tmp_out);
@@ -1590,40 +1828,56 @@ impl MachineX86_64 {
                     this.assembler
                         .emit_cvttss2si_64(XMMOrMemory::XMM(tmp_in), tmp_out);
                 }
+                Ok(())
             },
-        );
+        )?;
 
         self.assembler
             .emit_mov(Size::S32, Location::GPR(tmp_out), ret);
         self.release_simd(tmp_in);
         self.release_gpr(tmp_out);
+        Ok(())
     }
-    fn convert_i32_f32_u_u(&mut self, loc: Location, ret: Location) {
+    fn convert_i32_f32_u_u(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         if self.assembler.arch_has_itruncf() {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap();
-            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in));
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in))?;
             self.assembler.arch_emit_i32_trunc_uf32(tmp_in, tmp_out);
-            self.emit_relaxed_mov(Size::S32, Location::GPR(tmp_out), ret);
+            self.emit_relaxed_mov(Size::S32, Location::GPR(tmp_out), ret)?;
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         } else {
-            let tmp_out = self.acquire_temp_gpr().unwrap();
-            let tmp_in = self.acquire_temp_simd().unwrap();
-            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in));
-            self.emit_f32_int_conv_check_trap(tmp_in, GEF32_LT_U32_MIN, LEF32_GT_U32_MAX);
+            let tmp_out = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp_in))?;
+            self.emit_f32_int_conv_check_trap(tmp_in, GEF32_LT_U32_MIN, LEF32_GT_U32_MAX)?;
 
             self.assembler
                 .emit_cvttss2si_64(XMMOrMemory::XMM(tmp_in), tmp_out);
-            self.move_location(Size::S32, Location::GPR(tmp_out), ret);
+            self.move_location(Size::S32, Location::GPR(tmp_out), ret)?;
 
             self.release_simd(tmp_in);
             self.release_gpr(tmp_out);
         }
+        Ok(())
     }
 
-    fn emit_relaxed_atomic_xchg(&mut self, sz: Size, src: Location, dst: Location) {
-        self.emit_relaxed_binop(AssemblerX64::emit_xchg, sz, src, dst);
+    fn emit_relaxed_atomic_xchg(
+        &mut self,
+        sz: Size,
+        src: Location,
+        dst: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop(AssemblerX64::emit_xchg, sz, src, dst)
     }
 
     fn used_gprs_contains(&self, r: &GPR) -> bool {
@@ -1745,16 +1999,17 @@ impl Machine for MachineX86_64 {
         self.used_gprs_insert(gpr);
     }
 
-    fn push_used_gpr(&mut self, used_gprs: &Vec<GPR>) -> usize {
+    fn push_used_gpr(&mut self, used_gprs: &Vec<GPR>) -> Result<usize, CodegenError> {
         for r in used_gprs.iter() {
             self.assembler.emit_push(Size::S64, Location::GPR(*r));
         }
-        used_gprs.len() * 8
+        Ok(used_gprs.len() * 8)
     }
-    fn pop_used_gpr(&mut self, used_gprs: &Vec<GPR>) {
+    fn pop_used_gpr(&mut self, used_gprs: &Vec<GPR>) -> Result<(), CodegenError> {
         for r in used_gprs.iter().rev() {
             self.assembler.emit_pop(Size::S64, Location::GPR(*r));
         }
+        Ok(())
     }
 
     // Picks an unused XMM register.
@@ -1799,32 +2054,34 @@ impl Machine for MachineX86_64 {
         assert_eq!(self.used_simd_remove(&simd), true);
     }
 
-    fn push_used_simd(&mut self, used_xmms: &Vec<XMM>) -> usize {
-        self.adjust_stack((used_xmms.len() * 8) as u32);
+    fn push_used_simd(&mut self, used_xmms: &Vec<XMM>) -> Result<usize, CodegenError> {
+        let stack_adjust = FrameLayout::X86_64.spill_size(used_xmms.len());
+        self.adjust_stack(stack_adjust as u32);
 
         for (i, r) in used_xmms.iter().enumerate() {
             self.move_location(
                 Size::S64,
                 Location::SIMD(*r),
                 Location::Memory(GPR::RSP, (i * 8) as i32),
-            );
+            )?;
         }
 
-        used_xmms.len() * 8
+        Ok(stack_adjust)
     }
-    fn pop_used_simd(&mut self, used_xmms: &Vec<XMM>) {
+    fn pop_used_simd(&mut self, used_xmms: &Vec<XMM>) -> Result<(), CodegenError> {
         for (i, r) in used_xmms.iter().enumerate() {
             self.move_location(
                 Size::S64,
                 Location::Memory(GPR::RSP, (i * 8) as i32),
                 Location::SIMD(*r),
-            );
+            )?;
         }
         self.assembler.emit_add(
             Size::S64,
-            Location::Imm32((used_xmms.len() * 8) as u32),
+            Location::Imm32(FrameLayout::X86_64.spill_size(used_xmms.len()) as u32),
             Location::GPR(GPR::RSP),
         );
+        Ok(())
     }
 
     /// Set the source location of the Wasm to the given offset.
@@ -1892,9 +2149,8 @@ impl Machine for MachineX86_64 {
         Location::Memory(GPR::RBP, -stack_offset)
     }
 
-    // Return a rounded stack adjustement value (must be multiple of 16bytes on ARM64 for example)
-    fn round_stack_adjust(&self, value: usize) -> usize {
-        value
+    fn frame_layout(&self) -> FrameLayout {
+        FrameLayout::X86_64
     }
 
     // Adjust stack for locals
@@ -1921,7 +2177,12 @@ impl Machine for MachineX86_64 {
         );
     }
     // push a value on the stack for a native call
-    fn move_location_for_native(&mut self, _size: Size, loc: Location, dest: Location) {
+    fn move_location_for_native(
+        &mut self,
+        _size: Size,
+        loc: Location,
+        dest: Location,
+    ) -> Result<(), CodegenError> {
         match loc {
             Location::Imm64(_) | Location::Memory(_, _) | Location::Memory2(_, _, _, _) => {
                 let tmp = self.pick_temp_gpr();
@@ -1939,11 +2200,13 @@ impl Machine for MachineX86_64 {
             }
             _ => self.assembler.emit_mov(Size::S64, loc, dest),
         }
+        Ok(())
     }
 
     // Zero a location that is 32bits
-    fn zero_location(&mut self, size: Size, location: Location) {
+    fn zero_location(&mut self, size: Size, location: Location) -> Result<(), CodegenError> {
         self.assembler.emit_mov(size, Location::Imm32(0), location);
+        Ok(())
     }
 
     // GPR Reg used for local pointer on the stack
@@ -1964,7 +2227,10 @@ impl Machine for MachineX86_64 {
             1 => Location::GPR(GPR::R13),
             2 => Location::GPR(GPR::R14),
             3 => Location::GPR(GPR::RBX),
-            _ => Location::Memory(GPR::RBP, -(((idx - 3) * 8 + callee_saved_regs_size) as i32)),
+            _ => Location::Memory(
+                GPR::RBP,
+                FrameLayout::X86_64.local_offset(idx, 4, callee_saved_regs_size),
+            ),
         }
     }
     // Move a local to the stack
@@ -2050,7 +2316,11 @@ impl Machine for MachineX86_64 {
                 1 => Location::GPR(GPR::RDX),
                 2 => Location::GPR(GPR::R8),
                 3 => Location::GPR(GPR::R9),
-                _ => Location::Memory(GPR::RBP, (32 + 16 + (idx - 4) * 8) as i32),
+                // The caller reserves 32 bytes of shadow space above the return address.
+                _ => Location::Memory(
+                    GPR::RBP,
+                    FrameLayout::X86_64.stack_argument_offset(32 + (idx - 4) * 8),
+                ),
             },
             _ => match idx {
                 0 => Location::GPR(GPR::RDI),
@@ -2059,7 +2329,10 @@ impl Machine for MachineX86_64 {
                 3 => Location::GPR(GPR::RCX),
                 4 => Location::GPR(GPR::R8),
                 5 => Location::GPR(GPR::R9),
-                _ => Location::Memory(GPR::RBP, (16 + (idx - 6) * 8) as i32),
+                _ => Location::Memory(
+                    GPR::RBP,
+                    FrameLayout::X86_64.stack_argument_offset((idx - 6) * 8),
+                ),
             },
         }
     }
@@ -2075,7 +2348,11 @@ impl Machine for MachineX86_64 {
                 1 => Location::GPR(GPR::RDX),
                 2 => Location::GPR(GPR::R8),
                 3 => Location::GPR(GPR::R9),
-                _ => Location::Memory(GPR::RBP, (32 + 16 + (idx - 4) * 8) as i32),
+                // The caller reserves 32 bytes of shadow space above the return address.
+                _ => Location::Memory(
+                    GPR::RBP,
+                    FrameLayout::X86_64.stack_argument_offset(32 + (idx - 4) * 8),
+                ),
             },
             _ => match idx {
                 0 => Location::GPR(GPR::RDI),
@@ -2084,12 +2361,20 @@ impl Machine for MachineX86_64 {
                 3 => Location::GPR(GPR::RCX),
                 4 => Location::GPR(GPR::R8),
                 5 => Location::GPR(GPR::R9),
-                _ => Location::Memory(GPR::RBP, (16 + (idx - 6) * 8) as i32),
+                _ => Location::Memory(
+                    GPR::RBP,
+                    FrameLayout::X86_64.stack_argument_offset((idx - 6) * 8),
+                ),
             },
         }
     }
     // move a location to another
-    fn move_location(&mut self, size: Size, source: Location, dest: Location) {
+    fn move_location(
+        &mut self,
+        size: Size,
+        source: Location,
+        dest: Location,
+    ) -> Result<(), CodegenError> {
         match source {
             Location::GPR(_) => {
                 self.assembler.emit_mov(size, source, dest);
@@ -2099,39 +2384,46 @@ impl Machine for MachineX86_64 {
                     self.assembler.emit_mov(size, source, dest);
                 }
                 Location::Memory(_, _) | Location::Memory2(_, _, _, _) => {
-                    let tmp = self.pick_temp_gpr().unwrap();
+                    let tmp = self.pick_temp_gpr().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
                     self.assembler.emit_mov(size, source, Location::GPR(tmp));
                     self.assembler.emit_mov(size, Location::GPR(tmp), dest);
                 }
-                _ => unreachable!(),
+                _ => codegen_error!("singlepass move_location unreachable"),
             },
             Location::Memory2(_, _, _, _) => match dest {
                 Location::GPR(_) | Location::SIMD(_) => {
                     self.assembler.emit_mov(size, source, dest);
                 }
                 Location::Memory(_, _) | Location::Memory2(_, _, _, _) => {
-                    let tmp = self.pick_temp_gpr().unwrap();
+                    let tmp = self.pick_temp_gpr().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
                     self.assembler.emit_mov(size, source, Location::GPR(tmp));
                     self.assembler.emit_mov(size, Location::GPR(tmp), dest);
                 }
-                _ => unreachable!(),
+                _ => codegen_error!("singlepass move_location unreachable"),
             },
             Location::Imm8(_) | Location::Imm32(_) | Location::Imm64(_) => match dest {
                 Location::GPR(_) | Location::SIMD(_) => {
                     self.assembler.emit_mov(size, source, dest);
                 }
                 Location::Memory(_, _) | Location::Memory2(_, _, _, _) => {
-                    let tmp = self.pick_temp_gpr().unwrap();
+                    let tmp = self.pick_temp_gpr().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
                     self.assembler.emit_mov(size, source, Location::GPR(tmp));
                     self.assembler.emit_mov(size, Location::GPR(tmp), dest);
                 }
-                _ => unreachable!(),
+                _ => codegen_error!("singlepass move_location unreachable"),
             },
             Location::SIMD(_) => {
                 self.assembler.emit_mov(size, source, dest);
             }
-            _ => unreachable!(),
+            _ => codegen_error!("singlepass move_location unreachable"),
         }
+        Ok(())
     }
     // move a location to another
     fn move_location_extend(
@@ -2141,13 +2433,15 @@ impl Machine for MachineX86_64 {
         source: Location,
         size_op: Size,
         dest: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         let dst = match dest {
             Location::Memory(_, _) | Location::Memory2(_, _, _, _) => {
-                Location::GPR(self.acquire_temp_gpr().unwrap())
+                Location::GPR(self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?)
             }
             Location::GPR(_) | Location::SIMD(_) => dest,
-            _ => unreachable!(),
+            _ => codegen_error!("singlepass move_location_extend unreachable"),
         };
         match source {
             Location::GPR(_) | Location::Memory(_, _) | Location::Memory2(_, _, _, _) => {
@@ -2162,17 +2456,23 @@ impl Machine for MachineX86_64 {
                     }
                 }
             }
-            _ => unreachable!(),
+            _ => codegen_error!("singlepass move_location_extend unreachable"),
         }
         if dst != dest {
             self.assembler.emit_mov(size_op, dst, dest);
             match dst {
                 Location::GPR(x) => self.release_gpr(x),
-                _ => unreachable!(),
+                _ => codegen_error!("singlepass move_location_extend unreachable"),
             };
         }
+        Ok(())
     }
-    fn load_address(&mut self, size: Size, reg: Location, mem: Location) {
+    fn load_address(
+        &mut self,
+        size: Size,
+        reg: Location,
+        mem: Location,
+    ) -> Result<(), CodegenError> {
         match reg {
             Location::GPR(_) => {
                 match mem {
@@ -2183,14 +2483,19 @@ impl Machine for MachineX86_64 {
                         }
                         self.assembler.emit_mov(size, mem, reg);
                     }
-                    _ => unreachable!(),
+                    _ => codegen_error!("singlepass load_address unreachable"),
                 }
             }
-            _ => unreachable!(),
+            _ => codegen_error!("singlepass load_address unreachable"),
         }
+        Ok(())
     }
     // Init the stack loc counter
-    fn init_stack_loc(&mut self, init_stack_loc_cnt: u64, last_stack_loc: Location) {
+    fn init_stack_loc(
+        &mut self,
+        init_stack_loc_cnt: u64,
+        last_stack_loc: Location,
+    ) -> Result<(), CodegenError> {
         // Since these assemblies take up to 24 bytes, if more than 2 slots are initialized, then they are smaller.
         self.assembler.emit_mov(
             Size::S64,
@@ -2202,18 +2507,21 @@ impl Machine for MachineX86_64 {
         self.assembler
             .emit_lea(Size::S64, last_stack_loc, Location::GPR(GPR::RDI));
         self.assembler.emit_rep_stosq();
+        Ok(())
     }
     // Restore save_area
-    fn restore_saved_area(&mut self, saved_area_offset: i32) {
+    fn restore_saved_area(&mut self, saved_area_offset: i32) -> Result<(), CodegenError> {
         self.assembler.emit_lea(
             Size::S64,
             Location::Memory(GPR::RBP, -saved_area_offset),
             Location::GPR(GPR::RSP),
         );
+        Ok(())
     }
     // Pop a location
-    fn pop_location(&mut self, location: Location) {
+    fn pop_location(&mut self, location: Location) -> Result<(), CodegenError> {
         self.assembler.emit_pop(Size::S64, location);
+        Ok(())
     }
     // Create a new `MachineState` with default values.
     fn new_machine_state(&self) -> MachineState {
@@ -2233,52 +2541,72 @@ impl Machine for MachineX86_64 {
         self.assembler.finalize_function();
     }
 
-    fn emit_function_prolog(&mut self) {
-        self.emit_push(Size::S64, Location::GPR(GPR::RBP));
+    fn emit_function_prolog(&mut self) -> Result<(), CodegenError> {
+        self.emit_push(Size::S64, Location::GPR(GPR::RBP))?;
         self.emit_unwind_op(UnwindOps::PushFP { up_to_sp: 16 });
-        self.move_location(Size::S64, Location::GPR(GPR::RSP), Location::GPR(GPR::RBP));
+        self.move_location(Size::S64, Location::GPR(GPR::RSP), Location::GPR(GPR::RBP))?;
         self.emit_unwind_op(UnwindOps::DefineNewFrame);
+        Ok(())
     }
 
-    fn emit_function_epilog(&mut self) {
-        self.move_location(Size::S64, Location::GPR(GPR::RBP), Location::GPR(GPR::RSP));
-        self.emit_pop(Size::S64, Location::GPR(GPR::RBP));
+    fn emit_function_epilog(&mut self) -> Result<(), CodegenError> {
+        self.move_location(Size::S64, Location::GPR(GPR::RBP), Location::GPR(GPR::RSP))?;
+        self.emit_pop(Size::S64, Location::GPR(GPR::RBP))
     }
 
-    fn emit_function_return_value(&mut self, ty: WpType, canonicalize: bool, loc: Location) {
+    fn emit_function_return_value(
+        &mut self,
+        ty: WpType,
+        canonicalize: bool,
+        loc: Location,
+    ) -> Result<(), CodegenError> {
         if canonicalize {
             self.canonicalize_nan(
                 match ty {
                     WpType::F32 => Size::S32,
                     WpType::F64 => Size::S64,
-                    _ => unreachable!(),
+                    _ => codegen_error!("singlepass emit_function_return_value unreachable"),
                 },
                 loc,
                 Location::GPR(GPR::RAX),
-            );
+            )?;
         } else {
-            self.emit_relaxed_mov(Size::S64, loc, Location::GPR(GPR::RAX));
+            self.emit_relaxed_mov(Size::S64, loc, Location::GPR(GPR::RAX))?;
         }
+        Ok(())
     }
 
-    fn emit_function_return_float(&mut self) {
+    fn emit_function_return_float(&mut self) -> Result<(), CodegenError> {
         self.move_location(
             Size::S64,
             Location::GPR(GPR::RAX),
             Location::SIMD(XMM::XMM0),
-        );
+        )
     }
 
     fn arch_supports_canonicalize_nan(&self) -> bool {
         self.assembler.arch_supports_canonicalize_nan()
     }
-    fn canonicalize_nan(&mut self, sz: Size, input: Location, output: Location) {
-        let tmp1 = self.acquire_temp_simd().unwrap();
-        let tmp2 = self.acquire_temp_simd().unwrap();
-        let tmp3 = self.acquire_temp_simd().unwrap();
-
-        self.emit_relaxed_mov(sz, input, Location::SIMD(tmp1));
-        let tmpg1 = self.acquire_temp_gpr().unwrap();
+    fn canonicalize_nan(
+        &mut self,
+        sz: Size,
+        input: Location,
+        output: Location,
+    ) -> Result<(), CodegenError> {
+        let tmp1 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
+        let tmp2 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
+        let tmp3 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp simd".to_owned(),
+        })?;
+
+        self.emit_relaxed_mov(sz, input, Location::SIMD(tmp1))?;
+        let tmpg1 = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
 
         match sz {
             Size::S32 => {
@@ -2288,8 +2616,8 @@ impl Machine for MachineX86_64 {
                     Size::S32,
                     Location::Imm32(0x7FC0_0000), // Canonical NaN
                     Location::GPR(tmpg1),
-                );
-                self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp3));
+                )?;
+                self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp3))?;
                 self.assembler
                     .emit_vblendvps(tmp2, XMMOrMemory::XMM(tmp3), tmp1, tmp1);
             }
@@ -2300,20 +2628,21 @@ impl Machine for MachineX86_64 {
                     Size::S64,
                     Location::Imm64(0x7FF8_0000_0000_0000), // Canonical NaN
                     Location::GPR(tmpg1),
-                );
-                self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp3));
+                )?;
+                self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp3))?;
                 self.assembler
                     .emit_vblendvpd(tmp2, XMMOrMemory::XMM(tmp3), tmp1, tmp1);
             }
-            _ => unreachable!(),
+            _ => codegen_error!("singlepass canonicalize_nan unreachable"),
         }
 
-        self.emit_relaxed_mov(sz, Location::SIMD(tmp1), output);
+        self.emit_relaxed_mov(sz, Location::SIMD(tmp1), output)?;
 
         self.release_gpr(tmpg1);
         self.release_simd(tmp3);
         self.release_simd(tmp2);
         self.release_simd(tmp1);
+        Ok(())
     }
 
     fn emit_illegal_op(&mut self, trap: TrapCode) {
@@ -2368,35 +2697,94 @@ impl Machine for MachineX86_64 {
         self.assembler.emit_bkpt();
     }
 
-    fn emit_call_location(&mut self, location: Location) {
+    fn emit_patch_point(&mut self) {
+        // A one-byte nop, replaced by `int3` atomically.
+        self.assembler.emit_nop();
+    }
+
+    fn emit_call_location(&mut self, location: Location) -> Result<(), CodegenError> {
         self.assembler.emit_call_location(location);
+        Ok(())
     }
 
-    fn location_address(&mut self, size: Size, source: Location, dest: Location) {
+    fn location_address(
+        &mut self,
+        size: Size,
+        source: Location,
+        dest: Location,
+    ) -> Result<(), CodegenError> {
         self.assembler.emit_lea(size, source, dest);
+        Ok(())
     }
     // logic
-    fn location_and(&mut self, size: Size, source: Location, dest: Location, _flags: bool) {
+    fn location_and(
+        &mut self,
+        size: Size,
+        source: Location,
+        dest: Location,
+        _flags: bool,
+    ) -> Result<(), CodegenError> {
         self.assembler.emit_and(size, source, dest);
+        Ok(())
     }
-    fn location_xor(&mut self, size: Size, source: Location, dest: Location, _flags: bool) {
+    fn location_xor(
+        &mut self,
+        size: Size,
+        source: Location,
+        dest: Location,
+        _flags: bool,
+    ) -> Result<(), CodegenError> {
         self.assembler.emit_xor(size, source, dest);
+        Ok(())
     }
-    fn location_or(&mut self, size: Size, source: Location, dest: Location, _flags: bool) {
+    fn location_or(
+        &mut self,
+        size: Size,
+        source: Location,
+        dest: Location,
+        _flags: bool,
+    ) -> Result<(), CodegenError> {
         self.assembler.emit_or(size, source, dest);
+        Ok(())
     }
-    fn location_test(&mut self, size: Size, source: Location, dest: Location) {
+    fn location_test(
+        &mut self,
+        size: Size,
+        source: Location,
+        dest: Location,
+    ) -> Result<(), CodegenError> {
         self.assembler.emit_test(size, source, dest);
+        Ok(())
     }
     // math
-    fn location_add(&mut self, size: Size, source: Location, dest: Location, _flags: bool) {
+    fn location_add(
+        &mut self,
+        size: Size,
+        source: Location,
+        dest: Location,
+        _flags: bool,
+    ) -> Result<(), CodegenError> {
         self.assembler.emit_add(size, source, dest);
+        Ok(())
     }
-    fn location_sub(&mut self, size: Size, source: Location, dest: Location, _flags: bool) {
+    fn location_sub(
+        &mut self,
+        size: Size,
+        source: Location,
+        dest: Location,
+        _flags: bool,
+    ) -> Result<(), CodegenError> {
         self.assembler.emit_sub(size, source, dest);
+        Ok(())
     }
-    fn location_cmp(&mut self, size: Size, source: Location, dest: Location) {
+    fn location_cmp(
+        &mut self,
+        size: Size,
+        source: Location,
+        dest: Location,
+    ) -> Result<(), CodegenError> {
         self.assembler.emit_cmp(size, source, dest);
+        Ok(())
     }
     // (un)conditionnal jmp
     // (un)conditionnal jmp
@@ -2421,16 +2809,35 @@ impl Machine for MachineX86_64 {
     fn jmp_on_overflow(&mut self, label: Label) {
         self.assembler.emit_jmp(Condition::Carry, label);
     }
+    fn arch_supports_compare_and_branch(&self) -> bool {
+        false
+    }
+    fn emit_compare_and_branch(
+        &mut self,
+        _size: Size,
+        _comparison: IntegerComparison,
+        _loc_a: Location,
+        _loc_b: Location,
+        _label: Label,
+    ) -> Result<(), CodegenError> {
+        Err(CodegenError {
+            message: "compare and branch is not supported on x86_64".to_owned(),
+        })
+    }
 
     // jmp table
-    fn emit_jmp_to_jumptable(&mut self, label: Label, cond: Location) {
-        let tmp1 = self.pick_temp_gpr().unwrap();
+    fn emit_jmp_to_jumptable(&mut self, label: Label, cond: Location) -> Result<(), CodegenError> {
+        let tmp1 = self.pick_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         self.reserve_gpr(tmp1);
-        let tmp2 = self.pick_temp_gpr().unwrap();
+        let tmp2 = self.pick_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         self.reserve_gpr(tmp2);
 
         self.assembler.emit_lea_label(label, Location::GPR(tmp1));
-        self.move_location(Size::S32, cond, Location::GPR(tmp2));
+        self.move_location(Size::S32, cond, Location::GPR(tmp2))?;
 
         let instr_size = self.assembler.get_jmp_instr_size();
         self.assembler.emit_imul_imm32_gpr64(instr_size as _, tmp2);
@@ -2439,30 +2846,34 @@ impl Machine for MachineX86_64 {
         self.assembler.emit_jmp_location(Location::GPR(tmp2));
         self.release_gpr(tmp2);
         self.release_gpr(tmp1);
+        Ok(())
     }
 
-    fn align_for_loop(&mut self) {
-        // Pad with NOPs to the next 16-byte boundary.
+    fn align_for_loop(&mut self, alignment: Option<usize>) {
+        // Pad with NOPs to the next 16-byte boundary by default.
         // Here we don't use the dynasm `.align 16` attribute because it pads the alignment with single-byte nops
         // which may lead to efficiency problems.
-        match self.assembler.get_offset().0 % 16 {
+        let alignment = alignment.unwrap_or(16);
+        match self.assembler.get_offset().0 % alignment {
             0 => {}
             x => {
-                self.assembler.emit_nop_n(16 - x);
+                self.assembler.emit_nop_n(alignment - x);
             }
         }
-        assert_eq!(self.assembler.get_offset().0 % 16, 0);
+        assert_eq!(self.assembler.get_offset().0 % alignment, 0);
     }
 
     fn emit_ret(&mut self) {
         self.assembler.emit_ret();
     }
 
-    fn emit_push(&mut self, size: Size, loc: Location) {
+    fn emit_push(&mut self, size: Size, loc: Location) -> Result<(), CodegenError> {
         self.assembler.emit_push(size, loc);
+        Ok(())
     }
-    fn emit_pop(&mut self, size: Size, loc: Location) {
+    fn emit_pop(&mut self, size: Size, loc: Location) -> Result<(), CodegenError> {
         self.assembler.emit_pop(size, loc);
+        Ok(())
     }
 
     fn emit_memory_fence(&mut self) {
@@ -2476,28 +2887,40 @@ impl Machine for MachineX86_64 {
         source: Location,
         size_op: Size,
         dest: Location,
-    ) {
-        self.move_location_extend(size_val, signed, source, size_op, dest);
+    ) -> Result<(), CodegenError> {
+        self.move_location_extend(size_val, signed, source, size_op, dest)?;
         self.assembler.emit_neg(size_val, dest);
+        Ok(())
     }
 
-    fn emit_imul_imm32(&mut self, size: Size, imm32: u32, gpr: GPR) {
+    fn emit_imul_imm32(&mut self, size: Size, imm32: u32, gpr: GPR) -> Result<(), CodegenError> {
         match size {
             Size::S64 => {
                 self.assembler.emit_imul_imm32_gpr64(imm32, gpr);
             }
             _ => {
-                unreachable!();
+                codegen_error!("singlepass emit_imul_imm32 unreachable");
             }
         }
+        Ok(())
     }
 
     // relaxed binop based...
-    fn emit_relaxed_mov(&mut self, sz: Size, src: Location, dst: Location) {
-        self.emit_relaxed_binop(AssemblerX64::emit_mov, sz, src, dst);
+    fn emit_relaxed_mov(
+        &mut self,
+        sz: Size,
+        src: Location,
+        dst: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop(AssemblerX64::emit_mov, sz, src, dst)
     }
-    fn emit_relaxed_cmp(&mut self, sz: Size, src: Location, dst: Location) {
-        self.emit_relaxed_binop(AssemblerX64::emit_cmp, sz, src, dst);
+    fn emit_relaxed_cmp(
+        &mut self,
+        sz: Size,
+        src: Location,
+        dst: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_binop(AssemblerX64::emit_cmp, sz, src, dst)
     }
     fn emit_relaxed_zero_extension(
         &mut self,
@@ -2505,12 +2928,13 @@ impl Machine for MachineX86_64 {
         src: Location,
         sz_dst: Size,
         dst: Location,
-    ) {
+    ) -> Result<(), CodegenError> {
         if (sz_src == Size::S32 || sz_src == Size::S64) && sz_dst == Size::S64 {
-            self.emit_relaxed_binop(AssemblerX64::emit_mov, sz_src, src, dst);
+            self.emit_relaxed_binop(AssemblerX64::emit_mov, sz_src, src, dst)?;
         } else {
-            self.emit_relaxed_zx_sx(AssemblerX64::emit_movzx, sz_src, src, sz_dst, dst);
+            self.emit_relaxed_zx_sx(AssemblerX64::emit_movzx, sz_src, src, sz_dst, dst)?;
         }
+        Ok(())
     }
     fn emit_relaxed_sign_extension(
         &mut self,
@@ -2518,18 +2942,33 @@ impl Machine for MachineX86_64 {
         src: Location,
         sz_dst: Size,
         dst: Location,
-    ) {
-        self.emit_relaxed_zx_sx(AssemblerX64::emit_movsx, sz_src, src, sz_dst, dst);
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_zx_sx(AssemblerX64::emit_movsx, sz_src, src, sz_dst, dst)
     }
 
-    fn emit_binop_add32(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_binop_i32(AssemblerX64::emit_add, loc_a, loc_b, ret);
+    fn emit_binop_add32(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_binop_i32(AssemblerX64::emit_add, loc_a, loc_b, ret)
     }
-    fn emit_binop_sub32(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_binop_i32(AssemblerX64::emit_sub, loc_a, loc_b, ret);
+    fn emit_binop_sub32(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_binop_i32(AssemblerX64::emit_sub, loc_a, loc_b, ret)
     }
-    fn emit_binop_mul32(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_binop_i32(AssemblerX64::emit_imul, loc_a, loc_b, ret);
+    fn emit_binop_mul32(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_binop_i32(AssemblerX64::emit_imul, loc_a, loc_b, ret)
     }
     fn emit_binop_udiv32(
         &mut self,
@@ -2538,7 +2977,7 @@ impl Machine for MachineX86_64 {
         ret: Location,
         integer_division_by_zero: Label,
         _integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         // We assume that RAX and RDX are temporary registers here.
         self.assembler
             .emit_mov(Size::S32, loc_a, Location::GPR(GPR::RAX));
@@ -2548,11 +2987,12 @@ impl Machine for MachineX86_64 {
             AssemblerX64::emit_div,
             Size::S32,
             loc_b,
+            IntegerDivision::UnsignedDiv,
             integer_division_by_zero,
-        );
+        )?;
         self.assembler
             .emit_mov(Size::S32, Location::GPR(GPR::RAX), ret);
-        offset
+        Ok(offset)
     }
     fn emit_binop_sdiv32(
         &mut self,
@@ -2561,7 +3001,7 @@ impl Machine for MachineX86_64 {
         ret: Location,
         integer_division_by_zero: Label,
         _integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         // We assume that RAX and RDX are temporary registers here.
         self.assembler
             .emit_mov(Size::S32, loc_a, Location::GPR(GPR::RAX));
@@ -2570,11 +3010,12 @@ impl Machine for MachineX86_64 {
             AssemblerX64::emit_idiv,
             Size::S32,
             loc_b,
+            IntegerDivision::SignedDiv,
             integer_division_by_zero,
-        );
+        )?;
         self.assembler
             .emit_mov(Size::S32, Location::GPR(GPR::RAX), ret);
-        offset
+        Ok(offset)
     }
     fn emit_binop_urem32(
         &mut self,
@@ -2583,7 +3024,7 @@ impl Machine for MachineX86_64 {
         ret: Location,
         integer_division_by_zero: Label,
         _integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         // We assume that RAX and RDX are temporary registers here.
         self.assembler
             .emit_mov(Size::S32, loc_a, Location::GPR(GPR::RAX));
@@ -2593,11 +3034,12 @@ impl Machine for MachineX86_64 {
             AssemblerX64::emit_div,
             Size::S32,
             loc_b,
+            IntegerDivision::UnsignedRem,
             integer_division_by_zero,
-        );
+        )?;
         self.assembler
             .emit_mov(Size::S32, Location::GPR(GPR::RDX), ret);
-        offset
+        Ok(offset)
     }
     fn emit_binop_srem32(
         &mut self,
@@ -2606,16 +3048,16 @@ impl Machine for MachineX86_64 {
         ret: Location,
         integer_division_by_zero: Label,
         _integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         // We assume that RAX and RDX are temporary registers here.
         let normal_path = self.assembler.get_label();
         let end = self.assembler.get_label();
 
-        self.emit_relaxed_cmp(Size::S32, Location::Imm32(0x80000000), loc_a);
+        self.emit_relaxed_cmp(Size::S32, Location::Imm32(0x80000000), loc_a)?;
         self.assembler.emit_jmp(Condition::NotEqual, normal_path);
-        self.emit_relaxed_cmp(Size::S32, Location::Imm32(0xffffffff), loc_b);
+        self.emit_relaxed_cmp(Size::S32, Location::Imm32(0xffffffff), loc_b)?;
         self.assembler.emit_jmp(Condition::NotEqual, normal_path);
-        self.move_location(Size::S32, Location::Imm32(0), ret);
+        self.move_location(Size::S32, Location::Imm32(0), ret)?;
         self.assembler.emit_jmp(Condition::None, end);
 
         self.emit_label(normal_path);
@@ -2626,70 +3068,140 @@ impl Machine for MachineX86_64 {
             AssemblerX64::emit_idiv,
             Size::S32,
             loc_b,
+            IntegerDivision::SignedRem,
             integer_division_by_zero,
-        );
+        )?;
         self.assembler
             .emit_mov(Size::S32, Location::GPR(GPR::RDX), ret);
 
         self.emit_label(end);
-        offset
+        Ok(offset)
     }
-    fn emit_binop_and32(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_binop_i32(AssemblerX64::emit_and, loc_a, loc_b, ret);
+    fn emit_binop_and32(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_binop_i32(AssemblerX64::emit_and, loc_a, loc_b, ret)
     }
-    fn emit_binop_or32(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_binop_i32(AssemblerX64::emit_or, loc_a, loc_b, ret);
+    fn emit_binop_or32(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_binop_i32(AssemblerX64::emit_or, loc_a, loc_b, ret)
     }
-    fn emit_binop_xor32(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_binop_i32(AssemblerX64::emit_xor, loc_a, loc_b, ret);
+    fn emit_binop_xor32(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_binop_i32(AssemblerX64::emit_xor, loc_a, loc_b, ret)
     }
-    fn i32_cmp_ge_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::GreaterEqual, loc_a, loc_b, ret);
+    fn i32_cmp_ge_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::GreaterEqual, loc_a, loc_b, ret)
     }
-    fn i32_cmp_gt_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Greater, loc_a, loc_b, ret);
+    fn i32_cmp_gt_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Greater, loc_a, loc_b, ret)
     }
-    fn i32_cmp_le_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::LessEqual, loc_a, loc_b, ret);
+    fn i32_cmp_le_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::LessEqual, loc_a, loc_b, ret)
     }
-    fn i32_cmp_lt_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Less, loc_a, loc_b, ret);
+    fn i32_cmp_lt_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Less, loc_a, loc_b, ret)
     }
-    fn i32_cmp_ge_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::AboveEqual, loc_a, loc_b, ret);
+    fn i32_cmp_ge_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::AboveEqual, loc_a, loc_b, ret)
     }
-    fn i32_cmp_gt_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Above, loc_a, loc_b, ret);
+    fn i32_cmp_gt_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Above, loc_a, loc_b, ret)
     }
-    fn i32_cmp_le_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::BelowEqual, loc_a, loc_b, ret);
+    fn i32_cmp_le_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::BelowEqual, loc_a, loc_b, ret)
     }
-    fn i32_cmp_lt_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Below, loc_a, loc_b, ret);
+    fn i32_cmp_lt_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Below, loc_a, loc_b, ret)
     }
-    fn i32_cmp_ne(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::NotEqual, loc_a, loc_b, ret);
+    fn i32_cmp_ne(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::NotEqual, loc_a, loc_b, ret)
     }
-    fn i32_cmp_eq(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i32_dynamic_b(Condition::Equal, loc_a, loc_b, ret);
+    fn i32_cmp_eq(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i32_dynamic_b(Condition::Equal, loc_a, loc_b, ret)
     }
-    fn i32_clz(&mut self, loc: Location, ret: Location) {
+    fn i32_clz(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         let src = match loc {
             Location::Imm32(_) | Location::Memory(_, _) => {
-                let tmp = self.acquire_temp_gpr().unwrap();
-                self.move_location(Size::S32, loc, Location::GPR(tmp));
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                self.move_location(Size::S32, loc, Location::GPR(tmp))?;
                 tmp
             }
             Location::GPR(reg) => reg,
             _ => {
-                unreachable!();
+                codegen_error!("singlepass i32_clz unreachable");
             }
         };
         let dst = match ret {
-            Location::Memory(_, _) => self.acquire_temp_gpr().unwrap(),
+            Location::Memory(_, _) => self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?,
             Location::GPR(reg) => reg,
             _ => {
-                unreachable!();
+                codegen_error!("singlepass i32_clz unreachable");
             }
         };
 
@@ -2708,7 +3220,7 @@ impl Machine for MachineX86_64 {
                 .emit_xor(Size::S32, Location::Imm32(31), Location::GPR(dst));
             self.assembler.emit_jmp(Condition::None, end);
             self.emit_label(zero_path);
-            self.move_location(Size::S32, Location::Imm32(32), Location::GPR(dst));
+            self.move_location(Size::S32, Location::Imm32(32), Location::GPR(dst))?;
             self.emit_label(end);
         }
         match loc {
@@ -2718,27 +3230,32 @@ impl Machine for MachineX86_64 {
             _ => {}
         };
         if let Location::Memory(_, _) = ret {
-            self.move_location(Size::S32, Location::GPR(dst), ret);
+            self.move_location(Size::S32, Location::GPR(dst), ret)?;
             self.release_gpr(dst);
         };
+        Ok(())
     }
-    fn i32_ctz(&mut self, loc: Location, ret: Location) {
+    fn i32_ctz(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         let src = match loc {
             Location::Imm32(_) | Location::Memory(_, _) => {
-                let tmp = self.acquire_temp_gpr().unwrap();
-                self.move_location(Size::S32, loc, Location::GPR(tmp));
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                self.move_location(Size::S32, loc, Location::GPR(tmp))?;
                 tmp
             }
             Location::GPR(reg) => reg,
             _ => {
-                unreachable!();
+                codegen_error!("singlepass i32_ctz unreachable");
             }
         };
         let dst = match ret {
-            Location::Memory(_, _) => self.acquire_temp_gpr().unwrap(),
+            Location::Memory(_, _) => self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?,
             Location::GPR(reg) => reg,
             _ => {
-                unreachable!();
+                codegen_error!("singlepass i32_ctz unreachable");
             }
         };
 
@@ -2755,7 +3272,7 @@ impl Machine for MachineX86_64 {
                 .emit_bsf(Size::S32, Location::GPR(src), Location::GPR(dst));
             self.assembler.emit_jmp(Condition::None, end);
             self.emit_label(zero_path);
-            self.move_location(Size::S32, Location::Imm32(32), Location::GPR(dst));
+            self.move_location(Size::S32, Location::Imm32(32), Location::GPR(dst))?;
             self.emit_label(end);
         }
 
@@ -2766,23 +3283,28 @@ impl Machine for MachineX86_64 {
             _ => {}
         };
         if let Location::Memory(_, _) = ret {
-            self.move_location(Size::S32, Location::GPR(dst), ret);
+            self.move_location(Size::S32, Location::GPR(dst), ret)?;
             self.release_gpr(dst);
         };
+        Ok(())
     }
-    fn i32_popcnt(&mut self, loc: Location, ret: Location) {
+    fn i32_popcnt(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         match loc {
             Location::Imm32(_) => {
-                let tmp = self.acquire_temp_gpr().unwrap();
-                self.move_location(Size::S32, loc, Location::GPR(tmp));
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                self.move_location(Size::S32, loc, Location::GPR(tmp))?;
                 if let Location::Memory(_, _) = ret {
-                    let out_tmp = self.acquire_temp_gpr().unwrap();
+                    let out_tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
                     self.assembler.emit_popcnt(
                         Size::S32,
                         Location::GPR(tmp),
                         Location::GPR(out_tmp),
                     );
-                    self.move_location(Size::S32, Location::GPR(out_tmp), ret);
+                    self.move_location(Size::S32, Location::GPR(out_tmp), ret)?;
                     self.release_gpr(out_tmp);
                 } else {
                     self.assembler
@@ -2792,34 +3314,62 @@ impl Machine for MachineX86_64 {
             }
             Location::Memory(_, _) | Location::GPR(_) => {
                 if let Location::Memory(_, _) = ret {
-                    let out_tmp = self.acquire_temp_gpr().unwrap();
+                    let out_tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
                     self.assembler
                         .emit_popcnt(Size::S32, loc, Location::GPR(out_tmp));
-                    self.move_location(Size::S32, Location::GPR(out_tmp), ret);
+                    self.move_location(Size::S32, Location::GPR(out_tmp), ret)?;
                     self.release_gpr(out_tmp);
                 } else {
                     self.assembler.emit_popcnt(Size::S32, loc, ret);
                 }
             }
             _ => {
-                unreachable!();
+                codegen_error!("singlepass i32_popcnt unreachable");
             }
         }
+        Ok(())
     }
-    fn i32_shl(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_shift_i32(AssemblerX64::emit_shl, loc_a, loc_b, ret);
+    fn i32_shl(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_shift_i32(AssemblerX64::emit_shl, loc_a, loc_b, ret)
     }
-    fn i32_shr(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_shift_i32(AssemblerX64::emit_shr, loc_a, loc_b, ret);
+    fn i32_shr(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_shift_i32(AssemblerX64::emit_shr, loc_a, loc_b, ret)
     }
-    fn i32_sar(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_shift_i32(AssemblerX64::emit_sar, loc_a, loc_b, ret);
+    fn i32_sar(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_shift_i32(AssemblerX64::emit_sar, loc_a, loc_b, ret)
     }
-    fn i32_rol(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_shift_i32(AssemblerX64::emit_rol, loc_a, loc_b, ret);
+    fn i32_rol(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_shift_i32(AssemblerX64::emit_rol, loc_a, loc_b, ret)
     }
-    fn i32_ror(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_shift_i32(AssemblerX64::emit_ror, loc_a, loc_b, ret);
+    fn i32_ror(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_shift_i32(AssemblerX64::emit_ror, loc_a, loc_b, ret)
     }
     fn i32_load(
         &mut self,
@@ -2830,7 +3380,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -2846,9 +3396,9 @@ impl Machine for MachineX86_64 {
                     Size::S32,
                     Location::Memory(addr, 0),
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i32_load_8u(
         &mut self,
@@ -2859,7 +3409,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -2876,9 +3426,9 @@ impl Machine for MachineX86_64 {
                     Location::Memory(addr, 0),
                     Size::S32,
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i32_load_8s(
         &mut self,
@@ -2889,7 +3439,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -2906,9 +3456,9 @@ impl Machine for MachineX86_64 {
                     Location::Memory(addr, 0),
                     Size::S32,
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i32_load_16u(
         &mut self,
@@ -2919,7 +3469,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -2936,9 +3486,9 @@ impl Machine for MachineX86_64 {
                     Location::Memory(addr, 0),
                     Size::S32,
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i32_load_16s(
         &mut self,
@@ -2949,7 +3499,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -2966,9 +3516,9 @@ impl Machine for MachineX86_64 {
                     Location::Memory(addr, 0),
                     Size::S32,
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i32_atomic_load(
         &mut self,
@@ -2979,7 +3529,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -2989,10 +3539,8 @@ impl Machine for MachineX86_64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_mov(Size::S32, Location::Memory(addr, 0), ret);
-            },
-        );
+            |this, addr| this.emit_relaxed_mov(Size::S32, Location::Memory(addr, 0), ret),
+        )
     }
     fn i32_atomic_load_8u(
         &mut self,
@@ -3003,7 +3551,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -3019,9 +3567,9 @@ impl Machine for MachineX86_64 {
                     Location::Memory(addr, 0),
                     Size::S32,
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i32_atomic_load_16u(
         &mut self,
@@ -3032,7 +3580,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -3048,9 +3596,9 @@ impl Machine for MachineX86_64 {
                     Location::Memory(addr, 0),
                     Size::S32,
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i32_save(
         &mut self,
@@ -3061,7 +3609,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -3077,9 +3625,9 @@ impl Machine for MachineX86_64 {
                     Size::S32,
                     target_value,
                     Location::Memory(addr, 0),
-                );
+                )
             },
-        );
+        )
     }
     fn i32_save_8(
         &mut self,
@@ -3090,7 +3638,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -3106,9 +3654,9 @@ impl Machine for MachineX86_64 {
                     Size::S8,
                     target_value,
                     Location::Memory(addr, 0),
-                );
+                )
             },
-        );
+        )
     }
     fn i32_save_16(
         &mut self,
@@ -3119,7 +3667,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -3135,9 +3683,9 @@ impl Machine for MachineX86_64 {
                     Size::S16,
                     target_value,
                     Location::Memory(addr, 0),
-                );
+                )
             },
-        );
+        )
     }
     fn i32_atomic_save(
         &mut self,
@@ -3148,7 +3696,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -3158,10 +3706,8 @@ impl Machine for MachineX86_64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_atomic_xchg(Size::S32, value, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_atomic_xchg(Size::S32, value, Location::Memory(addr, 0)),
+        )
     }
     fn i32_atomic_save_8(
         &mut self,
@@ -3172,7 +3718,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -3182,10 +3728,8 @@ impl Machine for MachineX86_64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_atomic_xchg(Size::S8, value, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_atomic_xchg(Size::S8, value, Location::Memory(addr, 0)),
+        )
     }
     fn i32_atomic_save_16(
         &mut self,
@@ -3196,7 +3740,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -3206,10 +3750,8 @@ impl Machine for MachineX86_64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_atomic_xchg(Size::S16, value, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_atomic_xchg(Size::S16, value, Location::Memory(addr, 0)),
+        )
     }
     // i32 atomic Add with i32
     fn i32_atomic_add(
@@ -3222,9 +3764,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.move_location(Size::S32, loc, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.move_location(Size::S32, loc, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -3240,10 +3784,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S32, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S32, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i32 atomic Add with u8
     fn i32_atomic_add_8u(
@@ -3256,9 +3802,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.move_location_extend(Size::S8, false, loc, Size::S32, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.move_location_extend(Size::S8, false, loc, Size::S32, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -3274,10 +3822,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S32, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S32, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i32 atomic Add with u16
     fn i32_atomic_add_16u(
@@ -3290,9 +3840,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.move_location_extend(Size::S16, false, loc, Size::S32, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.move_location_extend(Size::S16, false, loc, Size::S32, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -3308,10 +3860,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S32, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S32, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i32 atomic Sub with i32
     fn i32_atomic_sub(
@@ -3324,9 +3878,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.location_neg(Size::S32, false, loc, Size::S32, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.location_neg(Size::S32, false, loc, Size::S32, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -3342,10 +3898,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S32, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S32, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i32 atomic Sub with u8
     fn i32_atomic_sub_8u(
@@ -3358,9 +3916,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.location_neg(Size::S8, false, loc, Size::S32, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.location_neg(Size::S8, false, loc, Size::S32, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -3376,10 +3936,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S32, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S32, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i32 atomic Sub with u16
     fn i32_atomic_sub_16u(
@@ -3392,9 +3954,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.location_neg(Size::S16, false, loc, Size::S32, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.location_neg(Size::S16, false, loc, Size::S32, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -3410,10 +3974,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S32, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S32, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i32 atomic And with i32
     fn i32_atomic_and(
@@ -3426,7 +3992,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -3442,8 +4008,9 @@ impl Machine for MachineX86_64 {
             |this, src, dst| {
                 this.assembler
                     .emit_and(Size::S32, Location::GPR(src), Location::GPR(dst));
+                Ok(())
             },
-        );
+        )
     }
     // i32 atomic And with u8
     fn i32_atomic_and_8u(
@@ -3456,7 +4023,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -3472,8 +4039,9 @@ impl Machine for MachineX86_64 {
             |this, src, dst| {
                 this.assembler
                     .emit_and(Size::S32, Location::GPR(src), Location::GPR(dst));
+                Ok(())
             },
-        );
+        )
     }
     // i32 atomic And with u16
     fn i32_atomic_and_16u(
@@ -3486,7 +4054,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -3502,8 +4070,9 @@ impl Machine for MachineX86_64 {
             |this, src, dst| {
                 this.assembler
                     .emit_and(Size::S32, Location::GPR(src), Location::GPR(dst));
+                Ok(())
             },
-        );
+        )
     }
     // i32 atomic Or with i32
     fn i32_atomic_or(
@@ -3516,7 +4085,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -3532,8 +4101,9 @@ impl Machine for MachineX86_64 {
             |this, src, dst| {
                 this.assembler
                     .emit_or(Size::S32, Location::GPR(src), Location::GPR(dst));
+                Ok(())
             },
-        );
+        )
     }
     // i32 atomic Or with u8
     fn i32_atomic_or_8u(
@@ -3546,7 +4116,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -3562,8 +4132,9 @@ impl Machine for MachineX86_64 {
             |this, src, dst| {
                 this.assembler
                     .emit_or(Size::S32, Location::GPR(src), Location::GPR(dst));
+                Ok(())
             },
-        );
+        )
     }
     // i32 atomic Or with u16
     fn i32_atomic_or_16u(
@@ -3576,7 +4147,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -3592,8 +4163,9 @@ impl Machine for MachineX86_64 {
             |this, src, dst| {
                 this.assembler
                     .emit_or(Size::S32, Location::GPR(src), Location::GPR(dst));
+                Ok(())
             },
-        );
+        )
     }
     // i32 atomic Xor with i32
     fn i32_atomic_xor(
@@ -3606,7 +4178,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -3622,8 +4194,9 @@ impl Machine for MachineX86_64 {
             |this, src, dst| {
                 this.assembler
                     .emit_xor(Size::S32, Location::GPR(src), Location::GPR(dst));
+                Ok(())
             },
-        );
+        )
     }
     // i32 atomic Xor with u8
     fn i32_atomic_xor_8u(
@@ -3636,7 +4209,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -3652,8 +4225,9 @@ impl Machine for MachineX86_64 {
             |this, src, dst| {
                 this.assembler
                     .emit_xor(Size::S32, Location::GPR(src), Location::GPR(dst));
+                Ok(())
             },
-        );
+        )
     }
     // i32 atomic Xor with u16
     fn i32_atomic_xor_16u(
@@ -3666,7 +4240,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -3682,8 +4256,9 @@ impl Machine for MachineX86_64 {
             |this, src, dst| {
                 this.assembler
                     .emit_xor(Size::S32, Location::GPR(src), Location::GPR(dst));
+                Ok(())
             },
-        );
+        )
     }
     // i32 atomic Exchange with i32
     fn i32_atomic_xchg(
@@ -3696,9 +4271,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.move_location(Size::S32, loc, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.move_location(Size::S32, loc, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -3714,10 +4291,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S32, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S32, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i32 atomic Exchange with u8
     fn i32_atomic_xchg_8u(
@@ -3730,8 +4309,10 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         self.assembler
             .emit_movzx(Size::S8, loc, Size::S32, Location::GPR(value));
         self.memory_op(
@@ -3746,10 +4327,12 @@ impl Machine for MachineX86_64 {
             |this, addr| {
                 this.assembler
                     .emit_xchg(Size::S8, Location::GPR(value), Location::Memory(addr, 0));
+                Ok(())
             },
-        );
-        self.move_location(Size::S32, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S32, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i32 atomic Exchange with u16
     fn i32_atomic_xchg_16u(
@@ -3762,8 +4345,10 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         self.assembler
             .emit_movzx(Size::S16, loc, Size::S32, Location::GPR(value));
         self.memory_op(
@@ -3781,10 +4366,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S32, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S32, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i32 atomic Exchange with i32
     fn i32_atomic_cmpxchg(
@@ -3798,7 +4385,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         let compare = self.reserve_unused_temp_gpr(GPR::RAX);
         let value = if cmp == Location::GPR(GPR::R14) {
             if new == Location::GPR(GPR::R13) {
@@ -3832,10 +4419,12 @@ impl Machine for MachineX86_64 {
                 );
                 this.assembler
                     .emit_mov(Size::S32, Location::GPR(compare), ret);
+                Ok(())
             },
-        );
+        )?;
         self.assembler.emit_pop(Size::S64, Location::GPR(value));
         self.release_gpr(compare);
+        Ok(())
     }
     // i32 atomic Exchange with u8
     fn i32_atomic_cmpxchg_8u(
@@ -3849,7 +4438,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         let compare = self.reserve_unused_temp_gpr(GPR::RAX);
         let value = if cmp == Location::GPR(GPR::R14) {
             if new == Location::GPR(GPR::R13) {
@@ -3883,10 +4472,12 @@ impl Machine for MachineX86_64 {
                 );
                 this.assembler
                     .emit_movzx(Size::S8, Location::GPR(compare), Size::S32, ret);
+                Ok(())
             },
-        );
+        )?;
         self.assembler.emit_pop(Size::S64, Location::GPR(value));
         self.release_gpr(compare);
+        Ok(())
     }
     // i32 atomic Exchange with u16
     fn i32_atomic_cmpxchg_16u(
@@ -3900,7 +4491,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         let compare = self.reserve_unused_temp_gpr(GPR::RAX);
         let value = if cmp == Location::GPR(GPR::R14) {
             if new == Location::GPR(GPR::R13) {
@@ -3934,10 +4525,12 @@ impl Machine for MachineX86_64 {
                 );
                 this.assembler
                     .emit_movzx(Size::S16, Location::GPR(compare), Size::S32, ret);
+                Ok(())
             },
-        );
+        )?;
         self.assembler.emit_pop(Size::S64, Location::GPR(value));
         self.release_gpr(compare);
+        Ok(())
     }
 
     fn emit_call_with_reloc(
@@ -3959,23 +4552,38 @@ impl Machine for MachineX86_64 {
         relocations
     }
 
-    fn emit_binop_add64(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_binop_i64(AssemblerX64::emit_add, loc_a, loc_b, ret);
-    }
-    fn emit_binop_sub64(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_binop_i64(AssemblerX64::emit_sub, loc_a, loc_b, ret);
-    }
-    fn emit_binop_mul64(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_binop_i64(AssemblerX64::emit_imul, loc_a, loc_b, ret);
+    fn emit_binop_add64(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_binop_i64(AssemblerX64::emit_add, loc_a, loc_b, ret)
     }
-    fn emit_binop_udiv64(
+    fn emit_binop_sub64(
         &mut self,
         loc_a: Location,
         loc_b: Location,
         ret: Location,
-        integer_division_by_zero: Label,
-        _integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<(), CodegenError> {
+        self.emit_binop_i64(AssemblerX64::emit_sub, loc_a, loc_b, ret)
+    }
+    fn emit_binop_mul64(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_binop_i64(AssemblerX64::emit_imul, loc_a, loc_b, ret)
+    }
+    fn emit_binop_udiv64(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+        integer_division_by_zero: Label,
+        _integer_overflow: Label,
+    ) -> Result<usize, CodegenError> {
         // We assume that RAX and RDX are temporary registers here.
         self.assembler
             .emit_mov(Size::S64, loc_a, Location::GPR(GPR::RAX));
@@ -3985,11 +4593,12 @@ impl Machine for MachineX86_64 {
             AssemblerX64::emit_div,
             Size::S64,
             loc_b,
+            IntegerDivision::UnsignedDiv,
             integer_division_by_zero,
-        );
+        )?;
         self.assembler
             .emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
-        offset
+        Ok(offset)
     }
     fn emit_binop_sdiv64(
         &mut self,
@@ -3998,7 +4607,7 @@ impl Machine for MachineX86_64 {
         ret: Location,
         integer_division_by_zero: Label,
         _integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         // We assume that RAX and RDX are temporary registers here.
         self.assembler
             .emit_mov(Size::S64, loc_a, Location::GPR(GPR::RAX));
@@ -4007,11 +4616,12 @@ impl Machine for MachineX86_64 {
             AssemblerX64::emit_idiv,
             Size::S64,
             loc_b,
+            IntegerDivision::SignedDiv,
             integer_division_by_zero,
-        );
+        )?;
         self.assembler
             .emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
-        offset
+        Ok(offset)
     }
     fn emit_binop_urem64(
         &mut self,
@@ -4020,7 +4630,7 @@ impl Machine for MachineX86_64 {
         ret: Location,
         integer_division_by_zero: Label,
         _integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         // We assume that RAX and RDX are temporary registers here.
         self.assembler
             .emit_mov(Size::S64, loc_a, Location::GPR(GPR::RAX));
@@ -4030,11 +4640,12 @@ impl Machine for MachineX86_64 {
             AssemblerX64::emit_div,
             Size::S64,
             loc_b,
+            IntegerDivision::UnsignedRem,
             integer_division_by_zero,
-        );
+        )?;
         self.assembler
             .emit_mov(Size::S64, Location::GPR(GPR::RDX), ret);
-        offset
+        Ok(offset)
     }
     fn emit_binop_srem64(
         &mut self,
@@ -4043,16 +4654,16 @@ impl Machine for MachineX86_64 {
         ret: Location,
         integer_division_by_zero: Label,
         _integer_overflow: Label,
-    ) -> usize {
+    ) -> Result<usize, CodegenError> {
         // We assume that RAX and RDX are temporary registers here.
         let normal_path = self.assembler.get_label();
         let end = self.assembler.get_label();
 
-        self.emit_relaxed_cmp(Size::S64, Location::Imm64(0x8000000000000000u64), loc_a);
+        self.emit_relaxed_cmp(Size::S64, Location::Imm64(0x8000000000000000u64), loc_a)?;
         self.assembler.emit_jmp(Condition::NotEqual, normal_path);
-        self.emit_relaxed_cmp(Size::S64, Location::Imm64(0xffffffffffffffffu64), loc_b);
+        self.emit_relaxed_cmp(Size::S64, Location::Imm64(0xffffffffffffffffu64), loc_b)?;
         self.assembler.emit_jmp(Condition::NotEqual, normal_path);
-        self.move_location(Size::S64, Location::Imm64(0), ret);
+        self.move_location(Size::S64, Location::Imm64(0), ret)?;
         self.assembler.emit_jmp(Condition::None, end);
 
         self.emit_label(normal_path);
@@ -4063,70 +4674,140 @@ impl Machine for MachineX86_64 {
             AssemblerX64::emit_idiv,
             Size::S64,
             loc_b,
+            IntegerDivision::SignedRem,
             integer_division_by_zero,
-        );
+        )?;
         self.assembler
             .emit_mov(Size::S64, Location::GPR(GPR::RDX), ret);
 
         self.emit_label(end);
-        offset
+        Ok(offset)
     }
-    fn emit_binop_and64(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_binop_i64(AssemblerX64::emit_and, loc_a, loc_b, ret);
+    fn emit_binop_and64(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_binop_i64(AssemblerX64::emit_and, loc_a, loc_b, ret)
     }
-    fn emit_binop_or64(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_binop_i64(AssemblerX64::emit_or, loc_a, loc_b, ret);
+    fn emit_binop_or64(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_binop_i64(AssemblerX64::emit_or, loc_a, loc_b, ret)
     }
-    fn emit_binop_xor64(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_binop_i64(AssemblerX64::emit_xor, loc_a, loc_b, ret);
+    fn emit_binop_xor64(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_binop_i64(AssemblerX64::emit_xor, loc_a, loc_b, ret)
     }
-    fn i64_cmp_ge_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::GreaterEqual, loc_a, loc_b, ret);
+    fn i64_cmp_ge_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::GreaterEqual, loc_a, loc_b, ret)
     }
-    fn i64_cmp_gt_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Greater, loc_a, loc_b, ret);
+    fn i64_cmp_gt_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Greater, loc_a, loc_b, ret)
     }
-    fn i64_cmp_le_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::LessEqual, loc_a, loc_b, ret);
+    fn i64_cmp_le_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::LessEqual, loc_a, loc_b, ret)
     }
-    fn i64_cmp_lt_s(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Less, loc_a, loc_b, ret);
+    fn i64_cmp_lt_s(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Less, loc_a, loc_b, ret)
     }
-    fn i64_cmp_ge_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::AboveEqual, loc_a, loc_b, ret);
+    fn i64_cmp_ge_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::AboveEqual, loc_a, loc_b, ret)
     }
-    fn i64_cmp_gt_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Above, loc_a, loc_b, ret);
+    fn i64_cmp_gt_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Above, loc_a, loc_b, ret)
     }
-    fn i64_cmp_le_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::BelowEqual, loc_a, loc_b, ret);
+    fn i64_cmp_le_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::BelowEqual, loc_a, loc_b, ret)
     }
-    fn i64_cmp_lt_u(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Below, loc_a, loc_b, ret);
+    fn i64_cmp_lt_u(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Below, loc_a, loc_b, ret)
     }
-    fn i64_cmp_ne(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::NotEqual, loc_a, loc_b, ret);
+    fn i64_cmp_ne(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::NotEqual, loc_a, loc_b, ret)
     }
-    fn i64_cmp_eq(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_cmpop_i64_dynamic_b(Condition::Equal, loc_a, loc_b, ret);
+    fn i64_cmp_eq(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_cmpop_i64_dynamic_b(Condition::Equal, loc_a, loc_b, ret)
     }
-    fn i64_clz(&mut self, loc: Location, ret: Location) {
+    fn i64_clz(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         let src = match loc {
             Location::Imm64(_) | Location::Imm32(_) | Location::Memory(_, _) => {
-                let tmp = self.acquire_temp_gpr().unwrap();
-                self.move_location(Size::S64, loc, Location::GPR(tmp));
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                self.move_location(Size::S64, loc, Location::GPR(tmp))?;
                 tmp
             }
             Location::GPR(reg) => reg,
             _ => {
-                unreachable!();
+                codegen_error!("singlepass i64_clz unreachable");
             }
         };
         let dst = match ret {
-            Location::Memory(_, _) => self.acquire_temp_gpr().unwrap(),
+            Location::Memory(_, _) => self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?,
             Location::GPR(reg) => reg,
             _ => {
-                unreachable!();
+                codegen_error!("singlepass i64_clz unreachable");
             }
         };
 
@@ -4145,7 +4826,7 @@ impl Machine for MachineX86_64 {
                 .emit_xor(Size::S64, Location::Imm32(63), Location::GPR(dst));
             self.assembler.emit_jmp(Condition::None, end);
             self.emit_label(zero_path);
-            self.move_location(Size::S64, Location::Imm32(64), Location::GPR(dst));
+            self.move_location(Size::S64, Location::Imm32(64), Location::GPR(dst))?;
             self.emit_label(end);
         }
         match loc {
@@ -4155,27 +4836,32 @@ impl Machine for MachineX86_64 {
             _ => {}
         };
         if let Location::Memory(_, _) = ret {
-            self.move_location(Size::S64, Location::GPR(dst), ret);
+            self.move_location(Size::S64, Location::GPR(dst), ret)?;
             self.release_gpr(dst);
         };
+        Ok(())
     }
-    fn i64_ctz(&mut self, loc: Location, ret: Location) {
+    fn i64_ctz(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         let src = match loc {
             Location::Imm64(_) | Location::Imm32(_) | Location::Memory(_, _) => {
-                let tmp = self.acquire_temp_gpr().unwrap();
-                self.move_location(Size::S64, loc, Location::GPR(tmp));
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                self.move_location(Size::S64, loc, Location::GPR(tmp))?;
                 tmp
             }
             Location::GPR(reg) => reg,
             _ => {
-                unreachable!();
+                codegen_error!("singlepass i64_ctz unreachable");
             }
         };
         let dst = match ret {
-            Location::Memory(_, _) => self.acquire_temp_gpr().unwrap(),
+            Location::Memory(_, _) => self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?,
             Location::GPR(reg) => reg,
             _ => {
-                unreachable!();
+                codegen_error!("singlepass i64_ctz unreachable");
             }
         };
 
@@ -4192,7 +4878,7 @@ impl Machine for MachineX86_64 {
                 .emit_bsf(Size::S64, Location::GPR(src), Location::GPR(dst));
             self.assembler.emit_jmp(Condition::None, end);
             self.emit_label(zero_path);
-            self.move_location(Size::S64, Location::Imm64(64), Location::GPR(dst));
+            self.move_location(Size::S64, Location::Imm64(64), Location::GPR(dst))?;
             self.emit_label(end);
         }
 
@@ -4203,23 +4889,28 @@ impl Machine for MachineX86_64 {
             _ => {}
         };
         if let Location::Memory(_, _) = ret {
-            self.move_location(Size::S64, Location::GPR(dst), ret);
+            self.move_location(Size::S64, Location::GPR(dst), ret)?;
             self.release_gpr(dst);
         };
+        Ok(())
     }
-    fn i64_popcnt(&mut self, loc: Location, ret: Location) {
+    fn i64_popcnt(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         match loc {
             Location::Imm64(_) | Location::Imm32(_) => {
-                let tmp = self.acquire_temp_gpr().unwrap();
-                self.move_location(Size::S64, loc, Location::GPR(tmp));
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
+                self.move_location(Size::S64, loc, Location::GPR(tmp))?;
                 if let Location::Memory(_, _) = ret {
-                    let out_tmp = self.acquire_temp_gpr().unwrap();
+                    let out_tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
                     self.assembler.emit_popcnt(
                         Size::S64,
                         Location::GPR(tmp),
                         Location::GPR(out_tmp),
                     );
-                    self.move_location(Size::S64, Location::GPR(out_tmp), ret);
+                    self.move_location(Size::S64, Location::GPR(out_tmp), ret)?;
                     self.release_gpr(out_tmp);
                 } else {
                     self.assembler
@@ -4229,34 +4920,62 @@ impl Machine for MachineX86_64 {
             }
             Location::Memory(_, _) | Location::GPR(_) => {
                 if let Location::Memory(_, _) = ret {
-                    let out_tmp = self.acquire_temp_gpr().unwrap();
+                    let out_tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                        message: "singlepass cannot acquire temp gpr".to_owned(),
+                    })?;
                     self.assembler
                         .emit_popcnt(Size::S64, loc, Location::GPR(out_tmp));
-                    self.move_location(Size::S64, Location::GPR(out_tmp), ret);
+                    self.move_location(Size::S64, Location::GPR(out_tmp), ret)?;
                     self.release_gpr(out_tmp);
                 } else {
                     self.assembler.emit_popcnt(Size::S64, loc, ret);
                 }
             }
             _ => {
-                unreachable!();
+                codegen_error!("singlepass i64_popcnt unreachable");
             }
         }
+        Ok(())
     }
-    fn i64_shl(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_shift_i64(AssemblerX64::emit_shl, loc_a, loc_b, ret);
+    fn i64_shl(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_shift_i64(AssemblerX64::emit_shl, loc_a, loc_b, ret)
     }
-    fn i64_shr(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_shift_i64(AssemblerX64::emit_shr, loc_a, loc_b, ret);
+    fn i64_shr(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_shift_i64(AssemblerX64::emit_shr, loc_a, loc_b, ret)
     }
-    fn i64_sar(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_shift_i64(AssemblerX64::emit_sar, loc_a, loc_b, ret);
+    fn i64_sar(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_shift_i64(AssemblerX64::emit_sar, loc_a, loc_b, ret)
     }
-    fn i64_rol(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_shift_i64(AssemblerX64::emit_rol, loc_a, loc_b, ret);
+    fn i64_rol(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_shift_i64(AssemblerX64::emit_rol, loc_a, loc_b, ret)
     }
-    fn i64_ror(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_shift_i64(AssemblerX64::emit_ror, loc_a, loc_b, ret);
+    fn i64_ror(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_shift_i64(AssemblerX64::emit_ror, loc_a, loc_b, ret)
     }
     fn i64_load(
         &mut self,
@@ -4267,7 +4986,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -4283,9 +5002,9 @@ impl Machine for MachineX86_64 {
                     Size::S64,
                     Location::Memory(addr, 0),
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i64_load_8u(
         &mut self,
@@ -4296,7 +5015,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -4313,9 +5032,9 @@ impl Machine for MachineX86_64 {
                     Location::Memory(addr, 0),
                     Size::S64,
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i64_load_8s(
         &mut self,
@@ -4326,7 +5045,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -4343,9 +5062,9 @@ impl Machine for MachineX86_64 {
                     Location::Memory(addr, 0),
                     Size::S64,
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i64_load_16u(
         &mut self,
@@ -4356,7 +5075,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -4373,9 +5092,9 @@ impl Machine for MachineX86_64 {
                     Location::Memory(addr, 0),
                     Size::S64,
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i64_load_16s(
         &mut self,
@@ -4386,7 +5105,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -4403,9 +5122,9 @@ impl Machine for MachineX86_64 {
                     Location::Memory(addr, 0),
                     Size::S64,
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i64_load_32u(
         &mut self,
@@ -4416,7 +5135,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -4437,7 +5156,7 @@ impl Machine for MachineX86_64 {
                         ); // clear upper bits
                     }
                     _ => {
-                        unreachable!();
+                        codegen_error!("singlepass i64_load_32u unreachable");
                     }
                 }
                 this.emit_relaxed_binop(
@@ -4445,9 +5164,9 @@ impl Machine for MachineX86_64 {
                     Size::S32,
                     Location::Memory(addr, 0),
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i64_load_32s(
         &mut self,
@@ -4458,7 +5177,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -4475,9 +5194,9 @@ impl Machine for MachineX86_64 {
                     Location::Memory(addr, 0),
                     Size::S64,
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i64_atomic_load(
         &mut self,
@@ -4488,7 +5207,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -4498,10 +5217,8 @@ impl Machine for MachineX86_64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_mov(Size::S64, Location::Memory(addr, 0), ret);
-            },
-        );
+            |this, addr| this.emit_relaxed_mov(Size::S64, Location::Memory(addr, 0), ret),
+        )
     }
     fn i64_atomic_load_8u(
         &mut self,
@@ -4512,7 +5229,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -4528,9 +5245,9 @@ impl Machine for MachineX86_64 {
                     Location::Memory(addr, 0),
                     Size::S64,
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i64_atomic_load_16u(
         &mut self,
@@ -4541,7 +5258,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -4557,9 +5274,9 @@ impl Machine for MachineX86_64 {
                     Location::Memory(addr, 0),
                     Size::S64,
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i64_atomic_load_32u(
         &mut self,
@@ -4570,7 +5287,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -4588,10 +5305,10 @@ impl Machine for MachineX86_64 {
                             Size::S32,
                             Location::Imm32(0),
                             Location::Memory(base, offset + 4),
-                        ); // clear upper bits
+                        )?; // clear upper bits
                     }
                     _ => {
-                        unreachable!();
+                        codegen_error!("singlepass i64_atomic_load_32u unreachable");
                     }
                 }
                 this.emit_relaxed_zero_extension(
@@ -4599,9 +5316,9 @@ impl Machine for MachineX86_64 {
                     Location::Memory(addr, 0),
                     Size::S64,
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn i64_save(
         &mut self,
@@ -4612,7 +5329,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -4628,9 +5345,9 @@ impl Machine for MachineX86_64 {
                     Size::S64,
                     target_value,
                     Location::Memory(addr, 0),
-                );
+                )
             },
-        );
+        )
     }
     fn i64_save_8(
         &mut self,
@@ -4641,7 +5358,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -4657,9 +5374,9 @@ impl Machine for MachineX86_64 {
                     Size::S8,
                     target_value,
                     Location::Memory(addr, 0),
-                );
+                )
             },
-        );
+        )
     }
     fn i64_save_16(
         &mut self,
@@ -4670,7 +5387,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -4686,9 +5403,9 @@ impl Machine for MachineX86_64 {
                     Size::S16,
                     target_value,
                     Location::Memory(addr, 0),
-                );
+                )
             },
-        );
+        )
     }
     fn i64_save_32(
         &mut self,
@@ -4699,7 +5416,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -4715,9 +5432,9 @@ impl Machine for MachineX86_64 {
                     Size::S32,
                     target_value,
                     Location::Memory(addr, 0),
-                );
+                )
             },
-        );
+        )
     }
     fn i64_atomic_save(
         &mut self,
@@ -4728,7 +5445,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -4738,10 +5455,8 @@ impl Machine for MachineX86_64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_atomic_xchg(Size::S64, value, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_atomic_xchg(Size::S64, value, Location::Memory(addr, 0)),
+        )
     }
     fn i64_atomic_save_8(
         &mut self,
@@ -4752,7 +5467,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -4762,10 +5477,8 @@ impl Machine for MachineX86_64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_atomic_xchg(Size::S8, value, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_atomic_xchg(Size::S8, value, Location::Memory(addr, 0)),
+        )
     }
     fn i64_atomic_save_16(
         &mut self,
@@ -4776,7 +5489,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -4786,10 +5499,8 @@ impl Machine for MachineX86_64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_atomic_xchg(Size::S16, value, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_atomic_xchg(Size::S16, value, Location::Memory(addr, 0)),
+        )
     }
     fn i64_atomic_save_32(
         &mut self,
@@ -4800,7 +5511,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             target_addr,
             memarg,
@@ -4810,10 +5521,8 @@ impl Machine for MachineX86_64 {
             imported_memories,
             offset,
             heap_access_oob,
-            |this, addr| {
-                this.emit_relaxed_atomic_xchg(Size::S32, value, Location::Memory(addr, 0));
-            },
-        );
+            |this, addr| this.emit_relaxed_atomic_xchg(Size::S32, value, Location::Memory(addr, 0)),
+        )
     }
     // i64 atomic Add with i64
     fn i64_atomic_add(
@@ -4826,9 +5535,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.move_location(Size::S64, loc, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.move_location(Size::S64, loc, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -4844,10 +5555,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S64, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S64, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i64 atomic Add with u8
     fn i64_atomic_add_8u(
@@ -4860,9 +5573,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.move_location_extend(Size::S8, false, loc, Size::S64, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.move_location_extend(Size::S8, false, loc, Size::S64, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -4878,10 +5593,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S64, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S64, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i64 atomic Add with u16
     fn i64_atomic_add_16u(
@@ -4894,9 +5611,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.move_location_extend(Size::S16, false, loc, Size::S64, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.move_location_extend(Size::S16, false, loc, Size::S64, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -4912,10 +5631,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S64, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S64, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i64 atomic Add with u32
     fn i64_atomic_add_32u(
@@ -4928,9 +5649,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.move_location_extend(Size::S32, false, loc, Size::S64, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.move_location_extend(Size::S32, false, loc, Size::S64, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -4946,10 +5669,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S64, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S64, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i64 atomic Sub with i64
     fn i64_atomic_sub(
@@ -4962,9 +5687,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.location_neg(Size::S64, false, loc, Size::S64, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.location_neg(Size::S64, false, loc, Size::S64, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -4980,10 +5707,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S64, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S64, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i64 atomic Sub with u8
     fn i64_atomic_sub_8u(
@@ -4996,9 +5725,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.location_neg(Size::S8, false, loc, Size::S64, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.location_neg(Size::S8, false, loc, Size::S64, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -5014,10 +5745,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S64, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S64, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i64 atomic Sub with u16
     fn i64_atomic_sub_16u(
@@ -5030,9 +5763,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.location_neg(Size::S16, false, loc, Size::S64, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.location_neg(Size::S16, false, loc, Size::S64, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -5048,10 +5783,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S64, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S64, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i64 atomic Sub with u32
     fn i64_atomic_sub_32u(
@@ -5064,9 +5801,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.location_neg(Size::S32, false, loc, Size::S64, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.location_neg(Size::S32, false, loc, Size::S64, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -5082,10 +5821,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S64, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S64, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i64 atomic And with i64
     fn i64_atomic_and(
@@ -5098,7 +5839,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -5114,8 +5855,9 @@ impl Machine for MachineX86_64 {
             |this, src, dst| {
                 this.assembler
                     .emit_and(Size::S64, Location::GPR(src), Location::GPR(dst));
+                Ok(())
             },
-        );
+        )
     }
     // i64 atomic And with u8
     fn i64_atomic_and_8u(
@@ -5128,7 +5870,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -5144,8 +5886,9 @@ impl Machine for MachineX86_64 {
             |this, src, dst| {
                 this.assembler
                     .emit_and(Size::S64, Location::GPR(src), Location::GPR(dst));
+                Ok(())
             },
-        );
+        )
     }
     // i64 atomic And with u16
     fn i64_atomic_and_16u(
@@ -5158,7 +5901,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -5174,8 +5917,9 @@ impl Machine for MachineX86_64 {
             |this, src, dst| {
                 this.assembler
                     .emit_and(Size::S64, Location::GPR(src), Location::GPR(dst));
+                Ok(())
             },
-        );
+        )
     }
     // i64 atomic And with u32
     fn i64_atomic_and_32u(
@@ -5188,7 +5932,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -5204,8 +5948,9 @@ impl Machine for MachineX86_64 {
             |this, src, dst| {
                 this.assembler
                     .emit_and(Size::S64, Location::GPR(src), Location::GPR(dst));
+                Ok(())
             },
-        );
+        )
     }
     // i64 atomic Or with i64
     fn i64_atomic_or(
@@ -5218,7 +5963,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -5232,9 +5977,9 @@ impl Machine for MachineX86_64 {
             offset,
             heap_access_oob,
             |this, src, dst| {
-                this.location_or(Size::S64, Location::GPR(src), Location::GPR(dst), false);
+                this.location_or(Size::S64, Location::GPR(src), Location::GPR(dst), false)
             },
-        );
+        )
     }
     // i64 atomic Or with u8
     fn i64_atomic_or_8u(
@@ -5247,7 +5992,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -5261,9 +6006,9 @@ impl Machine for MachineX86_64 {
             offset,
             heap_access_oob,
             |this, src, dst| {
-                this.location_or(Size::S64, Location::GPR(src), Location::GPR(dst), false);
+                this.location_or(Size::S64, Location::GPR(src), Location::GPR(dst), false)
             },
-        );
+        )
     }
     // i64 atomic Or with u16
     fn i64_atomic_or_16u(
@@ -5276,7 +6021,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -5290,9 +6035,9 @@ impl Machine for MachineX86_64 {
             offset,
             heap_access_oob,
             |this, src, dst| {
-                this.location_or(Size::S64, Location::GPR(src), Location::GPR(dst), false);
+                this.location_or(Size::S64, Location::GPR(src), Location::GPR(dst), false)
             },
-        );
+        )
     }
     // i64 atomic Or with u32
     fn i64_atomic_or_32u(
@@ -5305,7 +6050,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -5319,9 +6064,9 @@ impl Machine for MachineX86_64 {
             offset,
             heap_access_oob,
             |this, src, dst| {
-                this.location_or(Size::S64, Location::GPR(src), Location::GPR(dst), false);
+                this.location_or(Size::S64, Location::GPR(src), Location::GPR(dst), false)
             },
-        );
+        )
     }
     // i64 atomic xor with i64
     fn i64_atomic_xor(
@@ -5334,7 +6079,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -5348,9 +6093,9 @@ impl Machine for MachineX86_64 {
             offset,
             heap_access_oob,
             |this, src, dst| {
-                this.location_xor(Size::S64, Location::GPR(src), Location::GPR(dst), false);
+                this.location_xor(Size::S64, Location::GPR(src), Location::GPR(dst), false)
             },
-        );
+        )
     }
     // i64 atomic xor with u8
     fn i64_atomic_xor_8u(
@@ -5363,7 +6108,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -5377,9 +6122,9 @@ impl Machine for MachineX86_64 {
             offset,
             heap_access_oob,
             |this, src, dst| {
-                this.location_xor(Size::S64, Location::GPR(src), Location::GPR(dst), false);
+                this.location_xor(Size::S64, Location::GPR(src), Location::GPR(dst), false)
             },
-        );
+        )
     }
     // i64 atomic xor with u16
     fn i64_atomic_xor_16u(
@@ -5392,7 +6137,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -5406,9 +6151,9 @@ impl Machine for MachineX86_64 {
             offset,
             heap_access_oob,
             |this, src, dst| {
-                this.location_xor(Size::S64, Location::GPR(src), Location::GPR(dst), false);
+                this.location_xor(Size::S64, Location::GPR(src), Location::GPR(dst), false)
             },
-        );
+        )
     }
     // i64 atomic xor with u32
     fn i64_atomic_xor_32u(
@@ -5421,7 +6166,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.emit_compare_and_swap(
             loc,
             target,
@@ -5435,9 +6180,9 @@ impl Machine for MachineX86_64 {
             offset,
             heap_access_oob,
             |this, src, dst| {
-                this.location_xor(Size::S64, Location::GPR(src), Location::GPR(dst), false);
+                this.location_xor(Size::S64, Location::GPR(src), Location::GPR(dst), false)
             },
-        );
+        )
     }
     // i64 atomic Exchange with i64
     fn i64_atomic_xchg(
@@ -5450,9 +6195,11 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
-        self.move_location(Size::S64, loc, Location::GPR(value));
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.move_location(Size::S64, loc, Location::GPR(value))?;
         self.memory_op(
             target,
             memarg,
@@ -5468,10 +6215,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S64, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S64, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i64 atomic Exchange with u8
     fn i64_atomic_xchg_8u(
@@ -5484,8 +6233,10 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         self.assembler
             .emit_movzx(Size::S8, loc, Size::S64, Location::GPR(value));
         self.memory_op(
@@ -5500,10 +6251,12 @@ impl Machine for MachineX86_64 {
             |this, addr| {
                 this.assembler
                     .emit_xchg(Size::S8, Location::GPR(value), Location::Memory(addr, 0));
+                Ok(())
             },
-        );
-        self.move_location(Size::S64, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S64, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i64 atomic Exchange with u16
     fn i64_atomic_xchg_16u(
@@ -5516,8 +6269,10 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         self.assembler
             .emit_movzx(Size::S16, loc, Size::S64, Location::GPR(value));
         self.memory_op(
@@ -5535,10 +6290,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S64, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S64, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i64 atomic Exchange with u32
     fn i64_atomic_xchg_32u(
@@ -5551,8 +6308,10 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
-        let value = self.acquire_temp_gpr().unwrap();
+    ) -> Result<(), CodegenError> {
+        let value = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
         self.assembler
             .emit_movzx(Size::S32, loc, Size::S64, Location::GPR(value));
         self.memory_op(
@@ -5570,10 +6329,12 @@ impl Machine for MachineX86_64 {
                     Location::GPR(value),
                     Location::Memory(addr, 0),
                 );
+                Ok(())
             },
-        );
-        self.move_location(Size::S64, Location::GPR(value), ret);
+        )?;
+        self.move_location(Size::S64, Location::GPR(value), ret)?;
         self.release_gpr(value);
+        Ok(())
     }
     // i64 atomic Exchange with i64
     fn i64_atomic_cmpxchg(
@@ -5587,7 +6348,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         let compare = self.reserve_unused_temp_gpr(GPR::RAX);
         let value = if cmp == Location::GPR(GPR::R14) {
             if new == Location::GPR(GPR::R13) {
@@ -5621,10 +6382,12 @@ impl Machine for MachineX86_64 {
                 );
                 this.assembler
                     .emit_mov(Size::S64, Location::GPR(compare), ret);
+                Ok(())
             },
-        );
+        )?;
         self.assembler.emit_pop(Size::S64, Location::GPR(value));
         self.release_gpr(compare);
+        Ok(())
     }
     // i64 atomic Exchange with u8
     fn i64_atomic_cmpxchg_8u(
@@ -5638,7 +6401,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         let compare = self.reserve_unused_temp_gpr(GPR::RAX);
         let value = if cmp == Location::GPR(GPR::R14) {
             if new == Location::GPR(GPR::R13) {
@@ -5672,10 +6435,12 @@ impl Machine for MachineX86_64 {
                 );
                 this.assembler
                     .emit_movzx(Size::S8, Location::GPR(compare), Size::S64, ret);
+                Ok(())
             },
-        );
+        )?;
         self.assembler.emit_pop(Size::S64, Location::GPR(value));
         self.release_gpr(compare);
+        Ok(())
     }
     // i64 atomic Exchange with u16
     fn i64_atomic_cmpxchg_16u(
@@ -5689,7 +6454,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         let compare = self.reserve_unused_temp_gpr(GPR::RAX);
         let value = if cmp == Location::GPR(GPR::R14) {
             if new == Location::GPR(GPR::R13) {
@@ -5723,10 +6488,12 @@ impl Machine for MachineX86_64 {
                 );
                 this.assembler
                     .emit_movzx(Size::S16, Location::GPR(compare), Size::S64, ret);
+                Ok(())
             },
-        );
+        )?;
         self.assembler.emit_pop(Size::S64, Location::GPR(value));
         self.release_gpr(compare);
+        Ok(())
     }
     // i64 atomic Exchange with u32
     fn i64_atomic_cmpxchg_32u(
@@ -5740,7 +6507,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         let compare = self.reserve_unused_temp_gpr(GPR::RAX);
         let value = if cmp == Location::GPR(GPR::R14) {
             if new == Location::GPR(GPR::R13) {
@@ -5774,10 +6541,12 @@ impl Machine for MachineX86_64 {
                 );
                 this.assembler
                     .emit_mov(Size::S32, Location::GPR(compare), ret);
+                Ok(())
             },
-        );
+        )?;
         self.assembler.emit_pop(Size::S64, Location::GPR(value));
         self.release_gpr(compare);
+        Ok(())
     }
 
     fn f32_load(
@@ -5789,7 +6558,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -5805,9 +6574,9 @@ impl Machine for MachineX86_64 {
                     Size::S32,
                     Location::Memory(addr, 0),
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn f32_save(
         &mut self,
@@ -5819,7 +6588,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         let canonicalize = canonicalize && self.arch_supports_canonicalize_nan();
         self.memory_op(
             target_addr,
@@ -5837,12 +6606,13 @@ impl Machine for MachineX86_64 {
                         Size::S32,
                         target_value,
                         Location::Memory(addr, 0),
-                    );
+                    )?;
                 } else {
-                    this.canonicalize_nan(Size::S32, target_value, Location::Memory(addr, 0));
+                    this.canonicalize_nan(Size::S32, target_value, Location::Memory(addr, 0))?;
                 }
+                Ok(())
             },
-        );
+        )
     }
     fn f64_load(
         &mut self,
@@ -5853,7 +6623,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         self.memory_op(
             addr,
             memarg,
@@ -5869,9 +6639,9 @@ impl Machine for MachineX86_64 {
                     Size::S64,
                     Location::Memory(addr, 0),
                     ret,
-                );
+                )
             },
-        );
+        )
     }
     fn f64_save(
         &mut self,
@@ -5883,7 +6653,7 @@ impl Machine for MachineX86_64 {
         imported_memories: bool,
         offset: i32,
         heap_access_oob: Label,
-    ) {
+    ) -> Result<(), CodegenError> {
         let canonicalize = canonicalize && self.arch_supports_canonicalize_nan();
         self.memory_op(
             target_addr,
@@ -5901,38 +6671,54 @@ impl Machine for MachineX86_64 {
                         Size::S64,
                         target_value,
                         Location::Memory(addr, 0),
-                    );
+                    )?;
                 } else {
-                    this.canonicalize_nan(Size::S64, target_value, Location::Memory(addr, 0));
+                    this.canonicalize_nan(Size::S64, target_value, Location::Memory(addr, 0))?;
                 }
+                Ok(())
             },
-        );
+        )
     }
 
-    fn convert_f64_i64(&mut self, loc: Location, signed: bool, ret: Location) {
+    fn convert_f64_i64(
+        &mut self,
+        loc: Location,
+        signed: bool,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         if self.assembler.arch_has_fconverti() {
-            let tmp_out = self.acquire_temp_simd().unwrap();
-            let tmp_in = self.acquire_temp_gpr().unwrap();
-            self.emit_relaxed_mov(Size::S64, loc, Location::GPR(tmp_in));
+            let tmp_out = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S64, loc, Location::GPR(tmp_in))?;
             if signed {
                 self.assembler.arch_emit_f64_convert_si64(tmp_in, tmp_out);
             } else {
                 self.assembler.arch_emit_f64_convert_ui64(tmp_in, tmp_out);
             }
-            self.emit_relaxed_mov(Size::S64, Location::SIMD(tmp_out), ret);
+            self.emit_relaxed_mov(Size::S64, Location::SIMD(tmp_out), ret)?;
             self.release_gpr(tmp_in);
             self.release_simd(tmp_out);
         } else {
-            let tmp_out = self.acquire_temp_simd().unwrap();
-            let tmp_in = self.acquire_temp_gpr().unwrap();
+            let tmp_out = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
             if signed {
                 self.assembler
                     .emit_mov(Size::S64, loc, Location::GPR(tmp_in));
                 self.assembler
                     .emit_vcvtsi2sd_64(tmp_out, GPROrMemory::GPR(tmp_in), tmp_out);
-                self.move_location(Size::S64, Location::SIMD(tmp_out), ret);
+                self.move_location(Size::S64, Location::SIMD(tmp_out), ret)?;
             } else {
-                let tmp = self.acquire_temp_gpr().unwrap();
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
 
                 let do_convert = self.assembler.get_label();
                 let end_convert = self.assembler.get_label();
@@ -5945,7 +6731,7 @@ impl Machine for MachineX86_64 {
                     .emit_vcvtsi2sd_64(tmp_out, GPROrMemory::GPR(tmp_in), tmp_out);
                 self.assembler.emit_jmp(Condition::None, end_convert);
                 self.emit_label(do_convert);
-                self.move_location(Size::S64, Location::GPR(tmp_in), Location::GPR(tmp));
+                self.move_location(Size::S64, Location::GPR(tmp_in), Location::GPR(tmp))?;
                 self.assembler
                     .emit_and(Size::S64, Location::Imm32(1), Location::GPR(tmp));
                 self.assembler
@@ -5957,7 +6743,7 @@ impl Machine for MachineX86_64 {
                 self.assembler
                     .emit_vaddsd(tmp_out, XMMOrMemory::XMM(tmp_out), tmp_out);
                 self.emit_label(end_convert);
-                self.move_location(Size::S64, Location::SIMD(tmp_out), ret);
+                self.move_location(Size::S64, Location::SIMD(tmp_out), ret)?;
 
                 self.release_gpr(tmp);
             }
@@ -5965,23 +6751,37 @@ impl Machine for MachineX86_64 {
             self.release_gpr(tmp_in);
             self.release_simd(tmp_out);
         }
+        Ok(())
     }
-    fn convert_f64_i32(&mut self, loc: Location, signed: bool, ret: Location) {
+    fn convert_f64_i32(
+        &mut self,
+        loc: Location,
+        signed: bool,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         if self.assembler.arch_has_fconverti() {
-            let tmp_out = self.acquire_temp_simd().unwrap();
-            let tmp_in = self.acquire_temp_gpr().unwrap();
-            self.emit_relaxed_mov(Size::S32, loc, Location::GPR(tmp_in));
+            let tmp_out = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S32, loc, Location::GPR(tmp_in))?;
             if signed {
                 self.assembler.arch_emit_f64_convert_si32(tmp_in, tmp_out);
             } else {
                 self.assembler.arch_emit_f64_convert_ui32(tmp_in, tmp_out);
             }
-            self.emit_relaxed_mov(Size::S64, Location::SIMD(tmp_out), ret);
+            self.emit_relaxed_mov(Size::S64, Location::SIMD(tmp_out), ret)?;
             self.release_gpr(tmp_in);
             self.release_simd(tmp_out);
         } else {
-            let tmp_out = self.acquire_temp_simd().unwrap();
-            let tmp_in = self.acquire_temp_gpr().unwrap();
+            let tmp_out = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
 
             self.assembler
                 .emit_mov(Size::S32, loc, Location::GPR(tmp_in));
@@ -5992,36 +6792,52 @@ impl Machine for MachineX86_64 {
                 self.assembler
                     .emit_vcvtsi2sd_64(tmp_out, GPROrMemory::GPR(tmp_in), tmp_out);
             }
-            self.move_location(Size::S64, Location::SIMD(tmp_out), ret);
+            self.move_location(Size::S64, Location::SIMD(tmp_out), ret)?;
 
             self.release_gpr(tmp_in);
             self.release_simd(tmp_out);
         }
+        Ok(())
     }
-    fn convert_f32_i64(&mut self, loc: Location, signed: bool, ret: Location) {
+    fn convert_f32_i64(
+        &mut self,
+        loc: Location,
+        signed: bool,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         if self.assembler.arch_has_fconverti() {
-            let tmp_out = self.acquire_temp_simd().unwrap();
-            let tmp_in = self.acquire_temp_gpr().unwrap();
-            self.emit_relaxed_mov(Size::S64, loc, Location::GPR(tmp_in));
+            let tmp_out = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S64, loc, Location::GPR(tmp_in))?;
             if signed {
                 self.assembler.arch_emit_f32_convert_si64(tmp_in, tmp_out);
             } else {
                 self.assembler.arch_emit_f32_convert_ui64(tmp_in, tmp_out);
             }
-            self.emit_relaxed_mov(Size::S32, Location::SIMD(tmp_out), ret);
+            self.emit_relaxed_mov(Size::S32, Location::SIMD(tmp_out), ret)?;
             self.release_gpr(tmp_in);
             self.release_simd(tmp_out);
         } else {
-            let tmp_out = self.acquire_temp_simd().unwrap();
-            let tmp_in = self.acquire_temp_gpr().unwrap();
+            let tmp_out = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
             if signed {
                 self.assembler
                     .emit_mov(Size::S64, loc, Location::GPR(tmp_in));
                 self.assembler
                     .emit_vcvtsi2ss_64(tmp_out, GPROrMemory::GPR(tmp_in), tmp_out);
-                self.move_location(Size::S32, Location::SIMD(tmp_out), ret);
+                self.move_location(Size::S32, Location::SIMD(tmp_out), ret)?;
             } else {
-                let tmp = self.acquire_temp_gpr().unwrap();
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                    message: "singlepass cannot acquire temp gpr".to_owned(),
+                })?;
 
                 let do_convert = self.assembler.get_label();
                 let end_convert = self.assembler.get_label();
@@ -6034,7 +6850,7 @@ impl Machine for MachineX86_64 {
                     .emit_vcvtsi2ss_64(tmp_out, GPROrMemory::GPR(tmp_in), tmp_out);
                 self.assembler.emit_jmp(Condition::None, end_convert);
                 self.emit_label(do_convert);
-                self.move_location(Size::S64, Location::GPR(tmp_in), Location::GPR(tmp));
+                self.move_location(Size::S64, Location::GPR(tmp_in), Location::GPR(tmp))?;
                 self.assembler
                     .emit_and(Size::S64, Location::Imm32(1), Location::GPR(tmp));
                 self.assembler
@@ -6046,30 +6862,44 @@ impl Machine for MachineX86_64 {
                 self.assembler
                     .emit_vaddss(tmp_out, XMMOrMemory::XMM(tmp_out), tmp_out);
                 self.emit_label(end_convert);
-                self.move_location(Size::S32, Location::SIMD(tmp_out), ret);
+                self.move_location(Size::S32, Location::SIMD(tmp_out), ret)?;
 
                 self.release_gpr(tmp);
             }
             self.release_gpr(tmp_in);
             self.release_simd(tmp_out);
         }
+        Ok(())
     }
-    fn convert_f32_i32(&mut self, loc: Location, signed: bool, ret: Location) {
+    fn convert_f32_i32(
+        &mut self,
+        loc: Location,
+        signed: bool,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         if self.assembler.arch_has_fconverti() {
-            let tmp_out = self.acquire_temp_simd().unwrap();
-            let tmp_in = self.acquire_temp_gpr().unwrap();
-            self.emit_relaxed_mov(Size::S32, loc, Location::GPR(tmp_in));
+            let tmp_out = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S32, loc, Location::GPR(tmp_in))?;
             if signed {
                 self.assembler.arch_emit_f32_convert_si32(tmp_in, tmp_out);
             } else {
                 self.assembler.arch_emit_f32_convert_ui32(tmp_in, tmp_out);
             }
-            self.emit_relaxed_mov(Size::S32, Location::SIMD(tmp_out), ret);
+            self.emit_relaxed_mov(Size::S32, Location::SIMD(tmp_out), ret)?;
             self.release_gpr(tmp_in);
             self.release_simd(tmp_out);
         } else {
-            let tmp_out = self.acquire_temp_simd().unwrap();
-            let tmp_in = self.acquire_temp_gpr().unwrap();
+            let tmp_out = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmp_in = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
 
             self.assembler
                 .emit_mov(Size::S32, loc, Location::GPR(tmp_in));
@@ -6080,90 +6910,131 @@ impl Machine for MachineX86_64 {
                 self.assembler
                     .emit_vcvtsi2ss_64(tmp_out, GPROrMemory::GPR(tmp_in), tmp_out);
             }
-            self.move_location(Size::S32, Location::SIMD(tmp_out), ret);
+            self.move_location(Size::S32, Location::SIMD(tmp_out), ret)?;
 
             self.release_gpr(tmp_in);
             self.release_simd(tmp_out);
         }
+        Ok(())
     }
-    fn convert_i64_f64(&mut self, loc: Location, ret: Location, signed: bool, sat: bool) {
+    fn convert_i64_f64(
+        &mut self,
+        loc: Location,
+        ret: Location,
+        signed: bool,
+        sat: bool,
+    ) -> Result<(), CodegenError> {
         match (signed, sat) {
-            (false, true) => self.convert_i64_f64_u_s(loc, ret),
-            (false, false) => self.convert_i64_f64_u_u(loc, ret),
-            (true, true) => self.convert_i64_f64_s_s(loc, ret),
-            (true, false) => self.convert_i64_f64_s_u(loc, ret),
+            (false, true) => self.convert_i64_f64_u_s(loc, ret)?,
+            (false, false) => self.convert_i64_f64_u_u(loc, ret)?,
+            (true, true) => self.convert_i64_f64_s_s(loc, ret)?,
+            (true, false) => self.convert_i64_f64_s_u(loc, ret)?,
         }
+        Ok(())
     }
-    fn convert_i32_f64(&mut self, loc: Location, ret: Location, signed: bool, sat: bool) {
+    fn convert_i32_f64(
+        &mut self,
+        loc: Location,
+        ret: Location,
+        signed: bool,
+        sat: bool,
+    ) -> Result<(), CodegenError> {
         match (signed, sat) {
-            (false, true) => self.convert_i32_f64_u_s(loc, ret),
-            (false, false) => self.convert_i32_f64_u_u(loc, ret),
-            (true, true) => self.convert_i32_f64_s_s(loc, ret),
-            (true, false) => self.convert_i32_f64_s_u(loc, ret),
+            (false, true) => self.convert_i32_f64_u_s(loc, ret)?,
+            (false, false) => self.convert_i32_f64_u_u(loc, ret)?,
+            (true, true) => self.convert_i32_f64_s_s(loc, ret)?,
+            (true, false) => self.convert_i32_f64_s_u(loc, ret)?,
         }
+        Ok(())
     }
-    fn convert_i64_f32(&mut self, loc: Location, ret: Location, signed: bool, sat: bool) {
+    fn convert_i64_f32(
+        &mut self,
+        loc: Location,
+        ret: Location,
+        signed: bool,
+        sat: bool,
+    ) -> Result<(), CodegenError> {
         match (signed, sat) {
-            (false, true) => self.convert_i64_f32_u_s(loc, ret),
-            (false, false) => self.convert_i64_f32_u_u(loc, ret),
-            (true, true) => self.convert_i64_f32_s_s(loc, ret),
-            (true, false) => self.convert_i64_f32_s_u(loc, ret),
+            (false, true) => self.convert_i64_f32_u_s(loc, ret)?,
+            (false, false) => self.convert_i64_f32_u_u(loc, ret)?,
+            (true, true) => self.convert_i64_f32_s_s(loc, ret)?,
+            (true, false) => self.convert_i64_f32_s_u(loc, ret)?,
         }
+        Ok(())
     }
-    fn convert_i32_f32(&mut self, loc: Location, ret: Location, signed: bool, sat: bool) {
+    fn convert_i32_f32(
+        &mut self,
+        loc: Location,
+        ret: Location,
+        signed: bool,
+        sat: bool,
+    ) -> Result<(), CodegenError> {
         match (signed, sat) {
-            (false, true) => self.convert_i32_f32_u_s(loc, ret),
-            (false, false) => self.convert_i32_f32_u_u(loc, ret),
-            (true, true) => self.convert_i32_f32_s_s(loc, ret),
-            (true, false) => self.convert_i32_f32_s_u(loc, ret),
+            (false, true) => self.convert_i32_f32_u_s(loc, ret)?,
+            (false, false) => self.convert_i32_f32_u_u(loc, ret)?,
+            (true, true) => self.convert_i32_f32_s_s(loc, ret)?,
+            (true, false) => self.convert_i32_f32_s_u(loc, ret)?,
         }
+        Ok(())
     }
-    fn convert_f64_f32(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vcvtss2sd, loc, loc, ret);
+    fn convert_f64_f32(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vcvtss2sd, loc, loc, ret)
     }
-    fn convert_f32_f64(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vcvtsd2ss, loc, loc, ret);
+    fn convert_f32_f64(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vcvtsd2ss, loc, loc, ret)
     }
-    fn f64_neg(&mut self, loc: Location, ret: Location) {
+    fn f64_neg(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         if self.assembler.arch_has_fneg() {
-            let tmp = self.acquire_temp_simd().unwrap();
-            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp));
+            let tmp = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S64, loc, Location::SIMD(tmp))?;
             self.assembler.arch_emit_f64_neg(tmp, tmp);
-            self.emit_relaxed_mov(Size::S64, Location::SIMD(tmp), ret);
+            self.emit_relaxed_mov(Size::S64, Location::SIMD(tmp), ret)?;
             self.release_simd(tmp);
         } else {
-            let tmp = self.acquire_temp_gpr().unwrap();
-            self.move_location(Size::S64, loc, Location::GPR(tmp));
+            let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            self.move_location(Size::S64, loc, Location::GPR(tmp))?;
             self.assembler.emit_btc_gpr_imm8_64(63, tmp);
-            self.move_location(Size::S64, Location::GPR(tmp), ret);
+            self.move_location(Size::S64, Location::GPR(tmp), ret)?;
             self.release_gpr(tmp);
         }
+        Ok(())
     }
-    fn f64_abs(&mut self, loc: Location, ret: Location) {
-        let tmp = self.acquire_temp_gpr().unwrap();
-        let c = self.acquire_temp_gpr().unwrap();
+    fn f64_abs(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        let c = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
 
-        self.move_location(Size::S64, loc, Location::GPR(tmp));
+        self.move_location(Size::S64, loc, Location::GPR(tmp))?;
         self.move_location(
             Size::S64,
             Location::Imm64(0x7fffffffffffffffu64),
             Location::GPR(c),
-        );
+        )?;
         self.assembler
             .emit_and(Size::S64, Location::GPR(c), Location::GPR(tmp));
-        self.move_location(Size::S64, Location::GPR(tmp), ret);
+        self.move_location(Size::S64, Location::GPR(tmp), ret)?;
 
         self.release_gpr(c);
         self.release_gpr(tmp);
+        Ok(())
     }
-    fn emit_i64_copysign(&mut self, tmp1: GPR, tmp2: GPR) {
-        let c = self.acquire_temp_gpr().unwrap();
+    fn emit_i64_copysign(&mut self, tmp1: GPR, tmp2: GPR) -> Result<(), CodegenError> {
+        let c = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
 
         self.move_location(
             Size::S64,
             Location::Imm64(0x7fffffffffffffffu64),
             Location::GPR(c),
-        );
+        )?;
         self.assembler
             .emit_and(Size::S64, Location::GPR(c), Location::GPR(tmp1));
 
@@ -6171,7 +7042,7 @@ impl Machine for MachineX86_64 {
             Size::S64,
             Location::Imm64(0x8000000000000000u64),
             Location::GPR(c),
-        );
+        )?;
         self.assembler
             .emit_and(Size::S64, Location::GPR(c), Location::GPR(tmp2));
 
@@ -6179,93 +7050,143 @@ impl Machine for MachineX86_64 {
             .emit_or(Size::S64, Location::GPR(tmp2), Location::GPR(tmp1));
 
         self.release_gpr(c);
+        Ok(())
     }
-    fn f64_sqrt(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vsqrtsd, loc, loc, ret);
+    fn f64_sqrt(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vsqrtsd, loc, loc, ret)
     }
-    fn f64_trunc(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vroundsd_trunc, loc, loc, ret);
+    fn f64_trunc(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vroundsd_trunc, loc, loc, ret)
     }
-    fn f64_ceil(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vroundsd_ceil, loc, loc, ret);
+    fn f64_ceil(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vroundsd_ceil, loc, loc, ret)
     }
-    fn f64_floor(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vroundsd_floor, loc, loc, ret);
+    fn f64_floor(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vroundsd_floor, loc, loc, ret)
     }
-    fn f64_nearest(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vroundsd_nearest, loc, loc, ret);
+    fn f64_nearest(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vroundsd_nearest, loc, loc, ret)
     }
-    fn f64_cmp_ge(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vcmpgesd, loc_a, loc_b, ret);
+    fn f64_cmp_ge(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vcmpgesd, loc_a, loc_b, ret)?;
         self.assembler.emit_and(Size::S32, Location::Imm32(1), ret);
+        Ok(())
     }
-    fn f64_cmp_gt(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vcmpgtsd, loc_a, loc_b, ret);
+    fn f64_cmp_gt(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vcmpgtsd, loc_a, loc_b, ret)?;
         self.assembler.emit_and(Size::S32, Location::Imm32(1), ret);
+        Ok(())
     }
-    fn f64_cmp_le(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vcmplesd, loc_a, loc_b, ret);
+    fn f64_cmp_le(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vcmplesd, loc_a, loc_b, ret)?;
         self.assembler.emit_and(Size::S32, Location::Imm32(1), ret);
+        Ok(())
     }
-    fn f64_cmp_lt(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vcmpltsd, loc_a, loc_b, ret);
+    fn f64_cmp_lt(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vcmpltsd, loc_a, loc_b, ret)?;
         self.assembler.emit_and(Size::S32, Location::Imm32(1), ret);
+        Ok(())
     }
-    fn f64_cmp_ne(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vcmpneqsd, loc_a, loc_b, ret);
+    fn f64_cmp_ne(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vcmpneqsd, loc_a, loc_b, ret)?;
         self.assembler.emit_and(Size::S32, Location::Imm32(1), ret);
+        Ok(())
     }
-    fn f64_cmp_eq(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vcmpeqsd, loc_a, loc_b, ret);
+    fn f64_cmp_eq(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vcmpeqsd, loc_a, loc_b, ret)?;
         self.assembler.emit_and(Size::S32, Location::Imm32(1), ret);
+        Ok(())
     }
-    fn f64_min(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f64_min(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         if !self.arch_supports_canonicalize_nan() {
-            self.emit_relaxed_avx(AssemblerX64::emit_vminsd, loc_a, loc_b, ret);
+            self.emit_relaxed_avx(AssemblerX64::emit_vminsd, loc_a, loc_b, ret)?;
         } else {
-            let tmp1 = self.acquire_temp_simd().unwrap();
-            let tmp2 = self.acquire_temp_simd().unwrap();
-            let tmpg1 = self.acquire_temp_gpr().unwrap();
-            let tmpg2 = self.acquire_temp_gpr().unwrap();
+            let tmp1 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmp2 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmpg1 = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmpg2 = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
 
             let src1 = match loc_a {
                 Location::SIMD(x) => x,
                 Location::GPR(_) | Location::Memory(_, _) => {
-                    self.move_location(Size::S64, loc_a, Location::SIMD(tmp1));
+                    self.move_location(Size::S64, loc_a, Location::SIMD(tmp1))?;
                     tmp1
                 }
                 Location::Imm32(_) => {
-                    self.move_location(Size::S32, loc_a, Location::GPR(tmpg1));
-                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp1));
+                    self.move_location(Size::S32, loc_a, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp1))?;
                     tmp1
                 }
                 Location::Imm64(_) => {
-                    self.move_location(Size::S64, loc_a, Location::GPR(tmpg1));
-                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp1));
+                    self.move_location(Size::S64, loc_a, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp1))?;
                     tmp1
                 }
                 _ => {
-                    unreachable!();
+                    codegen_error!("singlepass f64_min unreachable");
                 }
             };
             let src2 = match loc_b {
                 Location::SIMD(x) => x,
                 Location::GPR(_) | Location::Memory(_, _) => {
-                    self.move_location(Size::S64, loc_b, Location::SIMD(tmp2));
+                    self.move_location(Size::S64, loc_b, Location::SIMD(tmp2))?;
                     tmp2
                 }
                 Location::Imm32(_) => {
-                    self.move_location(Size::S32, loc_b, Location::GPR(tmpg1));
-                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp2));
+                    self.move_location(Size::S32, loc_b, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp2))?;
                     tmp2
                 }
                 Location::Imm64(_) => {
-                    self.move_location(Size::S64, loc_b, Location::GPR(tmpg1));
-                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp2));
+                    self.move_location(Size::S64, loc_b, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp2))?;
                     tmp2
                 }
                 _ => {
-                    unreachable!();
+                    codegen_error!("singlepass f64_min unreachable");
                 }
             };
 
@@ -6273,8 +7194,8 @@ impl Machine for MachineX86_64 {
             let tmp_xmm2 = XMM::XMM9;
             let tmp_xmm3 = XMM::XMM10;
 
-            self.move_location(Size::S64, Location::SIMD(src1), Location::GPR(tmpg1));
-            self.move_location(Size::S64, Location::SIMD(src2), Location::GPR(tmpg2));
+            self.move_location(Size::S64, Location::SIMD(src1), Location::GPR(tmpg1))?;
+            self.move_location(Size::S64, Location::SIMD(src2), Location::GPR(tmpg2))?;
             self.assembler
                 .emit_cmp(Size::S64, Location::GPR(tmpg2), Location::GPR(tmpg1));
             self.assembler
@@ -6291,8 +7212,8 @@ impl Machine for MachineX86_64 {
                 Size::S64,
                 Location::Imm64(0x8000_0000_0000_0000), // Negative zero
                 Location::GPR(tmpg1),
-            );
-            self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp_xmm2));
+            )?;
+            self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp_xmm2))?;
             self.emit_label(label2);
             self.assembler
                 .emit_vcmpeqsd(src1, XMMOrMemory::XMM(src2), tmp_xmm3);
@@ -6305,8 +7226,8 @@ impl Machine for MachineX86_64 {
                 Size::S64,
                 Location::Imm64(0x7FF8_0000_0000_0000), // Canonical NaN
                 Location::GPR(tmpg1),
-            );
-            self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(src2));
+            )?;
+            self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(src2))?;
             self.assembler
                 .emit_vblendvpd(src1, XMMOrMemory::XMM(src2), tmp_xmm1, src1);
             match ret {
@@ -6315,10 +7236,10 @@ impl Machine for MachineX86_64 {
                         .emit_vmovaps(XMMOrMemory::XMM(src1), XMMOrMemory::XMM(x));
                 }
                 Location::Memory(_, _) | Location::GPR(_) => {
-                    self.move_location(Size::S64, Location::SIMD(src1), ret);
+                    self.move_location(Size::S64, Location::SIMD(src1), ret)?;
                 }
                 _ => {
-                    unreachable!();
+                    codegen_error!("singlepass f64_min unreachable");
                 }
             }
 
@@ -6327,54 +7248,68 @@ impl Machine for MachineX86_64 {
             self.release_simd(tmp2);
             self.release_simd(tmp1);
         }
+        Ok(())
     }
-    fn f64_max(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f64_max(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         if !self.arch_supports_canonicalize_nan() {
-            self.emit_relaxed_avx(AssemblerX64::emit_vmaxsd, loc_a, loc_b, ret);
+            self.emit_relaxed_avx(AssemblerX64::emit_vmaxsd, loc_a, loc_b, ret)?;
         } else {
-            let tmp1 = self.acquire_temp_simd().unwrap();
-            let tmp2 = self.acquire_temp_simd().unwrap();
-            let tmpg1 = self.acquire_temp_gpr().unwrap();
-            let tmpg2 = self.acquire_temp_gpr().unwrap();
+            let tmp1 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmp2 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmpg1 = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmpg2 = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
 
             let src1 = match loc_a {
                 Location::SIMD(x) => x,
                 Location::GPR(_) | Location::Memory(_, _) => {
-                    self.move_location(Size::S64, loc_a, Location::SIMD(tmp1));
+                    self.move_location(Size::S64, loc_a, Location::SIMD(tmp1))?;
                     tmp1
                 }
                 Location::Imm32(_) => {
-                    self.move_location(Size::S32, loc_a, Location::GPR(tmpg1));
-                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp1));
+                    self.move_location(Size::S32, loc_a, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp1))?;
                     tmp1
                 }
                 Location::Imm64(_) => {
-                    self.move_location(Size::S64, loc_a, Location::GPR(tmpg1));
-                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp1));
+                    self.move_location(Size::S64, loc_a, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp1))?;
                     tmp1
                 }
                 _ => {
-                    unreachable!();
+                    codegen_error!("singlepass f64_max unreachable");
                 }
             };
             let src2 = match loc_b {
                 Location::SIMD(x) => x,
                 Location::GPR(_) | Location::Memory(_, _) => {
-                    self.move_location(Size::S64, loc_b, Location::SIMD(tmp2));
+                    self.move_location(Size::S64, loc_b, Location::SIMD(tmp2))?;
                     tmp2
                 }
                 Location::Imm32(_) => {
-                    self.move_location(Size::S32, loc_b, Location::GPR(tmpg1));
-                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp2));
+                    self.move_location(Size::S32, loc_b, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp2))?;
                     tmp2
                 }
                 Location::Imm64(_) => {
-                    self.move_location(Size::S64, loc_b, Location::GPR(tmpg1));
-                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp2));
+                    self.move_location(Size::S64, loc_b, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp2))?;
                     tmp2
                 }
                 _ => {
-                    unreachable!();
+                    codegen_error!("singlepass f64_max unreachable");
                 }
             };
 
@@ -6382,8 +7317,8 @@ impl Machine for MachineX86_64 {
             let tmp_xmm2 = XMM::XMM9;
             let tmp_xmm3 = XMM::XMM10;
 
-            self.move_location(Size::S64, Location::SIMD(src1), Location::GPR(tmpg1));
-            self.move_location(Size::S64, Location::SIMD(src2), Location::GPR(tmpg2));
+            self.move_location(Size::S64, Location::SIMD(src1), Location::GPR(tmpg1))?;
+            self.move_location(Size::S64, Location::SIMD(src2), Location::GPR(tmpg2))?;
             self.assembler
                 .emit_cmp(Size::S64, Location::GPR(tmpg2), Location::GPR(tmpg1));
             self.assembler
@@ -6409,8 +7344,8 @@ impl Machine for MachineX86_64 {
                 Size::S64,
                 Location::Imm64(0x7FF8_0000_0000_0000), // Canonical NaN
                 Location::GPR(tmpg1),
-            );
-            self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(src2));
+            )?;
+            self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(src2))?;
             self.assembler
                 .emit_vblendvpd(src1, XMMOrMemory::XMM(src2), tmp_xmm1, src1);
             match ret {
@@ -6419,10 +7354,10 @@ impl Machine for MachineX86_64 {
                         .emit_vmovapd(XMMOrMemory::XMM(src1), XMMOrMemory::XMM(x));
                 }
                 Location::Memory(_, _) | Location::GPR(_) => {
-                    self.move_location(Size::S64, Location::SIMD(src1), ret);
+                    self.move_location(Size::S64, Location::SIMD(src1), ret)?;
                 }
                 _ => {
-                    unreachable!();
+                    codegen_error!("singlepass f64_max unreachable");
                 }
             }
 
@@ -6431,44 +7366,73 @@ impl Machine for MachineX86_64 {
             self.release_simd(tmp2);
             self.release_simd(tmp1);
         }
+        Ok(())
     }
-    fn f64_add(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vaddsd, loc_a, loc_b, ret);
+    fn f64_add(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vaddsd, loc_a, loc_b, ret)
     }
-    fn f64_sub(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vsubsd, loc_a, loc_b, ret);
+    fn f64_sub(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vsubsd, loc_a, loc_b, ret)
     }
-    fn f64_mul(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vmulsd, loc_a, loc_b, ret);
+    fn f64_mul(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vmulsd, loc_a, loc_b, ret)
     }
-    fn f64_div(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vdivsd, loc_a, loc_b, ret);
+    fn f64_div(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vdivsd, loc_a, loc_b, ret)
     }
-    fn f32_neg(&mut self, loc: Location, ret: Location) {
+    fn f32_neg(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
         if self.assembler.arch_has_fneg() {
-            let tmp = self.acquire_temp_simd().unwrap();
-            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp));
+            let tmp = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            self.emit_relaxed_mov(Size::S32, loc, Location::SIMD(tmp))?;
             self.assembler.arch_emit_f32_neg(tmp, tmp);
-            self.emit_relaxed_mov(Size::S32, Location::SIMD(tmp), ret);
+            self.emit_relaxed_mov(Size::S32, Location::SIMD(tmp), ret)?;
             self.release_simd(tmp);
         } else {
-            let tmp = self.acquire_temp_gpr().unwrap();
-            self.move_location(Size::S32, loc, Location::GPR(tmp));
+            let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            self.move_location(Size::S32, loc, Location::GPR(tmp))?;
             self.assembler.emit_btc_gpr_imm8_32(31, tmp);
-            self.move_location(Size::S32, Location::GPR(tmp), ret);
+            self.move_location(Size::S32, Location::GPR(tmp), ret)?;
             self.release_gpr(tmp);
         }
+        Ok(())
     }
-    fn f32_abs(&mut self, loc: Location, ret: Location) {
-        let tmp = self.acquire_temp_gpr().unwrap();
-        self.move_location(Size::S32, loc, Location::GPR(tmp));
+    fn f32_abs(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+            message: "singlepass cannot acquire temp gpr".to_owned(),
+        })?;
+        self.move_location(Size::S32, loc, Location::GPR(tmp))?;
         self.assembler.emit_and(
             Size::S32,
             Location::Imm32(0x7fffffffu32),
             Location::GPR(tmp),
         );
-        self.move_location(Size::S32, Location::GPR(tmp), ret);
+        self.move_location(Size::S32, Location::GPR(tmp), ret)?;
         self.release_gpr(tmp);
+        Ok(())
     }
     fn emit_i32_copysign(&mut self, tmp1: GPR, tmp2: GPR) {
         self.assembler.emit_and(
@@ -6484,92 +7448,141 @@ impl Machine for MachineX86_64 {
         self.assembler
             .emit_or(Size::S32, Location::GPR(tmp2), Location::GPR(tmp1));
     }
-    fn f32_sqrt(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vsqrtss, loc, loc, ret);
+    fn f32_sqrt(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vsqrtss, loc, loc, ret)
     }
-    fn f32_trunc(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vroundss_trunc, loc, loc, ret);
+    fn f32_trunc(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vroundss_trunc, loc, loc, ret)
     }
-    fn f32_ceil(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vroundss_ceil, loc, loc, ret);
+    fn f32_ceil(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vroundss_ceil, loc, loc, ret)
     }
-    fn f32_floor(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vroundss_floor, loc, loc, ret);
+    fn f32_floor(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vroundss_floor, loc, loc, ret)
     }
-    fn f32_nearest(&mut self, loc: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vroundss_nearest, loc, loc, ret);
+    fn f32_nearest(&mut self, loc: Location, ret: Location) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vroundss_nearest, loc, loc, ret)
     }
-    fn f32_cmp_ge(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vcmpgess, loc_a, loc_b, ret);
+    fn f32_cmp_ge(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vcmpgess, loc_a, loc_b, ret)?;
         self.assembler.emit_and(Size::S32, Location::Imm32(1), ret);
+        Ok(())
     }
-    fn f32_cmp_gt(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vcmpgtss, loc_a, loc_b, ret);
+    fn f32_cmp_gt(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vcmpgtss, loc_a, loc_b, ret)?;
         self.assembler.emit_and(Size::S32, Location::Imm32(1), ret);
+        Ok(())
     }
-    fn f32_cmp_le(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vcmpless, loc_a, loc_b, ret);
+    fn f32_cmp_le(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vcmpless, loc_a, loc_b, ret)?;
         self.assembler.emit_and(Size::S32, Location::Imm32(1), ret);
+        Ok(())
     }
-    fn f32_cmp_lt(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vcmpltss, loc_a, loc_b, ret);
+    fn f32_cmp_lt(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vcmpltss, loc_a, loc_b, ret)?;
         self.assembler.emit_and(Size::S32, Location::Imm32(1), ret);
+        Ok(())
     }
-    fn f32_cmp_ne(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vcmpneqss, loc_a, loc_b, ret);
+    fn f32_cmp_ne(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vcmpneqss, loc_a, loc_b, ret)?;
         self.assembler.emit_and(Size::S32, Location::Imm32(1), ret);
+        Ok(())
     }
-    fn f32_cmp_eq(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vcmpeqss, loc_a, loc_b, ret);
+    fn f32_cmp_eq(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vcmpeqss, loc_a, loc_b, ret)?;
         self.assembler.emit_and(Size::S32, Location::Imm32(1), ret);
+        Ok(())
     }
-    fn f32_min(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f32_min(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         if !self.arch_supports_canonicalize_nan() {
-            self.emit_relaxed_avx(AssemblerX64::emit_vminss, loc_a, loc_b, ret);
+            self.emit_relaxed_avx(AssemblerX64::emit_vminss, loc_a, loc_b, ret)?;
         } else {
-            let tmp1 = self.acquire_temp_simd().unwrap();
-            let tmp2 = self.acquire_temp_simd().unwrap();
-            let tmpg1 = self.acquire_temp_gpr().unwrap();
-            let tmpg2 = self.acquire_temp_gpr().unwrap();
+            let tmp1 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmp2 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmpg1 = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmpg2 = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
 
             let src1 = match loc_a {
                 Location::SIMD(x) => x,
                 Location::GPR(_) | Location::Memory(_, _) => {
-                    self.move_location(Size::S64, loc_a, Location::SIMD(tmp1));
+                    self.move_location(Size::S64, loc_a, Location::SIMD(tmp1))?;
                     tmp1
                 }
                 Location::Imm32(_) => {
-                    self.move_location(Size::S32, loc_a, Location::GPR(tmpg1));
-                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp1));
+                    self.move_location(Size::S32, loc_a, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp1))?;
                     tmp1
                 }
                 Location::Imm64(_) => {
-                    self.move_location(Size::S64, loc_a, Location::GPR(tmpg1));
-                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp1));
+                    self.move_location(Size::S64, loc_a, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp1))?;
                     tmp1
                 }
                 _ => {
-                    unreachable!();
+                    codegen_error!("singlepass f32_min unreachable");
                 }
             };
             let src2 = match loc_b {
                 Location::SIMD(x) => x,
                 Location::GPR(_) | Location::Memory(_, _) => {
-                    self.move_location(Size::S64, loc_b, Location::SIMD(tmp2));
+                    self.move_location(Size::S64, loc_b, Location::SIMD(tmp2))?;
                     tmp2
                 }
                 Location::Imm32(_) => {
-                    self.move_location(Size::S32, loc_b, Location::GPR(tmpg1));
-                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp2));
+                    self.move_location(Size::S32, loc_b, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp2))?;
                     tmp2
                 }
                 Location::Imm64(_) => {
-                    self.move_location(Size::S64, loc_b, Location::GPR(tmpg1));
-                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp2));
+                    self.move_location(Size::S64, loc_b, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp2))?;
                     tmp2
                 }
                 _ => {
-                    unreachable!();
+                    codegen_error!("singlepass f32_min unreachable");
                 }
             };
 
@@ -6577,8 +7590,8 @@ impl Machine for MachineX86_64 {
             let tmp_xmm2 = XMM::XMM9;
             let tmp_xmm3 = XMM::XMM10;
 
-            self.move_location(Size::S32, Location::SIMD(src1), Location::GPR(tmpg1));
-            self.move_location(Size::S32, Location::SIMD(src2), Location::GPR(tmpg2));
+            self.move_location(Size::S32, Location::SIMD(src1), Location::GPR(tmpg1))?;
+            self.move_location(Size::S32, Location::SIMD(src2), Location::GPR(tmpg2))?;
             self.assembler
                 .emit_cmp(Size::S32, Location::GPR(tmpg2), Location::GPR(tmpg1));
             self.assembler
@@ -6595,8 +7608,8 @@ impl Machine for MachineX86_64 {
                 Size::S64,
                 Location::Imm32(0x8000_0000), // Negative zero
                 Location::GPR(tmpg1),
-            );
-            self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp_xmm2));
+            )?;
+            self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp_xmm2))?;
             self.emit_label(label2);
             self.assembler
                 .emit_vcmpeqss(src1, XMMOrMemory::XMM(src2), tmp_xmm3);
@@ -6609,8 +7622,8 @@ impl Machine for MachineX86_64 {
                 Size::S64,
                 Location::Imm32(0x7FC0_0000), // Canonical NaN
                 Location::GPR(tmpg1),
-            );
-            self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(src2));
+            )?;
+            self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(src2))?;
             self.assembler
                 .emit_vblendvps(src1, XMMOrMemory::XMM(src2), tmp_xmm1, src1);
             match ret {
@@ -6619,10 +7632,10 @@ impl Machine for MachineX86_64 {
                         .emit_vmovaps(XMMOrMemory::XMM(src1), XMMOrMemory::XMM(x));
                 }
                 Location::Memory(_, _) | Location::GPR(_) => {
-                    self.move_location(Size::S64, Location::SIMD(src1), ret);
+                    self.move_location(Size::S64, Location::SIMD(src1), ret)?;
                 }
                 _ => {
-                    unreachable!();
+                    codegen_error!("singlepass f32_min unreachable");
                 }
             }
 
@@ -6631,54 +7644,68 @@ impl Machine for MachineX86_64 {
             self.release_simd(tmp2);
             self.release_simd(tmp1);
         }
+        Ok(())
     }
-    fn f32_max(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
+    fn f32_max(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
         if !self.arch_supports_canonicalize_nan() {
-            self.emit_relaxed_avx(AssemblerX64::emit_vmaxss, loc_a, loc_b, ret);
+            self.emit_relaxed_avx(AssemblerX64::emit_vmaxss, loc_a, loc_b, ret)?;
         } else {
-            let tmp1 = self.acquire_temp_simd().unwrap();
-            let tmp2 = self.acquire_temp_simd().unwrap();
-            let tmpg1 = self.acquire_temp_gpr().unwrap();
-            let tmpg2 = self.acquire_temp_gpr().unwrap();
+            let tmp1 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmp2 = self.acquire_temp_simd().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp simd".to_owned(),
+            })?;
+            let tmpg1 = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
+            let tmpg2 = self.acquire_temp_gpr().ok_or_else(|| CodegenError {
+                message: "singlepass cannot acquire temp gpr".to_owned(),
+            })?;
 
             let src1 = match loc_a {
                 Location::SIMD(x) => x,
                 Location::GPR(_) | Location::Memory(_, _) => {
-                    self.move_location(Size::S64, loc_a, Location::SIMD(tmp1));
+                    self.move_location(Size::S64, loc_a, Location::SIMD(tmp1))?;
                     tmp1
                 }
                 Location::Imm32(_) => {
-                    self.move_location(Size::S32, loc_a, Location::GPR(tmpg1));
-                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp1));
+                    self.move_location(Size::S32, loc_a, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp1))?;
                     tmp1
                 }
                 Location::Imm64(_) => {
-                    self.move_location(Size::S64, loc_a, Location::GPR(tmpg1));
-                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp1));
+                    self.move_location(Size::S64, loc_a, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp1))?;
                     tmp1
                 }
                 _ => {
-                    unreachable!();
+                    codegen_error!("singlepass f32_max unreachable");
                 }
             };
             let src2 = match loc_b {
                 Location::SIMD(x) => x,
                 Location::GPR(_) | Location::Memory(_, _) => {
-                    self.move_location(Size::S64, loc_b, Location::SIMD(tmp2));
+                    self.move_location(Size::S64, loc_b, Location::SIMD(tmp2))?;
                     tmp2
                 }
                 Location::Imm32(_) => {
-                    self.move_location(Size::S32, loc_b, Location::GPR(tmpg1));
-                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp2));
+                    self.move_location(Size::S32, loc_b, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S32, Location::GPR(tmpg1), Location::SIMD(tmp2))?;
                     tmp2
                 }
                 Location::Imm64(_) => {
-                    self.move_location(Size::S64, loc_b, Location::GPR(tmpg1));
-                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp2));
+                    self.move_location(Size::S64, loc_b, Location::GPR(tmpg1))?;
+                    self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp2))?;
                     tmp2
                 }
                 _ => {
-                    unreachable!();
+                    codegen_error!("singlepass f32_max unreachable");
                 }
             };
 
@@ -6686,8 +7713,8 @@ impl Machine for MachineX86_64 {
             let tmp_xmm2 = XMM::XMM9;
             let tmp_xmm3 = XMM::XMM10;
 
-            self.move_location(Size::S32, Location::SIMD(src1), Location::GPR(tmpg1));
-            self.move_location(Size::S32, Location::SIMD(src2), Location::GPR(tmpg2));
+            self.move_location(Size::S32, Location::SIMD(src1), Location::GPR(tmpg1))?;
+            self.move_location(Size::S32, Location::SIMD(src2), Location::GPR(tmpg2))?;
             self.assembler
                 .emit_cmp(Size::S32, Location::GPR(tmpg2), Location::GPR(tmpg1));
             self.assembler
@@ -6713,8 +7740,8 @@ impl Machine for MachineX86_64 {
                 Size::S64,
                 Location::Imm32(0x7FC0_0000), // Canonical NaN
                 Location::GPR(tmpg1),
-            );
-            self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(src2));
+            )?;
+            self.move_location(Size::S64, Location::GPR(tmpg1), Location::SIMD(src2))?;
             self.assembler
                 .emit_vblendvps(src1, XMMOrMemory::XMM(src2), tmp_xmm1, src1);
             match ret {
@@ -6723,10 +7750,10 @@ impl Machine for MachineX86_64 {
                         .emit_vmovaps(XMMOrMemory::XMM(src1), XMMOrMemory::XMM(x));
                 }
                 Location::Memory(_, _) | Location::GPR(_) => {
-                    self.move_location(Size::S64, Location::SIMD(src1), ret);
+                    self.move_location(Size::S64, Location::SIMD(src1), ret)?;
                 }
                 _ => {
-                    unreachable!();
+                    codegen_error!("singlepass f32_max unreachable");
                 }
             }
 
@@ -6735,18 +7762,39 @@ impl Machine for MachineX86_64 {
             self.release_simd(tmp2);
             self.release_simd(tmp1);
         }
+        Ok(())
     }
-    fn f32_add(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vaddss, loc_a, loc_b, ret);
+    fn f32_add(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vaddss, loc_a, loc_b, ret)
     }
-    fn f32_sub(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vsubss, loc_a, loc_b, ret);
+    fn f32_sub(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vsubss, loc_a, loc_b, ret)
     }
-    fn f32_mul(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vmulss, loc_a, loc_b, ret);
+    fn f32_mul(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vmulss, loc_a, loc_b, ret)
     }
-    fn f32_div(&mut self, loc_a: Location, loc_b: Location, ret: Location) {
-        self.emit_relaxed_avx(AssemblerX64::emit_vdivss, loc_a, loc_b, ret);
+    fn f32_div(
+        &mut self,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CodegenError> {
+        self.emit_relaxed_avx(AssemblerX64::emit_vdivss, loc_a, loc_b, ret)
     }
 
     fn gen_std_trampoline(
@@ -7208,3 +8256,76 @@ impl Machine for MachineX86_64 {
         None
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn division_trap_codes() {
+        let mut machine = MachineX86_64::new(None);
+        let integer_division_by_zero = machine.get_label();
+        let integer_overflow = machine.get_label();
+        let (a, b, ret) = (
+            Location::GPR(GPR::RSI),
+            Location::GPR(GPR::RCX),
+            Location::GPR(GPR::RDI),
+        );
+
+        let offsets = [
+            (
+                machine
+                    .emit_binop_udiv32(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerDivisionByZero,
+            ),
+            (
+                machine
+                    .emit_binop_sdiv32(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerOverflow,
+            ),
+            (
+                machine
+                    .emit_binop_urem32(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerDivisionByZero,
+            ),
+            (
+                machine
+                    .emit_binop_srem32(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerDivisionByZero,
+            ),
+            (
+                machine
+                    .emit_binop_udiv64(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerDivisionByZero,
+            ),
+            (
+                machine
+                    .emit_binop_sdiv64(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerOverflow,
+            ),
+            (
+                machine
+                    .emit_binop_urem64(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerDivisionByZero,
+            ),
+            (
+                machine
+                    .emit_binop_srem64(a, b, ret, integer_division_by_zero, integer_overflow)
+                    .unwrap(),
+                TrapCode::IntegerDivisionByZero,
+            ),
+        ];
+
+        for (offset, code) in offsets.iter() {
+            assert_eq!(machine.trap_table.offset_to_code.get(offset), Some(code));
+        }
+        assert_eq!(machine.trap_table.offset_to_code.len(), offsets.len());
+    }
+}
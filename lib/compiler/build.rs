@@ -0,0 +1,18 @@
+//! Wasmer compiler build script.
+//!
+//! Records the version of `rustc` building the crate, which compiler
+//! plugins must share with their host.
+
+use std::env;
+use std::process::Command;
+use std::str;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = match Command::new(rustc).arg("--version").output() {
+        Ok(output) => str::from_utf8(&output.stdout).unwrap().trim().to_string(),
+        Err(_) => "rustc unknown".to_string(),
+    };
+    println!("cargo:rustc-env=WASMER_COMPILER_RUSTC_VERSION={}", version);
+}
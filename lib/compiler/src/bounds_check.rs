@@ -0,0 +1,51 @@
+//! Elimination of the bounds checks of memory accesses at constant
+//! addresses.
+//!
+//! Toolchains based on LLVM lay out the globals of a program, like its
+//! stack pointer or static data, at fixed addresses at the start of the
+//! linear memory, and access them with an `i32.const` address. Since a
+//! memory is never smaller than its declared minimum and never shrinks,
+//! such an access below the minimum can't be out of bounds, and compilers
+//! can leave its bounds check out.
+
+/// Whether an access of `access_size` bytes at the constant `address`,
+/// plus the static `offset` of the instruction, is always in bounds of a
+/// memory whose minimum size is `minimum_size` bytes.
+///
+/// ```
+/// # use wasmer_compiler::is_constant_access_in_bounds;
+/// // In a memory of at least 1 page.
+/// assert!(is_constant_access_in_bounds(65536, 1024, 8, 4));
+/// assert!(is_constant_access_in_bounds(65536, 65532, 0, 4));
+/// assert!(!is_constant_access_in_bounds(65536, 65532, 1, 4));
+/// ```
+pub fn is_constant_access_in_bounds(
+    minimum_size: u64,
+    address: u32,
+    offset: u64,
+    access_size: u32,
+) -> bool {
+    u64::from(address)
+        .checked_add(offset)
+        .and_then(|start| start.checked_add(access_size.into()))
+        .map_or(false, |end| end <= minimum_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accesses_past_the_minimum_size_are_checked() {
+        assert!(is_constant_access_in_bounds(65536, 0, 0, 8));
+        assert!(is_constant_access_in_bounds(65536, 0, 65528, 8));
+        assert!(!is_constant_access_in_bounds(65536, 0, 65529, 8));
+        assert!(!is_constant_access_in_bounds(0, 0, 0, 1));
+        assert!(!is_constant_access_in_bounds(
+            u64::MAX,
+            u32::MAX,
+            u64::MAX,
+            1
+        ));
+    }
+}
@@ -0,0 +1,102 @@
+//! Cancellation of the compilation of a module.
+
+use crate::error::CompileError;
+use crate::lib::std::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+/// A handle to give up on the compilation of a module, either on request
+/// or once a deadline has passed.
+///
+/// Compilers check the token between functions, and Singlepass also
+/// within large functions, so a cancelled compilation stops shortly
+/// after with [`CompileError::Cancelled`]. Clones of a token share its
+/// state: cancelling one cancels them all.
+///
+/// ```
+/// # use wasmer_compiler::CancellationToken;
+/// # use std::time::Duration;
+/// let token = CancellationToken::new().with_timeout(Duration::from_secs(5));
+/// let handle = token.clone();
+/// assert!(!token.is_cancelled());
+/// handle.cancel();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    #[cfg(feature = "std")]
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Creates a token that is only cancelled by [`CancellationToken::cancel`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also cancels the compilation once `deadline` has passed.
+    #[cfg(feature = "std")]
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Also cancels the compilation once `timeout` has elapsed from now.
+    #[cfg(feature = "std")]
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// Cancels the compilations using this token or one of its clones.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the token was cancelled or its deadline has passed.
+    pub fn is_cancelled(&self) -> bool {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return true;
+        }
+        #[cfg(feature = "std")]
+        {
+            if let Some(deadline) = self.deadline {
+                return Instant::now() >= deadline;
+            }
+        }
+        false
+    }
+
+    /// Returns [`CompileError::Cancelled`] if the token was cancelled or
+    /// its deadline has passed.
+    pub fn check(&self) -> Result<(), CompileError> {
+        if self.is_cancelled() {
+            Err(CompileError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_is_shared_by_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(token.check().is_ok());
+        clone.cancel();
+        assert!(matches!(token.check(), Err(CompileError::Cancelled)));
+    }
+
+    #[test]
+    fn deadline() {
+        let token = CancellationToken::new().with_timeout(Duration::from_secs(3600));
+        assert!(!token.is_cancelled());
+        let token = CancellationToken::new().with_deadline(Instant::now());
+        assert!(token.is_cancelled());
+    }
+}
@@ -1,20 +1,58 @@
 //! This module mainly outputs the `Compiler` trait that custom
 //! compilers will need to implement.
 
+use crate::cancellation::CancellationToken;
 use crate::error::CompileError;
 use crate::function::Compilation;
 use crate::lib::std::boxed::Box;
 use crate::lib::std::sync::Arc;
+use crate::limits::ValidationLimits;
 use crate::module::CompileModuleInfo;
 use crate::target::Target;
 use crate::translator::ModuleMiddleware;
 use crate::FunctionBodyData;
 use crate::ModuleTranslationState;
 use crate::SectionIndex;
+#[cfg(feature = "std")]
+use crate::WasmError;
+use core::ops::Range;
 use loupe::MemoryUsage;
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{Features, FunctionIndex, LocalFunctionIndex, SignatureIndex};
-use wasmparser::{Validator, WasmFeatures};
+#[cfg(feature = "std")]
+use wasmparser::{Chunk, ValidPayload};
+use wasmparser::{Parser, Payload, Validator, WasmFeatures};
+
+/// How many bytes are requested from the reader at a time when
+/// validating a module in a streaming fashion.
+#[cfg(feature = "std")]
+const STREAMING_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Converts the Wasmer `Features` into the features understood by `wasmparser`.
+///
+/// `wasmparser` can't validate components yet, so the component model
+/// proposal is never reported as supported by a compiler and doesn't
+/// need to be forwarded here.
+fn wasm_features(features: &Features) -> WasmFeatures {
+    WasmFeatures {
+        bulk_memory: features.bulk_memory,
+        threads: features.threads,
+        reference_types: features.reference_types,
+        multi_value: features.multi_value,
+        simd: features.simd,
+        tail_call: features.tail_call,
+        module_linking: features.module_linking,
+        multi_memory: features.multi_memory,
+        memory64: features.memory64,
+        exceptions: features.exceptions,
+        deterministic_only: false,
+        extended_const: features.extended_const,
+        relaxed_simd: features.relaxed_simd,
+        mutable_global: true,
+        saturating_float_to_int: true,
+        sign_extension: true,
+    }
+}
 
 /// The compiler configuration options.
 pub trait CompilerConfig {
@@ -56,6 +94,15 @@ pub trait CompilerConfig {
         // in case they create an IR that they can verify.
     }
 
+    /// Sets the alignment in bytes of loop headers, a power of two.
+    ///
+    /// Aligning loops can speed up their execution on some CPUs, at the
+    /// cost of padding the code.
+    fn loop_alignment(&mut self, _alignment: usize) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they align the loops they emit.
+    }
+
     /// Gets the custom compiler config
     fn compiler(self: Box<Self>) -> Box<dyn Compiler>;
 
@@ -64,6 +111,18 @@ pub trait CompilerConfig {
         Features::default()
     }
 
+    /// Gets the features this compiler is able to compile in the given
+    /// target.
+    ///
+    /// The features of an engine are restricted to these, so that every
+    /// module passing validation can also be compiled. By default, the
+    /// proposals handled by the shared translator are supported.
+    fn supported_features_for_target(&self, _target: &Target) -> Features {
+        let mut features = Features::default();
+        features.threads(true);
+        features
+    }
+
     /// Pushes a middleware onto the back of the middleware chain.
     fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>);
 }
@@ -78,41 +137,113 @@ where
 }
 
 /// An implementation of a Compiler from parsed WebAssembly module to Compiled native code.
-pub trait Compiler: Send + MemoryUsage {
+pub trait Compiler: Send + Sync + MemoryUsage {
     /// Validates a module.
     ///
     /// It returns the a succesful Result in case is valid, `CompileError` in case is not.
     fn validate_module<'data>(
         &self,
         features: &Features,
+        limits: &ValidationLimits,
         data: &'data [u8],
     ) -> Result<(), CompileError> {
         let mut validator = Validator::new();
-        let wasm_features = WasmFeatures {
-            bulk_memory: features.bulk_memory,
-            threads: features.threads,
-            reference_types: features.reference_types,
-            multi_value: features.multi_value,
-            simd: features.simd,
-            tail_call: features.tail_call,
-            module_linking: features.module_linking,
-            multi_memory: features.multi_memory,
-            memory64: features.memory64,
-            exceptions: features.exceptions,
-            deterministic_only: false,
-            extended_const: features.extended_const,
-            relaxed_simd: features.relaxed_simd,
-            mutable_global: true,
-            saturating_float_to_int: true,
-            sign_extension: true,
-        };
-        validator.wasm_features(wasm_features);
+        validator.wasm_features(wasm_features(features));
         validator
             .validate_all(data)
             .map_err(|e| CompileError::Validate(format!("{}", e)))?;
+        if !limits.is_unlimited() {
+            check_limits(limits, Parser::new(0), data, 0..data.len())?;
+        }
         Ok(())
     }
 
+    /// Validates a module while it is being read from `reader`.
+    ///
+    /// Sections and function bodies are validated as soon as they are
+    /// fully received, so an invalid module is rejected without waiting
+    /// for the rest of it. On success the complete module bytes are
+    /// returned so they can be compiled without reading them again.
+    #[cfg(feature = "std")]
+    fn validate_module_streaming(
+        &self,
+        features: &Features,
+        limits: &ValidationLimits,
+        reader: &mut dyn std::io::Read,
+    ) -> Result<Vec<u8>, CompileError> {
+        let mut validator = Validator::new();
+        validator.wasm_features(wasm_features(features));
+        let validate_error =
+            |e: wasmparser::BinaryReaderError| CompileError::Validate(format!("{}", e));
+
+        let mut data = Vec::new();
+        let mut consumed = 0;
+        let mut eof = false;
+        let mut parser = Parser::new(0);
+        let mut parents = Vec::new();
+        loop {
+            let parsed = match parser
+                .parse(&data[consumed..], eof)
+                .map_err(validate_error)?
+            {
+                Chunk::NeedMoreData(_) => None,
+                Chunk::Parsed {
+                    consumed: size,
+                    payload,
+                } => {
+                    let is_end = matches!(payload, Payload::End);
+                    match validator.payload(&payload).map_err(validate_error)? {
+                        ValidPayload::Ok => {}
+                        ValidPayload::Submodule(submodule) => {
+                            parents.push(std::mem::replace(&mut parser, submodule));
+                        }
+                        ValidPayload::Func(mut func_validator, body) => {
+                            func_validator.validate(&body).map_err(validate_error)?;
+                            limits.check_function(&body)?;
+                        }
+                    }
+                    // The end of a nested module resumes parsing its parent.
+                    let finished = is_end && {
+                        match parents.pop() {
+                            Some(parent) => {
+                                parser = parent;
+                                false
+                            }
+                            None => true,
+                        }
+                    };
+                    Some((size, finished))
+                }
+            };
+            match parsed {
+                Some((size, finished)) => {
+                    consumed += size;
+                    if finished {
+                        return Ok(data);
+                    }
+                }
+                None => {
+                    let len = data.len();
+                    data.resize(len + STREAMING_CHUNK_SIZE, 0);
+                    let read = loop {
+                        match reader.read(&mut data[len..]) {
+                            Ok(read) => break read,
+                            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                            Err(e) => {
+                                return Err(CompileError::Wasm(WasmError::Generic(format!(
+                                    "Error when reading the module: {}",
+                                    e
+                                ))))
+                            }
+                        }
+                    };
+                    data.truncate(len + read);
+                    eof = read == 0;
+                }
+            }
+        }
+    }
+
     /// Compiles a parsed module.
     ///
     /// It returns the [`Compilation`] or a [`CompileError`].
@@ -125,6 +256,28 @@ pub trait Compiler: Send + MemoryUsage {
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
     ) -> Result<Compilation, CompileError>;
 
+    /// Compiles a parsed module, giving up with
+    /// [`CompileError::Cancelled`] once `cancellation` is cancelled.
+    ///
+    /// The default implementation only checks `cancellation` before and
+    /// after compiling the module. Compilers should check it between
+    /// functions instead.
+    fn compile_module_cancellable<'data, 'module>(
+        &self,
+        target: &Target,
+        module: &'module CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        // The list of function bodies
+        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
+        cancellation: &CancellationToken,
+    ) -> Result<Compilation, CompileError> {
+        cancellation.check()?;
+        let compilation =
+            self.compile_module(target, module, module_translation, function_body_inputs)?;
+        cancellation.check()?;
+        Ok(compilation)
+    }
+
     /// Compiles a module into a native object file.
     ///
     /// It returns the bytes as a `&[u8]` or a [`CompileError`].
@@ -146,6 +299,27 @@ pub trait Compiler: Send + MemoryUsage {
     fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>];
 }
 
+/// Checks the functions of the module at `module` in the valid binary
+/// `wasm`, including those of its nested modules, against `limits`.
+fn check_limits(
+    limits: &ValidationLimits,
+    parser: Parser,
+    wasm: &[u8],
+    module: Range<usize>,
+) -> Result<(), CompileError> {
+    for payload in parser.parse_all(&wasm[module]) {
+        match payload.map_err(|e| CompileError::Validate(format!("{}", e)))? {
+            Payload::CodeSectionEntry(body) => limits.check_function(&body)?,
+            // The ranges of nested modules are offsets in the whole binary
+            Payload::ModuleSectionEntry { parser, range } => {
+                check_limits(limits, parser, wasm, range.start..range.end)?
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 /// The kinds of wasmer_types objects that might be found in a native object file.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Symbol {
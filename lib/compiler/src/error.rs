@@ -1,3 +1,4 @@
+use crate::lib::std::fmt;
 use crate::lib::std::string::String;
 #[cfg(feature = "std")]
 use thiserror::Error;
@@ -40,6 +41,18 @@ pub enum CompileError {
     /// Insufficient resources available for execution.
     #[cfg_attr(feature = "std", error("Insufficient resources: {0}"))]
     Resource(String),
+
+    /// The compilation was cancelled through a [`CancellationToken`],
+    /// or its deadline passed.
+    ///
+    /// [`CancellationToken`]: crate::CancellationToken
+    #[cfg_attr(feature = "std", error("The compilation was cancelled"))]
+    Cancelled,
+
+    /// A function of the module exceeds one of the
+    /// [`ValidationLimits`](crate::ValidationLimits) of the engine.
+    #[cfg_attr(feature = "std", error("Validation error: {0}"))]
+    LimitExceeded(LimitExceeded),
 }
 
 impl From<WasmError> for CompileError {
@@ -48,6 +61,50 @@ impl From<WasmError> for CompileError {
     }
 }
 
+/// A structural limit that can be set in
+/// [`ValidationLimits`](crate::ValidationLimits).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationLimit {
+    /// The size in bytes of the body of a function.
+    FunctionBodySize,
+    /// The number of locals of a function.
+    Locals,
+    /// The number of targets of a `br_table` instruction.
+    BrTableTargets,
+    /// The number of nested blocks in a function.
+    NestingDepth,
+}
+
+impl fmt::Display for ValidationLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::FunctionBodySize => "function body size",
+            Self::Locals => "number of locals",
+            Self::BrTableTargets => "number of br_table targets",
+            Self::NestingDepth => "nesting depth",
+        })
+    }
+}
+
+/// A function exceeding one of the
+/// [`ValidationLimits`](crate::ValidationLimits) of the engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[cfg_attr(
+    feature = "std",
+    error("the {limit} of the function at offset {offset} is {value}, over the limit of {max}")
+)]
+pub struct LimitExceeded {
+    /// The limit that was exceeded.
+    pub limit: ValidationLimit,
+    /// The offset of the body of the function in the module.
+    pub offset: usize,
+    /// The value found in the function.
+    pub value: u64,
+    /// The maximum set for the limit.
+    pub max: u32,
+}
+
 /// A error in the middleware.
 #[derive(Debug)]
 #[cfg_attr(feature = "std", derive(Error))]
@@ -6,6 +6,7 @@
 
 use crate::lib::std::vec::Vec;
 use crate::section::{CustomSection, SectionIndex};
+use crate::sourceloc::SourceLoc;
 use crate::trap::TrapInformation;
 use crate::{CompiledFunctionUnwindInfo, FunctionAddressMap, Relocation};
 use loupe::MemoryUsage;
@@ -34,6 +35,103 @@ pub struct CompiledFunctionFrameInfo {
 
     /// The address map.
     pub address_map: FunctionAddressMap,
+
+    /// The breakpoints of a function compiled for single-stepping.
+    ///
+    /// Code offsets of the step points MUST be in ascending order.
+    pub step_points: Vec<StepPoint>,
+
+    /// The nops of a function compiled with patch points, where
+    /// breakpoints can be patched in at runtime.
+    ///
+    /// Code offsets of the patch points MUST be in ascending order.
+    pub patch_points: Vec<PatchPoint>,
+
+    /// The native stack space used by a call to the function, in bytes,
+    /// not counting the functions it calls. Only set by the compilers
+    /// which know the layout of their frames.
+    pub frame_size: Option<u32>,
+}
+
+/// A breakpoint placed after a WebAssembly instruction, when compiling
+/// for single-stepping.
+#[cfg_attr(feature = "enable-serde", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "enable-rkyv",
+    derive(RkyvSerialize, RkyvDeserialize, Archive)
+)]
+#[derive(Debug, Clone, PartialEq, Eq, MemoryUsage)]
+pub struct StepPoint {
+    /// The offset of the breakpoint instruction in the function body.
+    pub code_offset: u32,
+    /// The WebAssembly instruction which has just been executed.
+    pub srcloc: SourceLoc,
+    /// Where the locals of the function are, arguments first.
+    pub locals: Vec<ValueLocation>,
+    /// Where the values of the operand stack are, bottom first.
+    pub stack: Vec<ValueLocation>,
+}
+
+/// A nop the size of a breakpoint instruction, reserved when compiling
+/// with patch points so that a breakpoint can be patched in at runtime to
+/// trace the execution of the function.
+#[cfg_attr(feature = "enable-serde", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "enable-rkyv",
+    derive(RkyvSerialize, RkyvDeserialize, Archive)
+)]
+#[derive(Debug, Clone, PartialEq, Eq, MemoryUsage)]
+pub struct PatchPoint {
+    /// The offset of the nop in the function body.
+    pub code_offset: u32,
+    /// Where the patch point is placed in the function.
+    pub kind: PatchPointKind,
+    /// The WebAssembly instruction at the patch point: the first one of
+    /// the function for its entry, and the `loop` for a loop header.
+    pub srcloc: SourceLoc,
+}
+
+/// Where a [`PatchPoint`] is placed in a function.
+#[cfg_attr(feature = "enable-serde", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "enable-rkyv",
+    derive(RkyvSerialize, RkyvDeserialize, Archive)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, MemoryUsage)]
+pub enum PatchPointKind {
+    /// At the entry of the function, once its frame is set up.
+    FunctionEntry,
+    /// At the start of each iteration of a loop.
+    LoopHeader,
+}
+
+/// Where a WebAssembly value is stored when a step point is reached.
+///
+/// Registers are given by their number in the instruction encoding of
+/// the target, e.g. `3` is `rbx` on x86-64 and `x3` on ARM64.
+#[cfg_attr(feature = "enable-serde", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "enable-rkyv",
+    derive(RkyvSerialize, RkyvDeserialize, Archive)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, MemoryUsage)]
+pub enum ValueLocation {
+    /// In a general purpose register.
+    Register(u16),
+    /// In a floating point or vector register.
+    SimdRegister(u16),
+    /// In memory, at an offset from the value of a general purpose
+    /// register.
+    Memory {
+        /// The register holding the base address.
+        base: u16,
+        /// The offset from the base address.
+        offset: i32,
+    },
+    /// A constant which isn't materialized.
+    Constant(u64),
+    /// The value can't be located.
+    Unknown,
 }
 
 /// The function body.
@@ -52,6 +150,26 @@ pub struct FunctionBody {
     pub unwind_info: Option<CompiledFunctionUnwindInfo>,
 }
 
+/// A borrowed function body, whose bytes may be read in place from a
+/// serialized artifact rather than from a [`FunctionBody`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FunctionBodyRef<'a> {
+    /// The function body bytes.
+    pub body: &'a [u8],
+
+    /// The function unwind info
+    pub unwind_info: Option<&'a CompiledFunctionUnwindInfo>,
+}
+
+impl<'a> From<&'a FunctionBody> for FunctionBodyRef<'a> {
+    fn from(function: &'a FunctionBody) -> Self {
+        Self {
+            body: &function.body,
+            unwind_info: function.unwind_info.as_ref(),
+        }
+    }
+}
+
 /// The result of compiling a WebAssembly function.
 ///
 /// This structure only have the compiled information data
@@ -49,11 +49,16 @@ mod lib {
 }
 
 mod address_map;
+mod bounds_check;
+mod cancellation;
 #[cfg(feature = "translator")]
 mod compiler;
 mod error;
 mod function;
+mod limits;
 mod module;
+#[cfg(feature = "plugin")]
+mod plugin;
 mod relocation;
 mod target;
 mod trap;
@@ -65,16 +70,25 @@ mod section;
 mod sourceloc;
 
 pub use crate::address_map::{FunctionAddressMap, InstructionAddressMap};
+pub use crate::bounds_check::is_constant_access_in_bounds;
+pub use crate::cancellation::CancellationToken;
 #[cfg(feature = "translator")]
 pub use crate::compiler::{Compiler, CompilerConfig, Symbol, SymbolRegistry};
 pub use crate::error::{
-    CompileError, MiddlewareError, ParseCpuFeatureError, WasmError, WasmResult,
+    CompileError, LimitExceeded, MiddlewareError, ParseCpuFeatureError, ValidationLimit, WasmError,
+    WasmResult,
 };
 pub use crate::function::{
     Compilation, CompiledFunction, CompiledFunctionFrameInfo, CustomSections, Dwarf, FunctionBody,
-    Functions,
+    FunctionBodyRef, Functions, PatchPoint, PatchPointKind, StepPoint, ValueLocation,
 };
+pub use crate::limits::ValidationLimits;
 pub use crate::module::CompileModuleInfo;
+#[cfg(feature = "plugin")]
+pub use crate::plugin::{
+    CompilerPlugin, CompilerPluginDeclaration, CompilerPluginError, COMPILER_PLUGIN_ABI_VERSION,
+    RUSTC_VERSION,
+};
 pub use crate::relocation::{Relocation, RelocationKind, RelocationTarget, Relocations};
 pub use crate::section::{CustomSection, CustomSectionProtection, SectionBody, SectionIndex};
 pub use crate::sourceloc::SourceLoc;
@@ -84,9 +98,9 @@ pub use crate::target::{
 };
 #[cfg(feature = "translator")]
 pub use crate::translator::{
-    translate_module, wptype_to_type, FunctionBinaryReader, FunctionBodyData, FunctionMiddleware,
-    MiddlewareBinaryReader, MiddlewareReaderState, ModuleEnvironment, ModuleMiddleware,
-    ModuleMiddlewareChain, ModuleTranslationState,
+    translate_module, wptype_to_type, FunctionAnalysis, FunctionBinaryReader, FunctionBodyData,
+    FunctionMiddleware, MiddlewareBinaryReader, MiddlewareReaderState, ModuleAnalysis,
+    ModuleEnvironment, ModuleMiddleware, ModuleMiddlewareChain, ModuleTranslationState,
 };
 pub use crate::trap::TrapInformation;
 pub use crate::unwind::CompiledFunctionUnwindInfo;
@@ -0,0 +1,136 @@
+//! Structural limits enforced on the functions of a module while it is
+//! validated.
+
+#[cfg(feature = "translator")]
+use crate::error::{CompileError, LimitExceeded, ValidationLimit};
+use loupe::MemoryUsage;
+#[cfg(feature = "translator")]
+use wasmparser::{BinaryReaderError, FunctionBody, Operator};
+
+/// Limits on the shape of the functions of a module, checked during
+/// validation.
+///
+/// Only the limits of the WebAssembly implementation apply by default.
+/// Hosts loading untrusted modules can lower them to reject
+/// pathological functions before spending time compiling them; a module
+/// exceeding one of them fails to validate with
+/// [`CompileError::LimitExceeded`].
+///
+/// ```
+/// # use wasmer_compiler::ValidationLimits;
+/// let mut limits = ValidationLimits::new();
+/// limits.max_locals(1000).max_nesting_depth(100);
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq, MemoryUsage)]
+pub struct ValidationLimits {
+    /// The maximum size in bytes of the body of a function, including
+    /// its local declarations.
+    pub max_function_body_size: Option<u32>,
+    /// The maximum number of locals of a function, not counting its
+    /// parameters.
+    pub max_locals: Option<u32>,
+    /// The maximum number of targets of a `br_table` instruction, not
+    /// counting the default one.
+    pub max_br_table_targets: Option<u32>,
+    /// The maximum number of nested blocks (`block`, `loop`, `if` and
+    /// `try`) in a function.
+    pub max_nesting_depth: Option<u32>,
+}
+
+impl ValidationLimits {
+    /// Creates limits only enforcing those of the WebAssembly
+    /// implementation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum size in bytes of the body of a function.
+    pub fn max_function_body_size(&mut self, max: u32) -> &mut Self {
+        self.max_function_body_size = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of locals of a function.
+    pub fn max_locals(&mut self, max: u32) -> &mut Self {
+        self.max_locals = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of targets of a `br_table` instruction.
+    pub fn max_br_table_targets(&mut self, max: u32) -> &mut Self {
+        self.max_br_table_targets = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of nested blocks in a function.
+    pub fn max_nesting_depth(&mut self, max: u32) -> &mut Self {
+        self.max_nesting_depth = Some(max);
+        self
+    }
+
+    /// Whether no limit is set.
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Checks the body of a function, which must be valid, against the
+    /// limits.
+    #[cfg(feature = "translator")]
+    pub(crate) fn check_function(&self, body: &FunctionBody) -> Result<(), CompileError> {
+        let offset = body.range().start;
+        let check = |limit, max: Option<u32>, value: u64| match max {
+            Some(max) if value > u64::from(max) => {
+                Err(CompileError::LimitExceeded(LimitExceeded {
+                    limit,
+                    offset,
+                    value,
+                    max,
+                }))
+            }
+            _ => Ok(()),
+        };
+        let reader_error = |e: BinaryReaderError| CompileError::Validate(format!("{}", e));
+
+        let size = body.range().end - body.range().start;
+        check(
+            ValidationLimit::FunctionBodySize,
+            self.max_function_body_size,
+            size as u64,
+        )?;
+
+        if self.max_locals.is_some() {
+            let mut reader = body.get_locals_reader().map_err(reader_error)?;
+            let mut locals = 0;
+            for _ in 0..reader.get_count() {
+                let (count, _) = reader.read().map_err(reader_error)?;
+                locals += u64::from(count);
+            }
+            check(ValidationLimit::Locals, self.max_locals, locals)?;
+        }
+
+        if self.max_br_table_targets.is_some() || self.max_nesting_depth.is_some() {
+            let mut reader = body.get_operators_reader().map_err(reader_error)?;
+            let mut depth = 0;
+            while !reader.eof() {
+                match reader.read().map_err(reader_error)? {
+                    Operator::Block { .. }
+                    | Operator::Loop { .. }
+                    | Operator::If { .. }
+                    | Operator::Try { .. } => {
+                        depth += 1;
+                        check(ValidationLimit::NestingDepth, self.max_nesting_depth, depth)?;
+                    }
+                    // The last `end` closes the function itself
+                    Operator::End | Operator::Delegate { .. } => depth = depth.saturating_sub(1),
+                    Operator::BrTable { table } => check(
+                        ValidationLimit::BrTableTargets,
+                        self.max_br_table_targets,
+                        table.len() as u64,
+                    )?,
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
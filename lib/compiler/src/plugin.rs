@@ -0,0 +1,288 @@
+//! Compilers shipped as plugins, dynamic libraries loaded at runtime.
+//!
+//! A plugin is a `cdylib` crate depending on `wasmer-compiler` which
+//! declares its [`CompilerConfig`] with [`declare_compiler_plugin!`]:
+//!
+//! ```ignore
+//! fn create() -> MyCompilerConfig {
+//!     MyCompilerConfig::default()
+//! }
+//!
+//! wasmer_compiler::declare_compiler_plugin!(create);
+//! ```
+//!
+//! The plugin and the host exchange Rust trait objects, so they must be
+//! built with the same version of `wasmer-compiler` and the same `rustc`.
+//! Both are recorded in the declaration and checked by
+//! [`CompilerPlugin::load`] before anything else is used.
+
+use crate::cancellation::CancellationToken;
+use crate::compiler::{Compiler, CompilerConfig, SymbolRegistry};
+use crate::error::CompileError;
+use crate::function::Compilation;
+use crate::limits::ValidationLimits;
+use crate::module::CompileModuleInfo;
+use crate::target::Target;
+use crate::translator::{FunctionBodyData, ModuleMiddleware, ModuleTranslationState};
+use libloading::Library;
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::mem;
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::{Features, LocalFunctionIndex};
+
+/// The version of the contract between the host and the plugins. It's
+/// bumped whenever [`CompilerPluginDeclaration`] changes.
+pub const COMPILER_PLUGIN_ABI_VERSION: u32 = 1;
+
+/// The `rustc` which built this crate.
+pub const RUSTC_VERSION: &str = env!("WASMER_COMPILER_RUSTC_VERSION");
+
+/// The name of the symbol under which a plugin exports its declaration.
+const DECLARATION_SYMBOL: &[u8] = b"wasmer_compiler_plugin_declaration\0";
+
+/// What a compiler plugin exports, see [`declare_compiler_plugin!`].
+#[repr(C)]
+pub struct CompilerPluginDeclaration {
+    /// The [`COMPILER_PLUGIN_ABI_VERSION`] the plugin was built with.
+    /// It's first so it can be checked whatever the rest of the layout.
+    pub abi_version: u32,
+    /// The version of `wasmer-compiler` the plugin was built with.
+    pub wasmer_version: &'static str,
+    /// The `rustc` the plugin was built with.
+    pub rustc_version: &'static str,
+    /// Creates the configuration of the compiler.
+    pub create: fn() -> Box<dyn CompilerConfig>,
+}
+
+/// Declares the compiler of a plugin, from a function returning its
+/// [`CompilerConfig`].
+#[macro_export]
+macro_rules! declare_compiler_plugin {
+    ($create:path) => {
+        #[no_mangle]
+        #[doc(hidden)]
+        #[allow(non_upper_case_globals)]
+        pub static wasmer_compiler_plugin_declaration: $crate::CompilerPluginDeclaration =
+            $crate::CompilerPluginDeclaration {
+                abi_version: $crate::COMPILER_PLUGIN_ABI_VERSION,
+                wasmer_version: $crate::VERSION,
+                rustc_version: $crate::RUSTC_VERSION,
+                create: || -> Box<dyn $crate::CompilerConfig> { Box::new($create()) },
+            };
+    };
+}
+
+/// An error loading a compiler plugin.
+#[derive(Error, Debug)]
+pub enum CompilerPluginError {
+    /// The dynamic library couldn't be loaded, or it doesn't declare a
+    /// compiler.
+    #[error("failed to load the compiler plugin: {0}")]
+    Load(String),
+    /// The plugin was built for another version of the contract.
+    #[error("the compiler plugin uses ABI version {found}, expected {expected}")]
+    AbiVersion {
+        /// The version the plugin was built with.
+        found: u32,
+        /// The version of the host.
+        expected: u32,
+    },
+    /// The plugin was built with another version of `wasmer-compiler` or
+    /// another `rustc`.
+    #[error("the compiler plugin was built with {found}, expected {expected}")]
+    Incompatible {
+        /// What the plugin was built with.
+        found: String,
+        /// What the host was built with.
+        expected: String,
+    },
+}
+
+impl From<CompilerPluginError> for CompileError {
+    fn from(error: CompilerPluginError) -> Self {
+        Self::Codegen(error.to_string())
+    }
+}
+
+/// A compiler plugin, loaded from a dynamic library.
+pub struct CompilerPlugin {
+    // Fields are dropped in order: the configuration must be dropped
+    // while its code is still loaded.
+    config: Box<dyn CompilerConfig>,
+    library: Arc<Library>,
+}
+
+impl CompilerPlugin {
+    /// Loads the plugin at `path` and creates the configuration of its
+    /// compiler.
+    ///
+    /// # Safety
+    ///
+    /// Loading a library runs its initialization code, and the plugin
+    /// is trusted to uphold the contract of its declaration.
+    pub unsafe fn load(path: impl AsRef<Path>) -> Result<Self, CompilerPluginError> {
+        let library = Library::new(path.as_ref())
+            .map_err(|error| CompilerPluginError::Load(error.to_string()))?;
+        let declaration = *library
+            .get::<*const CompilerPluginDeclaration>(DECLARATION_SYMBOL)
+            .map_err(|error| CompilerPluginError::Load(error.to_string()))?;
+        let declaration = &*declaration;
+        check_declaration(
+            declaration.abi_version,
+            declaration.wasmer_version,
+            declaration.rustc_version,
+        )?;
+
+        Ok(Self {
+            config: (declaration.create)(),
+            library: Arc::new(library),
+        })
+    }
+
+    /// The configuration of the compiler, to tune it before creating
+    /// the [`Compiler`].
+    pub fn config_mut(&mut self) -> &mut dyn CompilerConfig {
+        &mut *self.config
+    }
+
+    /// Creates the compiler. The library stays loaded as long as the
+    /// compiler lives.
+    pub fn compiler(self) -> Box<dyn Compiler> {
+        let Self { config, library } = self;
+        Box::new(PluginCompiler {
+            compiler: config.compiler(),
+            _library: library,
+        })
+    }
+}
+
+/// Checks that a plugin was built for this host.
+fn check_declaration(
+    abi_version: u32,
+    wasmer_version: &str,
+    rustc_version: &str,
+) -> Result<(), CompilerPluginError> {
+    if abi_version != COMPILER_PLUGIN_ABI_VERSION {
+        return Err(CompilerPluginError::AbiVersion {
+            found: abi_version,
+            expected: COMPILER_PLUGIN_ABI_VERSION,
+        });
+    }
+    let found = format!("wasmer-compiler {} and {}", wasmer_version, rustc_version);
+    let expected = format!("wasmer-compiler {} and {}", crate::VERSION, RUSTC_VERSION);
+    if found != expected {
+        return Err(CompilerPluginError::Incompatible { found, expected });
+    }
+    Ok(())
+}
+
+/// A compiler whose code lives in a plugin, which it keeps loaded.
+struct PluginCompiler {
+    // Dropped before the library, see `CompilerPlugin`.
+    compiler: Box<dyn Compiler>,
+    _library: Arc<Library>,
+}
+
+impl MemoryUsage for PluginCompiler {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.compiler.size_of_val(tracker)
+    }
+}
+
+impl Compiler for PluginCompiler {
+    fn validate_module<'data>(
+        &self,
+        features: &Features,
+        limits: &ValidationLimits,
+        data: &'data [u8],
+    ) -> Result<(), CompileError> {
+        self.compiler.validate_module(features, limits, data)
+    }
+
+    fn validate_module_streaming(
+        &self,
+        features: &Features,
+        limits: &ValidationLimits,
+        reader: &mut dyn std::io::Read,
+    ) -> Result<Vec<u8>, CompileError> {
+        self.compiler
+            .validate_module_streaming(features, limits, reader)
+    }
+
+    fn compile_module<'data, 'module>(
+        &self,
+        target: &Target,
+        module: &'module CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
+    ) -> Result<Compilation, CompileError> {
+        self.compiler
+            .compile_module(target, module, module_translation, function_body_inputs)
+    }
+
+    fn compile_module_cancellable<'data, 'module>(
+        &self,
+        target: &Target,
+        module: &'module CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
+        cancellation: &CancellationToken,
+    ) -> Result<Compilation, CompileError> {
+        self.compiler.compile_module_cancellable(
+            target,
+            module,
+            module_translation,
+            function_body_inputs,
+            cancellation,
+        )
+    }
+
+    fn experimental_native_compile_module<'data, 'module>(
+        &self,
+        target: &Target,
+        module: &'module CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        function_body_inputs: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
+        symbol_registry: &dyn SymbolRegistry,
+        wasmer_metadata: &[u8],
+    ) -> Option<Result<Vec<u8>, CompileError>> {
+        self.compiler.experimental_native_compile_module(
+            target,
+            module,
+            module_translation,
+            function_body_inputs,
+            symbol_registry,
+            wasmer_metadata,
+        )
+    }
+
+    fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
+        self.compiler.get_middlewares()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_the_declaration() {
+        let wasmer = crate::VERSION;
+        assert!(check_declaration(COMPILER_PLUGIN_ABI_VERSION, wasmer, RUSTC_VERSION).is_ok());
+        assert!(matches!(
+            check_declaration(COMPILER_PLUGIN_ABI_VERSION + 1, wasmer, RUSTC_VERSION),
+            Err(CompilerPluginError::AbiVersion { .. })
+        ));
+        assert!(matches!(
+            check_declaration(COMPILER_PLUGIN_ABI_VERSION, "0.0.0", RUSTC_VERSION),
+            Err(CompilerPluginError::Incompatible { .. })
+        ));
+        assert!(matches!(
+            check_declaration(COMPILER_PLUGIN_ABI_VERSION, wasmer, "rustc 0.0.0"),
+            Err(CompilerPluginError::Incompatible { .. })
+        ));
+    }
+}
@@ -0,0 +1,312 @@
+//! Static analyses of a WebAssembly module, to pre-screen modules, pick
+//! limits and audit the imports they need before running them.
+
+use super::sections::read_elems;
+use crate::lib::std::vec::Vec;
+use crate::WasmResult;
+use wasmer_types::entity::packed_option::ReservedValue;
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{FunctionIndex, LocalFunctionIndex};
+use wasmparser::{ExternalKind, ImportSectionEntryType, Operator, Parser, Payload};
+
+/// What is statically known about a function.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FunctionAnalysis {
+    /// The functions called directly, sorted and without duplicates.
+    pub callees: Vec<FunctionIndex>,
+    /// Whether the function calls through a table.
+    pub calls_indirect: bool,
+    /// Whether the function can call itself, directly or through other
+    /// functions, without going through a table.
+    pub recursive: bool,
+    /// The deepest nesting of `loop`s in the function, `0` if it has no
+    /// loop.
+    pub max_loop_depth: u32,
+    /// The native stack space taken by a call to this function, in bytes,
+    /// not counting the functions it calls. Only known for the
+    /// compilers which report the layout of their frames.
+    pub frame_size: Option<u32>,
+}
+
+/// Static analyses of a WebAssembly module: its call graph, its loops
+/// and how much native stack its functions use.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleAnalysis {
+    num_imported_functions: usize,
+    functions: PrimaryMap<LocalFunctionIndex, FunctionAnalysis>,
+    /// The exported functions, sorted and without duplicates.
+    exported_functions: Vec<FunctionIndex>,
+    start_function: Option<FunctionIndex>,
+    /// The functions which may be stored in a table: those of the element
+    /// segments and those referenced by `ref.func`, sorted and without
+    /// duplicates.
+    address_taken: Vec<FunctionIndex>,
+    /// Whether a table is imported or exported, letting the host read and
+    /// write its elements.
+    shared_tables: bool,
+}
+
+impl ModuleAnalysis {
+    /// Analyzes a valid WebAssembly module.
+    pub fn new(data: &[u8]) -> WasmResult<Self> {
+        let mut analysis = Self::default();
+        for payload in Parser::new(0).parse_all(data) {
+            match payload? {
+                Payload::ImportSection(imports) => {
+                    for import in imports {
+                        match import?.ty {
+                            ImportSectionEntryType::Function(_) => {
+                                analysis.num_imported_functions += 1;
+                            }
+                            ImportSectionEntryType::Table(_) => analysis.shared_tables = true,
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::GlobalSection(globals) => {
+                    for global in globals {
+                        let mut reader = global?.init_expr.get_binary_reader();
+                        if let Operator::RefFunc { function_index } = reader.read_operator()? {
+                            analysis
+                                .address_taken
+                                .push(FunctionIndex::from_u32(function_index));
+                        }
+                    }
+                }
+                Payload::ExportSection(exports) => {
+                    for export in exports {
+                        let export = export?;
+                        match export.kind {
+                            ExternalKind::Function => analysis
+                                .exported_functions
+                                .push(FunctionIndex::from_u32(export.index)),
+                            ExternalKind::Table => analysis.shared_tables = true,
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::StartSection { func, .. } => {
+                    analysis.start_function = Some(FunctionIndex::from_u32(func));
+                }
+                Payload::ElementSection(elements) => {
+                    for element in elements {
+                        let elems = read_elems(&element?.items)?;
+                        analysis.address_taken.extend(
+                            elems
+                                .iter()
+                                .copied()
+                                .filter(|index| !index.is_reserved_value()),
+                        );
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let mut function = FunctionAnalysis::default();
+                    // Whether each enclosing block is a loop.
+                    let mut blocks = Vec::new();
+                    let mut loop_depth = 0;
+                    let mut reader = body.get_operators_reader()?;
+                    while !reader.eof() {
+                        match reader.read()? {
+                            Operator::Loop { .. } => {
+                                blocks.push(true);
+                                loop_depth += 1;
+                                function.max_loop_depth = function.max_loop_depth.max(loop_depth);
+                            }
+                            Operator::Block { .. } | Operator::If { .. } => {
+                                blocks.push(false);
+                            }
+                            Operator::End => {
+                                if let Some(true) = blocks.pop() {
+                                    loop_depth -= 1;
+                                }
+                            }
+                            Operator::Call { function_index }
+                            | Operator::ReturnCall { function_index } => {
+                                function
+                                    .callees
+                                    .push(FunctionIndex::from_u32(function_index));
+                            }
+                            Operator::CallIndirect { .. } | Operator::ReturnCallIndirect { .. } => {
+                                function.calls_indirect = true;
+                            }
+                            Operator::RefFunc { function_index } => {
+                                analysis
+                                    .address_taken
+                                    .push(FunctionIndex::from_u32(function_index));
+                            }
+                            _ => {}
+                        }
+                    }
+                    function.callees.sort();
+                    function.callees.dedup();
+                    analysis.functions.push(function);
+                }
+                _ => {}
+            }
+        }
+        analysis.exported_functions.sort();
+        analysis.exported_functions.dedup();
+        analysis.address_taken.sort();
+        analysis.address_taken.dedup();
+        analysis.find_recursion();
+        Ok(analysis)
+    }
+
+    /// The analyses of the functions defined by the module.
+    pub fn functions(&self) -> &PrimaryMap<LocalFunctionIndex, FunctionAnalysis> {
+        &self.functions
+    }
+
+    /// The analysis of a function, `None` if it's imported.
+    pub fn function(&self, index: FunctionIndex) -> Option<&FunctionAnalysis> {
+        self.functions.get(self.local_function_index(index)?)
+    }
+
+    /// Records the size of the frame of a function, as reported by the
+    /// compiler.
+    pub fn set_frame_size(&mut self, index: LocalFunctionIndex, frame_size: u32) {
+        self.functions[index].frame_size = Some(frame_size);
+    }
+
+    /// Whether the module may run forever: statically, any loop,
+    /// recursion or call through a table may never end.
+    pub fn has_unbounded_loops(&self) -> bool {
+        self.functions.values().any(|function| {
+            function.max_loop_depth > 0 || function.recursive || function.calls_indirect
+        })
+    }
+
+    /// The deepest native stack a call to the function may use, in
+    /// bytes, counting the functions it calls. Imported functions count
+    /// as using no stack.
+    ///
+    /// It's `None` if the function may call itself or call through a
+    /// table, or if the size of a frame isn't known.
+    pub fn max_stack_usage(&self, index: FunctionIndex) -> Option<u64> {
+        let mut memo = PrimaryMap::with_capacity(self.functions.len());
+        for _ in 0..self.functions.len() {
+            memo.push(None);
+        }
+        self.stack_usage(index, &mut memo)
+    }
+
+    fn stack_usage(
+        &self,
+        index: FunctionIndex,
+        memo: &mut PrimaryMap<LocalFunctionIndex, Option<Option<u64>>>,
+    ) -> Option<u64> {
+        let local_index = match self.local_function_index(index) {
+            Some(local_index) => local_index,
+            None => return Some(0),
+        };
+        if let Some(usage) = memo[local_index] {
+            return usage;
+        }
+        let function = &self.functions[local_index];
+        let usage = if function.recursive || function.calls_indirect {
+            None
+        } else {
+            function.frame_size.and_then(|frame_size| {
+                let mut deepest = 0;
+                for &callee in function.callees.iter() {
+                    deepest = deepest.max(self.stack_usage(callee, memo)?);
+                }
+                Some(frame_size as u64 + deepest)
+            })
+        };
+        memo[local_index] = Some(usage);
+        usage
+    }
+
+    /// The imported functions a call to the function may end up calling,
+    /// directly or through other functions, sorted. A call through a table
+    /// may reach any function which can be stored in a table.
+    pub fn imports_used_by(&self, index: FunctionIndex) -> Vec<FunctionIndex> {
+        self.imports_reachable_from(vec![index])
+    }
+
+    /// The imported functions the module may ever call, sorted: those
+    /// reachable from its exported functions and its start function. The
+    /// other imports can never be called, so a host may stub them out.
+    ///
+    /// When a table is imported or exported, the functions stored in
+    /// tables count as exported too, as the host can call them.
+    pub fn reachable_imports(&self) -> Vec<FunctionIndex> {
+        let mut roots = self.exported_functions.clone();
+        roots.extend(self.start_function);
+        if self.shared_tables {
+            roots.extend(self.address_taken.iter().copied());
+        }
+        self.imports_reachable_from(roots)
+    }
+
+    fn imports_reachable_from(&self, mut pending: Vec<FunctionIndex>) -> Vec<FunctionIndex> {
+        let mut seen = vec![false; self.num_imported_functions + self.functions.len()];
+        let mut followed_indirect_calls = false;
+        let mut imports = Vec::new();
+        while let Some(index) = pending.pop() {
+            match seen.get_mut(index.index()) {
+                Some(seen) if !*seen => *seen = true,
+                _ => continue,
+            }
+            let function = match self.local_function_index(index) {
+                Some(local_index) => &self.functions[local_index],
+                None => {
+                    imports.push(index);
+                    continue;
+                }
+            };
+            pending.extend(function.callees.iter().copied());
+            if function.calls_indirect && !followed_indirect_calls {
+                followed_indirect_calls = true;
+                pending.extend(self.address_taken.iter().copied());
+                // The host can store the exported functions in the tables
+                // it shares with the module.
+                if self.shared_tables {
+                    pending.extend(self.exported_functions.iter().copied());
+                }
+            }
+        }
+        imports.sort();
+        imports
+    }
+
+    fn local_function_index(&self, index: FunctionIndex) -> Option<LocalFunctionIndex> {
+        index
+            .index()
+            .checked_sub(self.num_imported_functions)
+            .map(LocalFunctionIndex::new)
+    }
+
+    /// Marks the functions which are in a cycle of the call graph.
+    fn find_recursion(&mut self) {
+        let recursive = self
+            .functions
+            .keys()
+            .filter(|&start| self.reaches(start, start))
+            .collect::<Vec<_>>();
+        for index in recursive {
+            self.functions[index].recursive = true;
+        }
+    }
+
+    /// Whether `from` calls `to`, directly or through other functions.
+    fn reaches(&self, from: LocalFunctionIndex, to: LocalFunctionIndex) -> bool {
+        let mut seen = vec![false; self.functions.len()];
+        let mut pending = self.functions[from].callees.clone();
+        while let Some(callee) = pending.pop() {
+            let callee = match self.local_function_index(callee) {
+                Some(callee) => callee,
+                None => continue,
+            };
+            if callee == to {
+                return true;
+            }
+            if !seen[callee.index()] {
+                seen[callee.index()] = true;
+                pending.extend(self.functions[callee].callees.iter().copied());
+            }
+        }
+        false
+    }
+}
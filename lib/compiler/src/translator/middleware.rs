@@ -59,6 +59,9 @@ pub struct MiddlewareReaderState<'a> {
 
     /// The pending operations added by the middleware.
     pending_operations: VecDeque<Operator<'a>>,
+
+    /// The offset in the module of the original operator being processed.
+    operator_offset: usize,
 }
 
 /// Trait for generating middleware chains from "prototype" (generator) chains.
@@ -97,6 +100,15 @@ impl<'a> MiddlewareReaderState<'a> {
     pub fn push_operator(&mut self, operator: Operator<'a>) {
         self.pending_operations.push_back(operator);
     }
+
+    /// The offset in the module of the original operator being processed.
+    ///
+    /// All the operators fed to the middlewares come from this original
+    /// operator, either directly or through the previous middlewares of
+    /// the chain.
+    pub fn operator_offset(&self) -> usize {
+        self.operator_offset
+    }
 }
 
 impl<'a> Extend<Operator<'a>> for MiddlewareReaderState<'a> {
@@ -119,6 +131,7 @@ impl<'a> MiddlewareBinaryReader<'a> {
             state: MiddlewareReaderState {
                 inner,
                 pending_operations: VecDeque::new(),
+                operator_offset: original_offset,
             },
             chain: vec![],
         }
@@ -149,6 +162,7 @@ impl<'a> FunctionBinaryReader<'a> for MiddlewareBinaryReader<'a> {
 
         // Try to fill the `self.pending_operations` buffer, until it is non-empty.
         while self.state.pending_operations.is_empty() {
+            self.state.operator_offset = self.state.inner.original_position();
             let raw_op = self.state.inner.read_operator()?;
 
             // Fill the initial raw operator into pending buffer.
@@ -5,6 +5,7 @@
 //! compilers rather than just Cranelift.
 //!
 //! [cranelift-wasm]: https://crates.io/crates/cranelift-wasm/
+mod analysis;
 mod environ;
 mod middleware;
 mod module;
@@ -13,6 +14,7 @@ mod state;
 mod error;
 mod sections;
 
+pub use self::analysis::{FunctionAnalysis, ModuleAnalysis};
 pub use self::environ::{FunctionBinaryReader, FunctionBodyData, ModuleEnvironment};
 pub use self::middleware::{
     FunctionMiddleware, MiddlewareBinaryReader, MiddlewareReaderState, ModuleMiddleware,
@@ -319,7 +319,7 @@ pub fn parse_start_section(index: u32, environ: &mut ModuleEnvironment) -> WasmR
     Ok(())
 }
 
-fn read_elems(items: &ElementItems) -> WasmResult<Box<[FunctionIndex]>> {
+pub(super) fn read_elems(items: &ElementItems) -> WasmResult<Box<[FunctionIndex]>> {
     let items_reader = items.get_items_reader()?;
     let mut elems = Vec::with_capacity(usize::try_from(items_reader.get_count()).unwrap());
     for item in items_reader {
@@ -1,11 +1,12 @@
 extern crate proc_macro;
 
 use proc_macro2::TokenStream;
-use proc_macro_error::{abort, proc_macro_error, set_dummy};
+use proc_macro_error::{abort, abort_call_site, proc_macro_error, set_dummy};
 use quote::{quote, quote_spanned, ToTokens};
 use syn::{spanned::Spanned, *};
 
 mod parse;
+mod wit;
 
 use crate::parse::WasmerAttr;
 
@@ -17,6 +18,20 @@ pub fn derive_wasmer_env(input: proc_macro::TokenStream) -> proc_macro::TokenStr
     gen.into()
 }
 
+/// Generates host-side bindings for the functions described by a WIT
+/// interface file, resolved relative to the crate's `Cargo.toml`.
+///
+/// See `wasmer::import!` for more information.
+#[proc_macro_error]
+#[proc_macro]
+pub fn import(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let path: LitStr = match syn::parse(input) {
+        Ok(path) => path,
+        Err(e) => abort_call_site!("expected a path to a `.wit` file: {}", e),
+    };
+    wit::expand_import(path).into()
+}
+
 fn impl_wasmer_env_for_struct(
     name: &Ident,
     data: &DataStruct,
@@ -0,0 +1,450 @@
+//! A small WIT (WebAssembly Interface Types) front-end used by the
+//! `import!` macro.
+//!
+//! Only the subset of WIT needed to describe flat function interfaces is
+//! supported: free functions whose parameters and results are scalars,
+//! `string` or `list<u8>`. Values are lowered and lifted following the
+//! canonical ABI conventions used by `wit-bindgen` guests
+//! (`canonical_abi_realloc` / `canonical_abi_free`, return areas for
+//! indirect results).
+
+use proc_macro2::{Span, TokenStream};
+use proc_macro_error::abort_call_site;
+use quote::{format_ident, quote};
+use syn::{Ident, LitStr};
+
+/// A WIT value type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WitType {
+    Bool,
+    U8,
+    S8,
+    U16,
+    S16,
+    U32,
+    S32,
+    U64,
+    S64,
+    Float32,
+    Float64,
+    Char,
+    String,
+    ListU8,
+}
+
+impl WitType {
+    fn parse(ty: &str) -> Result<Self, String> {
+        let compact = ty.split_whitespace().collect::<String>();
+        Ok(match compact.as_str() {
+            "bool" => Self::Bool,
+            "u8" => Self::U8,
+            "s8" => Self::S8,
+            "u16" => Self::U16,
+            "s16" => Self::S16,
+            "u32" => Self::U32,
+            "s32" => Self::S32,
+            "u64" => Self::U64,
+            "s64" => Self::S64,
+            "float32" => Self::Float32,
+            "float64" => Self::Float64,
+            "char" => Self::Char,
+            "string" => Self::String,
+            "list<u8>" => Self::ListU8,
+            other => return Err(format!("unsupported WIT type `{}`", other)),
+        })
+    }
+
+    /// Whether the value lives in linear memory and is passed as a
+    /// `(pointer, length)` pair.
+    fn is_indirect(self) -> bool {
+        matches!(self, Self::String | Self::ListU8)
+    }
+
+    /// The Rust type used in the generated method signatures for parameters.
+    fn rust_param_type(self) -> TokenStream {
+        match self {
+            Self::String => quote!(&str),
+            Self::ListU8 => quote!(&[u8]),
+            other => other.rust_result_type(),
+        }
+    }
+
+    /// The Rust type used in the generated method signatures for results.
+    fn rust_result_type(self) -> TokenStream {
+        match self {
+            Self::Bool => quote!(bool),
+            Self::U8 => quote!(u8),
+            Self::S8 => quote!(i8),
+            Self::U16 => quote!(u16),
+            Self::S16 => quote!(i16),
+            Self::U32 => quote!(u32),
+            Self::S32 => quote!(i32),
+            Self::U64 => quote!(u64),
+            Self::S64 => quote!(i64),
+            Self::Float32 => quote!(f32),
+            Self::Float64 => quote!(f64),
+            Self::Char => quote!(char),
+            Self::String => quote!(String),
+            Self::ListU8 => quote!(Vec<u8>),
+        }
+    }
+
+    /// The core Wasm types this value is flattened to.
+    fn wasm_types(self) -> Vec<TokenStream> {
+        match self {
+            Self::U64 | Self::S64 => vec![quote!(i64)],
+            Self::Float32 => vec![quote!(f32)],
+            Self::Float64 => vec![quote!(f64)],
+            Self::String | Self::ListU8 => vec![quote!(i32), quote!(i32)],
+            _ => vec![quote!(i32)],
+        }
+    }
+}
+
+/// A WIT function declaration.
+#[derive(Debug)]
+pub struct WitFunction {
+    pub name: String,
+    pub params: Vec<(String, WitType)>,
+    pub result: Option<WitType>,
+}
+
+/// Parses the functions of a WIT document.
+pub fn parse_document(source: &str) -> Result<Vec<WitFunction>, String> {
+    let mut functions = vec![];
+
+    for (line_number, line) in source.lines().enumerate() {
+        let line = match line.find("//") {
+            Some(comment) => &line[..comment],
+            None => line,
+        }
+        .trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let error = |message: String| format!("line {}: {}", line_number + 1, message);
+        let (name, signature) = line
+            .split_once(':')
+            .ok_or_else(|| error("expected `name: func(...)`".to_string()))?;
+        let signature = signature
+            .trim()
+            .strip_prefix("func")
+            .ok_or_else(|| error("only function declarations are supported".to_string()))?
+            .trim();
+        let params_end = signature
+            .find(')')
+            .ok_or_else(|| error("unterminated parameter list".to_string()))?;
+        let params_source = signature[..params_end]
+            .trim()
+            .strip_prefix('(')
+            .ok_or_else(|| error("expected `(`".to_string()))?;
+
+        let mut params = vec![];
+        for param in params_source.split(',').map(str::trim) {
+            if param.is_empty() {
+                continue;
+            }
+            let (param_name, param_type) = param
+                .split_once(':')
+                .ok_or_else(|| error(format!("expected `name: type`, found `{}`", param)))?;
+            let param_type = WitType::parse(param_type).map_err(error)?;
+            params.push((param_name.trim().to_string(), param_type));
+        }
+
+        let rest = signature[params_end + 1..].trim();
+        let result = if rest.is_empty() {
+            None
+        } else {
+            let result = rest
+                .strip_prefix("->")
+                .ok_or_else(|| error(format!("unexpected `{}`", rest)))?;
+            Some(WitType::parse(result).map_err(error)?)
+        };
+
+        functions.push(WitFunction {
+            name: name.trim().to_string(),
+            params,
+            result,
+        });
+    }
+
+    Ok(functions)
+}
+
+fn to_snake_case(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+fn to_camel_case(name: &str) -> String {
+    name.split(|c: char| c == '-' || c == '_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Expands `import!("path/to/interface.wit")`.
+pub fn expand_import(path: LitStr) -> TokenStream {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+    let full_path = std::path::Path::new(&manifest_dir).join(path.value());
+    let source = match std::fs::read_to_string(&full_path) {
+        Ok(source) => source,
+        Err(e) => abort_call_site!("failed to read `{}`: {}", full_path.display(), e),
+    };
+    let functions = match parse_document(&source) {
+        Ok(functions) => functions,
+        Err(e) => abort_call_site!("failed to parse `{}`: {}", full_path.display(), e),
+    };
+    let struct_name = full_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(to_camel_case)
+        .unwrap_or_else(|| abort_call_site!("invalid WIT file name"));
+    let struct_name = Ident::new(&struct_name, Span::call_site());
+    let full_path_str = full_path.display().to_string();
+
+    let mut fields = vec![];
+    let mut field_inits = vec![];
+    let mut methods = vec![];
+
+    for function in &functions {
+        let export_name = &function.name;
+        let field = format_ident!("func_{}", to_snake_case(&function.name));
+        let method = format_ident!("{}", to_snake_case(&function.name));
+
+        let wasm_params = function
+            .params
+            .iter()
+            .flat_map(|(_, ty)| ty.wasm_types())
+            .collect::<Vec<_>>();
+        let wasm_result = match function.result {
+            // Indirect results are written to a return area whose
+            // address is returned by the function.
+            Some(ty) if ty.is_indirect() => quote!(i32),
+            Some(ty) => {
+                let types = ty.wasm_types();
+                quote!(#(#types)*)
+            }
+            None => quote!(()),
+        };
+
+        // `NativeFunc` takes single parameters unwrapped, e.g.
+        // `NativeFunc<i32, i32>`, and multiple ones as a tuple.
+        let wasm_args = if wasm_params.len() == 1 {
+            wasm_params[0].clone()
+        } else {
+            quote!((#(#wasm_params),*))
+        };
+        fields.push(quote! {
+            #field: ::wasmer::NativeFunc<#wasm_args, #wasm_result>
+        });
+        field_inits.push(quote! {
+            #field: instance.exports.get_native_function(#export_name)?
+        });
+
+        let mut rust_params = vec![];
+        let mut lowering = vec![];
+        let mut call_args = vec![];
+        for (index, (name, ty)) in function.params.iter().enumerate() {
+            let name = format_ident!("{}", to_snake_case(name));
+            let rust_type = ty.rust_param_type();
+            rust_params.push(quote!(#name: #rust_type));
+            match ty {
+                WitType::String | WitType::ListU8 => {
+                    let ptr = format_ident!("ptr{}", index);
+                    let bytes = if *ty == WitType::String {
+                        quote!(#name.as_bytes())
+                    } else {
+                        quote!(#name)
+                    };
+                    lowering.push(quote! {
+                        let #ptr = self.lower_bytes(#bytes)?;
+                    });
+                    call_args.push(quote!(#ptr));
+                    call_args.push(quote!(#name.len() as i32));
+                }
+                WitType::Bool | WitType::Char => call_args.push(quote!(#name as i32)),
+                WitType::U8 | WitType::S8 | WitType::U16 | WitType::S16 | WitType::U32 => {
+                    call_args.push(quote!(#name as i32))
+                }
+                WitType::U64 => call_args.push(quote!(#name as i64)),
+                _ => call_args.push(quote!(#name)),
+            }
+        }
+
+        let (rust_result, lifting) = match function.result {
+            None => (quote!(()), quote!(Ok(result))),
+            Some(ty) => {
+                let lifting = match ty {
+                    WitType::String => quote! {
+                        String::from_utf8(self.lift_bytes(result)?).map_err(|e| {
+                            ::wasmer::RuntimeError::new(format!("invalid UTF-8 string: {}", e))
+                        })
+                    },
+                    WitType::ListU8 => quote!(self.lift_bytes(result)),
+                    WitType::Bool => quote!(Ok(result != 0)),
+                    WitType::Char => quote! {
+                        ::std::char::from_u32(result as u32).ok_or_else(|| {
+                            ::wasmer::RuntimeError::new(format!("invalid char: {:#x}", result))
+                        })
+                    },
+                    WitType::U8
+                    | WitType::S8
+                    | WitType::U16
+                    | WitType::S16
+                    | WitType::U32
+                    | WitType::U64 => {
+                        let rust_type = ty.rust_result_type();
+                        quote!(Ok(result as #rust_type))
+                    }
+                    _ => quote!(Ok(result)),
+                };
+                (ty.rust_result_type(), lifting)
+            }
+        };
+
+        let doc = format!("Calls the `{}` export of the guest.", export_name);
+        methods.push(quote! {
+            #[doc = #doc]
+            pub fn #method(&self, #(#rust_params),*) -> Result<#rust_result, ::wasmer::RuntimeError> {
+                #(#lowering)*
+                let result = self.#field.call(#(#call_args),*)?;
+                #lifting
+            }
+        });
+    }
+
+    let doc = format!("Bindings generated from `{}`.", path.value());
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Clone)]
+        pub struct #struct_name {
+            memory: Option<::wasmer::Memory>,
+            realloc: Option<::wasmer::NativeFunc<(i32, i32, i32, i32), i32>>,
+            free: Option<::wasmer::NativeFunc<(i32, i32, i32), ()>>,
+            #(#fields,)*
+        }
+
+        impl #struct_name {
+            /// Looks up the exports described by the interface in `instance`.
+            pub fn new(instance: &::wasmer::Instance) -> Result<Self, ::wasmer::ExportError> {
+                // Make sure the crate is rebuilt when the interface changes.
+                const _: &str = include_str!(#full_path_str);
+
+                Ok(Self {
+                    memory: instance.exports.get_memory("memory").ok().cloned(),
+                    realloc: instance.exports.get_native_function("canonical_abi_realloc").ok(),
+                    free: instance.exports.get_native_function("canonical_abi_free").ok(),
+                    #(#field_inits,)*
+                })
+            }
+
+            #(#methods)*
+
+            #[allow(dead_code)]
+            fn memory(&self) -> Result<&::wasmer::Memory, ::wasmer::RuntimeError> {
+                self.memory
+                    .as_ref()
+                    .ok_or_else(|| ::wasmer::RuntimeError::new("the guest doesn't export `memory`"))
+            }
+
+            #[allow(dead_code)]
+            fn lower_bytes(&self, bytes: &[u8]) -> Result<i32, ::wasmer::RuntimeError> {
+                let realloc = self.realloc.as_ref().ok_or_else(|| {
+                    ::wasmer::RuntimeError::new("the guest doesn't export `canonical_abi_realloc`")
+                })?;
+                let ptr = realloc.call(0, 0, 1, bytes.len() as i32)?;
+                let view = self.memory()?.view::<u8>();
+                let start = ptr as u32 as usize;
+                let cells = view.get(start..start + bytes.len()).ok_or_else(|| {
+                    ::wasmer::RuntimeError::new("`canonical_abi_realloc` returned an out-of-bounds pointer")
+                })?;
+                for (cell, byte) in cells.iter().zip(bytes) {
+                    cell.set(*byte);
+                }
+                Ok(ptr)
+            }
+
+            #[allow(dead_code)]
+            fn lift_bytes(&self, ret_area: i32) -> Result<Vec<u8>, ::wasmer::RuntimeError> {
+                let out_of_bounds = || ::wasmer::RuntimeError::new("out-of-bounds result in guest memory");
+                let view = self.memory()?.view::<u8>();
+                let read_i32 = |offset: usize| -> Result<i32, ::wasmer::RuntimeError> {
+                    let cells = view.get(offset..offset + 4).ok_or_else(out_of_bounds)?;
+                    let mut bytes = [0u8; 4];
+                    for (byte, cell) in bytes.iter_mut().zip(cells) {
+                        *byte = cell.get();
+                    }
+                    Ok(i32::from_le_bytes(bytes))
+                };
+                let ret_area = ret_area as u32 as usize;
+                let ptr = read_i32(ret_area)?;
+                let len = read_i32(ret_area + 4)?;
+                let start = ptr as u32 as usize;
+                let bytes = view
+                    .get(start..start + len as u32 as usize)
+                    .ok_or_else(out_of_bounds)?
+                    .iter()
+                    .map(|cell| cell.get())
+                    .collect::<Vec<u8>>();
+                if let Some(free) = &self.free {
+                    free.call(ptr, len, 1)?;
+                }
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_functions() {
+        let functions = parse_document(
+            r#"
+            // A comment.
+            add: func(a: s32, b: s32) -> s32
+            greet: func(name: string) -> string
+            log-bytes: func(bytes: list< u8 >)
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(functions.len(), 3);
+        assert_eq!(functions[0].name, "add");
+        assert_eq!(
+            functions[0].params,
+            vec![
+                ("a".to_string(), WitType::S32),
+                ("b".to_string(), WitType::S32)
+            ]
+        );
+        assert_eq!(functions[0].result, Some(WitType::S32));
+        assert_eq!(functions[1].result, Some(WitType::String));
+        assert_eq!(functions[2].params[0].1, WitType::ListU8);
+        assert_eq!(functions[2].result, None);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse_document("add func(a: s32)").is_err());
+        assert!(parse_document("add: func(a: record)").is_err());
+        assert!(parse_document("foo: u32").is_err());
+    }
+
+    #[test]
+    fn camel_case() {
+        assert_eq!(to_camel_case("my-interface"), "MyInterface");
+        assert_eq!(to_camel_case("fs"), "Fs");
+    }
+}
@@ -45,7 +45,7 @@ use wasmer_types::{
     SignatureIndex, TableIndex,
 };
 use wasmer_vm::{
-    FuncDataRegistry, FunctionBodyPtr, MemoryStyle, TableStyle, VMFunctionBody,
+    FuncDataRegistry, FunctionBodyPtr, MemoryStyle, SignatureRegistry, TableStyle, VMFunctionBody,
     VMSharedSignatureIndex, VMTrampoline,
 };
 
@@ -61,6 +61,9 @@ pub struct DylibArtifact {
     finished_dynamic_function_trampolines: BoxedSlice<FunctionIndex, FunctionBodyPtr>,
     func_data_registry: Arc<FuncDataRegistry>,
     signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
+    // The registry holding `signatures`, none when cross-compiling.
+    #[loupe(skip)]
+    signature_registry: Option<Arc<SignatureRegistry>>,
     frame_info_registration: Mutex<Option<GlobalFrameInfoRegistration>>,
 }
 
@@ -71,6 +74,11 @@ impl Drop for DylibArtifact {
                 error!("cannot delete the temporary dylib artifact: {}", err);
             }
         }
+        if let Some(signature_registry) = &self.signature_registry {
+            for signature in self.signatures.values() {
+                signature_registry.unregister(*signature);
+            }
+        }
     }
 }
 
@@ -219,6 +227,7 @@ impl DylibArtifact {
             data_initializers,
             function_body_lengths,
             cpu_features: target.cpu_features().as_u64(),
+            native_libraries: engine_inner.native_libraries().to_vec(),
         };
 
         let serialized_data = metadata.serialize()?;
@@ -479,6 +488,7 @@ impl DylibArtifact {
                 .into_boxed_slice(),
             func_data_registry: Arc::new(FuncDataRegistry::new()),
             signatures: signatures.into_boxed_slice(),
+            signature_registry: None,
             frame_info_registration: Mutex::new(None),
         })
     }
@@ -490,6 +500,8 @@ impl DylibArtifact {
         dylib_path: PathBuf,
         lib: Library,
     ) -> Result<Self, CompileError> {
+        engine_inner.load_native_libraries(&metadata.native_libraries)?;
+
         unsafe {
             let trampolines_symbol: LibrarySymbol<usize> = lib
                 .get(WASMER_TRAMPOLINES_SYMBOL)
@@ -578,6 +590,7 @@ impl DylibArtifact {
                 .into_boxed_slice(),
             func_data_registry: engine_inner.func_data().clone(),
             signatures: signatures.into_boxed_slice(),
+            signature_registry: Some(engine_inner.signatures().clone()),
             frame_info_registration: Mutex::new(None),
         })
     }
@@ -677,6 +690,14 @@ impl DylibArtifact {
     pub fn metadata(&self) -> &ModuleMetadata {
         &self.metadata
     }
+
+    /// The native libraries this artifact depends on, loaded along
+    /// with it.
+    ///
+    /// See [`DylibEngine::set_native_libraries`].
+    pub fn native_libraries(&self) -> &[String] {
+        &self.metadata.native_libraries
+    }
 }
 
 impl ArtifactCreate for DylibArtifact {
@@ -869,6 +890,9 @@ impl Artifact for DylibArtifact {
                         body_len: extent.length,
                         ..Default::default()
                     },
+                    step_points: vec![],
+                    patch_points: vec![],
+                    frame_size: None,
                 })
                 .collect::<PrimaryMap<LocalFunctionIndex, _>>()
         };
@@ -41,6 +41,9 @@ impl Dylib {
     }
 
     /// Set the features
+    ///
+    /// The features the compiler can't compile for the target are
+    /// disabled when the engine is built.
     pub fn features(mut self, features: Features) -> Self {
         self.features = Some(features);
         self
@@ -55,7 +58,8 @@ impl Dylib {
                 let target = self.target.unwrap_or_default();
                 let features = self
                     .features
-                    .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
+                    .unwrap_or_else(|| compiler_config.default_features_for_target(&target))
+                    .intersection(&compiler_config.supported_features_for_target(&target));
                 let compiler = compiler_config.compiler();
                 DylibEngine::new(compiler, target, features)
             }
@@ -1,14 +1,16 @@
 //! Dylib Engine.
 
+use crate::native_library::{self, NativeLibraryResolution};
 use crate::DylibArtifact;
 use libloading::Library;
 use loupe::MemoryUsage;
+use std::collections::HashSet;
 use std::path::Path;
 use std::sync::Arc;
 use std::sync::Mutex;
 use wasmer_compiler::{CompileError, Target};
 #[cfg(feature = "compiler")]
-use wasmer_compiler::{Compiler, Triple};
+use wasmer_compiler::{Compiler, Triple, ValidationLimits};
 use wasmer_engine::{Artifact, DeserializeError, Engine, EngineId, Tunables};
 #[cfg(feature = "compiler")]
 use wasmer_types::Features;
@@ -36,13 +38,16 @@ impl DylibEngine {
         Self {
             inner: Arc::new(Mutex::new(DylibEngineInner {
                 compiler: Some(compiler),
-                signatures: SignatureRegistry::new(),
+                signatures: Arc::new(SignatureRegistry::new()),
                 func_data: Arc::new(FuncDataRegistry::new()),
                 prefixer: None,
                 features,
                 is_cross_compiling,
                 linker,
                 libraries: vec![],
+                native_libraries: vec![],
+                native_library_resolver: None,
+                loaded_native_libraries: HashSet::new(),
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
@@ -69,12 +74,16 @@ impl DylibEngine {
                 compiler: None,
                 #[cfg(feature = "compiler")]
                 features: Features::default(),
-                signatures: SignatureRegistry::new(),
+                signatures: Arc::new(SignatureRegistry::new()),
                 func_data: Arc::new(FuncDataRegistry::new()),
                 prefixer: None,
                 is_cross_compiling: false,
                 linker: Linker::None,
                 libraries: vec![],
+                #[cfg(feature = "compiler")]
+                native_libraries: vec![],
+                native_library_resolver: None,
+                loaded_native_libraries: HashSet::new(),
             })),
             target: Arc::new(Target::default()),
             engine_id: EngineId::default(),
@@ -99,6 +108,45 @@ impl DylibEngine {
         inner.prefixer = Some(Box::new(prefixer));
     }
 
+    /// Declares the native libraries that the modules compiled by this
+    /// engine depend on, by name.
+    ///
+    /// The names are recorded in the artifacts, and the libraries are
+    /// loaded, through the resolver set with
+    /// [`DylibEngine::set_native_library_resolver`], each time an
+    /// artifact is loaded. Their symbols are then used to resolve the
+    /// undefined symbols of the artifact.
+    ///
+    /// # Important
+    ///
+    /// The libraries run with the privileges of the host: only declare
+    /// libraries that are trusted.
+    #[cfg(feature = "compiler")]
+    pub fn set_native_libraries<I, S>(&mut self, libraries: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut inner = self.inner_mut();
+        inner.native_libraries = libraries.into_iter().map(Into::into).collect();
+    }
+
+    /// Sets the resolver deciding how the native libraries that the
+    /// artifacts depend on are loaded, see
+    /// [`DylibEngine::set_native_libraries`].
+    ///
+    /// The resolver is called with the name of each library, the first
+    /// time an artifact depending on it is loaded. Without a resolver,
+    /// the libraries are looked up by name in the system search paths.
+    /// Returning an error makes the loading of the artifact fail.
+    pub fn set_native_library_resolver<F>(&mut self, resolver: F)
+    where
+        F: Fn(&str) -> Result<NativeLibraryResolution, String> + Send + 'static,
+    {
+        let mut inner = self.inner_mut();
+        inner.native_library_resolver = Some(Box::new(resolver));
+    }
+
     pub(crate) fn inner(&self) -> std::sync::MutexGuard<'_, DylibEngineInner> {
         self.inner.lock().unwrap()
     }
@@ -131,6 +179,11 @@ impl Engine for DylibEngine {
         compiler.signatures().lookup(sig)
     }
 
+    /// The shared signature registry
+    fn signature_registry(&self) -> Arc<SignatureRegistry> {
+        self.inner().signatures().clone()
+    }
+
     /// Validates a WebAssembly module
     fn validate(&self, binary: &[u8]) -> Result<(), CompileError> {
         self.inner().validate(binary)
@@ -227,6 +280,9 @@ impl Linker {
     }
 }
 
+/// Decides how a native library is loaded, given its name.
+type NativeLibraryResolver = Box<dyn Fn(&str) -> Result<NativeLibraryResolution, String> + Send>;
+
 /// The inner contents of `DylibEngine`
 #[derive(MemoryUsage)]
 pub struct DylibEngineInner {
@@ -240,7 +296,7 @@ pub struct DylibEngineInner {
 
     /// The signature registry is used mainly to operate with trampolines
     /// performantly.
-    signatures: SignatureRegistry,
+    signatures: Arc<SignatureRegistry>,
 
     /// The backing storage of `VMFuncRef`s. This centralized store ensures that 2
     /// functions with the same `VMCallerCheckedAnyfunc` will have the same `VMFuncRef`.
@@ -262,6 +318,18 @@ pub struct DylibEngineInner {
     /// List of libraries loaded by this engine.
     #[loupe(skip)]
     libraries: Vec<Library>,
+
+    /// The native libraries the modules compiled by this engine depend on.
+    #[cfg(feature = "compiler")]
+    native_libraries: Vec<String>,
+
+    /// How the native libraries the artifacts depend on are loaded.
+    #[loupe(skip)]
+    native_library_resolver: Option<NativeLibraryResolver>,
+
+    /// The names of the native libraries already loaded by this engine.
+    #[loupe(skip)]
+    loaded_native_libraries: HashSet<String>,
 }
 
 impl DylibEngineInner {
@@ -294,7 +362,8 @@ impl DylibEngineInner {
     /// Validate the module
     #[cfg(feature = "compiler")]
     pub fn validate(&self, data: &[u8]) -> Result<(), CompileError> {
-        self.compiler()?.validate_module(self.features(), data)
+        self.compiler()?
+            .validate_module(self.features(), &ValidationLimits::default(), data)
     }
 
     /// Validate the module
@@ -306,7 +375,7 @@ impl DylibEngineInner {
     }
 
     /// Shared signature registry.
-    pub fn signatures(&self) -> &SignatureRegistry {
+    pub fn signatures(&self) -> &Arc<SignatureRegistry> {
         &self.signatures
     }
 
@@ -326,4 +395,73 @@ impl DylibEngineInner {
     pub(crate) fn add_library(&mut self, library: Library) {
         self.libraries.push(library);
     }
+
+    #[cfg(feature = "compiler")]
+    pub(crate) fn native_libraries(&self) -> &[String] {
+        &self.native_libraries
+    }
+
+    /// Loads the native libraries an artifact depends on, unless they
+    /// have already been loaded.
+    pub(crate) fn load_native_libraries(&mut self, names: &[String]) -> Result<(), CompileError> {
+        for name in names {
+            if self.loaded_native_libraries.contains(name) {
+                continue;
+            }
+            let resolution = match &self.native_library_resolver {
+                Some(resolver) => resolver(name).map_err(|e| {
+                    CompileError::Codegen(format!(
+                        "cannot resolve the native library `{}`: {}",
+                        name, e
+                    ))
+                })?,
+                None => native_library::default_resolution(name),
+            };
+            if let NativeLibraryResolution::Path(path) = resolution {
+                let library = unsafe { native_library::load(&path) }.map_err(|e| {
+                    CompileError::Codegen(format!(
+                        "cannot load the native library `{}` from `{}`: {}",
+                        name,
+                        path.display(),
+                        e
+                    ))
+                })?;
+                self.add_library(library);
+            }
+            self.loaded_native_libraries.insert(name.clone());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn native_libraries_are_resolved_once() {
+        let resolved = Arc::new(Mutex::new(vec![]));
+        let mut engine = DylibEngine::headless();
+        engine.set_native_library_resolver({
+            let resolved = resolved.clone();
+            move |name| {
+                resolved.lock().unwrap().push(name.to_string());
+                match name {
+                    "helpers" => Ok(NativeLibraryResolution::Provided),
+                    _ => Err("not vetted".to_string()),
+                }
+            }
+        });
+
+        let mut inner = engine.inner_mut();
+        let helpers = vec!["helpers".to_string()];
+        inner.load_native_libraries(&helpers).unwrap();
+        inner.load_native_libraries(&helpers).unwrap();
+        let error = inner
+            .load_native_libraries(&["other".to_string()])
+            .unwrap_err();
+        assert!(error.to_string().contains("not vetted"));
+        assert_eq!(*resolved.lock().unwrap(), vec!["helpers", "other"]);
+    }
 }
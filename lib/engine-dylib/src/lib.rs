@@ -25,12 +25,14 @@
 mod artifact;
 mod builder;
 mod engine;
+mod native_library;
 mod serialize;
 mod trampoline;
 
 pub use crate::artifact::DylibArtifact;
 pub use crate::builder::Dylib;
 pub use crate::engine::DylibEngine;
+pub use crate::native_library::NativeLibraryResolution;
 
 /// Version number of this crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,40 @@
+//! Native libraries that dylib artifacts depend on.
+
+use libloading::Library;
+use std::path::{Path, PathBuf};
+
+/// How a native library a dylib artifact depends on is loaded, as
+/// decided by the resolver given to
+/// [`DylibEngine::set_native_library_resolver`].
+///
+/// [`DylibEngine::set_native_library_resolver`]: crate::DylibEngine::set_native_library_resolver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NativeLibraryResolution {
+    /// Load the library from the given path, or look it up in the
+    /// system search paths if it is only a file name (as `dlopen` does).
+    Path(PathBuf),
+    /// The symbols of the library are already available in the
+    /// process, for example because they are linked in the executable,
+    /// so nothing is loaded.
+    Provided,
+}
+
+/// The resolution used when no resolver has been set: the library is
+/// looked up by name in the system search paths.
+pub(crate) fn default_resolution(name: &str) -> NativeLibraryResolution {
+    NativeLibraryResolution::Path(PathBuf::from(name))
+}
+
+/// Loads a native library so that its symbols can be used to resolve the
+/// undefined symbols of the artifacts loaded afterwards.
+pub(crate) unsafe fn load(path: &Path) -> Result<Library, libloading::Error> {
+    #[cfg(unix)]
+    {
+        use libloading::os::unix::{Library as UnixLibrary, RTLD_GLOBAL, RTLD_NOW};
+        UnixLibrary::open(Some(path), RTLD_NOW | RTLD_GLOBAL).map(Into::into)
+    }
+    #[cfg(not(unix))]
+    {
+        Library::new(path)
+    }
+}
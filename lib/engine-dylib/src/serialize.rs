@@ -38,6 +38,8 @@ pub struct ModuleMetadata {
     // The function body lengths (used to find function by address)
     pub function_body_lengths: PrimaryMap<LocalFunctionIndex, u64>,
     pub cpu_features: u64,
+    // The native libraries to load before the artifact is used
+    pub native_libraries: Vec<String>,
 }
 
 pub struct ModuleMetadataSymbolRegistry<'a> {
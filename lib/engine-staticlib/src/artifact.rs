@@ -24,7 +24,9 @@ use wasmer_engine::{
 #[cfg(feature = "compiler")]
 use wasmer_engine::{Engine, Tunables};
 #[cfg(feature = "compiler")]
-use wasmer_object::{emit_compilation, emit_data, get_object_for_target};
+use wasmer_object::{
+    emit_compilation_with_options, emit_data, get_object_for_target, ObjectOptions,
+};
 use wasmer_types::entity::EntityRef;
 use wasmer_types::entity::{BoxedSlice, PrimaryMap};
 #[cfg(feature = "compiler")]
@@ -34,8 +36,8 @@ use wasmer_types::{
     SignatureIndex, TableIndex,
 };
 use wasmer_vm::{
-    FuncDataRegistry, FunctionBodyPtr, MemoryStyle, TableStyle, VMSharedSignatureIndex,
-    VMTrampoline,
+    FuncDataRegistry, FunctionBodyPtr, MemoryStyle, SignatureRegistry, TableStyle,
+    VMSharedSignatureIndex, VMTrampoline,
 };
 
 /// A compiled wasm module, ready to be instantiated.
@@ -48,6 +50,8 @@ pub struct StaticlibArtifact {
     finished_function_call_trampolines: BoxedSlice<SignatureIndex, VMTrampoline>,
     finished_dynamic_function_trampolines: BoxedSlice<FunctionIndex, FunctionBodyPtr>,
     signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
+    #[loupe(skip)]
+    signature_registry: Arc<SignatureRegistry>,
     func_data_registry: Arc<FuncDataRegistry>,
     /// Length of the serialized metadata
     metadata_length: usize,
@@ -63,6 +67,14 @@ fn to_compile_error(err: impl Error) -> CompileError {
 #[allow(dead_code)]
 const WASMER_METADATA_SYMBOL: &[u8] = b"WASMER_METADATA";
 
+impl Drop for StaticlibArtifact {
+    fn drop(&mut self) {
+        for signature in self.signatures.values() {
+            self.signature_registry.unregister(*signature);
+        }
+    }
+}
+
 impl StaticlibArtifact {
     // Mach-O header in Mac
     #[allow(dead_code)]
@@ -251,8 +263,20 @@ impl StaticlibArtifact {
             let mut obj = get_object_for_target(&target_triple).map_err(to_compile_error)?;
             emit_data(&mut obj, WASMER_METADATA_SYMBOL, &metadata_binary, 1)
                 .map_err(to_compile_error)?;
-            emit_compilation(&mut obj, compilation, &symbol_registry, &target_triple)
-                .map_err(to_compile_error)?;
+            // The symbols are only referenced by the code the object is
+            // linked with.
+            let options = ObjectOptions {
+                function_sections: engine_inner.function_sections(),
+                hidden_symbols: true,
+            };
+            emit_compilation_with_options(
+                &mut obj,
+                compilation,
+                &symbol_registry,
+                &target_triple,
+                &options,
+            )
+            .map_err(to_compile_error)?;
             obj.write().map_err(to_compile_error)?
         };
 
@@ -279,7 +303,7 @@ impl StaticlibArtifact {
             PrimaryMap::new();
         let finished_dynamic_function_trampolines: PrimaryMap<FunctionIndex, FunctionBodyPtr> =
             PrimaryMap::new();
-        let signature_registry = engine_inner.signatures();
+        let signature_registry = engine_inner.signatures().clone();
         let signatures = metadata
             .compile_info
             .module
@@ -298,6 +322,7 @@ impl StaticlibArtifact {
             finished_dynamic_function_trampolines: finished_dynamic_function_trampolines
                 .into_boxed_slice(),
             signatures: signatures.into_boxed_slice(),
+            signature_registry,
             func_data_registry: engine_inner.func_data().clone(),
             metadata_length,
             symbol_registry,
@@ -338,7 +363,7 @@ impl StaticlibArtifact {
         let mut finished_functions = PrimaryMap::new();
 
         let engine_inner = engine.inner();
-        let signature_registry = engine_inner.signatures();
+        let signature_registry = engine_inner.signatures().clone();
         let func_data_registry = engine_inner.func_data().clone();
         let mut sig_map: BTreeMap<SignatureIndex, VMSharedSignatureIndex> = BTreeMap::new();
 
@@ -347,8 +372,10 @@ impl StaticlibArtifact {
         for i in 0..num_imported_functions {
             let sig_idx = metadata.compile_info.module.functions[FunctionIndex::new(i)];
             let func_type = &metadata.compile_info.module.signatures[sig_idx];
-            let vm_shared_idx = signature_registry.register(&func_type);
-            sig_map.insert(sig_idx, vm_shared_idx);
+            // Only take one reference per signature, released on drop.
+            sig_map
+                .entry(sig_idx)
+                .or_insert_with(|| signature_registry.register(func_type));
         }
         // read finished functions in order now...
         for i in 0..num_finished_functions {
@@ -356,8 +383,10 @@ impl StaticlibArtifact {
             let func_idx = metadata.compile_info.module.func_index(local_func_idx);
             let sig_idx = metadata.compile_info.module.functions[func_idx];
             let func_type = &metadata.compile_info.module.signatures[sig_idx];
-            let vm_shared_idx = signature_registry.register(&func_type);
-            sig_map.insert(sig_idx, vm_shared_idx);
+            // Only take one reference per signature, released on drop.
+            sig_map
+                .entry(sig_idx)
+                .or_insert_with(|| signature_registry.register(func_type));
 
             byte_buffer[0..WORD_SIZE]
                 .clone_from_slice(&bytes[cur_offset..(cur_offset + WORD_SIZE)]);
@@ -419,6 +448,7 @@ impl StaticlibArtifact {
             finished_dynamic_function_trampolines: finished_dynamic_function_trampolines
                 .into_boxed_slice(),
             signatures: signatures.into_boxed_slice(),
+            signature_registry,
             func_data_registry,
             metadata_length: 0,
             symbol_registry,
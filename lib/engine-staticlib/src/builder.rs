@@ -6,6 +6,7 @@ pub struct Staticlib {
     compiler_config: Option<Box<dyn CompilerConfig>>,
     target: Option<Target>,
     features: Option<Features>,
+    function_sections: bool,
 }
 
 impl Staticlib {
@@ -22,6 +23,7 @@ impl Staticlib {
             compiler_config: Some(compiler_config),
             target: None,
             features: None,
+            function_sections: false,
         }
     }
 
@@ -31,6 +33,7 @@ impl Staticlib {
             compiler_config: None,
             target: None,
             features: None,
+            function_sections: false,
         }
     }
 
@@ -41,11 +44,22 @@ impl Staticlib {
     }
 
     /// Set the features
+    ///
+    /// The features the compiler can't compile for the target are
+    /// disabled when the engine is built.
     pub fn features(mut self, features: Features) -> Self {
         self.features = Some(features);
         self
     }
 
+    /// Emit each function in its own section, named
+    /// `.text.wasm.<symbol name>`, so that linkers can remove the unused
+    /// ones, for example with `--gc-sections`.
+    pub fn function_sections(mut self, enabled: bool) -> Self {
+        self.function_sections = enabled;
+        self
+    }
+
     /// Build the `StaticlibEngine` for this configuration
     pub fn engine(self) -> StaticlibEngine {
         if let Some(_compiler_config) = self.compiler_config {
@@ -55,9 +69,12 @@ impl Staticlib {
                 let target = self.target.unwrap_or_default();
                 let features = self
                     .features
-                    .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
+                    .unwrap_or_else(|| compiler_config.default_features_for_target(&target))
+                    .intersection(&compiler_config.supported_features_for_target(&target));
                 let compiler = compiler_config.compiler();
-                StaticlibEngine::new(compiler, target, features)
+                let mut engine = StaticlibEngine::new(compiler, target, features);
+                engine.set_function_sections(self.function_sections);
+                engine
             }
 
             #[cfg(not(feature = "compiler"))]
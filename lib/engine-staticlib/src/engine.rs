@@ -3,9 +3,9 @@ use loupe::MemoryUsage;
 use std::io::Read;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
-#[cfg(feature = "compiler")]
-use wasmer_compiler::Compiler;
 use wasmer_compiler::{CompileError, Target};
+#[cfg(feature = "compiler")]
+use wasmer_compiler::{Compiler, ValidationLimits};
 use wasmer_engine::{Artifact, DeserializeError, Engine, EngineId, Tunables};
 #[cfg(feature = "compiler")]
 use wasmer_types::Features;
@@ -30,10 +30,11 @@ impl StaticlibEngine {
         Self {
             inner: Arc::new(Mutex::new(StaticlibEngineInner {
                 compiler: Some(compiler),
-                signatures: SignatureRegistry::new(),
+                signatures: Arc::new(SignatureRegistry::new()),
                 func_data: Arc::new(FuncDataRegistry::new()),
                 prefixer: None,
                 features,
+                function_sections: false,
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
@@ -60,7 +61,9 @@ impl StaticlibEngine {
                 compiler: None,
                 #[cfg(feature = "compiler")]
                 features: Features::default(),
-                signatures: SignatureRegistry::new(),
+                #[cfg(feature = "compiler")]
+                function_sections: false,
+                signatures: Arc::new(SignatureRegistry::new()),
                 func_data: Arc::new(FuncDataRegistry::new()),
                 prefixer: None,
             })),
@@ -88,6 +91,14 @@ impl StaticlibEngine {
         inner.prefixer = Some(Box::new(prefixer));
     }
 
+    /// Emits each function in its own section of the generated object,
+    /// so that linkers can remove the unused ones.
+    #[cfg(feature = "compiler")]
+    pub fn set_function_sections(&mut self, enabled: bool) {
+        let mut inner = self.inner_mut();
+        inner.function_sections = enabled;
+    }
+
     pub(crate) fn inner(&self) -> std::sync::MutexGuard<'_, StaticlibEngineInner> {
         self.inner.lock().unwrap()
     }
@@ -120,6 +131,11 @@ impl Engine for StaticlibEngine {
         compiler.signatures().lookup(sig)
     }
 
+    /// The shared signature registry
+    fn signature_registry(&self) -> Arc<SignatureRegistry> {
+        self.inner().signatures().clone()
+    }
+
     /// Validates a WebAssembly module
     fn validate(&self, binary: &[u8]) -> Result<(), CompileError> {
         self.inner().validate(binary)
@@ -188,9 +204,13 @@ pub struct StaticlibEngineInner {
     #[cfg(feature = "compiler")]
     features: Features,
 
+    /// Whether each function is emitted in its own section
+    #[cfg(feature = "compiler")]
+    function_sections: bool,
+
     /// The signature registry is used mainly to operate with trampolines
     /// performantly.
-    signatures: SignatureRegistry,
+    signatures: Arc<SignatureRegistry>,
 
     /// The backing storage of `VMFuncRef`s. This centralized store ensures that 2
     /// functions with the same `VMCallerCheckedAnyfunc` will have the same `VMFuncRef`.
@@ -231,10 +251,16 @@ impl StaticlibEngineInner {
         &self.features
     }
 
+    #[cfg(feature = "compiler")]
+    pub(crate) fn function_sections(&self) -> bool {
+        self.function_sections
+    }
+
     /// Validate the module
     #[cfg(feature = "compiler")]
     pub fn validate<'data>(&self, data: &'data [u8]) -> Result<(), CompileError> {
-        self.compiler()?.validate_module(self.features(), data)
+        self.compiler()?
+            .validate_module(self.features(), &ValidationLimits::default(), data)
     }
 
     /// Validate the module
@@ -246,7 +272,7 @@ impl StaticlibEngineInner {
     }
 
     /// Shared signature registry.
-    pub fn signatures(&self) -> &SignatureRegistry {
+    pub fn signatures(&self) -> &Arc<SignatureRegistry> {
         &self.signatures
     }
 
@@ -5,10 +5,18 @@ use crate::engine::{UniversalEngine, UniversalEngineInner};
 use crate::link::link_module;
 use enumset::EnumSet;
 use loupe::MemoryUsage;
+use memmap2::Mmap;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 #[cfg(feature = "compiler")]
+use wasmer_compiler::CancellationToken;
+#[cfg(feature = "compiler")]
 use wasmer_compiler::ModuleEnvironment;
-use wasmer_compiler::{CompileError, CpuFeature, Features, Triple};
+use wasmer_compiler::{
+    CompileError, CompiledFunctionFrameInfo, CpuFeature, CustomSectionProtection, Features,
+    FunctionBody, FunctionBodyRef, SectionIndex, Triple,
+};
 use wasmer_engine::{
     register_frame_info, Artifact, DeserializeError, FunctionExtent, GlobalFrameInfoRegistration,
     MetadataHeader, SerializeError,
@@ -16,15 +24,17 @@ use wasmer_engine::{
 #[cfg(feature = "compiler")]
 use wasmer_engine::{Engine, Tunables};
 use wasmer_engine_universal_artifact::ArtifactCreate;
-use wasmer_engine_universal_artifact::{SerializableModule, UniversalArtifactBuild};
-use wasmer_types::entity::{BoxedSlice, PrimaryMap};
+use wasmer_engine_universal_artifact::{
+    ArchivedSerializableModule, SerializableModule, UniversalArtifactBuild,
+};
+use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
     FunctionIndex, LocalFunctionIndex, MemoryIndex, ModuleInfo, OwnedDataInitializer,
     SignatureIndex, TableIndex,
 };
 use wasmer_vm::{
-    FuncDataRegistry, FunctionBodyPtr, MemoryStyle, TableStyle, VMSharedSignatureIndex,
-    VMTrampoline,
+    FuncDataRegistry, FunctionBodyPtr, MemoryStyle, SignatureRegistry, TableStyle,
+    VMSharedSignatureIndex, VMTrampoline,
 };
 
 /// A compiled wasm module, ready to be instantiated.
@@ -36,9 +46,137 @@ pub struct UniversalArtifact {
     finished_function_call_trampolines: BoxedSlice<SignatureIndex, VMTrampoline>,
     finished_dynamic_function_trampolines: BoxedSlice<FunctionIndex, FunctionBodyPtr>,
     signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
+    #[loupe(skip)]
+    signature_registry: Arc<SignatureRegistry>,
     func_data_registry: Arc<FuncDataRegistry>,
     frame_info_registration: Mutex<Option<GlobalFrameInfoRegistration>>,
     finished_function_lengths: BoxedSlice<LocalFunctionIndex, usize>,
+    /// The file the artifact was deserialized from, which holds its
+    /// code rather than `artifact`.
+    #[loupe(skip)]
+    serialized: Option<Mmap>,
+}
+
+/// The code of an artifact, borrowed to be copied in executable memory.
+pub(crate) struct ArtifactCode<'a> {
+    pub(crate) function_bodies: PrimaryMap<LocalFunctionIndex, FunctionBodyRef<'a>>,
+    pub(crate) function_call_trampolines: PrimaryMap<SignatureIndex, FunctionBodyRef<'a>>,
+    pub(crate) dynamic_function_trampolines: PrimaryMap<FunctionIndex, FunctionBodyRef<'a>>,
+    pub(crate) custom_sections: PrimaryMap<SectionIndex, (&'a CustomSectionProtection, &'a [u8])>,
+}
+
+impl<'a> ArtifactCode<'a> {
+    /// The code held by `artifact`.
+    fn from_artifact(artifact: &'a UniversalArtifactBuild) -> Self {
+        Self {
+            function_bodies: artifact
+                .get_function_bodies_ref()
+                .values()
+                .map(FunctionBodyRef::from)
+                .collect(),
+            function_call_trampolines: artifact
+                .get_function_call_trampolines_ref()
+                .values()
+                .map(FunctionBodyRef::from)
+                .collect(),
+            dynamic_function_trampolines: artifact
+                .get_dynamic_function_trampolines_ref()
+                .values()
+                .map(FunctionBodyRef::from)
+                .collect(),
+            custom_sections: artifact
+                .get_custom_sections_ref()
+                .values()
+                .map(|section| (&section.protection, section.bytes.as_slice()))
+                .collect(),
+        }
+    }
+
+    /// The code read in place from `archived`, for an `artifact`
+    /// deserialized from it without its code.
+    fn from_archive(
+        artifact: &'a UniversalArtifactBuild,
+        archived: &'a ArchivedSerializableModule,
+    ) -> Self {
+        fn in_place<'a, K: EntityRef>(
+            functions: &'a PrimaryMap<K, FunctionBody>,
+            bodies: impl Iterator<Item = &'a [u8]>,
+        ) -> PrimaryMap<K, FunctionBodyRef<'a>> {
+            functions
+                .values()
+                .zip(bodies)
+                .map(|(function, body)| FunctionBodyRef {
+                    body,
+                    unwind_info: function.unwind_info.as_ref(),
+                })
+                .collect()
+        }
+
+        Self {
+            function_bodies: in_place(
+                artifact.get_function_bodies_ref(),
+                archived.function_bodies(),
+            ),
+            function_call_trampolines: in_place(
+                artifact.get_function_call_trampolines_ref(),
+                archived.function_call_trampolines(),
+            ),
+            dynamic_function_trampolines: in_place(
+                artifact.get_dynamic_function_trampolines_ref(),
+                archived.dynamic_function_trampolines(),
+            ),
+            custom_sections: artifact
+                .get_custom_sections_ref()
+                .values()
+                .zip(archived.custom_sections())
+                .map(|(section, bytes)| (&section.protection, bytes))
+                .collect(),
+        }
+    }
+}
+
+impl<'a> ArtifactCode<'a> {
+    /// Feeds what determines the linked code of `artifact` to `hasher`:
+    /// its code and the relocations applied to it.
+    pub(crate) fn hash_linking_inputs(
+        &self,
+        artifact: &UniversalArtifactBuild,
+        hasher: &mut impl Hasher,
+    ) {
+        for function in self
+            .function_bodies
+            .values()
+            .chain(self.function_call_trampolines.values())
+            .chain(self.dynamic_function_trampolines.values())
+        {
+            function.body.hash(hasher);
+        }
+        for (protection, bytes) in self.custom_sections.values() {
+            (**protection == CustomSectionProtection::ReadExecute).hash(hasher);
+            bytes.hash(hasher);
+        }
+        let relocations = artifact.get_function_relocations();
+        for relocation in relocations
+            .values()
+            .chain(artifact.get_custom_section_relocations_ref().values())
+            .flatten()
+        {
+            format!("{:?}", relocation.kind).hash(hasher);
+            format!("{:?}", relocation.reloc_target).hash(hasher);
+            relocation.offset.hash(hasher);
+            relocation.addend.hash(hasher);
+        }
+        artifact.get_libcall_trampolines().hash(hasher);
+        artifact.get_libcall_trampoline_len().hash(hasher);
+    }
+}
+
+impl Drop for UniversalArtifact {
+    fn drop(&mut self) {
+        for signature in self.signatures.values() {
+            self.signature_registry.unregister(*signature);
+        }
+    }
 }
 
 impl UniversalArtifact {
@@ -48,6 +186,7 @@ impl UniversalArtifact {
         engine: &UniversalEngine,
         data: &[u8],
         tunables: &dyn Tunables,
+        cancellation: &CancellationToken,
     ) -> Result<Self, CompileError> {
         let environ = ModuleEnvironment::new();
         let mut inner_engine = engine.inner_mut();
@@ -70,6 +209,7 @@ impl UniversalArtifact {
             engine.target(),
             memory_styles,
             table_styles,
+            cancellation,
         )?;
 
         Self::from_parts(&mut inner_engine, artifact)
@@ -106,37 +246,102 @@ impl UniversalArtifact {
         Self::from_parts(&mut inner_engine, artifact).map_err(DeserializeError::Compiler)
     }
 
+    /// Deserialize a UniversalArtifactBuild from a file.
+    ///
+    /// The file is mapped in memory, and the code is copied from it
+    /// straight to executable memory: only the metadata of the module is
+    /// deserialized, so loading it takes a time proportional to its
+    /// relocations rather than to its code. The mapping is kept to
+    /// serialize the artifact again.
+    ///
+    /// # Safety
+    /// This function is unsafe because rkyv reads directly without validating
+    /// the data, and the file must not be modified while it's mapped.
+    pub unsafe fn deserialize_from_file(
+        engine: &UniversalEngine,
+        path: &Path,
+    ) -> Result<Self, DeserializeError> {
+        let file = std::fs::File::open(path)?;
+        let mmap = Mmap::map(&file)?;
+        if !UniversalArtifactBuild::is_deserializable(&mmap) {
+            return Err(DeserializeError::Incompatible(
+                "The provided bytes are not wasmer-universal".to_string(),
+            ));
+        }
+        let mut artifact = {
+            let bytes = &mmap[UniversalArtifactBuild::MAGIC_HEADER.len()..];
+            let metadata_len = MetadataHeader::parse(bytes)?;
+            let metadata_slice: &[u8] = &bytes[MetadataHeader::LEN..][..metadata_len];
+            let archived = SerializableModule::archive_from_slice(metadata_slice)?;
+            let serializable = SerializableModule::deserialize_metadata_from_archive(archived)?;
+            let artifact = UniversalArtifactBuild::from_serializable(serializable);
+            let mut inner_engine = engine.inner_mut();
+            Self::from_code(&mut inner_engine, artifact, Some(archived))
+                .map_err(DeserializeError::Compiler)?
+        };
+        artifact.serialized = Some(mmap);
+        Ok(artifact)
+    }
+
     /// Construct a `UniversalArtifactBuild` from component parts.
     pub fn from_parts(
         engine_inner: &mut UniversalEngineInner,
         artifact: UniversalArtifactBuild,
     ) -> Result<Self, CompileError> {
+        Self::from_code(engine_inner, artifact, None)
+    }
+
+    /// Construct a `UniversalArtifact` from component parts, with the
+    /// code read in place from `archived` if the `artifact` was
+    /// deserialized from it without its code.
+    fn from_code(
+        engine_inner: &mut UniversalEngineInner,
+        artifact: UniversalArtifactBuild,
+        archived: Option<&ArchivedSerializableModule>,
+    ) -> Result<Self, CompileError> {
+        let (allocated, eh_frame, shared) = {
+            let code = match archived {
+                Some(archived) => ArtifactCode::from_archive(&artifact, archived),
+                None => ArtifactCode::from_artifact(&artifact),
+            };
+            let eh_frame = artifact
+                .get_debug_ref()
+                .as_ref()
+                .map(|debug| (debug.eh_frame, code.custom_sections[debug.eh_frame].1.len()));
+            let shared = engine_inner.shared_code(&artifact, &code);
+            (
+                engine_inner.allocate(artifact.module_ref(), &code, shared.as_ref())?,
+                eh_frame,
+                shared,
+            )
+        };
         let (
             finished_functions,
             finished_function_call_trampolines,
             finished_dynamic_function_trampolines,
             custom_sections,
-        ) = engine_inner.allocate(
-            artifact.module_ref(),
-            artifact.get_function_bodies_ref(),
-            artifact.get_function_call_trampolines_ref(),
-            artifact.get_dynamic_function_trampolines_ref(),
-            artifact.get_custom_sections_ref(),
-        )?;
-
-        link_module(
-            artifact.module_ref(),
-            &finished_functions,
-            artifact.get_function_relocations().clone(),
-            &custom_sections,
-            artifact.get_custom_section_relocations_ref(),
-            artifact.get_libcall_trampolines(),
-            artifact.get_libcall_trampoline_len(),
-        );
+            linked,
+        ) = allocated;
+
+        if !linked {
+            link_module(
+                artifact.module_ref(),
+                &finished_functions,
+                artifact.get_function_relocations().clone(),
+                &custom_sections,
+                artifact.get_custom_section_relocations_ref(),
+                artifact.get_libcall_trampolines(),
+                artifact.get_libcall_trampoline_len(),
+                engine_inner.relocation_hook().as_deref(),
+            )?;
+            if let Some(shared) = &shared {
+                engine_inner.share_compiled_code(shared);
+            }
+        }
 
         // Compute indices into the shared signature table.
+        let signature_registry = engine_inner.signatures().clone();
         let signatures = {
-            let signature_registry = engine_inner.signatures();
             artifact
                 .module()
                 .signatures
@@ -145,18 +350,10 @@ impl UniversalArtifact {
                 .collect::<PrimaryMap<_, _>>()
         };
 
-        let eh_frame = match artifact.get_debug_ref() {
-            Some(debug) => {
-                let eh_frame_section_size = artifact.get_custom_sections_ref()[debug.eh_frame]
-                    .bytes
-                    .len();
-                let eh_frame_section_pointer = custom_sections[debug.eh_frame];
-                Some(unsafe {
-                    std::slice::from_raw_parts(*eh_frame_section_pointer, eh_frame_section_size)
-                })
-            }
-            None => None,
-        };
+        let eh_frame = eh_frame.map(|(eh_frame_section, eh_frame_section_size)| {
+            let eh_frame_section_pointer = custom_sections[eh_frame_section];
+            unsafe { std::slice::from_raw_parts(*eh_frame_section_pointer, eh_frame_section_size) }
+        });
 
         // Make all code compiled thus far executable.
         engine_inner.publish_compiled_code();
@@ -186,9 +383,11 @@ impl UniversalArtifact {
             finished_function_call_trampolines,
             finished_dynamic_function_trampolines,
             signatures,
+            signature_registry,
             frame_info_registration: Mutex::new(None),
             finished_function_lengths,
             func_data_registry,
+            serialized: None,
         })
     }
     /// Get the default extension when serializing this artifact
@@ -235,7 +434,10 @@ impl ArtifactCreate for UniversalArtifact {
     }
 
     fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
-        self.artifact.serialize()
+        match &self.serialized {
+            Some(serialized) => Ok(serialized.to_vec()),
+            None => self.artifact.serialize(),
+        }
     }
 }
 
@@ -268,6 +470,14 @@ impl Artifact for UniversalArtifact {
         &self.finished_functions
     }
 
+    fn finished_function_lengths(&self) -> Option<&BoxedSlice<LocalFunctionIndex, usize>> {
+        Some(&self.finished_function_lengths)
+    }
+
+    fn frame_infos(&self) -> Option<&PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>> {
+        Some(self.artifact.get_frame_info_ref())
+    }
+
     fn finished_function_call_trampolines(&self) -> &BoxedSlice<SignatureIndex, VMTrampoline> {
         &self.finished_function_call_trampolines
     }
@@ -1,5 +1,7 @@
-use crate::UniversalEngine;
-use wasmer_compiler::{CompilerConfig, Features, Target};
+use crate::{CodeAlignment, RelocationHook, UniversalEngine};
+use std::path::PathBuf;
+use std::sync::Arc;
+use wasmer_compiler::{CompilerConfig, Features, Target, ValidationLimits};
 
 /// The Universal builder
 pub struct Universal {
@@ -7,6 +9,11 @@ pub struct Universal {
     compiler_config: Option<Box<dyn CompilerConfig>>,
     target: Option<Target>,
     features: Option<Features>,
+    validation_limits: ValidationLimits,
+    relocation_hook: Option<Arc<dyn RelocationHook>>,
+    shared_code_dir: Option<PathBuf>,
+    code_alignment: CodeAlignment,
+    dedup_code: bool,
 }
 
 impl Universal {
@@ -19,6 +26,11 @@ impl Universal {
             compiler_config: Some(compiler_config.into()),
             target: None,
             features: None,
+            validation_limits: ValidationLimits::default(),
+            relocation_hook: None,
+            shared_code_dir: None,
+            code_alignment: CodeAlignment::default(),
+            dedup_code: false,
         }
     }
 
@@ -28,6 +40,11 @@ impl Universal {
             compiler_config: None,
             target: None,
             features: None,
+            validation_limits: ValidationLimits::default(),
+            relocation_hook: None,
+            shared_code_dir: None,
+            code_alignment: CodeAlignment::default(),
+            dedup_code: false,
         }
     }
 
@@ -38,29 +55,117 @@ impl Universal {
     }
 
     /// Set the features
+    ///
+    /// The features the compiler can't compile for the target are
+    /// disabled when the engine is built.
     pub fn features(mut self, features: Features) -> Self {
         self.features = Some(features);
         self
     }
 
+    /// Set the limits on the shape of the functions of the modules,
+    /// checked when they are validated
+    pub fn validation_limits(mut self, limits: ValidationLimits) -> Self {
+        self.validation_limits = limits;
+        self
+    }
+
+    /// Set the hook called as the code of the modules is linked, to
+    /// observe and patch it
+    pub fn relocation_hook(mut self, hook: impl RelocationHook + 'static) -> Self {
+        self.relocation_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Share the linked code of the modules with the other processes
+    /// using the same directory.
+    ///
+    /// The code of each module is saved to a file in `dir` once linked,
+    /// and the processes loading the same module map that file rather
+    /// than linking their own copy, so that they use the same physical
+    /// pages. `dir` should be on a memory-backed filesystem that allows
+    /// executing mapped files, such as `/dev/shm` on Linux when it isn't
+    /// mounted `noexec`.
+    ///
+    /// The linked code refers to the runtime, so it's only shared between
+    /// processes of the same binary loaded at the same address: typically
+    /// workers forked from the same parent. Code is only shared on Unix
+    /// platforms; if it can't be, each process keeps a private copy.
+    ///
+    /// The files in `dir` are mapped as executable code, so `dir` must
+    /// only be writable by users trusted to run code in these processes.
+    /// A file is only mapped if it's owned by the effective user of the
+    /// process, isn't writable by anyone else, and was saved from the
+    /// same code and libcalls as the module being loaded.
+    pub fn shared_code_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.shared_code_dir = Some(dir.into());
+        self
+    }
+
+    /// Set the alignment of functions and loops in the generated code,
+    /// to tune it for the instruction cache of the target CPU
+    ///
+    /// The alignment of loops is passed on to the compiler, which may
+    /// ignore it.
+    pub fn code_alignment(mut self, alignment: CodeAlignment) -> Self {
+        self.code_alignment = alignment;
+        self
+    }
+
+    /// Deduplicate the code of the modules compiled by the engine.
+    ///
+    /// Compiling the same module bytes again, for example for another
+    /// store sharing the engine, then returns the artifact compiled the
+    /// first time as long as a module still uses it, rather than a copy
+    /// of its code. The artifact is only shared between stores whose
+    /// tunables give the same memory and table styles. The statistics on
+    /// the deduplication are given by
+    /// [`UniversalEngine::code_dedup_stats`].
+    pub fn dedup_code(mut self, enable: bool) -> Self {
+        self.dedup_code = enable;
+        self
+    }
+
     /// Build the `UniversalEngine` for this configuration
     #[cfg(feature = "compiler")]
     pub fn engine(self) -> UniversalEngine {
         let target = self.target.unwrap_or_default();
-        if let Some(compiler_config) = self.compiler_config {
+        let engine = if let Some(mut compiler_config) = self.compiler_config {
+            if let Some(alignment) = self.code_alignment.loop_header {
+                compiler_config.loop_alignment(alignment);
+            }
             let features = self
                 .features
-                .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
+                .unwrap_or_else(|| compiler_config.default_features_for_target(&target))
+                .intersection(&compiler_config.supported_features_for_target(&target));
             let compiler = compiler_config.compiler();
             UniversalEngine::new(compiler, target, features)
         } else {
             UniversalEngine::headless()
-        }
+        };
+        engine.inner_mut().set_relocation_hook(self.relocation_hook);
+        engine.inner_mut().set_shared_code_dir(self.shared_code_dir);
+        engine.inner_mut().set_code_alignment(self.code_alignment);
+        engine.inner_mut().set_dedup_code(self.dedup_code);
+        engine
+            .inner_mut()
+            .builder_mut()
+            .set_validation_limits(self.validation_limits);
+        engine
     }
 
     /// Build the `UniversalEngine` for this configuration
     #[cfg(not(feature = "compiler"))]
     pub fn engine(self) -> UniversalEngine {
-        UniversalEngine::headless()
+        let engine = UniversalEngine::headless();
+        engine.inner_mut().set_relocation_hook(self.relocation_hook);
+        engine.inner_mut().set_shared_code_dir(self.shared_code_dir);
+        engine.inner_mut().set_code_alignment(self.code_alignment);
+        engine.inner_mut().set_dedup_code(self.dedup_code);
+        engine
+            .inner_mut()
+            .builder_mut()
+            .set_validation_limits(self.validation_limits);
+        engine
     }
 }
@@ -4,10 +4,19 @@
 //! Memory management for executable code.
 use crate::unwind::UnwindRegistry;
 use loupe::MemoryUsage;
-use wasmer_compiler::{CompiledFunctionUnwindInfo, CustomSection, FunctionBody};
+#[cfg(unix)]
+use std::fs::{File, OpenOptions};
+#[cfg(unix)]
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{FileExt, MetadataExt, OpenOptionsExt};
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+use wasmer_compiler::{CompiledFunctionUnwindInfo, FunctionBodyRef};
 use wasmer_vm::{Mmap, VMFunctionBody};
 
-/// The optimal alignment for functions.
+/// The default alignment for functions.
 ///
 /// On x86-64, this is 16 since it's what the optimizations assume.
 /// When we add support for other architectures, we should also figure out their
@@ -18,21 +27,106 @@ const ARCH_FUNCTION_ALIGNMENT: usize = 16;
 ///
 const DATA_SECTION_ALIGNMENT: usize = 64;
 
+/// The alignment of the code of the modules, to tune how it's laid out
+/// in the instruction cache of the CPU.
+///
+/// Aligning functions and loops to the fetch block or cache line size of
+/// a microarchitecture can speed up their execution, at the cost of
+/// larger code.
+///
+/// ```
+/// # use wasmer_engine_universal::CodeAlignment;
+/// let mut alignment = CodeAlignment::new();
+/// alignment.function(64).loop_header(32);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, MemoryUsage)]
+pub struct CodeAlignment {
+    /// The alignment in bytes of the start of functions: 16, 32 or 64.
+    /// Defaults to 16.
+    pub function: usize,
+    /// The alignment in bytes of the first instruction of loops, a power
+    /// of two, if not the default of the compiler. Loops are only aligned
+    /// in memory up to the alignment of functions.
+    pub loop_header: Option<usize>,
+    /// The number of bytes left free after each function, before the
+    /// next one is aligned. Defaults to 0.
+    pub function_padding: usize,
+}
+
+impl CodeAlignment {
+    /// Creates the default alignment.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the alignment in bytes of the start of functions.
+    ///
+    /// # Panics
+    ///
+    /// If `alignment` isn't 16, 32 or 64.
+    pub fn function(&mut self, alignment: usize) -> &mut Self {
+        assert!(
+            [16, 32, 64].contains(&alignment),
+            "the function alignment must be 16, 32 or 64 bytes"
+        );
+        self.function = alignment;
+        self
+    }
+
+    /// Sets the alignment in bytes of the first instruction of loops.
+    ///
+    /// # Panics
+    ///
+    /// If `alignment` isn't a power of two.
+    pub fn loop_header(&mut self, alignment: usize) -> &mut Self {
+        assert!(
+            alignment.is_power_of_two(),
+            "the loop alignment must be a power of two"
+        );
+        self.loop_header = Some(alignment);
+        self
+    }
+
+    /// Sets the number of bytes left free after each function.
+    pub fn function_padding(&mut self, padding: usize) -> &mut Self {
+        self.function_padding = padding;
+        self
+    }
+}
+
+impl Default for CodeAlignment {
+    fn default() -> Self {
+        Self {
+            function: ARCH_FUNCTION_ALIGNMENT,
+            loop_header: None,
+            function_padding: 0,
+        }
+    }
+}
+
 /// Memory manager for executable code.
 #[derive(MemoryUsage)]
 pub struct CodeMemory {
     unwind_registry: UnwindRegistry,
     mmap: Mmap,
     start_of_nonexecutable_pages: usize,
+    alignment: CodeAlignment,
 }
 
 impl CodeMemory {
     /// Create a new `CodeMemory` instance.
     pub fn new() -> Self {
+        Self::with_alignment(CodeAlignment::default())
+    }
+
+    /// Create a new `CodeMemory` instance laying out functions as per
+    /// `alignment`.
+    pub fn with_alignment(alignment: CodeAlignment) -> Self {
         Self {
             unwind_registry: UnwindRegistry::new(),
             mmap: Mmap::new(),
             start_of_nonexecutable_pages: 0,
+            alignment,
         }
     }
 
@@ -42,72 +136,247 @@ impl CodeMemory {
     }
 
     /// Allocate a single contiguous block of memory for the functions and custom sections, and copy the data in place.
+    #[allow(clippy::type_complexity)]
     pub fn allocate(
         &mut self,
-        functions: &[&FunctionBody],
-        executable_sections: &[&CustomSection],
-        data_sections: &[&CustomSection],
+        functions: &[FunctionBodyRef],
+        executable_sections: &[&[u8]],
+        data_sections: &[&[u8]],
     ) -> Result<(Vec<&mut [VMFunctionBody]>, Vec<&mut [u8]>, Vec<&mut [u8]>), String> {
-        let mut function_result = vec![];
-        let mut data_section_result = vec![];
-        let mut executable_section_result = vec![];
+        // 1. Allocate the pages. Mark them all read-write.
+        let total_len = self.total_len(functions, executable_sections, data_sections);
+        self.mmap = Mmap::with_at_least(total_len)?;
+
+        // 2. Copy the functions and sections in place.
+        Ok(self.place(functions, executable_sections, data_sections, true))
+    }
 
+    /// Maps the code saved to `shared` by [`CodeMemory::share`] instead
+    /// of allocating memory for the functions and custom sections, if it
+    /// was linked from the same inputs and can be mapped at the address
+    /// it was linked at.
+    ///
+    /// The file is only mapped if it's owned by the effective user of
+    /// this process and no one else can write to it.
+    ///
+    /// The returned functions and sections are already linked, and must
+    /// not be written to so that their pages stay shared.
+    #[cfg(unix)]
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn map_shared(
+        &mut self,
+        shared: &SharedCode,
+        functions: &[FunctionBodyRef],
+        executable_sections: &[&[u8]],
+        data_sections: &[&[u8]],
+    ) -> Option<(Vec<&mut [VMFunctionBody]>, Vec<&mut [u8]>, Vec<&mut [u8]>)> {
         let page_size = region::page::size();
+        let total_len = round_up(
+            self.total_len(functions, executable_sections, data_sections),
+            page_size,
+        );
+        let file = File::open(&shared.path).ok()?;
+        let metadata = file.metadata().ok()?;
+        if metadata.uid() != unsafe { libc::geteuid() } || metadata.mode() & 0o022 != 0 {
+            return None;
+        }
+        let mut header = [0u8; SHARED_HEADER_LEN];
+        file.read_exact_at(&mut header, 0).ok()?;
+        let (address, len, inputs_len) = SharedCodeHeader::parse(&header)?;
+        if len != total_len || inputs_len != shared.inputs.len() {
+            return None;
+        }
+        let code_offset = SharedCodeHeader::code_offset(inputs_len, page_size);
+        if metadata.len() != (code_offset + len) as u64 {
+            return None;
+        }
+        // The name of the file is only a hash of the inputs, so compare
+        // them in full
+        let mut inputs = vec![0u8; inputs_len];
+        file.read_exact_at(&mut inputs, SHARED_HEADER_LEN as u64)
+            .ok()?;
+        if inputs != shared.inputs {
+            return None;
+        }
 
-        // 1. Calculate the total size, that is:
-        // - function body size, including all trampolines
-        // -- windows unwind info
-        // -- padding between functions
-        // - executable section body
-        // -- padding between executable sections
-        // - padding until a new page to change page permissions
-        // - data section body size
-        // -- padding between data sections
+        let ptr = unsafe {
+            libc::mmap(
+                address as *mut libc::c_void,
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                code_offset as libc::off_t,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return None;
+        }
+        // The address is only a hint, the code can't be used anywhere else
+        let mmap = unsafe { Mmap::from_raw_parts(ptr as *mut u8, len) };
+        if ptr as usize != address {
+            return None;
+        }
+        self.mmap = mmap;
+        Some(self.place(functions, executable_sections, data_sections, false))
+    }
 
-        let total_len = round_up(
+    /// Saves the linked code to `shared`, along with the inputs it was
+    /// linked from, so that other processes can map it with
+    /// [`CodeMemory::map_shared`], then maps the file in place of the
+    /// code so that this process shares its pages too.
+    ///
+    /// This must be called before the code is published. Nothing is done
+    /// if the file already exists.
+    #[cfg(unix)]
+    pub(crate) fn share(&mut self, shared: &SharedCode) -> io::Result<()> {
+        let path = &shared.path;
+        if self.mmap.is_empty() || path.exists() {
+            return Ok(());
+        }
+        let page_size = region::page::size();
+        let code_offset = SharedCodeHeader::code_offset(shared.inputs.len(), page_size);
+        let mut header = vec![0u8; code_offset];
+        SharedCodeHeader::write(
+            &mut header,
+            self.mmap.as_ptr() as usize,
+            self.mmap.len(),
+            &shared.inputs,
+        );
+
+        // Write a temporary file first, so that the file at `path` is
+        // always complete
+        let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .mode(0o644)
+            .open(&tmp_path)?;
+        let result = (|| {
+            file.write_all(&header)?;
+            file.write_all(self.mmap.as_slice())?;
+            file.sync_all()?;
+
+            // Make sure code can be executed from the directory, as
+            // it may be mounted `noexec`
+            let probe = unsafe {
+                libc::mmap(
+                    std::ptr::null_mut(),
+                    page_size,
+                    libc::PROT_READ | libc::PROT_EXEC,
+                    libc::MAP_PRIVATE,
+                    file.as_raw_fd(),
+                    code_offset as libc::off_t,
+                )
+            };
+            if probe == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            unsafe { libc::munmap(probe, page_size) };
+
+            let ptr = unsafe {
+                libc::mmap(
+                    self.mmap.as_mut_ptr() as *mut libc::c_void,
+                    self.mmap.len(),
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_PRIVATE | libc::MAP_FIXED,
+                    file.as_raw_fd(),
+                    code_offset as libc::off_t,
+                )
+            };
+            if ptr == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            std::fs::rename(&tmp_path, path)
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp_path);
+        }
+        result
+    }
+
+    /// Calculates the size of the memory needed for the functions and
+    /// custom sections, that is:
+    /// - function body size, including all trampolines
+    ///   - windows unwind info
+    ///   - padding between functions
+    /// - executable section body
+    ///   - padding between executable sections
+    /// - padding until a new page to change page permissions
+    /// - data section body size
+    ///   - padding between data sections
+    fn total_len(
+        &self,
+        functions: &[FunctionBodyRef],
+        executable_sections: &[&[u8]],
+        data_sections: &[&[u8]],
+    ) -> usize {
+        let page_size = region::page::size();
+        let CodeAlignment {
+            function: alignment,
+            function_padding: padding,
+            ..
+        } = self.alignment;
+        round_up(
             functions.iter().fold(0, |acc, func| {
                 round_up(
-                    acc + Self::function_allocation_size(func),
-                    ARCH_FUNCTION_ALIGNMENT,
+                    acc + Self::function_allocation_size(func) + padding,
+                    alignment,
                 )
-            }) + executable_sections.iter().fold(0, |acc, exec| {
-                round_up(acc + exec.bytes.len(), ARCH_FUNCTION_ALIGNMENT)
-            }),
+            }) + executable_sections
+                .iter()
+                .fold(0, |acc, exec| round_up(acc + exec.len(), alignment)),
             page_size,
         ) + data_sections.iter().fold(0, |acc, data| {
-            round_up(acc + data.bytes.len(), DATA_SECTION_ALIGNMENT)
-        });
-
-        // 2. Allocate the pages. Mark them all read-write.
+            round_up(acc + data.len(), DATA_SECTION_ALIGNMENT)
+        })
+    }
 
-        self.mmap = Mmap::with_at_least(total_len)?;
+    /// Determine where the pointers to each function, executable section
+    /// or data section are in the allocated memory. Copy the functions
+    /// and sections there if `copy` is set. Collect the addresses of each
+    /// and return them.
+    #[allow(clippy::type_complexity)]
+    fn place(
+        &mut self,
+        functions: &[FunctionBodyRef],
+        executable_sections: &[&[u8]],
+        data_sections: &[&[u8]],
+        copy: bool,
+    ) -> (Vec<&mut [VMFunctionBody]>, Vec<&mut [u8]>, Vec<&mut [u8]>) {
+        let mut function_result = vec![];
+        let mut data_section_result = vec![];
+        let mut executable_section_result = vec![];
 
-        // 3. Determine where the pointers to each function, executable section
-        // or data section are. Copy the functions. Collect the addresses of each and return them.
+        let page_size = region::page::size();
+        let CodeAlignment {
+            function: alignment,
+            function_padding: padding,
+            ..
+        } = self.alignment;
 
         let mut bytes = 0;
         let mut buf = self.mmap.as_mut_slice();
         for func in functions {
-            let len = round_up(
-                Self::function_allocation_size(func),
-                ARCH_FUNCTION_ALIGNMENT,
-            );
+            let len = round_up(Self::function_allocation_size(func) + padding, alignment);
             let (func_buf, next_buf) = buf.split_at_mut(len);
             buf = next_buf;
             bytes += len;
 
-            let vmfunc = Self::copy_function(&mut self.unwind_registry, func, func_buf);
-            assert_eq!(vmfunc.as_ptr() as usize % ARCH_FUNCTION_ALIGNMENT, 0);
+            let vmfunc = Self::copy_function(&mut self.unwind_registry, func, func_buf, copy);
+            assert_eq!(vmfunc.as_ptr() as usize % alignment, 0);
             function_result.push(vmfunc);
         }
         for section in executable_sections {
-            let section = &section.bytes;
-            assert_eq!(buf.as_mut_ptr() as usize % ARCH_FUNCTION_ALIGNMENT, 0);
-            let len = round_up(section.len(), ARCH_FUNCTION_ALIGNMENT);
+            assert_eq!(buf.as_mut_ptr() as usize % alignment, 0);
+            let len = round_up(section.len(), alignment);
             let (s, next_buf) = buf.split_at_mut(len);
             buf = next_buf;
             bytes += len;
-            s[..section.len()].copy_from_slice(section.as_slice());
+            if copy {
+                s[..section.len()].copy_from_slice(section);
+            }
             executable_section_result.push(s);
         }
 
@@ -120,21 +389,22 @@ impl CodeMemory {
             buf = buf.split_at_mut(padding).1;
 
             for section in data_sections {
-                let section = &section.bytes;
                 assert_eq!(buf.as_mut_ptr() as usize % DATA_SECTION_ALIGNMENT, 0);
                 let len = round_up(section.len(), DATA_SECTION_ALIGNMENT);
                 let (s, next_buf) = buf.split_at_mut(len);
                 buf = next_buf;
-                s[..section.len()].copy_from_slice(section.as_slice());
+                if copy {
+                    s[..section.len()].copy_from_slice(section);
+                }
                 data_section_result.push(s);
             }
         }
 
-        Ok((
+        (
             function_result,
             executable_section_result,
             data_section_result,
-        ))
+        )
     }
 
     /// Apply the page permissions.
@@ -154,8 +424,8 @@ impl CodeMemory {
     }
 
     /// Calculates the allocation size of the given compiled function.
-    fn function_allocation_size(func: &FunctionBody) -> usize {
-        match &func.unwind_info {
+    fn function_allocation_size(func: &FunctionBodyRef) -> usize {
+        match func.unwind_info {
             Some(CompiledFunctionUnwindInfo::WindowsX64(info)) => {
                 // Windows unwind information is required to be emitted into code memory
                 // This is because it must be a positive relative offset from the start of the memory
@@ -166,23 +436,25 @@ impl CodeMemory {
         }
     }
 
-    /// Copies the data of the compiled function to the given buffer.
+    /// Copies the data of the compiled function to the given buffer, unless
+    /// `copy` is unset because it's already there.
     ///
     /// This will also add the function to the current function table.
     fn copy_function<'a>(
         registry: &mut UnwindRegistry,
-        func: &FunctionBody,
+        func: &FunctionBodyRef,
         buf: &'a mut [u8],
+        copy: bool,
     ) -> &'a mut [VMFunctionBody] {
-        assert_eq!(buf.as_ptr() as usize % ARCH_FUNCTION_ALIGNMENT, 0);
-
         let func_len = func.body.len();
 
         let (body, remainder) = buf.split_at_mut(func_len);
-        body.copy_from_slice(&func.body);
+        if copy {
+            body.copy_from_slice(func.body);
+        }
         let vmfunc = Self::view_as_mut_vmfunc_slice(body);
 
-        if let Some(CompiledFunctionUnwindInfo::WindowsX64(info)) = &func.unwind_info {
+        if let Some(CompiledFunctionUnwindInfo::WindowsX64(info)) = func.unwind_info {
             // Windows unwind information is written following the function body
             // Keep unwind information 32-bit aligned (round up to the nearest 4 byte boundary)
             let unwind_start = (func_len + 3) & !3;
@@ -190,10 +462,10 @@ impl CodeMemory {
             let padding = unwind_start - func_len;
             assert_eq!((func_len + padding) % 4, 0);
             let slice = remainder.split_at_mut(padding + unwind_size).0;
-            slice[padding..].copy_from_slice(&info);
+            slice[padding..].copy_from_slice(info);
         }
 
-        if let Some(info) = &func.unwind_info {
+        if let Some(info) = func.unwind_info {
             registry
                 .register(vmfunc.as_ptr() as usize, 0, func_len as u32, info)
                 .expect("failed to register unwind information");
@@ -210,6 +482,60 @@ impl CodeMemory {
     }
 }
 
+/// A file holding linked code shared between processes.
+#[cfg_attr(not(unix), allow(dead_code))]
+pub(crate) struct SharedCode {
+    /// The path of the file.
+    pub(crate) path: PathBuf,
+    /// Everything the linked code is derived from, which the file must
+    /// have been saved with to be mapped.
+    pub(crate) inputs: Vec<u8>,
+}
+
+/// The length of the header of the files holding shared code.
+#[cfg(unix)]
+const SHARED_HEADER_LEN: usize = 40;
+
+/// The header of the files holding shared code, followed by the inputs
+/// of the linking, then from the next page boundary by the code.
+#[cfg(unix)]
+struct SharedCodeHeader;
+
+#[cfg(unix)]
+impl SharedCodeHeader {
+    const MAGIC: &'static [u8; 16] = b"wasmer-shared-02";
+
+    /// The offset of the code in a file holding `inputs_len` bytes of
+    /// inputs.
+    fn code_offset(inputs_len: usize, page_size: usize) -> usize {
+        round_up(SHARED_HEADER_LEN + inputs_len, page_size)
+    }
+
+    /// Writes the header and the `inputs` for code linked at `address`
+    /// and spanning `len` bytes.
+    fn write(header: &mut [u8], address: usize, len: usize, inputs: &[u8]) {
+        header[..16].copy_from_slice(Self::MAGIC);
+        header[16..24].copy_from_slice(&(address as u64).to_le_bytes());
+        header[24..32].copy_from_slice(&(len as u64).to_le_bytes());
+        header[32..40].copy_from_slice(&(inputs.len() as u64).to_le_bytes());
+        header[SHARED_HEADER_LEN..SHARED_HEADER_LEN + inputs.len()].copy_from_slice(inputs);
+    }
+
+    /// Parses the address and length of the code, and the length of the
+    /// inputs, from `header`.
+    fn parse(header: &[u8; SHARED_HEADER_LEN]) -> Option<(usize, usize, usize)> {
+        if &header[..16] != Self::MAGIC {
+            return None;
+        }
+        let field = |offset: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&header[offset..offset + 8]);
+            u64::from_le_bytes(bytes) as usize
+        };
+        Some((field(16), field(24), field(32)))
+    }
+}
+
 fn round_up(size: usize, multiple: usize) -> usize {
     debug_assert!(multiple.is_power_of_two());
     (size + (multiple - 1)) & !(multiple - 1)
@@ -217,9 +543,37 @@ fn round_up(size: usize, multiple: usize) -> usize {
 
 #[cfg(test)]
 mod tests {
-    use super::CodeMemory;
+    use super::{CodeAlignment, CodeMemory};
+    use wasmer_compiler::FunctionBodyRef;
+
     fn _assert() {
         fn _assert_send_sync<T: Send + Sync>() {}
         _assert_send_sync::<CodeMemory>();
     }
+
+    #[test]
+    fn functions_are_aligned_and_padded() {
+        let mut alignment = CodeAlignment::new();
+        alignment.function(64).function_padding(40);
+        let mut code_memory = CodeMemory::with_alignment(alignment);
+        let bodies = [vec![0xc3u8; 30], vec![0xc3u8; 1], vec![0xc3u8; 100]];
+        let functions = bodies
+            .iter()
+            .map(|body| FunctionBodyRef {
+                body,
+                unwind_info: None,
+            })
+            .collect::<Vec<_>>();
+        let (functions, _, _) = code_memory.allocate(&functions, &[], &[]).unwrap();
+        let addresses = functions
+            .iter()
+            .map(|f| f.as_ptr() as usize)
+            .collect::<Vec<_>>();
+        for address in addresses.iter() {
+            assert_eq!(address % 64, 0);
+        }
+        // 30 + 40 bytes, then 1 + 40 bytes
+        assert_eq!(addresses[1] - addresses[0], 128);
+        assert_eq!(addresses[2] - addresses[1], 64);
+    }
 }
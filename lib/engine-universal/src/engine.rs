@@ -1,20 +1,35 @@
 //! Universal compilation.
 
-use crate::CodeMemory;
+use crate::artifact::ArtifactCode;
+use crate::code_memory::SharedCode;
+use crate::relocation::RelocationHook;
 use crate::UniversalArtifact;
+use crate::{CodeAlignment, CodeMemory};
+use enum_iterator::IntoEnumIterator;
 use loupe::MemoryUsage;
-use std::sync::{Arc, Mutex};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "compiler")]
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+#[cfg(feature = "compiler")]
+use wasmer_compiler::CancellationToken;
 #[cfg(feature = "compiler")]
 use wasmer_compiler::Compiler;
-use wasmer_compiler::{
-    CompileError, CustomSection, CustomSectionProtection, FunctionBody, SectionIndex, Target,
-};
+#[cfg(feature = "plugin")]
+use wasmer_compiler::CompilerPlugin;
+use wasmer_compiler::{CompileError, CustomSectionProtection, SectionIndex, Target};
 use wasmer_engine::{Artifact, DeserializeError, Engine, EngineId, FunctionExtent, Tunables};
-use wasmer_engine_universal_artifact::UniversalEngineBuilder;
+#[cfg(feature = "compiler")]
+use wasmer_engine_universal_artifact::ArtifactCreate;
+use wasmer_engine_universal_artifact::{UniversalArtifactBuild, UniversalEngineBuilder};
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{
-    Features, FunctionIndex, FunctionType, LocalFunctionIndex, ModuleInfo, SignatureIndex,
+    Features, FunctionIndex, FunctionType, LibCall, LocalFunctionIndex, ModuleInfo, SignatureIndex,
 };
+use wasmer_vm::libcalls::function_pointer;
 use wasmer_vm::{
     FuncDataRegistry, FunctionBodyPtr, SectionBodyPtr, SignatureRegistry, VMCallerCheckedAnyfunc,
     VMFuncRef, VMFunctionBody, VMSharedSignatureIndex, VMTrampoline,
@@ -37,8 +52,14 @@ impl UniversalEngine {
             inner: Arc::new(Mutex::new(UniversalEngineInner {
                 builder: UniversalEngineBuilder::new(Some(compiler), features),
                 code_memory: vec![],
-                signatures: SignatureRegistry::new(),
+                signatures: Arc::new(SignatureRegistry::new()),
                 func_data: Arc::new(FuncDataRegistry::new()),
+                relocation_hook: None,
+                shared_code_dir: None,
+                code_alignment: CodeAlignment::default(),
+                dedup_code: false,
+                artifacts: HashMap::new(),
+                dedup_stats: CodeDedupStats::default(),
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
@@ -63,14 +84,34 @@ impl UniversalEngine {
             inner: Arc::new(Mutex::new(UniversalEngineInner {
                 builder: UniversalEngineBuilder::new(None, Features::default()),
                 code_memory: vec![],
-                signatures: SignatureRegistry::new(),
+                signatures: Arc::new(SignatureRegistry::new()),
                 func_data: Arc::new(FuncDataRegistry::new()),
+                relocation_hook: None,
+                shared_code_dir: None,
+                code_alignment: CodeAlignment::default(),
+                dedup_code: false,
+                artifacts: HashMap::new(),
+                dedup_stats: CodeDedupStats::default(),
             })),
             target: Arc::new(Target::default()),
             engine_id: EngineId::default(),
         }
     }
 
+    /// Replaces the compiler of the engine. The modules compiled from now
+    /// on don't share code with the ones compiled by the previous
+    /// compiler.
+    #[cfg(feature = "compiler")]
+    pub fn set_compiler(&self, compiler: Box<dyn Compiler>) {
+        self.inner_mut().set_compiler(compiler);
+    }
+
+    /// The statistics on the deduplication of the code of the modules
+    /// compiled by this engine.
+    pub fn code_dedup_stats(&self) -> CodeDedupStats {
+        self.inner().dedup_stats
+    }
+
     pub(crate) fn inner(&self) -> std::sync::MutexGuard<'_, UniversalEngineInner> {
         self.inner.lock().unwrap()
     }
@@ -103,11 +144,33 @@ impl Engine for UniversalEngine {
         compiler.signatures().lookup(sig)
     }
 
+    /// The shared signature registry
+    fn signature_registry(&self) -> Arc<SignatureRegistry> {
+        self.inner().signatures().clone()
+    }
+
     /// Validates a WebAssembly module
     fn validate(&self, binary: &[u8]) -> Result<(), CompileError> {
         self.inner().validate(binary)
     }
 
+    /// Validates a WebAssembly module while it is being read
+    ///
+    /// The engine isn't locked while reading, as `reader` may be slow or
+    /// use the engine itself.
+    #[cfg(feature = "compiler")]
+    fn validate_streaming(&self, reader: &mut dyn Read) -> Result<Vec<u8>, CompileError> {
+        let (compiler, features, limits) = {
+            let inner = self.inner();
+            (
+                inner.builder.compiler_handle()?,
+                inner.builder.features().clone(),
+                inner.builder.validation_limits().clone(),
+            )
+        };
+        compiler.validate_module_streaming(&features, &limits, reader)
+    }
+
     /// Compile a WebAssembly binary
     #[cfg(feature = "compiler")]
     fn compile(
@@ -115,7 +178,40 @@ impl Engine for UniversalEngine {
         binary: &[u8],
         tunables: &dyn Tunables,
     ) -> Result<Arc<dyn Artifact>, CompileError> {
-        Ok(Arc::new(UniversalArtifact::new(&self, binary, tunables)?))
+        self.compile_cancellable(binary, tunables, &CancellationToken::new())
+    }
+
+    /// Compile a WebAssembly binary, checking `cancellation` between
+    /// functions
+    #[cfg(feature = "compiler")]
+    fn compile_cancellable(
+        &self,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+        cancellation: &CancellationToken,
+    ) -> Result<Arc<dyn Artifact>, CompileError> {
+        let key = if self.inner().dedup_code() {
+            let mut hasher = DefaultHasher::new();
+            binary.hash(&mut hasher);
+            Some(hasher.finish())
+        } else {
+            None
+        };
+        if let Some(key) = key {
+            if let Some(artifact) = self.inner_mut().lookup_artifact(key, binary, tunables) {
+                return Ok(artifact);
+            }
+        }
+        let artifact = Arc::new(UniversalArtifact::new(
+            &self,
+            binary,
+            tunables,
+            cancellation,
+        )?);
+        if let Some(key) = key {
+            self.inner_mut().register_artifact(key, binary, &artifact);
+        }
+        Ok(artifact)
     }
 
     /// Compile a WebAssembly binary
@@ -136,6 +232,25 @@ impl Engine for UniversalEngine {
         Ok(Arc::new(UniversalArtifact::deserialize(&self, &bytes)?))
     }
 
+    /// Deserializes a WebAssembly module from a path, reading its code
+    /// in place from the mapped file
+    unsafe fn deserialize_from_file(
+        &self,
+        file_ref: &Path,
+    ) -> Result<Arc<dyn Artifact>, DeserializeError> {
+        Ok(Arc::new(UniversalArtifact::deserialize_from_file(
+            &self, file_ref,
+        )?))
+    }
+
+    /// Replaces the compiler by the one of a compiler plugin
+    #[cfg(feature = "plugin")]
+    fn register_compiler(&self, path: &Path) -> Result<(), CompileError> {
+        let compiler = unsafe { CompilerPlugin::load(path) }?.compiler();
+        self.set_compiler(compiler);
+        Ok(())
+    }
+
     fn id(&self) -> &EngineId {
         &self.engine_id
     }
@@ -155,11 +270,83 @@ pub struct UniversalEngineInner {
     code_memory: Vec<CodeMemory>,
     /// The signature registry is used mainly to operate with trampolines
     /// performantly.
-    signatures: SignatureRegistry,
+    signatures: Arc<SignatureRegistry>,
     /// The backing storage of `VMFuncRef`s. This centralized store ensures that 2
     /// functions with the same `VMCallerCheckedAnyfunc` will have the same `VMFuncRef`.
     /// It also guarantees that the `VMFuncRef`s stay valid until the engine is dropped.
     func_data: Arc<FuncDataRegistry>,
+    /// The hook called as the code of the modules is linked.
+    #[loupe(skip)]
+    relocation_hook: Option<Arc<dyn RelocationHook>>,
+    /// The directory holding the linked code shared between processes.
+    #[loupe(skip)]
+    shared_code_dir: Option<PathBuf>,
+    /// The alignment of the functions in code memory.
+    code_alignment: CodeAlignment,
+    /// Whether compiling the same module bytes again returns the artifact
+    /// compiled the first time.
+    dedup_code: bool,
+    /// The live artifacts compiled by the engine, by the hash of their
+    /// module bytes, when code is deduplicated.
+    #[loupe(skip)]
+    #[cfg_attr(not(feature = "compiler"), allow(dead_code))]
+    artifacts: HashMap<u64, Vec<DedupEntry>>,
+    /// The statistics on the deduplication of code.
+    dedup_stats: CodeDedupStats,
+}
+
+/// Statistics on the deduplication of the code of the modules compiled by
+/// a [`UniversalEngine`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, MemoryUsage)]
+pub struct CodeDedupStats {
+    /// The compilations which returned the artifact of identical module
+    /// bytes compiled before.
+    pub hits: u64,
+    /// The compilations which compiled a new artifact.
+    pub misses: u64,
+}
+
+/// An artifact compiled by the engine, returned again for the same module
+/// bytes as long as it's alive.
+#[cfg_attr(not(feature = "compiler"), allow(dead_code))]
+struct DedupEntry {
+    /// The module bytes, compared in full, since the hash used as key
+    /// isn't collision-resistant.
+    binary: Box<[u8]>,
+    artifact: Weak<UniversalArtifact>,
+}
+
+/// Records the bytes fed to it instead of hashing them, so that the
+/// inputs of the linking of shared code can be compared in full.
+#[derive(Default)]
+struct InputRecorder(Vec<u8>);
+
+impl Hasher for InputRecorder {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        unreachable!("the inputs are recorded, not hashed")
+    }
+}
+
+/// Whether `artifact` was compiled with the memory and table styles
+/// `tunables` would give its module, so that it can be instantiated by a
+/// store using them.
+#[cfg(feature = "compiler")]
+fn styles_match(artifact: &UniversalArtifact, tunables: &dyn Tunables) -> bool {
+    let module = artifact.module_ref();
+    module
+        .memories
+        .values()
+        .map(|memory_type| tunables.memory_style(memory_type))
+        .eq(artifact.memory_styles().values().cloned())
+        && module
+            .tables
+            .values()
+            .map(|table_type| tunables.table_style(table_type))
+            .eq(artifact.table_styles().values().cloned())
 }
 
 impl UniversalEngineInner {
@@ -169,6 +356,14 @@ impl UniversalEngineInner {
         self.builder.compiler()
     }
 
+    /// Replaces the compiler, forgetting the artifacts compiled by the
+    /// previous one so that they aren't returned for the same modules.
+    #[cfg(feature = "compiler")]
+    pub fn set_compiler(&mut self, compiler: Box<dyn Compiler>) {
+        self.builder.set_compiler(compiler);
+        self.artifacts.clear();
+    }
+
     /// Validate the module
     pub fn validate<'data>(&self, data: &'data [u8]) -> Result<(), CompileError> {
         self.builder.validate(data)
@@ -184,51 +379,85 @@ impl UniversalEngineInner {
     }
 
     /// Allocate compiled functions into memory
+    ///
+    /// If `shared` is the file holding the code already linked by
+    /// another process and it can be mapped, the code is mapped instead,
+    /// and the last returned value is `true` to tell it's already linked.
     #[allow(clippy::type_complexity)]
     pub(crate) fn allocate(
         &mut self,
         _module: &ModuleInfo,
-        functions: &PrimaryMap<LocalFunctionIndex, FunctionBody>,
-        function_call_trampolines: &PrimaryMap<SignatureIndex, FunctionBody>,
-        dynamic_function_trampolines: &PrimaryMap<FunctionIndex, FunctionBody>,
-        custom_sections: &PrimaryMap<SectionIndex, CustomSection>,
+        code: &ArtifactCode,
+        shared: Option<&SharedCode>,
     ) -> Result<
         (
             PrimaryMap<LocalFunctionIndex, FunctionExtent>,
             PrimaryMap<SignatureIndex, VMTrampoline>,
             PrimaryMap<FunctionIndex, FunctionBodyPtr>,
             PrimaryMap<SectionIndex, SectionBodyPtr>,
+            bool,
         ),
         CompileError,
     > {
-        let function_bodies = functions
+        let function_bodies = code
+            .function_bodies
             .values()
-            .chain(function_call_trampolines.values())
-            .chain(dynamic_function_trampolines.values())
+            .chain(code.function_call_trampolines.values())
+            .chain(code.dynamic_function_trampolines.values())
+            .copied()
             .collect::<Vec<_>>();
-        let (executable_sections, data_sections): (Vec<_>, _) = custom_sections
+        let (executable_sections, data_sections): (Vec<_>, Vec<_>) = code
+            .custom_sections
             .values()
-            .partition(|section| section.protection == CustomSectionProtection::ReadExecute);
-        self.code_memory.push(CodeMemory::new());
+            .copied()
+            .partition(|(protection, _)| **protection == CustomSectionProtection::ReadExecute);
+        let executable_sections = executable_sections
+            .into_iter()
+            .map(|(_, bytes)| bytes)
+            .collect::<Vec<_>>();
+        let data_sections = data_sections
+            .into_iter()
+            .map(|(_, bytes)| bytes)
+            .collect::<Vec<_>>();
+        self.code_memory
+            .push(CodeMemory::with_alignment(self.code_alignment));
+        let code_memory = self.code_memory.last_mut().unwrap();
+
+        #[cfg(unix)]
+        let mapped = shared.and_then(|shared| {
+            code_memory.map_shared(
+                shared,
+                function_bodies.as_slice(),
+                executable_sections.as_slice(),
+                data_sections.as_slice(),
+            )
+        });
+        #[cfg(not(unix))]
+        let mapped = {
+            let _ = shared;
+            None
+        };
+        let linked = mapped.is_some();
 
         let (mut allocated_functions, allocated_executable_sections, allocated_data_sections) =
-            self.code_memory
-                .last_mut()
-                .unwrap()
-                .allocate(
-                    function_bodies.as_slice(),
-                    executable_sections.as_slice(),
-                    data_sections.as_slice(),
-                )
-                .map_err(|message| {
-                    CompileError::Resource(format!(
-                        "failed to allocate memory for functions: {}",
-                        message
-                    ))
-                })?;
+            match mapped {
+                Some(mapped) => mapped,
+                None => code_memory
+                    .allocate(
+                        function_bodies.as_slice(),
+                        executable_sections.as_slice(),
+                        data_sections.as_slice(),
+                    )
+                    .map_err(|message| {
+                        CompileError::Resource(format!(
+                            "failed to allocate memory for functions: {}",
+                            message
+                        ))
+                    })?,
+            };
 
         let allocated_functions_result = allocated_functions
-            .drain(0..functions.len())
+            .drain(0..code.function_bodies.len())
             .map(|slice| FunctionExtent {
                 ptr: FunctionBodyPtr(slice.as_ptr()),
                 length: slice.len(),
@@ -238,7 +467,7 @@ impl UniversalEngineInner {
         let mut allocated_function_call_trampolines: PrimaryMap<SignatureIndex, VMTrampoline> =
             PrimaryMap::new();
         for ptr in allocated_functions
-            .drain(0..function_call_trampolines.len())
+            .drain(0..code.function_call_trampolines.len())
             .map(|slice| slice.as_ptr())
         {
             let trampoline =
@@ -253,11 +482,12 @@ impl UniversalEngineInner {
 
         let mut exec_iter = allocated_executable_sections.iter();
         let mut data_iter = allocated_data_sections.iter();
-        let allocated_custom_sections = custom_sections
-            .iter()
-            .map(|(_, section)| {
+        let allocated_custom_sections = code
+            .custom_sections
+            .values()
+            .map(|(protection, _)| {
                 SectionBodyPtr(
-                    if section.protection == CustomSectionProtection::ReadExecute {
+                    if **protection == CustomSectionProtection::ReadExecute {
                         exec_iter.next()
                     } else {
                         data_iter.next()
@@ -273,9 +503,22 @@ impl UniversalEngineInner {
             allocated_function_call_trampolines,
             allocated_dynamic_function_trampolines,
             allocated_custom_sections,
+            linked,
         ))
     }
 
+    /// Saves the code just linked to `shared` so that other processes
+    /// can map it rather than linking their own copy.
+    ///
+    /// Sharing is an optimization: if the code can't be saved, this
+    /// process keeps its private copy.
+    pub(crate) fn share_compiled_code(&mut self, shared: &SharedCode) {
+        #[cfg(unix)]
+        let _ = self.code_memory.last_mut().unwrap().share(shared);
+        #[cfg(not(unix))]
+        let _ = shared;
+    }
+
     /// Make memory containing compiled code executable.
     pub(crate) fn publish_compiled_code(&mut self) {
         self.code_memory.last_mut().unwrap().publish();
@@ -295,10 +538,132 @@ impl UniversalEngineInner {
     }
 
     /// Shared signature registry.
-    pub fn signatures(&self) -> &SignatureRegistry {
+    pub fn signatures(&self) -> &Arc<SignatureRegistry> {
         &self.signatures
     }
 
+    /// The hook called as the code of the modules is linked.
+    pub fn relocation_hook(&self) -> &Option<Arc<dyn RelocationHook>> {
+        &self.relocation_hook
+    }
+
+    /// Sets the hook called as the code of the modules is linked.
+    pub fn set_relocation_hook(&mut self, hook: Option<Arc<dyn RelocationHook>>) {
+        self.relocation_hook = hook;
+    }
+
+    /// The directory holding the linked code shared between processes.
+    pub fn shared_code_dir(&self) -> Option<&Path> {
+        self.shared_code_dir.as_deref()
+    }
+
+    /// Sets the directory holding the linked code shared between processes.
+    ///
+    /// The directory must only be writable by trusted users, see
+    /// [`Universal::shared_code_dir`](crate::Universal::shared_code_dir).
+    pub fn set_shared_code_dir(&mut self, dir: Option<PathBuf>) {
+        self.shared_code_dir = dir;
+    }
+
+    /// The alignment of the code of the modules.
+    pub fn code_alignment(&self) -> &CodeAlignment {
+        &self.code_alignment
+    }
+
+    /// Sets the alignment of the code of the modules loaded from now on.
+    pub fn set_code_alignment(&mut self, alignment: CodeAlignment) {
+        self.code_alignment = alignment;
+    }
+
+    /// Whether compiling the same module bytes again returns the artifact
+    /// compiled the first time.
+    pub fn dedup_code(&self) -> bool {
+        self.dedup_code
+    }
+
+    /// Sets whether compiling the same module bytes again returns the
+    /// artifact compiled the first time.
+    pub fn set_dedup_code(&mut self, dedup_code: bool) {
+        self.dedup_code = dedup_code;
+    }
+
+    /// The live artifact compiled from `binary`, whose hash is `key`, if
+    /// it can be used with `tunables`.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn lookup_artifact(
+        &mut self,
+        key: u64,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+    ) -> Option<Arc<UniversalArtifact>> {
+        let artifact = self
+            .artifacts
+            .get(&key)?
+            .iter()
+            .filter(|entry| &*entry.binary == binary)
+            .filter_map(|entry| entry.artifact.upgrade())
+            .find(|artifact| styles_match(artifact, tunables))?;
+        self.dedup_stats.hits += 1;
+        Some(artifact)
+    }
+
+    /// Keeps track of `artifact`, compiled from `binary` whose hash is
+    /// `key`, and forgets the artifacts which have been dropped.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn register_artifact(
+        &mut self,
+        key: u64,
+        binary: &[u8],
+        artifact: &Arc<UniversalArtifact>,
+    ) {
+        self.dedup_stats.misses += 1;
+        self.artifacts.retain(|_, entries| {
+            entries.retain(|entry| entry.artifact.strong_count() > 0);
+            !entries.is_empty()
+        });
+        self.artifacts.entry(key).or_default().push(DedupEntry {
+            binary: binary.into(),
+            artifact: Arc::downgrade(artifact),
+        });
+    }
+
+    /// The file holding the linked `code` of `artifact` shared between
+    /// processes, if code is shared.
+    ///
+    /// The code can only be shared between processes running the same
+    /// binary loaded at the same address, such as workers forked from the
+    /// same parent, since it refers to the libcalls of the runtime: the
+    /// inputs of the linking include the addresses of the libcalls along
+    /// with the artifact. The name of the file is a hash of the inputs,
+    /// and the file is only mapped if it was saved with the same inputs.
+    /// The code isn't shared when a relocation hook is set, as the hook
+    /// must observe the linking.
+    pub(crate) fn shared_code(
+        &self,
+        artifact: &UniversalArtifactBuild,
+        code: &ArtifactCode,
+    ) -> Option<SharedCode> {
+        if self.relocation_hook.is_some() {
+            return None;
+        }
+        let dir = self.shared_code_dir.as_ref()?;
+
+        let mut inputs = InputRecorder::default();
+        crate::VERSION.hash(&mut inputs);
+        self.code_alignment.hash(&mut inputs);
+        for libcall in LibCall::into_enum_iter() {
+            function_pointer(libcall).hash(&mut inputs);
+        }
+        code.hash_linking_inputs(artifact, &mut inputs);
+
+        let mut hasher = DefaultHasher::new();
+        inputs.0.hash(&mut hasher);
+        Some(SharedCode {
+            path: dir.join(format!("{:016x}.code", hasher.finish())),
+            inputs: inputs.0,
+        })
+    }
+
     /// Shared func metadata registry.
     pub(crate) fn func_data(&self) -> &Arc<FuncDataRegistry> {
         &self.func_data
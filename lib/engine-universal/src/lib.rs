@@ -28,13 +28,15 @@ mod builder;
 mod code_memory;
 mod engine;
 mod link;
+mod relocation;
 mod unwind;
 
 pub use crate::artifact::UniversalArtifact;
 pub use crate::builder::Universal;
-pub use crate::code_memory::CodeMemory;
-pub use crate::engine::UniversalEngine;
+pub use crate::code_memory::{CodeAlignment, CodeMemory};
+pub use crate::engine::{CodeDedupStats, UniversalEngine};
 pub use crate::link::link_module;
+pub use crate::relocation::{LinkedCode, PatchError, RelocationHook};
 
 /// Version number of this crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
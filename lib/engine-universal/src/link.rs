@@ -1,7 +1,10 @@
 //! Linking for Universal-compiled code.
 
+use crate::relocation::{is_reachable, LinkedCode, RelocationHook};
 use std::ptr::{read_unaligned, write_unaligned};
-use wasmer_compiler::{Relocation, RelocationKind, RelocationTarget, Relocations, SectionIndex};
+use wasmer_compiler::{
+    CompileError, Relocation, RelocationKind, RelocationTarget, Relocations, SectionIndex,
+};
 use wasmer_engine::FunctionExtent;
 use wasmer_engine_universal_artifact::get_libcall_trampoline;
 use wasmer_types::entity::PrimaryMap;
@@ -9,6 +12,7 @@ use wasmer_types::{LocalFunctionIndex, ModuleInfo};
 use wasmer_vm::libcalls::function_pointer;
 use wasmer_vm::SectionBodyPtr;
 
+#[allow(clippy::too_many_arguments)]
 fn apply_relocation(
     body: usize,
     r: &Relocation,
@@ -16,7 +20,9 @@ fn apply_relocation(
     allocated_sections: &PrimaryMap<SectionIndex, SectionBodyPtr>,
     libcall_trampolines: SectionIndex,
     libcall_trampoline_len: usize,
-) {
+    code: &LinkedCode,
+    hook: Option<&dyn RelocationHook>,
+) -> Result<(), CompileError> {
     let target_func_address: usize = match r.reloc_target {
         RelocationTarget::LocalFunc(index) => *allocated_functions[index].ptr as usize,
         RelocationTarget::LibCall(libcall) => {
@@ -36,6 +42,18 @@ fn apply_relocation(
             *allocated_sections[custom_section] as usize
         }
     };
+    let site = body + r.offset as usize;
+    let target_func_address =
+        match hook.and_then(|hook| hook.relocate(code, r, site, target_func_address)) {
+            Some(target) if !is_reachable(r.kind, site, target) => {
+                return Err(CompileError::Codegen(format!(
+                    "the relocation hook redirected a {} relocation to {:?} out of its reach",
+                    r.kind, r.reloc_target
+                )))
+            }
+            Some(target) => target,
+            None => target_func_address,
+        };
 
     match r.kind {
         RelocationKind::Abs8 => unsafe {
@@ -69,24 +87,40 @@ fn apply_relocation(
                 | (read_unaligned(reloc_address as *mut u32) & 0xfc00_0000);
             write_unaligned(reloc_address as *mut u32, reloc_delta);
         },
+        RelocationKind::S390xPCRel32Dbl => unsafe {
+            let (reloc_address, reloc_delta) = r.for_address(body, target_func_address as u64);
+            // The offset is counted in halfwords, and written in the byte
+            // order of the host, which is big-endian.
+            write_unaligned(
+                reloc_address as *mut u32,
+                ((reloc_delta as i64) >> 1) as u32,
+            );
+        },
         kind => panic!(
             "Relocation kind unsupported in the current architecture {}",
             kind
         ),
     }
+    Ok(())
 }
 
 /// Links a module, patching the allocated functions with the
 /// required relocations and jump tables.
+///
+/// The `hook`, if any, may redirect the relocations and then patch the
+/// functions.
+#[allow(clippy::too_many_arguments)]
 pub fn link_module(
-    _module: &ModuleInfo,
+    module: &ModuleInfo,
     allocated_functions: &PrimaryMap<LocalFunctionIndex, FunctionExtent>,
     function_relocations: Relocations,
     allocated_sections: &PrimaryMap<SectionIndex, SectionBodyPtr>,
     section_relocations: &PrimaryMap<SectionIndex, Vec<Relocation>>,
     libcall_trampolines: SectionIndex,
     trampoline_len: usize,
-) {
+    hook: Option<&dyn RelocationHook>,
+) -> Result<(), CompileError> {
+    let mut code = LinkedCode::new(module, allocated_functions, &function_relocations);
     for (i, section_relocs) in section_relocations.iter() {
         let body = *allocated_sections[i] as usize;
         for r in section_relocs {
@@ -97,7 +131,9 @@ pub fn link_module(
                 allocated_sections,
                 libcall_trampolines,
                 trampoline_len,
-            );
+                &code,
+                hook,
+            )?;
         }
     }
     for (i, function_relocs) in function_relocations.iter() {
@@ -110,7 +146,18 @@ pub fn link_module(
                 allocated_sections,
                 libcall_trampolines,
                 trampoline_len,
-            );
+                &code,
+                hook,
+            )?;
         }
     }
+    if let Some(hook) = hook {
+        hook.patch(&mut code).map_err(|error| {
+            CompileError::Codegen(format!(
+                "the relocation hook failed to patch the code: {}",
+                error
+            ))
+        })?;
+    }
+    Ok(())
 }
@@ -0,0 +1,171 @@
+//! Hooks observing and patching the code of the modules as it's linked.
+
+use std::convert::TryFrom;
+use std::fmt;
+use thiserror::Error;
+use wasmer_compiler::{Relocation, RelocationKind, Relocations};
+use wasmer_engine::FunctionExtent;
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::{LocalFunctionIndex, ModuleInfo};
+
+/// A hook of the universal engine, called as the code of each module is
+/// linked, before it's made executable.
+///
+/// It may redirect relocations, for instance to replace a libcall, and
+/// patch the code of the functions once they are relocated, for
+/// instance to jump to instrumentation thunks.
+///
+/// The hook is set with [`Universal::relocation_hook`](crate::Universal::relocation_hook).
+pub trait RelocationHook: Send + Sync {
+    /// Called before applying `relocation` at the address `site`, which
+    /// points it at `target`. Returns the address it should point at
+    /// instead, if any.
+    ///
+    /// The new target must be reachable by the kind of relocation,
+    /// otherwise the module fails to load.
+    fn relocate(
+        &self,
+        _code: &LinkedCode,
+        _relocation: &Relocation,
+        _site: usize,
+        _target: usize,
+    ) -> Option<usize> {
+        None
+    }
+
+    /// Called once the relocations of the module are applied, to patch
+    /// its code. An error makes the module fail to load.
+    fn patch(&self, _code: &mut LinkedCode) -> Result<(), PatchError> {
+        Ok(())
+    }
+}
+
+/// An error patching the code of a module.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// The module has no such local function.
+    #[error("there's no local function {0:?}")]
+    UnknownFunction(LocalFunctionIndex),
+
+    /// The patch goes past the end of the function.
+    #[error("patching {len} bytes at offset {offset} of a function of {function_len} bytes")]
+    OutOfBounds {
+        /// The offset of the patch in the function.
+        offset: usize,
+        /// The length of the patch.
+        len: usize,
+        /// The length of the function.
+        function_len: usize,
+    },
+
+    /// The patch overwrites bytes written by a relocation.
+    #[error("patching the relocation at offset {0} of the function")]
+    OverlapsRelocation(usize),
+
+    /// The hook failed for a reason of its own.
+    #[error("{0}")]
+    Other(String),
+}
+
+/// The code of a module being linked, as seen by a [`RelocationHook`].
+pub struct LinkedCode<'a> {
+    module: &'a ModuleInfo,
+    functions: &'a PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+    relocations: &'a Relocations,
+}
+
+impl<'a> LinkedCode<'a> {
+    pub(crate) fn new(
+        module: &'a ModuleInfo,
+        functions: &'a PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+        relocations: &'a Relocations,
+    ) -> Self {
+        Self {
+            module,
+            functions,
+            relocations,
+        }
+    }
+
+    /// The module being linked.
+    pub fn module(&self) -> &ModuleInfo {
+        self.module
+    }
+
+    /// The address of a local function.
+    pub fn function_address(&self, index: LocalFunctionIndex) -> Option<usize> {
+        self.functions.get(index).map(|extent| *extent.ptr as usize)
+    }
+
+    /// The code of a local function.
+    pub fn function_code(&self, index: LocalFunctionIndex) -> Option<&[u8]> {
+        let extent = self.functions.get(index)?;
+        Some(unsafe { std::slice::from_raw_parts(*extent.ptr as *const u8, extent.length) })
+    }
+
+    /// Overwrites the code of a local function at `offset` with `bytes`.
+    ///
+    /// The patch must lie within the function, and not overwrite what
+    /// the relocations wrote.
+    pub fn patch(
+        &mut self,
+        index: LocalFunctionIndex,
+        offset: usize,
+        bytes: &[u8],
+    ) -> Result<(), PatchError> {
+        let extent = self
+            .functions
+            .get(index)
+            .ok_or(PatchError::UnknownFunction(index))?;
+        let end = offset
+            .checked_add(bytes.len())
+            .filter(|&end| end <= extent.length)
+            .ok_or(PatchError::OutOfBounds {
+                offset,
+                len: bytes.len(),
+                function_len: extent.length,
+            })?;
+        if let Some(relocation) = self.relocations.get(index).and_then(|relocations| {
+            relocations.iter().find(|relocation| {
+                let start = relocation.offset as usize;
+                start < end && offset < start + relocation_len(relocation.kind)
+            })
+        }) {
+            return Err(PatchError::OverlapsRelocation(relocation.offset as usize));
+        }
+        unsafe {
+            let code = std::slice::from_raw_parts_mut(*extent.ptr as *mut u8, extent.length);
+            code[offset..end].copy_from_slice(bytes);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for LinkedCode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LinkedCode")
+            .field("module", &self.module.name())
+            .field("functions", &self.functions.len())
+            .finish()
+    }
+}
+
+/// The bytes a relocation writes.
+fn relocation_len(kind: RelocationKind) -> usize {
+    match kind {
+        RelocationKind::Abs8 | RelocationKind::X86PCRel8 => 8,
+        _ => 4,
+    }
+}
+
+/// Whether a relocation of `kind` at `site` can point at `target`.
+pub(crate) fn is_reachable(kind: RelocationKind, site: usize, target: usize) -> bool {
+    let delta = (target as i64).wrapping_sub(site as i64);
+    match kind {
+        RelocationKind::Abs8 | RelocationKind::X86PCRel8 => true,
+        RelocationKind::Abs4 => u32::try_from(target).is_ok(),
+        RelocationKind::Arm64Call => delta.abs() < 0x1000_0000,
+        RelocationKind::S390xPCRel32Dbl => delta % 2 == 0 && i32::try_from(delta / 2).is_ok(),
+        _ => i32::try_from(delta).is_ok(),
+    }
+}
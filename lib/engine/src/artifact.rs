@@ -3,8 +3,8 @@ use loupe::MemoryUsage;
 use std::any::Any;
 pub use wasmer_artifact::MetadataHeader;
 use wasmer_artifact::{ArtifactCreate, Upcastable};
-use wasmer_compiler::CpuFeature;
-use wasmer_types::entity::BoxedSlice;
+use wasmer_compiler::{CompiledFunctionFrameInfo, CpuFeature};
+use wasmer_types::entity::{BoxedSlice, PrimaryMap};
 use wasmer_types::{DataInitializer, FunctionIndex, LocalFunctionIndex, SignatureIndex};
 use wasmer_vm::{
     FuncDataRegistry, FunctionBodyPtr, InstanceAllocator, InstanceHandle, TrapHandler,
@@ -44,6 +44,18 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage + ArtifactCreate {
     /// Get the func data registry
     fn func_data_registry(&self) -> &FuncDataRegistry;
 
+    /// Returns the length in bytes of the code of each function, if this
+    /// `Artifact` keeps it.
+    fn finished_function_lengths(&self) -> Option<&BoxedSlice<LocalFunctionIndex, usize>> {
+        None
+    }
+
+    /// Returns the frame information of the compiled functions, if this
+    /// `Artifact` keeps it.
+    fn frame_infos(&self) -> Option<&PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>> {
+        None
+    }
+
     /// Do preinstantiation logic that is executed before instantiating
     fn preinstantiate(&self) -> Result<(), InstantiationError> {
         Ok(())
@@ -4,12 +4,13 @@ use crate::tunables::Tunables;
 use crate::{Artifact, DeserializeError};
 use loupe::MemoryUsage;
 use memmap2::Mmap;
+use std::io::Read;
 use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
 use std::sync::Arc;
-use wasmer_compiler::{CompileError, Target};
+use wasmer_compiler::{CancellationToken, CompileError, Target, WasmError};
 use wasmer_types::FunctionType;
-use wasmer_vm::{VMCallerCheckedAnyfunc, VMFuncRef, VMSharedSignatureIndex};
+use wasmer_vm::{SignatureRegistry, VMCallerCheckedAnyfunc, VMFuncRef, VMSharedSignatureIndex};
 
 /// A unimplemented Wasmer `Engine`.
 ///
@@ -30,9 +31,29 @@ pub trait Engine: MemoryUsage {
     /// Lookup a signature
     fn lookup_signature(&self, sig: VMSharedSignatureIndex) -> Option<FunctionType>;
 
+    /// The registry of the signatures shared by the modules of this engine.
+    fn signature_registry(&self) -> Arc<SignatureRegistry>;
+
     /// Validates a WebAssembly module
     fn validate(&self, binary: &[u8]) -> Result<(), CompileError>;
 
+    /// Validates a WebAssembly module while it is being read from `reader`,
+    /// returning the module bytes once they are all received and valid.
+    ///
+    /// Engines able to do so validate each section as soon as it arrives.
+    /// By default the whole module is buffered before being validated.
+    fn validate_streaming(&self, reader: &mut dyn Read) -> Result<Vec<u8>, CompileError> {
+        let mut binary = Vec::new();
+        reader.read_to_end(&mut binary).map_err(|e| {
+            CompileError::Wasm(WasmError::Generic(format!(
+                "Error when reading the module: {}",
+                e
+            )))
+        })?;
+        self.validate(&binary)?;
+        Ok(binary)
+    }
+
     /// Compile a WebAssembly binary
     fn compile(
         &self,
@@ -40,6 +61,24 @@ pub trait Engine: MemoryUsage {
         tunables: &dyn Tunables,
     ) -> Result<Arc<dyn Artifact>, CompileError>;
 
+    /// Compile a WebAssembly binary, giving up with
+    /// [`CompileError::Cancelled`] once `cancellation` is cancelled.
+    ///
+    /// By default `cancellation` is only checked before and after the
+    /// compilation, engines should let the compiler check it between
+    /// functions.
+    fn compile_cancellable(
+        &self,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+        cancellation: &CancellationToken,
+    ) -> Result<Arc<dyn Artifact>, CompileError> {
+        cancellation.check()?;
+        let artifact = self.compile(binary, tunables)?;
+        cancellation.check()?;
+        Ok(artifact)
+    }
+
     /// Deserializes a WebAssembly module
     ///
     /// # Safety
@@ -61,6 +100,19 @@ pub trait Engine: MemoryUsage {
         self.deserialize(&mmap)
     }
 
+    /// Replaces the compiler of the engine by the one of the compiler
+    /// plugin at `path`, a dynamic library declaring its compiler with
+    /// `wasmer_compiler::declare_compiler_plugin!`.
+    ///
+    /// The modules which are already compiled are not affected. By
+    /// default engines don't support compiler plugins.
+    fn register_compiler(&self, path: &Path) -> Result<(), CompileError> {
+        Err(CompileError::Codegen(format!(
+            "This engine doesn't support compiler plugins, can't register {}",
+            path.display()
+        )))
+    }
+
     /// A unique identifier for this object.
     ///
     /// This exists to allow us to compare two Engines for equality. Otherwise,
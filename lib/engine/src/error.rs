@@ -2,6 +2,7 @@
 use crate::trap::RuntimeError;
 use thiserror::Error;
 pub use wasmer_artifact::{DeserializeError, ImportError, SerializeError};
+use wasmer_types::{GlobalType, MemoryType, TableType};
 
 /// The WebAssembly.LinkError object indicates an error during
 /// module instantiation (besides traps from the start function).
@@ -16,6 +17,42 @@ pub enum LinkError {
     #[error("Error while importing {0:?}.{1:?}: {2}")]
     Import(String, String, ImportError),
 
+    /// An imported memory doesn't satisfy the limits the module declares
+    /// for it.
+    #[error("Error while importing {import_name}: incompatible import type. Expected memory {expected} but received memory {found}")]
+    IncompatibleMemoryLimits {
+        /// The memory type declared by the module.
+        expected: MemoryType,
+        /// The type of the memory provided.
+        found: MemoryType,
+        /// The name of the import, as `module.field`.
+        import_name: String,
+    },
+
+    /// An imported table doesn't satisfy the element type or the limits
+    /// the module declares for it.
+    #[error("Error while importing {import_name}: incompatible import type. Expected table {expected} but received table {found}")]
+    IncompatibleTableLimits {
+        /// The table type declared by the module.
+        expected: TableType,
+        /// The type of the table provided.
+        found: TableType,
+        /// The name of the import, as `module.field`.
+        import_name: String,
+    },
+
+    /// An imported global doesn't have the type or the mutability the
+    /// module declares for it.
+    #[error("Error while importing {import_name}: incompatible import type. Expected global {expected} but received global {found}")]
+    IncompatibleGlobalType {
+        /// The global type declared by the module.
+        expected: GlobalType,
+        /// The type of the global provided.
+        found: GlobalType,
+        /// The name of the import, as `module.field`.
+        import_name: String,
+    },
+
     /// A trap ocurred during linking.
     #[error("RuntimeError occurred during linking: {0}")]
     Trap(#[source] RuntimeError),
@@ -35,6 +35,7 @@ impl From<VMExtern> for Export {
             VMExtern::Function(vm_function) => Self::Function(ExportFunction {
                 vm_function,
                 metadata: None,
+                info: None,
             }),
             VMExtern::Memory(vm_memory) => Self::Memory(vm_memory),
             VMExtern::Table(vm_table) => Self::Table(vm_table),
@@ -143,6 +144,21 @@ pub struct ExportFunction {
     /// with each `Instance` as well as being responsible for the
     /// underlying memory of the host env.
     pub metadata: Option<Arc<ExportFunctionMetadata>>,
+    /// What the embedder told about the function, if it's a host function.
+    pub info: Option<Arc<HostFunctionInfo>>,
+}
+
+/// Descriptive information attached to a host function by the embedder,
+/// to audit and log which capabilities the modules importing it were
+/// given.
+#[derive(Debug, Clone, Default, PartialEq, Eq, MemoryUsage)]
+pub struct HostFunctionInfo {
+    /// A human readable name for the function.
+    pub name: Option<String>,
+    /// What the function does or which capability it grants.
+    pub docs: Option<String>,
+    /// The cost of a call to the function, in units of the embedder.
+    pub cost: Option<u64>,
 }
 
 impl From<ExportFunction> for Export {
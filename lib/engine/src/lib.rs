@@ -34,7 +34,7 @@ mod tunables;
 pub use crate::artifact::Artifact;
 pub use crate::engine::{Engine, EngineId};
 pub use crate::error::{InstantiationError, LinkError};
-pub use crate::export::{Export, ExportFunction, ExportFunctionMetadata};
+pub use crate::export::{Export, ExportFunction, ExportFunctionMetadata, HostFunctionInfo};
 pub use crate::resolver::{
     resolve_imports, ChainableNamedResolver, NamedResolver, NamedResolverChain, NullResolver,
     Resolver,
@@ -121,6 +121,45 @@ fn get_extern_from_export(_module: &ModuleInfo, export: &Export) -> ExternType {
     }
 }
 
+/// The error for an import resolved to an export of incompatible type,
+/// detailing the limits of memories and tables and the types of globals.
+fn incompatible_import(
+    module_name: &str,
+    field: &str,
+    import_extern: ExternType,
+    export_extern: ExternType,
+) -> LinkError {
+    let import_name = format!("{}.{}", module_name, field);
+    match (import_extern, export_extern) {
+        (ExternType::Memory(expected), ExternType::Memory(found)) => {
+            LinkError::IncompatibleMemoryLimits {
+                expected,
+                found,
+                import_name,
+            }
+        }
+        (ExternType::Table(expected), ExternType::Table(found)) => {
+            LinkError::IncompatibleTableLimits {
+                expected,
+                found,
+                import_name,
+            }
+        }
+        (ExternType::Global(expected), ExternType::Global(found)) => {
+            LinkError::IncompatibleGlobalType {
+                expected,
+                found,
+                import_name,
+            }
+        }
+        (import_extern, export_extern) => LinkError::Import(
+            module_name.to_string(),
+            field.to_string(),
+            ImportError::IncompatibleType(import_extern, export_extern),
+        ),
+    }
+}
+
 /// This function allows to match all imports of a `ModuleInfo` with concrete definitions provided by
 /// a `Resolver`.
 ///
@@ -154,10 +193,11 @@ pub fn resolve_imports(
         };
         let export_extern = get_extern_from_export(module, &resolved);
         if !export_extern.is_compatible_with(&import_extern) {
-            return Err(LinkError::Import(
-                module_name.to_string(),
-                field.to_string(),
-                ImportError::IncompatibleType(import_extern, export_extern),
+            return Err(incompatible_import(
+                module_name,
+                field,
+                import_extern,
+                export_extern,
             ));
         }
         match resolved {
@@ -223,10 +263,11 @@ pub fn resolve_imports(
                     let import_table_ty = t.from.ty();
                     let expected_table_ty = &module.tables[*index];
                     if import_table_ty.ty != expected_table_ty.ty {
-                        return Err(LinkError::Import(
-                            module_name.to_string(),
-                            field.to_string(),
-                            ImportError::IncompatibleType(import_extern, export_extern),
+                        return Err(incompatible_import(
+                            module_name,
+                            field,
+                            import_extern,
+                            export_extern,
                         ));
                     }
 
@@ -1,9 +1,10 @@
-use super::frame_info::{FrameInfo, GlobalFrameInfo, FRAME_INFO};
-use backtrace::Backtrace;
+use super::frame_info::{FrameInfo, GlobalFrameInfo, HostFrameInfo, TraceFrame};
+use backtrace::{Backtrace, BacktraceFrame};
+use std::any::Any;
 use std::error::Error;
 use std::fmt;
 use std::sync::Arc;
-use wasmer_vm::{raise_user_trap, Trap, TrapCode};
+use wasmer_vm::{host_function_stack_base, raise_user_trap, Trap, TrapCode};
 
 /// A struct representing an aborted instruction execution, with a message
 /// indicating the cause.
@@ -19,6 +20,7 @@ enum RuntimeErrorSource {
     OOM,
     User(Box<dyn Error + Send + Sync>),
     Trap(TrapCode),
+    CustomTrap(CustomTrap),
 }
 
 impl fmt::Display for RuntimeErrorSource {
@@ -28,10 +30,88 @@ impl fmt::Display for RuntimeErrorSource {
             Self::User(s) => write!(f, "{}", s),
             Self::OOM => write!(f, "Wasmer VM out of memory"),
             Self::Trap(s) => write!(f, "{}", s.message()),
+            Self::CustomTrap(s) => write!(f, "{}", s.message()),
         }
     }
 }
 
+/// A trap raised by the embedder, for instance from a host function
+/// imported by the module or called by code inserted by a middleware.
+///
+/// Its code is chosen by the embedder, so that distinct kinds of custom
+/// traps (a stack limit being hit, a sandbox policy violation...) can be
+/// told apart. It can also carry an arbitrary payload.
+///
+/// A `RuntimeError` holding a custom trap reports [`TrapCode::Custom`]
+/// from [`RuntimeError::to_trap`], and the trap itself is available via
+/// [`RuntimeError::custom_trap`].
+///
+/// # Example
+/// ```
+/// use wasmer_engine::{CustomTrap, RuntimeError};
+/// use wasmer_vm::TrapCode;
+///
+/// const STACK_LIMIT_REACHED: u32 = 1;
+///
+/// let error: RuntimeError = CustomTrap::new(STACK_LIMIT_REACHED, "stack limit reached")
+///     .with_payload(1024usize)
+///     .into();
+/// let trap = error.custom_trap().unwrap();
+/// assert_eq!(trap.code(), STACK_LIMIT_REACHED);
+/// assert_eq!(trap.payload::<usize>(), Some(&1024));
+/// assert_eq!(error.to_trap(), Some(TrapCode::Custom));
+/// ```
+#[derive(Debug)]
+pub struct CustomTrap {
+    code: u32,
+    message: String,
+    payload: Option<Box<dyn Any + Send + Sync>>,
+}
+
+impl CustomTrap {
+    /// Creates a new custom trap with the given `code` and `message`.
+    pub fn new<I: Into<String>>(code: u32, message: I) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            payload: None,
+        }
+    }
+
+    /// Attaches a payload to the trap.
+    pub fn with_payload<T: Any + Send + Sync>(mut self, payload: T) -> Self {
+        self.payload = Some(Box::new(payload));
+        self
+    }
+
+    /// Returns the embedder-defined code of the trap.
+    pub fn code(&self) -> u32 {
+        self.code
+    }
+
+    /// Returns the message of the trap.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the payload of the trap, if there is one of type `T`.
+    pub fn payload<T: Any>(&self) -> Option<&T> {
+        self.payload.as_ref()?.downcast_ref::<T>()
+    }
+}
+
+impl From<CustomTrap> for RuntimeError {
+    fn from(trap: CustomTrap) -> Self {
+        let info = GlobalFrameInfo::read();
+        Self::new_with_trace(
+            &info,
+            None,
+            RuntimeErrorSource::CustomTrap(trap),
+            Backtrace::new_unresolved(),
+        )
+    }
+}
+
 struct RuntimeErrorInner {
     /// The source error (this can be a custom user `Error` or a [`TrapCode`])
     source: RuntimeErrorSource,
@@ -39,6 +119,10 @@ struct RuntimeErrorInner {
     wasm_trace: Vec<FrameInfo>,
     /// The native backtrace
     native_trace: Backtrace,
+    /// The index in `native_trace` of the frame of each entry of `wasm_trace`
+    wasm_frame_indices: Vec<usize>,
+    /// The address whose access caused the trap, if any
+    fault_address: Option<usize>,
 }
 
 fn _assert_trap_is_sync_and_send(t: &Trap) -> (&dyn Sync, &dyn Send) {
@@ -54,7 +138,7 @@ impl RuntimeError {
     /// assert_eq!("unexpected error", trap.message());
     /// ```
     pub fn new<I: Into<String>>(message: I) -> Self {
-        let info = FRAME_INFO.read().unwrap();
+        let info = GlobalFrameInfo::read();
         let msg = message.into();
         Self::new_with_trace(
             &info,
@@ -66,7 +150,17 @@ impl RuntimeError {
 
     /// Create a new RuntimeError from a Trap.
     pub fn from_trap(trap: Trap) -> Self {
-        let info = FRAME_INFO.read().unwrap();
+        let error = Self::from_trap_with_trace(trap);
+        // Within a host function called from WebAssembly, the trace of a
+        // nested call stops at the host function.
+        match host_function_stack_base() {
+            Some(base) => error.extend_trace(Some(base)),
+            None => error,
+        }
+    }
+
+    fn from_trap_with_trace(trap: Trap) -> Self {
+        let info = GlobalFrameInfo::read();
         match trap {
             // A user error
             Trap::User(error) => {
@@ -90,13 +184,22 @@ impl RuntimeError {
                 pc,
                 signal_trap,
                 backtrace,
+                fault_address,
             } => {
                 let code = info
                     .lookup_trap_info(pc)
                     .map_or(signal_trap.unwrap_or(TrapCode::StackOverflow), |info| {
                         info.trap_code
                     });
-                Self::new_with_trace(&info, Some(pc), RuntimeErrorSource::Trap(code), backtrace)
+                let mut error = Self::new_with_trace(
+                    &info,
+                    Some(pc),
+                    RuntimeErrorSource::Trap(code),
+                    backtrace,
+                );
+                // The error was just created, nobody else holds it yet.
+                Arc::get_mut(&mut error.inner).unwrap().fault_address = fault_address;
+                error
             }
             // A trap triggered manually from the Wasmer runtime
             Trap::Lib {
@@ -121,7 +224,7 @@ impl RuntimeError {
             // The error is already a RuntimeError, we return it directly
             Ok(runtime_error) => *runtime_error,
             Err(error) => {
-                let info = FRAME_INFO.read().unwrap();
+                let info = GlobalFrameInfo::read();
                 Self::new_with_trace(
                     &info,
                     None,
@@ -138,45 +241,69 @@ impl RuntimeError {
         source: RuntimeErrorSource,
         native_trace: Backtrace,
     ) -> Self {
-        let frames: Vec<usize> = native_trace
-            .frames()
-            .iter()
-            .filter_map(|frame| {
-                let pc = frame.ip() as usize;
-                if pc == 0 {
-                    None
-                } else {
-                    // Note that we need to be careful about the pc we pass in here to
-                    // lookup frame information. This program counter is used to
-                    // translate back to an original source location in the origin wasm
-                    // module. If this pc is the exact pc that the trap happened at,
-                    // then we look up that pc precisely. Otherwise backtrace
-                    // information typically points at the pc *after* the call
-                    // instruction (because otherwise it's likely a call instruction on
-                    // the stack). In that case we want to lookup information for the
-                    // previous instruction (the call instruction) so we subtract one as
-                    // the lookup.
-                    let pc_to_lookup = if Some(pc) == trap_pc { pc } else { pc - 1 };
-                    Some(pc_to_lookup)
-                }
-            })
-            .collect();
-
-        // Let's construct the trace
-        let wasm_trace = frames
-            .into_iter()
-            .filter_map(|pc| info.lookup_frame_info(pc))
-            .collect::<Vec<_>>();
+        let (wasm_trace, wasm_frame_indices) =
+            lookup_wasm_frames(info, trap_pc, native_trace.frames());
 
         Self {
             inner: Arc::new(RuntimeErrorInner {
                 source,
                 wasm_trace,
                 native_trace,
+                wasm_frame_indices,
+                fault_address: None,
             }),
         }
     }
 
+    /// Continues the trace of an error raised by a host function with the
+    /// frames of the WebAssembly code which called it.
+    ///
+    /// Host functions run on the host stack, so the native backtrace of an
+    /// error raised in one, or in WebAssembly code it called in turn, stops
+    /// at the host function. This is called back on the stack of the
+    /// calling WebAssembly code, before the error is raised as a trap there.
+    #[doc(hidden)]
+    pub fn with_caller_frames(self) -> Self {
+        self.extend_trace(None)
+    }
+
+    /// Appends the frames of the current stack, up to the stack address
+    /// `base` if any, to the trace.
+    fn extend_trace(self, base: Option<usize>) -> Self {
+        let mut inner = match Arc::try_unwrap(self.inner) {
+            Ok(inner) => inner,
+            Err(inner) => return Self { inner },
+        };
+        let mut current_frames = Vec::new();
+        backtrace::trace(|frame| {
+            if base.map_or(false, |base| frame.sp() as usize >= base) {
+                return false;
+            }
+            current_frames.push(BacktraceFrame::from(frame.clone()));
+            true
+        });
+
+        let mut frames: Vec<BacktraceFrame> = inner.native_trace.into();
+        // The backtrace already went past the host function if it could be
+        // unwound through the trampolines in between.
+        let outermost_ip = current_frames.last().map(|frame| frame.ip());
+        if !frames.iter().any(|frame| Some(frame.ip()) == outermost_ip) {
+            let info = GlobalFrameInfo::read();
+            let (wasm_trace, wasm_frame_indices) = lookup_wasm_frames(&info, None, &current_frames);
+            let offset = frames.len();
+            frames.extend(current_frames);
+            inner.wasm_trace.extend(wasm_trace);
+            inner
+                .wasm_frame_indices
+                .extend(wasm_frame_indices.iter().map(|index| index + offset));
+        }
+        inner.native_trace = frames.into();
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
     /// Returns a reference the `message` stored in `Trap`.
     pub fn message(&self) -> String {
         self.inner.source.to_string()
@@ -188,6 +315,101 @@ impl RuntimeError {
         &self.inner.wasm_trace
     }
 
+    /// Returns an identifier of the WebAssembly frames that led to this
+    /// trap, or `None` if there are none.
+    ///
+    /// The identifier only depends on the function index and the offset
+    /// within the function of each frame, not on native addresses or on
+    /// how the module was named or compiled. Identical traps of the same
+    /// module get the same identifier on every machine and with every
+    /// version of Wasmer, which allows grouping crash reports.
+    pub fn trace_id(&self) -> Option<u64> {
+        if self.inner.wasm_trace.is_empty() {
+            return None;
+        }
+        // FNV-1a, whose output is specified, unlike the one of the
+        // standard hashers.
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+        let mut hash = OFFSET_BASIS;
+        for frame in &self.inner.wasm_trace {
+            let func_offset = frame.func_offset() as u32;
+            for byte in frame
+                .func_index()
+                .to_le_bytes()
+                .iter()
+                .chain(func_offset.to_le_bytes().iter())
+            {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(PRIME);
+            }
+        }
+        Some(hash)
+    }
+
+    /// Returns the WebAssembly frames that led to this trap, interleaved
+    /// with the frames of the host functions between them.
+    ///
+    /// This shows which imported host function was executing when a
+    /// WebAssembly function it called trapped, or where in a host
+    /// function an error was raised. The host frames below the outermost
+    /// WebAssembly frame, as well as the frames of the Wasmer runtime
+    /// itself, are left out.
+    ///
+    /// The native symbols are resolved when this is called, which is
+    /// comparatively slow.
+    pub fn full_trace(&self) -> Vec<TraceFrame> {
+        let last_wasm_frame = match self.inner.wasm_frame_indices.last() {
+            Some(&index) => index,
+            None => return Vec::new(),
+        };
+        let mut native_trace = self.inner.native_trace.clone();
+        native_trace.resolve();
+
+        let mut wasm_frames = self
+            .inner
+            .wasm_frame_indices
+            .iter()
+            .zip(self.inner.wasm_trace.iter())
+            .peekable();
+        let mut trace = Vec::new();
+        for (index, frame) in native_trace.frames()[..=last_wasm_frame].iter().enumerate() {
+            if let Some((_, wasm_frame)) = wasm_frames.next_if(|&(&i, _)| i == index) {
+                trace.push(TraceFrame::Wasm(wasm_frame.clone()));
+                continue;
+            }
+            // A native frame may have several symbols when functions were
+            // inlined into it, the innermost one comes first.
+            for symbol in frame.symbols() {
+                let name = match symbol.name() {
+                    Some(name) => name.to_string(),
+                    None => continue,
+                };
+                if is_runtime_frame(&name) {
+                    continue;
+                }
+                trace.push(TraceFrame::Host(HostFrameInfo::new(
+                    name,
+                    symbol.filename().map(|path| path.to_path_buf()),
+                    symbol.lineno(),
+                )));
+            }
+        }
+        trace
+    }
+
+    /// Returns the native address whose access caused the trap, if the
+    /// trap was caused by an invalid memory access.
+    ///
+    /// Subtracting the base address of the accessed memory (see
+    /// `Memory::data_ptr`) gives the offending offset in the memory.
+    ///
+    /// Out-of-bounds accesses detected by explicit bounds checks in the
+    /// generated code rather than by the hardware have no fault address.
+    pub fn fault_address(&self) -> Option<usize> {
+        self.inner.fault_address
+    }
+
     /// Attempts to downcast the `RuntimeError` to a concrete type.
     pub fn downcast<T: Error + 'static>(self) -> Result<T, Self> {
         match Arc::try_unwrap(self.inner) {
@@ -205,10 +427,18 @@ impl RuntimeError {
 
     /// Returns trap code, if it's a Trap
     pub fn to_trap(self) -> Option<TrapCode> {
-        if let RuntimeErrorSource::Trap(trap_code) = self.inner.source {
-            Some(trap_code)
-        } else {
-            None
+        match self.inner.source {
+            RuntimeErrorSource::Trap(trap_code) => Some(trap_code),
+            RuntimeErrorSource::CustomTrap(_) => Some(TrapCode::Custom),
+            _ => None,
+        }
+    }
+
+    /// Returns the custom trap raised by the embedder, if it's one
+    pub fn custom_trap(&self) -> Option<&CustomTrap> {
+        match &self.inner.source {
+            RuntimeErrorSource::CustomTrap(trap) => Some(trap),
+            _ => None,
         }
     }
 
@@ -221,6 +451,65 @@ impl RuntimeError {
     }
 }
 
+/// The WebAssembly frames among the native `frames`, with their indices.
+///
+/// `trap_pc` is the exact address of the trapping instruction, if the
+/// frames are the ones of a trap in WebAssembly code.
+fn lookup_wasm_frames(
+    info: &GlobalFrameInfo,
+    trap_pc: Option<usize>,
+    frames: &[BacktraceFrame],
+) -> (Vec<FrameInfo>, Vec<usize>) {
+    let mut wasm_trace = Vec::new();
+    let mut wasm_frame_indices = Vec::new();
+    for (index, frame) in frames.iter().enumerate() {
+        let pc = frame.ip() as usize;
+        if pc == 0 {
+            continue;
+        }
+        // Note that we need to be careful about the pc we pass in here to
+        // lookup frame information. This program counter is used to
+        // translate back to an original source location in the origin wasm
+        // module. If this pc is the exact pc that the trap happened at,
+        // then we look up that pc precisely. Otherwise backtrace
+        // information typically points at the pc *after* the call
+        // instruction (because otherwise it's likely a call instruction on
+        // the stack). In that case we want to lookup information for the
+        // previous instruction (the call instruction) so we subtract one as
+        // the lookup.
+        let pc_to_lookup = if Some(pc) == trap_pc { pc } else { pc - 1 };
+        if let Some(frame_info) = info.lookup_frame_info(pc_to_lookup) {
+            wasm_trace.push(frame_info);
+            wasm_frame_indices.push(index);
+        }
+    }
+    (wasm_trace, wasm_frame_indices)
+}
+
+/// Whether a native frame belongs to the Wasmer runtime or to the
+/// standard library rather than to the embedder, and is thus left out
+/// of [`RuntimeError::full_trace`].
+fn is_runtime_frame(name: &str) -> bool {
+    const RUNTIME_PREFIXES: &[&str] = &[
+        "wasmer::",
+        "wasmer_vm::",
+        "wasmer_engine::",
+        "wasmer_engine_universal::",
+        "wasmer_engine_dylib::",
+        "wasmer_engine_staticlib::",
+        "backtrace::",
+        "corosensei::",
+        "std::",
+        "core::",
+        "alloc::",
+        "__",
+    ];
+    let name = name.trim_start_matches('<');
+    RUNTIME_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
 impl fmt::Debug for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("RuntimeError")
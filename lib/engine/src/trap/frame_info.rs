@@ -11,11 +11,17 @@
 //! let module: ModuleInfo = ...;
 //! FRAME_INFO.register(module, compiled_functions);
 //! ```
+use super::sampling::HotFunction;
 use loupe::MemoryUsage;
 use std::cmp;
 use std::collections::BTreeMap;
-use std::sync::{Arc, RwLock};
-use wasmer_compiler::{CompiledFunctionFrameInfo, SourceLoc, TrapInformation};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use wasmer_compiler::{
+    CompiledFunctionFrameInfo, PatchPoint, SourceLoc, StepPoint, TrapInformation,
+};
 use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{LocalFunctionIndex, ModuleInfo};
 use wasmer_vm::FunctionBodyPtr;
@@ -58,6 +64,8 @@ struct ModuleInfoFrameInfo {
     functions: BTreeMap<usize, FunctionInfo>,
     module: Arc<ModuleInfo>,
     frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+    /// The samples of the program counter taken in each function.
+    samples: PrimaryMap<LocalFunctionIndex, AtomicU64>,
 }
 
 impl ModuleInfoFrameInfo {
@@ -83,6 +91,21 @@ struct FunctionInfo {
 }
 
 impl GlobalFrameInfo {
+    /// Locks `FRAME_INFO` for reading.
+    ///
+    /// The lock is shared by all the engines and stores of the process, so
+    /// a thread panicking while holding it must not make it unusable for
+    /// the others. Writers only insert or remove whole modules, which keeps
+    /// the map consistent even if they panic.
+    pub fn read() -> RwLockReadGuard<'static, Self> {
+        FRAME_INFO.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks `FRAME_INFO` for writing, see [`GlobalFrameInfo::read`].
+    fn write() -> RwLockWriteGuard<'static, Self> {
+        FRAME_INFO.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
     /// Fetches frame information about a program counter in a backtrace.
     ///
     /// Returns an object if this `pc` is known to some previously registered
@@ -151,6 +174,73 @@ impl GlobalFrameInfo {
         Some(&traps[idx])
     }
 
+    /// Fetches the step point of a breakpoint, and the index of the
+    /// function it's in.
+    pub fn lookup_step_point(&self, pc: usize) -> Option<(u32, &StepPoint)> {
+        let module = self.module_info(pc)?;
+        let func = module.function_info(pc)?;
+        let step_points = &module.function_debug_info(func.local_index).step_points;
+        let idx = step_points
+            .binary_search_by_key(&((pc - func.start) as u32), |point| point.code_offset)
+            .ok()?;
+        let func_index = module.module.func_index(func.local_index);
+        Some((func_index.index() as u32, &step_points[idx]))
+    }
+
+    /// Fetches the patch point of a breakpoint, and the index of the
+    /// function it's in.
+    pub fn lookup_patch_point(&self, pc: usize) -> Option<(u32, &PatchPoint)> {
+        let module = self.module_info(pc)?;
+        let func = module.function_info(pc)?;
+        let patch_points = &module.function_debug_info(func.local_index).patch_points;
+        let idx = patch_points
+            .binary_search_by_key(&((pc - func.start) as u32), |point| point.code_offset)
+            .ok()?;
+        let func_index = module.module.func_index(func.local_index);
+        Some((func_index.index() as u32, &patch_points[idx]))
+    }
+
+    /// Fetches the patch points of the function starting at `address`.
+    pub fn function_patch_points(&self, address: usize) -> Option<&[PatchPoint]> {
+        let module = self.module_info(address)?;
+        let func = module.function_info(address)?;
+        if func.start != address {
+            return None;
+        }
+        Some(&module.function_debug_info(func.local_index).patch_points)
+    }
+
+    /// Counts a sample of the program counter in the function it's in,
+    /// if it's in WebAssembly code.
+    pub(crate) fn record_sample(&self, pc: usize) {
+        if let Some(module) = self.module_info(pc) {
+            if let Some(func) = module.function_info(pc) {
+                module.samples[func.local_index].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// The functions of the registered modules which have been sampled.
+    pub(crate) fn sampled_functions(&self) -> Vec<HotFunction> {
+        let mut functions = vec![];
+        for module in self.ranges.values() {
+            for (local_index, samples) in module.samples.iter() {
+                let samples = samples.load(Ordering::Relaxed);
+                if samples == 0 {
+                    continue;
+                }
+                let func_index = module.module.func_index(local_index);
+                functions.push(HotFunction {
+                    module_name: module.module.name(),
+                    func_index: func_index.index() as u32,
+                    function_name: module.module.function_names.get(&func_index).cloned(),
+                    samples,
+                });
+            }
+        }
+        functions
+    }
+
     /// Gets a module given a pc
     fn module_info(&self, pc: usize) -> Option<&ModuleInfoFrameInfo> {
         let (end, module_info) = self.ranges.range(pc..).next()?;
@@ -164,9 +254,7 @@ impl GlobalFrameInfo {
 
 impl Drop for GlobalFrameInfoRegistration {
     fn drop(&mut self) {
-        if let Ok(mut info) = FRAME_INFO.write() {
-            info.ranges.remove(&self.key);
-        }
+        GlobalFrameInfo::write().ranges.remove(&self.key);
     }
 }
 
@@ -217,8 +305,14 @@ pub fn register(
     if functions.is_empty() {
         return None;
     }
+    if frame_infos
+        .values()
+        .any(|frame_info| !frame_info.step_points.is_empty() || !frame_info.patch_points.is_empty())
+    {
+        wasmer_vm::set_step_handler(super::step::handle_step);
+    }
 
-    let mut info = FRAME_INFO.write().unwrap();
+    let mut info = GlobalFrameInfo::write();
     // First up assert that our chunk of jit functions doesn't collide with
     // any other known chunks of jit functions...
     if let Some((_, prev)) = info.ranges.range(max..).next() {
@@ -235,6 +329,7 @@ pub fn register(
             start: min,
             functions,
             module,
+            samples: frame_infos.keys().map(|_| AtomicU64::new(0)).collect(),
             frame_infos,
         },
     );
@@ -318,3 +413,84 @@ impl FrameInfo {
         (self.instr.bits() - self.func_start.bits()) as usize
     }
 }
+
+/// Description of a native frame of the host, such as an imported host
+/// function, found between WebAssembly frames in a
+/// [`RuntimeError::full_trace`](crate::RuntimeError::full_trace).
+#[derive(Debug, Clone)]
+pub struct HostFrameInfo {
+    name: String,
+    filename: Option<PathBuf>,
+    lineno: Option<u32>,
+}
+
+impl HostFrameInfo {
+    pub(crate) fn new(name: String, filename: Option<PathBuf>, lineno: Option<u32>) -> Self {
+        Self {
+            name,
+            filename,
+            lineno,
+        }
+    }
+
+    /// Returns the demangled name of the native function of this frame.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the source file of the native function, if debug
+    /// information is available.
+    pub fn filename(&self) -> Option<&Path> {
+        self.filename.as_deref()
+    }
+
+    /// Returns the line in the source file, if debug information is
+    /// available.
+    pub fn lineno(&self) -> Option<u32> {
+        self.lineno
+    }
+}
+
+/// A frame of a [`RuntimeError::full_trace`](crate::RuntimeError::full_trace),
+/// which interleaves the WebAssembly frames with the host frames between
+/// them.
+#[derive(Debug, Clone)]
+pub enum TraceFrame {
+    /// A frame of a WebAssembly function.
+    Wasm(FrameInfo),
+    /// A frame of a native host function.
+    Host(HostFrameInfo),
+}
+
+impl fmt::Display for TraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Wasm(frame) => {
+                match frame.function_name() {
+                    Some(name) => match rustc_demangle::try_demangle(name) {
+                        Ok(name) => write!(f, "{}", name)?,
+                        Err(_) => write!(f, "{}", name)?,
+                    },
+                    None => write!(f, "<unnamed>")?,
+                }
+                write!(
+                    f,
+                    " ({}[{}]:0x{:x})",
+                    frame.module_name(),
+                    frame.func_index(),
+                    frame.module_offset()
+                )
+            }
+            Self::Host(frame) => {
+                write!(f, "{} (host", frame.name())?;
+                if let Some(filename) = frame.filename() {
+                    write!(f, " {}", filename.display())?;
+                    if let Some(lineno) = frame.lineno() {
+                        write!(f, ":{}", lineno)?;
+                    }
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
@@ -1,7 +1,13 @@
 mod error;
 mod frame_info;
-pub use error::RuntimeError;
+mod probe;
+mod sampling;
+mod step;
+pub use error::{CustomTrap, RuntimeError};
 pub use frame_info::{
     register as register_frame_info, FrameInfo, FunctionExtent, GlobalFrameInfoRegistration,
-    FRAME_INFO,
+    HostFrameInfo, TraceFrame, FRAME_INFO,
 };
+pub use probe::{set_probe_callback, set_probes, Probe};
+pub use sampling::{hot_functions, start_sampling, stop_sampling, HotFunction};
+pub use step::{set_step_callback, Step};
@@ -0,0 +1,88 @@
+//! Dynamic tracing of functions compiled with patch points.
+//!
+//! The nop of a patch point is replaced by a breakpoint to enable its
+//! probe, and the breakpoint calls the probe callback before the
+//! execution resumes, as uprobes do for native code.
+
+use super::frame_info::GlobalFrameInfo;
+use std::io;
+use std::sync::{Arc, PoisonError, RwLock};
+use wasmer_compiler::PatchPointKind;
+use wasmer_vm::VMFunctionBody;
+
+/// A probe hit by a thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Probe {
+    /// The index of the function in its module.
+    pub func_index: u32,
+    /// Where the probe is placed in the function.
+    pub kind: PatchPointKind,
+    /// The offset of the instruction at the probe in the module.
+    pub module_offset: u32,
+}
+
+type ProbeCallback = Arc<dyn Fn(&Probe) + Send + Sync>;
+
+lazy_static::lazy_static! {
+    static ref PROBE_CALLBACK: RwLock<Option<ProbeCallback>> = RwLock::new(None);
+}
+
+/// Sets the function called when any thread hits an enabled probe, or
+/// unsets it with `None`.
+///
+/// The callback runs from a signal handler: it must not call back into
+/// WebAssembly, and should only record the probes it's given.
+#[allow(clippy::type_complexity)]
+pub fn set_probe_callback(callback: Option<Box<dyn Fn(&Probe) + Send + Sync>>) {
+    *PROBE_CALLBACK
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = callback.map(Arc::from);
+}
+
+/// Enables or disables the probes of the patch points of `kind` in the
+/// function whose code starts at `address`, returning how many there are.
+///
+/// The code is patched in place, so the function may be running on other
+/// threads. A function without patch points of this kind, such as a host
+/// function, has no probes to enable.
+pub fn set_probes(
+    address: *const VMFunctionBody,
+    kind: PatchPointKind,
+    enabled: bool,
+) -> io::Result<usize> {
+    let info = GlobalFrameInfo::read();
+    let patch_points = match info.function_patch_points(address as usize) {
+        Some(patch_points) => patch_points,
+        None => return Ok(0),
+    };
+    let mut count = 0;
+    for point in patch_points.iter().filter(|point| point.kind == kind) {
+        // The module can't be unregistered while `FRAME_INFO` is locked,
+        // so its code is still there.
+        unsafe {
+            wasmer_vm::set_breakpoint(address as usize + point.code_offset as usize, enabled)?
+        };
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Gives the breakpoint at `pc` to the probe callback if it's an enabled
+/// probe.
+pub(crate) fn handle_probe(info: &GlobalFrameInfo, pc: usize) -> bool {
+    let (func_index, point) = match info.lookup_patch_point(pc) {
+        Some(found) => found,
+        None => return false,
+    };
+    // The callback may be being replaced: the probe is skipped then.
+    if let Ok(callback) = PROBE_CALLBACK.try_read() {
+        if let Some(callback) = callback.as_ref() {
+            callback(&Probe {
+                func_index,
+                kind: point.kind,
+                module_offset: point.srcloc.bits(),
+            });
+        }
+    }
+    true
+}
@@ -0,0 +1,63 @@
+//! Detection of the hot functions by sampling the program counter of the
+//! running threads, without instrumenting the code.
+//!
+//! While sampling, the threads consuming CPU time are interrupted at a
+//! regular interval of CPU time, and each sample taken in WebAssembly
+//! code is counted in the function it's in, as given by the address
+//! maps of the registered modules. The counts are the input of tiering
+//! and of profile-guided optimizations.
+
+use super::frame_info::{GlobalFrameInfo, FRAME_INFO};
+use std::cmp::Reverse;
+use std::io;
+use std::sync::TryLockError;
+use std::time::Duration;
+
+/// A function sampled while running, see [`hot_functions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotFunction {
+    /// The name of the module of the function.
+    pub module_name: String,
+    /// The index of the function in its module.
+    pub func_index: u32,
+    /// The name of the function, if the module gives it.
+    pub function_name: Option<String>,
+    /// The samples taken in the function.
+    pub samples: u64,
+}
+
+/// Starts sampling the program counter of the threads of the process
+/// every `interval` of CPU time they consume.
+///
+/// Sampling is process-wide and uses the `SIGPROF` profiling timer, so it
+/// can't be combined with another profiler using it. It's only supported
+/// on Linux, on x86-64 and ARM64.
+pub fn start_sampling(interval: Duration) -> io::Result<()> {
+    wasmer_vm::start_sampling(interval, record_sample)
+}
+
+/// Stops sampling. The samples taken so far are kept.
+pub fn stop_sampling() -> io::Result<()> {
+    wasmer_vm::stop_sampling()
+}
+
+/// The functions of the loaded modules sampled so far, the hottest first.
+///
+/// The samples of a module are dropped with it.
+pub fn hot_functions() -> Vec<HotFunction> {
+    let mut functions = GlobalFrameInfo::read().sampled_functions();
+    functions.sort_by_key(|function| Reverse(function.samples));
+    functions
+}
+
+/// The sample handler of the engine, given to the VM.
+fn record_sample(pc: usize) {
+    // Modules are never registered while their code runs on this thread,
+    // but another thread may hold the lock: the sample is dropped then.
+    let info = match FRAME_INFO.try_read() {
+        Ok(info) => info,
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(TryLockError::WouldBlock) => return,
+    };
+    info.record_sample(pc);
+}
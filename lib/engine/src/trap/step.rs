@@ -0,0 +1,83 @@
+//! Reports the execution of functions compiled for single-stepping,
+//! one WebAssembly instruction at a time.
+
+use super::frame_info::FRAME_INFO;
+use std::cell::RefCell;
+use std::sync::TryLockError;
+use wasmer_compiler::ValueLocation;
+use wasmer_vm::StepContext;
+
+/// The state of a function compiled for single-stepping, right after
+/// one of its instructions has been executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// The index of the function in its module.
+    pub func_index: u32,
+    /// The offset of the instruction in the module.
+    pub module_offset: u32,
+    /// The bits of the locals, arguments first. Values narrower than
+    /// 64 bits are in the low bits. `None` if a value can't be read on
+    /// this platform.
+    pub locals: Vec<Option<u64>>,
+    /// The bits of the values on the operand stack, bottom first.
+    pub stack: Vec<Option<u64>>,
+}
+
+type StepCallback = Box<dyn FnMut(&Step)>;
+
+thread_local! {
+    static STEP_CALLBACK: RefCell<Option<StepCallback>> = RefCell::new(None);
+}
+
+/// Sets the function called after each instruction executed by the
+/// current thread in functions compiled for single-stepping, or unsets
+/// it with `None`.
+///
+/// The callback runs from a signal handler: it must not call back into
+/// WebAssembly, and should only record the steps it's given.
+pub fn set_step_callback(callback: Option<StepCallback>) {
+    STEP_CALLBACK.with(|cell| *cell.borrow_mut() = callback);
+}
+
+/// The step handler of the engine, given to the VM.
+pub(crate) fn handle_step(context: &StepContext) -> bool {
+    // Modules are never registered while their code runs on this thread,
+    // but another thread may hold the lock.
+    let info = match FRAME_INFO.try_read() {
+        Ok(info) => info,
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(TryLockError::WouldBlock) => return false,
+    };
+    let (func_index, point) = match info.lookup_step_point(context.pc()) {
+        Some(found) => found,
+        None => return super::probe::handle_probe(&info, context.pc()),
+    };
+    STEP_CALLBACK.with(|cell| {
+        if let Ok(mut callback) = cell.try_borrow_mut() {
+            if let Some(callback) = callback.as_mut() {
+                let read = |location: &ValueLocation| read_value(context, *location);
+                callback(&Step {
+                    func_index,
+                    module_offset: point.srcloc.bits(),
+                    locals: point.locals.iter().map(read).collect(),
+                    stack: point.stack.iter().map(read).collect(),
+                });
+            }
+        }
+    });
+    true
+}
+
+fn read_value(context: &StepContext, location: ValueLocation) -> Option<u64> {
+    match location {
+        ValueLocation::Register(index) => context.register(index),
+        ValueLocation::SimdRegister(index) => context.simd_register(index),
+        ValueLocation::Memory { base, offset } => {
+            let address = (context.register(base)? as i64).wrapping_add(offset as i64);
+            // The slots of the frame of the function being executed.
+            Some(unsafe { std::ptr::read_unaligned(address as *const u64) })
+        }
+        ValueLocation::Constant(value) => Some(value),
+        ValueLocation::Unknown => None,
+    }
+}
@@ -0,0 +1,524 @@
+//! `branch_profiling` is a middleware counting how many times the
+//! conditional branches and the call sites of a module are executed,
+//! to collect the profile used by profile-guided optimizations.
+//!
+//! The counters are 64-bit integers stored in a region of the linear
+//! memory of the module reserved by the embedder, for example at the
+//! end of the memory or in a static area the guest leaves unused.
+//! Nothing checks that the guest does not use the region: it is up to
+//! the embedder to reserve it.
+//!
+//! Each counter is identified by the function holding it, the offset of
+//! the instrumented operator in the module, and the counted event. The
+//! counts are extracted with [`BranchProfiling::profile`].
+
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::fmt;
+use std::io::{self, Write};
+use std::mem;
+use std::sync::{Arc, Mutex};
+use wasmer::wasmparser::{
+    MemoryImmediate, Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType,
+};
+use wasmer::{
+    ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance, LocalFunctionIndex,
+    MemoryAccessError, MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, ModuleInfo};
+
+/// The name of the export giving access to the memory holding the
+/// counters.
+const MEMORY_EXPORT_NAME: &str = "wasmer_branch_profiling_memory";
+
+/// An event counted by the [`BranchProfiling`] middleware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ProfileEvent {
+    /// The branch of an `if` or a `br_if` was taken: its condition was
+    /// not zero.
+    BranchTaken,
+    /// The branch of an `if` or a `br_if` was not taken: its condition
+    /// was zero.
+    BranchNotTaken,
+    /// A `br_table` was executed.
+    BranchTable,
+    /// A direct call was executed.
+    Call,
+    /// An indirect call was executed.
+    CallIndirect,
+}
+
+impl ProfileEvent {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::BranchTaken => "taken",
+            Self::BranchNotTaken => "not-taken",
+            Self::BranchTable => "br-table",
+            Self::Call => "call",
+            Self::CallIndirect => "call-indirect",
+        }
+    }
+}
+
+impl fmt::Display for ProfileEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An instrumented site: the counter with the same index counts its event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ProfileSite {
+    function: FunctionIndex,
+    offset: usize,
+    event: ProfileEvent,
+}
+
+/// A count of the profile of a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileCount {
+    /// The function holding the instrumented operator.
+    pub function: FunctionIndex,
+    /// The offset of the instrumented operator in the module.
+    pub offset: usize,
+    /// The counted event.
+    pub event: ProfileEvent,
+    /// How many times the event happened.
+    pub count: u64,
+}
+
+/// The counts collected by the [`BranchProfiling`] middleware.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BranchProfile {
+    counts: Vec<ProfileCount>,
+    missed_sites: usize,
+}
+
+impl BranchProfile {
+    /// The counts of all the instrumented sites, sorted by function and
+    /// offset.
+    pub fn counts(&self) -> &[ProfileCount] {
+        &self.counts
+    }
+
+    /// The count of `event` for the operator at `offset`, if it was
+    /// instrumented.
+    pub fn count(&self, offset: usize, event: ProfileEvent) -> Option<u64> {
+        self.counts
+            .iter()
+            .find(|count| count.offset == offset && count.event == event)
+            .map(|count| count.count)
+    }
+
+    /// How many sites were not instrumented because the reserved region
+    /// was full.
+    pub fn missed_sites(&self) -> usize {
+        self.missed_sites
+    }
+
+    /// Writes the profile in its textual format: a header line followed
+    /// by one `<function index> <offset> <event> <count>` line per count.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "# wasmer branch profile v1")?;
+        for count in &self.counts {
+            writeln!(
+                writer,
+                "{} {} {} {}",
+                count.function.index(),
+                count.offset,
+                count.event,
+                count.count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct ProfilingState {
+    /// The instrumented sites, by counter index.
+    sites: Vec<ProfileSite>,
+    /// How many sites were not instrumented because the reserved
+    /// region was full.
+    missed_sites: usize,
+    /// The global in which conditions are saved while being counted,
+    /// set once the module info has been transformed.
+    scratch_global: Option<GlobalIndex>,
+    num_imported_functions: usize,
+    /// Whether the module has a memory to hold the counters.
+    enabled: bool,
+}
+
+/// The module-level branch profiling middleware.
+///
+/// # Panic
+///
+/// An instance of `BranchProfiling` should _not_ be shared among
+/// different modules, since it tracks module-specific information like
+/// the instrumented sites. Attempts to use a `BranchProfiling` instance
+/// from multiple modules will result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::BranchProfiling;
+///
+/// fn create_branch_profiling_middleware(compiler_config: &mut dyn CompilerConfig) -> Arc<BranchProfiling> {
+///     // Let's reserve room for 4096 counters at the start of the
+///     // second page of the memory.
+///     let branch_profiling = Arc::new(BranchProfiling::new(0x1_0000, 4096));
+///
+///     compiler_config.push_middleware(branch_profiling.clone());
+///
+///     // Keep the middleware to get the profile of the instances.
+///     branch_profiling
+/// }
+/// ```
+pub struct BranchProfiling {
+    /// The offset in memory of the region holding the counters.
+    base: u32,
+    /// How many counters fit in the region.
+    capacity: u32,
+    state: Arc<Mutex<ProfilingState>>,
+}
+
+impl BranchProfiling {
+    /// Creates a `BranchProfiling` middleware storing at most `capacity`
+    /// counters from the offset `base` of the memory of the module.
+    ///
+    /// The region is `8 * capacity` bytes long. Sites found once the
+    /// region is full are not instrumented.
+    pub fn new(base: u32, capacity: u32) -> Self {
+        Self {
+            base,
+            capacity,
+            state: Arc::new(Mutex::new(ProfilingState::default())),
+        }
+    }
+
+    /// The offset in memory of the region holding the counters.
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+
+    /// The size in bytes of the region holding the counters.
+    pub fn region_size(&self) -> u64 {
+        self.capacity as u64 * 8
+    }
+
+    /// Reads the counters of an [`Instance`] of the module compiled with
+    /// this middleware.
+    ///
+    /// # Panic
+    ///
+    /// The [`Instance`] must have been processed with this middleware at
+    /// compile time, otherwise this will panic.
+    pub fn profile(&self, instance: &Instance) -> Result<BranchProfile, MemoryAccessError> {
+        let state = self.state.lock().unwrap();
+        let mut counts = Vec::with_capacity(state.sites.len());
+        if !state.sites.is_empty() {
+            let memory = instance
+                .exports
+                .get_memory(MEMORY_EXPORT_NAME)
+                .expect("Can't get `wasmer_branch_profiling_memory` from Instance");
+            let mut bytes = vec![0; state.sites.len() * 8];
+            memory.read(self.base as u64, &mut bytes)?;
+            for (site, count) in state.sites.iter().zip(bytes.chunks_exact(8)) {
+                let mut count_bytes = [0; 8];
+                count_bytes.copy_from_slice(count);
+                counts.push(ProfileCount {
+                    function: site.function,
+                    offset: site.offset,
+                    event: site.event,
+                    count: u64::from_le_bytes(count_bytes),
+                });
+            }
+        }
+        counts.sort_by_key(|count| (count.function, count.offset, count.event));
+        Ok(BranchProfile {
+            counts,
+            missed_sites: state.missed_sites,
+        })
+    }
+
+    /// Sets all the counters of an [`Instance`] back to zero.
+    ///
+    /// # Panic
+    ///
+    /// The [`Instance`] must have been processed with this middleware at
+    /// compile time, otherwise this will panic.
+    pub fn reset(&self, instance: &Instance) -> Result<(), MemoryAccessError> {
+        let state = self.state.lock().unwrap();
+        if state.sites.is_empty() {
+            return Ok(());
+        }
+        let memory = instance
+            .exports
+            .get_memory(MEMORY_EXPORT_NAME)
+            .expect("Can't get `wasmer_branch_profiling_memory` from Instance");
+        memory.write(self.base as u64, &vec![0; state.sites.len() * 8])
+    }
+}
+
+impl fmt::Debug for BranchProfiling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BranchProfiling")
+            .field("base", &self.base)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+impl ModuleMiddleware for BranchProfiling {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let state = self.state.lock().unwrap();
+        Box::new(FunctionBranchProfiling {
+            function: FunctionIndex::new(
+                state.num_imported_functions + local_function_index.index(),
+            ),
+            scratch_global: state.scratch_global,
+            base: self.base,
+            capacity: self.capacity,
+            state: self.state.clone(),
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.scratch_global.is_some() {
+            panic!("BranchProfiling::transform_module_info: Attempting to use a `BranchProfiling` middleware from multiple modules.");
+        }
+
+        // Append a global to save the conditions being counted.
+        let scratch_global = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+
+        state.scratch_global = Some(scratch_global);
+        state.num_imported_functions = module_info.num_imported_functions;
+        state.enabled = !module_info.memories.is_empty();
+        if state.enabled {
+            module_info.exports.insert(
+                MEMORY_EXPORT_NAME.to_string(),
+                ExportIndex::Memory(MemoryIndex::new(0)),
+            );
+        }
+    }
+}
+
+impl MemoryUsage for BranchProfiling {
+    fn size_of_val(&self, _tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + self.state.lock().unwrap().sites.capacity() * mem::size_of::<ProfileSite>()
+    }
+}
+
+/// The function-level branch profiling middleware.
+struct FunctionBranchProfiling {
+    function: FunctionIndex,
+    scratch_global: Option<GlobalIndex>,
+    base: u32,
+    capacity: u32,
+    state: Arc<Mutex<ProfilingState>>,
+}
+
+impl fmt::Debug for FunctionBranchProfiling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionBranchProfiling")
+            .field("function", &self.function)
+            .finish()
+    }
+}
+
+/// The operators incrementing the counter at `address`.
+fn increment<'a>(address: u32) -> [Operator<'a>; 6] {
+    let memarg = MemoryImmediate {
+        align: 3,
+        offset: 0,
+        memory: 0,
+    };
+    [
+        Operator::I32Const {
+            value: address as i32,
+        },
+        Operator::I32Const {
+            value: address as i32,
+        },
+        Operator::I64Load { memarg },
+        Operator::I64Const { value: 1 },
+        Operator::I64Add,
+        Operator::I64Store { memarg },
+    ]
+}
+
+impl FunctionBranchProfiling {
+    /// Allocates the counters for `events` at `offset`, returning their
+    /// addresses, or `None` if they do not fit in the region.
+    fn allocate(&self, offset: usize, events: &[ProfileEvent]) -> Option<Vec<u32>> {
+        let mut state = self.state.lock().unwrap();
+        if !state.enabled {
+            return None;
+        }
+        let first = state.sites.len();
+        if first + events.len() > self.capacity as usize {
+            state.missed_sites += 1;
+            return None;
+        }
+        state.sites.extend(events.iter().map(|&event| ProfileSite {
+            function: self.function,
+            offset,
+            event,
+        }));
+        Some(
+            (first..first + events.len())
+                .map(|index| self.base + index as u32 * 8)
+                .collect(),
+        )
+    }
+}
+
+impl FunctionMiddleware for FunctionBranchProfiling {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let offset = state.operator_offset();
+        match operator {
+            Operator::If { .. } | Operator::BrIf { .. } => {
+                let events = [ProfileEvent::BranchTaken, ProfileEvent::BranchNotTaken];
+                if let Some(addresses) = self.allocate(offset, &events) {
+                    let scratch = self.scratch_global.unwrap().as_u32();
+                    // Save the condition, count it, and put it back for
+                    // the original operator.
+                    state.extend(&[
+                        Operator::GlobalSet {
+                            global_index: scratch,
+                        },
+                        Operator::GlobalGet {
+                            global_index: scratch,
+                        },
+                        Operator::If {
+                            ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+                        },
+                    ]);
+                    state.extend(increment(addresses[0]));
+                    state.push_operator(Operator::Else);
+                    state.extend(increment(addresses[1]));
+                    state.extend(&[
+                        Operator::End,
+                        Operator::GlobalGet {
+                            global_index: scratch,
+                        },
+                    ]);
+                }
+            }
+            Operator::BrTable { .. } | Operator::Call { .. } | Operator::CallIndirect { .. } => {
+                let event = match operator {
+                    Operator::BrTable { .. } => ProfileEvent::BranchTable,
+                    Operator::Call { .. } => ProfileEvent::Call,
+                    _ => ProfileEvent::CallIndirect,
+                };
+                if let Some(addresses) = self.allocate(offset, &[event]) {
+                    state.extend(increment(addresses[0]));
+                }
+            }
+            _ => {}
+        }
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Module, Store, Universal};
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (memory 1)
+            (func $double (param i32) (result i32)
+                local.get 0
+                i32.const 2
+                i32.mul)
+            (func $abs (param i32) (result i32)
+                local.get 0
+                i32.const 0
+                i32.lt_s
+                if (result i32)
+                    i32.const 0
+                    local.get 0
+                    i32.sub
+                else
+                    local.get 0
+                end
+                call $double)
+            (export "abs" (func $abs)))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn counts_branches_and_calls() {
+        let branch_profiling = Arc::new(BranchProfiling::new(1024, 16));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(branch_profiling.clone());
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+
+        let abs = instance
+            .exports
+            .get_function("abs")
+            .unwrap()
+            .native::<i32, i32>()
+            .unwrap();
+        assert_eq!(abs.call(-3).unwrap(), 6);
+        assert_eq!(abs.call(-4).unwrap(), 8);
+        assert_eq!(abs.call(5).unwrap(), 10);
+
+        let profile = branch_profiling.profile(&instance).unwrap();
+        let events = profile
+            .counts()
+            .iter()
+            .map(|count| (count.function.index(), count.event, count.count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                (1, ProfileEvent::BranchTaken, 2),
+                (1, ProfileEvent::BranchNotTaken, 1),
+                (1, ProfileEvent::Call, 3),
+            ]
+        );
+        assert_eq!(profile.missed_sites(), 0);
+
+        let mut text = vec![];
+        profile.write_to(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.starts_with("# wasmer branch profile v1\n"));
+        assert_eq!(text.lines().count(), 4);
+
+        branch_profiling.reset(&instance).unwrap();
+        let profile = branch_profiling.profile(&instance).unwrap();
+        assert!(profile.counts().iter().all(|count| count.count == 0));
+    }
+}
@@ -0,0 +1,430 @@
+//! `cost_presets` provides ready-made cost functions for the
+//! [`Metering`](crate::Metering) middleware, one per backend.
+//!
+//! The costs are relative: `1` is the cost of an integer addition with
+//! the backend of the preset. They approximate the execution time of
+//! each class of operators, from the code the backend emits for it, and
+//! are meant as a sensible default rather than an exact model.
+//!
+//! Operators calling into the runtime, like `memory.grow` or the bulk
+//! memory operators, are given a fixed cost which doesn't depend on
+//! their operands.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use wasmer::CompilerConfig;
+//! use wasmer_middlewares::{CostPreset, Metering};
+//!
+//! fn create_metering_middleware(compiler_config: &mut dyn CompilerConfig) {
+//!     let metering = Arc::new(Metering::with_preset(1_000_000, CostPreset::Aarch64Singlepass));
+//!
+//!     compiler_config.push_middleware(metering);
+//! }
+//! ```
+
+use wasmer::wasmparser::Operator;
+
+/// A cost table shipped with this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CostPreset {
+    /// Costs for the Singlepass compiler targeting x86_64.
+    X86_64Singlepass,
+    /// Costs for the Singlepass compiler targeting aarch64.
+    Aarch64Singlepass,
+    /// Costs for the Cranelift compiler targeting x86_64.
+    Cranelift,
+}
+
+impl CostPreset {
+    /// The cost of `operator` with this preset.
+    pub fn cost(self, operator: &Operator) -> u64 {
+        let class = OperatorClass::of(operator) as usize;
+        match self {
+            Self::X86_64Singlepass => X86_64_SINGLEPASS_COSTS[class],
+            Self::Aarch64Singlepass => AARCH64_SINGLEPASS_COSTS[class],
+            Self::Cranelift => CRANELIFT_COSTS[class],
+        }
+    }
+
+    /// The cost function of this preset, as given to
+    /// [`Metering::new`](crate::Metering::new).
+    pub fn cost_function(self) -> fn(&Operator) -> u64 {
+        match self {
+            Self::X86_64Singlepass => x86_64_singlepass_cost,
+            Self::Aarch64Singlepass => aarch64_singlepass_cost,
+            Self::Cranelift => cranelift_cost,
+        }
+    }
+}
+
+fn x86_64_singlepass_cost(operator: &Operator) -> u64 {
+    CostPreset::X86_64Singlepass.cost(operator)
+}
+
+fn aarch64_singlepass_cost(operator: &Operator) -> u64 {
+    CostPreset::Aarch64Singlepass.cost(operator)
+}
+
+fn cranelift_cost(operator: &Operator) -> u64 {
+    CostPreset::Cranelift.cost(operator)
+}
+
+/// The classes of operators costing the same, used as indexes in the
+/// cost tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OperatorClass {
+    /// Operators emitting no code.
+    Free = 0,
+    Local,
+    Global,
+    Const,
+    IntAlu,
+    IntPopcnt,
+    IntMul,
+    IntDiv,
+    FloatAlu,
+    FloatMinMax,
+    FloatMul,
+    FloatDiv,
+    FloatSqrt,
+    /// Float to integer truncations, which check their operand.
+    FloatTrunc,
+    FloatTruncSat,
+    /// The other conversions.
+    Convert,
+    Load,
+    Store,
+    Branch,
+    BranchTable,
+    Call,
+    CallIndirect,
+    MemorySize,
+    MemoryGrow,
+    /// Bulk memory and table operators, calling into the runtime.
+    Bulk,
+    Table,
+    Atomic,
+    AtomicWait,
+    /// Everything else, mostly SIMD operators.
+    Other,
+}
+
+const NUM_CLASSES: usize = OperatorClass::Other as usize + 1;
+
+// The tables are indexed by `OperatorClass`, in the same order.
+
+const X86_64_SINGLEPASS_COSTS: [u64; NUM_CLASSES] = [
+    0,    // Free
+    1,    // Local
+    2,    // Global
+    1,    // Const
+    1,    // IntAlu
+    1,    // IntPopcnt
+    3,    // IntMul
+    25,   // IntDiv
+    3,    // FloatAlu
+    8,    // FloatMinMax
+    4,    // FloatMul
+    12,   // FloatDiv
+    18,   // FloatSqrt
+    10,   // FloatTrunc
+    6,    // FloatTruncSat
+    2,    // Convert
+    3,    // Load
+    3,    // Store
+    2,    // Branch
+    5,    // BranchTable
+    10,   // Call
+    20,   // CallIndirect
+    30,   // MemorySize
+    5000, // MemoryGrow
+    100,  // Bulk
+    20,   // Table
+    10,   // Atomic
+    1000, // AtomicWait
+    8,    // Other
+];
+
+const AARCH64_SINGLEPASS_COSTS: [u64; NUM_CLASSES] = [
+    0,    // Free
+    1,    // Local
+    2,    // Global
+    1,    // Const
+    1,    // IntAlu
+    4,    // IntPopcnt, going through the SIMD unit
+    3,    // IntMul
+    12,   // IntDiv
+    3,    // FloatAlu
+    6,    // FloatMinMax
+    4,    // FloatMul
+    12,   // FloatDiv
+    18,   // FloatSqrt
+    8,    // FloatTrunc
+    4,    // FloatTruncSat
+    2,    // Convert
+    3,    // Load
+    3,    // Store
+    2,    // Branch
+    5,    // BranchTable
+    10,   // Call
+    20,   // CallIndirect
+    30,   // MemorySize
+    5000, // MemoryGrow
+    100,  // Bulk
+    20,   // Table
+    10,   // Atomic
+    1000, // AtomicWait
+    8,    // Other
+];
+
+const CRANELIFT_COSTS: [u64; NUM_CLASSES] = [
+    0,    // Free
+    0,    // Local, kept in registers
+    1,    // Global
+    0,    // Const, folded in the instructions
+    1,    // IntAlu
+    1,    // IntPopcnt
+    3,    // IntMul
+    25,   // IntDiv
+    3,    // FloatAlu
+    4,    // FloatMinMax
+    4,    // FloatMul
+    12,   // FloatDiv
+    18,   // FloatSqrt
+    6,    // FloatTrunc
+    4,    // FloatTruncSat
+    2,    // Convert
+    2,    // Load
+    2,    // Store
+    1,    // Branch
+    4,    // BranchTable
+    8,    // Call
+    15,   // CallIndirect
+    20,   // MemorySize
+    5000, // MemoryGrow
+    100,  // Bulk
+    15,   // Table
+    8,    // Atomic
+    1000, // AtomicWait
+    4,    // Other
+];
+
+impl OperatorClass {
+    fn of(operator: &Operator) -> Self {
+        use Operator::*;
+
+        match operator {
+            Nop | Block { .. } | Loop { .. } | Else | End | Drop | Unreachable => Self::Free,
+            LocalGet { .. } | LocalSet { .. } | LocalTee { .. } => Self::Local,
+            GlobalGet { .. } | GlobalSet { .. } => Self::Global,
+            I32Const { .. } | I64Const { .. } | F32Const { .. } | F64Const { .. } => Self::Const,
+
+            I32Popcnt | I64Popcnt => Self::IntPopcnt,
+            I32Mul | I64Mul => Self::IntMul,
+            I32DivS | I32DivU | I32RemS | I32RemU | I64DivS | I64DivU | I64RemS | I64RemU => {
+                Self::IntDiv
+            }
+            I32Eqz
+            | I32Eq
+            | I32Ne
+            | I32LtS
+            | I32LtU
+            | I32GtS
+            | I32GtU
+            | I32LeS
+            | I32LeU
+            | I32GeS
+            | I32GeU
+            | I64Eqz
+            | I64Eq
+            | I64Ne
+            | I64LtS
+            | I64LtU
+            | I64GtS
+            | I64GtU
+            | I64LeS
+            | I64LeU
+            | I64GeS
+            | I64GeU
+            | I32Clz
+            | I32Ctz
+            | I32Add
+            | I32Sub
+            | I32And
+            | I32Or
+            | I32Xor
+            | I32Shl
+            | I32ShrS
+            | I32ShrU
+            | I32Rotl
+            | I32Rotr
+            | I64Clz
+            | I64Ctz
+            | I64Add
+            | I64Sub
+            | I64And
+            | I64Or
+            | I64Xor
+            | I64Shl
+            | I64ShrS
+            | I64ShrU
+            | I64Rotl
+            | I64Rotr
+            | I32WrapI64
+            | I64ExtendI32S
+            | I64ExtendI32U
+            | I32Extend8S
+            | I32Extend16S
+            | I64Extend8S
+            | I64Extend16S
+            | I64Extend32S
+            | Select
+            | TypedSelect { .. } => Self::IntAlu,
+
+            F32Min | F32Max | F64Min | F64Max => Self::FloatMinMax,
+            F32Mul | F64Mul => Self::FloatMul,
+            F32Div | F64Div => Self::FloatDiv,
+            F32Sqrt | F64Sqrt => Self::FloatSqrt,
+            F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge | F64Eq | F64Ne | F64Lt | F64Gt
+            | F64Le | F64Ge | F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest
+            | F32Add | F32Sub | F32Copysign | F64Abs | F64Neg | F64Ceil | F64Floor | F64Trunc
+            | F64Nearest | F64Add | F64Sub | F64Copysign => Self::FloatAlu,
+
+            I32TruncF32S | I32TruncF32U | I32TruncF64S | I32TruncF64U | I64TruncF32S
+            | I64TruncF32U | I64TruncF64S | I64TruncF64U => Self::FloatTrunc,
+            I32TruncSatF32S | I32TruncSatF32U | I32TruncSatF64S | I32TruncSatF64U
+            | I64TruncSatF32S | I64TruncSatF32U | I64TruncSatF64S | I64TruncSatF64U => {
+                Self::FloatTruncSat
+            }
+            F32ConvertI32S | F32ConvertI32U | F32ConvertI64S | F32ConvertI64U | F32DemoteF64
+            | F64ConvertI32S | F64ConvertI32U | F64ConvertI64S | F64ConvertI64U | F64PromoteF32
+            | I32ReinterpretF32 | I64ReinterpretF64 | F32ReinterpretI32 | F64ReinterpretI64 => {
+                Self::Convert
+            }
+
+            I32Load { .. }
+            | I64Load { .. }
+            | F32Load { .. }
+            | F64Load { .. }
+            | I32Load8S { .. }
+            | I32Load8U { .. }
+            | I32Load16S { .. }
+            | I32Load16U { .. }
+            | I64Load8S { .. }
+            | I64Load8U { .. }
+            | I64Load16S { .. }
+            | I64Load16U { .. }
+            | I64Load32S { .. }
+            | I64Load32U { .. } => Self::Load,
+            I32Store { .. }
+            | I64Store { .. }
+            | F32Store { .. }
+            | F64Store { .. }
+            | I32Store8 { .. }
+            | I32Store16 { .. }
+            | I64Store8 { .. }
+            | I64Store16 { .. }
+            | I64Store32 { .. } => Self::Store,
+
+            Br { .. } | BrIf { .. } | If { .. } | Return => Self::Branch,
+            BrTable { .. } => Self::BranchTable,
+            Call { .. } | ReturnCall { .. } | RefFunc { .. } => Self::Call,
+            CallIndirect { .. } | ReturnCallIndirect { .. } => Self::CallIndirect,
+
+            MemorySize { .. } => Self::MemorySize,
+            MemoryGrow { .. } => Self::MemoryGrow,
+            MemoryInit { .. }
+            | DataDrop { .. }
+            | MemoryCopy { .. }
+            | MemoryFill { .. }
+            | TableInit { .. }
+            | ElemDrop { .. }
+            | TableCopy { .. }
+            | TableFill { .. }
+            | TableGrow { .. } => Self::Bulk,
+            TableGet { .. } | TableSet { .. } | TableSize { .. } | RefNull { .. } | RefIsNull => {
+                Self::Table
+            }
+
+            MemoryAtomicNotify { .. } | MemoryAtomicWait32 { .. } | MemoryAtomicWait64 { .. } => {
+                Self::AtomicWait
+            }
+            AtomicFence { .. }
+            | I32AtomicLoad { .. }
+            | I64AtomicLoad { .. }
+            | I32AtomicStore { .. }
+            | I64AtomicStore { .. }
+            | I32AtomicRmwAdd { .. }
+            | I64AtomicRmwAdd { .. }
+            | I32AtomicRmwCmpxchg { .. }
+            | I64AtomicRmwCmpxchg { .. } => Self::Atomic,
+
+            _ => Self::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::metering::{get_remaining_points, MeteringPoints};
+    use crate::Metering;
+    use std::sync::Arc;
+    use wasmer::{
+        imports, wat2wasm, CompilerConfig, Cranelift, Instance, Module, Store, Universal,
+    };
+
+    #[test]
+    fn presets_cost_operators() {
+        for preset in &[
+            CostPreset::X86_64Singlepass,
+            CostPreset::Aarch64Singlepass,
+            CostPreset::Cranelift,
+        ] {
+            assert_eq!(preset.cost(&Operator::Nop), 0);
+            assert_eq!(preset.cost(&Operator::I32Add), 1);
+            assert!(preset.cost(&Operator::I64DivS) > preset.cost(&Operator::I64Mul));
+            assert_eq!(
+                preset.cost_function()(&Operator::I64DivS),
+                preset.cost(&Operator::I64DivS)
+            );
+        }
+    }
+
+    #[test]
+    fn metering_with_preset_works() {
+        let metering = Arc::new(Metering::with_preset(100, CostPreset::X86_64Singlepass));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(metering);
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(
+            &store,
+            wat2wasm(
+                br#"
+                (module
+                (func (export "mul") (param i64 i64) (result i64)
+                    local.get 0
+                    local.get 1
+                    i64.mul))
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+        let mul = instance
+            .exports
+            .get_native_function::<(i64, i64), i64>("mul")
+            .unwrap();
+        mul.call(6, 7).unwrap();
+
+        // Two `local.get` costing 1 point, and an `i64.mul` costing 3.
+        assert_eq!(
+            get_remaining_points(&instance),
+            MeteringPoints::Remaining(95)
+        );
+    }
+}
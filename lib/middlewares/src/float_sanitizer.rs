@@ -0,0 +1,559 @@
+//! `float_sanitizer` is a middleware checking the results of the float
+//! arithmetic of a module, to catch the first operator producing a NaN
+//! or an infinity instead of letting it propagate silently through a
+//! numeric pipeline.
+//!
+//! The checked operators are the scalar `add`, `sub`, `mul`, `div`,
+//! `sqrt`, `min`, `max` and `f32.demote_f64`. What happens when one of
+//! them produces a value it shouldn't depends on the
+//! [`FloatSanitizerMode`]: the instance traps, or the violation is only
+//! recorded and the execution goes on. Either way, the last violation
+//! is given by [`FloatSanitizer::violation`] and the number of
+//! violations by [`FloatSanitizer::violations`].
+//!
+//! SIMD float operators are not checked.
+
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::fmt;
+use std::mem;
+use std::sync::Mutex;
+use wasmer::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
+use wasmer::{
+    ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance, LocalFunctionIndex,
+    MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{FunctionIndex, GlobalIndex, ModuleInfo};
+
+/// The name of the export holding the last violation, or -1.
+const VIOLATION_EXPORT_NAME: &str = "wasmer_float_sanitizer_violation";
+
+/// The name of the export holding the number of violations.
+const VIOLATIONS_EXPORT_NAME: &str = "wasmer_float_sanitizer_violations";
+
+/// What the [`FloatSanitizer`] does when an operator produces a NaN or
+/// an infinity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatSanitizerMode {
+    /// Trap, with the violation given by [`FloatSanitizer::violation`].
+    Trap,
+    /// Record the violation and go on, so that the host can check the
+    /// instance after a call.
+    Record,
+}
+
+/// What a checked operator produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatViolationKind {
+    /// A NaN.
+    Nan,
+    /// An infinity, positive or negative.
+    Infinity,
+}
+
+/// An operator which produced a NaN or an infinity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FloatViolation {
+    /// The function of the operator.
+    pub function: FunctionIndex,
+    /// The offset of the operator in the module.
+    pub offset: u32,
+    /// What the operator produced.
+    pub kind: FloatViolationKind,
+}
+
+impl FloatViolation {
+    /// Packs the violation in the value of the violation global. The
+    /// function takes the high 32 bits, the offset the next 31 and the
+    /// kind the lowest one.
+    fn encode(self) -> i64 {
+        let kind = match self.kind {
+            FloatViolationKind::Nan => 0,
+            FloatViolationKind::Infinity => 1,
+        };
+        ((self.function.as_u32() as i64) << 32) | ((self.offset as i64 & 0x7fff_ffff) << 1) | kind
+    }
+
+    fn decode(value: i64) -> Option<Self> {
+        if value < 0 {
+            return None;
+        }
+        Some(Self {
+            function: FunctionIndex::from_u32((value >> 32) as u32),
+            offset: ((value >> 1) & 0x7fff_ffff) as u32,
+            kind: if value & 1 == 0 {
+                FloatViolationKind::Nan
+            } else {
+                FloatViolationKind::Infinity
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug, MemoryUsage)]
+struct FloatSanitizerGlobalIndexes {
+    /// Save the value being checked, by type.
+    f32_value: GlobalIndex,
+    f64_value: GlobalIndex,
+    /// The infinities, to compare with.
+    f32_infinity: GlobalIndex,
+    f64_infinity: GlobalIndex,
+    /// The last violation, encoded, or -1.
+    violation: GlobalIndex,
+    /// The number of violations.
+    violations: GlobalIndex,
+}
+
+/// The module-level float sanitizer middleware.
+///
+/// # Panic
+///
+/// An instance of `FloatSanitizer` should _not_ be shared among
+/// different modules, since it tracks module-specific information like
+/// the global indexes used by the checks. Attempts to use a
+/// `FloatSanitizer` instance from multiple modules will result in a
+/// panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::{float_sanitizer::FloatSanitizerMode, FloatSanitizer};
+///
+/// fn create_float_sanitizer_middleware(compiler_config: &mut dyn CompilerConfig) -> Arc<FloatSanitizer> {
+///     // Let's trap on NaNs, but let infinities through.
+///     let float_sanitizer = Arc::new(
+///         FloatSanitizer::new(FloatSanitizerMode::Trap).check_infinities(false),
+///     );
+///
+///     compiler_config.push_middleware(float_sanitizer.clone());
+///
+///     // Keep the middleware to inspect the violations.
+///     float_sanitizer
+/// }
+/// ```
+pub struct FloatSanitizer {
+    /// What to do on a violation.
+    mode: FloatSanitizerMode,
+
+    /// Whether infinities are violations, as well as NaNs.
+    check_infinities: bool,
+
+    /// The global indexes used by the checks, and the number of
+    /// imported functions.
+    state: Mutex<Option<(FloatSanitizerGlobalIndexes, usize)>>,
+}
+
+impl FloatSanitizer {
+    /// Creates a `FloatSanitizer` middleware checking for NaNs and
+    /// infinities.
+    pub fn new(mode: FloatSanitizerMode) -> Self {
+        Self {
+            mode,
+            check_infinities: true,
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Sets whether infinities are violations, as well as NaNs.
+    pub fn check_infinities(mut self, check: bool) -> Self {
+        self.check_infinities = check;
+        self
+    }
+
+    /// What is done on a violation.
+    pub fn mode(&self) -> FloatSanitizerMode {
+        self.mode
+    }
+
+    /// The last violation in an [`Instance`], if any.
+    ///
+    /// # Panic
+    ///
+    /// The [`Instance`] must have been processed with this middleware at
+    /// compile time, otherwise this will panic.
+    pub fn violation(&self, instance: &Instance) -> Option<FloatViolation> {
+        FloatViolation::decode(get_i64(instance, VIOLATION_EXPORT_NAME))
+    }
+
+    /// The number of violations in an [`Instance`].
+    ///
+    /// # Panic
+    ///
+    /// The [`Instance`] must have been processed with this middleware at
+    /// compile time, otherwise this will panic.
+    pub fn violations(&self, instance: &Instance) -> u64 {
+        get_i64(instance, VIOLATIONS_EXPORT_NAME) as u64
+    }
+
+    /// Forgets the violations of an [`Instance`].
+    ///
+    /// # Panic
+    ///
+    /// The [`Instance`] must have been processed with this middleware at
+    /// compile time, otherwise this will panic.
+    pub fn reset(&self, instance: &Instance) {
+        for (name, value) in [(VIOLATION_EXPORT_NAME, -1i64), (VIOLATIONS_EXPORT_NAME, 0)] {
+            instance
+                .exports
+                .get_global(name)
+                .unwrap_or_else(|_| panic!("Can't get `{}` from Instance", name))
+                .set(value.into())
+                .unwrap_or_else(|_| panic!("Can't set `{}` in Instance", name));
+        }
+    }
+}
+
+fn get_i64(instance: &Instance, name: &str) -> i64 {
+    instance
+        .exports
+        .get_global(name)
+        .unwrap_or_else(|_| panic!("Can't get `{}` from Instance", name))
+        .get()
+        .i64()
+        .unwrap_or_else(|| panic!("`{}` from Instance has wrong type", name))
+}
+
+impl fmt::Debug for FloatSanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FloatSanitizer")
+            .field("mode", &self.mode)
+            .field("check_infinities", &self.check_infinities)
+            .finish()
+    }
+}
+
+impl ModuleMiddleware for FloatSanitizer {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let state = self.state.lock().unwrap();
+        Box::new(FunctionFloatSanitizer {
+            mode: self.mode,
+            check_infinities: self.check_infinities,
+            function: state
+                .as_ref()
+                .map_or(FunctionIndex::new(0), |(_, imported)| {
+                    FunctionIndex::new(imported + local_function_index.index())
+                }),
+            global_indexes: state.as_ref().map(|(indexes, _)| indexes.clone()),
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.is_some() {
+            panic!("FloatSanitizer::transform_module_info: Attempting to use a `FloatSanitizer` middleware from multiple modules.");
+        }
+
+        let mut push_global = |ty, mutability, init| {
+            module_info.global_initializers.push(init);
+            module_info.globals.push(GlobalType::new(ty, mutability))
+        };
+        let indexes = FloatSanitizerGlobalIndexes {
+            f32_value: push_global(Type::F32, Mutability::Var, GlobalInit::F32Const(0.0)),
+            f64_value: push_global(Type::F64, Mutability::Var, GlobalInit::F64Const(0.0)),
+            f32_infinity: push_global(
+                Type::F32,
+                Mutability::Const,
+                GlobalInit::F32Const(f32::INFINITY),
+            ),
+            f64_infinity: push_global(
+                Type::F64,
+                Mutability::Const,
+                GlobalInit::F64Const(f64::INFINITY),
+            ),
+            violation: push_global(Type::I64, Mutability::Var, GlobalInit::I64Const(-1)),
+            violations: push_global(Type::I64, Mutability::Var, GlobalInit::I64Const(0)),
+        };
+
+        module_info.exports.insert(
+            VIOLATION_EXPORT_NAME.to_string(),
+            ExportIndex::Global(indexes.violation),
+        );
+        module_info.exports.insert(
+            VIOLATIONS_EXPORT_NAME.to_string(),
+            ExportIndex::Global(indexes.violations),
+        );
+
+        *state = Some((indexes, module_info.num_imported_functions));
+    }
+}
+
+impl MemoryUsage for FloatSanitizer {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + self
+                .state
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map_or(0, |(indexes, _)| indexes.size_of_val(tracker))
+    }
+}
+
+/// The type of the result of a checked operator.
+#[derive(Clone, Copy)]
+enum FloatResult {
+    F32,
+    F64,
+}
+
+fn float_result(operator: &Operator) -> Option<FloatResult> {
+    match operator {
+        Operator::F32Add
+        | Operator::F32Sub
+        | Operator::F32Mul
+        | Operator::F32Div
+        | Operator::F32Sqrt
+        | Operator::F32Min
+        | Operator::F32Max
+        | Operator::F32DemoteF64 => Some(FloatResult::F32),
+        Operator::F64Add
+        | Operator::F64Sub
+        | Operator::F64Mul
+        | Operator::F64Div
+        | Operator::F64Sqrt
+        | Operator::F64Min
+        | Operator::F64Max => Some(FloatResult::F64),
+        _ => None,
+    }
+}
+
+/// The function-level float sanitizer middleware.
+#[derive(Debug)]
+struct FunctionFloatSanitizer {
+    mode: FloatSanitizerMode,
+    check_infinities: bool,
+    function: FunctionIndex,
+    global_indexes: Option<FloatSanitizerGlobalIndexes>,
+}
+
+impl FunctionFloatSanitizer {
+    /// The operators recording a violation, and trapping in
+    /// [`FloatSanitizerMode::Trap`].
+    fn report<'a>(
+        &self,
+        indexes: &FloatSanitizerGlobalIndexes,
+        violation: FloatViolation,
+    ) -> Vec<Operator<'a>> {
+        let mut operators = vec![
+            Operator::I64Const {
+                value: violation.encode(),
+            },
+            Operator::GlobalSet {
+                global_index: indexes.violation.as_u32(),
+            },
+            Operator::GlobalGet {
+                global_index: indexes.violations.as_u32(),
+            },
+            Operator::I64Const { value: 1 },
+            Operator::I64Add,
+            Operator::GlobalSet {
+                global_index: indexes.violations.as_u32(),
+            },
+        ];
+        if self.mode == FloatSanitizerMode::Trap {
+            operators.push(Operator::Unreachable);
+        }
+        operators
+    }
+
+    /// The operators checking the float on top of the stack, produced by
+    /// the operator at `offset`.
+    fn check<'a>(
+        &self,
+        indexes: &FloatSanitizerGlobalIndexes,
+        result: FloatResult,
+        offset: u32,
+    ) -> Vec<Operator<'a>> {
+        let (value, infinity, abs, eq, ne) = match result {
+            FloatResult::F32 => (
+                indexes.f32_value.as_u32(),
+                indexes.f32_infinity.as_u32(),
+                Operator::F32Abs,
+                Operator::F32Eq,
+                Operator::F32Ne,
+            ),
+            FloatResult::F64 => (
+                indexes.f64_value.as_u32(),
+                indexes.f64_infinity.as_u32(),
+                Operator::F64Abs,
+                Operator::F64Eq,
+                Operator::F64Ne,
+            ),
+        };
+        let violation = |kind| FloatViolation {
+            function: self.function,
+            offset,
+            kind,
+        };
+
+        // if value != value { report NaN }
+        let mut operators = vec![
+            Operator::GlobalSet {
+                global_index: value,
+            },
+            Operator::GlobalGet {
+                global_index: value,
+            },
+            Operator::GlobalGet {
+                global_index: value,
+            },
+            ne,
+            Operator::If {
+                ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+            },
+        ];
+        operators.extend(self.report(indexes, violation(FloatViolationKind::Nan)));
+        operators.push(Operator::End);
+
+        // if abs(value) == infinity { report infinity }
+        if self.check_infinities {
+            operators.extend(vec![
+                Operator::GlobalGet {
+                    global_index: value,
+                },
+                abs,
+                Operator::GlobalGet {
+                    global_index: infinity,
+                },
+                eq,
+                Operator::If {
+                    ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+                },
+            ]);
+            operators.extend(self.report(indexes, violation(FloatViolationKind::Infinity)));
+            operators.push(Operator::End);
+        }
+
+        // Put the value back.
+        operators.push(Operator::GlobalGet {
+            global_index: value,
+        });
+        operators
+    }
+}
+
+impl FunctionMiddleware for FunctionFloatSanitizer {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let offset = state.operator_offset() as u32;
+        let result = float_result(&operator);
+        state.push_operator(operator);
+
+        if let (Some(indexes), Some(result)) = (&self.global_indexes, result) {
+            state.extend(self.check(indexes, result, offset));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Module, Store, Universal};
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (func (export "div") (param f64 f64) (result f64)
+                local.get 0
+                local.get 1
+                f64.div)
+            (func (export "sqrt") (param f32) (result f32)
+                local.get 0
+                f32.sqrt))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn instantiate(float_sanitizer: FloatSanitizer) -> (Arc<FloatSanitizer>, Instance) {
+        let float_sanitizer = Arc::new(float_sanitizer);
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(float_sanitizer.clone());
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+        (float_sanitizer, instance)
+    }
+
+    #[test]
+    fn encodes_violations() {
+        let violation = FloatViolation {
+            function: FunctionIndex::new(3),
+            offset: 0x1234,
+            kind: FloatViolationKind::Infinity,
+        };
+        assert_eq!(FloatViolation::decode(violation.encode()), Some(violation));
+        assert_eq!(FloatViolation::decode(-1), None);
+    }
+
+    #[test]
+    fn traps_on_nans_and_infinities() {
+        let (float_sanitizer, instance) =
+            instantiate(FloatSanitizer::new(FloatSanitizerMode::Trap));
+        let div = instance
+            .exports
+            .get_native_function::<(f64, f64), f64>("div")
+            .unwrap();
+        let sqrt = instance
+            .exports
+            .get_native_function::<f32, f32>("sqrt")
+            .unwrap();
+
+        assert_eq!(div.call(1.0, 4.0).unwrap(), 0.25);
+        assert_eq!(float_sanitizer.violation(&instance), None);
+
+        assert!(div.call(1.0, 0.0).is_err());
+        let violation = float_sanitizer.violation(&instance).unwrap();
+        assert_eq!(violation.function, FunctionIndex::new(0));
+        assert_eq!(violation.kind, FloatViolationKind::Infinity);
+
+        assert!(sqrt.call(-1.0).is_err());
+        let violation = float_sanitizer.violation(&instance).unwrap();
+        assert_eq!(violation.function, FunctionIndex::new(1));
+        assert_eq!(violation.kind, FloatViolationKind::Nan);
+        assert_eq!(float_sanitizer.violations(&instance), 2);
+
+        float_sanitizer.reset(&instance);
+        assert_eq!(float_sanitizer.violation(&instance), None);
+        assert_eq!(float_sanitizer.violations(&instance), 0);
+    }
+
+    #[test]
+    fn records_violations() {
+        let (float_sanitizer, instance) =
+            instantiate(FloatSanitizer::new(FloatSanitizerMode::Record).check_infinities(false));
+        let div = instance
+            .exports
+            .get_native_function::<(f64, f64), f64>("div")
+            .unwrap();
+
+        // Infinities are let through.
+        assert_eq!(div.call(1.0, 0.0).unwrap(), f64::INFINITY);
+        assert_eq!(float_sanitizer.violations(&instance), 0);
+
+        assert!(div.call(0.0, 0.0).unwrap().is_nan());
+        assert!(div.call(0.0, 0.0).unwrap().is_nan());
+        assert_eq!(float_sanitizer.violations(&instance), 2);
+        assert_eq!(
+            float_sanitizer.violation(&instance).unwrap().kind,
+            FloatViolationKind::Nan
+        );
+    }
+}
@@ -1,6 +1,20 @@
+pub mod branch_profiling;
+pub mod cost_presets;
+pub mod float_sanitizer;
+pub mod memory_sanitizer;
 pub mod metering;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod nondeterminism;
+pub mod yield_points;
 
 // The most commonly used symbol are exported at top level of the
 // module. Others are available via modules,
 // e.g. `wasmer_middlewares::metering::get_remaining_points`
+pub use branch_profiling::BranchProfiling;
+pub use cost_presets::CostPreset;
+pub use float_sanitizer::FloatSanitizer;
+pub use memory_sanitizer::MemorySanitizer;
 pub use metering::Metering;
+pub use nondeterminism::NondeterminismScrubber;
+pub use yield_points::YieldPoints;
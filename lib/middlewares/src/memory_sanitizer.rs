@@ -0,0 +1,488 @@
+//! `memory_sanitizer` is a middleware checking the loads and stores
+//! of a module against a shadow map of its linear memory, to catch the
+//! accesses to poisoned areas, like the redzones around the blocks of a
+//! guest allocator, that the bounds checks of the memory cannot catch.
+//!
+//! The memory is split into granules of 8 bytes. The shadow map holds
+//! one byte per granule, not zero if the granule is poisoned, and lives
+//! in a region of the memory reserved by the embedder, from the offset
+//! given to [`MemorySanitizer::new`] and `memory size / 8` bytes long.
+//!
+//! The shadow map is maintained by the guest, calling the `poison` and
+//! `unpoison` functions of the `wasmer_sanitizer` namespace given by
+//! [`MemorySanitizer::imports`], or by the host with
+//! [`MemorySanitizer::poison`] and [`MemorySanitizer::unpoison`]. Since
+//! whole granules are poisoned, the guest allocations should be aligned
+//! and padded to 8 bytes.
+//!
+//! An access touching a poisoned granule traps, and the address of the
+//! poisoned byte is given by [`MemorySanitizer::violation`]. SIMD
+//! accesses and atomic accesses are not checked.
+
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::fmt;
+use std::mem;
+use std::sync::Mutex;
+use wasmer::wasmparser::{
+    MemoryImmediate, Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType,
+};
+use wasmer::{
+    ExportIndex, Exports, Function, FunctionMiddleware, GlobalInit, GlobalType, Instance, LazyInit,
+    LocalFunctionIndex, Memory, MemoryAccessError, MiddlewareError, MiddlewareReaderState,
+    ModuleMiddleware, Mutability, Store, Type, WasmerEnv,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{GlobalIndex, MemoryIndex, ModuleInfo};
+
+/// The name of the export giving access to the checked memory.
+const MEMORY_EXPORT_NAME: &str = "wasmer_sanitizer_memory";
+
+/// The name of the export holding the address of the last violation.
+const VIOLATION_EXPORT_NAME: &str = "wasmer_sanitizer_violation";
+
+/// The size of a granule, as a shift.
+const GRANULE_SHIFT: u32 = 3;
+
+#[derive(Clone, Debug, MemoryUsage)]
+struct SanitizerGlobalIndexes {
+    /// Saves the address of the access being checked.
+    address: GlobalIndex,
+    /// Save the value of the store being checked, by type.
+    i32_value: GlobalIndex,
+    i64_value: GlobalIndex,
+    f32_value: GlobalIndex,
+    f64_value: GlobalIndex,
+    /// The address of the last violation, or -1.
+    violation: GlobalIndex,
+}
+
+/// The module-level memory sanitizer middleware.
+///
+/// # Panic
+///
+/// An instance of `MemorySanitizer` should _not_ be shared among
+/// different modules, since it tracks module-specific information like
+/// the global indexes used by the checks. Attempts to use a
+/// `MemorySanitizer` instance from multiple modules will result in a
+/// panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::MemorySanitizer;
+///
+/// fn create_memory_sanitizer_middleware(compiler_config: &mut dyn CompilerConfig) -> Arc<MemorySanitizer> {
+///     // Let's keep the shadow map from the offset 16MiB of the memory,
+///     // reserved by the guest.
+///     let memory_sanitizer = Arc::new(MemorySanitizer::new(16 * 1024 * 1024));
+///
+///     compiler_config.push_middleware(memory_sanitizer.clone());
+///
+///     // Keep the middleware to provide the `wasmer_sanitizer` imports.
+///     memory_sanitizer
+/// }
+/// ```
+pub struct MemorySanitizer {
+    /// The offset in memory of the shadow map.
+    shadow_base: u32,
+
+    /// The global indexes used by the checks.
+    global_indexes: Mutex<Option<SanitizerGlobalIndexes>>,
+}
+
+impl MemorySanitizer {
+    /// Creates a `MemorySanitizer` middleware keeping the shadow map
+    /// from the offset `shadow_base` of the memory of the module.
+    pub fn new(shadow_base: u32) -> Self {
+        Self {
+            shadow_base,
+            global_indexes: Mutex::new(None),
+        }
+    }
+
+    /// The offset in memory of the shadow map.
+    pub fn shadow_base(&self) -> u32 {
+        self.shadow_base
+    }
+
+    /// The `poison(ptr: i32, len: i32)` and `unpoison(ptr: i32, len: i32)`
+    /// functions to import in the `wasmer_sanitizer` namespace, for
+    /// instances of modules compiled with this middleware.
+    pub fn imports(&self, store: &Store) -> Exports {
+        let env = SanitizerEnv {
+            memory: LazyInit::new(),
+            shadow_base: self.shadow_base,
+        };
+        let mut exports = Exports::new();
+        exports.insert(
+            "poison",
+            Function::new_native_with_env(
+                store,
+                env.clone(),
+                |env: &SanitizerEnv, ptr: u32, len: u32| {
+                    set_shadow(env.memory_ref().unwrap(), env.shadow_base, ptr, len, 1)
+                },
+            ),
+        );
+        exports.insert(
+            "unpoison",
+            Function::new_native_with_env(store, env, |env: &SanitizerEnv, ptr: u32, len: u32| {
+                set_shadow(env.memory_ref().unwrap(), env.shadow_base, ptr, len, 0)
+            }),
+        );
+        exports
+    }
+
+    /// Poisons the granules overlapping `len` bytes from `ptr` in the
+    /// memory of an [`Instance`].
+    ///
+    /// # Panic
+    ///
+    /// The [`Instance`] must have been processed with this middleware at
+    /// compile time, otherwise this will panic.
+    pub fn poison(&self, instance: &Instance, ptr: u32, len: u32) -> Result<(), MemoryAccessError> {
+        set_shadow(memory(instance), self.shadow_base, ptr, len, 1)
+    }
+
+    /// Unpoisons the granules overlapping `len` bytes from `ptr` in the
+    /// memory of an [`Instance`].
+    ///
+    /// # Panic
+    ///
+    /// The [`Instance`] must have been processed with this middleware at
+    /// compile time, otherwise this will panic.
+    pub fn unpoison(
+        &self,
+        instance: &Instance,
+        ptr: u32,
+        len: u32,
+    ) -> Result<(), MemoryAccessError> {
+        set_shadow(memory(instance), self.shadow_base, ptr, len, 0)
+    }
+
+    /// The address of the poisoned byte of the last access which
+    /// trapped in an [`Instance`], if any.
+    ///
+    /// # Panic
+    ///
+    /// The [`Instance`] must have been processed with this middleware at
+    /// compile time, otherwise this will panic.
+    pub fn violation(&self, instance: &Instance) -> Option<u32> {
+        let violation: i64 = instance
+            .exports
+            .get_global(VIOLATION_EXPORT_NAME)
+            .expect("Can't get `wasmer_sanitizer_violation` from Instance")
+            .get()
+            .i64()
+            .expect("`wasmer_sanitizer_violation` from Instance has wrong type");
+        if violation < 0 {
+            None
+        } else {
+            Some(violation as u32)
+        }
+    }
+}
+
+impl fmt::Debug for MemorySanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemorySanitizer")
+            .field("shadow_base", &self.shadow_base)
+            .field("global_indexes", &self.global_indexes)
+            .finish()
+    }
+}
+
+#[derive(WasmerEnv, Clone)]
+struct SanitizerEnv {
+    #[wasmer(export(name = "wasmer_sanitizer_memory"))]
+    memory: LazyInit<Memory>,
+    shadow_base: u32,
+}
+
+fn memory(instance: &Instance) -> &Memory {
+    instance
+        .exports
+        .get_memory(MEMORY_EXPORT_NAME)
+        .expect("Can't get `wasmer_sanitizer_memory` from Instance")
+}
+
+fn set_shadow(
+    memory: &Memory,
+    shadow_base: u32,
+    ptr: u32,
+    len: u32,
+    value: u8,
+) -> Result<(), MemoryAccessError> {
+    if len == 0 {
+        return Ok(());
+    }
+    let first = (ptr as u64) >> GRANULE_SHIFT;
+    let last = (ptr as u64 + len as u64 - 1) >> GRANULE_SHIFT;
+    memory.write(
+        shadow_base as u64 + first,
+        &vec![value; (last - first + 1) as usize],
+    )
+}
+
+impl ModuleMiddleware for MemorySanitizer {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(FunctionMemorySanitizer {
+            shadow_base: self.shadow_base,
+            global_indexes: self.global_indexes.lock().unwrap().clone(),
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut global_indexes = self.global_indexes.lock().unwrap();
+
+        if global_indexes.is_some() {
+            panic!("MemorySanitizer::transform_module_info: Attempting to use a `MemorySanitizer` middleware from multiple modules.");
+        }
+
+        let mut push_global = |ty, init| {
+            module_info.global_initializers.push(init);
+            module_info
+                .globals
+                .push(GlobalType::new(ty, Mutability::Var))
+        };
+        let indexes = SanitizerGlobalIndexes {
+            address: push_global(Type::I32, GlobalInit::I32Const(0)),
+            i32_value: push_global(Type::I32, GlobalInit::I32Const(0)),
+            i64_value: push_global(Type::I64, GlobalInit::I64Const(0)),
+            f32_value: push_global(Type::F32, GlobalInit::F32Const(0.0)),
+            f64_value: push_global(Type::F64, GlobalInit::F64Const(0.0)),
+            violation: push_global(Type::I64, GlobalInit::I64Const(-1)),
+        };
+
+        module_info.exports.insert(
+            VIOLATION_EXPORT_NAME.to_string(),
+            ExportIndex::Global(indexes.violation),
+        );
+        if !module_info.memories.is_empty() {
+            module_info.exports.insert(
+                MEMORY_EXPORT_NAME.to_string(),
+                ExportIndex::Memory(MemoryIndex::new(0)),
+            );
+        }
+
+        *global_indexes = Some(indexes);
+    }
+}
+
+impl MemoryUsage for MemorySanitizer {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.global_indexes.size_of_val(tracker)
+            - mem::size_of_val(&self.global_indexes)
+    }
+}
+
+/// The function-level memory sanitizer middleware.
+#[derive(Debug)]
+struct FunctionMemorySanitizer {
+    shadow_base: u32,
+    global_indexes: Option<SanitizerGlobalIndexes>,
+}
+
+impl FunctionMemorySanitizer {
+    /// The operators trapping if the byte at `offset` from the saved
+    /// address is in a poisoned granule.
+    fn check<'a>(&self, indexes: &SanitizerGlobalIndexes, offset: u32) -> [Operator<'a>; 14] {
+        let address = indexes.address.as_u32();
+        [
+            Operator::GlobalGet {
+                global_index: address,
+            },
+            Operator::I32Const {
+                value: offset as i32,
+            },
+            Operator::I32Add,
+            Operator::I32Const {
+                value: GRANULE_SHIFT as i32,
+            },
+            Operator::I32ShrU,
+            Operator::I32Load8U {
+                memarg: MemoryImmediate {
+                    align: 0,
+                    offset: self.shadow_base as u64,
+                    memory: 0,
+                },
+            },
+            Operator::If {
+                ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+            },
+            Operator::GlobalGet {
+                global_index: address,
+            },
+            Operator::I32Const {
+                value: offset as i32,
+            },
+            Operator::I32Add,
+            Operator::I64ExtendI32U,
+            Operator::GlobalSet {
+                global_index: indexes.violation.as_u32(),
+            },
+            Operator::Unreachable,
+            Operator::End,
+        ]
+    }
+}
+
+impl FunctionMiddleware for FunctionMemorySanitizer {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let indexes = match &self.global_indexes {
+            Some(indexes) => indexes,
+            None => {
+                state.push_operator(operator);
+                return Ok(());
+            }
+        };
+
+        // The memory immediate, the size of the access, and for stores,
+        // the global saving the stored value.
+        let access = match operator {
+            Operator::I32Load { memarg } | Operator::F32Load { memarg } => Some((memarg, 4, None)),
+            Operator::I64Load { memarg } | Operator::F64Load { memarg } => Some((memarg, 8, None)),
+            Operator::I32Load8S { memarg }
+            | Operator::I32Load8U { memarg }
+            | Operator::I64Load8S { memarg }
+            | Operator::I64Load8U { memarg } => Some((memarg, 1, None)),
+            Operator::I32Load16S { memarg }
+            | Operator::I32Load16U { memarg }
+            | Operator::I64Load16S { memarg }
+            | Operator::I64Load16U { memarg } => Some((memarg, 2, None)),
+            Operator::I64Load32S { memarg } | Operator::I64Load32U { memarg } => {
+                Some((memarg, 4, None))
+            }
+            Operator::I32Store { memarg } => Some((memarg, 4, Some(indexes.i32_value))),
+            Operator::I32Store8 { memarg } => Some((memarg, 1, Some(indexes.i32_value))),
+            Operator::I32Store16 { memarg } => Some((memarg, 2, Some(indexes.i32_value))),
+            Operator::I64Store { memarg } => Some((memarg, 8, Some(indexes.i64_value))),
+            Operator::I64Store8 { memarg } => Some((memarg, 1, Some(indexes.i64_value))),
+            Operator::I64Store16 { memarg } => Some((memarg, 2, Some(indexes.i64_value))),
+            Operator::I64Store32 { memarg } => Some((memarg, 4, Some(indexes.i64_value))),
+            Operator::F32Store { memarg } => Some((memarg, 4, Some(indexes.f32_value))),
+            Operator::F64Store { memarg } => Some((memarg, 8, Some(indexes.f64_value))),
+            _ => None,
+        };
+
+        if let Some((memarg, size, value)) = access {
+            // Accesses with an offset past 4GiB trap anyway.
+            if memarg.memory == 0 && memarg.offset + size <= u32::MAX as u64 {
+                let first = memarg.offset as u32;
+                let last = first + size as u32 - 1;
+
+                // Save the stored value, then the address.
+                if let Some(value) = value {
+                    state.push_operator(Operator::GlobalSet {
+                        global_index: value.as_u32(),
+                    });
+                }
+                state.push_operator(Operator::GlobalSet {
+                    global_index: indexes.address.as_u32(),
+                });
+
+                state.extend(self.check(indexes, first));
+                if size > 1 {
+                    state.extend(self.check(indexes, last));
+                }
+
+                // Put the address and the value back for the access.
+                state.push_operator(Operator::GlobalGet {
+                    global_index: indexes.address.as_u32(),
+                });
+                if let Some(value) = value {
+                    state.push_operator(Operator::GlobalGet {
+                        global_index: value.as_u32(),
+                    });
+                }
+            }
+        }
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Module, Universal};
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (import "wasmer_sanitizer" "poison" (func $poison (param i32 i32)))
+            (memory 1)
+            (func (export "setup")
+                ;; A redzone after a 64 bytes block.
+                i32.const 64
+                i32.const 8
+                call $poison)
+            (func (export "load") (param i32) (result i32)
+                local.get 0
+                i32.load)
+            (func (export "store") (param i32 i64)
+                local.get 0
+                local.get 1
+                i64.store offset=4))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn traps_on_poisoned_accesses() {
+        let memory_sanitizer = Arc::new(MemorySanitizer::new(0x8000));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(memory_sanitizer.clone());
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+        let sanitizer_imports = memory_sanitizer.imports(&store);
+        let import_object = imports! {
+            "wasmer_sanitizer" => sanitizer_imports,
+        };
+        let instance = Instance::new(&module, &import_object).unwrap();
+
+        instance
+            .exports
+            .get_native_function::<(), ()>("setup")
+            .unwrap()
+            .call()
+            .unwrap();
+        let load = instance
+            .exports
+            .get_native_function::<i32, i32>("load")
+            .unwrap();
+        let store = instance
+            .exports
+            .get_native_function::<(i32, i64), ()>("store")
+            .unwrap();
+
+        load.call(60).unwrap();
+        assert_eq!(memory_sanitizer.violation(&instance), None);
+
+        // Bytes 62 to 65 overlap the redzone.
+        assert!(load.call(62).is_err());
+        assert_eq!(memory_sanitizer.violation(&instance), Some(65));
+
+        // Bytes 60 to 67, with the offset.
+        assert!(store.call(56, 1).is_err());
+        assert_eq!(memory_sanitizer.violation(&instance), Some(67));
+
+        memory_sanitizer.unpoison(&instance, 64, 8).unwrap();
+        load.call(62).unwrap();
+        store.call(56, 1).unwrap();
+    }
+}
@@ -20,6 +20,8 @@ use wasmer::{
 };
 use wasmer_types::{GlobalIndex, ModuleInfo};
 
+use crate::CostPreset;
+
 #[derive(Clone, MemoryUsage)]
 struct MeteringGlobalIndexes(GlobalIndex, GlobalIndex);
 
@@ -135,6 +137,16 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> Metering<F> {
     }
 }
 
+impl Metering<fn(&Operator) -> u64> {
+    /// Creates a `Metering` middleware using the cost table of `preset`.
+    ///
+    /// See the [`cost_presets`](crate::cost_presets) module for the
+    /// available presets.
+    pub fn with_preset(initial_limit: u64, preset: CostPreset) -> Self {
+        Self::new(initial_limit, preset.cost_function())
+    }
+}
+
 impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for Metering<F> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Metering")
@@ -0,0 +1,186 @@
+//! `metrics` is an import namespace, `wasmer_metrics`, giving guests
+//! access to performance counters of the host, so that benchmarking
+//! harnesses running inside WebAssembly can measure themselves, for
+//! example to compare compilers.
+//!
+//! The namespace provides the following functions:
+//!
+//! - `cycles() -> i64`: the cycle counter of the CPU, the time-stamp
+//!   counter on x86-64 and the virtual counter on AArch64, or `-1` on
+//!   other architectures. Only differences between two readings on the
+//!   same thread are meaningful;
+//! - `time_ns() -> i64`: the nanoseconds elapsed on a monotonic clock
+//!   since the namespace was created;
+//! - `remaining_points() -> i64`: the points left to the instance by the
+//!   [`Metering`](crate::Metering) middleware, or `-1` if the module isn't
+//!   metered. Since the middleware accounts for the cost of the operators
+//!   before each call, it includes everything executed so far.
+//!
+//! The namespace is only available with the `metrics` feature of the
+//! crate.
+//!
+//! ```
+//! # use wasmer::{imports, Instance, Module, Store};
+//! # use wasmer_middlewares::metrics::{metrics_namespace, NAMESPACE};
+//! # let store = Store::default();
+//! let module = Module::new(&store, r#"(module
+//!     (import "wasmer_metrics" "time_ns" (func $time_ns (result i64)))
+//!     (func (export "elapsed") (result i64)
+//!         (local $start i64)
+//!         (local.set $start (call $time_ns))
+//!         (i64.sub (call $time_ns) (local.get $start))))"#)?;
+//! let metrics = metrics_namespace(&store);
+//! let import_object = imports! {
+//!     NAMESPACE => metrics,
+//! };
+//! let instance = Instance::new(&module, &import_object)?;
+//! let elapsed = instance.exports.get_native_function::<(), i64>("elapsed")?;
+//! assert!(elapsed.call()? >= 0);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::time::Instant;
+use wasmer::{Exports, Function, Global, LazyInit, Store, Value, WasmerEnv};
+
+/// The name of the import namespace.
+pub const NAMESPACE: &str = "wasmer_metrics";
+
+#[derive(WasmerEnv, Clone)]
+struct MetricsEnv {
+    start: Instant,
+    #[wasmer(export(optional = true, name = "wasmer_metering_remaining_points"))]
+    remaining_points: LazyInit<Global>,
+    #[wasmer(export(optional = true, name = "wasmer_metering_points_exhausted"))]
+    points_exhausted: LazyInit<Global>,
+}
+
+/// Creates the functions of the `wasmer_metrics` namespace, to be
+/// registered under [`NAMESPACE`].
+pub fn metrics_namespace(store: &Store) -> Exports {
+    let env = MetricsEnv {
+        start: Instant::now(),
+        remaining_points: LazyInit::new(),
+        points_exhausted: LazyInit::new(),
+    };
+    let mut namespace = Exports::new();
+    namespace.insert("cycles", Function::new_native(store, cycles));
+    namespace.insert(
+        "time_ns",
+        Function::new_native_with_env(store, env.clone(), time_ns),
+    );
+    namespace.insert(
+        "remaining_points",
+        Function::new_native_with_env(store, env, remaining_points),
+    );
+    namespace
+}
+
+fn cycles() -> i64 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        unsafe { std::arch::x86_64::_rdtsc() as i64 }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        let counter: u64;
+        unsafe { std::arch::asm!("mrs {}, cntvct_el0", out(reg) counter) };
+        counter as i64
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        -1
+    }
+}
+
+fn time_ns(env: &MetricsEnv) -> i64 {
+    env.start.elapsed().as_nanos() as i64
+}
+
+fn remaining_points(env: &MetricsEnv) -> i64 {
+    let (remaining_points, points_exhausted) =
+        match (env.remaining_points_ref(), env.points_exhausted_ref()) {
+            (Some(remaining_points), Some(points_exhausted)) => {
+                (remaining_points, points_exhausted)
+            }
+            _ => return -1,
+        };
+    if let Value::I32(exhausted) = points_exhausted.get() {
+        if exhausted > 0 {
+            return 0;
+        }
+    }
+    match remaining_points.get() {
+        Value::I64(points) => points,
+        _ => -1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Metering;
+    use std::sync::Arc;
+    use wasmer::wasmparser::Operator;
+    use wasmer::{imports, CompilerConfig, Cranelift, Instance, Module, Universal};
+
+    const WAT: &str = r#"
+        (module
+          (import "wasmer_metrics" "cycles" (func $cycles (result i64)))
+          (import "wasmer_metrics" "time_ns" (func $time_ns (result i64)))
+          (import "wasmer_metrics" "remaining_points" (func $remaining_points (result i64)))
+          (func (export "cycles") (result i64) (call $cycles))
+          (func (export "time_ns") (result i64) (call $time_ns))
+          (func (export "remaining_points") (result i64)
+            (drop (i32.const 0))
+            (call $remaining_points)))
+    "#;
+
+    fn instance(store: &Store) -> Instance {
+        let module = Module::new(store, WAT).unwrap();
+        let metrics = metrics_namespace(store);
+        let import_object = imports! {
+            NAMESPACE => metrics,
+        };
+        Instance::new(&module, &import_object).unwrap()
+    }
+
+    fn call(instance: &Instance, name: &str) -> i64 {
+        let function = instance.exports.get_native_function::<(), i64>(name);
+        function.unwrap().call().unwrap()
+    }
+
+    #[test]
+    fn counters_are_monotonic() {
+        let store = Store::default();
+        let instance = instance(&store);
+
+        let time = call(&instance, "time_ns");
+        assert!(time >= 0);
+        assert!(call(&instance, "time_ns") >= time);
+
+        let cycles = call(&instance, "cycles");
+        if cfg!(any(target_arch = "x86_64", target_arch = "aarch64")) {
+            assert!(call(&instance, "cycles") >= cycles);
+        } else {
+            assert_eq!(cycles, -1);
+        }
+    }
+
+    #[test]
+    fn remaining_points_are_read_from_the_metering_globals() {
+        let store = Store::default();
+        assert_eq!(call(&instance(&store), "remaining_points"), -1);
+
+        let metering = Arc::new(Metering::new(100, |operator: &Operator| match operator {
+            Operator::I32Const { .. } => 1,
+            _ => 0,
+        }));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(metering);
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let instance = instance(&store);
+        assert_eq!(call(&instance, "remaining_points"), 99);
+        assert_eq!(call(&instance, "remaining_points"), 98);
+    }
+}
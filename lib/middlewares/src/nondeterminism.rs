@@ -0,0 +1,611 @@
+//! `nondeterminism` is a middleware finding the sources of
+//! nondeterminism of a module, and rewriting or rejecting them, so that
+//! the module runs the same way on every host. This is needed, for
+//! example, by consensus systems which execute the same contracts on
+//! many machines.
+//!
+//! The sources of nondeterminism are:
+//!
+//! * the float operators which can produce a NaN: the bits of the NaN
+//!   they produce depend on the host, and can be observed with a
+//!   reinterpretation or a store;
+//! * the SIMD float operators, for the same reason;
+//! * the imported functions denied by the configuration, like the
+//!   functions reading a clock.
+//!
+//! What is done with them depends on the [`ScrubMode`]. In every mode,
+//! the sources found are listed by [`NondeterminismScrubber::report`].
+
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::collections::HashSet;
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use wasmer::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
+use wasmer::{
+    FunctionMiddleware, GlobalInit, GlobalType, LocalFunctionIndex, MiddlewareError,
+    MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{FunctionIndex, GlobalIndex, ImportIndex, ModuleInfo};
+
+/// The name used in the errors of this middleware.
+const MIDDLEWARE_NAME: &str = "nondeterminism_scrubber";
+
+/// The bits of the canonical NaNs.
+const CANONICAL_NAN_F32: u32 = 0x7fc0_0000;
+const CANONICAL_NAN_F64: u64 = 0x7ff8_0000_0000_0000;
+
+/// The imports of WASI which are not deterministic.
+const WASI_NONDETERMINISTIC_IMPORTS: &[&str] = &[
+    "clock_res_get",
+    "clock_time_get",
+    "random_get",
+    "sched_yield",
+];
+
+/// What the [`NondeterminismScrubber`] does with the sources of
+/// nondeterminism.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrubMode {
+    /// Only report the sources, without changing the module.
+    Report,
+    /// Replace the NaNs produced by scalar float operators by canonical
+    /// NaNs, and trap on the calls to denied imports. SIMD float
+    /// operators can't be rewritten cheaply, so they are rejected.
+    CanonicalizeNans,
+    /// Trap when a scalar float operator produces a NaN, and on the
+    /// calls to denied imports. SIMD float operators are rejected.
+    TrapOnNans,
+    /// Fail the compilation of the modules holding any source.
+    Reject,
+}
+
+/// A kind of source of nondeterminism.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NondeterminismKind {
+    /// A scalar float operator which can produce a NaN.
+    FloatNan,
+    /// A SIMD float operator which can produce NaNs.
+    SimdFloatNan,
+    /// A denied imported function.
+    DeniedImport {
+        /// The module of the import.
+        module: String,
+        /// The name of the import.
+        name: String,
+    },
+}
+
+/// A source of nondeterminism found in a module.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NondeterminismSource {
+    /// The function holding the source, or `None` for imports.
+    pub function: Option<FunctionIndex>,
+    /// The offset of the operator in the module, or `0` for imports.
+    pub offset: usize,
+    /// The kind of source.
+    pub kind: NondeterminismKind,
+}
+
+#[derive(Debug, Default)]
+struct ScrubberState {
+    sources: Vec<NondeterminismSource>,
+    num_imported_functions: usize,
+    /// The denied imported functions.
+    denied_functions: HashSet<FunctionIndex>,
+    /// The globals in which the float results are saved while being
+    /// checked, set once the module info has been transformed.
+    scratch_globals: Option<(GlobalIndex, GlobalIndex)>,
+}
+
+/// The module-level nondeterminism scrubber middleware.
+///
+/// # Panic
+///
+/// An instance of `NondeterminismScrubber` should _not_ be shared among
+/// different modules, since it tracks module-specific information like
+/// the sources found. Attempts to use a `NondeterminismScrubber`
+/// instance from multiple modules will result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::nondeterminism::{NondeterminismScrubber, ScrubMode};
+///
+/// fn create_nondeterminism_scrubber(compiler_config: &mut dyn CompilerConfig) -> Arc<NondeterminismScrubber> {
+///     let scrubber = Arc::new(
+///         NondeterminismScrubber::new(ScrubMode::CanonicalizeNans)
+///             .deny_wasi_nondeterminism()
+///             .deny_import("env", "now"),
+///     );
+///
+///     compiler_config.push_middleware(scrubber.clone());
+///
+///     // Keep the middleware to get the report once compiled.
+///     scrubber
+/// }
+/// ```
+pub struct NondeterminismScrubber {
+    mode: ScrubMode,
+    /// The denied imports, by module and name.
+    denied_imports: HashSet<(String, String)>,
+    state: Arc<Mutex<ScrubberState>>,
+}
+
+impl NondeterminismScrubber {
+    /// Creates a `NondeterminismScrubber` middleware with the given mode
+    /// and no denied import.
+    pub fn new(mode: ScrubMode) -> Self {
+        Self {
+            mode,
+            denied_imports: HashSet::new(),
+            state: Arc::new(Mutex::new(ScrubberState::default())),
+        }
+    }
+
+    /// Denies the imported function `name` from `module`.
+    pub fn deny_import(mut self, module: impl Into<String>, name: impl Into<String>) -> Self {
+        self.denied_imports.insert((module.into(), name.into()));
+        self
+    }
+
+    /// Denies the imported functions of every WASI version which read a
+    /// clock, produce random bytes, or yield to the scheduler.
+    pub fn deny_wasi_nondeterminism(mut self) -> Self {
+        for module in &["wasi_unstable", "wasi_snapshot_preview1"] {
+            for name in WASI_NONDETERMINISTIC_IMPORTS {
+                self = self.deny_import(*module, *name);
+            }
+        }
+        self
+    }
+
+    /// The mode of this middleware.
+    pub fn mode(&self) -> ScrubMode {
+        self.mode
+    }
+
+    /// The sources of nondeterminism found in the module compiled with
+    /// this middleware, sorted by function and offset.
+    pub fn report(&self) -> Vec<NondeterminismSource> {
+        let mut sources = self.state.lock().unwrap().sources.clone();
+        sources.sort();
+        sources
+    }
+}
+
+impl fmt::Debug for NondeterminismScrubber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NondeterminismScrubber")
+            .field("mode", &self.mode)
+            .field("denied_imports", &self.denied_imports)
+            .finish()
+    }
+}
+
+impl ModuleMiddleware for NondeterminismScrubber {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let state = self.state.lock().unwrap();
+        Box::new(FunctionNondeterminismScrubber {
+            mode: self.mode,
+            function: FunctionIndex::new(
+                state.num_imported_functions + local_function_index.index(),
+            ),
+            denied_functions: state.denied_functions.clone(),
+            scratch_globals: state.scratch_globals,
+            state: self.state.clone(),
+            checked_imports: false,
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.scratch_globals.is_some() {
+            panic!("NondeterminismScrubber::transform_module_info: Attempting to use a `NondeterminismScrubber` middleware from multiple modules.");
+        }
+
+        for ((module, name, _), import) in &module_info.imports {
+            if let ImportIndex::Function(function_index) = import {
+                if self
+                    .denied_imports
+                    .contains(&(module.clone(), name.clone()))
+                {
+                    state.denied_functions.insert(*function_index);
+                    state.sources.push(NondeterminismSource {
+                        function: None,
+                        offset: 0,
+                        kind: NondeterminismKind::DeniedImport {
+                            module: module.clone(),
+                            name: name.clone(),
+                        },
+                    });
+                }
+            }
+        }
+        state.num_imported_functions = module_info.num_imported_functions;
+
+        // Append the globals saving the float results being checked.
+        let f32_scratch = module_info
+            .globals
+            .push(GlobalType::new(Type::F32, Mutability::Var));
+        module_info
+            .global_initializers
+            .push(GlobalInit::F32Const(0.0));
+        let f64_scratch = module_info
+            .globals
+            .push(GlobalType::new(Type::F64, Mutability::Var));
+        module_info
+            .global_initializers
+            .push(GlobalInit::F64Const(0.0));
+        state.scratch_globals = Some((f32_scratch, f64_scratch));
+    }
+}
+
+impl MemoryUsage for NondeterminismScrubber {
+    fn size_of_val(&self, _tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + self.state.lock().unwrap().sources.capacity() * mem::size_of::<NondeterminismSource>()
+    }
+}
+
+/// The function-level nondeterminism scrubber middleware.
+struct FunctionNondeterminismScrubber {
+    mode: ScrubMode,
+    function: FunctionIndex,
+    denied_functions: HashSet<FunctionIndex>,
+    scratch_globals: Option<(GlobalIndex, GlobalIndex)>,
+    state: Arc<Mutex<ScrubberState>>,
+    /// Whether the denied imports have been checked, in `Reject` mode.
+    checked_imports: bool,
+}
+
+impl fmt::Debug for FunctionNondeterminismScrubber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionNondeterminismScrubber")
+            .field("mode", &self.mode)
+            .field("function", &self.function)
+            .finish()
+    }
+}
+
+/// A scalar float operator producing a value of this type can produce
+/// a NaN.
+#[derive(Clone, Copy)]
+enum FloatResult {
+    F32,
+    F64,
+}
+
+fn float_result(operator: &Operator) -> Option<FloatResult> {
+    match operator {
+        Operator::F32Add
+        | Operator::F32Sub
+        | Operator::F32Mul
+        | Operator::F32Div
+        | Operator::F32Sqrt
+        | Operator::F32Min
+        | Operator::F32Max
+        | Operator::F32Ceil
+        | Operator::F32Floor
+        | Operator::F32Trunc
+        | Operator::F32Nearest
+        | Operator::F32DemoteF64 => Some(FloatResult::F32),
+        Operator::F64Add
+        | Operator::F64Sub
+        | Operator::F64Mul
+        | Operator::F64Div
+        | Operator::F64Sqrt
+        | Operator::F64Min
+        | Operator::F64Max
+        | Operator::F64Ceil
+        | Operator::F64Floor
+        | Operator::F64Trunc
+        | Operator::F64Nearest
+        | Operator::F64PromoteF32 => Some(FloatResult::F64),
+        _ => None,
+    }
+}
+
+fn is_simd_float(operator: &Operator) -> bool {
+    matches!(
+        operator,
+        Operator::F32x4Add
+            | Operator::F32x4Sub
+            | Operator::F32x4Mul
+            | Operator::F32x4Div
+            | Operator::F32x4Sqrt
+            | Operator::F32x4Min
+            | Operator::F32x4Max
+            | Operator::F32x4Ceil
+            | Operator::F32x4Floor
+            | Operator::F32x4Trunc
+            | Operator::F32x4Nearest
+            | Operator::F32x4DemoteF64x2Zero
+            | Operator::F64x2Add
+            | Operator::F64x2Sub
+            | Operator::F64x2Mul
+            | Operator::F64x2Div
+            | Operator::F64x2Sqrt
+            | Operator::F64x2Min
+            | Operator::F64x2Max
+            | Operator::F64x2Ceil
+            | Operator::F64x2Floor
+            | Operator::F64x2Trunc
+            | Operator::F64x2Nearest
+            | Operator::F64x2PromoteLowF32x4
+    )
+}
+
+impl FunctionNondeterminismScrubber {
+    fn record(&self, offset: usize, kind: NondeterminismKind) {
+        self.state
+            .lock()
+            .unwrap()
+            .sources
+            .push(NondeterminismSource {
+                function: Some(self.function),
+                offset,
+                kind,
+            });
+    }
+
+    fn reject(&self, offset: usize, what: &str) -> MiddlewareError {
+        MiddlewareError::new(
+            MIDDLEWARE_NAME,
+            format!(
+                "{} at offset {} in function {} is not deterministic",
+                what,
+                offset,
+                self.function.index()
+            ),
+        )
+    }
+
+    /// The operators checking the float on top of the stack, saved in
+    /// `scratch`.
+    fn scrub_float<'a>(&self, result: FloatResult, scratch: GlobalIndex) -> Vec<Operator<'a>> {
+        let scratch = scratch.as_u32();
+        let (canonical_nan, not_nan, is_nan) = match result {
+            FloatResult::F32 => (
+                [
+                    Operator::I32Const {
+                        value: CANONICAL_NAN_F32 as i32,
+                    },
+                    Operator::F32ReinterpretI32,
+                ],
+                Operator::F32Eq,
+                Operator::F32Ne,
+            ),
+            FloatResult::F64 => (
+                [
+                    Operator::I64Const {
+                        value: CANONICAL_NAN_F64 as i64,
+                    },
+                    Operator::F64ReinterpretI64,
+                ],
+                Operator::F64Eq,
+                Operator::F64Ne,
+            ),
+        };
+        let mut operators = vec![
+            Operator::GlobalSet {
+                global_index: scratch,
+            },
+            Operator::GlobalGet {
+                global_index: scratch,
+            },
+        ];
+        if self.mode == ScrubMode::CanonicalizeNans {
+            // select(value, canonical NaN, value == value)
+            operators.extend(canonical_nan);
+            operators.extend(vec![
+                Operator::GlobalGet {
+                    global_index: scratch,
+                },
+                Operator::GlobalGet {
+                    global_index: scratch,
+                },
+                not_nan,
+                Operator::Select,
+            ]);
+        } else {
+            // if value != value { unreachable }
+            operators.extend(vec![
+                Operator::GlobalGet {
+                    global_index: scratch,
+                },
+                is_nan,
+                Operator::If {
+                    ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+                },
+                Operator::Unreachable,
+                Operator::End,
+                Operator::GlobalGet {
+                    global_index: scratch,
+                },
+            ]);
+        }
+        operators
+    }
+}
+
+impl FunctionMiddleware for FunctionNondeterminismScrubber {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let offset = state.operator_offset();
+
+        // Modules with denied imports are rejected as soon as one of
+        // their functions is compiled.
+        if self.mode == ScrubMode::Reject && !self.checked_imports {
+            self.checked_imports = true;
+            if !self.denied_functions.is_empty() {
+                return Err(MiddlewareError::new(
+                    MIDDLEWARE_NAME,
+                    "the module imports functions which are not deterministic",
+                ));
+            }
+        }
+
+        if let Operator::Call { function_index } = operator {
+            if self
+                .denied_functions
+                .contains(&FunctionIndex::from_u32(function_index))
+                && self.mode != ScrubMode::Report
+            {
+                // The call is still emitted, but never reached.
+                state.push_operator(Operator::Unreachable);
+            }
+            state.push_operator(operator);
+            return Ok(());
+        }
+
+        if is_simd_float(&operator) {
+            self.record(offset, NondeterminismKind::SimdFloatNan);
+            if self.mode != ScrubMode::Report {
+                return Err(self.reject(offset, "a SIMD float operator"));
+            }
+            state.push_operator(operator);
+            return Ok(());
+        }
+
+        let result = float_result(&operator);
+        state.push_operator(operator);
+        if let Some(result) = result {
+            self.record(offset, NondeterminismKind::FloatNan);
+            match self.mode {
+                ScrubMode::Report => {}
+                ScrubMode::Reject => return Err(self.reject(offset, "a float operator")),
+                ScrubMode::CanonicalizeNans | ScrubMode::TrapOnNans => {
+                    let (f32_scratch, f64_scratch) = self.scratch_globals.unwrap();
+                    let scratch = match result {
+                        FloatResult::F32 => f32_scratch,
+                        FloatResult::F64 => f64_scratch,
+                    };
+                    state.extend(self.scrub_float(result, scratch));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wasmer::{
+        imports, wat2wasm, CompilerConfig, Cranelift, Function, Instance, Module, Store, Universal,
+    };
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (import "env" "now" (func $now (result i64)))
+            (func (export "nan_bits") (param f32) (result i32)
+                local.get 0
+                local.get 0
+                f32.sub
+                i32.reinterpret_f32)
+            (func (export "now") (result i64)
+                call $now))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn compile(mode: ScrubMode) -> (Arc<NondeterminismScrubber>, Result<Instance, String>) {
+        let scrubber = Arc::new(NondeterminismScrubber::new(mode).deny_import("env", "now"));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(scrubber.clone());
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let instance = Module::new(&store, bytecode())
+            .map_err(|e| e.to_string())
+            .and_then(|module| {
+                let import_object = imports! {
+                    "env" => {
+                        "now" => Function::new_native(&store, || 42i64),
+                    },
+                };
+                Instance::new(&module, &import_object).map_err(|e| e.to_string())
+            });
+        (scrubber, instance)
+    }
+
+    #[test]
+    fn reports_sources() {
+        let (scrubber, instance) = compile(ScrubMode::Report);
+        instance.unwrap();
+        let kinds = scrubber
+            .report()
+            .into_iter()
+            .map(|source| source.kind)
+            .collect::<Vec<_>>();
+        // Imports come first, having no function.
+        assert_eq!(
+            kinds,
+            vec![
+                NondeterminismKind::DeniedImport {
+                    module: "env".to_string(),
+                    name: "now".to_string(),
+                },
+                NondeterminismKind::FloatNan,
+            ]
+        );
+    }
+
+    #[test]
+    fn canonicalizes_nans() {
+        let (_, instance) = compile(ScrubMode::CanonicalizeNans);
+        let instance = instance.unwrap();
+        let nan_bits = instance
+            .exports
+            .get_native_function::<f32, i32>("nan_bits")
+            .unwrap();
+        // inf - inf is a NaN, whose bits depend on the host.
+        assert_eq!(
+            nan_bits.call(f32::INFINITY).unwrap(),
+            CANONICAL_NAN_F32 as i32
+        );
+        assert_eq!(nan_bits.call(1.0).unwrap(), 0);
+
+        // Denied imports trap.
+        let now = instance
+            .exports
+            .get_native_function::<(), i64>("now")
+            .unwrap();
+        assert!(now.call().is_err());
+    }
+
+    #[test]
+    fn traps_on_nans() {
+        let (_, instance) = compile(ScrubMode::TrapOnNans);
+        let instance = instance.unwrap();
+        let nan_bits = instance
+            .exports
+            .get_native_function::<f32, i32>("nan_bits")
+            .unwrap();
+        assert!(nan_bits.call(f32::INFINITY).is_err());
+        assert_eq!(nan_bits.call(1.0).unwrap(), 0);
+    }
+
+    #[test]
+    fn rejects_sources() {
+        let (_, instance) = compile(ScrubMode::Reject);
+        assert!(instance.unwrap_err().contains("not deterministic"));
+    }
+}
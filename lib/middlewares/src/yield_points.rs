@@ -0,0 +1,480 @@
+//! `yield_points` is a middleware inserting calls to a host `yield`
+//! hook while an instance runs, every time it has executed a given
+//! amount of cost units, so that single-threaded embedders, like game
+//! engines or UI threads, can interleave the execution of WebAssembly
+//! with the processing of their events.
+//!
+//! The cost of the operators is accumulated per basic block as the
+//! [`Metering`](crate::Metering) middleware does, except that the cost of
+//! a `loop` operator is accounted for on each iteration of the loop: with
+//! [`YieldPoints::every_loop_iterations`], the hook is called every given
+//! number of loop iterations.
+//!
+//! The hook is imported by the middleware as the `yield` function of the
+//! `wasmer_yield` namespace, provided by [`YieldPoints::imports`]. The
+//! function is inserted after the functions imported by the module, and
+//! the functions defined by the module are renumbered accordingly.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use wasmer::{imports, CompilerConfig, Cranelift, Instance, Module, Store, Universal};
+//! use wasmer_middlewares::YieldPoints;
+//!
+//! let yield_points = Arc::new(YieldPoints::every_loop_iterations(1000));
+//! let mut compiler_config = Cranelift::default();
+//! compiler_config.push_middleware(yield_points.clone());
+//! let store = Store::new(&Universal::new(compiler_config).engine());
+//!
+//! let module = Module::new(&store, r#"(module
+//!     (func (export "spin") (param $n i32)
+//!         (loop $continue
+//!             (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+//!             (br_if $continue (local.get $n)))))"#)?;
+//! let hook = yield_points.imports(&store, || {
+//!     // Process the pending events of the embedder.
+//!     Ok(())
+//! });
+//! let import_object = imports! {
+//!     "wasmer_yield" => hook,
+//! };
+//! let instance = Instance::new(&module, &import_object)?;
+//! let spin = instance.exports.get_native_function::<i32, ()>("spin")?;
+//! spin.call(1_000_000)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use wasmer::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
+use wasmer::{
+    ExportIndex, Exports, Function, FunctionMiddleware, FunctionType, GlobalInit, GlobalType,
+    LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability,
+    RuntimeError, Store, Type, WasmerEnv,
+};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{FunctionIndex, GlobalIndex, ImportIndex, ModuleInfo};
+
+/// The namespace of the imported hook.
+pub const NAMESPACE: &str = "wasmer_yield";
+
+/// The name of the imported hook.
+pub const HOOK_NAME: &str = "yield";
+
+#[derive(Clone, Debug, MemoryUsage)]
+struct YieldPointsIndexes {
+    /// The cost units left before the next call to the hook.
+    remaining: GlobalIndex,
+    /// The imported hook.
+    hook: FunctionIndex,
+}
+
+/// The module-level yield points middleware.
+///
+/// # Panic
+///
+/// An instance of `YieldPoints` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the index
+/// of the imported hook. Attempts to use a `YieldPoints` instance from
+/// multiple modules will result in a panic.
+pub struct YieldPoints<F: Fn(&Operator) -> u64 + Send + Sync> {
+    /// The cost units executed between two calls to the hook.
+    interval: u64,
+
+    /// Function that maps each operator to a cost in units.
+    cost_function: Arc<F>,
+
+    /// The indexes used by the yield points.
+    indexes: Mutex<Option<YieldPointsIndexes>>,
+}
+
+/// The function-level yield points middleware.
+pub struct FunctionYieldPoints<F: Fn(&Operator) -> u64 + Send + Sync> {
+    /// The cost units executed between two calls to the hook.
+    interval: u64,
+
+    /// Function that maps each operator to a cost in units.
+    cost_function: Arc<F>,
+
+    /// The indexes used by the yield points.
+    indexes: YieldPointsIndexes,
+
+    /// Accumulated cost of the current basic block.
+    accumulated_cost: u64,
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> YieldPoints<F> {
+    /// Creates a `YieldPoints` middleware calling the hook every
+    /// `interval` cost units, as given by `cost_function`.
+    pub fn new(interval: u64, cost_function: F) -> Self {
+        Self {
+            interval,
+            cost_function: Arc::new(cost_function),
+            indexes: Mutex::new(None),
+        }
+    }
+
+    /// The `yield` function to import in the [`NAMESPACE`] namespace, for
+    /// instances of modules compiled with this middleware, calling `hook`.
+    ///
+    /// An error returned by `hook` stops the execution of the instance
+    /// with this error.
+    pub fn imports(
+        &self,
+        store: &Store,
+        hook: impl Fn() -> Result<(), RuntimeError> + Send + Sync + 'static,
+    ) -> Exports {
+        let env = YieldEnv {
+            hook: Arc::new(hook),
+        };
+        let mut exports = Exports::new();
+        exports.insert(
+            HOOK_NAME,
+            Function::new_native_with_env(store, env, |env: &YieldEnv| (env.hook)()),
+        );
+        exports
+    }
+}
+
+impl YieldPoints<fn(&Operator) -> u64> {
+    /// Creates a `YieldPoints` middleware calling the hook every
+    /// `iterations` iterations of the loops of the module.
+    pub fn every_loop_iterations(iterations: u64) -> Self {
+        Self::new(iterations, |operator| match operator {
+            Operator::Loop { .. } => 1,
+            _ => 0,
+        })
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for YieldPoints<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("YieldPoints")
+            .field("interval", &self.interval)
+            .field("cost_function", &"<function>")
+            .field("indexes", &self.indexes)
+            .finish()
+    }
+}
+
+#[derive(WasmerEnv, Clone)]
+struct YieldEnv {
+    hook: Arc<dyn Fn() -> Result<(), RuntimeError> + Send + Sync>,
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> ModuleMiddleware for YieldPoints<F> {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(FunctionYieldPoints {
+            interval: self.interval,
+            cost_function: self.cost_function.clone(),
+            indexes: self.indexes.lock().unwrap().clone().unwrap(),
+            accumulated_cost: 0,
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut indexes = self.indexes.lock().unwrap();
+
+        if indexes.is_some() {
+            panic!("YieldPoints::transform_module_info: Attempting to use a `YieldPoints` middleware from multiple modules.");
+        }
+
+        // Append a global for the remaining cost units and initialize it.
+        let remaining = module_info
+            .globals
+            .push(GlobalType::new(Type::I64, Mutability::Var));
+        module_info
+            .global_initializers
+            .push(GlobalInit::I64Const(self.interval as i64));
+
+        *indexes = Some(YieldPointsIndexes {
+            remaining,
+            hook: import_hook(module_info),
+        });
+    }
+}
+
+/// Imports the hook after the functions imported by the module, and
+/// renumbers the functions defined by the module.
+fn import_hook(module_info: &mut ModuleInfo) -> FunctionIndex {
+    let hook = FunctionIndex::new(module_info.num_imported_functions);
+    let shift = |index: &mut FunctionIndex| {
+        if *index >= hook {
+            *index = FunctionIndex::new(index.index() + 1);
+        }
+    };
+
+    let signature = module_info
+        .signatures
+        .push(FunctionType::new(vec![], vec![]));
+    let mut functions: Vec<_> = module_info.functions.values().cloned().collect();
+    functions.insert(hook.index(), signature);
+    module_info.functions = functions.into_iter().collect::<PrimaryMap<_, _>>();
+    module_info.num_imported_functions += 1;
+
+    // The imports are resolved in order, so the hook goes after the
+    // last imported function.
+    let position = module_info
+        .imports
+        .values()
+        .rposition(|import| matches!(import, ImportIndex::Function(_)))
+        .map_or(0, |position| position + 1);
+    let mut imports: Vec<_> = module_info
+        .imports
+        .drain(..)
+        .map(|((module, field, _), import)| (module, field, import))
+        .collect();
+    imports.insert(
+        position,
+        (
+            NAMESPACE.to_string(),
+            HOOK_NAME.to_string(),
+            ImportIndex::Function(hook),
+        ),
+    );
+    module_info.imports = imports
+        .into_iter()
+        .enumerate()
+        .map(|(index, (module, field, import))| ((module, field, index as u32), import))
+        .collect();
+
+    for export in module_info.exports.values_mut() {
+        if let ExportIndex::Function(index) = export {
+            shift(index);
+        }
+    }
+    if let Some(index) = module_info.start_function.as_mut() {
+        shift(index);
+    }
+    for initializer in module_info.table_initializers.iter_mut() {
+        initializer.elements.iter_mut().for_each(shift);
+    }
+    for elements in module_info.passive_elements.values_mut() {
+        elements.iter_mut().for_each(shift);
+    }
+    for initializer in module_info.global_initializers.values_mut() {
+        if let GlobalInit::RefFunc(index) = initializer {
+            shift(index);
+        }
+    }
+    module_info.function_names = module_info
+        .function_names
+        .drain()
+        .map(|(mut index, name)| {
+            shift(&mut index);
+            (index, name)
+        })
+        .collect();
+
+    hook
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> MemoryUsage for YieldPoints<F> {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.indexes.size_of_val(tracker) - mem::size_of_val(&self.indexes)
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for FunctionYieldPoints<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionYieldPoints")
+            .field("interval", &self.interval)
+            .field("cost_function", &"<function>")
+            .field("indexes", &self.indexes)
+            .finish()
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionYieldPoints<F> {
+    /// Renumbers the functions defined by the module, which come after
+    /// the hook.
+    fn renumber(&self, function_index: u32) -> u32 {
+        if function_index >= self.indexes.hook.as_u32() {
+            function_index + 1
+        } else {
+            function_index
+        }
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionYieldPoints<F> {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let operator = match operator {
+            Operator::Call { function_index } => Operator::Call {
+                function_index: self.renumber(function_index),
+            },
+            Operator::ReturnCall { function_index } => Operator::ReturnCall {
+                function_index: self.renumber(function_index),
+            },
+            Operator::RefFunc { function_index } => Operator::RefFunc {
+                function_index: self.renumber(function_index),
+            },
+            operator => operator,
+        };
+
+        // The cost of a loop is accounted for in its body, so that it's
+        // paid on each iteration.
+        let cost = (self.cost_function)(&operator);
+        let is_loop = matches!(operator, Operator::Loop { .. });
+        if !is_loop {
+            self.accumulated_cost += cost;
+        }
+
+        // Possible sources and targets of a branch. Finalize the cost of the previous basic block.
+        match operator {
+            Operator::Loop { .. }
+            | Operator::End
+            | Operator::Else
+            | Operator::Br { .. }
+            | Operator::BrTable { .. }
+            | Operator::BrIf { .. }
+            | Operator::Call { .. }
+            | Operator::CallIndirect { .. }
+            | Operator::Return => {
+                if self.accumulated_cost > 0 {
+                    let remaining = self.indexes.remaining.as_u32();
+                    state.extend(&[
+                        // globals[remaining] -= self.accumulated_cost;
+                        Operator::GlobalGet {
+                            global_index: remaining,
+                        },
+                        Operator::I64Const {
+                            value: self.accumulated_cost as i64,
+                        },
+                        Operator::I64Sub,
+                        Operator::GlobalSet {
+                            global_index: remaining,
+                        },
+                        // if globals[remaining] <= 0 { globals[remaining] = self.interval; hook(); }
+                        Operator::GlobalGet {
+                            global_index: remaining,
+                        },
+                        Operator::I64Const { value: 0 },
+                        Operator::I64LeS,
+                        Operator::If {
+                            ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+                        },
+                        Operator::I64Const {
+                            value: self.interval as i64,
+                        },
+                        Operator::GlobalSet {
+                            global_index: remaining,
+                        },
+                        Operator::Call {
+                            function_index: self.indexes.hook.as_u32(),
+                        },
+                        Operator::End,
+                    ]);
+
+                    self.accumulated_cost = 0;
+                }
+            }
+            _ => {}
+        }
+        state.push_operator(operator);
+
+        if is_loop {
+            self.accumulated_cost = cost;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use wasmer::{imports, CompilerConfig, Cranelift, Instance, Module, Universal};
+
+    const WAT: &str = r#"
+        (module
+          (import "env" "double" (func $double (param i32) (result i32)))
+          (table 1 funcref)
+          (elem (i32.const 0) $spin)
+          (func $spin (export "spin") (param $n i32) (result i32)
+            (local $sum i32)
+            (loop $continue
+              (local.set $sum (i32.add (local.get $sum) (local.get $n)))
+              (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+              (br_if $continue (local.get $n)))
+            (call $double (local.get $sum)))
+          (func (export "spin_indirectly") (param $n i32) (result i32)
+            (call_indirect (param i32) (result i32) (local.get $n) (i32.const 0))))
+    "#;
+
+    fn instance(
+        yield_points: Arc<YieldPoints<fn(&Operator) -> u64>>,
+    ) -> (Instance, Arc<AtomicUsize>) {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(yield_points.clone());
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, WAT).unwrap();
+
+        let yields = Arc::new(AtomicUsize::new(0));
+        let counter = yields.clone();
+        let hook = yield_points.imports(&store, move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        });
+        let import_object = imports! {
+            "env" => {
+                "double" => Function::new_native(&store, |x: i32| x * 2),
+            },
+            NAMESPACE => hook,
+        };
+        (Instance::new(&module, &import_object).unwrap(), yields)
+    }
+
+    #[test]
+    fn hook_is_called_every_loop_iterations() {
+        let (instance, yields) = instance(Arc::new(YieldPoints::every_loop_iterations(10)));
+        let spin = instance
+            .exports
+            .get_native_function::<i32, i32>("spin")
+            .unwrap();
+        assert_eq!(spin.call(100).unwrap(), 10100);
+        assert_eq!(yields.load(Ordering::SeqCst), 10);
+
+        // The functions of the module are renumbered after the hook.
+        let spin_indirectly = instance
+            .exports
+            .get_native_function::<i32, i32>("spin_indirectly")
+            .unwrap();
+        assert_eq!(spin_indirectly.call(25).unwrap(), 650);
+        assert_eq!(yields.load(Ordering::SeqCst), 12);
+    }
+
+    #[test]
+    fn hook_errors_stop_the_execution() {
+        let yield_points = Arc::new(YieldPoints::every_loop_iterations(10));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(yield_points.clone());
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, WAT).unwrap();
+        let hook = yield_points.imports(&store, || Err(RuntimeError::new("interrupted")));
+        let import_object = imports! {
+            "env" => {
+                "double" => Function::new_native(&store, |x: i32| x * 2),
+            },
+            NAMESPACE => hook,
+        };
+        let instance = Instance::new(&module, &import_object).unwrap();
+        let spin = instance
+            .exports
+            .get_native_function::<i32, i32>("spin")
+            .unwrap();
+        assert_eq!(spin.call(100).unwrap_err().message(), "interrupted");
+        assert_eq!(spin.call(5).unwrap(), 30);
+    }
+}
@@ -23,4 +23,7 @@ mod error;
 mod module;
 
 pub use crate::error::ObjectError;
-pub use crate::module::{emit_compilation, emit_data, get_object_for_target};
+pub use crate::module::{
+    emit_compilation, emit_compilation_with_options, emit_data, get_object_for_target,
+    ObjectOptions,
+};
@@ -1,6 +1,7 @@
 use crate::error::ObjectError;
 use object::write::{
-    Object, Relocation, StandardSection, StandardSegment, Symbol as ObjSymbol, SymbolSection,
+    Object, Relocation, SectionId, StandardSection, StandardSegment, Symbol as ObjSymbol, SymbolId,
+    SymbolSection,
 };
 use object::{
     elf, macho, RelocationEncoding, RelocationKind, SectionKind, SymbolFlags, SymbolKind,
@@ -15,6 +16,28 @@ use wasmer_types::LocalFunctionIndex;
 
 const DWARF_SECTION_NAME: &[u8] = b".eh_frame";
 
+/// Options of the emission of a compilation into an object.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectOptions {
+    /// Emit each function and trampoline in its own section, named
+    /// `.text.wasm.<symbol name>`, like `-ffunction-sections` does, so
+    /// that linkers can remove the unused ones.
+    pub function_sections: bool,
+    /// Give the symbols of the compilation hidden visibility, so that the
+    /// output they are linked into doesn't export them.
+    pub hidden_symbols: bool,
+}
+
+impl ObjectOptions {
+    fn symbol_scope(&self) -> SymbolScope {
+        if self.hidden_symbols {
+            SymbolScope::Linkage
+        } else {
+            SymbolScope::Dynamic
+        }
+    }
+}
+
 /// Create an object for a given target `Triple`.
 ///
 /// # Usage
@@ -129,6 +152,46 @@ pub fn emit_compilation(
     compilation: Compilation,
     symbol_registry: &impl SymbolRegistry,
     triple: &Triple,
+) -> Result<(), ObjectError> {
+    emit_compilation_with_options(
+        obj,
+        compilation,
+        symbol_registry,
+        triple,
+        &ObjectOptions::default(),
+    )
+}
+
+/// Emit the compilation result into an existing object, as described by
+/// `options`.
+///
+/// # Usage
+///
+/// ```rust
+/// # use wasmer_compiler::{Compilation, SymbolRegistry, Triple};
+/// # use wasmer_object::ObjectError;
+/// use wasmer_object::{get_object_for_target, emit_compilation_with_options, ObjectOptions};
+///
+/// # fn emit_compilation_into_object(
+/// #     triple: &Triple,
+/// #     compilation: Compilation,
+/// #     symbol_registry: impl SymbolRegistry,
+/// # ) -> Result<(), ObjectError> {
+/// let mut object = get_object_for_target(&triple)?;
+/// let options = ObjectOptions {
+///     function_sections: true,
+///     hidden_symbols: true,
+/// };
+/// emit_compilation_with_options(&mut object, compilation, &symbol_registry, &triple, &options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn emit_compilation_with_options(
+    obj: &mut Object,
+    compilation: Compilation,
+    symbol_registry: &impl SymbolRegistry,
+    triple: &Triple,
+    options: &ObjectOptions,
 ) -> Result<(), ObjectError> {
     let function_bodies = compilation.get_function_bodies();
     let function_relocations = compilation.get_relocations();
@@ -182,7 +245,7 @@ pub fn emit_compilation(
                     value: 0,
                     size: custom_section.bytes.len() as _,
                     kind: section_kind,
-                    scope: SymbolScope::Dynamic,
+                    scope: options.symbol_scope(),
                     weak: false,
                     section: SymbolSection::Section(section_id),
                     flags: SymbolFlags::None,
@@ -204,19 +267,7 @@ pub fn emit_compilation(
         .map(|(function_local_index, function)| {
             let function_name =
                 symbol_registry.symbol_to_name(Symbol::LocalFunction(function_local_index));
-            let section_id = obj.section_id(StandardSection::Text);
-            let symbol_id = obj.add_symbol(ObjSymbol {
-                name: function_name.into_bytes(),
-                value: 0,
-                size: function.body.len() as _,
-                kind: SymbolKind::Text,
-                scope: SymbolScope::Dynamic,
-                weak: false,
-                section: SymbolSection::Section(section_id),
-                flags: SymbolFlags::None,
-            });
-            obj.add_symbol_data(symbol_id, section_id, &function.body, align);
-            (section_id, symbol_id)
+            add_function(obj, function_name, &function.body, align, options)
         })
         .collect::<PrimaryMap<LocalFunctionIndex, _>>();
 
@@ -224,36 +275,14 @@ pub fn emit_compilation(
     for (signature_index, function) in function_call_trampolines.into_iter() {
         let function_name =
             symbol_registry.symbol_to_name(Symbol::FunctionCallTrampoline(signature_index));
-        let section_id = obj.section_id(StandardSection::Text);
-        let symbol_id = obj.add_symbol(ObjSymbol {
-            name: function_name.into_bytes(),
-            value: 0,
-            size: function.body.len() as _,
-            kind: SymbolKind::Text,
-            scope: SymbolScope::Dynamic,
-            weak: false,
-            section: SymbolSection::Section(section_id),
-            flags: SymbolFlags::None,
-        });
-        obj.add_symbol_data(symbol_id, section_id, &function.body, align);
+        add_function(obj, function_name, &function.body, align, options);
     }
 
     // Add dynamic function trampolines
     for (func_index, function) in dynamic_function_trampolines.into_iter() {
         let function_name =
             symbol_registry.symbol_to_name(Symbol::DynamicFunctionTrampoline(func_index));
-        let section_id = obj.section_id(StandardSection::Text);
-        let symbol_id = obj.add_symbol(ObjSymbol {
-            name: function_name.into_bytes(),
-            value: 0,
-            size: function.body.len() as _,
-            kind: SymbolKind::Text,
-            scope: SymbolScope::Dynamic,
-            weak: false,
-            section: SymbolSection::Section(section_id),
-            flags: SymbolFlags::None,
-        });
-        obj.add_symbol_data(symbol_id, section_id, &function.body, align);
+        add_function(obj, function_name, &function.body, align, options);
     }
 
     let mut all_relocations = Vec::new();
@@ -382,3 +411,37 @@ pub fn emit_compilation(
 
     Ok(())
 }
+
+/// Adds the code of a function to the text of the object, or to its own
+/// section with function sections.
+fn add_function(
+    obj: &mut Object,
+    name: String,
+    body: &[u8],
+    align: u64,
+    options: &ObjectOptions,
+) -> (SectionId, SymbolId) {
+    let (section_id, offset) = if options.function_sections {
+        let subsection_name = format!("wasm.{}", name);
+        obj.add_subsection(
+            StandardSection::Text,
+            subsection_name.as_bytes(),
+            body,
+            align,
+        )
+    } else {
+        let section_id = obj.section_id(StandardSection::Text);
+        (section_id, obj.append_section_data(section_id, body, align))
+    };
+    let symbol_id = obj.add_symbol(ObjSymbol {
+        name: name.into_bytes(),
+        value: offset,
+        size: body.len() as _,
+        kind: SymbolKind::Text,
+        scope: options.symbol_scope(),
+        weak: false,
+        section: SymbolSection::Section(section_id),
+        flags: SymbolFlags::None,
+    });
+    (section_id, symbol_id)
+}
@@ -91,5 +91,7 @@ pub use crate::entity_impl;
 pub use boxed_slice::BoxedSlice;
 pub use iter::{Iter, IterMut};
 pub use keys::Keys;
+#[cfg(feature = "enable-rkyv")]
+pub use primary_map::ArchivedPrimaryMap;
 pub use primary_map::PrimaryMap;
 pub use secondary_map::SecondaryMap;
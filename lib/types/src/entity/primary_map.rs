@@ -161,6 +161,18 @@ where
     }
 }
 
+#[cfg(feature = "enable-rkyv")]
+impl<K, V> ArchivedPrimaryMap<K, V>
+where
+    K: EntityRef,
+    V: Archive,
+{
+    /// Get the archived values of the map, in the order of their keys.
+    pub fn values(&self) -> &[V::Archived] {
+        self.elems.as_slice()
+    }
+}
+
 impl<K, V> Default for PrimaryMap<K, V>
 where
     K: EntityRef,
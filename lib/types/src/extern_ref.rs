@@ -1,4 +1,5 @@
 use std::any::Any;
+use std::fmt;
 use std::ptr;
 use std::sync::atomic;
 
@@ -47,6 +48,21 @@ impl VMExternRef {
         Self(Box::into_raw(Box::new(VMExternRefInner::new::<T>(value))))
     }
 
+    /// Make a new extern reference, calling `finalizer` with the value
+    /// once the last reference is dropped.
+    pub fn with_finalizer<T, F>(value: T, finalizer: F) -> Self
+    where
+        T: Any + Send + Sync + 'static + Sized,
+        F: FnOnce(T) + Send + Sync + 'static,
+    {
+        let mut inner = VMExternRefInner::new::<T>(value);
+        inner.finalizer = Some(Box::new(move |data| {
+            // The data was made from a `T` above.
+            finalizer(*data.downcast::<T>().unwrap())
+        }));
+        Self(Box::into_raw(Box::new(inner)))
+    }
+
     /// Try to downcast to the given value
     pub fn downcast<T>(&self) -> Option<&T>
     where
@@ -129,7 +145,10 @@ impl VMExternRef {
                     ref_inner.decrement_and_drop()
                 };
                 if should_drop {
-                    let _ = Box::from_raw(self.0 as *mut VMExternRefInner);
+                    let inner = Box::from_raw(self.0 as *mut VMExternRefInner);
+                    if let Some(finalizer) = inner.finalizer {
+                        finalizer(inner.data);
+                    }
                 }
             }
         }
@@ -146,13 +165,27 @@ impl VMExternRef {
     }
 }
 
-#[derive(Debug)]
 #[repr(C)]
 pub(crate) struct VMExternRefInner {
     strong: atomic::AtomicUsize,
     /// Do something obviously correct to get started. This can "easily" be improved
     /// to be an inline allocation later as the logic is fully encapsulated.
     data: Box<dyn Any + Send + Sync + 'static>,
+    /// Called with the data when the last reference is dropped.
+    finalizer: Option<Finalizer>,
+}
+
+/// The finalizer of the data of an extern reference.
+type Finalizer = Box<dyn FnOnce(Box<dyn Any + Send + Sync + 'static>) + Send + Sync>;
+
+impl fmt::Debug for VMExternRefInner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VMExternRefInner")
+            .field("strong", &self.strong)
+            .field("data", &self.data)
+            .field("finalizer", &self.finalizer.is_some())
+            .finish()
+    }
 }
 
 impl VMExternRefInner {
@@ -163,6 +196,7 @@ impl VMExternRefInner {
         Self {
             strong: atomic::AtomicUsize::new(1),
             data: Box::new(value),
+            finalizer: None,
         }
     }
 
@@ -262,6 +296,23 @@ impl ExternRef {
         }
     }
 
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    /// Make a new extern reference, calling `finalizer` with the value
+    /// once the last reference is dropped, either by the host or by the
+    /// VM.
+    ///
+    /// The finalizer runs on the thread dropping the last reference,
+    /// which may be running WebAssembly code: it must not panic.
+    pub fn with_finalizer<T, F>(value: T, finalizer: F) -> Self
+    where
+        T: Any + Send + Sync + 'static + Sized,
+        F: FnOnce(T) + Send + Sync + 'static,
+    {
+        Self {
+            inner: VMExternRef::with_finalizer(value, finalizer),
+        }
+    }
+
     #[cfg(feature = "experimental-reference-types-extern-ref")]
     /// Try to downcast to the given value
     pub fn downcast<T>(&self) -> Option<&T>
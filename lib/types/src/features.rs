@@ -39,6 +39,8 @@ pub struct Features {
     pub relaxed_simd: bool,
     /// Extended constant expressions proposal should be enabled
     pub extended_const: bool,
+    /// Component Model proposal should be enabled
+    pub component_model: bool,
 }
 
 impl Features {
@@ -61,6 +63,7 @@ impl Features {
             exceptions: false,
             relaxed_simd: false,
             extended_const: false,
+            component_model: false,
         }
     }
 
@@ -236,6 +239,110 @@ impl Features {
         self.memory64 = enable;
         self
     }
+
+    /// Configures whether the WebAssembly exception handling proposal
+    /// will be enabled.
+    ///
+    /// The [WebAssembly exception handling proposal][proposal] is not
+    /// currently fully standardized and is undergoing development.
+    /// Support for this feature can be enabled through this method for
+    /// appropriate WebAssembly modules.
+    ///
+    /// This feature gates the `try`, `catch` and `throw` instructions
+    /// and the tag section.
+    ///
+    /// This is `false` by default.
+    ///
+    /// [proposal]: https://github.com/WebAssembly/exception-handling
+    pub fn exceptions(&mut self, enable: bool) -> &mut Self {
+        self.exceptions = enable;
+        self
+    }
+
+    /// Configures whether the WebAssembly relaxed SIMD proposal will
+    /// be enabled.
+    ///
+    /// The [WebAssembly relaxed SIMD proposal][proposal] is not
+    /// currently fully standardized and is undergoing development.
+    /// Support for this feature can be enabled through this method for
+    /// appropriate WebAssembly modules.
+    ///
+    /// This feature gates SIMD operators whose results may differ
+    /// between platforms. Note that enabling it will also enable the
+    /// SIMD feature.
+    ///
+    /// This is `false` by default.
+    ///
+    /// [proposal]: https://github.com/WebAssembly/relaxed-simd
+    pub fn relaxed_simd(&mut self, enable: bool) -> &mut Self {
+        self.relaxed_simd = enable;
+        // The relaxed SIMD proposal extends the SIMD proposal
+        if enable {
+            self.simd(true);
+        }
+        self
+    }
+
+    /// Configures whether the WebAssembly extended constant expressions
+    /// proposal will be enabled.
+    ///
+    /// The [WebAssembly extended constant expressions proposal][proposal]
+    /// is not currently fully standardized and is undergoing development.
+    /// Support for this feature can be enabled through this method for
+    /// appropriate WebAssembly modules.
+    ///
+    /// This feature allows integer arithmetic in the initializers of
+    /// globals and the offsets of data and element segments.
+    ///
+    /// This is `false` by default.
+    ///
+    /// [proposal]: https://github.com/WebAssembly/extended-const
+    pub fn extended_const(&mut self, enable: bool) -> &mut Self {
+        self.extended_const = enable;
+        self
+    }
+
+    /// Configures whether the WebAssembly component model proposal will
+    /// be enabled.
+    ///
+    /// The [WebAssembly component model proposal][proposal] is not
+    /// currently fully standardized and is undergoing development.
+    /// Support for this feature can be enabled through this method for
+    /// appropriate WebAssembly modules.
+    ///
+    /// This feature allows parsing and validating components, which
+    /// compose core modules through typed interfaces.
+    ///
+    /// This is `false` by default.
+    ///
+    /// [proposal]: https://github.com/WebAssembly/component-model
+    pub fn component_model(&mut self, enable: bool) -> &mut Self {
+        self.component_model = enable;
+        self
+    }
+
+    /// Returns the features enabled both in `self` and in `other`.
+    ///
+    /// This is used to restrict the features requested by an embedder
+    /// to the ones a compiler supports, so that a module accepted by
+    /// the validator can always be compiled.
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self {
+            threads: self.threads && other.threads,
+            reference_types: self.reference_types && other.reference_types,
+            simd: self.simd && other.simd,
+            bulk_memory: self.bulk_memory && other.bulk_memory,
+            multi_value: self.multi_value && other.multi_value,
+            tail_call: self.tail_call && other.tail_call,
+            module_linking: self.module_linking && other.module_linking,
+            multi_memory: self.multi_memory && other.multi_memory,
+            memory64: self.memory64 && other.memory64,
+            exceptions: self.exceptions && other.exceptions,
+            relaxed_simd: self.relaxed_simd && other.relaxed_simd,
+            extended_const: self.extended_const && other.extended_const,
+            component_model: self.component_model && other.component_model,
+        }
+    }
 }
 
 impl Default for Features {
@@ -265,6 +372,7 @@ mod test_features {
                 exceptions: false,
                 relaxed_simd: false,
                 extended_const: false,
+                component_model: false,
             }
         );
     }
@@ -344,4 +452,34 @@ mod test_features {
         features.memory64(true);
         assert!(features.memory64);
     }
+
+    #[test]
+    fn enable_relaxed_simd() {
+        let mut features = Features::new();
+        features.simd(false).relaxed_simd(true);
+        assert!(features.relaxed_simd);
+        assert!(features.simd);
+    }
+
+    #[test]
+    fn enable_component_model() {
+        let mut features = Features::new();
+        features.component_model(true);
+        assert!(features.component_model);
+    }
+
+    #[test]
+    fn intersection() {
+        let mut requested = Features::new();
+        requested.threads(true).tail_call(true).extended_const(true);
+        let mut supported = Features::new();
+        supported.threads(true).simd(false);
+
+        let effective = requested.intersection(&supported);
+        assert!(effective.threads);
+        assert!(!effective.simd);
+        assert!(!effective.tail_call);
+        assert!(!effective.extended_const);
+        assert!(effective.bulk_memory);
+    }
 }
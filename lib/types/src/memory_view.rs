@@ -1,12 +1,15 @@
 use crate::lib::std::cell::Cell;
 use crate::lib::std::marker::PhantomData;
+use crate::lib::std::mem;
 use crate::lib::std::ops::Deref;
+use crate::lib::std::ptr;
 // use crate::lib::std::ops::{Bound, RangeBounds};
 use crate::lib::std::slice;
 use crate::lib::std::sync::atomic::{
     AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicU16, AtomicU32, AtomicU64, AtomicU8,
 };
 use crate::native::ValueType;
+use thiserror::Error;
 
 pub trait Atomic {
     type Output;
@@ -46,15 +49,38 @@ impl Atomicity for Atomically {}
 pub struct NonAtomically;
 impl Atomicity for NonAtomically {}
 
+/// An out-of-bounds access to a memory.
+///
+/// The `offset` and the `length` of the access are in bytes, except for
+/// accesses through a [`MemoryView`], where they're in elements of the
+/// view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("out of bounds memory access of length {length} at offset {offset}")]
+pub struct MemoryAccessError {
+    /// Where the access started.
+    pub offset: u64,
+    /// How long the access was.
+    pub length: u64,
+}
+
 /// A view into a memory.
 pub struct MemoryView<'a, T: 'a, A = NonAtomically> {
     ptr: *mut T,
     // Note: the length is in the terms of `size::<T>()`.
     // The total length in memory is `size::<T>() * length`.
     length: usize,
+    source: Option<ViewSource>,
     _phantom: PhantomData<(&'a [Cell<T>], A)>,
 }
 
+/// Where the current start address and size in bytes of the memory of
+/// a view are read from when it's refreshed.
+#[derive(Clone, Copy)]
+struct ViewSource {
+    base: *const *mut u8,
+    current_length: *const usize,
+}
+
 impl<'a, T> MemoryView<'a, T, NonAtomically>
 where
     T: ValueType,
@@ -64,10 +90,32 @@ where
         Self {
             ptr,
             length: length as usize,
+            source: None,
             _phantom: PhantomData,
         }
     }
 
+    /// Creates a new MemoryView of a whole memory, given where its start
+    /// address and size in bytes are stored, so that it can be
+    /// [refreshed](Self::refresh) after the memory grows.
+    ///
+    /// # Safety
+    ///
+    /// Both pointers must stay valid for the lifetime of the view.
+    pub unsafe fn new_refreshable(base: *const *mut u8, current_length: *const usize) -> Self {
+        let mut view = Self {
+            ptr: ptr::null_mut(),
+            length: 0,
+            source: Some(ViewSource {
+                base,
+                current_length,
+            }),
+            _phantom: PhantomData,
+        };
+        view.refresh();
+        view
+    }
+
     /// Creates a subarray view from this `MemoryView`.
     pub fn subarray(&self, start: u32, end: u32) -> Self {
         assert!(
@@ -82,7 +130,55 @@ where
         Self {
             ptr: unsafe { self.ptr.add(start as usize) },
             length: (end - start) as usize,
+            source: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a subarray view from this `MemoryView`, failing instead of
+    /// panicking if the range is out of bounds.
+    pub fn try_subarray(&self, start: u32, end: u32) -> Result<Self, MemoryAccessError> {
+        let length = end.saturating_sub(start) as usize;
+        self.check_bounds(start, length)?;
+        Ok(Self {
+            ptr: unsafe { self.ptr.add(start as usize) },
+            length,
+            source: None,
             _phantom: PhantomData,
+        })
+    }
+
+    /// Reads `buf.len()` elements starting at `offset` into `buf`.
+    ///
+    /// Returns an error, leaving `buf` untouched, if the read goes past
+    /// the end of the view.
+    pub fn read(&self, offset: u32, buf: &mut [T]) -> Result<(), MemoryAccessError> {
+        self.check_bounds(offset, buf.len())?;
+        unsafe {
+            ptr::copy(self.ptr.add(offset as usize), buf.as_mut_ptr(), buf.len());
+        }
+        Ok(())
+    }
+
+    /// Writes the elements of `data` starting at `offset`.
+    ///
+    /// Returns an error, leaving the memory untouched, if the write goes
+    /// past the end of the view.
+    pub fn write(&self, offset: u32, data: &[T]) -> Result<(), MemoryAccessError> {
+        self.check_bounds(offset, data.len())?;
+        unsafe {
+            ptr::copy(data.as_ptr(), self.ptr.add(offset as usize), data.len());
+        }
+        Ok(())
+    }
+
+    fn check_bounds(&self, offset: u32, length: usize) -> Result<(), MemoryAccessError> {
+        match (offset as usize).checked_add(length) {
+            Some(end) if end <= self.length => Ok(()),
+            _ => Err(MemoryAccessError {
+                offset: offset.into(),
+                length: length as u64,
+            }),
         }
     }
 
@@ -110,11 +206,29 @@ impl<'a, T: Atomic> MemoryView<'a, T> {
         MemoryView {
             ptr: self.ptr as *mut T::Output,
             length: self.length,
+            source: self.source,
             _phantom: PhantomData,
         }
     }
 }
 
+impl<'a, T, A: Atomicity> MemoryView<'a, T, A> {
+    /// Reads the start address and size of the memory again, as they
+    /// change when the memory grows, for example from a host function
+    /// called by WebAssembly.
+    ///
+    /// Views created with [`MemoryView::new`] and subarrays keep their
+    /// bounds.
+    pub fn refresh(&mut self) {
+        if let Some(source) = self.source {
+            unsafe {
+                self.ptr = *source.base as *mut T;
+                self.length = *source.current_length / mem::size_of::<T>();
+            }
+        }
+    }
+}
+
 impl<'a, T> Deref for MemoryView<'a, T, NonAtomically> {
     type Target = [Cell<T>];
     fn deref(&self) -> &[Cell<T>] {
@@ -130,3 +244,60 @@ impl<'a, T> Deref for MemoryView<'a, T, Atomically> {
         unsafe { slice::from_raw_parts(self.ptr as *const T, self.length) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_of_bounds_accesses_are_errors() {
+        let mut data = [0u32; 4];
+        let view = unsafe { MemoryView::<u32>::new(data.as_mut_ptr(), 4) };
+
+        view.write(1, &[7, 8]).unwrap();
+        let mut buf = [0; 3];
+        view.read(1, &mut buf).unwrap();
+        assert_eq!(buf, [7, 8, 0]);
+
+        assert_eq!(
+            view.write(3, &[1, 2]),
+            Err(MemoryAccessError {
+                offset: 3,
+                length: 2
+            })
+        );
+        assert_eq!(
+            view.read(u32::MAX, &mut buf),
+            Err(MemoryAccessError {
+                offset: u32::MAX as u64,
+                length: 3
+            })
+        );
+        assert_eq!(view[3].get(), 0);
+
+        assert_eq!(view.try_subarray(2, 4).unwrap().len(), 2);
+        assert!(view.try_subarray(2, 5).is_err());
+    }
+
+    #[test]
+    fn refreshed_views_follow_their_memory() {
+        let mut small = [1u32; 2];
+        let mut large = [2u32; 4];
+        // Stands for a `VMMemoryDefinition`.
+        let mut definition = (small.as_mut_ptr() as *mut u8, 8);
+        let base: *mut *mut u8 = &mut definition.0;
+        let current_length: *mut usize = &mut definition.1;
+        let mut view = unsafe { MemoryView::<u32>::new_refreshable(base, current_length) };
+        assert_eq!(view.len(), 2);
+        assert_eq!(view[1].get(), 1);
+
+        unsafe {
+            *base = large.as_mut_ptr() as *mut u8;
+            *current_length = 16;
+        }
+        view.refresh();
+        assert_eq!(view.len(), 4);
+        assert_eq!(view[3].get(), 2);
+        assert_eq!(view.atomically().len(), 4);
+    }
+}
@@ -65,6 +65,13 @@ pub enum TrapCode {
 
     /// An atomic memory access was attempted with an unaligned pointer.
     UnalignedAtomic = 11,
+
+    /// A trap raised by the embedder (from a host function, for instance
+    /// one called by code inserted by a middleware) rather than by the
+    /// WebAssembly code itself.
+    ///
+    /// Its embedder-defined code and payload are kept in the runtime error.
+    Custom = 12,
 }
 
 impl TrapCode {
@@ -83,6 +90,7 @@ impl TrapCode {
             Self::BadConversionToInteger => "invalid conversion to integer",
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unaligned atomic access",
+            Self::Custom => "custom trap",
         }
     }
 }
@@ -102,6 +110,7 @@ impl Display for TrapCode {
             Self::BadConversionToInteger => "bad_toint",
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unalign_atom",
+            Self::Custom => "custom",
         };
         f.write_str(identifier)
     }
@@ -124,6 +133,7 @@ impl FromStr for TrapCode {
             "bad_toint" => Ok(TrapCode::BadConversionToInteger),
             "unreachable" => Ok(TrapCode::UnreachableCodeReached),
             "unalign_atom" => Ok(TrapCode::UnalignedAtomic),
+            "custom" => Ok(TrapCode::Custom),
             _ => Err(()),
         }
     }
@@ -134,7 +144,7 @@ mod tests {
     use super::*;
 
     // Everything but user-defined codes.
-    const CODES: [TrapCode; 12] = [
+    const CODES: [TrapCode; 13] = [
         TrapCode::StackOverflow,
         TrapCode::HeapAccessOutOfBounds,
         TrapCode::HeapMisaligned,
@@ -147,6 +157,7 @@ mod tests {
         TrapCode::BadConversionToInteger,
         TrapCode::UnreachableCodeReached,
         TrapCode::UnalignedAtomic,
+        TrapCode::Custom,
     ];
 
     #[test]
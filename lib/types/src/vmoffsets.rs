@@ -525,8 +525,9 @@ impl VMOffsets {
             .unwrap()
     }
 
-    /// Return the size of the `VMContext` allocation.
-    pub fn size_of_vmctx(&self) -> u32 {
+    /// The offset of the `call_indirect` inline caches, one pointer to
+    /// the `VMCallerCheckedAnyfunc` last called per signature.
+    pub fn vmctx_call_indirect_caches_begin(&self) -> u32 {
         self.vmctx_builtin_functions_begin()
             .checked_add(
                 VMBuiltinFunctionIndex::builtin_functions_total_number()
@@ -536,6 +537,17 @@ impl VMOffsets {
             .unwrap()
     }
 
+    /// Return the size of the `VMContext` allocation.
+    pub fn size_of_vmctx(&self) -> u32 {
+        self.vmctx_call_indirect_caches_begin()
+            .checked_add(
+                self.num_signature_ids
+                    .checked_mul(u32::from(self.pointer_size))
+                    .unwrap(),
+            )
+            .unwrap()
+    }
+
     /// Return the offset to `VMSharedSignatureIndex` index `index`.
     pub fn vmctx_vmshared_signature_id(&self, index: SignatureIndex) -> u32 {
         assert_lt!(index.as_u32(), self.num_signature_ids);
@@ -710,6 +722,20 @@ impl VMOffsets {
             .unwrap()
     }
 
+    /// Return the offset to the `call_indirect` inline cache of the
+    /// signature `index`.
+    pub fn vmctx_call_indirect_cache(&self, index: SignatureIndex) -> u32 {
+        assert_lt!(index.as_u32(), self.num_signature_ids);
+        self.vmctx_call_indirect_caches_begin()
+            .checked_add(
+                index
+                    .as_u32()
+                    .checked_mul(u32::from(self.pointer_size))
+                    .unwrap(),
+            )
+            .unwrap()
+    }
+
     /// Return the offset to builtin function in `VMBuiltinFunctionsArray` index `index`.
     pub fn vmctx_builtin_function(&self, index: VMBuiltinFunctionIndex) -> u32 {
         self.vmctx_builtin_functions_begin()
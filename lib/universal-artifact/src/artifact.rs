@@ -12,9 +12,9 @@ use std::mem;
 use std::sync::Arc;
 use wasmer_artifact::{MetadataHeader, SerializeError};
 use wasmer_compiler::{
-    CompileError, CompileModuleInfo, CompiledFunctionFrameInfo, CpuFeature, CustomSection, Dwarf,
-    Features, FunctionBody, ModuleEnvironment, ModuleMiddlewareChain, Relocation, SectionIndex,
-    Target, Triple,
+    CancellationToken, CompileError, CompileModuleInfo, CompiledFunctionFrameInfo, CpuFeature,
+    CustomSection, Dwarf, Features, FunctionBody, ModuleEnvironment, ModuleMiddlewareChain,
+    Relocation, SectionIndex, Target, Triple,
 };
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{
@@ -45,6 +45,7 @@ impl UniversalArtifactBuild {
         target: &Target,
         memory_styles: PrimaryMap<MemoryIndex, MemoryStyle>,
         table_styles: PrimaryMap<TableIndex, TableStyle>,
+        cancellation: &CancellationToken,
     ) -> Result<Self, CompileError> {
         let environ = ModuleEnvironment::new();
         let features = inner_engine.features();
@@ -66,7 +67,7 @@ impl UniversalArtifactBuild {
         };
 
         // Compile the Module
-        let compilation = compiler.compile_module(
+        let compilation = compiler.compile_module_cancellable(
             target,
             &compile_info,
             // SAFETY: Calling `unwrap` is correct since
@@ -74,6 +75,7 @@ impl UniversalArtifactBuild {
             // `module_translation_state`.
             translation.module_translation_state.as_ref().unwrap(),
             translation.function_body_inputs,
+            cancellation,
         )?;
         let function_call_trampolines = compilation.get_function_call_trampolines();
         let dynamic_function_trampolines = compilation.get_dynamic_function_trampolines();
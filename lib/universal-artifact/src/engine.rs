@@ -1,8 +1,10 @@
 //! Universal compilation.
 
 use loupe::MemoryUsage;
+use std::sync::Arc;
 use wasmer_compiler::CompileError;
 use wasmer_compiler::Compiler;
+use wasmer_compiler::ValidationLimits;
 use wasmer_types::Features;
 
 /// The Builder contents of `UniversalEngine`
@@ -10,16 +12,28 @@ use wasmer_types::Features;
 pub struct UniversalEngineBuilder {
     /// The compiler
     #[cfg(feature = "compiler")]
-    compiler: Option<Box<dyn Compiler>>,
+    compiler: Option<Arc<dyn Compiler>>,
     /// The features to compile the Wasm module with
     features: Features,
+    /// The limits enforced on the functions while validating
+    validation_limits: ValidationLimits,
 }
 
 impl UniversalEngineBuilder {
     /// Create a new builder with pre-made components
     #[cfg(feature = "compiler")]
     pub fn new(compiler: Option<Box<dyn Compiler>>, features: Features) -> Self {
-        UniversalEngineBuilder { compiler, features }
+        UniversalEngineBuilder {
+            compiler: compiler.map(Arc::from),
+            features,
+            validation_limits: ValidationLimits::default(),
+        }
+    }
+
+    /// Replaces the compiler associated to this engine.
+    #[cfg(feature = "compiler")]
+    pub fn set_compiler(&mut self, compiler: Box<dyn Compiler>) {
+        self.compiler = Some(compiler.into());
     }
 
     /// Gets the compiler associated to this engine.
@@ -33,6 +47,15 @@ impl UniversalEngineBuilder {
         Ok(&**self.compiler.as_ref().unwrap())
     }
 
+    /// Gets a handle on the compiler associated to this engine, which
+    /// can be used once the builder is no longer borrowed.
+    #[cfg(feature = "compiler")]
+    pub fn compiler_handle(&self) -> Result<Arc<dyn Compiler>, CompileError> {
+        self.compiler.clone().ok_or_else(|| {
+            CompileError::Codegen("The UniversalEngine is not compiled in.".to_string())
+        })
+    }
+
     /// Gets the compiler associated to this engine.
     #[cfg(not(feature = "compiler"))]
     pub fn compiler(&self) -> Result<&dyn Compiler, CompileError> {
@@ -44,7 +67,8 @@ impl UniversalEngineBuilder {
     /// Validate the module
     #[cfg(feature = "compiler")]
     pub fn validate<'data>(&self, data: &'data [u8]) -> Result<(), CompileError> {
-        self.compiler()?.validate_module(self.features(), data)
+        self.compiler()?
+            .validate_module(self.features(), self.validation_limits(), data)
     }
 
     /// Validate the module
@@ -60,4 +84,14 @@ impl UniversalEngineBuilder {
     pub fn features(&self) -> &Features {
         &self.features
     }
+
+    /// The limits enforced on the functions while validating
+    pub fn validation_limits(&self) -> &ValidationLimits {
+        &self.validation_limits
+    }
+
+    /// Replaces the limits enforced on the functions while validating
+    pub fn set_validation_limits(&mut self, limits: ValidationLimits) {
+        self.validation_limits = limits;
+    }
 }
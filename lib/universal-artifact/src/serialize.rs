@@ -7,9 +7,9 @@ use rkyv::{
 use wasmer_artifact::{DeserializeError, SerializeError};
 use wasmer_compiler::{
     CompileModuleInfo, CompiledFunctionFrameInfo, CustomSection, Dwarf, FunctionBody, Relocation,
-    SectionIndex,
+    SectionBody, SectionIndex,
 };
-use wasmer_types::entity::PrimaryMap;
+use wasmer_types::entity::{ArchivedPrimaryMap, EntityRef, PrimaryMap};
 use wasmer_types::{FunctionIndex, LocalFunctionIndex, OwnedDataInitializer, SignatureIndex};
 
 /// The compilation related data for a serialized modules
@@ -78,11 +78,15 @@ impl SerializableModule {
         Self::deserialize_from_archive(archived)
     }
 
+    /// Get the archive of a Module in a slice, without deserializing it.
+    /// The slice must have the following format:
+    /// RKYV serialization (any length) + POS (8 bytes)
+    ///
     /// # Safety
     ///
     /// This method is unsafe.
     /// Please check `SerializableModule::deserialize` for more details.
-    unsafe fn archive_from_slice<'a>(
+    pub unsafe fn archive_from_slice<'a>(
         metadata_slice: &'a [u8],
     ) -> Result<&'a ArchivedSerializableModule, DeserializeError> {
         if metadata_slice.len() < 8 {
@@ -107,4 +111,131 @@ impl SerializableModule {
         RkyvDeserialize::deserialize(archived, &mut deserializer)
             .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))
     }
+
+    /// Deserialize a compilation module from an archive, leaving the
+    /// bytes of the function bodies and custom sections empty.
+    ///
+    /// The code is meant to be read in place from the archive, with
+    /// `ArchivedSerializableModule::function_bodies` and the like, so
+    /// loading a module doesn't copy it.
+    pub fn deserialize_metadata_from_archive(
+        archived: &ArchivedSerializableModule,
+    ) -> Result<Self, DeserializeError> {
+        let mut deserializer = SharedDeserializeMap::new();
+        let compilation = &archived.compilation;
+        Ok(Self {
+            compilation: SerializableCompilation {
+                function_bodies: deserialize_without_code(
+                    &compilation.function_bodies,
+                    &mut deserializer,
+                )?,
+                function_relocations: deserialize(
+                    &compilation.function_relocations,
+                    &mut deserializer,
+                )?,
+                function_frame_info: deserialize(
+                    &compilation.function_frame_info,
+                    &mut deserializer,
+                )?,
+                function_call_trampolines: deserialize_without_code(
+                    &compilation.function_call_trampolines,
+                    &mut deserializer,
+                )?,
+                dynamic_function_trampolines: deserialize_without_code(
+                    &compilation.dynamic_function_trampolines,
+                    &mut deserializer,
+                )?,
+                custom_sections: compilation
+                    .custom_sections
+                    .values()
+                    .iter()
+                    .map(|section| {
+                        Ok(CustomSection {
+                            protection: deserialize(&section.protection, &mut deserializer)?,
+                            bytes: SectionBody::default(),
+                            relocations: deserialize(&section.relocations, &mut deserializer)?,
+                        })
+                    })
+                    .collect::<Result<_, DeserializeError>>()?,
+                custom_section_relocations: deserialize(
+                    &compilation.custom_section_relocations,
+                    &mut deserializer,
+                )?,
+                debug: deserialize(&compilation.debug, &mut deserializer)?,
+                libcall_trampolines: deserialize(
+                    &compilation.libcall_trampolines,
+                    &mut deserializer,
+                )?,
+                libcall_trampoline_len: deserialize(
+                    &compilation.libcall_trampoline_len,
+                    &mut deserializer,
+                )?,
+            },
+            compile_info: deserialize(&archived.compile_info, &mut deserializer)?,
+            data_initializers: deserialize(&archived.data_initializers, &mut deserializer)?,
+            cpu_features: deserialize(&archived.cpu_features, &mut deserializer)?,
+        })
+    }
+}
+
+impl ArchivedSerializableModule {
+    /// Get the bytes of the function bodies, in place.
+    pub fn function_bodies(&self) -> impl Iterator<Item = &[u8]> {
+        code(&self.compilation.function_bodies)
+    }
+
+    /// Get the bytes of the function call trampolines, in place.
+    pub fn function_call_trampolines(&self) -> impl Iterator<Item = &[u8]> {
+        code(&self.compilation.function_call_trampolines)
+    }
+
+    /// Get the bytes of the dynamic function trampolines, in place.
+    pub fn dynamic_function_trampolines(&self) -> impl Iterator<Item = &[u8]> {
+        code(&self.compilation.dynamic_function_trampolines)
+    }
+
+    /// Get the bytes of the custom sections, in place.
+    pub fn custom_sections(&self) -> impl Iterator<Item = &[u8]> {
+        self.compilation
+            .custom_sections
+            .values()
+            .iter()
+            .map(|section| section.bytes.as_slice())
+    }
+}
+
+fn deserialize<T: Archive>(
+    archived: &T::Archived,
+    deserializer: &mut SharedDeserializeMap,
+) -> Result<T, DeserializeError>
+where
+    T::Archived: RkyvDeserialize<T, SharedDeserializeMap>,
+{
+    RkyvDeserialize::deserialize(archived, deserializer)
+        .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))
+}
+
+fn deserialize_without_code<K: EntityRef>(
+    archived: &ArchivedPrimaryMap<K, FunctionBody>,
+    deserializer: &mut SharedDeserializeMap,
+) -> Result<PrimaryMap<K, FunctionBody>, DeserializeError> {
+    archived
+        .values()
+        .iter()
+        .map(|function| {
+            Ok(FunctionBody {
+                body: Vec::new(),
+                unwind_info: deserialize(&function.unwind_info, deserializer)?,
+            })
+        })
+        .collect()
+}
+
+fn code<K: EntityRef>(
+    functions: &ArchivedPrimaryMap<K, FunctionBody>,
+) -> impl Iterator<Item = &[u8]> {
+    functions
+        .values()
+        .iter()
+        .map(|function| function.body.as_slice())
 }
@@ -26,6 +26,15 @@ const X86_64_TRAMPOLINE: [u8; 16] = [
     0xff, 0x25, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];
 
+// r1 is a scratch register of the s390x ABI. The address is aligned on 8
+// bytes, as required by LGRL.
+// LGRL %r1, .+8      c4 18 00 00 00 04
+// BR %r1             07 f1
+// JMPADDR            00 00 00 00 00 00 00 00
+const S390X_TRAMPOLINE: [u8; 16] = [
+    0xc4, 0x18, 0x00, 0x00, 0x00, 0x04, 0x07, 0xf1, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
 fn make_trampoline(
     target: &Target,
     libcall: LibCall,
@@ -51,6 +60,15 @@ fn make_trampoline(
                 addend: 0,
             });
         }
+        Architecture::S390x => {
+            code.extend(&S390X_TRAMPOLINE);
+            relocations.push(Relocation {
+                kind: RelocationKind::Abs8,
+                reloc_target: RelocationTarget::LibCall(libcall),
+                offset: code.len() as u32 - 8,
+                addend: 0,
+            });
+        }
         arch => panic!("Unsupported architecture: {}", arch),
     };
 }
@@ -60,6 +78,7 @@ pub fn libcall_trampoline_len(target: &Target) -> usize {
     match target.triple().architecture {
         Architecture::Aarch64(_) => AARCH64_TRAMPOLINE.len(),
         Architecture::X86_64 => X86_64_TRAMPOLINE.len(),
+        Architecture::S390x => S390X_TRAMPOLINE.len(),
         arch => panic!("Unsupported architecture: {}", arch),
     }
 }
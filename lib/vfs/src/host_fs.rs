@@ -1,6 +1,6 @@
 use crate::{
-    DirEntry, FileDescriptor, FileType, FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir,
-    Result, VirtualFile,
+    Advice, DirEntry, FileDescriptor, FileType, FsError, Metadata, OpenOptions, OpenOptionsConfig,
+    ReadDir, Result, VirtualFile,
 };
 #[cfg(feature = "enable-serde")]
 use serde::{de, Deserialize, Serialize};
@@ -420,6 +420,18 @@ impl VirtualFile for File {
     fn bytes_available(&self) -> Result<usize> {
         host_file_bytes_available(self.inner.try_into_filedescriptor()?)
     }
+
+    fn allocate(&mut self, offset: u64, len: u64) -> Result<()> {
+        host_file_allocate(&self.inner, offset, len)
+    }
+
+    fn set_times(&mut self, accessed: Option<u64>, modified: Option<u64>) -> Result<()> {
+        host_file_set_times(&self.inner, accessed, modified)
+    }
+
+    fn advise(&mut self, offset: u64, len: u64, advice: Advice) -> Result<()> {
+        host_file_advise(&self.inner, offset, len, advice)
+    }
 }
 
 #[cfg(unix)]
@@ -442,6 +454,111 @@ fn host_file_bytes_available(_host_fd: FileDescriptor) -> Result<usize> {
     unimplemented!("host_file_bytes_available not yet implemented for non-Unix-like targets.  This probably means the program tried to use wasi::poll_oneoff")
 }
 
+/// Maps the error numbers of the calls to the file system of the host.
+#[cfg(unix)]
+fn host_errno_into_fs_error(errno: libc::c_int) -> FsError {
+    match errno {
+        libc::EBADF => FsError::InvalidFd,
+        libc::EINVAL => FsError::InvalidInput,
+        libc::EINTR => FsError::Interrupted,
+        libc::EPERM | libc::EACCES | libc::EROFS => FsError::PermissionDenied,
+        libc::ENOSPC | libc::EFBIG => FsError::WriteZero,
+        libc::ENODEV | libc::ESPIPE => FsError::NoDevice,
+        _ => FsError::IOError,
+    }
+}
+
+/// Grows `file` to `offset + len` bytes if it is shorter.
+fn host_file_grow(file: &fs::File, offset: u64, len: u64) -> Result<()> {
+    let end = offset.checked_add(len).ok_or(FsError::InvalidInput)?;
+    if end > file.metadata()?.len() {
+        file.set_len(end)?;
+    }
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn host_file_allocate(file: &fs::File, offset: u64, len: u64) -> Result<()> {
+    let host_offset = offset.try_into().map_err(|_| FsError::InvalidInput)?;
+    let host_len = len.try_into().map_err(|_| FsError::InvalidInput)?;
+    // `posix_fallocate` returns the error number rather than setting
+    // `errno`.
+    match unsafe { libc::posix_fallocate(file.as_raw_fd(), host_offset, host_len) } {
+        0 => Ok(()),
+        // The file system can't preallocate, the file is grown instead.
+        libc::EOPNOTSUPP => host_file_grow(file, offset, len),
+        errno => Err(host_errno_into_fs_error(errno)),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+fn host_file_allocate(file: &fs::File, offset: u64, len: u64) -> Result<()> {
+    host_file_grow(file, offset, len)
+}
+
+#[cfg(unix)]
+fn host_file_set_times(
+    file: &fs::File,
+    accessed: Option<u64>,
+    modified: Option<u64>,
+) -> Result<()> {
+    fn timespec(time: Option<u64>) -> libc::timespec {
+        match time {
+            Some(time) => libc::timespec {
+                tv_sec: (time / 1_000_000_000) as _,
+                tv_nsec: (time % 1_000_000_000) as _,
+            },
+            None => libc::timespec {
+                tv_sec: 0,
+                tv_nsec: libc::UTIME_OMIT,
+            },
+        }
+    }
+
+    let times = [timespec(accessed), timespec(modified)];
+    if unsafe { libc::futimens(file.as_raw_fd(), times.as_ptr()) } == 0 {
+        Ok(())
+    } else {
+        let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+        Err(host_errno_into_fs_error(errno))
+    }
+}
+
+#[cfg(not(unix))]
+fn host_file_set_times(
+    _file: &fs::File,
+    _accessed: Option<u64>,
+    _modified: Option<u64>,
+) -> Result<()> {
+    // The times are only kept by the file system using the file.
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn host_file_advise(file: &fs::File, offset: u64, len: u64, advice: Advice) -> Result<()> {
+    let advice = match advice {
+        Advice::Normal => libc::POSIX_FADV_NORMAL,
+        Advice::Sequential => libc::POSIX_FADV_SEQUENTIAL,
+        Advice::Random => libc::POSIX_FADV_RANDOM,
+        Advice::WillNeed => libc::POSIX_FADV_WILLNEED,
+        Advice::DontNeed => libc::POSIX_FADV_DONTNEED,
+        Advice::NoReuse => libc::POSIX_FADV_NOREUSE,
+    };
+    let offset = offset.try_into().map_err(|_| FsError::InvalidInput)?;
+    let len = len.try_into().map_err(|_| FsError::InvalidInput)?;
+    // Like `posix_fallocate`, `posix_fadvise` returns the error number.
+    match unsafe { libc::posix_fadvise(file.as_raw_fd(), offset, len, advice) } {
+        0 => Ok(()),
+        errno => Err(host_errno_into_fs_error(errno)),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+fn host_file_advise(_file: &fs::File, _offset: u64, _len: u64, _advice: Advice) -> Result<()> {
+    // The advice is only a hint, which the host can't take.
+    Ok(())
+}
+
 /// A wrapper type around Stdout that implements `VirtualFile` and
 /// `Serialize` + `Deserialize`.
 #[derive(Debug, Default)]
@@ -728,3 +845,46 @@ impl VirtualFile for Stdin {
         io::stdin().try_into_filedescriptor().ok()
     }
 }
+
+#[cfg(test)]
+mod test_host_file {
+    use super::FileSystem;
+    use crate::{Advice, FileSystem as FS, VirtualFile};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn open(name: &str) -> (PathBuf, Box<dyn VirtualFile>) {
+        let path = std::env::temp_dir().join(format!("wasmer-vfs-{}-{}", name, std::process::id()));
+        let file = FileSystem
+            .new_open_options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        (path, file)
+    }
+
+    #[test]
+    fn test_allocate_never_shrinks() {
+        let (path, mut file) = open("allocate");
+        file.allocate(10, 100).unwrap();
+        assert_eq!(file.size(), 110);
+        file.allocate(0, 10).unwrap();
+        assert_eq!(file.size(), 110);
+        assert!(file.allocate(u64::MAX, 1).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_set_times() {
+        let (path, mut file) = open("set-times");
+        let modified = 1_500_000_000_123_000_000;
+        file.set_times(None, Some(modified)).unwrap();
+        #[cfg(unix)]
+        assert_eq!(file.last_modified(), modified);
+        file.advise(0, 0, Advice::Sequential).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+}
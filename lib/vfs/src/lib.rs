@@ -179,6 +179,36 @@ pub trait VirtualFile: fmt::Debug + Send + Write + Read + Seek + 'static + Upcas
     /// Returns the number of bytes available.  This function must not block
     fn bytes_available(&self) -> Result<usize>;
 
+    /// Makes sure the bytes from `offset` to `offset + len` are allocated,
+    /// growing the file if it is shorter, never shrinking it.
+    /// Default implementation grows the file with [`VirtualFile::set_len`].
+    fn allocate(&mut self, offset: u64, len: u64) -> Result<()> {
+        let end = offset.checked_add(len).ok_or(FsError::InvalidInput)?;
+        if end > self.size() {
+            self.set_len(end)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the last access and modification times of the file, in
+    /// nanoseconds as UNIX timestamps, leaving the ones which are `None`
+    /// unchanged.
+    /// Default implementation returns `Ok(())`, for files whose times are
+    /// only kept by the file system using them.
+    fn set_times(&mut self, accessed: Option<u64>, modified: Option<u64>) -> Result<()> {
+        let _ = (accessed, modified);
+        Ok(())
+    }
+
+    /// Advises how the bytes from `offset` to `offset + len` will be
+    /// accessed, `len == 0` meaning until the end of the file.
+    /// Default implementation returns `Ok(())`, since the advice is only a
+    /// hint.
+    fn advise(&mut self, offset: u64, len: u64, advice: Advice) -> Result<()> {
+        let _ = (offset, len, advice);
+        Ok(())
+    }
+
     /// Used for polling.  Default returns `None` because this method cannot be implemented for most types
     /// Returns the underlying host fd
     fn get_fd(&self) -> Option<FileDescriptor> {
@@ -186,6 +216,24 @@ pub trait VirtualFile: fmt::Debug + Send + Write + Read + Seek + 'static + Upcas
     }
 }
 
+/// How a program will access the bytes of a file, given to
+/// [`VirtualFile::advise`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Advice {
+    /// No particular access pattern.
+    Normal,
+    /// The bytes will be accessed in order.
+    Sequential,
+    /// The bytes will be accessed in random order.
+    Random,
+    /// The bytes will be accessed soon.
+    WillNeed,
+    /// The bytes won't be accessed soon.
+    DontNeed,
+    /// The bytes will be accessed only once.
+    NoReuse,
+}
+
 // Implementation of `Upcastable` taken from https://users.rust-lang.org/t/why-does-downcasting-not-work-for-subtraits/33286/7 .
 /// Trait needed to get downcasting from `VirtualFile` to work.
 pub trait Upcastable {
@@ -0,0 +1,162 @@
+//! Tracking of the pages written to in linear memories, using the
+//! soft-dirty bits of Linux.
+//!
+//! Writing `4` to `/proc/self/clear_refs` clears the soft-dirty bits of
+//! every page of the process, and the kernel sets the bit of a page again
+//! when it's written to, which `/proc/self/pagemap` reports. As the bits
+//! are process wide, the pages of every tracked memory are collected
+//! before they are cleared, so that tracking one memory doesn't make the
+//! others lose their dirty pages.
+
+use crate::mmap::Mmap;
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::FileExt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use wasmer_types::{Pages, WASM_PAGE_SIZE};
+
+/// The bit of a `/proc/self/pagemap` entry set for soft-dirty pages.
+const PAGEMAP_SOFT_DIRTY: u64 = 1 << 55;
+
+/// The number of `/proc/self/pagemap` entries read at once.
+const PAGEMAP_CHUNK: usize = 4096;
+
+/// The pages of a tracked memory.
+struct Tracked {
+    /// The address of the memory.
+    base: usize,
+    /// The current length of the memory in bytes.
+    len: usize,
+    /// The wasm pages written to since they were last taken.
+    dirty: BTreeSet<u32>,
+}
+
+lazy_static! {
+    static ref SUPPORTED: bool = probe().unwrap_or(false);
+    static ref TRACKED: Mutex<BTreeMap<u64, Tracked>> = Mutex::new(BTreeMap::new());
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Whether the soft-dirty bit of a page is cleared and set again
+/// when the page is written to.
+///
+/// Kernels built without `CONFIG_MEM_SOFT_DIRTY` never set the bit,
+/// which would make every page look clean.
+fn probe() -> io::Result<bool> {
+    let page_size = region::page::size();
+    let mut mmap = Mmap::accessible_reserved(page_size, page_size)
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+    let pagemap = File::open("/proc/self/pagemap")?;
+    let page = mmap.as_mut_ptr();
+    unsafe { page.write_volatile(1) };
+    clear_soft_dirty()?;
+    let cleared = !is_soft_dirty(&pagemap, page as usize)?;
+    unsafe { page.write_volatile(2) };
+    Ok(cleared && is_soft_dirty(&pagemap, page as usize)?)
+}
+
+fn is_soft_dirty(pagemap: &File, address: usize) -> io::Result<bool> {
+    let mut entry = [0; 8];
+    pagemap.read_exact_at(&mut entry, (address / region::page::size() * 8) as u64)?;
+    Ok(u64::from_ne_bytes(entry) & PAGEMAP_SOFT_DIRTY != 0)
+}
+
+fn clear_soft_dirty() -> io::Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .open("/proc/self/clear_refs")?
+        .write_all(b"4")
+}
+
+/// Adds the soft-dirty pages of every tracked memory to their dirty
+/// pages, then clears the soft-dirty bits.
+fn collect(tracked: &mut BTreeMap<u64, Tracked>) -> io::Result<()> {
+    let page_size = region::page::size();
+    let pagemap = File::open("/proc/self/pagemap")?;
+    let mut buffer = vec![0; PAGEMAP_CHUNK * 8];
+    for memory in tracked.values_mut() {
+        let first = memory.base / page_size;
+        let count = memory.len / page_size;
+        let mut done = 0;
+        while done < count {
+            let chunk = (count - done).min(PAGEMAP_CHUNK);
+            let entries = &mut buffer[..chunk * 8];
+            pagemap.read_exact_at(entries, ((first + done) * 8) as u64)?;
+            for (index, entry) in entries.chunks_exact(8).enumerate() {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(entry);
+                if u64::from_ne_bytes(bytes) & PAGEMAP_SOFT_DIRTY != 0 {
+                    let offset = (done + index) * page_size;
+                    memory.dirty.insert((offset / WASM_PAGE_SIZE) as u32);
+                }
+            }
+            done += chunk;
+        }
+    }
+    clear_soft_dirty()
+}
+
+/// Records the wasm pages of a memory written to, until dropped.
+#[derive(Debug)]
+pub(crate) struct DirtyPageTracker {
+    id: u64,
+}
+
+impl DirtyPageTracker {
+    /// Whether the host supports tracking dirty pages.
+    pub(crate) fn is_supported() -> bool {
+        *SUPPORTED
+    }
+
+    /// Starts tracking the `len` bytes of memory at `base`.
+    pub(crate) fn start(base: usize, len: usize) -> io::Result<Self> {
+        if !Self::is_supported() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "the kernel doesn't report soft-dirty pages",
+            ));
+        }
+        let mut tracked = TRACKED.lock().unwrap();
+        collect(&mut tracked)?;
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        tracked.insert(
+            id,
+            Tracked {
+                base,
+                len,
+                dirty: BTreeSet::new(),
+            },
+        );
+        Ok(Self { id })
+    }
+
+    /// Follows the memory after it grew, and possibly moved.
+    pub(crate) fn resized(&self, base: usize, len: usize) {
+        if let Some(memory) = TRACKED.lock().unwrap().get_mut(&self.id) {
+            memory.base = base;
+            memory.len = len;
+        }
+    }
+
+    /// Returns the wasm pages written to since they were last taken, or
+    /// since the tracking started.
+    pub(crate) fn take(&self) -> io::Result<Vec<Pages>> {
+        let mut tracked = TRACKED.lock().unwrap();
+        collect(&mut tracked)?;
+        let memory = tracked.get_mut(&self.id).unwrap();
+        Ok(std::mem::take(&mut memory.dirty)
+            .into_iter()
+            .map(Pages)
+            .collect())
+    }
+}
+
+impl Drop for DirtyPageTracker {
+    fn drop(&mut self) {
+        TRACKED.lock().unwrap().remove(&self.id);
+    }
+}
@@ -4,6 +4,7 @@
 use crate::global::Global;
 use crate::instance::WeakOrStrongInstanceRef;
 use crate::memory::Memory;
+use crate::mpk::{protection_keys_in_use, ProtectionDomain};
 use crate::table::Table;
 use crate::vmcontext::{VMFunctionBody, VMFunctionEnvironment, VMFunctionKind, VMTrampoline};
 use loupe::MemoryUsage;
@@ -64,6 +65,21 @@ impl VMFunction {
         }
         Some(())
     }
+    /// Restricts the current thread to the memories of the instance of
+    /// the function, until the returned domain is dropped. Returns `None`
+    /// when no memory is tagged with a protection key.
+    pub fn enter_protection_domain(&self) -> Option<ProtectionDomain> {
+        if !protection_keys_in_use() {
+            return None;
+        }
+        let keys = match self.instance_ref.as_ref()? {
+            WeakOrStrongInstanceRef::Strong(instance_ref) => instance_ref.protection_keys(),
+            WeakOrStrongInstanceRef::Weak(instance_ref) => {
+                instance_ref.upgrade()?.protection_keys()
+            }
+        };
+        ProtectionDomain::enter(keys)
+    }
 }
 
 /// # Safety
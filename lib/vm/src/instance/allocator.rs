@@ -103,6 +103,13 @@ impl InstanceAllocator {
         (allocator, memories, tables)
     }
 
+    /// The bytes allocated for an instance of `module` and its
+    /// `VMContext`.
+    pub fn instance_size(module: &ModuleInfo) -> usize {
+        let offsets = VMOffsets::new(mem::size_of::<usize>() as u8, module);
+        Self::instance_layout(&offsets).size()
+    }
+
     /// Calculate the appropriate layout for the [`Instance`].
     fn instance_layout(offsets: &VMOffsets) -> Layout {
         let vmctx_size = usize::try_from(offsets.size_of_vmctx())
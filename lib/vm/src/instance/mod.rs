@@ -18,6 +18,7 @@ use crate::func_data_registry::VMFuncRef;
 use crate::global::Global;
 use crate::imports::Imports;
 use crate::memory::{Memory, MemoryError};
+use crate::mpk::{ProtectionDomain, ProtectionKeys};
 use crate::table::{Table, TableElement};
 use crate::trap::{catch_traps, Trap, TrapCode, TrapHandler};
 use crate::vmcontext::{
@@ -32,15 +33,13 @@ use loupe::{MemoryUsage, MemoryUsageTracker};
 use memoffset::offset_of;
 use more_asserts::assert_lt;
 use std::any::Any;
-use std::cell::RefCell;
 use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::ffi;
 use std::fmt;
 use std::mem;
 use std::ptr::{self, NonNull};
-use std::slice;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use wasmer_types::entity::{packed_option::ReservedValue, BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
     DataIndex, DataInitializer, ElemIndex, ExportIndex, FunctionIndex, GlobalIndex, GlobalInit,
@@ -71,6 +70,9 @@ pub(crate) struct Instance {
     /// WebAssembly linear memory data.
     memories: BoxedSlice<LocalMemoryIndex, Arc<dyn Memory>>,
 
+    /// The protection keys of the local and imported memories.
+    protection_keys: ProtectionKeys,
+
     /// WebAssembly table data.
     tables: BoxedSlice<LocalTableIndex, Arc<dyn Table>>,
 
@@ -86,11 +88,14 @@ pub(crate) struct Instance {
 
     /// Passive elements in this instantiation. As `elem.drop`s happen, these
     /// entries get removed.
-    passive_elements: RefCell<HashMap<ElemIndex, Box<[VMFuncRef]>>>,
+    ///
+    /// They are behind locks as the instance can be used from several threads
+    /// at once, see [`InstanceRef`].
+    passive_elements: Mutex<HashMap<ElemIndex, Box<[VMFuncRef]>>>,
 
     /// Passive data segments from our module. As `data.drop`s happen, entries
     /// get removed. A missing entry is considered equivalent to an empty slice.
-    passive_data: RefCell<HashMap<DataIndex, Arc<[u8]>>>,
+    passive_data: Mutex<HashMap<DataIndex, Arc<[u8]>>>,
 
     /// Mapping of function indices to their func ref backing data. `VMFuncRef`s
     /// will point to elements here for functions defined or imported by this
@@ -378,6 +383,11 @@ impl Instance {
         unsafe { self.vmctx_plus_offset(self.offsets.vmctx_builtin_functions_begin()) }
     }
 
+    /// Return a pointer to the `call_indirect` inline caches.
+    fn call_indirect_caches_ptr(&self) -> *mut *const VMCallerCheckedAnyfunc {
+        unsafe { self.vmctx_plus_offset(self.offsets.vmctx_call_indirect_caches_begin()) }
+    }
+
     /// Return a reference to the vmctx used by compiled wasm code.
     fn vmctx(&self) -> &VMContext {
         &self.vmctx
@@ -426,6 +436,7 @@ impl Instance {
         };
 
         // Make the call.
+        let _domain = ProtectionDomain::enter(self.protection_keys);
         unsafe {
             catch_traps(trap_handler, || {
                 mem::transmute::<*const VMFunctionBody, unsafe extern "C" fn(VMFunctionEnvironment)>(
@@ -660,7 +671,7 @@ impl Instance {
         // https://webassembly.github.io/bulk-memory-operations/core/exec/instructions.html#exec-table-init
 
         let table = self.get_table(table_index);
-        let passive_elements = self.passive_elements.borrow();
+        let passive_elements = self.passive_elements.lock().unwrap();
         let elem = passive_elements
             .get(&elem_index)
             .map_or::<&[VMFuncRef], _>(&[], |e| &**e);
@@ -719,7 +730,7 @@ impl Instance {
     pub(crate) fn elem_drop(&self, elem_index: ElemIndex) {
         // https://webassembly.github.io/reference-types/core/exec/instructions.html#exec-elem-drop
 
-        let mut passive_elements = self.passive_elements.borrow_mut();
+        let mut passive_elements = self.passive_elements.lock().unwrap();
         passive_elements.remove(&elem_index);
         // Note that we don't check that we actually removed an element because
         // dropping a non-passive element is a no-op (not a trap).
@@ -812,8 +823,8 @@ impl Instance {
         // https://webassembly.github.io/bulk-memory-operations/core/exec/instructions.html#exec-memory-init
 
         let memory = self.get_memory(memory_index);
-        let passive_data = self.passive_data.borrow();
-        let data = passive_data.get(&data_index).map_or(&[][..], |d| &**d);
+        let passive_data = self.passive_data.lock().unwrap().get(&data_index).cloned();
+        let data = passive_data.as_deref().unwrap_or(&[]);
 
         if src
             .checked_add(len)
@@ -827,10 +838,11 @@ impl Instance {
 
         let src_slice = &data[src as usize..(src + len) as usize];
 
+        // The memory may be shared with other threads, so no slice is made
+        // over it.
         unsafe {
             let dst_start = memory.base.add(dst as usize);
-            let dst_slice = slice::from_raw_parts_mut(dst_start, len as usize);
-            dst_slice.copy_from_slice(src_slice);
+            ptr::copy_nonoverlapping(src_slice.as_ptr(), dst_start, src_slice.len());
         }
 
         Ok(())
@@ -838,7 +850,7 @@ impl Instance {
 
     /// Drop the given data segment, truncating its length to zero.
     pub(crate) fn data_drop(&self, data_index: DataIndex) {
-        let mut passive_data = self.passive_data.borrow_mut();
+        let mut passive_data = self.passive_data.lock().unwrap();
         passive_data.remove(&data_index);
     }
 
@@ -852,6 +864,17 @@ impl Instance {
         }
     }
 
+    /// Get a locally-defined memory.
+    pub(crate) fn get_local_memory(&self, index: LocalMemoryIndex) -> &dyn Memory {
+        self.memories[index].as_ref()
+    }
+
+    /// Get an imported, foreign memory.
+    pub(crate) fn get_foreign_memory(&self, index: MemoryIndex) -> &dyn Memory {
+        let import = self.imported_memory(index);
+        &*import.from
+    }
+
     /// Get a locally-defined table.
     pub(crate) fn get_local_table(&self, index: LocalTableIndex) -> &dyn Table {
         self.tables[index].as_ref()
@@ -916,7 +939,16 @@ impl InstanceHandle {
             .map(|m| m.vmglobal())
             .collect::<PrimaryMap<LocalGlobalIndex, _>>()
             .into_boxed_slice();
-        let passive_data = RefCell::new(module.passive_data.clone());
+        let passive_data = Mutex::new(module.passive_data.clone());
+        let mut protection_keys = ProtectionKeys::default();
+        for memory in finished_memories
+            .values()
+            .chain(imports.memories.values().map(|import| &import.from))
+        {
+            if let Some(key) = memory.protection_key() {
+                protection_keys.insert(key);
+            }
+        }
 
         let handle = {
             let offsets = allocator.offsets().clone();
@@ -927,6 +959,7 @@ impl InstanceHandle {
                 module,
                 offsets,
                 memories: finished_memories,
+                protection_keys,
                 tables: finished_tables,
                 globals: finished_globals,
                 functions: finished_functions,
@@ -997,6 +1030,11 @@ impl InstanceHandle {
             instance.builtin_functions_ptr() as *mut VMBuiltinFunctionsArray,
             VMBuiltinFunctionsArray::initialized(),
         );
+        ptr::write_bytes(
+            instance.call_indirect_caches_ptr(),
+            0,
+            vmshared_signatures.len(),
+        );
 
         // Perform infallible initialization in this constructor, while fallible
         // initialization is deferred to the `initialize` method.
@@ -1284,22 +1322,19 @@ fn get_memory_init_start(init: &DataInitializer<'_>, instance: &Instance) -> usi
     start
 }
 
-#[allow(clippy::mut_from_ref)]
-/// Return a byte-slice view of a memory's data.
-unsafe fn get_memory_slice<'instance>(
+/// Return the memory a data initializer writes to.
+fn get_memory_object<'instance>(
     init: &DataInitializer<'_>,
     instance: &'instance Instance,
-) -> &'instance mut [u8] {
-    let memory = if let Some(local_memory_index) = instance
+) -> &'instance dyn Memory {
+    if let Some(local_memory_index) = instance
         .module
         .local_memory_index(init.location.memory_index)
     {
-        instance.memory(local_memory_index)
+        instance.get_local_memory(local_memory_index)
     } else {
-        let import = instance.imported_memory(init.location.memory_index);
-        *import.definition.as_ref()
-    };
-    slice::from_raw_parts_mut(memory.base, memory.current_length.try_into().unwrap())
+        instance.get_foreign_memory(init.location.memory_index)
+    }
 }
 
 /// Compute the offset for a table element initializer.
@@ -1352,7 +1387,7 @@ fn initialize_tables(instance: &Instance) -> Result<(), Trap> {
 /// `ModuleInfo::passive_elements`'s `FunctionIndex`s into `VMCallerCheckedAnyfunc`s for
 /// this instance.
 fn initialize_passive_elements(instance: &Instance) {
-    let mut passive_elements = instance.passive_elements.borrow_mut();
+    let mut passive_elements = instance.passive_elements.lock().unwrap();
     debug_assert!(
         passive_elements.is_empty(),
         "should only be called once, at initialization time"
@@ -1393,10 +1428,7 @@ fn initialize_memories(
         }
 
         unsafe {
-            let mem_slice = get_memory_slice(init, instance);
-            let end = start + init.data.len();
-            let to_init = &mut mem_slice[start..end];
-            to_init.copy_from_slice(init.data);
+            get_memory_object(init, instance).initialize_with_data(start, init.data);
         }
     }
 
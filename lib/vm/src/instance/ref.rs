@@ -1,4 +1,5 @@
 use super::Instance;
+use crate::mpk::ProtectionKeys;
 use loupe::{MemoryUsage, MemoryUsageTracker};
 use std::alloc::Layout;
 use std::convert::TryFrom;
@@ -78,7 +79,17 @@ impl Drop for InstanceInner {
     }
 }
 
-/// TODO: Review this super carefully.
+// An `Instance` can be used from any thread, and from several threads at
+// once:
+//
+// - its state mutated by the host (memories, tables, globals, passive
+//   segments) is behind locks, and the host functions it imports must be
+//   `Send + Sync`;
+// - the `VMContext` is written by WebAssembly code without synchronization,
+//   like a linear memory shared between threads: concurrent calls see each
+//   other's writes with no ordering, but never corrupt the runtime;
+// - the trap handling state is thread-local, so a trap unwinds the thread
+//   that raised it only.
 unsafe impl Send for InstanceInner {}
 unsafe impl Sync for InstanceInner {}
 
@@ -137,6 +148,11 @@ impl InstanceRef {
         (&*self.0).as_ref()
     }
 
+    /// The protection keys of the memories the instance can access.
+    pub fn protection_keys(&self) -> ProtectionKeys {
+        self.as_ref().protection_keys
+    }
+
     /// Only succeeds if ref count is 1.
     #[inline]
     pub(super) fn as_mut(&mut self) -> Option<&mut Instance> {
@@ -0,0 +1,465 @@
+//! Memories whose pages are populated on first access, using Linux's
+//! `userfaultfd`.
+//!
+//! A [`LazyMemory`] registers its accessible pages with a process wide
+//! userfaultfd. The data segments copied while instantiating a module are
+//! only recorded: the first access to a page makes a handler thread build
+//! it from zeros and the recorded segments overlapping it. Instantiating a
+//! module with a large memory then costs nothing for the pages the
+//! instance never touches.
+
+use crate::memory::{LinearMemory, Memory, MemoryError};
+use crate::vmcontext::VMMemoryDefinition;
+use lazy_static::lazy_static;
+use loupe::MemoryUsage;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::io;
+use std::mem;
+use std::os::unix::io::RawFd;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use wasmer_types::{Bytes, MemoryStyle, MemoryType, Pages};
+
+// The userfaultfd ABI, from `linux/userfaultfd.h`.
+const UFFD_API: u64 = 0xAA;
+const UFFDIO_API: u64 = 0xC018_AA3F;
+const UFFDIO_REGISTER: u64 = 0xC020_AA00;
+const UFFDIO_UNREGISTER: u64 = 0x8010_AA01;
+const UFFDIO_COPY: u64 = 0xC028_AA03;
+const UFFDIO_ZEROPAGE: u64 = 0xC020_AA04;
+const UFFDIO_REGISTER_MODE_MISSING: u64 = 1;
+const UFFD_EVENT_PAGEFAULT: u8 = 0x12;
+
+#[repr(C)]
+struct UffdioApi {
+    api: u64,
+    features: u64,
+    ioctls: u64,
+}
+
+#[repr(C)]
+struct UffdioRange {
+    start: u64,
+    len: u64,
+}
+
+#[repr(C)]
+struct UffdioRegister {
+    range: UffdioRange,
+    mode: u64,
+    ioctls: u64,
+}
+
+#[repr(C)]
+struct UffdioCopy {
+    dst: u64,
+    src: u64,
+    len: u64,
+    mode: u64,
+    copy: i64,
+}
+
+#[repr(C)]
+struct UffdioZeropage {
+    range: UffdioRange,
+    mode: u64,
+    zeropage: i64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct UffdMsg {
+    event: u8,
+    reserved1: u8,
+    reserved2: u16,
+    reserved3: u32,
+    flags: u64,
+    address: u64,
+    ptid: u32,
+    padding: u32,
+}
+
+/// The pages of a lazy memory registered with the userfaultfd.
+struct Region {
+    /// Length of the region in bytes.
+    len: usize,
+    /// The data segments recorded for the region, by offset. Later ones
+    /// override the earlier ones.
+    segments: Vec<(usize, Box<[u8]>)>,
+    /// Whether each page of the region was populated already.
+    populated: Vec<bool>,
+}
+
+impl Region {
+    /// The contents of the page at `offset`, or `None` for a zero page.
+    fn page_contents(&self, offset: usize, page_size: usize) -> Option<Vec<u8>> {
+        let mut contents: Option<Vec<u8>> = None;
+        for (start, data) in &self.segments {
+            let from = cmp::max(*start, offset);
+            let to = cmp::min(start + data.len(), offset + page_size);
+            if from >= to {
+                continue;
+            }
+            let page = contents.get_or_insert_with(|| vec![0; page_size]);
+            page[from - offset..to - offset].copy_from_slice(&data[from - start..to - start]);
+        }
+        contents
+    }
+}
+
+/// The regions, keyed by their start address.
+type Regions = Mutex<BTreeMap<usize, Region>>;
+
+/// The process wide userfaultfd and the thread serving its page faults.
+struct Handler {
+    fd: RawFd,
+    regions: Arc<Regions>,
+}
+
+lazy_static! {
+    static ref HANDLER: Option<Handler> = Handler::new().ok();
+}
+
+fn last_error() -> io::Error {
+    io::Error::last_os_error()
+}
+
+/// Issues the userfaultfd `request` on `fd`. The type of the requests
+/// depends on the libc, hence the cast.
+#[allow(trivial_numeric_casts)]
+unsafe fn uffd_ioctl<T>(fd: RawFd, request: u64, argument: &mut T) -> libc::c_int {
+    libc::ioctl(fd, request as _, argument as *mut T)
+}
+
+impl Handler {
+    fn new() -> io::Result<Self> {
+        // Faults from the kernel must be handled too, for example when a
+        // `read` syscall fills untouched pages, so `UFFD_USER_MODE_ONLY`
+        // cannot be used.
+        let fd = unsafe { libc::syscall(libc::SYS_userfaultfd, libc::O_CLOEXEC) } as RawFd;
+        if fd < 0 {
+            return Err(last_error());
+        }
+        let mut api = UffdioApi {
+            api: UFFD_API,
+            features: 0,
+            ioctls: 0,
+        };
+        if unsafe { uffd_ioctl(fd, UFFDIO_API, &mut api) } < 0 {
+            let error = last_error();
+            unsafe { libc::close(fd) };
+            return Err(error);
+        }
+
+        let regions = Arc::new(Mutex::new(BTreeMap::new()));
+        let thread_regions = regions.clone();
+        if let Err(error) = thread::Builder::new()
+            .name("wasmer-userfaultfd".to_string())
+            .spawn(move || serve_faults(fd, &thread_regions))
+        {
+            unsafe { libc::close(fd) };
+            return Err(error);
+        }
+        Ok(Self { fd, regions })
+    }
+
+    fn register(&self, start: usize, len: usize) -> io::Result<()> {
+        let mut regions = self.regions.lock().unwrap();
+        let mut register = UffdioRegister {
+            range: UffdioRange {
+                start: start as u64,
+                len: len as u64,
+            },
+            mode: UFFDIO_REGISTER_MODE_MISSING,
+            ioctls: 0,
+        };
+        if unsafe { uffd_ioctl(self.fd, UFFDIO_REGISTER, &mut register) } < 0 {
+            return Err(last_error());
+        }
+        regions.insert(
+            start,
+            Region {
+                len,
+                segments: Vec::new(),
+                populated: vec![false; len / region::page::size()],
+            },
+        );
+        Ok(())
+    }
+
+    fn unregister(&self, start: usize) {
+        let mut regions = self.regions.lock().unwrap();
+        if let Some(region) = regions.remove(&start) {
+            let mut range = UffdioRange {
+                start: start as u64,
+                len: region.len as u64,
+            };
+            // Unregistering wakes up the threads still waiting on a fault.
+            unsafe { uffd_ioctl(self.fd, UFFDIO_UNREGISTER, &mut range) };
+        }
+    }
+}
+
+/// Serves the page faults of the regions until the process exits.
+fn serve_faults(fd: RawFd, regions: &Regions) {
+    let mut msg = UffdMsg::default();
+    loop {
+        let read = unsafe {
+            libc::read(
+                fd,
+                &mut msg as *mut UffdMsg as *mut libc::c_void,
+                mem::size_of::<UffdMsg>(),
+            )
+        };
+        if read < 0 {
+            match last_error().raw_os_error() {
+                Some(libc::EINTR) | Some(libc::EAGAIN) => continue,
+                _ => return,
+            }
+        }
+        if read as usize == mem::size_of::<UffdMsg>() && msg.event == UFFD_EVENT_PAGEFAULT {
+            populate(fd, regions, msg.address as usize);
+        }
+    }
+}
+
+/// Populates the page containing `address`.
+fn populate(fd: RawFd, regions: &Regions, address: usize) {
+    let page_size = region::page::size();
+    let page = address & !(page_size - 1);
+    let mut regions = regions.lock().unwrap();
+    let (start, region) = match regions.range_mut(..=page).next_back() {
+        Some((start, region)) if page < start + region.len => (*start, region),
+        // The memory was dropped meanwhile, which woke the faulting thread.
+        _ => return,
+    };
+    let offset = page - start;
+
+    loop {
+        let result = match region.page_contents(offset, page_size) {
+            Some(contents) => {
+                let mut copy = UffdioCopy {
+                    dst: page as u64,
+                    src: contents.as_ptr() as u64,
+                    len: page_size as u64,
+                    mode: 0,
+                    copy: 0,
+                };
+                unsafe { uffd_ioctl(fd, UFFDIO_COPY, &mut copy) }
+            }
+            None => {
+                let mut zeropage = UffdioZeropage {
+                    range: UffdioRange {
+                        start: page as u64,
+                        len: page_size as u64,
+                    },
+                    mode: 0,
+                    zeropage: 0,
+                };
+                unsafe { uffd_ioctl(fd, UFFDIO_ZEROPAGE, &mut zeropage) }
+            }
+        };
+        // `EAGAIN` means the mappings were changing, and `EEXIST` that the
+        // page was populated by another fault.
+        if result < 0 && last_error().raw_os_error() == Some(libc::EAGAIN) {
+            continue;
+        }
+        break;
+    }
+    region.populated[offset / page_size] = true;
+}
+
+/// A linear memory whose pages are populated on first access.
+///
+/// Only static memories can be lazy, as dynamic ones may move when they
+/// grow. The pages added by growing the memory are not lazy.
+#[derive(Debug, MemoryUsage)]
+pub struct LazyMemory {
+    memory: LinearMemory,
+    // The address of the region registered with the userfaultfd.
+    base: usize,
+}
+
+impl LazyMemory {
+    /// Whether memories of the given style can be lazy on this host.
+    ///
+    /// The host must allow the process to use `userfaultfd`, see the
+    /// `vm.unprivileged_userfaultfd` sysctl.
+    pub fn is_supported(style: &MemoryStyle) -> bool {
+        matches!(style, MemoryStyle::Static { .. }) && HANDLER.is_some()
+    }
+
+    /// Create a new lazy memory instance with specified minimum and maximum number of wasm pages.
+    ///
+    /// This creates a `LazyMemory` with owned metadata: this can be used to create a memory
+    /// that will be imported into Wasm modules.
+    pub fn new(memory: &MemoryType, style: &MemoryStyle) -> Result<Self, MemoryError> {
+        Self::check_supported(style)?;
+        Self::register(LinearMemory::new(memory, style)?)
+    }
+
+    /// Create a new lazy memory instance with specified minimum and maximum number of wasm pages.
+    ///
+    /// This creates a `LazyMemory` with metadata owned by a VM, pointed to by
+    /// `vm_memory_location`: this can be used to create a local memory.
+    ///
+    /// # Safety
+    /// - `vm_memory_location` must point to a valid location in VM memory.
+    pub unsafe fn from_definition(
+        memory: &MemoryType,
+        style: &MemoryStyle,
+        vm_memory_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Self, MemoryError> {
+        Self::check_supported(style)?;
+        Self::register(LinearMemory::from_definition(
+            memory,
+            style,
+            vm_memory_location,
+        )?)
+    }
+
+    fn check_supported(style: &MemoryStyle) -> Result<(), MemoryError> {
+        if Self::is_supported(style) {
+            Ok(())
+        } else {
+            Err(MemoryError::Generic(format!(
+                "lazy memories are not supported for {:?} on this host",
+                style
+            )))
+        }
+    }
+
+    fn register(memory: LinearMemory) -> Result<Self, MemoryError> {
+        let definition = unsafe { *memory.vmmemory().as_ref() };
+        let base = definition.base as usize;
+        if definition.current_length > 0 {
+            HANDLER
+                .as_ref()
+                .unwrap()
+                .register(base, definition.current_length)
+                .map_err(|error| {
+                    MemoryError::Generic(format!(
+                        "failed to register the memory with userfaultfd: {}",
+                        error
+                    ))
+                })?;
+        }
+        Ok(Self { memory, base })
+    }
+}
+
+impl Drop for LazyMemory {
+    fn drop(&mut self) {
+        // The region must be unregistered before the pages are unmapped,
+        // and their address possibly reused.
+        HANDLER.as_ref().unwrap().unregister(self.base);
+    }
+}
+
+impl Memory for LazyMemory {
+    fn ty(&self) -> MemoryType {
+        self.memory.ty()
+    }
+
+    fn style(&self) -> &MemoryStyle {
+        self.memory.style()
+    }
+
+    fn size(&self) -> Pages {
+        self.memory.size()
+    }
+
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        self.memory.grow(delta)
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.memory.vmmemory()
+    }
+
+    /// Records `data` to populate the pages it covers on first access.
+    ///
+    /// The data is copied eagerly when one of these pages was accessed
+    /// already.
+    unsafe fn initialize_with_data(&self, start: usize, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        {
+            let page_size = region::page::size();
+            let mut regions = HANDLER.as_ref().unwrap().regions.lock().unwrap();
+            if let Some(region) = regions.get_mut(&self.base) {
+                let end = start + data.len();
+                if end <= region.len
+                    && !region.populated[start / page_size..=(end - 1) / page_size]
+                        .iter()
+                        .any(|populated| *populated)
+                {
+                    region.segments.push((start, data.into()));
+                    return;
+                }
+            }
+        }
+        // The regions must be unlocked, as the copy may fault.
+        self.memory.initialize_with_data(start, data)
+    }
+
+    fn start_tracking(&self) -> Result<(), MemoryError> {
+        self.memory.start_tracking()
+    }
+
+    fn take_dirty_pages(&self) -> Result<Vec<Pages>, MemoryError> {
+        self.memory.take_dirty_pages()
+    }
+
+    fn seal(&self) -> Result<(), MemoryError> {
+        self.memory.seal()
+    }
+
+    fn is_sealed(&self) -> bool {
+        self.memory.is_sealed()
+    }
+
+    fn commit(&self, start: usize, len: usize) -> Result<(), MemoryError> {
+        self.memory.commit(start, len)
+    }
+
+    fn resident_size(&self) -> Result<Bytes, MemoryError> {
+        self.memory.resident_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::slice;
+
+    #[test]
+    fn populates_pages_on_access() {
+        let style = MemoryStyle::Static {
+            bound: Pages(16),
+            offset_guard_size: 0,
+        };
+        if !LazyMemory::is_supported(&style) {
+            return;
+        }
+        let memory = LazyMemory::new(&MemoryType::new(2, Some(16), false), &style).unwrap();
+        unsafe {
+            memory.initialize_with_data(10, &[1, 2, 3]);
+            memory.initialize_with_data(11, &[4]);
+            memory.initialize_with_data(0x1_0000 - 1, &[5, 6]);
+            let definition = memory.vmmemory().as_ref();
+            let bytes = slice::from_raw_parts(definition.base, definition.current_length);
+            assert_eq!(&bytes[9..14], &[0, 1, 4, 3, 0]);
+            assert_eq!(&bytes[0x1_0000 - 1..0x1_0000 + 1], &[5, 6]);
+            assert!(bytes[0x1_8000..].iter().all(|byte| *byte == 0));
+
+            // The first page is populated, so this one is copied eagerly.
+            memory.initialize_with_data(12, &[7]);
+            assert_eq!(bytes[12], 7);
+        }
+        assert_eq!(memory.grow(Pages(1)).unwrap(), Pages(2));
+    }
+}
@@ -20,14 +20,23 @@
     )
 )]
 
+#[cfg(target_os = "linux")]
+mod dirty_pages;
 mod export;
 mod func_data_registry;
 mod global;
 mod imports;
 mod instance;
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod lazy_memory;
 mod memory;
 mod mmap;
+mod mpk;
 mod probestack;
+mod residency;
 mod sig_registry;
 mod table;
 mod trap;
@@ -43,8 +52,14 @@ pub use crate::instance::{
     ImportFunctionEnv, ImportInitializerFuncPtr, InstanceAllocator, InstanceHandle,
     WeakOrStrongInstanceRef,
 };
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub use crate::lazy_memory::LazyMemory;
 pub use crate::memory::{LinearMemory, Memory, MemoryError};
 pub use crate::mmap::Mmap;
+pub use crate::mpk::{protection_keys_in_use, ProtectedMemory, ProtectionDomain, ProtectionKeys};
 pub use crate::probestack::PROBESTACK;
 pub use crate::sig_registry::SignatureRegistry;
 pub use crate::table::{LinearTable, Table, TableElement};
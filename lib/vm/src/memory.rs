@@ -5,7 +5,10 @@
 //!
 //! `LinearMemory` is to WebAssembly linear memories what `Table` is to WebAssembly tables.
 
+#[cfg(target_os = "linux")]
+use crate::dirty_pages::DirtyPageTracker;
 use crate::mmap::Mmap;
+use crate::residency;
 use crate::vmcontext::VMMemoryDefinition;
 use loupe::MemoryUsage;
 use more_asserts::assert_ge;
@@ -13,7 +16,8 @@ use std::borrow::BorrowMut;
 use std::cell::UnsafeCell;
 use std::convert::TryInto;
 use std::fmt;
-use std::ptr::NonNull;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 use thiserror::Error;
 use wasmer_types::{Bytes, MemoryStyle, MemoryType, Pages};
@@ -55,6 +59,9 @@ pub enum MemoryError {
         /// The number of pages requested as the maximum amount of memory.
         max_allowed: Pages,
     },
+    /// The memory is sealed, so it can't grow anymore.
+    #[error("The memory is sealed")]
+    Sealed,
     /// A user defined error value, used for error cases not listed above.
     #[error("A user-defined error occurred: {0}")]
     Generic(String),
@@ -78,6 +85,71 @@ pub trait Memory: fmt::Debug + Send + Sync + MemoryUsage {
     ///
     /// The pointer returned in [`VMMemoryDefinition`] must be valid for the lifetime of this memory.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition>;
+
+    /// Copies the data segment `data` to the byte offset `start` of the
+    /// memory, while the instance using it is being initialized.
+    ///
+    /// Implementations may defer the copy until the pages are first
+    /// accessed; the default implementation copies eagerly.
+    ///
+    /// # Safety
+    /// - `start + data.len()` must not exceed the current length of the memory.
+    unsafe fn initialize_with_data(&self, start: usize, data: &[u8]) {
+        let definition = self.vmmemory();
+        let base = definition.as_ref().base;
+        ptr::copy_nonoverlapping(data.as_ptr(), base.add(start), data.len());
+    }
+
+    /// Returns the protection key tagging the pages of this memory, if any.
+    fn protection_key(&self) -> Option<u32> {
+        None
+    }
+
+    /// Starts recording which wasm pages of the memory are written to,
+    /// forgetting the pages recorded so far.
+    fn start_tracking(&self) -> Result<(), MemoryError> {
+        Err(MemoryError::Generic(
+            "dirty page tracking is not supported for this memory".to_string(),
+        ))
+    }
+
+    /// Returns the indices of the wasm pages written to since the last
+    /// call, or since the tracking started, in ascending order.
+    fn take_dirty_pages(&self) -> Result<Vec<Pages>, MemoryError> {
+        Err(MemoryError::Generic(
+            "dirty page tracking is not supported for this memory".to_string(),
+        ))
+    }
+
+    /// Prevents the memory from growing from now on, whether from
+    /// WebAssembly or from the host.
+    fn seal(&self) -> Result<(), MemoryError> {
+        Err(MemoryError::Generic(
+            "sealing is not supported for this memory".to_string(),
+        ))
+    }
+
+    /// Returns whether the memory is sealed.
+    fn is_sealed(&self) -> bool {
+        false
+    }
+
+    /// Faults in the pages of the `len` bytes at `start` in the memory,
+    /// without changing their contents.
+    fn commit(&self, start: usize, len: usize) -> Result<(), MemoryError> {
+        let _ = (start, len);
+        Err(MemoryError::Generic(
+            "committing pages is not supported for this memory".to_string(),
+        ))
+    }
+
+    /// Returns how many bytes of the memory are resident in physical
+    /// memory.
+    fn resident_size(&self) -> Result<Bytes, MemoryError> {
+        Err(MemoryError::Generic(
+            "measuring the resident pages is not supported for this memory".to_string(),
+        ))
+    }
 }
 
 /// A linear memory instance.
@@ -105,6 +177,16 @@ pub struct LinearMemory {
     // Records whether we're using a bounds-checking strategy which requires
     // handlers to catch trapping accesses.
     pub(crate) needs_signal_handlers: bool,
+
+    // Records the pages written to, once tracking started.
+    #[cfg(target_os = "linux")]
+    #[loupe(skip)]
+    dirty_pages: Mutex<Option<DirtyPageTracker>>,
+
+    // Whether the memory can't grow anymore. It's only set while `mmap`
+    // is locked.
+    #[loupe(skip)]
+    sealed: AtomicBool,
 }
 
 /// A type to help manage who is responsible for the backing memory of them
@@ -248,6 +330,9 @@ impl LinearMemory {
             },
             memory: *memory,
             style: style.clone(),
+            #[cfg(target_os = "linux")]
+            dirty_pages: Mutex::new(None),
+            sealed: AtomicBool::new(false),
         })
     }
 
@@ -302,6 +387,9 @@ impl Memory for LinearMemory {
         if delta.0 == 0 {
             return Ok(mmap.size);
         }
+        if self.sealed.load(Ordering::SeqCst) {
+            return Err(MemoryError::Sealed);
+        }
 
         let new_pages = mmap
             .size
@@ -370,6 +458,12 @@ impl Memory for LinearMemory {
             let md = md_ptr.as_mut();
             md.current_length = new_pages.bytes().0.try_into().unwrap();
             md.base = mmap.alloc.as_mut_ptr() as _;
+            #[cfg(target_os = "linux")]
+            {
+                if let Some(tracker) = &*self.dirty_pages.lock().unwrap() {
+                    tracker.resized(md.base as usize, md.current_length);
+                }
+            }
         }
 
         Ok(prev_pages)
@@ -380,4 +474,70 @@ impl Memory for LinearMemory {
         let _mmap_guard = self.mmap.lock().unwrap();
         unsafe { self.get_vm_memory_definition() }
     }
+
+    /// Tracks the pages with the soft-dirty bits of Linux.
+    ///
+    /// These bits are process wide: pages written to while the dirty
+    /// pages of any memory are being taken may be missed.
+    #[cfg(target_os = "linux")]
+    fn start_tracking(&self) -> Result<(), MemoryError> {
+        let _mmap_guard = self.mmap.lock().unwrap();
+        let definition = unsafe { *self.get_vm_memory_definition().as_ref() };
+        let tracker = DirtyPageTracker::start(definition.base as usize, definition.current_length)
+            .map_err(|error| {
+                MemoryError::Generic(format!("failed to track the dirty pages: {}", error))
+            })?;
+        *self.dirty_pages.lock().unwrap() = Some(tracker);
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn take_dirty_pages(&self) -> Result<Vec<Pages>, MemoryError> {
+        match &*self.dirty_pages.lock().unwrap() {
+            Some(tracker) => tracker.take().map_err(|error| {
+                MemoryError::Generic(format!("failed to take the dirty pages: {}", error))
+            }),
+            None => Err(MemoryError::Generic(
+                "the dirty pages of the memory are not tracked".to_string(),
+            )),
+        }
+    }
+
+    fn seal(&self) -> Result<(), MemoryError> {
+        // Once the memory is locked, no growth is in progress.
+        let _mmap_guard = self.mmap.lock().unwrap();
+        self.sealed.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn is_sealed(&self) -> bool {
+        self.sealed.load(Ordering::SeqCst)
+    }
+
+    fn commit(&self, start: usize, len: usize) -> Result<(), MemoryError> {
+        // The memory can't move while it's locked.
+        let _mmap_guard = self.mmap.lock().unwrap();
+        let definition = unsafe { *self.get_vm_memory_definition().as_ref() };
+        if start
+            .checked_add(len)
+            .map_or(true, |end| end > definition.current_length)
+        {
+            return Err(MemoryError::Generic(format!(
+                "the range of {} bytes at {} is out of bounds of the memory",
+                len, start
+            )));
+        }
+        residency::commit(definition.base as usize + start, len)
+            .map_err(|error| MemoryError::Generic(format!("failed to commit the pages: {}", error)))
+    }
+
+    fn resident_size(&self) -> Result<Bytes, MemoryError> {
+        let _mmap_guard = self.mmap.lock().unwrap();
+        let definition = unsafe { *self.get_vm_memory_definition().as_ref() };
+        residency::resident_size(definition.base as usize, definition.current_length)
+            .map(Bytes)
+            .map_err(|error| {
+                MemoryError::Generic(format!("failed to measure the resident pages: {}", error))
+            })
+    }
 }
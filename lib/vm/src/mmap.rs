@@ -228,6 +228,20 @@ impl Mmap {
         Ok(())
     }
 
+    /// Takes ownership of `len` bytes of memory mapped at `ptr` by the
+    /// caller, for instance from a file, to be unmapped on drop.
+    ///
+    /// # Safety
+    /// `ptr` and `len` must describe a page-aligned mapping created with
+    /// `mmap` that isn't owned by anything else.
+    #[cfg(not(target_os = "windows"))]
+    pub unsafe fn from_raw_parts(ptr: *mut u8, len: usize) -> Self {
+        Self {
+            ptr: ptr as usize,
+            len,
+        }
+    }
+
     /// Return the allocated memory as a slice of u8.
     pub fn as_slice(&self) -> &[u8] {
         unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
@@ -0,0 +1,371 @@
+//! Isolation of the memories of co-located instances with memory
+//! protection keys, on x86_64 CPUs supporting PKU.
+//!
+//! A [`ProtectedMemory`] tags all its pages with a protection key, taken
+//! from a pool allocated the first time protection keys are used. While a
+//! thread runs wasm code in a [`ProtectionDomain`], its PKRU register
+//! denies access to the pages tagged with the keys of the other instances,
+//! so even miscompiled code cannot reach their memories.
+//!
+//! The hardware only has 15 keys to hand out: beyond that many memories,
+//! keys are shared and the memories sharing a key are not isolated from
+//! each other.
+
+use crate::memory::{Memory, MemoryError};
+use crate::vmcontext::VMMemoryDefinition;
+use lazy_static::lazy_static;
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use wasmer_types::{Bytes, MemoryStyle, MemoryType, Pages};
+
+cfg_if::cfg_if! {
+    if #[cfg(all(target_os = "linux", target_arch = "x86_64"))] {
+        mod sys {
+            use std::arch::asm;
+            use std::arch::x86_64::{__cpuid_count, __get_cpuid_max};
+
+            /// Whether the CPU supports protection keys and the OS enabled them.
+            pub fn has_pku() -> bool {
+                // CPUID.(EAX=07H, ECX=0H):ECX.OSPKE [bit 4]
+                unsafe { __get_cpuid_max(0).0 >= 7 && __cpuid_count(7, 0).ecx & (1 << 4) != 0 }
+            }
+
+            pub fn pkey_alloc() -> Option<u32> {
+                let key = unsafe { libc::syscall(libc::SYS_pkey_alloc, 0, 0) };
+                if key < 0 {
+                    None
+                } else {
+                    Some(key as u32)
+                }
+            }
+
+            pub unsafe fn pkey_mprotect(
+                start: *mut u8,
+                len: usize,
+                writable: bool,
+                key: u32,
+            ) -> Result<(), String> {
+                let prot = if writable {
+                    libc::PROT_READ | libc::PROT_WRITE
+                } else {
+                    libc::PROT_NONE
+                };
+                if libc::syscall(libc::SYS_pkey_mprotect, start, len, prot, key) < 0 {
+                    Err(std::io::Error::last_os_error().to_string())
+                } else {
+                    Ok(())
+                }
+            }
+
+            pub fn read_pkru() -> u32 {
+                let pkru: u32;
+                unsafe {
+                    asm!(
+                        "rdpkru",
+                        in("ecx") 0,
+                        out("eax") pkru,
+                        out("edx") _,
+                        options(nomem, nostack, preserves_flags),
+                    );
+                }
+                pkru
+            }
+
+            pub fn write_pkru(pkru: u32) {
+                unsafe {
+                    asm!(
+                        "wrpkru",
+                        in("eax") pkru,
+                        in("ecx") 0,
+                        in("edx") 0,
+                        options(nostack, preserves_flags),
+                    );
+                }
+            }
+        }
+    } else {
+        mod sys {
+            pub fn has_pku() -> bool {
+                false
+            }
+
+            pub fn pkey_alloc() -> Option<u32> {
+                None
+            }
+
+            pub unsafe fn pkey_mprotect(
+                _start: *mut u8,
+                _len: usize,
+                _writable: bool,
+                _key: u32,
+            ) -> Result<(), String> {
+                unreachable!("protection keys are not supported on this platform")
+            }
+
+            pub fn read_pkru() -> u32 {
+                unreachable!("protection keys are not supported on this platform")
+            }
+
+            pub fn write_pkru(_pkru: u32) {
+                unreachable!("protection keys are not supported on this platform")
+            }
+        }
+    }
+}
+
+/// A set of protection keys.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, MemoryUsage)]
+pub struct ProtectionKeys(u32);
+
+impl ProtectionKeys {
+    /// Adds `key` to the set.
+    pub fn insert(&mut self, key: u32) {
+        self.0 |= 1 << key;
+    }
+
+    /// Whether the set contains `key`.
+    pub fn contains(&self, key: u32) -> bool {
+        self.0 & (1 << key) != 0
+    }
+
+    /// Whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// The PKRU bits denying all access to the pages tagged with these keys.
+    fn pkru_bits(&self) -> u32 {
+        (0..16)
+            .filter(|key| self.contains(*key))
+            .fold(0, |bits, key| bits | (0b11 << (2 * key)))
+    }
+}
+
+lazy_static! {
+    /// The keys allocated for the memories. The default key, 0, is never
+    /// part of it.
+    static ref POOL: Vec<u32> = {
+        let mut keys = Vec::new();
+        if sys::has_pku() {
+            while let Some(key) = sys::pkey_alloc() {
+                keys.push(key);
+            }
+        }
+        keys
+    };
+
+    static ref POOL_KEYS: ProtectionKeys = {
+        let mut set = ProtectionKeys::default();
+        for key in POOL.iter() {
+            set.insert(*key);
+        }
+        set
+    };
+}
+
+/// Whether a memory was tagged with a protection key, so calls into wasm
+/// need to switch PKRU.
+static IN_USE: AtomicBool = AtomicBool::new(false);
+
+/// The index of the next key of the pool to give to a memory.
+static NEXT_KEY: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// How many protection domains the thread is nested in.
+    static DEPTH: Cell<usize> = Cell::new(0);
+}
+
+/// Returns whether memories are tagged with protection keys in this
+/// process.
+pub fn protection_keys_in_use() -> bool {
+    IN_USE.load(Ordering::Relaxed)
+}
+
+/// A memory whose pages are tagged with a protection key.
+///
+/// Only static memories can be protected, as dynamic ones may move to an
+/// untagged mapping when they grow.
+#[derive(Debug)]
+pub struct ProtectedMemory {
+    memory: Box<dyn Memory>,
+    key: u32,
+}
+
+impl ProtectedMemory {
+    /// Whether memories of the given style can be protected on this host.
+    pub fn is_supported(style: &MemoryStyle) -> bool {
+        matches!(style, MemoryStyle::Static { .. }) && !POOL.is_empty()
+    }
+
+    /// Tags all the pages of `memory`, including its guard pages, with a
+    /// protection key.
+    pub fn new(memory: Box<dyn Memory>) -> Result<Self, MemoryError> {
+        let style = memory.style().clone();
+        let mapped_bytes = match style {
+            MemoryStyle::Static {
+                bound,
+                offset_guard_size,
+            } if !POOL.is_empty() => bound.bytes().0 + offset_guard_size as usize,
+            _ => {
+                return Err(MemoryError::Generic(format!(
+                    "protection keys are not supported for {:?} on this host",
+                    style
+                )))
+            }
+        };
+        let key = POOL[NEXT_KEY.fetch_add(1, Ordering::Relaxed) % POOL.len()];
+
+        unsafe {
+            let definition = *memory.vmmemory().as_ref();
+            let accessible = definition.current_length;
+            let tag = |start: usize, len: usize, writable: bool| {
+                if len == 0 {
+                    return Ok(());
+                }
+                sys::pkey_mprotect(definition.base.add(start), len, writable, key).map_err(
+                    |error| {
+                        MemoryError::Generic(format!(
+                            "failed to tag the memory with a protection key: {}",
+                            error
+                        ))
+                    },
+                )
+            };
+            tag(0, accessible, true)?;
+            tag(accessible, mapped_bytes - accessible, false)?;
+        }
+
+        // The host keeps access to the memory.
+        let mut keys = ProtectionKeys::default();
+        keys.insert(key);
+        sys::write_pkru(sys::read_pkru() & !keys.pkru_bits());
+        IN_USE.store(true, Ordering::Relaxed);
+
+        Ok(Self { memory, key })
+    }
+}
+
+impl MemoryUsage for ProtectedMemory {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + MemoryUsage::size_of_val(&*self.memory, tracker)
+    }
+}
+
+impl Memory for ProtectedMemory {
+    fn ty(&self) -> MemoryType {
+        self.memory.ty()
+    }
+
+    fn style(&self) -> &MemoryStyle {
+        self.memory.style()
+    }
+
+    fn size(&self) -> Pages {
+        self.memory.size()
+    }
+
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        // Growing changes the protection of the pages but keeps their key.
+        self.memory.grow(delta)
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.memory.vmmemory()
+    }
+
+    unsafe fn initialize_with_data(&self, start: usize, data: &[u8]) {
+        self.memory.initialize_with_data(start, data)
+    }
+
+    fn protection_key(&self) -> Option<u32> {
+        Some(self.key)
+    }
+
+    fn start_tracking(&self) -> Result<(), MemoryError> {
+        self.memory.start_tracking()
+    }
+
+    fn take_dirty_pages(&self) -> Result<Vec<Pages>, MemoryError> {
+        self.memory.take_dirty_pages()
+    }
+
+    fn seal(&self) -> Result<(), MemoryError> {
+        self.memory.seal()
+    }
+
+    fn is_sealed(&self) -> bool {
+        self.memory.is_sealed()
+    }
+
+    fn commit(&self, start: usize, len: usize) -> Result<(), MemoryError> {
+        self.memory.commit(start, len)
+    }
+
+    fn resident_size(&self) -> Result<Bytes, MemoryError> {
+        self.memory.resident_size()
+    }
+}
+
+/// Restricts the memory accesses of the current thread to the default
+/// protection key and the keys of one instance, until dropped.
+///
+/// When the outermost domain of a thread is left, the host is given back
+/// access to all the protected memories.
+#[derive(Debug)]
+pub struct ProtectionDomain {
+    previous: u32,
+    // The domain must be left on the thread which entered it.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl ProtectionDomain {
+    /// Enters the domain allowing `keys`. Returns `None` when no memory
+    /// is protected.
+    pub fn enter(keys: ProtectionKeys) -> Option<Self> {
+        if !protection_keys_in_use() {
+            return None;
+        }
+        let previous = sys::read_pkru();
+        let denied = POOL_KEYS.pkru_bits() & !keys.pkru_bits();
+        sys::write_pkru((previous | denied) & !keys.pkru_bits());
+        DEPTH.with(|depth| depth.set(depth.get() + 1));
+        Some(Self {
+            previous,
+            _not_send: PhantomData,
+        })
+    }
+}
+
+impl Drop for ProtectionDomain {
+    fn drop(&mut self) {
+        let outermost = DEPTH.with(|depth| {
+            depth.set(depth.get() - 1);
+            depth.get() == 0
+        });
+        if outermost {
+            sys::write_pkru(self.previous & !POOL_KEYS.pkru_bits());
+        } else {
+            sys::write_pkru(self.previous);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protection_keys_set() {
+        let mut keys = ProtectionKeys::default();
+        assert!(keys.is_empty());
+        keys.insert(1);
+        keys.insert(3);
+        assert!(keys.contains(1));
+        assert!(!keys.contains(2));
+        assert_eq!(keys.pkru_bits(), 0b1100_1100);
+    }
+}
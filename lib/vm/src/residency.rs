@@ -0,0 +1,100 @@
+//! Committing the pages of linear memories ahead of their use, and
+//! measuring how much of them is resident.
+//!
+//! Committing writes to every page of a range without changing it, so
+//! that the kernel backs the pages with physical memory now instead of
+//! when the program first touches them. Linux 5.14 and later do it at
+//! once with `MADV_POPULATE_WRITE`.
+
+use std::io;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The `madvise` advice faulting pages in as if written to, from
+/// `linux/mman.h`.
+#[cfg(target_os = "linux")]
+const MADV_POPULATE_WRITE: libc::c_int = 23;
+
+/// Returns the bounds of the pages overlapping `start..start + len`.
+fn page_bounds(start: usize, len: usize) -> (usize, usize) {
+    let page_size = region::page::size();
+    let first = start & !(page_size - 1);
+    let end = (start + len + page_size - 1) & !(page_size - 1);
+    (first, end)
+}
+
+/// Faults in the pages overlapping `start..start + len`, which must be
+/// accessible.
+///
+/// The memory may be in use on other threads: its contents are
+/// unchanged.
+pub(crate) fn commit(start: usize, len: usize) -> io::Result<()> {
+    if len == 0 {
+        return Ok(());
+    }
+    let (first, end) = page_bounds(start, len);
+    #[cfg(target_os = "linux")]
+    {
+        let advised = unsafe { libc::madvise(first as *mut _, end - first, MADV_POPULATE_WRITE) };
+        if advised == 0 {
+            return Ok(());
+        }
+        let error = io::Error::last_os_error();
+        // Older kernels don't know the advice.
+        if error.raw_os_error() != Some(libc::EINVAL) {
+            return Err(error);
+        }
+    }
+    // An atomic addition of zero writes to the page without racing with
+    // the writes of other threads.
+    for page in (first..end).step_by(region::page::size()) {
+        unsafe { (*(page as *const AtomicU8)).fetch_add(0, Ordering::Relaxed) };
+    }
+    Ok(())
+}
+
+/// Returns how many bytes of the pages overlapping `start..start + len`
+/// are resident in physical memory.
+#[cfg(unix)]
+pub(crate) fn resident_size(start: usize, len: usize) -> io::Result<usize> {
+    if len == 0 {
+        return Ok(0);
+    }
+    let page_size = region::page::size();
+    let (first, end) = page_bounds(start, len);
+    let mut residency = vec![0u8; (end - first) / page_size];
+    if unsafe { libc::mincore(first as *mut _, end - first, residency.as_mut_ptr() as _) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let resident_pages = residency.iter().filter(|page| **page & 1 != 0).count();
+    Ok(resident_pages * page_size)
+}
+
+/// Returns how many bytes of the pages overlapping `start..start + len`
+/// are resident in physical memory.
+#[cfg(not(unix))]
+pub(crate) fn resident_size(start: usize, len: usize) -> io::Result<usize> {
+    let _ = (start, len);
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "measuring the resident pages isn't supported on this platform",
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::mmap::Mmap;
+
+    #[test]
+    fn committed_pages_are_resident() {
+        let page_size = region::page::size();
+        let mut mmap = Mmap::accessible_reserved(8 * page_size, 8 * page_size).unwrap();
+        let base = mmap.as_mut_ptr() as usize;
+        unsafe { *(base as *mut u8).add(3 * page_size + 1) = 7 };
+        assert_eq!(resident_size(base, 8 * page_size).unwrap(), page_size);
+
+        commit(base + page_size + 1, 2 * page_size).unwrap();
+        assert_eq!(resident_size(base, 8 * page_size).unwrap(), 3 * page_size);
+        assert_eq!(mmap.as_slice()[3 * page_size + 1], 7);
+    }
+}
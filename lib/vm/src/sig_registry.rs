@@ -6,12 +6,19 @@
 
 use crate::vmcontext::VMSharedSignatureIndex;
 use loupe::MemoryUsage;
-use more_asserts::{assert_lt, debug_assert_lt};
+use more_asserts::assert_lt;
 use std::collections::{hash_map, HashMap};
-use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::RwLock;
 use wasmer_types::FunctionType;
 
+/// The next index to give to a signature, shared by all the registries of
+/// the process so that the indices of different engines never collide: a
+/// function leaking from one engine into the tables of another fails the
+/// signature check of `call_indirect` rather than being called with the
+/// wrong signature.
+static NEXT_INDEX: AtomicU32 = AtomicU32::new(0);
+
 /// WebAssembly requires that the caller and callee signatures in an indirect
 /// call must match. To implement this efficiently, keep a registry of all
 /// signatures, shared by all instances, so that call sites can just do an
@@ -29,7 +36,14 @@ pub struct SignatureRegistry {
 #[derive(Debug, Default, MemoryUsage)]
 struct Inner {
     signature2index: HashMap<FunctionType, VMSharedSignatureIndex>,
-    index2signature: HashMap<VMSharedSignatureIndex, FunctionType>,
+    index2signature: HashMap<VMSharedSignatureIndex, Entry>,
+}
+
+#[derive(Debug, MemoryUsage)]
+struct Entry {
+    signature: FunctionType,
+    // How many times the signature was registered and not unregistered.
+    references: usize,
 }
 
 impl SignatureRegistry {
@@ -41,27 +55,63 @@ impl SignatureRegistry {
     }
 
     /// Register a signature and return its unique index.
+    ///
+    /// Each call takes a reference to the signature, to be released with
+    /// [`SignatureRegistry::unregister`] once the index is not used anymore.
     pub fn register(&self, sig: &FunctionType) -> VMSharedSignatureIndex {
         let mut inner = self.inner.write().unwrap();
-        let len = inner.signature2index.len();
-        match inner.signature2index.entry(sig.clone()) {
-            hash_map::Entry::Occupied(entry) => *entry.get(),
+        let Inner {
+            signature2index,
+            index2signature,
+        } = &mut *inner;
+        match signature2index.entry(sig.clone()) {
+            hash_map::Entry::Occupied(entry) => {
+                let sig_id = *entry.get();
+                index2signature.get_mut(&sig_id).unwrap().references += 1;
+                sig_id
+            }
             hash_map::Entry::Vacant(entry) => {
-                // Keep `signature_hash` len under 2**32 -- VMSharedSignatureIndex::new(std::u32::MAX)
+                // Indices are never reused, so that an index outliving its
+                // signature can't match the one of another signature.
+                let next_index = NEXT_INDEX.fetch_add(1, Ordering::Relaxed);
+                // Keep indices under 2**32 -- VMSharedSignatureIndex::new(std::u32::MAX)
                 // is reserved for VMSharedSignatureIndex::default().
-                debug_assert_lt!(
-                    len,
-                    std::u32::MAX as usize,
-                    "Invariant check: signature_hash.len() < std::u32::MAX"
+                assert_lt!(
+                    next_index,
+                    std::u32::MAX,
+                    "Invariant check: next_index < std::u32::MAX"
                 );
-                let sig_id = VMSharedSignatureIndex::new(u32::try_from(len).unwrap());
+                let sig_id = VMSharedSignatureIndex::new(next_index);
                 entry.insert(sig_id);
-                inner.index2signature.insert(sig_id, sig.clone());
+                index2signature.insert(
+                    sig_id,
+                    Entry {
+                        signature: sig.clone(),
+                        references: 1,
+                    },
+                );
                 sig_id
             }
         }
     }
 
+    /// Releases a reference taken by [`SignatureRegistry::register`].
+    ///
+    /// The signature is removed from the registry once no reference is
+    /// left. Its index is not reused.
+    pub fn unregister(&self, idx: VMSharedSignatureIndex) {
+        let mut inner = self.inner.write().unwrap();
+        let entry = match inner.index2signature.get_mut(&idx) {
+            Some(entry) => entry,
+            None => return,
+        };
+        entry.references -= 1;
+        if entry.references == 0 {
+            let entry = inner.index2signature.remove(&idx).unwrap();
+            inner.signature2index.remove(&entry.signature);
+        }
+    }
+
     /// Looks up a shared signature index within this registry.
     ///
     /// Note that for this operation to be semantically correct the `idx` must
@@ -72,6 +122,82 @@ impl SignatureRegistry {
             .unwrap()
             .index2signature
             .get(&idx)
-            .cloned()
+            .map(|entry| entry.signature.clone())
+    }
+
+    /// Returns the number of references taken to a signature, 0 if it's
+    /// not registered.
+    pub fn references(&self, idx: VMSharedSignatureIndex) -> usize {
+        self.inner
+            .read()
+            .unwrap()
+            .index2signature
+            .get(&idx)
+            .map_or(0, |entry| entry.references)
+    }
+
+    /// Returns the number of signatures in the registry.
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().index2signature.len()
+    }
+
+    /// Returns whether the registry is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the registered signatures and their index, ordered by index.
+    pub fn signatures(&self) -> Vec<(VMSharedSignatureIndex, FunctionType)> {
+        let mut signatures: Vec<_> = self
+            .inner
+            .read()
+            .unwrap()
+            .index2signature
+            .iter()
+            .map(|(idx, entry)| (*idx, entry.signature.clone()))
+            .collect();
+        signatures.sort_by_key(|(idx, _)| idx.as_u32());
+        signatures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer_types::Type;
+
+    #[test]
+    fn removes_unreferenced_signatures() {
+        let registry = SignatureRegistry::new();
+        let unary = FunctionType::new(vec![Type::I32], vec![]);
+        let binary = FunctionType::new(vec![Type::I32, Type::I32], vec![]);
+
+        let first = registry.register(&unary);
+        assert_eq!(registry.register(&unary), first);
+        let second = registry.register(&binary);
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.references(first), 2);
+
+        registry.unregister(first);
+        assert_eq!(registry.lookup(first), Some(unary.clone()));
+        registry.unregister(first);
+        assert_eq!(registry.lookup(first), None);
+        assert_eq!(registry.signatures(), vec![(second, binary)]);
+
+        // Indices of removed signatures are not reused.
+        assert_ne!(registry.register(&unary), first);
+    }
+
+    #[test]
+    fn indices_are_unique_across_registries() {
+        let first = SignatureRegistry::new();
+        let second = SignatureRegistry::new();
+        let unary = FunctionType::new(vec![Type::I32], vec![]);
+        let binary = FunctionType::new(vec![Type::I32, Type::I32], vec![]);
+
+        let index = first.register(&unary);
+        assert_ne!(second.register(&binary), index);
+        assert_ne!(second.register(&unary), index);
+        assert_eq!(second.lookup(index), None);
     }
 }
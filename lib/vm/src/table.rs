@@ -15,7 +15,8 @@ use std::cell::UnsafeCell;
 use std::convert::TryFrom;
 use std::fmt;
 use std::ptr::NonNull;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
 use wasmer_types::{ExternRef, TableStyle, TableType, TrapCode, Type as ValType};
 
 /// Trait for implementing the interface of a Wasm table.
@@ -50,6 +51,19 @@ pub trait Table: fmt::Debug + Send + Sync + MemoryUsage {
     /// Return a `VMTableDefinition` for exposing the table to compiled wasm code.
     fn vmtable(&self) -> NonNull<VMTableDefinition>;
 
+    /// Prevents the table from growing from now on, whether from
+    /// WebAssembly or from the host.
+    ///
+    /// Returns `false` if sealing is not supported for this table.
+    fn seal(&self) -> bool {
+        false
+    }
+
+    /// Returns whether the table is sealed.
+    fn is_sealed(&self) -> bool {
+        false
+    }
+
     /// Copy `len` elements from `src_table[src_index..]` into `dst_table[dst_index..]`.
     ///
     /// # Errors
@@ -171,6 +185,10 @@ pub struct LinearTable {
     /// Our chosen implementation style.
     style: TableStyle,
     vm_table_definition: VMTableDefinitionOwnership,
+    /// Whether the table can't grow anymore. It's only set while `vec` is
+    /// locked.
+    #[loupe(skip)]
+    sealed: AtomicBool,
 }
 
 /// A type to help manage who is responsible for the backing table of the
@@ -191,7 +209,22 @@ unsafe impl Send for LinearTable {}
 /// This is correct because all internal mutability is protected by a mutex.
 unsafe impl Sync for LinearTable {}
 
+impl Drop for LinearTable {
+    fn drop(&mut self) {
+        // The table owns a reference to each of its `externref`s.
+        if self.table.ty == ValType::ExternRef {
+            let vec = self.vec.get_mut().unwrap_or_else(PoisonError::into_inner);
+            for element in vec.iter_mut() {
+                unsafe { element.extern_ref.ref_drop() }
+            }
+        }
+    }
+}
+
 impl LinearTable {
+    /// The bytes taken by each element of a table.
+    pub const ELEMENT_SIZE: usize = std::mem::size_of::<RawTableElement>();
+
     /// Create a new linear table instance with specified minimum and maximum number of elements.
     ///
     /// This creates a `LinearTable` with metadata owned by a VM, pointed to by
@@ -264,6 +297,7 @@ impl LinearTable {
                         },
                     )))
                 },
+                sealed: AtomicBool::new(false),
             }),
         }
     }
@@ -320,6 +354,9 @@ impl Table for LinearTable {
             debug_assert_eq!(delta, 0);
             return Some(size);
         }
+        if self.sealed.load(Ordering::SeqCst) {
+            return None;
+        }
 
         // Update the ref count
         let element = match init_value {
@@ -406,4 +443,15 @@ impl Table for LinearTable {
         let _vec_guard = self.vec.lock().unwrap();
         unsafe { self.get_vm_table_definition() }
     }
+
+    fn seal(&self) -> bool {
+        // Once the table is locked, no growth is in progress.
+        let _vec_guard = self.vec.lock().unwrap();
+        self.sealed.store(true, Ordering::SeqCst);
+        true
+    }
+
+    fn is_sealed(&self) -> bool {
+        self.sealed.load(Ordering::SeqCst)
+    }
 }
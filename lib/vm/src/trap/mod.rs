@@ -3,13 +3,20 @@
 
 //! This is the module that facilitates the usage of Traps
 //! in Wasmer Runtime
+mod sampling;
+mod step;
 mod trap;
 mod traphandlers;
 
+pub use sampling::{start_sampling, stop_sampling, SampleHandler};
+pub use step::{set_breakpoint, set_step_handler, StepContext, StepHandler};
 pub use trap::Trap;
 pub use traphandlers::{
-    catch_traps, on_host_stack, raise_lib_trap, raise_user_trap, wasmer_call_trampoline,
+    catch_traps, host_function_stack_base, lazy_per_thread_init, on_host_stack, raise_lib_trap,
+    raise_user_trap, wasmer_call_trampoline, wasmer_call_trampoline_on_stack, CallStack,
     TrapHandler, TrapHandlerFn,
 };
 pub use traphandlers::{init_traps, resume_panic};
+#[cfg(unix)]
+pub use traphandlers::{wasmer_call_trampoline_on_region, StackRegion};
 pub use wasmer_types::TrapCode;
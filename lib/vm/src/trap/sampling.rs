@@ -0,0 +1,126 @@
+//! Sampling of the program counter of the running threads.
+//!
+//! A profiling timer interrupts the threads of the process consuming CPU
+//! time at a regular interval, and the interrupted program counter is
+//! given to the sample handler registered by the engine.
+
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Handles the program counter of an interrupted thread. It runs in a
+/// signal handler, so it must not allocate or block.
+pub type SampleHandler = fn(usize);
+
+static SAMPLE_HANDLER: AtomicUsize = AtomicUsize::new(0);
+
+cfg_if::cfg_if! {
+    if #[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))] {
+        use std::mem;
+        use std::ptr;
+        use std::sync::Mutex;
+
+        extern "C" {
+            // Not bound by `libc` on Linux.
+            fn setitimer(
+                which: libc::c_int,
+                new_value: *const libc::itimerval,
+                old_value: *mut libc::itimerval,
+            ) -> libc::c_int;
+        }
+
+        lazy_static::lazy_static! {
+            /// The action of `SIGPROF` before sampling started.
+            static ref PREV_SIGPROF: Mutex<Option<libc::sigaction>> = Mutex::new(None);
+        }
+
+        /// Starts interrupting the threads consuming CPU time every
+        /// `interval` of CPU time, giving their program counter to
+        /// `handler`.
+        pub fn start_sampling(interval: Duration, handler: SampleHandler) -> io::Result<()> {
+            let mut prev_sigprof = PREV_SIGPROF.lock().unwrap_or_else(|e| e.into_inner());
+            SAMPLE_HANDLER.store(handler as usize, Ordering::SeqCst);
+            if prev_sigprof.is_none() {
+                unsafe {
+                    let mut action: libc::sigaction = mem::zeroed();
+                    action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART | libc::SA_ONSTACK;
+                    action.sa_sigaction = sample_signal_handler as usize;
+                    libc::sigemptyset(&mut action.sa_mask);
+                    let mut prev: libc::sigaction = mem::zeroed();
+                    if libc::sigaction(libc::SIGPROF, &action, &mut prev) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    *prev_sigprof = Some(prev);
+                }
+            }
+            set_timer(interval)
+        }
+
+        /// Stops sampling, and restores the previous action of the
+        /// profiling signal.
+        pub fn stop_sampling() -> io::Result<()> {
+            let mut prev_sigprof = PREV_SIGPROF.lock().unwrap_or_else(|e| e.into_inner());
+            set_timer(Duration::from_secs(0))?;
+            SAMPLE_HANDLER.store(0, Ordering::SeqCst);
+            if let Some(prev) = prev_sigprof.take() {
+                if unsafe { libc::sigaction(libc::SIGPROF, &prev, ptr::null_mut()) } != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        }
+
+        fn set_timer(interval: Duration) -> io::Result<()> {
+            let interval = libc::timeval {
+                tv_sec: interval.as_secs() as libc::time_t,
+                tv_usec: interval.subsec_micros() as libc::suseconds_t,
+            };
+            let timer = libc::itimerval {
+                it_interval: interval,
+                it_value: interval,
+            };
+            if unsafe { setitimer(libc::ITIMER_PROF, &timer, ptr::null_mut()) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+
+        unsafe extern "C" fn sample_signal_handler(
+            _signum: libc::c_int,
+            _siginfo: *mut libc::siginfo_t,
+            context: *mut libc::c_void,
+        ) {
+            let handler = SAMPLE_HANDLER.load(Ordering::SeqCst);
+            if handler == 0 {
+                return;
+            }
+            let handler = mem::transmute::<usize, SampleHandler>(handler);
+            // The interrupted code may be about to read `errno`.
+            let errno = *libc::__errno_location();
+            let context = &*(context as *const libc::ucontext_t);
+            #[cfg(target_arch = "x86_64")]
+            handler(context.uc_mcontext.gregs[libc::REG_RIP as usize] as usize);
+            #[cfg(target_arch = "aarch64")]
+            handler(context.uc_mcontext.pc as usize);
+            *libc::__errno_location() = errno;
+        }
+    } else {
+        /// Starts interrupting the threads consuming CPU time every
+        /// `interval` of CPU time, giving their program counter to
+        /// `handler`.
+        ///
+        /// Sampling is only supported on Linux, on x86-64 and ARM64.
+        pub fn start_sampling(interval: Duration, handler: SampleHandler) -> io::Result<()> {
+            let _ = (interval, handler, &SAMPLE_HANDLER);
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "sampling isn't supported on this platform",
+            ))
+        }
+
+        /// Stops sampling.
+        pub fn stop_sampling() -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
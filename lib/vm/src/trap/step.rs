@@ -0,0 +1,198 @@
+//! Single-stepping support.
+//!
+//! Functions compiled for single-stepping execute a breakpoint after
+//! every WebAssembly instruction. The breakpoints are caught like traps
+//! and given to the step handler registered by the engine, then the
+//! execution resumes.
+//!
+//! Functions compiled with patch points reserve nops where breakpoints
+//! can be patched in at runtime, handled the same way.
+
+use std::io;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The state of a thread stopped at a breakpoint.
+pub struct StepContext<'a> {
+    pc: usize,
+    #[cfg(unix)]
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    context: &'a libc::ucontext_t,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a> StepContext<'a> {
+    /// The address of the breakpoint instruction.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Reads a general purpose register, given by its number in the
+    /// instruction encoding of the target.
+    pub fn register(&self, index: u16) -> Option<u64> {
+        cfg_if::cfg_if! {
+            if #[cfg(all(target_os = "linux", target_arch = "x86_64"))] {
+                const REGISTERS: [libc::c_int; 16] = [
+                    libc::REG_RAX,
+                    libc::REG_RCX,
+                    libc::REG_RDX,
+                    libc::REG_RBX,
+                    libc::REG_RSP,
+                    libc::REG_RBP,
+                    libc::REG_RSI,
+                    libc::REG_RDI,
+                    libc::REG_R8,
+                    libc::REG_R9,
+                    libc::REG_R10,
+                    libc::REG_R11,
+                    libc::REG_R12,
+                    libc::REG_R13,
+                    libc::REG_R14,
+                    libc::REG_R15,
+                ];
+                let register = *REGISTERS.get(index as usize)?;
+                Some(self.context.uc_mcontext.gregs[register as usize] as u64)
+            } else if #[cfg(all(target_os = "linux", target_arch = "aarch64"))] {
+                match index {
+                    0..=30 => Some(self.context.uc_mcontext.regs[index as usize]),
+                    31 => Some(self.context.uc_mcontext.sp),
+                    _ => None,
+                }
+            } else {
+                let _ = index;
+                None
+            }
+        }
+    }
+
+    /// Reads the low 64 bits of a floating point or vector register,
+    /// given by its number in the instruction encoding of the target.
+    pub fn simd_register(&self, index: u16) -> Option<u64> {
+        cfg_if::cfg_if! {
+            if #[cfg(all(target_os = "linux", target_arch = "x86_64"))] {
+                if self.context.uc_mcontext.fpregs.is_null() {
+                    return None;
+                }
+                let fpregs = unsafe { &*self.context.uc_mcontext.fpregs };
+                let element = fpregs._xmm.get(index as usize)?.element;
+                Some(element[0] as u64 | (element[1] as u64) << 32)
+            } else {
+                // The vector registers of ARM64 are saved in a variable
+                // part of the context, which isn't read yet.
+                let _ = index;
+                None
+            }
+        }
+    }
+}
+
+/// Handles a breakpoint, returning whether it was placed for
+/// single-stepping. Execution resumes after the breakpoint if it was.
+pub type StepHandler = fn(&StepContext) -> bool;
+
+static STEP_HANDLER: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets the function handling breakpoints, and starts catching them.
+///
+/// Breakpoints are only caught on Linux, on x86-64 and ARM64.
+pub fn set_step_handler(handler: StepHandler) {
+    STEP_HANDLER.store(handler as usize, Ordering::SeqCst);
+    super::traphandlers::init_step_signal();
+}
+
+/// Gives a breakpoint to the step handler, and moves past it if it was
+/// handled.
+#[cfg(unix)]
+pub(super) unsafe fn handle_step(context: &mut libc::ucontext_t) -> bool {
+    let handler = STEP_HANDLER.load(Ordering::SeqCst);
+    if handler == 0 {
+        return false;
+    }
+    let handler = std::mem::transmute::<usize, StepHandler>(handler);
+    let pc = match breakpoint_address(context) {
+        Some(pc) => pc,
+        None => return false,
+    };
+    let step = StepContext {
+        pc,
+        context,
+        _marker: PhantomData,
+    };
+    if !handler(&step) {
+        return false;
+    }
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    {
+        // `brk` reports its own address.
+        context.uc_mcontext.pc += 4;
+    }
+    true
+}
+
+/// The address of the breakpoint a thread stopped at.
+#[cfg(unix)]
+fn breakpoint_address(context: &libc::ucontext_t) -> Option<usize> {
+    cfg_if::cfg_if! {
+        if #[cfg(all(target_os = "linux", target_arch = "x86_64"))] {
+            // `int3` reports the address following it.
+            Some(context.uc_mcontext.gregs[libc::REG_RIP as usize] as usize - 1)
+        } else if #[cfg(all(target_os = "linux", target_arch = "aarch64"))] {
+            Some(context.uc_mcontext.pc as usize)
+        } else {
+            let _ = context;
+            None
+        }
+    }
+}
+
+/// Replaces the nop of a patch point at `address` by a breakpoint if
+/// `enabled`, or restores it otherwise.
+///
+/// The instruction is replaced atomically, so the code may be running on
+/// other threads, which either execute the nop or the breakpoint.
+///
+/// # Safety
+///
+/// `address` must be the address of a patch point in published code.
+pub unsafe fn set_breakpoint(address: usize, enabled: bool) -> io::Result<()> {
+    cfg_if::cfg_if! {
+        if #[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))] {
+            lazy_static::lazy_static! {
+                // Patching threads must not make the page read-only while
+                // another one writes to it.
+                static ref PATCHING: std::sync::Mutex<()> = std::sync::Mutex::new(());
+            }
+            let _patching = PATCHING.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+            let to_io_error = |e: region::Error| io::Error::new(io::ErrorKind::Other, e);
+            // The page stays executable while it's written to.
+            region::protect(address as *const u8, 1, region::Protection::READ_WRITE_EXECUTE)
+                .map_err(to_io_error)?;
+            #[cfg(target_arch = "x86_64")]
+            {
+                // `int3` and `nop`
+                let instruction = if enabled { 0xcc } else { 0x90 };
+                (*(address as *const std::sync::atomic::AtomicU8))
+                    .store(instruction, Ordering::SeqCst);
+            }
+            #[cfg(target_arch = "aarch64")]
+            {
+                extern "C" {
+                    fn __clear_cache(start: *mut libc::c_char, end: *mut libc::c_char);
+                }
+                // `brk #0` and `nop`
+                let instruction = if enabled { 0xd420_0000 } else { 0xd503_201f };
+                (*(address as *const std::sync::atomic::AtomicU32))
+                    .store(instruction, Ordering::SeqCst);
+                __clear_cache(address as *mut _, (address + 4) as *mut _);
+            }
+            region::protect(address as *const u8, 1, region::Protection::READ_EXECUTE)
+                .map_err(to_io_error)
+        } else {
+            let _ = (address, enabled);
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "breakpoints can't be patched in on this platform",
+            ))
+        }
+    }
+}
@@ -18,6 +18,9 @@ pub enum Trap {
         backtrace: Backtrace,
         /// Optional trapcode associated to the signal that caused the trap
         signal_trap: Option<TrapCode>,
+        /// The faulting address, if the trap was caused by an invalid
+        /// memory access
+        fault_address: Option<usize>,
     },
 
     /// A trap raised from a wasm libcall
@@ -43,11 +46,17 @@ impl Trap {
     /// Construct a new Wasm trap with the given source location and backtrace.
     ///
     /// Internally saves a backtrace when constructed.
-    pub fn wasm(pc: usize, backtrace: Backtrace, signal_trap: Option<TrapCode>) -> Self {
+    pub fn wasm(
+        pc: usize,
+        backtrace: Backtrace,
+        signal_trap: Option<TrapCode>,
+        fault_address: Option<usize>,
+    ) -> Self {
         Trap::Wasm {
             pc,
             backtrace,
             signal_trap,
+            fault_address,
         }
     }
 
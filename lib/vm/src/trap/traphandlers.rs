@@ -8,7 +8,9 @@ use crate::vmcontext::{VMFunctionBody, VMFunctionEnvironment, VMTrampoline};
 use crate::Trap;
 use backtrace::Backtrace;
 use core::ptr::{read, read_unaligned};
-use corosensei::stack::DefaultStack;
+#[cfg(unix)]
+use corosensei::stack::StackPointer;
+use corosensei::stack::{DefaultStack, Stack};
 use corosensei::trap::{CoroutineTrapHandler, TrapHandlerRegs};
 use corosensei::{CoroutineResult, ScopedCoroutine, Yielder};
 use scopeguard::defer;
@@ -16,6 +18,8 @@ use std::any::Any;
 use std::cell::Cell;
 use std::error::Error;
 use std::io;
+#[cfg(unix)]
+use std::marker::PhantomData;
 use std::mem;
 #[cfg(unix)]
 use std::mem::MaybeUninit;
@@ -106,34 +110,35 @@ cfg_if::cfg_if! {
         static mut PREV_SIGBUS: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
         static mut PREV_SIGILL: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
         static mut PREV_SIGFPE: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
+        static mut PREV_SIGTRAP: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
 
-        unsafe fn platform_init() {
-            let register = |slot: &mut MaybeUninit<libc::sigaction>, signal: i32| {
-                let mut handler: libc::sigaction = mem::zeroed();
-                // The flags here are relatively careful, and they are...
-                //
-                // SA_SIGINFO gives us access to information like the program
-                // counter from where the fault happened.
-                //
-                // SA_ONSTACK allows us to handle signals on an alternate stack,
-                // so that the handler can run in response to running out of
-                // stack space on the main stack. Rust installs an alternate
-                // stack with sigaltstack, so we rely on that.
-                //
-                // SA_NODEFER allows us to reenter the signal handler if we
-                // crash while handling the signal, and fall through to the
-                // Breakpad handler by testing handlingSegFault.
-                handler.sa_flags = libc::SA_SIGINFO | libc::SA_NODEFER | libc::SA_ONSTACK;
-                handler.sa_sigaction = trap_handler as usize;
-                libc::sigemptyset(&mut handler.sa_mask);
-                if libc::sigaction(signal, &handler, slot.as_mut_ptr()) != 0 {
-                    panic!(
-                        "unable to install signal handler: {}",
-                        io::Error::last_os_error(),
-                    );
-                }
-            };
+        unsafe fn register(slot: &mut MaybeUninit<libc::sigaction>, signal: i32) {
+            let mut handler: libc::sigaction = mem::zeroed();
+            // The flags here are relatively careful, and they are...
+            //
+            // SA_SIGINFO gives us access to information like the program
+            // counter from where the fault happened.
+            //
+            // SA_ONSTACK allows us to handle signals on an alternate stack,
+            // so that the handler can run in response to running out of
+            // stack space on the main stack. Rust installs an alternate
+            // stack with sigaltstack, so we rely on that.
+            //
+            // SA_NODEFER allows us to reenter the signal handler if we
+            // crash while handling the signal, and fall through to the
+            // Breakpad handler by testing handlingSegFault.
+            handler.sa_flags = libc::SA_SIGINFO | libc::SA_NODEFER | libc::SA_ONSTACK;
+            handler.sa_sigaction = trap_handler as usize;
+            libc::sigemptyset(&mut handler.sa_mask);
+            if libc::sigaction(signal, &handler, slot.as_mut_ptr()) != 0 {
+                panic!(
+                    "unable to install signal handler: {}",
+                    io::Error::last_os_error(),
+                );
+            }
+        }
 
+        unsafe fn platform_init() {
             // Allow handling OOB with signals on all architectures
             register(&mut PREV_SIGSEGV, libc::SIGSEGV);
 
@@ -199,8 +204,15 @@ cfg_if::cfg_if! {
                 libc::SIGBUS => &PREV_SIGBUS,
                 libc::SIGFPE => &PREV_SIGFPE,
                 libc::SIGILL => &PREV_SIGILL,
+                libc::SIGTRAP => &PREV_SIGTRAP,
                 _ => panic!("unknown signal: {}", signum),
             };
+            // Breakpoints placed for single-stepping resume the execution.
+            if signum == libc::SIGTRAP
+                && super::step::handle_step(&mut *(context as *mut libc::ucontext_t))
+            {
+                return;
+            }
             // We try to get the fault address associated to this signal
             let maybe_fault_address = match signum {
                 libc::SIGSEGV | libc::SIGBUS => {
@@ -249,6 +261,10 @@ cfg_if::cfg_if! {
             } else if previous.sa_sigaction == libc::SIG_DFL
             {
                 libc::sigaction(signum, previous, ptr::null_mut());
+                // A breakpoint isn't executed again when returning.
+                if signum == libc::SIGTRAP {
+                    libc::raise(signum);
+                }
             } else if previous.sa_sigaction != libc::SIG_IGN {
                 mem::transmute::<usize, extern "C" fn(libc::c_int)>(
                     previous.sa_sigaction
@@ -550,6 +566,17 @@ pub fn init_traps() {
     });
 }
 
+/// Starts catching the breakpoints placed for single-stepping.
+pub(super) fn init_step_signal() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        #[cfg(unix)]
+        unsafe {
+            register(&mut PREV_SIGTRAP, libc::SIGTRAP);
+        }
+    });
+}
+
 /// Raises a user-defined trap immediately.
 ///
 /// This function performs as-if a wasm trap was just executed, only the trap
@@ -622,6 +649,140 @@ pub unsafe fn wasmer_call_trampoline(
     })
 }
 
+/// A stack reserved to run WebAssembly calls on.
+///
+/// Calls usually take their stack from a pool shared by all threads,
+/// which requires a lock. A call given its own stack with
+/// [`wasmer_call_trampoline_on_stack`] neither locks nor allocates.
+pub struct CallStack(DefaultStack);
+
+impl CallStack {
+    /// The size of the stacks WebAssembly calls run on, in bytes.
+    pub const SIZE: usize = 1024 * 1024;
+
+    /// Allocates a stack.
+    pub fn new() -> io::Result<Self> {
+        DefaultStack::new(Self::SIZE).map(Self)
+    }
+}
+
+/// Like [`wasmer_call_trampoline`], but runs the call on `stack`.
+///
+/// # Safety
+///
+/// See [`wasmer_call_trampoline`].
+pub unsafe fn wasmer_call_trampoline_on_stack(
+    trap_handler: &(impl TrapHandler + 'static),
+    stack: &mut CallStack,
+    vmctx: VMFunctionEnvironment,
+    trampoline: VMTrampoline,
+    callee: *const VMFunctionBody,
+    values_vec: *mut u8,
+) -> Result<(), Trap> {
+    lazy_per_thread_init()?;
+
+    on_given_stack(trap_handler, &mut stack.0, || {
+        mem::transmute::<_, extern "C" fn(VMFunctionEnvironment, *const VMFunctionBody, *mut u8)>(
+            trampoline,
+        )(vmctx, callee, values_vec);
+    })
+    .map_err(UnwindReason::to_trap)
+}
+
+/// A region of memory owned by the caller, to run a WebAssembly call on.
+///
+/// The lowest page of the region is made inaccessible while it's used as a
+/// stack, so that a stack overflow traps instead of writing past the
+/// region, and is made accessible again when the `StackRegion` is dropped.
+/// The rest of the region must be large enough for the call, which
+/// `wasmer::Module::estimated_instance_size` estimates.
+#[cfg(unix)]
+pub struct StackRegion<'a> {
+    base: usize,
+    guard: usize,
+    page_size: usize,
+    _region: PhantomData<&'a mut [u8]>,
+}
+
+#[cfg(unix)]
+impl<'a> StackRegion<'a> {
+    /// The alignment of the top of the stack.
+    const ALIGNMENT: usize = 16;
+
+    /// Reserves the guard page of `memory` to run calls on it.
+    pub fn new(memory: &'a mut [u8]) -> io::Result<Self> {
+        let page_size = region::page::size();
+        let start = memory.as_mut_ptr() as usize;
+        let guard = (start + page_size - 1) & !(page_size - 1);
+        let base = (start + memory.len()) & !(Self::ALIGNMENT - 1);
+        // At least a page must be usable besides the guard page.
+        if base < guard + 2 * page_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the stack region is smaller than two pages",
+            ));
+        }
+        unsafe { region::protect(guard as *const u8, page_size, region::Protection::NONE) }
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        Ok(Self {
+            base,
+            guard,
+            page_size,
+            _region: PhantomData,
+        })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for StackRegion<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            region::protect(
+                self.guard as *const u8,
+                self.page_size,
+                region::Protection::READ_WRITE,
+            )
+        }
+        .expect("unable to restore the guard page of a stack region");
+    }
+}
+
+#[cfg(unix)]
+unsafe impl Stack for StackRegion<'_> {
+    fn base(&self) -> StackPointer {
+        StackPointer::new(self.base).unwrap()
+    }
+
+    fn limit(&self) -> StackPointer {
+        StackPointer::new(self.guard + self.page_size).unwrap()
+    }
+}
+
+/// Like [`wasmer_call_trampoline`], but runs the call on the caller-owned
+/// `region`.
+///
+/// # Safety
+///
+/// See [`wasmer_call_trampoline`].
+#[cfg(unix)]
+pub unsafe fn wasmer_call_trampoline_on_region(
+    trap_handler: &(impl TrapHandler + 'static),
+    region: &mut StackRegion<'_>,
+    vmctx: VMFunctionEnvironment,
+    trampoline: VMTrampoline,
+    callee: *const VMFunctionBody,
+    values_vec: *mut u8,
+) -> Result<(), Trap> {
+    lazy_per_thread_init()?;
+
+    on_given_stack(trap_handler, region, || {
+        mem::transmute::<_, extern "C" fn(VMFunctionEnvironment, *const VMFunctionBody, *mut u8)>(
+            trampoline,
+        )(vmctx, callee, values_vec);
+    })
+    .map_err(UnwindReason::to_trap)
+}
+
 /// Catches any wasm traps that happen within the execution of `closure`,
 /// returning them as a `Result`.
 ///
@@ -647,9 +808,13 @@ where
 //
 // We also do per-thread signal stack initialization on the first time
 // TRAP_HANDLER is accessed.
+//
+// HOST_FUNCTION_STACK_BASE is the address on the host stack past the frames
+// of the host function running there on behalf of WebAssembly, if any.
 thread_local! {
     static YIELDER: Cell<Option<NonNull<Yielder<(), UnwindReason>>>> = Cell::new(None);
     static TRAP_HANDLER: AtomicPtr<TrapHandlerContext> = AtomicPtr::new(ptr::null_mut());
+    static HOST_FUNCTION_STACK_BASE: Cell<Option<usize>> = Cell::new(None);
 }
 
 /// Read-only information that is used by signal handlers to handle and recover
@@ -795,9 +960,15 @@ impl<T> TrapHandlerContextInner<T> {
 
         // Set up the register state for exception return to force the
         // coroutine to return to its caller with UnwindReason::WasmTrap.
+        // The fault address of a stack overflow is not interesting: only
+        // keep the one of invalid memory accesses.
+        let fault_address =
+            maybe_fault_address.filter(|_| signal_trap != Some(TrapCode::StackOverflow));
+
         let unwind = UnwindReason::WasmTrap {
             backtrace,
             signal_trap,
+            fault_address,
             pc,
         };
         let regs = self
@@ -820,6 +991,7 @@ enum UnwindReason {
         backtrace: Backtrace,
         pc: usize,
         signal_trap: Option<TrapCode>,
+        fault_address: Option<usize>,
     },
 }
 
@@ -832,7 +1004,8 @@ impl UnwindReason {
                 backtrace,
                 pc,
                 signal_trap,
-            } => Trap::wasm(pc, backtrace, signal_trap),
+                fault_address,
+            } => Trap::wasm(pc, backtrace, signal_trap, fault_address),
             UnwindReason::Panic(panic) => std::panic::resume_unwind(panic),
         }
     }
@@ -866,8 +1039,17 @@ fn on_wasm_stack<F: FnOnce() -> T, T>(
     let stack = STACK_POOL.lock().unwrap().pop().unwrap_or_default();
     let mut stack = scopeguard::guard(stack, |stack| STACK_POOL.lock().unwrap().push(stack));
 
+    on_given_stack(trap_handler, &mut *stack, f)
+}
+
+/// Runs `f` on `stack` with a trap handler.
+fn on_given_stack<F: FnOnce() -> T, T, S: Stack>(
+    trap_handler: &(dyn TrapHandler + 'static),
+    stack: &mut S,
+    f: F,
+) -> Result<T, UnwindReason> {
     // Create a coroutine with a new stack to run the function on.
-    let mut coro = ScopedCoroutine::with_stack(&mut *stack, move |yielder, ()| {
+    let mut coro = ScopedCoroutine::with_stack(stack, move |yielder, ()| {
         // Save the yielder to TLS so that it can be used later.
         YIELDER.with(|cell| cell.set(Some(yielder.into())));
 
@@ -927,9 +1109,33 @@ pub fn on_host_stack<F: FnOnce() -> T, T>(f: F) -> T {
     struct SendWrapper<T>(T);
     unsafe impl<T> Send for SendWrapper<T> {}
     let wrapped = SendWrapper(f);
-    yielder.on_parent_stack(move || (wrapped.0)())
+    yielder.on_parent_stack(move || {
+        let base = 0u8;
+        let previous =
+            HOST_FUNCTION_STACK_BASE.with(|cell| cell.replace(Some(&base as *const u8 as usize)));
+        defer! {
+            HOST_FUNCTION_STACK_BASE.with(|cell| cell.set(previous));
+        }
+        (wrapped.0)()
+    })
+}
+
+/// Returns the address on the host stack past the frames of the host
+/// function called from WebAssembly which the current thread is running,
+/// if any.
+///
+/// The stack frames with a lower stack pointer belong to the host function,
+/// the others to whatever called into WebAssembly in the first place.
+pub fn host_function_stack_base() -> Option<usize> {
+    if YIELDER.with(|cell| cell.get()).is_some() {
+        // On the WebAssembly stack
+        return None;
+    }
+    HOST_FUNCTION_STACK_BASE.with(|cell| cell.get())
 }
 
+/// Prepares the current thread to handle traps. Calls into WebAssembly
+/// do it on their own, the first time they run on a thread.
 #[cfg(windows)]
 pub fn lazy_per_thread_init() -> Result<(), Trap> {
     // We need additional space on the stack to handle stack overflow
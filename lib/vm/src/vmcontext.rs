@@ -322,10 +322,14 @@ mod test_vmglobal_import {
 /// The fields compiled code needs to access to utilize a WebAssembly linear
 /// memory defined within the instance, namely the start address and the
 /// size in bytes.
+///
+/// Growing a dynamic memory may move it, so code keeping the start
+/// address around must load it again after any call, which may have
+/// grown the memory. The start address of a static memory never moves.
 #[derive(Debug, Copy, Clone)]
 #[repr(C)]
 pub struct VMMemoryDefinition {
-    /// The start address which is always valid, even if the memory grows.
+    /// The start address of the memory.
     pub base: *mut u8,
 
     /// The current logical size of this linear memory in bytes.
@@ -809,6 +813,11 @@ impl VMSharedSignatureIndex {
     pub fn new(value: u32) -> Self {
         Self(value)
     }
+
+    /// Return the index as a `u32`.
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
 }
 
 impl Default for VMSharedSignatureIndex {
@@ -43,8 +43,9 @@ mod utils;
 use crate::syscalls::*;
 
 pub use crate::state::{
-    Fd, Pipe, Stderr, Stdin, Stdout, WasiFs, WasiState, WasiStateBuilder, WasiStateCreationError,
-    ALL_RIGHTS, VIRTUAL_ROOT_FD,
+    DeterministicState, Fd, Pipe, Stderr, Stdin, Stdout, WasiFs, WasiQuotas, WasiShutdown,
+    WasiState, WasiStateBuilder, WasiStateCreationError, WasiUsage, ALL_RIGHTS, SHUTDOWN_FD_ENV,
+    VIRTUAL_ROOT_FD,
 };
 pub use crate::syscalls::types;
 pub use crate::utils::{get_wasi_version, get_wasi_versions, is_wasi_module, WasiVersion};
@@ -70,6 +71,8 @@ pub enum WasiError {
     Exit(syscalls::types::__wasi_exitcode_t),
     #[error("The WASI version could not be determined")]
     UnknownWasiVersion,
+    #[error("WASI program was terminated")]
+    Terminated,
 }
 
 /// The environment provided to the WASI imports.
@@ -132,6 +135,30 @@ impl WasiEnv {
         self.state.lock().unwrap()
     }
 
+    /// Get what the program used so far.
+    pub fn usage(&self) -> WasiUsage {
+        self.state().usage
+    }
+
+    /// Counts a call to a WASI function, failing once the quota of calls
+    /// is reached, and trapping once the program is terminated.
+    pub(crate) fn count_syscall(&self) -> Result<(), syscalls::types::__wasi_errno_t> {
+        let (counted, terminated) = {
+            let state = &mut *self.state();
+            let terminated = state
+                .shutdown
+                .as_ref()
+                .map_or(false, WasiShutdown::is_terminated);
+            (state.usage.syscall(&state.quotas), terminated)
+        };
+        if terminated {
+            // The state is unlocked, as the trap unwinds without dropping.
+            #[allow(deprecated)]
+            wasmer::RuntimeError::raise(Box::new(WasiError::Terminated));
+        }
+        counted
+    }
+
     /// Get a reference to the memory
     pub fn memory(&self) -> &Memory {
         self.memory_ref()
@@ -151,11 +178,21 @@ impl WasiEnv {
 /// Create an [`ImportObject`] with an existing [`WasiEnv`]. `WasiEnv`
 /// needs a [`WasiState`], that can be constructed from a
 /// [`WasiStateBuilder`](state::WasiStateBuilder).
+///
+/// In a deterministic [`Store`], the program runs with virtual clocks and
+/// seeded randomness, see
+/// [`WasiStateBuilder::deterministic`](state::WasiStateBuilder::deterministic).
 pub fn generate_import_object_from_env(
     store: &Store,
     wasi_env: WasiEnv,
     version: WasiVersion,
 ) -> ImportObject {
+    if store.is_deterministic() {
+        let mut state = wasi_env.state();
+        if state.deterministic.is_none() {
+            state.deterministic = Some(DeterministicState::new(0));
+        }
+    }
     match version {
         WasiVersion::Snapshot0 => generate_import_object_snapshot0(store, wasi_env),
         WasiVersion::Snapshot1 | WasiVersion::Latest => {
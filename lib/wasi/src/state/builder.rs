@@ -1,6 +1,9 @@
 //! Builder system for configuring a [`WasiState`] and creating it.
 
-use crate::state::{default_fs_backing, WasiFs, WasiState};
+use crate::state::{
+    default_fs_backing, fs_error_from_wasi_err, DeterministicState, PathRemap, WasiFs, WasiQuotas,
+    WasiShutdown, WasiState, WasiUsage, SHUTDOWN_FD_ENV,
+};
 use crate::syscalls::types::{__WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO};
 use crate::WasiEnv;
 use std::path::{Path, PathBuf};
@@ -39,12 +42,16 @@ pub struct WasiStateBuilder {
     envs: Vec<(Vec<u8>, Vec<u8>)>,
     preopens: Vec<PreopenedDir>,
     vfs_preopens: Vec<String>,
+    path_remaps: Vec<PathRemap>,
     #[allow(clippy::type_complexity)]
     setup_fs_fn: Option<Box<dyn Fn(&mut WasiFs) -> Result<(), String> + Send>>,
     stdout_override: Option<Box<dyn VirtualFile>>,
     stderr_override: Option<Box<dyn VirtualFile>>,
     stdin_override: Option<Box<dyn VirtualFile>>,
     fs_override: Option<Box<dyn wasmer_vfs::FileSystem>>,
+    deterministic: Option<DeterministicState>,
+    quotas: WasiQuotas,
+    shutdown: Option<WasiShutdown>,
 }
 
 impl std::fmt::Debug for WasiStateBuilder {
@@ -54,10 +61,14 @@ impl std::fmt::Debug for WasiStateBuilder {
             .field("args", &self.args)
             .field("envs", &self.envs)
             .field("preopens", &self.preopens)
+            .field("path_remaps", &self.path_remaps)
             .field("setup_fs_fn exists", &self.setup_fs_fn.is_some())
             .field("stdout_override exists", &self.stdout_override.is_some())
             .field("stderr_override exists", &self.stderr_override.is_some())
             .field("stdin_override exists", &self.stdin_override.is_some())
+            .field("deterministic", &self.deterministic)
+            .field("quotas", &self.quotas)
+            .field("shutdown", &self.shutdown)
             .finish()
     }
 }
@@ -75,6 +86,8 @@ pub enum WasiStateCreationError {
     PreopenedDirectoryError(String),
     #[error("mapped dir alias has wrong format: `{0}`")]
     MappedDirAliasFormattingError(String),
+    #[error("path remap has wrong format: `{0}`")]
+    PathRemapFormattingError(String),
     #[error("wasi filesystem creation error: `{0}`")]
     WasiFsCreationError(String),
     #[error("wasi filesystem setup error: `{0}`")]
@@ -275,6 +288,43 @@ impl WasiStateBuilder {
         Ok(self)
     }
 
+    /// Rewrites the absolute paths the program requests under
+    /// `guest_prefix`, such as `/etc/ssl`, to `target`, a path starting
+    /// with the alias of a preopened directory, such as `certs`. The
+    /// longest matching prefix wins, and remapped accesses are logged.
+    ///
+    /// This is for programs hardcoding absolute paths, see [`PathRemap`].
+    pub fn remap_path<GuestPath, Target>(
+        &mut self,
+        guest_prefix: GuestPath,
+        target: Target,
+    ) -> Result<&mut Self, WasiStateCreationError>
+    where
+        GuestPath: AsRef<Path>,
+        Target: AsRef<Path>,
+    {
+        let guest_prefix = guest_prefix.as_ref();
+        let target = target.as_ref();
+        if !guest_prefix.has_root() {
+            return Err(WasiStateCreationError::PathRemapFormattingError(format!(
+                "\"{}\" is not an absolute path",
+                guest_prefix.display()
+            )));
+        }
+        if target.has_root() {
+            return Err(WasiStateCreationError::PathRemapFormattingError(format!(
+                "\"{}\" must start with the alias of a preopened directory",
+                target.display()
+            )));
+        }
+        self.path_remaps.push(PathRemap {
+            guest_prefix: guest_prefix.to_owned(),
+            target: target.to_owned(),
+        });
+
+        Ok(self)
+    }
+
     /// Overwrite the default WASI `stdout`, if you want to hold on to the
     /// original `stdout` use [`WasiFs::swap_file`] after building.
     pub fn stdout(&mut self, new_file: Box<dyn VirtualFile>) -> &mut Self {
@@ -308,6 +358,33 @@ impl WasiStateBuilder {
         self
     }
 
+    /// Runs the program with virtual clocks and a pseudo random generator
+    /// seeded by `seed`, so it behaves the same way on every run.
+    ///
+    /// This is forced for the programs instantiated in a deterministic
+    /// [`Store`](wasmer::Store), with a seed of 0 unless set here.
+    pub fn deterministic(&mut self, seed: u64) -> &mut Self {
+        self.deterministic = Some(DeterministicState::new(seed));
+
+        self
+    }
+
+    /// Limits what the program may use, see [`WasiQuotas`]. What it
+    /// used is read from [`WasiState::usage`].
+    pub fn quotas(&mut self, quotas: WasiQuotas) -> &mut Self {
+        self.quotas = quotas;
+
+        self
+    }
+
+    /// Lets the host ask the program to terminate through `shutdown`,
+    /// see [`WasiShutdown`].
+    pub fn shutdown(&mut self, shutdown: WasiShutdown) -> &mut Self {
+        self.shutdown = Some(shutdown);
+
+        self
+    }
+
     /// Configure the WASI filesystem before running.
     // TODO: improve ergonomics on this function
     pub fn setup_fs(
@@ -409,6 +486,7 @@ impl WasiStateBuilder {
         // self.preopens are checked in [`PreopenDirBuilder::build`]
         let mut wasi_fs = WasiFs::new_with_preopen(&self.preopens, &self.vfs_preopens, fs_backing)
             .map_err(WasiStateCreationError::WasiFsCreationError)?;
+        wasi_fs.path_remaps = self.path_remaps.clone();
 
         // set up the file system, overriding base files and calling the setup function
         if let Some(stdin_override) = self.stdin_override.take() {
@@ -433,11 +511,20 @@ impl WasiStateBuilder {
             f(&mut wasi_fs).map_err(WasiStateCreationError::WasiFsSetupError)?;
         }
 
+        let mut envs = self.envs.clone();
+        if let Some(shutdown) = &self.shutdown {
+            let fd = wasi_fs
+                .open_shutdown_file(shutdown.clone())
+                .map_err(|errno| {
+                    WasiStateCreationError::FileSystemError(fs_error_from_wasi_err(errno))
+                })?;
+            envs.push((SHUTDOWN_FD_ENV.into(), fd.to_string().into_bytes()));
+        }
+
         Ok(WasiState {
             fs: wasi_fs,
             args: self.args.clone(),
-            envs: self
-                .envs
+            envs: envs
                 .iter()
                 .map(|(key, value)| {
                     let mut env = Vec::with_capacity(key.len() + value.len() + 1);
@@ -448,6 +535,10 @@ impl WasiStateBuilder {
                     env
                 })
                 .collect(),
+            deterministic: self.deterministic.clone(),
+            usage: WasiUsage::default(),
+            quotas: self.quotas,
+            shutdown: self.shutdown.clone(),
         })
     }
 
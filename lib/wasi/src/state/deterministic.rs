@@ -0,0 +1,93 @@
+use crate::syscalls::types::*;
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+
+/// How much the virtual clocks advance on each read, in nanoseconds.
+const TICK: __wasi_timestamp_t = 1_000_000;
+
+/// The clocks and the random source of a WASI program running
+/// deterministically.
+///
+/// All the clocks share one virtual time, starting at 0 and advancing by
+/// a fixed step each time a clock is read, and `random_get` returns the
+/// output of a pseudo random generator seeded by the embedder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct DeterministicState {
+    time: __wasi_timestamp_t,
+    rng: u64,
+}
+
+impl DeterministicState {
+    /// Creates the state of a program whose randomness is seeded by `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self { time: 0, rng: seed }
+    }
+
+    /// The resolution of the virtual clocks, in nanoseconds.
+    pub(crate) fn clock_res_get(
+        &self,
+        clock_id: __wasi_clockid_t,
+    ) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+        check_clock_id(clock_id)?;
+        Ok(TICK)
+    }
+
+    /// Reads the virtual time, in nanoseconds, and advances it.
+    pub(crate) fn clock_time_get(
+        &mut self,
+        clock_id: __wasi_clockid_t,
+    ) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+        check_clock_id(clock_id)?;
+        self.time += TICK;
+        Ok(self.time)
+    }
+
+    /// Fills `buf` with pseudo random bytes, using SplitMix64.
+    pub(crate) fn random_get(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = self.rng;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            chunk.copy_from_slice(&z.to_le_bytes()[..chunk.len()]);
+        }
+    }
+}
+
+fn check_clock_id(clock_id: __wasi_clockid_t) -> Result<(), __wasi_errno_t> {
+    match clock_id {
+        __WASI_CLOCK_REALTIME
+        | __WASI_CLOCK_MONOTONIC
+        | __WASI_CLOCK_PROCESS_CPUTIME_ID
+        | __WASI_CLOCK_THREAD_CPUTIME_ID => Ok(()),
+        _ => Err(__WASI_EINVAL),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_outputs() {
+        let mut a = DeterministicState::new(42);
+        let mut b = DeterministicState::new(42);
+        let (mut bytes_a, mut bytes_b) = ([0; 13], [0; 13]);
+        a.random_get(&mut bytes_a);
+        b.random_get(&mut bytes_b);
+        assert_eq!(bytes_a, bytes_b);
+        assert_ne!(bytes_a, [0; 13]);
+
+        let mut c = DeterministicState::new(43);
+        let mut bytes_c = [0; 13];
+        c.random_get(&mut bytes_c);
+        assert_ne!(bytes_a, bytes_c);
+
+        assert_eq!(a.clock_time_get(__WASI_CLOCK_MONOTONIC), Ok(TICK));
+        assert_eq!(a.clock_time_get(__WASI_CLOCK_REALTIME), Ok(2 * TICK));
+        assert_eq!(a.clock_res_get(__WASI_CLOCK_REALTIME), Ok(TICK));
+        assert_eq!(a.clock_time_get(4), Err(__WASI_EINVAL));
+    }
+}
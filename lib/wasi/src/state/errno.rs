@@ -0,0 +1,446 @@
+//! Translation of the errors of the host and of the virtual file system
+//! to WASI errnos.
+//!
+//! Errors reach the syscalls as [`io::Error`]s, carrying the raw error code
+//! of the host when they come from it, or as [`FsError`]s. Raw codes are
+//! translated first, with one table per host OS, since they are more
+//! precise than the [`io::ErrorKind`] the standard library folds them into;
+//! errors without one are translated from their kind.
+
+use crate::syscalls::types::*;
+use std::io;
+use wasmer_vfs::FsError;
+
+pub fn fs_error_from_wasi_err(err: __wasi_errno_t) -> FsError {
+    match err {
+        __WASI_EBADF => FsError::InvalidFd,
+        __WASI_EEXIST => FsError::AlreadyExists,
+        __WASI_EIO => FsError::IOError,
+        __WASI_EADDRINUSE => FsError::AddressInUse,
+        __WASI_EADDRNOTAVAIL => FsError::AddressNotAvailable,
+        __WASI_EPIPE => FsError::BrokenPipe,
+        __WASI_ECONNABORTED => FsError::ConnectionAborted,
+        __WASI_ECONNREFUSED => FsError::ConnectionRefused,
+        __WASI_ECONNRESET => FsError::ConnectionReset,
+        __WASI_EINTR => FsError::Interrupted,
+        __WASI_EINVAL => FsError::InvalidInput,
+        __WASI_ENOTCONN => FsError::NotConnected,
+        __WASI_ENODEV => FsError::NoDevice,
+        __WASI_ENOENT => FsError::EntityNotFound,
+        __WASI_EPERM => FsError::PermissionDenied,
+        __WASI_ETIMEDOUT => FsError::TimedOut,
+        __WASI_EPROTO => FsError::UnexpectedEof,
+        __WASI_EAGAIN => FsError::WouldBlock,
+        __WASI_ENOSPC => FsError::WriteZero,
+        __WASI_ENOTEMPTY => FsError::DirectoryNotEmpty,
+        _ => FsError::UnknownError,
+    }
+}
+
+pub fn fs_error_into_wasi_err(fs_error: FsError) -> __wasi_errno_t {
+    match fs_error {
+        FsError::AlreadyExists => __WASI_EEXIST,
+        FsError::AddressInUse => __WASI_EADDRINUSE,
+        FsError::AddressNotAvailable => __WASI_EADDRNOTAVAIL,
+        FsError::BaseNotDirectory => __WASI_ENOTDIR,
+        FsError::BrokenPipe => __WASI_EPIPE,
+        FsError::ConnectionAborted => __WASI_ECONNABORTED,
+        FsError::ConnectionRefused => __WASI_ECONNREFUSED,
+        FsError::ConnectionReset => __WASI_ECONNRESET,
+        FsError::Interrupted => __WASI_EINTR,
+        FsError::InvalidData => __WASI_EIO,
+        FsError::InvalidFd => __WASI_EBADF,
+        FsError::InvalidInput => __WASI_EINVAL,
+        FsError::IOError => __WASI_EIO,
+        FsError::NoDevice => __WASI_ENODEV,
+        FsError::NotAFile => __WASI_EINVAL,
+        FsError::NotConnected => __WASI_ENOTCONN,
+        FsError::EntityNotFound => __WASI_ENOENT,
+        FsError::PermissionDenied => __WASI_EPERM,
+        FsError::TimedOut => __WASI_ETIMEDOUT,
+        FsError::UnexpectedEof => __WASI_EPROTO,
+        FsError::WouldBlock => __WASI_EAGAIN,
+        FsError::WriteZero => __WASI_ENOSPC,
+        FsError::DirectoryNotEmpty => __WASI_ENOTEMPTY,
+        FsError::Lock | FsError::UnknownError => __WASI_EIO,
+    }
+}
+
+/// Translates an I/O error to a WASI errno, from its raw OS error code if
+/// it has one the host OS table knows, and from its kind otherwise.
+pub fn io_error_into_wasi_err(error: &io::Error) -> __wasi_errno_t {
+    error
+        .raw_os_error()
+        .and_then(os_error_into_wasi_err)
+        .unwrap_or_else(|| error_kind_into_wasi_err(error.kind()))
+}
+
+/// Translates the kind of an I/O error to a WASI errno, consistently with
+/// [`fs_error_into_wasi_err`] for the kinds an [`FsError`] can stand for.
+pub fn error_kind_into_wasi_err(kind: io::ErrorKind) -> __wasi_errno_t {
+    match kind {
+        io::ErrorKind::NotFound => __WASI_ENOENT,
+        io::ErrorKind::PermissionDenied => __WASI_EPERM,
+        io::ErrorKind::ConnectionRefused => __WASI_ECONNREFUSED,
+        io::ErrorKind::ConnectionReset => __WASI_ECONNRESET,
+        io::ErrorKind::ConnectionAborted => __WASI_ECONNABORTED,
+        io::ErrorKind::NotConnected => __WASI_ENOTCONN,
+        io::ErrorKind::AddrInUse => __WASI_EADDRINUSE,
+        io::ErrorKind::AddrNotAvailable => __WASI_EADDRNOTAVAIL,
+        io::ErrorKind::BrokenPipe => __WASI_EPIPE,
+        io::ErrorKind::AlreadyExists => __WASI_EEXIST,
+        io::ErrorKind::WouldBlock => __WASI_EAGAIN,
+        io::ErrorKind::InvalidInput => __WASI_EINVAL,
+        io::ErrorKind::InvalidData => __WASI_EIO,
+        io::ErrorKind::TimedOut => __WASI_ETIMEDOUT,
+        io::ErrorKind::WriteZero => __WASI_ENOSPC,
+        io::ErrorKind::Interrupted => __WASI_EINTR,
+        io::ErrorKind::Unsupported => __WASI_ENOTSUP,
+        io::ErrorKind::UnexpectedEof => __WASI_EPROTO,
+        io::ErrorKind::OutOfMemory => __WASI_ENOMEM,
+        _ => __WASI_EIO,
+    }
+}
+
+/// Translates a raw `errno` of the host to a WASI errno.
+///
+/// WASI errnos are the POSIX ones, so every one of them has a counterpart
+/// of the same name.
+#[cfg(unix)]
+pub fn os_error_into_wasi_err(errno: i32) -> Option<__wasi_errno_t> {
+    Some(match errno {
+        libc::E2BIG => __WASI_E2BIG,
+        libc::EACCES => __WASI_EACCES,
+        libc::EADDRINUSE => __WASI_EADDRINUSE,
+        libc::EADDRNOTAVAIL => __WASI_EADDRNOTAVAIL,
+        libc::EAFNOSUPPORT => __WASI_EAFNOSUPPORT,
+        libc::EAGAIN => __WASI_EAGAIN,
+        libc::EALREADY => __WASI_EALREADY,
+        libc::EBADF => __WASI_EBADF,
+        libc::EBADMSG => __WASI_EBADMSG,
+        libc::EBUSY => __WASI_EBUSY,
+        libc::ECANCELED => __WASI_ECANCELED,
+        libc::ECHILD => __WASI_ECHILD,
+        libc::ECONNABORTED => __WASI_ECONNABORTED,
+        libc::ECONNREFUSED => __WASI_ECONNREFUSED,
+        libc::ECONNRESET => __WASI_ECONNRESET,
+        libc::EDEADLK => __WASI_EDEADLK,
+        libc::EDESTADDRREQ => __WASI_EDESTADDRREQ,
+        libc::EDOM => __WASI_EDOM,
+        libc::EDQUOT => __WASI_EDQUOT,
+        libc::EEXIST => __WASI_EEXIST,
+        libc::EFAULT => __WASI_EFAULT,
+        libc::EFBIG => __WASI_EFBIG,
+        libc::EHOSTUNREACH => __WASI_EHOSTUNREACH,
+        libc::EIDRM => __WASI_EIDRM,
+        libc::EILSEQ => __WASI_EILSEQ,
+        libc::EINPROGRESS => __WASI_EINPROGRESS,
+        libc::EINTR => __WASI_EINTR,
+        libc::EINVAL => __WASI_EINVAL,
+        libc::EIO => __WASI_EIO,
+        libc::EISCONN => __WASI_EISCONN,
+        libc::EISDIR => __WASI_EISDIR,
+        libc::ELOOP => __WASI_ELOOP,
+        libc::EMFILE => __WASI_EMFILE,
+        libc::EMLINK => __WASI_EMLINK,
+        libc::EMSGSIZE => __WASI_EMSGSIZE,
+        libc::EMULTIHOP => __WASI_EMULTIHOP,
+        libc::ENAMETOOLONG => __WASI_ENAMETOOLONG,
+        libc::ENETDOWN => __WASI_ENETDOWN,
+        libc::ENETRESET => __WASI_ENETRESET,
+        libc::ENETUNREACH => __WASI_ENETUNREACH,
+        libc::ENFILE => __WASI_ENFILE,
+        libc::ENOBUFS => __WASI_ENOBUFS,
+        libc::ENODEV => __WASI_ENODEV,
+        libc::ENOENT => __WASI_ENOENT,
+        libc::ENOEXEC => __WASI_ENOEXEC,
+        libc::ENOLCK => __WASI_ENOLCK,
+        libc::ENOLINK => __WASI_ENOLINK,
+        libc::ENOMEM => __WASI_ENOMEM,
+        libc::ENOMSG => __WASI_ENOMSG,
+        libc::ENOPROTOOPT => __WASI_ENOPROTOOPT,
+        libc::ENOSPC => __WASI_ENOSPC,
+        libc::ENOSYS => __WASI_ENOSYS,
+        libc::ENOTCONN => __WASI_ENOTCONN,
+        libc::ENOTDIR => __WASI_ENOTDIR,
+        libc::ENOTEMPTY => __WASI_ENOTEMPTY,
+        libc::ENOTRECOVERABLE => __WASI_ENOTRECOVERABLE,
+        libc::ENOTSOCK => __WASI_ENOTSOCK,
+        libc::ENOTSUP => __WASI_ENOTSUP,
+        libc::ENOTTY => __WASI_ENOTTY,
+        libc::ENXIO => __WASI_ENXIO,
+        libc::EOVERFLOW => __WASI_EOVERFLOW,
+        libc::EOWNERDEAD => __WASI_EOWNERDEAD,
+        libc::EPERM => __WASI_EPERM,
+        libc::EPIPE => __WASI_EPIPE,
+        libc::EPROTO => __WASI_EPROTO,
+        libc::EPROTONOSUPPORT => __WASI_EPROTONOSUPPORT,
+        libc::EPROTOTYPE => __WASI_EPROTOTYPE,
+        libc::ERANGE => __WASI_ERANGE,
+        libc::EROFS => __WASI_EROFS,
+        libc::ESPIPE => __WASI_ESPIPE,
+        libc::ESRCH => __WASI_ESRCH,
+        libc::ESTALE => __WASI_ESTALE,
+        libc::ETIMEDOUT => __WASI_ETIMEDOUT,
+        libc::ETXTBSY => __WASI_ETXTBSY,
+        libc::EXDEV => __WASI_EXDEV,
+        // The same value as `ENOTSUP` on Linux, but not on macOS or BSDs.
+        errno if errno == libc::EOPNOTSUPP => __WASI_ENOTSUP,
+        errno if errno == libc::EWOULDBLOCK => __WASI_EAGAIN,
+        _ => return None,
+    })
+}
+
+/// Translates a raw Win32 or Windows Sockets error code of the host to a
+/// WASI errno.
+#[cfg(windows)]
+pub fn os_error_into_wasi_err(code: i32) -> Option<__wasi_errno_t> {
+    Some(match code {
+        win32::ERROR_FILE_NOT_FOUND | win32::ERROR_PATH_NOT_FOUND | win32::ERROR_INVALID_NAME => {
+            __WASI_ENOENT
+        }
+        win32::ERROR_TOO_MANY_OPEN_FILES => __WASI_EMFILE,
+        win32::ERROR_ACCESS_DENIED => __WASI_EACCES,
+        win32::ERROR_INVALID_HANDLE => __WASI_EBADF,
+        win32::ERROR_NOT_ENOUGH_MEMORY | win32::ERROR_OUTOFMEMORY => __WASI_ENOMEM,
+        win32::ERROR_NOT_SAME_DEVICE => __WASI_EXDEV,
+        win32::ERROR_WRITE_PROTECT => __WASI_EROFS,
+        win32::ERROR_SHARING_VIOLATION | win32::ERROR_LOCK_VIOLATION | win32::ERROR_BUSY => {
+            __WASI_EBUSY
+        }
+        win32::ERROR_HANDLE_DISK_FULL | win32::ERROR_DISK_FULL => __WASI_ENOSPC,
+        win32::ERROR_NOT_SUPPORTED | win32::ERROR_CALL_NOT_IMPLEMENTED => __WASI_ENOTSUP,
+        win32::ERROR_FILE_EXISTS | win32::ERROR_ALREADY_EXISTS => __WASI_EEXIST,
+        win32::ERROR_INVALID_PARAMETER | win32::ERROR_NEGATIVE_SEEK => __WASI_EINVAL,
+        win32::ERROR_BROKEN_PIPE | win32::ERROR_NO_DATA => __WASI_EPIPE,
+        win32::ERROR_DIR_NOT_EMPTY => __WASI_ENOTEMPTY,
+        win32::ERROR_FILENAME_EXCED_RANGE => __WASI_ENAMETOOLONG,
+        win32::ERROR_DIRECTORY => __WASI_ENOTDIR,
+        win32::ERROR_PRIVILEGE_NOT_HELD => __WASI_EPERM,
+        win32::ERROR_CANT_RESOLVE_FILENAME => __WASI_ELOOP,
+        win32::WSAEINTR => __WASI_EINTR,
+        win32::WSAEBADF => __WASI_EBADF,
+        win32::WSAEACCES => __WASI_EACCES,
+        win32::WSAEFAULT => __WASI_EFAULT,
+        win32::WSAEINVAL => __WASI_EINVAL,
+        win32::WSAEMFILE => __WASI_EMFILE,
+        win32::WSAEWOULDBLOCK => __WASI_EAGAIN,
+        win32::WSAEINPROGRESS => __WASI_EINPROGRESS,
+        win32::WSAEALREADY => __WASI_EALREADY,
+        win32::WSAENOTSOCK => __WASI_ENOTSOCK,
+        win32::WSAEDESTADDRREQ => __WASI_EDESTADDRREQ,
+        win32::WSAEMSGSIZE => __WASI_EMSGSIZE,
+        win32::WSAEPROTOTYPE => __WASI_EPROTOTYPE,
+        win32::WSAENOPROTOOPT => __WASI_ENOPROTOOPT,
+        win32::WSAEPROTONOSUPPORT => __WASI_EPROTONOSUPPORT,
+        win32::WSAEOPNOTSUPP => __WASI_ENOTSUP,
+        win32::WSAEAFNOSUPPORT => __WASI_EAFNOSUPPORT,
+        win32::WSAEADDRINUSE => __WASI_EADDRINUSE,
+        win32::WSAEADDRNOTAVAIL => __WASI_EADDRNOTAVAIL,
+        win32::WSAENETDOWN => __WASI_ENETDOWN,
+        win32::WSAENETUNREACH => __WASI_ENETUNREACH,
+        win32::WSAENETRESET => __WASI_ENETRESET,
+        win32::WSAECONNABORTED => __WASI_ECONNABORTED,
+        win32::WSAECONNRESET => __WASI_ECONNRESET,
+        win32::WSAENOBUFS => __WASI_ENOBUFS,
+        win32::WSAEISCONN => __WASI_EISCONN,
+        win32::WSAENOTCONN => __WASI_ENOTCONN,
+        win32::WSAETIMEDOUT => __WASI_ETIMEDOUT,
+        win32::WSAECONNREFUSED => __WASI_ECONNREFUSED,
+        win32::WSAELOOP => __WASI_ELOOP,
+        win32::WSAENAMETOOLONG => __WASI_ENAMETOOLONG,
+        win32::WSAEHOSTUNREACH => __WASI_EHOSTUNREACH,
+        win32::WSAENOTEMPTY => __WASI_ENOTEMPTY,
+        win32::WSAEDQUOT => __WASI_EDQUOT,
+        win32::WSAESTALE => __WASI_ESTALE,
+        _ => return None,
+    })
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn os_error_into_wasi_err(_code: i32) -> Option<__wasi_errno_t> {
+    None
+}
+
+/// The system error codes of Windows, from `winerror.h`.
+#[cfg(windows)]
+mod win32 {
+    pub const ERROR_FILE_NOT_FOUND: i32 = 2;
+    pub const ERROR_PATH_NOT_FOUND: i32 = 3;
+    pub const ERROR_TOO_MANY_OPEN_FILES: i32 = 4;
+    pub const ERROR_ACCESS_DENIED: i32 = 5;
+    pub const ERROR_INVALID_HANDLE: i32 = 6;
+    pub const ERROR_NOT_ENOUGH_MEMORY: i32 = 8;
+    pub const ERROR_OUTOFMEMORY: i32 = 14;
+    pub const ERROR_NOT_SAME_DEVICE: i32 = 17;
+    pub const ERROR_WRITE_PROTECT: i32 = 19;
+    pub const ERROR_SHARING_VIOLATION: i32 = 32;
+    pub const ERROR_LOCK_VIOLATION: i32 = 33;
+    pub const ERROR_HANDLE_DISK_FULL: i32 = 39;
+    pub const ERROR_NOT_SUPPORTED: i32 = 50;
+    pub const ERROR_FILE_EXISTS: i32 = 80;
+    pub const ERROR_INVALID_PARAMETER: i32 = 87;
+    pub const ERROR_BROKEN_PIPE: i32 = 109;
+    pub const ERROR_DISK_FULL: i32 = 112;
+    pub const ERROR_CALL_NOT_IMPLEMENTED: i32 = 120;
+    pub const ERROR_INVALID_NAME: i32 = 123;
+    pub const ERROR_NEGATIVE_SEEK: i32 = 131;
+    pub const ERROR_DIR_NOT_EMPTY: i32 = 145;
+    pub const ERROR_BUSY: i32 = 170;
+    pub const ERROR_ALREADY_EXISTS: i32 = 183;
+    pub const ERROR_FILENAME_EXCED_RANGE: i32 = 206;
+    pub const ERROR_NO_DATA: i32 = 232;
+    pub const ERROR_DIRECTORY: i32 = 267;
+    pub const ERROR_PRIVILEGE_NOT_HELD: i32 = 1314;
+    pub const ERROR_CANT_RESOLVE_FILENAME: i32 = 1921;
+    pub const WSAEINTR: i32 = 10004;
+    pub const WSAEBADF: i32 = 10009;
+    pub const WSAEACCES: i32 = 10013;
+    pub const WSAEFAULT: i32 = 10014;
+    pub const WSAEINVAL: i32 = 10022;
+    pub const WSAEMFILE: i32 = 10024;
+    pub const WSAEWOULDBLOCK: i32 = 10035;
+    pub const WSAEINPROGRESS: i32 = 10036;
+    pub const WSAEALREADY: i32 = 10037;
+    pub const WSAENOTSOCK: i32 = 10038;
+    pub const WSAEDESTADDRREQ: i32 = 10039;
+    pub const WSAEMSGSIZE: i32 = 10040;
+    pub const WSAEPROTOTYPE: i32 = 10041;
+    pub const WSAENOPROTOOPT: i32 = 10042;
+    pub const WSAEPROTONOSUPPORT: i32 = 10043;
+    pub const WSAEOPNOTSUPP: i32 = 10045;
+    pub const WSAEAFNOSUPPORT: i32 = 10047;
+    pub const WSAEADDRINUSE: i32 = 10048;
+    pub const WSAEADDRNOTAVAIL: i32 = 10049;
+    pub const WSAENETDOWN: i32 = 10050;
+    pub const WSAENETUNREACH: i32 = 10051;
+    pub const WSAENETRESET: i32 = 10052;
+    pub const WSAECONNABORTED: i32 = 10053;
+    pub const WSAECONNRESET: i32 = 10054;
+    pub const WSAENOBUFS: i32 = 10055;
+    pub const WSAEISCONN: i32 = 10056;
+    pub const WSAENOTCONN: i32 = 10057;
+    pub const WSAETIMEDOUT: i32 = 10060;
+    pub const WSAECONNREFUSED: i32 = 10061;
+    pub const WSAELOOP: i32 = 10062;
+    pub const WSAENAMETOOLONG: i32 = 10063;
+    pub const WSAEHOSTUNREACH: i32 = 10065;
+    pub const WSAENOTEMPTY: i32 = 10066;
+    pub const WSAEDQUOT: i32 = 10069;
+    pub const WSAESTALE: i32 = 10070;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_kinds_are_translated() {
+        let table = [
+            (io::ErrorKind::NotFound, __WASI_ENOENT),
+            (io::ErrorKind::PermissionDenied, __WASI_EPERM),
+            (io::ErrorKind::AlreadyExists, __WASI_EEXIST),
+            (io::ErrorKind::WouldBlock, __WASI_EAGAIN),
+            (io::ErrorKind::InvalidInput, __WASI_EINVAL),
+            (io::ErrorKind::BrokenPipe, __WASI_EPIPE),
+            (io::ErrorKind::Interrupted, __WASI_EINTR),
+            (io::ErrorKind::Unsupported, __WASI_ENOTSUP),
+            (io::ErrorKind::OutOfMemory, __WASI_ENOMEM),
+            (io::ErrorKind::Other, __WASI_EIO),
+        ];
+        for (kind, errno) in table {
+            assert_eq!(error_kind_into_wasi_err(kind), errno, "{:?}", kind);
+            assert_eq!(io_error_into_wasi_err(&kind.into()), errno, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn error_kinds_agree_with_fs_errors() {
+        let table = [
+            io::ErrorKind::NotFound,
+            io::ErrorKind::PermissionDenied,
+            io::ErrorKind::ConnectionRefused,
+            io::ErrorKind::ConnectionReset,
+            io::ErrorKind::ConnectionAborted,
+            io::ErrorKind::NotConnected,
+            io::ErrorKind::AddrInUse,
+            io::ErrorKind::AddrNotAvailable,
+            io::ErrorKind::BrokenPipe,
+            io::ErrorKind::AlreadyExists,
+            io::ErrorKind::WouldBlock,
+            io::ErrorKind::InvalidInput,
+            io::ErrorKind::InvalidData,
+            io::ErrorKind::TimedOut,
+            io::ErrorKind::WriteZero,
+            io::ErrorKind::Interrupted,
+            io::ErrorKind::UnexpectedEof,
+            io::ErrorKind::Other,
+        ];
+        for kind in table {
+            assert_eq!(
+                error_kind_into_wasi_err(kind),
+                fs_error_into_wasi_err(io::Error::from(kind).into()),
+                "{:?}",
+                kind
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_errnos_are_translated() {
+        let table = [
+            (libc::ENOENT, __WASI_ENOENT),
+            (libc::EACCES, __WASI_EACCES),
+            (libc::EPERM, __WASI_EPERM),
+            (libc::ENOTDIR, __WASI_ENOTDIR),
+            (libc::EISDIR, __WASI_EISDIR),
+            (libc::ELOOP, __WASI_ELOOP),
+            (libc::ENAMETOOLONG, __WASI_ENAMETOOLONG),
+            (libc::EXDEV, __WASI_EXDEV),
+            (libc::EROFS, __WASI_EROFS),
+            (libc::ENOSPC, __WASI_ENOSPC),
+            (libc::EAGAIN, __WASI_EAGAIN),
+            (libc::EWOULDBLOCK, __WASI_EAGAIN),
+            (libc::ENOTSUP, __WASI_ENOTSUP),
+            (libc::EOPNOTSUPP, __WASI_ENOTSUP),
+        ];
+        for (errno, wasi_errno) in table {
+            assert_eq!(os_error_into_wasi_err(errno), Some(wasi_errno), "{}", errno);
+            let error = io::Error::from_raw_os_error(errno);
+            assert_eq!(io_error_into_wasi_err(&error), wasi_errno, "{}", error);
+        }
+        assert_eq!(os_error_into_wasi_err(0), None);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_error_codes_are_translated() {
+        let table = [
+            (2, __WASI_ENOENT),
+            (3, __WASI_ENOENT),
+            (5, __WASI_EACCES),
+            (6, __WASI_EBADF),
+            (17, __WASI_EXDEV),
+            (32, __WASI_EBUSY),
+            (80, __WASI_EEXIST),
+            (145, __WASI_ENOTEMPTY),
+            (183, __WASI_EEXIST),
+            (267, __WASI_ENOTDIR),
+            (10035, __WASI_EAGAIN),
+            (10054, __WASI_ECONNRESET),
+        ];
+        for (code, wasi_errno) in table {
+            assert_eq!(os_error_into_wasi_err(code), Some(wasi_errno), "{}", code);
+            let error = io::Error::from_raw_os_error(code);
+            assert_eq!(io_error_into_wasi_err(&error), wasi_errno, "{}", error);
+        }
+        assert_eq!(os_error_into_wasi_err(0), None);
+    }
+
+    #[test]
+    fn unknown_os_errors_fall_back_to_their_kind() {
+        let error = io::Error::new(io::ErrorKind::NotFound, "missing");
+        assert_eq!(io_error_into_wasi_err(&error), __WASI_ENOENT);
+    }
+}
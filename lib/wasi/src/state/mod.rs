@@ -16,10 +16,20 @@
 #![allow(clippy::cognitive_complexity, clippy::too_many_arguments)]
 
 mod builder;
+mod deterministic;
+mod errno;
+mod path_remap;
+mod shutdown;
 mod types;
+mod usage;
 
 pub use self::builder::*;
+pub use self::deterministic::DeterministicState;
+pub use self::errno::*;
+pub use self::path_remap::PathRemap;
+pub use self::shutdown::{WasiShutdown, SHUTDOWN_FD_ENV};
 pub use self::types::*;
+pub use self::usage::{WasiQuotas, WasiUsage};
 use crate::syscalls::types::*;
 use generational_arena::Arena;
 pub use generational_arena::Index as Inode;
@@ -170,6 +180,9 @@ pub struct WasiFs {
     pub orphan_fds: HashMap<Inode, InodeVal>,
     #[cfg_attr(feature = "enable-serde", serde(skip, default = "default_fs_backing"))]
     pub fs_backing: Box<dyn FileSystem>,
+    /// The absolute paths of the program rewritten before resolving them.
+    #[cfg_attr(feature = "enable-serde", serde(default))]
+    pub path_remaps: Vec<PathRemap>,
 }
 
 /// Returns the default filesystem backing
@@ -411,6 +424,7 @@ impl WasiFs {
             inode_counter: Cell::new(1024),
             orphan_fds: HashMap::new(),
             fs_backing,
+            path_remaps: Vec::new(),
         };
         wasi_fs.create_stdin();
         wasi_fs.create_stdout();
@@ -1045,6 +1059,14 @@ impl WasiFs {
         path: &str,
         follow_symlinks: bool,
     ) -> Result<Inode, __wasi_errno_t> {
+        if let Some(remapped) = self.remap_path(base, Path::new(path)) {
+            return self.get_inode_at_path_inner(
+                VIRTUAL_ROOT_FD,
+                &remapped.to_string_lossy(),
+                0,
+                follow_symlinks,
+            );
+        }
         self.get_inode_at_path_inner(base, path, 0, follow_symlinks)
     }
 
@@ -1056,6 +1078,11 @@ impl WasiFs {
         path: &Path,
         follow_symlinks: bool,
     ) -> Result<(Inode, String), __wasi_errno_t> {
+        let remapped = self.remap_path(base, path);
+        let (base, path) = match &remapped {
+            Some(remapped) => (VIRTUAL_ROOT_FD, remapped.as_path()),
+            None => (base, path),
+        };
         let mut parent_dir = std::path::PathBuf::new();
         let mut components = path.components().rev();
         let new_entity_name = components
@@ -1067,7 +1094,7 @@ impl WasiFs {
         for comp in components.rev() {
             parent_dir.push(comp);
         }
-        self.get_inode_at_path(base, &parent_dir.to_string_lossy(), follow_symlinks)
+        self.get_inode_at_path_inner(base, &parent_dir.to_string_lossy(), 0, follow_symlinks)
             .map(|v| (v, new_entity_name))
     }
 
@@ -1191,7 +1218,7 @@ impl WasiFs {
                 match &mut inode.kind {
                     Kind::File { handle, .. } => {
                         if let Some(file) = handle {
-                            file.flush().map_err(|_| __WASI_EIO)?
+                            file.flush().map_err(|e| io_error_into_wasi_err(&e))?
                         } else {
                             return Err(__WASI_EIO);
                         }
@@ -1561,6 +1588,16 @@ pub struct WasiState {
     pub fs: WasiFs,
     pub args: Vec<Vec<u8>>,
     pub envs: Vec<Vec<u8>>,
+    /// The virtual clocks and seeded randomness the program runs with,
+    /// when it must run deterministically.
+    pub deterministic: Option<DeterministicState>,
+    /// What the program used so far.
+    pub usage: WasiUsage,
+    /// The limits on what the program may use.
+    pub quotas: WasiQuotas,
+    /// The signal through which the host asks the program to terminate.
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub shutdown: Option<WasiShutdown>,
 }
 
 impl WasiState {
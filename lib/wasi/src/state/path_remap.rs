@@ -0,0 +1,90 @@
+use super::{Kind, WasiFs};
+use crate::syscalls::types::*;
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Rewrites the absolute paths a program requests under `guest_prefix`,
+/// such as `/etc/ssl`, to `target`, a path from the virtual root starting
+/// with the alias of a preopened directory, such as `certs`.
+///
+/// It's for programs hardcoding absolute paths, which can then be given
+/// a directory of the host without preopening it at that path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct PathRemap {
+    /// The absolute path of the program to rewrite.
+    pub guest_prefix: PathBuf,
+    /// The path from the virtual root it's rewritten to.
+    pub target: PathBuf,
+}
+
+impl WasiFs {
+    /// Rewrites `path`, requested from the directory `base`, if it's
+    /// under the prefix of a remap, giving the path to resolve from the
+    /// virtual root instead. The longest matching prefix wins.
+    pub(crate) fn remap_path(&self, base: __wasi_fd_t, path: &Path) -> Option<PathBuf> {
+        if self.path_remaps.is_empty() {
+            return None;
+        }
+        let inode = &self.inodes[self.fd_map.get(&base)?.inode];
+        // The program resolves an absolute path from the preopened
+        // directory whose name is its longest prefix, or from the root.
+        let guest_path = match &inode.kind {
+            Kind::Root { .. } => Path::new("/").join(path),
+            Kind::Dir { .. } if inode.is_preopened && inode.name.starts_with('/') => {
+                Path::new(&inode.name).join(path)
+            }
+            _ => return None,
+        };
+        let (remap, rest) = self
+            .path_remaps
+            .iter()
+            .filter_map(|remap| Some((remap, guest_path.strip_prefix(&remap.guest_prefix).ok()?)))
+            .max_by_key(|(remap, _)| remap.guest_prefix.components().count())?;
+        let remapped = remap.target.join(rest);
+        debug!(
+            "remapped {} to {}",
+            guest_path.display(),
+            remapped.display()
+        );
+        Some(remapped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::{WasiState, VIRTUAL_ROOT_FD};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn remaps_the_longest_prefix() {
+        let state = WasiState::new("test_prog")
+            .remap_path("/etc", "etc")
+            .unwrap()
+            .remap_path("/etc/ssl", "certs/ssl")
+            .unwrap()
+            .build()
+            .unwrap();
+        let remap = |path: &str| state.fs.remap_path(VIRTUAL_ROOT_FD, Path::new(path));
+
+        assert_eq!(
+            remap("etc/ssl/cert.pem"),
+            Some(PathBuf::from("certs/ssl/cert.pem"))
+        );
+        assert_eq!(remap("etc/hosts"), Some(PathBuf::from("etc/hosts")));
+        assert_eq!(remap("etcetera"), None);
+        assert_eq!(remap("usr/share"), None);
+    }
+
+    #[test]
+    fn rejects_malformed_remaps() {
+        assert!(WasiState::new("test_prog")
+            .remap_path("etc", "etc")
+            .is_err());
+        assert!(WasiState::new("test_prog")
+            .remap_path("/etc", "/etc")
+            .is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use super::{InodeVal, Kind, WasiFs};
+use crate::syscalls::types::*;
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Seek, Write};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use wasmer_vfs::{FsError, VirtualFile};
+
+/// The environment variable holding the fd of the shutdown file.
+pub const SHUTDOWN_FD_ENV: &str = "WASMER_SHUTDOWN_FD";
+
+const RUNNING: u8 = 0;
+const REQUESTED: u8 = 1;
+const TERMINATED: u8 = 2;
+
+/// Lets the host ask a WASI program to terminate, and then force it to.
+///
+/// The program sees the request through the file whose fd is in the
+/// `WASMER_SHUTDOWN_FD` environment variable: `poll_oneoff` reports it
+/// readable once a shutdown is requested, and reading it gives `1`, or
+/// nothing before. The program is expected to finish what it's doing and
+/// exit.
+///
+/// Once the program is terminated, its next WASI call traps with
+/// [`WasiError::Terminated`](crate::WasiError::Terminated). A program
+/// computing without calling WASI isn't stopped by this, that's what the
+/// metering middleware is for.
+#[derive(Debug, Clone, Default)]
+pub struct WasiShutdown {
+    state: Arc<AtomicU8>,
+}
+
+impl WasiShutdown {
+    /// Creates a shutdown signal, not requested yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks the program to terminate.
+    pub fn request(&self) {
+        let _ = self
+            .state
+            .compare_exchange(RUNNING, REQUESTED, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    /// Asks the program to terminate, and terminates it once `grace`
+    /// has passed.
+    #[cfg(feature = "sys")]
+    pub fn request_with_grace_period(&self, grace: std::time::Duration) {
+        self.request();
+        let shutdown = self.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(grace);
+            shutdown.terminate();
+        });
+    }
+
+    /// Terminates the program, requested or not.
+    pub fn terminate(&self) {
+        self.state.store(TERMINATED, Ordering::SeqCst);
+    }
+
+    /// Whether the program was asked to terminate.
+    pub fn is_requested(&self) -> bool {
+        self.state.load(Ordering::SeqCst) >= REQUESTED
+    }
+
+    /// Whether the program is terminated.
+    pub fn is_terminated(&self) -> bool {
+        self.state.load(Ordering::SeqCst) == TERMINATED
+    }
+}
+
+/// The file through which the program sees a shutdown request.
+///
+/// A frozen [`WasiState`](super::WasiState) is unfrozen with a new
+/// shutdown signal, not requested.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub(crate) struct ShutdownFile {
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    shutdown: WasiShutdown,
+}
+
+impl Read for ShutdownFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match buf.first_mut() {
+            Some(byte) if self.shutdown.is_requested() => {
+                *byte = b'1';
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+impl Write for ShutdownFile {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "can not write to the shutdown file",
+        ))
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for ShutdownFile {
+    fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+        Ok(0)
+    }
+}
+
+#[cfg_attr(feature = "enable-serde", typetag::serde)]
+impl VirtualFile for ShutdownFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        0
+    }
+    fn set_len(&mut self, _len: u64) -> Result<(), FsError> {
+        Err(FsError::PermissionDenied)
+    }
+    fn unlink(&mut self) -> Result<(), FsError> {
+        Ok(())
+    }
+    fn bytes_available(&self) -> Result<usize, FsError> {
+        Ok(self.shutdown.is_requested() as usize)
+    }
+}
+
+impl WasiFs {
+    /// Opens the file through which the program sees a shutdown request.
+    pub(crate) fn open_shutdown_file(
+        &mut self,
+        shutdown: WasiShutdown,
+    ) -> Result<__wasi_fd_t, __wasi_errno_t> {
+        let stat = __wasi_filestat_t {
+            st_filetype: __WASI_FILETYPE_CHARACTER_DEVICE,
+            st_ino: self.get_next_inode_index(),
+            ..__wasi_filestat_t::default()
+        };
+        let inode = self.inodes.insert(InodeVal {
+            stat,
+            is_preopened: true,
+            name: "shutdown".to_string(),
+            kind: Kind::File {
+                handle: Some(Box::new(ShutdownFile { shutdown })),
+                path: "".into(),
+                fd: None,
+            },
+        });
+        self.create_fd(
+            __WASI_RIGHT_FD_READ | __WASI_RIGHT_POLL_FD_READWRITE,
+            0,
+            0,
+            0,
+            inode,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shutdown_file() {
+        let shutdown = WasiShutdown::new();
+        let mut file = ShutdownFile {
+            shutdown: shutdown.clone(),
+        };
+        let mut buf = [0; 4];
+
+        assert_eq!(file.bytes_available(), Ok(0));
+        assert_eq!(file.read(&mut buf).unwrap(), 0);
+
+        shutdown.request();
+        assert!(shutdown.is_requested());
+        assert!(!shutdown.is_terminated());
+        assert_eq!(file.bytes_available(), Ok(1));
+        assert_eq!(file.read(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], b'1');
+
+        shutdown.terminate();
+        shutdown.request();
+        assert!(shutdown.is_requested());
+        assert!(shutdown.is_terminated());
+    }
+}
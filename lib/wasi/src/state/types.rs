@@ -16,61 +16,6 @@ pub use wasmer_vfs::mem_fs::{Stderr, Stdin, Stdout};
 
 use wasmer_vfs::{FsError, VirtualFile};
 
-pub fn fs_error_from_wasi_err(err: __wasi_errno_t) -> FsError {
-    match err {
-        __WASI_EBADF => FsError::InvalidFd,
-        __WASI_EEXIST => FsError::AlreadyExists,
-        __WASI_EIO => FsError::IOError,
-        __WASI_EADDRINUSE => FsError::AddressInUse,
-        __WASI_EADDRNOTAVAIL => FsError::AddressNotAvailable,
-        __WASI_EPIPE => FsError::BrokenPipe,
-        __WASI_ECONNABORTED => FsError::ConnectionAborted,
-        __WASI_ECONNREFUSED => FsError::ConnectionRefused,
-        __WASI_ECONNRESET => FsError::ConnectionReset,
-        __WASI_EINTR => FsError::Interrupted,
-        __WASI_EINVAL => FsError::InvalidInput,
-        __WASI_ENOTCONN => FsError::NotConnected,
-        __WASI_ENODEV => FsError::NoDevice,
-        __WASI_ENOENT => FsError::EntityNotFound,
-        __WASI_EPERM => FsError::PermissionDenied,
-        __WASI_ETIMEDOUT => FsError::TimedOut,
-        __WASI_EPROTO => FsError::UnexpectedEof,
-        __WASI_EAGAIN => FsError::WouldBlock,
-        __WASI_ENOSPC => FsError::WriteZero,
-        __WASI_ENOTEMPTY => FsError::DirectoryNotEmpty,
-        _ => FsError::UnknownError,
-    }
-}
-
-pub fn fs_error_into_wasi_err(fs_error: FsError) -> __wasi_errno_t {
-    match fs_error {
-        FsError::AlreadyExists => __WASI_EEXIST,
-        FsError::AddressInUse => __WASI_EADDRINUSE,
-        FsError::AddressNotAvailable => __WASI_EADDRNOTAVAIL,
-        FsError::BaseNotDirectory => __WASI_ENOTDIR,
-        FsError::BrokenPipe => __WASI_EPIPE,
-        FsError::ConnectionAborted => __WASI_ECONNABORTED,
-        FsError::ConnectionRefused => __WASI_ECONNREFUSED,
-        FsError::ConnectionReset => __WASI_ECONNRESET,
-        FsError::Interrupted => __WASI_EINTR,
-        FsError::InvalidData => __WASI_EIO,
-        FsError::InvalidFd => __WASI_EBADF,
-        FsError::InvalidInput => __WASI_EINVAL,
-        FsError::IOError => __WASI_EIO,
-        FsError::NoDevice => __WASI_ENODEV,
-        FsError::NotAFile => __WASI_EINVAL,
-        FsError::NotConnected => __WASI_ENOTCONN,
-        FsError::EntityNotFound => __WASI_ENOENT,
-        FsError::PermissionDenied => __WASI_EPERM,
-        FsError::TimedOut => __WASI_ETIMEDOUT,
-        FsError::UnexpectedEof => __WASI_EPROTO,
-        FsError::WouldBlock => __WASI_EAGAIN,
-        FsError::WriteZero => __WASI_ENOSPC,
-        FsError::DirectoryNotEmpty => __WASI_ENOTEMPTY,
-        FsError::Lock | FsError::UnknownError => __WASI_EIO,
-    }
-}
-
 #[derive(Debug, Clone)]
 pub enum PollEvent {
     /// Data available to read
@@ -194,28 +139,44 @@ pub(crate) fn poll(
     if !(selfs.len() == events.len() && events.len() == seen_events.len()) {
         return Err(FsError::InvalidInput);
     }
-    let mut fds = selfs
+    let (host_files, virtual_files): (Vec<_>, Vec<_>) =
+        (0..selfs.len()).partition(|&i| selfs[i].get_fd().is_some());
+    let mut fds = host_files
         .iter()
-        .enumerate()
-        .filter_map(|(i, s)| s.get_fd().map(|rfd| (i, rfd)))
-        .map(|(i, host_fd)| libc::pollfd {
-            fd: host_fd.try_into().unwrap(),
+        .map(|&i| libc::pollfd {
+            fd: selfs[i].get_fd().unwrap().try_into().unwrap(),
             events: poll_event_set_to_platform_poll_events(events[i]),
             revents: 0,
         })
         .collect::<Vec<_>>();
-    let result = unsafe { libc::poll(fds.as_mut_ptr(), selfs.len() as _, 1) };
+    let result = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, 1) };
 
     if result < 0 {
         // TODO: check errno and return value
         return Err(FsError::IOError);
     }
     // convert result and write back values
-    for (i, fd) in fds.into_iter().enumerate() {
+    for (&i, fd) in host_files.iter().zip(fds) {
         seen_events[i] = platform_poll_events_to_pollevent_set(fd.revents);
     }
-    // unwrap is safe because we check for negative values above
-    Ok(result.try_into().unwrap())
+    // files without a host fd are readable when they have bytes, and
+    // always writable
+    let mut ready: u32 = result.try_into().unwrap();
+    for i in virtual_files {
+        let mut seen = PollEventBuilder::new();
+        for event in iterate_poll_events(events[i]) {
+            match event {
+                PollEvent::PollIn if selfs[i].bytes_available()? > 0 => seen = seen.add(event),
+                PollEvent::PollOut => seen = seen.add(event),
+                _ => (),
+            }
+        }
+        seen_events[i] = seen.build();
+        if seen_events[i] != 0 {
+            ready += 1;
+        }
+    }
+    Ok(ready)
 }
 
 #[cfg(not(unix))]
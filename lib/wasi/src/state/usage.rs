@@ -0,0 +1,140 @@
+use crate::syscalls::types::*;
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+
+/// What a WASI program used so far, for billing or to spot abuses.
+///
+/// It's read from [`WasiState::usage`](super::WasiState::usage).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct WasiUsage {
+    /// The WASI functions called.
+    pub syscalls: u64,
+    /// The bytes read with `fd_read` and `fd_pread`.
+    pub bytes_read: u64,
+    /// The bytes written with `fd_write` and `fd_pwrite`.
+    pub bytes_written: u64,
+    /// The files and directories opened with `path_open`.
+    pub files_opened: u64,
+}
+
+/// The limits on what a WASI program may use, none by default.
+///
+/// Once a quota is reached, the WASI functions fail with `EDQUOT`, or
+/// `ENOSPC` for writes, and the program carries on with the error.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct WasiQuotas {
+    /// The WASI functions which may be called. `proc_exit` is always
+    /// allowed.
+    pub max_syscalls: Option<u64>,
+    /// The bytes which may be read. A read is refused once the quota
+    /// is reached, and may read past it otherwise.
+    pub max_bytes_read: Option<u64>,
+    /// The bytes which may be written. A write is refused when all of
+    /// its bytes would not fit.
+    pub max_bytes_written: Option<u64>,
+    /// The files and directories which may be opened.
+    pub max_files_opened: Option<u64>,
+}
+
+impl WasiUsage {
+    /// Counts a call to a WASI function.
+    pub(crate) fn syscall(&mut self, quotas: &WasiQuotas) -> Result<(), __wasi_errno_t> {
+        if reached(self.syscalls, quotas.max_syscalls) {
+            return Err(__WASI_EDQUOT);
+        }
+        self.syscalls += 1;
+        Ok(())
+    }
+
+    /// Checks that a read may happen.
+    pub(crate) fn check_read(&self, quotas: &WasiQuotas) -> Result<(), __wasi_errno_t> {
+        if reached(self.bytes_read, quotas.max_bytes_read) {
+            return Err(__WASI_EDQUOT);
+        }
+        Ok(())
+    }
+
+    /// Counts the bytes of a read.
+    pub(crate) fn read(&mut self, bytes: u32) {
+        self.bytes_read += u64::from(bytes);
+    }
+
+    /// Checks that a write of `bytes` may happen.
+    pub(crate) fn check_write(
+        &self,
+        quotas: &WasiQuotas,
+        bytes: u64,
+    ) -> Result<(), __wasi_errno_t> {
+        match quotas.max_bytes_written {
+            Some(max) if self.bytes_written.saturating_add(bytes) > max => Err(__WASI_ENOSPC),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts the bytes of a write.
+    pub(crate) fn written(&mut self, bytes: u32) {
+        self.bytes_written += u64::from(bytes);
+    }
+
+    /// Checks that a file or directory may be opened.
+    pub(crate) fn check_open(&self, quotas: &WasiQuotas) -> Result<(), __wasi_errno_t> {
+        if reached(self.files_opened, quotas.max_files_opened) {
+            return Err(__WASI_EDQUOT);
+        }
+        Ok(())
+    }
+
+    /// Counts the opening of a file or directory.
+    pub(crate) fn opened(&mut self) {
+        self.files_opened += 1;
+    }
+}
+
+fn reached(used: u64, max: Option<u64>) -> bool {
+    max.map_or(false, |max| used >= max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enforces_quotas() {
+        let quotas = WasiQuotas {
+            max_syscalls: Some(2),
+            max_bytes_read: Some(10),
+            max_bytes_written: Some(10),
+            max_files_opened: Some(1),
+        };
+        let mut usage = WasiUsage::default();
+
+        assert_eq!(usage.syscall(&quotas), Ok(()));
+        assert_eq!(usage.syscall(&quotas), Ok(()));
+        assert_eq!(usage.syscall(&quotas), Err(__WASI_EDQUOT));
+        assert_eq!(usage.syscalls, 2);
+
+        assert_eq!(usage.check_read(&quotas), Ok(()));
+        usage.read(12);
+        assert_eq!(usage.check_read(&quotas), Err(__WASI_EDQUOT));
+
+        assert_eq!(usage.check_write(&quotas, 10), Ok(()));
+        assert_eq!(usage.check_write(&quotas, 11), Err(__WASI_ENOSPC));
+        usage.written(4);
+        assert_eq!(usage.check_write(&quotas, 7), Err(__WASI_ENOSPC));
+
+        assert_eq!(usage.check_open(&quotas), Ok(()));
+        usage.opened();
+        assert_eq!(usage.check_open(&quotas), Err(__WASI_EDQUOT));
+
+        let mut unlimited = WasiUsage::default();
+        for _ in 0..100 {
+            assert_eq!(unlimited.syscall(&WasiQuotas::default()), Ok(()));
+        }
+        assert_eq!(
+            unlimited.check_write(&WasiQuotas::default(), u64::MAX),
+            Ok(())
+        );
+    }
+}
@@ -22,7 +22,7 @@ use self::types::*;
 use crate::{
     ptr::{Array, WasmPtr},
     state::{
-        self, fs_error_into_wasi_err, iterate_poll_events, poll,
+        self, fs_error_into_wasi_err, io_error_into_wasi_err, iterate_poll_events, poll,
         virtual_file_type_to_wasi_file_type, Fd, Inode, InodeVal, Kind, PollEvent,
         PollEventBuilder, WasiState, MAX_SYMLINKS,
     },
@@ -33,7 +33,7 @@ use std::convert::{Infallible, TryInto};
 use std::io::{self, Read, Seek, Write};
 use tracing::{debug, trace};
 use wasmer::{Memory, RuntimeError, Value, WasmCell};
-use wasmer_vfs::{FsError, VirtualFile};
+use wasmer_vfs::{Advice, FsError, VirtualFile};
 
 #[cfg(any(
     target_os = "freebsd",
@@ -60,7 +60,7 @@ fn write_bytes_inner<T: Write>(
         let bytes = WasmPtr::<u8, Array>::new(iov_inner.buf).deref(memory, 0, iov_inner.buf_len)?;
         write_loc
             .write_all(&bytes.iter().map(|b_cell| b_cell.get()).collect::<Vec<u8>>())
-            .map_err(|_| __WASI_EIO)?;
+            .map_err(|e| io_error_into_wasi_err(&e))?;
 
         // TODO: handle failure more accurately
         bytes_written += iov_inner.buf_len;
@@ -78,6 +78,14 @@ fn write_bytes<T: Write>(
     result
 }
 
+/// The bytes a write of `iovs_arr_cell` takes.
+fn iovs_size(iovs_arr_cell: &[WasmCell<__wasi_ciovec_t>]) -> u64 {
+    iovs_arr_cell
+        .iter()
+        .map(|iov| u64::from(iov.get().buf_len))
+        .sum()
+}
+
 fn read_bytes<T: Read>(
     mut reader: T,
     memory: &Memory,
@@ -93,7 +101,9 @@ fn read_bytes<T: Read>(
         let iov_inner = iov.get();
         raw_bytes.clear();
         raw_bytes.resize(iov_inner.buf_len as usize, 0);
-        bytes_read += reader.read(&mut raw_bytes).map_err(|_| __WASI_EIO)? as u32;
+        bytes_read += reader
+            .read(&mut raw_bytes)
+            .map_err(|e| io_error_into_wasi_err(&e))? as u32;
         unsafe {
             memory
                 .uint8view()
@@ -146,6 +156,56 @@ fn get_current_time_in_nanos() -> Result<__wasi_timestamp_t, __wasi_errno_t> {
     Ok(duration.as_nanos() as __wasi_timestamp_t)
 }
 
+/// Sets the times of `inode` selected by `fst_flags`, for
+/// `fd_filestat_set_times` and `path_filestat_set_times`, on the host
+/// file too if there's one.
+fn set_inode_times(
+    inode: &mut InodeVal,
+    st_atim: __wasi_timestamp_t,
+    st_mtim: __wasi_timestamp_t,
+    fst_flags: __wasi_fstflags_t,
+) -> Result<(), __wasi_errno_t> {
+    let time = |set: __wasi_fstflags_t, set_now: __wasi_fstflags_t, time| {
+        if fst_flags & set != 0 {
+            if fst_flags & set_now != 0 {
+                return Err(__WASI_EINVAL);
+            }
+            Ok(Some(time))
+        } else if fst_flags & set_now != 0 {
+            get_current_time_in_nanos().map(Some)
+        } else {
+            Ok(None)
+        }
+    };
+    let accessed = time(
+        __WASI_FILESTAT_SET_ATIM,
+        __WASI_FILESTAT_SET_ATIM_NOW,
+        st_atim,
+    )?;
+    let modified = time(
+        __WASI_FILESTAT_SET_MTIM,
+        __WASI_FILESTAT_SET_MTIM_NOW,
+        st_mtim,
+    )?;
+
+    if let Kind::File {
+        handle: Some(handle),
+        ..
+    } = &mut inode.kind
+    {
+        handle
+            .set_times(accessed, modified)
+            .map_err(fs_error_into_wasi_err)?;
+    }
+    if let Some(accessed) = accessed {
+        inode.stat.st_atim = accessed;
+    }
+    if let Some(modified) = modified {
+        inode.stat.st_mtim = modified;
+    }
+    Ok(())
+}
+
 /// ### `args_get()`
 /// Read command-line argument data.
 /// The sizes of the buffers should match that returned by [`args_sizes_get()`](#args_sizes_get).
@@ -160,6 +220,7 @@ pub fn args_get(
     argv: WasmPtr<WasmPtr<u8, Array>, Array>,
     argv_buf: WasmPtr<u8, Array>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::args_get");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
@@ -191,6 +252,7 @@ pub fn args_sizes_get(
     argc: WasmPtr<u32>,
     argv_buf_size: WasmPtr<u32>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::args_sizes_get");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
@@ -220,10 +282,15 @@ pub fn clock_res_get(
     clock_id: __wasi_clockid_t,
     resolution: WasmPtr<__wasi_timestamp_t>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::clock_res_get");
     let memory = env.memory();
 
     let out_addr = wasi_try!(resolution.deref(memory));
+    if let Some(deterministic) = &env.state().deterministic {
+        out_addr.set(wasi_try!(deterministic.clock_res_get(clock_id)));
+        return __WASI_ESUCCESS;
+    }
     platform_clock_res_get(clock_id, out_addr)
 }
 
@@ -243,6 +310,7 @@ pub fn clock_time_get(
     precision: __wasi_timestamp_t,
     time: WasmPtr<__wasi_timestamp_t>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!(
         "wasi::clock_time_get clock_id: {}, precision: {}",
         clock_id, precision
@@ -250,6 +318,10 @@ pub fn clock_time_get(
     let memory = env.memory();
 
     let out_addr = wasi_try!(time.deref(memory));
+    if let Some(deterministic) = &mut env.state().deterministic {
+        out_addr.set(wasi_try!(deterministic.clock_time_get(clock_id)));
+        return __WASI_ESUCCESS;
+    }
     let result = platform_clock_time_get(clock_id, precision, out_addr);
     debug!(
         "time: {} => {}",
@@ -272,6 +344,7 @@ pub fn environ_get(
     environ: WasmPtr<WasmPtr<u8, Array>, Array>,
     environ_buf: WasmPtr<u8, Array>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!(
         "wasi::environ_get. Environ: {:?}, environ_buf: {:?}",
         environ, environ_buf
@@ -294,6 +367,7 @@ pub fn environ_sizes_get(
     environ_count: WasmPtr<u32>,
     environ_buf_size: WasmPtr<u32>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::environ_sizes_get");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
@@ -331,10 +405,37 @@ pub fn fd_advise(
     len: __wasi_filesize_t,
     advice: __wasi_advice_t,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_advise: fd={}", fd);
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    let fd_entry = wasi_try!(state.fs.get_fd(fd));
+    let inode = fd_entry.inode;
+
+    if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_ADVISE) {
+        return __WASI_EACCES;
+    }
+    let advice = match advice {
+        __WASI_ADVICE_NORMAL => Advice::Normal,
+        __WASI_ADVICE_SEQUENTIAL => Advice::Sequential,
+        __WASI_ADVICE_RANDOM => Advice::Random,
+        __WASI_ADVICE_WILLNEED => Advice::WillNeed,
+        __WASI_ADVICE_DONTNEED => Advice::DontNeed,
+        __WASI_ADVICE_NOREUSE => Advice::NoReuse,
+        _ => return __WASI_EINVAL,
+    };
+
+    // The advice is only a hint: it's passed on to the host for its files,
+    // and ignored otherwise.
+    if let Kind::File {
+        handle: Some(handle),
+        ..
+    } = &mut state.fs.inodes[inode].kind
+    {
+        wasi_try!(handle
+            .advise(offset, len, advice)
+            .map_err(fs_error_into_wasi_err));
+    }
 
-    // this is used for our own benefit, so just returning success is a valid
-    // implementation for now
     __WASI_ESUCCESS
 }
 
@@ -353,6 +454,7 @@ pub fn fd_allocate(
     offset: __wasi_filesize_t,
     len: __wasi_filesize_t,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_allocate");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
@@ -361,22 +463,27 @@ pub fn fd_allocate(
     if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_ALLOCATE) {
         return __WASI_EACCES;
     }
-    let new_size = wasi_try!(offset.checked_add(len), __WASI_EINVAL);
+    let end = wasi_try!(offset.checked_add(len), __WASI_EINVAL);
 
-    match &mut state.fs.inodes[inode].kind {
+    // The file is only ever grown, never shrunk.
+    let new_size = match &mut state.fs.inodes[inode].kind {
         Kind::File { handle, .. } => {
             if let Some(handle) = handle {
-                wasi_try!(handle.set_len(new_size).map_err(fs_error_into_wasi_err));
+                wasi_try!(handle.allocate(offset, len).map_err(fs_error_into_wasi_err));
+                handle.size()
             } else {
                 return __WASI_EBADF;
             }
         }
         Kind::Buffer { buffer } => {
-            buffer.resize(new_size as usize, 0);
+            if end as usize > buffer.len() {
+                buffer.resize(end as usize, 0);
+            }
+            buffer.len() as __wasi_filesize_t
         }
         Kind::Symlink { .. } => return __WASI_EBADF,
         Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
-    }
+    };
     state.fs.inodes[inode].stat.st_size = new_size;
     debug!("New file size: {}", new_size);
 
@@ -394,6 +501,7 @@ pub fn fd_allocate(
 /// - `__WASI_EBADF`
 ///     If `fd` is invalid or not open
 pub fn fd_close(env: &WasiEnv, fd: __wasi_fd_t) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_close: fd={}", fd);
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
@@ -410,6 +518,7 @@ pub fn fd_close(env: &WasiEnv, fd: __wasi_fd_t) -> __wasi_errno_t {
 /// - `__wasi_fd_t fd`
 ///     The file descriptor to sync
 pub fn fd_datasync(env: &WasiEnv, fd: __wasi_fd_t) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_datasync");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
@@ -437,6 +546,7 @@ pub fn fd_fdstat_get(
     fd: __wasi_fd_t,
     buf_ptr: WasmPtr<__wasi_fdstat_t>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!(
         "wasi::fd_fdstat_get: fd={}, buf_ptr={}",
         fd,
@@ -465,6 +575,7 @@ pub fn fd_fdstat_set_flags(
     fd: __wasi_fd_t,
     flags: __wasi_fdflags_t,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_fdstat_set_flags");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
@@ -472,7 +583,18 @@ pub fn fd_fdstat_set_flags(
     if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_FDSTAT_SET_FLAGS) {
         return __WASI_EACCES;
     }
+    if flags
+        & !(__WASI_FDFLAG_APPEND
+            | __WASI_FDFLAG_DSYNC
+            | __WASI_FDFLAG_NONBLOCK
+            | __WASI_FDFLAG_RSYNC
+            | __WASI_FDFLAG_SYNC)
+        != 0
+    {
+        return __WASI_EINVAL;
+    }
 
+    // `fd_write` reads the flags, appending when asked to.
     fd_entry.flags = flags;
     __WASI_ESUCCESS
 }
@@ -492,6 +614,7 @@ pub fn fd_fdstat_set_rights(
     fs_rights_base: __wasi_rights_t,
     fs_rights_inheriting: __wasi_rights_t,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_fdstat_set_rights");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
@@ -522,6 +645,7 @@ pub fn fd_filestat_get(
     fd: __wasi_fd_t,
     buf: WasmPtr<__wasi_filestat_t>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_filestat_get");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
@@ -549,6 +673,7 @@ pub fn fd_filestat_set_size(
     fd: __wasi_fd_t,
     st_size: __wasi_filesize_t,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_filestat_set_size");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
@@ -593,6 +718,7 @@ pub fn fd_filestat_set_times(
     st_mtim: __wasi_timestamp_t,
     fst_flags: __wasi_fstflags_t,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_filestat_set_times");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
@@ -601,33 +727,13 @@ pub fn fd_filestat_set_times(
         return __WASI_EACCES;
     }
 
-    if (fst_flags & __WASI_FILESTAT_SET_ATIM != 0 && fst_flags & __WASI_FILESTAT_SET_ATIM_NOW != 0)
-        || (fst_flags & __WASI_FILESTAT_SET_MTIM != 0
-            && fst_flags & __WASI_FILESTAT_SET_MTIM_NOW != 0)
-    {
-        return __WASI_EINVAL;
-    }
-
     let inode_idx = fd_entry.inode;
-    let inode = &mut state.fs.inodes[inode_idx];
-
-    if fst_flags & __WASI_FILESTAT_SET_ATIM != 0 || fst_flags & __WASI_FILESTAT_SET_ATIM_NOW != 0 {
-        let time_to_set = if fst_flags & __WASI_FILESTAT_SET_ATIM != 0 {
-            st_atim
-        } else {
-            wasi_try!(get_current_time_in_nanos())
-        };
-        inode.stat.st_atim = time_to_set;
-    }
-
-    if fst_flags & __WASI_FILESTAT_SET_MTIM != 0 || fst_flags & __WASI_FILESTAT_SET_MTIM_NOW != 0 {
-        let time_to_set = if fst_flags & __WASI_FILESTAT_SET_MTIM != 0 {
-            st_mtim
-        } else {
-            wasi_try!(get_current_time_in_nanos())
-        };
-        inode.stat.st_mtim = time_to_set;
-    }
+    wasi_try!(set_inode_times(
+        &mut state.fs.inodes[inode_idx],
+        st_atim,
+        st_mtim,
+        fst_flags
+    ));
 
     __WASI_ESUCCESS
 }
@@ -655,11 +761,13 @@ pub fn fd_pread(
     offset: __wasi_filesize_t,
     nread: WasmPtr<u32>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_pread: fd={}, offset={}", fd, offset);
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let iov_cells = wasi_try!(iovs.deref(memory, 0, iovs_len));
     let nread_cell = wasi_try!(nread.deref(memory));
+    wasi_try!(state.usage.check_read(&state.quotas));
 
     let bytes_read = match fd {
         __WASI_STDIN_FILENO => {
@@ -707,6 +815,7 @@ pub fn fd_pread(
         }
     };
 
+    state.usage.read(bytes_read);
     nread_cell.set(bytes_read);
     debug!("Success: {} bytes read", bytes_read);
     __WASI_ESUCCESS
@@ -725,6 +834,7 @@ pub fn fd_prestat_get(
     fd: __wasi_fd_t,
     buf: WasmPtr<__wasi_prestat_t>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_prestat_get: fd={}", fd);
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
@@ -741,6 +851,7 @@ pub fn fd_prestat_dir_name(
     path: WasmPtr<u8, Array>,
     path_len: u32,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!(
         "wasi::fd_prestat_dir_name: fd={}, path_len={}",
         fd, path_len
@@ -803,11 +914,15 @@ pub fn fd_pwrite(
     offset: __wasi_filesize_t,
     nwritten: WasmPtr<u32>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_pwrite");
     // TODO: refactor, this is just copied from `fd_write`...
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let iovs_arr_cell = wasi_try!(iovs.deref(memory, 0, iovs_len));
     let nwritten_cell = wasi_try!(nwritten.deref(memory));
+    wasi_try!(state
+        .usage
+        .check_write(&state.quotas, iovs_size(&iovs_arr_cell)));
 
     let bytes_written = match fd {
         __WASI_STDIN_FILENO => return __WASI_EINVAL,
@@ -866,6 +981,7 @@ pub fn fd_pwrite(
         }
     };
 
+    state.usage.written(bytes_written);
     nwritten_cell.set(bytes_written);
 
     __WASI_ESUCCESS
@@ -890,11 +1006,13 @@ pub fn fd_read(
     iovs_len: u32,
     nread: WasmPtr<u32>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_read: fd={}", fd);
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let iovs_arr_cell = wasi_try!(iovs.deref(memory, 0, iovs_len));
     let nread_cell = wasi_try!(nread.deref(memory));
+    wasi_try!(state.usage.check_read(&state.quotas));
 
     let bytes_read = match fd {
         __WASI_STDIN_FILENO => {
@@ -946,6 +1064,7 @@ pub fn fd_read(
         }
     };
 
+    state.usage.read(bytes_read);
     nread_cell.set(bytes_read);
 
     __WASI_ESUCCESS
@@ -974,6 +1093,7 @@ pub fn fd_readdir(
     cookie: __wasi_dircookie_t,
     bufused: WasmPtr<u32>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_readdir");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     // TODO: figure out how this is supposed to work;
@@ -994,14 +1114,14 @@ pub fn fd_readdir(
             // maintain consistent order via lexacographic sorting
             let fs_info = wasi_try!(wasi_try!(state.fs_read_dir(path))
                 .collect::<Result<Vec<_>, _>>()
-                .map_err(|_| __WASI_EIO));
+                .map_err(fs_error_into_wasi_err));
             let mut entry_vec = wasi_try!(fs_info
                 .into_iter()
                 .map(|entry| {
                     let filename = entry.file_name().to_string_lossy().to_string();
                     debug!("Getting file: {:?}", filename);
                     let filetype = virtual_file_type_to_wasi_file_type(
-                        entry.file_type().map_err(|_| __WASI_EIO)?,
+                        entry.file_type().map_err(fs_error_into_wasi_err)?,
                     );
                     Ok((
                         filename, filetype, 0, // TODO: inode
@@ -1091,6 +1211,7 @@ pub fn fd_readdir(
 /// - `__wasi_fd_t to`
 ///     Location to copy file descriptor to
 pub fn fd_renumber(env: &WasiEnv, from: __wasi_fd_t, to: __wasi_fd_t) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_renumber: from={}, to={}", from, to);
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.fd_map.get(&from).ok_or(__WASI_EBADF));
@@ -1124,6 +1245,7 @@ pub fn fd_seek(
     whence: __wasi_whence_t,
     newoffset: WasmPtr<__wasi_filesize_t>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_seek: fd={}, offset={}", fd, offset);
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let new_offset_cell = wasi_try!(newoffset.deref(memory));
@@ -1187,6 +1309,7 @@ pub fn fd_seek(
 /// - `__WASI_EPERM`
 /// - `__WASI_ENOTCAPABLE`
 pub fn fd_sync(env: &WasiEnv, fd: __wasi_fd_t) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_sync");
     debug!("=> fd={}", fd);
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
@@ -1225,6 +1348,7 @@ pub fn fd_tell(
     fd: __wasi_fd_t,
     offset: WasmPtr<__wasi_filesize_t>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::fd_tell");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let offset_cell = wasi_try!(offset.deref(memory));
@@ -1261,6 +1385,7 @@ pub fn fd_write(
     iovs_len: u32,
     nwritten: WasmPtr<u32>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     // If we are writing to stdout or stderr
     // we skip debug to not pollute the stdout/err
     // and do debugging happily after :)
@@ -1272,6 +1397,9 @@ pub fn fd_write(
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let iovs_arr_cell = wasi_try!(iovs.deref(memory, 0, iovs_len));
     let nwritten_cell = wasi_try!(nwritten.deref(memory));
+    wasi_try!(state
+        .usage
+        .check_write(&state.quotas, iovs_size(&iovs_arr_cell)));
 
     let bytes_written = match fd {
         __WASI_STDIN_FILENO => return __WASI_EINVAL,
@@ -1300,13 +1428,17 @@ pub fn fd_write(
                 return __WASI_EACCES;
             }
 
-            let offset = fd_entry.offset as usize;
+            let mut offset = fd_entry.offset as usize;
+            let append = fd_entry.flags & __WASI_FDFLAG_APPEND != 0;
             let inode_idx = fd_entry.inode;
             let inode = &mut state.fs.inodes[inode_idx];
 
             let bytes_written = match &mut inode.kind {
                 Kind::File { handle, .. } => {
                     if let Some(handle) = handle {
+                        if append {
+                            offset = handle.size() as usize;
+                        }
                         handle.seek(std::io::SeekFrom::Start(offset as u64));
                         wasi_try!(write_bytes(handle, memory, &iovs_arr_cell))
                     } else {
@@ -1325,13 +1457,14 @@ pub fn fd_write(
 
             // reborrow
             let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
-            fd_entry.offset += bytes_written as u64;
+            fd_entry.offset = (offset + bytes_written as usize) as u64;
             wasi_try!(state.fs.filestat_resync_size(fd));
 
             bytes_written
         }
     };
 
+    state.usage.written(bytes_written);
     nwritten_cell.set(bytes_written);
 
     __WASI_ESUCCESS
@@ -1356,6 +1489,7 @@ pub fn path_create_directory(
     path: WasmPtr<u8, Array>,
     path_len: u32,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::path_create_directory");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
@@ -1461,6 +1595,7 @@ pub fn path_filestat_get(
     path_len: u32,
     buf: WasmPtr<__wasi_filestat_t>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::path_filestat_get");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
@@ -1520,6 +1655,7 @@ pub fn path_filestat_set_times(
     st_mtim: __wasi_timestamp_t,
     fst_flags: __wasi_fstflags_t,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::path_filestat_set_times");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
@@ -1527,12 +1663,6 @@ pub fn path_filestat_set_times(
     if !has_rights(fd_entry.rights, __WASI_RIGHT_PATH_FILESTAT_SET_TIMES) {
         return __WASI_EACCES;
     }
-    if (fst_flags & __WASI_FILESTAT_SET_ATIM != 0 && fst_flags & __WASI_FILESTAT_SET_ATIM_NOW != 0)
-        || (fst_flags & __WASI_FILESTAT_SET_MTIM != 0
-            && fst_flags & __WASI_FILESTAT_SET_MTIM_NOW != 0)
-    {
-        return __WASI_EINVAL;
-    }
 
     let path_string = unsafe { get_input_str!(memory, path, path_len) };
     debug!("=> base_fd: {}, path: {}", fd, &path_string);
@@ -1542,29 +1672,12 @@ pub fn path_filestat_set_times(
         &path_string,
         flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
     ));
-    let stat = wasi_try!(state
-        .fs
-        .get_stat_for_kind(&state.fs.inodes[file_inode].kind)
-        .ok_or(__WASI_EIO));
-
-    let inode = &mut state.fs.inodes[fd_inode];
-
-    if fst_flags & __WASI_FILESTAT_SET_ATIM != 0 || fst_flags & __WASI_FILESTAT_SET_ATIM_NOW != 0 {
-        let time_to_set = if fst_flags & __WASI_FILESTAT_SET_ATIM != 0 {
-            st_atim
-        } else {
-            wasi_try!(get_current_time_in_nanos())
-        };
-        inode.stat.st_atim = time_to_set;
-    }
-    if fst_flags & __WASI_FILESTAT_SET_MTIM != 0 || fst_flags & __WASI_FILESTAT_SET_MTIM_NOW != 0 {
-        let time_to_set = if fst_flags & __WASI_FILESTAT_SET_MTIM != 0 {
-            st_mtim
-        } else {
-            wasi_try!(get_current_time_in_nanos())
-        };
-        inode.stat.st_mtim = time_to_set;
-    }
+    wasi_try!(set_inode_times(
+        &mut state.fs.inodes[file_inode],
+        st_atim,
+        st_mtim,
+        fst_flags
+    ));
 
     __WASI_ESUCCESS
 }
@@ -1596,6 +1709,7 @@ pub fn path_link(
     new_path: WasmPtr<u8, Array>,
     new_path_len: u32,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::path_link");
     if old_flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0 {
         debug!("  - will follow symlinks when opening path");
@@ -1681,11 +1795,13 @@ pub fn path_open(
     fs_flags: __wasi_fdflags_t,
     fd: WasmPtr<__wasi_fd_t>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::path_open");
     if dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0 {
         debug!("  - will follow symlinks when opening path");
     }
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    wasi_try!(state.usage.check_open(&state.quotas));
     /* TODO: find actual upper bound on name size (also this is a path, not a name :think-fish:) */
     if path_len > 1024 * 1024 {
         return __WASI_ENAMETOOLONG;
@@ -1875,6 +1991,7 @@ pub fn path_open(
         inode
     ));
 
+    state.usage.opened();
     fd_cell.set(out_fd);
     debug!("wasi::path_open returning fd {}", out_fd);
 
@@ -1906,6 +2023,7 @@ pub fn path_readlink(
     buf_len: u32,
     buf_used: WasmPtr<u32>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::path_readlink");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
@@ -1948,6 +2066,7 @@ pub fn path_remove_directory(
     path: WasmPtr<u8, Array>,
     path_len: u32,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     // TODO check if fd is a dir, ensure it's within sandbox, etc.
     debug!("wasi::path_remove_directory");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
@@ -2025,6 +2144,7 @@ pub fn path_rename(
     new_path: WasmPtr<u8, Array>,
     new_path_len: u32,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!(
         "wasi::path_rename: old_fd = {}, new_fd = {}",
         old_fd, new_fd
@@ -2152,6 +2272,7 @@ pub fn path_symlink(
     new_path: WasmPtr<u8, Array>,
     new_path_len: u32,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::path_symlink");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let old_path_str = unsafe { get_input_str!(memory, old_path, old_path_len) };
@@ -2229,6 +2350,7 @@ pub fn path_unlink_file(
     path: WasmPtr<u8, Array>,
     path_len: u32,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::path_unlink_file");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
@@ -2326,6 +2448,7 @@ pub fn poll_oneoff(
     nsubscriptions: u32,
     nevents: WasmPtr<u32>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::poll_oneoff");
     debug!("  => nsubscriptions = {}", nsubscriptions);
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
@@ -2514,15 +2637,18 @@ pub fn poll_oneoff(
     nsubscriptions: u32,
     nevents: WasmPtr<u32>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     unimplemented!();
 }
 
 pub fn proc_exit(env: &WasiEnv, code: __wasi_exitcode_t) -> Result<(), WasiError> {
+    env.state().usage.syscalls += 1;
     debug!("wasi::proc_exit, {}", code);
     Err(WasiError::Exit(code))
 }
 
 pub fn proc_raise(env: &WasiEnv, sig: __wasi_signal_t) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::proc_raise");
     unimplemented!("wasi::proc_raise")
 }
@@ -2535,10 +2661,17 @@ pub fn proc_raise(env: &WasiEnv, sig: __wasi_signal_t) -> __wasi_errno_t {
 /// - `size_t buf_len`
 ///     The number of bytes that will be written
 pub fn random_get(env: &WasiEnv, buf: u32, buf_len: u32) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::random_get buf_len: {}", buf_len);
     let memory = env.memory();
     let mut u8_buffer = vec![0; buf_len as usize];
-    let res = getrandom::getrandom(&mut u8_buffer);
+    let res = match &mut env.state().deterministic {
+        Some(deterministic) => {
+            deterministic.random_get(&mut u8_buffer);
+            Ok(())
+        }
+        None => getrandom::getrandom(&mut u8_buffer),
+    };
     match res {
         Ok(()) => {
             unsafe {
@@ -2556,6 +2689,7 @@ pub fn random_get(env: &WasiEnv, buf: u32, buf_len: u32) -> __wasi_errno_t {
 /// ### `sched_yield()`
 /// Yields execution of the thread
 pub fn sched_yield(env: &WasiEnv) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::sched_yield");
     ::std::thread::yield_now();
     __WASI_ESUCCESS
@@ -2570,6 +2704,7 @@ pub fn sock_recv(
     ro_datalen: WasmPtr<u32>,
     ro_flags: WasmPtr<__wasi_roflags_t>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::sock_recv");
     unimplemented!("wasi::sock_recv")
 }
@@ -2581,10 +2716,12 @@ pub fn sock_send(
     si_flags: __wasi_siflags_t,
     so_datalen: WasmPtr<u32>,
 ) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::sock_send");
     unimplemented!("wasi::sock_send")
 }
 pub fn sock_shutdown(env: &WasiEnv, sock: __wasi_fd_t, how: __wasi_sdflags_t) -> __wasi_errno_t {
+    wasi_try!(env.count_syscall());
     debug!("wasi::sock_shutdown");
     unimplemented!("wasi::sock_shutdown")
 }
@@ -0,0 +1,131 @@
+//! Integer comparisons branched on by `br_if` and `if`, which Singlepass
+//! fuses with the branch on some architectures.
+
+use anyhow::Result;
+use wasmer::*;
+
+const I32_VALUES: [i32; 6] = [0, 1, -1, 7, i32::MIN, i32::MAX];
+const I64_VALUES: [i64; 6] = [0, 1, -1, 7, i64::MIN, i64::MAX];
+
+const COMPARISONS: [&str; 10] = [
+    "eq", "ne", "lt_s", "le_s", "gt_s", "ge_s", "lt_u", "le_u", "gt_u", "ge_u",
+];
+
+/// The semantics of a comparison on `bits`-wide operands, which are sign
+/// extended if narrower than `i64`.
+fn compare(op: &str, bits: u32, a: i64, b: i64) -> bool {
+    let unsigned = |x: i64| (x as u64) & (u64::MAX >> (64 - bits));
+    match op {
+        "eq" => a == b,
+        "ne" => a != b,
+        "lt_s" => a < b,
+        "le_s" => a <= b,
+        "gt_s" => a > b,
+        "ge_s" => a >= b,
+        "lt_u" => unsigned(a) < unsigned(b),
+        "le_u" => unsigned(a) <= unsigned(b),
+        "gt_u" => unsigned(a) > unsigned(b),
+        "ge_u" => unsigned(a) >= unsigned(b),
+        _ => unreachable!(),
+    }
+}
+
+/// Functions named after `name` computing `op` as a value, through `br_if`
+/// and through `if`.
+fn functions(name: &str, ty: &str, op: &str, params: &str, operands: &str) -> String {
+    format!(
+        r#"
+        (func (export "value_{name}") {params} (result i32)
+          ({ty}.{op} {operands}))
+        (func (export "br_if_{name}") {params} (result i32)
+          (block
+            (br_if 0 ({ty}.{op} {operands}))
+            (return (i32.const 0)))
+          (i32.const 1))
+        (func (export "if_{name}") {params} (result i32)
+          (if (result i32) ({ty}.{op} {operands})
+            (then (i32.const 1))
+            (else (i32.const 0))))"#,
+        name = name,
+        ty = ty,
+        op = op,
+        params = params,
+        operands = operands,
+    )
+}
+
+fn check(instance: &Instance, name: &str, args: &[Val], expected: bool) -> Result<()> {
+    for kind in &["value", "br_if", "if"] {
+        let f = instance
+            .exports
+            .get_function(&format!("{}_{}", kind, name))?;
+        let result = f.call(args)?;
+        assert_eq!(
+            result[0].unwrap_i32(),
+            expected as i32,
+            "{}_{}{:?}",
+            kind,
+            name,
+            args
+        );
+    }
+    Ok(())
+}
+
+#[compiler_test(branches)]
+fn branch_on_integer_comparisons(mut config: crate::Config) -> Result<()> {
+    let store = config.store();
+
+    let mut wat = String::from("(module");
+    for ty in &["i32", "i64"] {
+        let params = format!("(param {ty}) (param {ty})", ty = ty);
+        for op in COMPARISONS.iter() {
+            let name = format!("{}_{}", ty, op);
+            wat += &functions(&name, ty, op, &params, "(local.get 0) (local.get 1)");
+        }
+        let param = format!("(param {})", ty);
+        wat += &functions(&format!("{}_eqz", ty), ty, "eqz", &param, "(local.get 0)");
+        // compared to constants, which can be immediates
+        let operands = format!("({ty}.const -1) ({ty}.const 0)", ty = ty);
+        wat += &functions(&format!("{}_const", ty), ty, "lt_s", "", &operands);
+    }
+    wat += ")";
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    for op in COMPARISONS.iter() {
+        for &a in I32_VALUES.iter() {
+            for &b in I32_VALUES.iter() {
+                let expected = compare(op, 32, a.into(), b.into());
+                check(
+                    &instance,
+                    &format!("i32_{}", op),
+                    &[Val::I32(a), Val::I32(b)],
+                    expected,
+                )?;
+            }
+        }
+    }
+    for op in COMPARISONS.iter() {
+        for &a in I64_VALUES.iter() {
+            for &b in I64_VALUES.iter() {
+                let expected = compare(op, 64, a, b);
+                check(
+                    &instance,
+                    &format!("i64_{}", op),
+                    &[Val::I64(a), Val::I64(b)],
+                    expected,
+                )?;
+            }
+        }
+    }
+    for &a in I32_VALUES.iter() {
+        check(&instance, "i32_eqz", &[Val::I32(a)], a == 0)?;
+    }
+    for &a in I64_VALUES.iter() {
+        check(&instance, "i64_eqz", &[Val::I64(a)], a == 0)?;
+    }
+    check(&instance, "i32_const", &[], true)?;
+    check(&instance, "i64_const", &[], true)?;
+    Ok(())
+}
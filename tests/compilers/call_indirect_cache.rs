@@ -0,0 +1,61 @@
+#![cfg(all(feature = "singlepass", feature = "universal"))]
+
+use anyhow::Result;
+use wasmer::*;
+use wasmer_compiler_singlepass::Singlepass;
+use wasmer_engine_universal::Universal;
+
+const WAT: &str = r#"(module
+    (type $unary (func (param i32) (result i32)))
+    (table (export "table") 2 funcref)
+    (elem (i32.const 0) $double $square)
+    (func $double (export "double") (type $unary) (i32.shl (local.get 0) (i32.const 1)))
+    (func $square (export "square") (type $unary) (i32.mul (local.get 0) (local.get 0)))
+    (func (export "nullary") (result i32) (i32.const 42))
+    (func (export "call") (param i32 i32) (result i32)
+        (call_indirect (type $unary) (local.get 1) (local.get 0))))"#;
+
+fn cached_store() -> Store {
+    let mut compiler = Singlepass::new();
+    compiler.call_indirect_inline_cache(true);
+    Store::new(&Universal::new(compiler).engine())
+}
+
+#[test]
+fn call_indirect_cache_follows_the_table() -> Result<()> {
+    let store = cached_store();
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let other = Instance::new(&module, &imports! {})?;
+    let call: NativeFunc<(i32, i32), i32> = instance.exports.get_native_function("call")?;
+    let table = instance.exports.get_table("table")?;
+
+    // Alternating targets miss the cache, repeated ones hit it.
+    for _ in 0..3 {
+        assert_eq!(call.call(0, 5)?, 10);
+        assert_eq!(call.call(0, 5)?, 10);
+        assert_eq!(call.call(1, 5)?, 25);
+    }
+
+    // A function of another instance is never cached.
+    let other_square = other.exports.get_function("square")?.clone();
+    table.set(0, Val::FuncRef(Some(other_square)))?;
+    assert_eq!(call.call(0, 6)?, 36);
+    assert_eq!(call.call(0, 6)?, 36);
+
+    // Replacing the cached function is seen by the next call.
+    let double = instance.exports.get_function("double")?.clone();
+    table.set(1, Val::FuncRef(Some(double)))?;
+    assert_eq!(call.call(1, 6)?, 12);
+
+    // The signature is still checked once the cache is filled.
+    let nullary = instance.exports.get_function("nullary")?.clone();
+    table.set(1, Val::FuncRef(Some(nullary)))?;
+    let err = call.call(1, 6).unwrap_err();
+    assert_eq!(err.to_trap(), Some(TrapCode::BadSignature));
+
+    table.set(0, Val::FuncRef(None))?;
+    let err = call.call(0, 6).unwrap_err();
+    assert_eq!(err.to_trap(), Some(TrapCode::IndirectCallToNull));
+    Ok(())
+}
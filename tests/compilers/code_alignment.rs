@@ -0,0 +1,37 @@
+#![cfg(feature = "universal")]
+
+use anyhow::Result;
+use wasmer::*;
+use wasmer_engine_universal::Universal;
+
+#[compiler_test(code_alignment)]
+fn aligned_and_padded_code_runs(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let mut alignment = CodeAlignment::new();
+    alignment.function(64).loop_header(64).function_padding(100);
+    let engine = Universal::new(config.compiler_config(false))
+        .code_alignment(alignment)
+        .engine();
+    let store = Store::new(&engine);
+
+    let wat = r#"(module
+        (func $double (param i32) (result i32)
+          (i32.add (local.get 0) (local.get 0)))
+        (func (export "sum_doubles") (param $n i32) (result i32)
+          (local $sum i32)
+          (loop $next
+            (local.set $sum
+              (i32.add (local.get $sum) (call $double (local.get $n))))
+            (br_if $next
+              (local.tee $n (i32.sub (local.get $n) (i32.const 1)))))
+          (local.get $sum)))"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let sum_doubles = instance
+        .exports
+        .get_native_function::<i32, i32>("sum_doubles")?;
+    assert_eq!(sum_doubles.call(10)?, 110);
+    Ok(())
+}
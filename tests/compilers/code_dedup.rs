@@ -0,0 +1,89 @@
+#![cfg(feature = "universal")]
+
+use anyhow::Result;
+use wasmer::*;
+use wasmer_engine_universal::Universal;
+
+const WAT: &str = r#"(module
+    (memory 1)
+    (func (export "run") (param i32) (result i32)
+      (i32.store (i32.const 0) (local.get 0))
+      (i32.mul (i32.load (i32.const 0)) (i32.const 2))))"#;
+
+fn run_address(module: &Module) -> Result<*const vm::VMFunctionBody> {
+    let instance = Instance::new(module, &imports! {})?;
+    let run: NativeFunc<i32, i32> = instance.exports.get_native_function("run")?;
+    assert_eq!(run.call(21)?, 42);
+    let run = instance.exports.get_function("run")?;
+    Ok(RawFunction::new(run).unwrap().address())
+}
+
+#[compiler_test(code_dedup)]
+fn stores_sharing_an_engine_share_code(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let engine = Universal::new(config.compiler_config(false))
+        .dedup_code(true)
+        .engine();
+
+    let first = Module::new(&Store::new(&engine), WAT)?;
+    let second = Module::new(&Store::new(&engine), WAT)?;
+    assert_eq!(run_address(&first)?, run_address(&second)?);
+    assert_eq!(
+        engine.code_dedup_stats(),
+        CodeDedupStats { hits: 1, misses: 1 }
+    );
+
+    // Stores whose memories have other styles need their own code.
+    let mut tunables = BaseTunables::for_target(engine.target());
+    tunables.static_memory_bound = Pages(0);
+    let dynamic = Module::new(&Store::new_with_tunables(&engine, tunables), WAT)?;
+    assert_ne!(run_address(&first)?, run_address(&dynamic)?);
+    assert_eq!(
+        engine.code_dedup_stats(),
+        CodeDedupStats { hits: 1, misses: 2 }
+    );
+
+    // The code is compiled again once the modules using it are dropped.
+    drop((first, second, dynamic));
+    Module::new(&Store::new(&engine), WAT)?;
+    assert_eq!(
+        engine.code_dedup_stats(),
+        CodeDedupStats { hits: 1, misses: 3 }
+    );
+    Ok(())
+}
+
+#[compiler_test(code_dedup)]
+fn code_is_not_shared_across_compilers(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let engine = Universal::new(config.compiler_config(false))
+        .dedup_code(true)
+        .engine();
+
+    let first = Module::new(&Store::new(&engine), WAT)?;
+    engine.set_compiler(config.compiler_config(false).compiler());
+    let second = Module::new(&Store::new(&engine), WAT)?;
+    assert_ne!(run_address(&first)?, run_address(&second)?);
+    assert_eq!(
+        engine.code_dedup_stats(),
+        CodeDedupStats { hits: 0, misses: 2 }
+    );
+    Ok(())
+}
+
+#[compiler_test(code_dedup)]
+fn code_is_not_shared_by_default(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let engine = Universal::new(config.compiler_config(false)).engine();
+    let first = Module::new(&Store::new(&engine), WAT)?;
+    let second = Module::new(&Store::new(&engine), WAT)?;
+    assert_ne!(run_address(&first)?, run_address(&second)?);
+    assert_eq!(engine.code_dedup_stats(), CodeDedupStats::default());
+    Ok(())
+}
@@ -0,0 +1,158 @@
+//! Checks that every module accepted by the validator can be compiled,
+//! whatever proposals are requested from the engine.
+
+use anyhow::Result;
+use wasmer::*;
+
+/// A small module exercising each proposal a `Features` can toggle.
+const PROPOSALS: &[(&str, &str)] = &[
+    (
+        "threads",
+        // Shared memories aren't supported by the translator yet, only
+        // the atomic instructions are.
+        r#"(module
+            (memory 1 1)
+            (func (export "run") (result i32)
+              (i32.atomic.load (i32.const 0))))"#,
+    ),
+    (
+        "reference-types",
+        r#"(module
+            (table 1 externref)
+            (func (export "run") (result externref)
+              (table.get 0 (i32.const 0))))"#,
+    ),
+    (
+        "simd",
+        r#"(module
+            (func (export "run") (result i32)
+              (i32x4.extract_lane 0
+                (i32x4.add (v128.const i32x4 1 2 3 4) (v128.const i32x4 1 2 3 4)))))"#,
+    ),
+    (
+        "bulk-memory",
+        r#"(module
+            (memory 1)
+            (func (export "run")
+              (memory.copy (i32.const 0) (i32.const 8) (i32.const 8))))"#,
+    ),
+    (
+        "multi-value",
+        r#"(module
+            (func (export "run") (result i32 i32)
+              (i32.const 1) (i32.const 2)))"#,
+    ),
+    (
+        "tail-call",
+        r#"(module
+            (func $f (result i32) (i32.const 1))
+            (func (export "run") (result i32)
+              (return_call $f)))"#,
+    ),
+    (
+        "multi-memory",
+        r#"(module
+            (memory 1)
+            (memory 1)
+            (func (export "run") (result i32)
+              (i32.load 1 (i32.const 0))))"#,
+    ),
+    (
+        "memory64",
+        r#"(module
+            (memory i64 1)
+            (func (export "run") (result i32)
+              (i32.load (i64.const 0))))"#,
+    ),
+    (
+        "exceptions",
+        r#"(module
+            (tag $e)
+            (func (export "run")
+              (throw $e)))"#,
+    ),
+    (
+        "relaxed-simd",
+        r#"(module
+            (func (export "run") (result f32)
+              (f32x4.extract_lane 0
+                (f32x4.relaxed_min (v128.const f32x4 1 2 3 4) (v128.const f32x4 4 3 2 1)))))"#,
+    ),
+    (
+        "extended-const",
+        r#"(module
+            (global $g i32 (i32.add (i32.const 1) (i32.const 2)))
+            (func (export "run") (result i32)
+              (global.get $g)))"#,
+    ),
+];
+
+fn all_features() -> Features {
+    let mut features = Features::new();
+    features
+        .threads(true)
+        .reference_types(true)
+        .simd(true)
+        .bulk_memory(true)
+        .multi_value(true)
+        .tail_call(true)
+        .module_linking(true)
+        .multi_memory(true)
+        .memory64(true)
+        .exceptions(true)
+        .relaxed_simd(true)
+        .extended_const(true)
+        .component_model(true);
+    features
+}
+
+fn check_parity(store: &Store) -> Result<()> {
+    for (proposal, wat) in PROPOSALS {
+        let wasm = wat2wasm(wat.as_bytes())?;
+        let validated = Module::validate(store, &wasm);
+        match Module::new(store, &wasm) {
+            Ok(module) => {
+                assert!(
+                    validated.is_ok(),
+                    "{}: compiled a module that doesn't validate",
+                    proposal
+                );
+                // Make sure the compiled code is complete enough to be linked
+                let _ = Instance::new(&module, &imports! {});
+            }
+            Err(CompileError::Validate(_)) => {
+                assert!(
+                    validated.is_err(),
+                    "{}: the validation result differs from compilation",
+                    proposal
+                );
+            }
+            Err(e) => panic!("{}: validates but fails to compile: {}", proposal, e),
+        }
+    }
+    Ok(())
+}
+
+#[compiler_test(features)]
+fn validation_matches_compilation_with_all_features(mut config: crate::Config) -> Result<()> {
+    config.set_features(all_features());
+    check_parity(&config.store())
+}
+
+#[compiler_test(features)]
+fn validation_matches_compilation_with_default_features(config: crate::Config) -> Result<()> {
+    check_parity(&config.store())
+}
+
+#[compiler_test(features)]
+fn unsupported_proposals_fail_validation(mut config: crate::Config) -> Result<()> {
+    config.set_features(all_features());
+    let store = config.store();
+    // No compiler implements tail calls yet
+    let wasm = wat2wasm(PROPOSALS[5].1.as_bytes())?;
+    assert!(matches!(
+        Module::new(&store, &wasm),
+        Err(CompileError::Validate(_))
+    ));
+    Ok(())
+}
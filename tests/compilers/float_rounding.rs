@@ -0,0 +1,109 @@
+#![cfg(all(feature = "cranelift", feature = "universal", target_arch = "x86_64"))]
+
+use anyhow::Result;
+use wasmer::*;
+use wasmer_compiler_cranelift::Cranelift;
+use wasmer_engine_universal::Universal;
+
+const WAT: &str = r#"(module
+    (func (export "f32.ceil") (param f32) (result f32) (f32.ceil (local.get 0)))
+    (func (export "f32.floor") (param f32) (result f32) (f32.floor (local.get 0)))
+    (func (export "f32.trunc") (param f32) (result f32) (f32.trunc (local.get 0)))
+    (func (export "f32.nearest") (param f32) (result f32) (f32.nearest (local.get 0)))
+    (func (export "f64.ceil") (param f64) (result f64) (f64.ceil (local.get 0)))
+    (func (export "f64.floor") (param f64) (result f64) (f64.floor (local.get 0)))
+    (func (export "f64.trunc") (param f64) (result f64) (f64.trunc (local.get 0)))
+    (func (export "f64.nearest") (param f64) (result f64) (f64.nearest (local.get 0))))"#;
+
+const OPS: [&str; 4] = ["ceil", "floor", "trunc", "nearest"];
+
+fn instance(target: Option<Target>) -> Result<Instance> {
+    let mut engine = Universal::new(Cranelift::new());
+    if let Some(target) = target {
+        engine = engine.target(target);
+    }
+    let store = Store::new(&engine.engine());
+    let module = Module::new(&store, WAT)?;
+    Ok(Instance::new(&module, &imports! {})?)
+}
+
+#[test]
+fn rounding_without_sse41_matches_the_native_instructions() -> Result<()> {
+    let native = instance(None)?;
+    let inline = instance(Some(Target::new(
+        Triple::host(),
+        CpuFeature::SSE2 | CpuFeature::SSE3 | CpuFeature::SSSE3,
+    )))?;
+
+    let f64_values = [
+        0.0,
+        -0.0,
+        0.3,
+        -0.3,
+        0.5,
+        -0.5,
+        0.7,
+        -0.7,
+        1.5,
+        -1.5,
+        2.5,
+        -2.5,
+        0.499_999_999_999_999_94,
+        4_503_599_627_370_495.5,
+        4_503_599_627_370_497.0,
+        -4_503_599_627_370_497.0,
+        1e300,
+        f64::MIN_POSITIVE,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+    ];
+    for op in OPS {
+        let name = format!("f64.{}", op);
+        let native = native.exports.get_native_function::<f64, f64>(&name)?;
+        let inline = inline.exports.get_native_function::<f64, f64>(&name)?;
+        for value in f64_values {
+            assert_eq!(
+                inline.call(value)?.to_bits(),
+                native.call(value)?.to_bits(),
+                "{}({})",
+                name,
+                value
+            );
+        }
+        assert!(inline.call(f64::NAN)?.is_nan());
+    }
+
+    let f32_values = [
+        0.0,
+        -0.0,
+        0.3,
+        -0.3,
+        0.5,
+        -0.5,
+        1.5,
+        -2.5,
+        0.499_999_97,
+        8_388_607.5,
+        -8_388_607.5,
+        16_777_216.0,
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+    ];
+    for op in OPS {
+        let name = format!("f32.{}", op);
+        let native = native.exports.get_native_function::<f32, f32>(&name)?;
+        let inline = inline.exports.get_native_function::<f32, f32>(&name)?;
+        for value in f32_values {
+            assert_eq!(
+                inline.call(value)?.to_bits(),
+                native.call(value)?.to_bits(),
+                "{}({})",
+                name,
+                value
+            );
+        }
+        assert!(inline.call(f32::NAN)?.is_nan());
+    }
+
+    Ok(())
+}
@@ -432,3 +432,37 @@ fn instance_local_memory_lifetime(config: crate::Config) -> Result<()> {
 
     Ok(())
 }
+
+#[compiler_test(imports)]
+fn frozen_func_env_calls_back_into_wasm(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"(module
+    (import "host" "callback" (func $callback (param i32) (result i32)))
+    (func $double (export "double") (param $x i32) (result i32)
+      (i32.mul (local.get $x) (i32.const 2)))
+    (func $main (export "main") (param $x i32) (result i32)
+      (call $callback (local.get $x)))
+)"#;
+    let module = Module::new(&store, wat)?;
+
+    #[derive(WasmerEnv, Clone)]
+    struct Env {
+        offset: i32,
+    }
+
+    fn callback(env: &FrozenFuncEnv<Env>, x: i32) -> i32 {
+        let double: NativeFunc<i32, i32> = env.get_native_function("double").unwrap();
+        double.call(x).unwrap() + env.offset
+    }
+
+    let imports = imports! {
+        "host" => {
+            "callback" => Function::new_native_with_env(&store, FrozenFuncEnv::new(Env { offset: 1 }), callback),
+        },
+    };
+    let instance = Instance::new(&module, &imports)?;
+    let main: NativeFunc<i32, i32> = instance.exports.get_native_function("main")?;
+    assert_eq!(main.call(20)?, 41);
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "universal")]
+
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use wasmer::*;
+use wasmer_engine_universal::Universal;
+
+const WAT: &str = r#"(module
+    (func $double (param i32) (result i32)
+      (i32.mul (local.get 0) (i32.const 2)))
+    (func (export "run") (param i32) (result i32)
+      (call $double (local.get 0))))"#;
+
+fn compile(compiler: impl CompilerConfig + 'static) -> Result<()> {
+    let store = Store::new(&Universal::new(compiler).engine());
+    Module::new(&store, WAT)?;
+    Ok(())
+}
+
+fn read(dir: &Path, file: &str) -> Result<String> {
+    Ok(fs::read_to_string(dir.join(file))?)
+}
+
+#[test]
+#[cfg(feature = "singlepass")]
+fn singlepass_dumps_annotated_code() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let mut compiler = wasmer_compiler_singlepass::Singlepass::new();
+    compiler.dump_code(Some(dir.path().join("singlepass")));
+    compile(compiler)?;
+
+    let dir = dir.path().join("singlepass");
+    let double = read(&dir, "function_0.s")?;
+    assert!(double.contains("I32Mul"), "{}", double);
+    assert!(double.contains("\t.byte "), "{}", double);
+    let run = read(&dir, "function_1.s")?;
+    assert!(run.contains("Call { function_index: 0 }"), "{}", run);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "cranelift")]
+fn cranelift_dumps_ir_and_disassembly() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let mut compiler = wasmer_compiler_cranelift::Cranelift::new();
+    compiler.dump_ir(Some(dir.path().to_owned()));
+    compile(compiler)?;
+
+    for function in &["function_0", "function_1"] {
+        let clif = read(dir.path(), &format!("{}.clif", function))?;
+        assert!(clif.starts_with("function "), "{}", clif);
+        read(dir.path(), &format!("{}.opt.clif", function))?;
+        read(dir.path(), &format!("{}.s", function))?;
+    }
+    assert!(read(dir.path(), "function_0.clif")?.contains("imul"));
+    Ok(())
+}
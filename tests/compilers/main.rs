@@ -5,16 +5,31 @@
 #[macro_use]
 extern crate compiler_test_derive;
 
+mod branches;
+mod call_indirect_cache;
+mod code_alignment;
+mod code_dedup;
 mod config;
 mod deterministic;
+mod features;
+mod float_rounding;
 mod imports;
+mod ir_dump;
 mod issues;
+mod memory_access;
 mod metering;
 mod middlewares;
 // mod multi_value_imports;
 mod native_functions;
+mod patch_points;
+mod relocation_hook;
+mod sampling;
 mod serialize;
+mod shared_code;
+mod single_step;
+mod stores;
 mod traps;
+mod validation_limits;
 mod wasi;
 mod wast;
 
@@ -0,0 +1,164 @@
+//! Memory accesses which compilers optimize: sequences of accesses, which
+//! Singlepass may compile reusing the base of the memory between them, and
+//! accesses at constant addresses, whose bounds checks may be left out.
+
+use anyhow::Result;
+use wasmer::*;
+
+/// A store whose memories are all dynamic, so that growing them can move
+/// their base.
+fn dynamic_memory_store(config: &crate::Config) -> Store {
+    let engine = config.engine(config.compiler_config(false));
+    let mut tunables = BaseTunables::for_target(engine.target());
+    tunables.static_memory_bound = Pages(0);
+    Store::new_with_tunables(&*engine, tunables)
+}
+
+#[compiler_test(memory_access)]
+fn accesses_across_grows_and_calls(config: crate::Config) -> Result<()> {
+    let store = dynamic_memory_store(&config);
+    let wat = r#"(module
+        (import "env" "grow" (func $host_grow (param i32)))
+        (memory (export "memory") 1)
+        (func $grow (param i32)
+          (drop (memory.grow (local.get 0))))
+        ;; copies `len` bytes from `src` to `dst`, byte by byte
+        (func $copy (param $dst i32) (param $src i32) (param $len i32)
+          (block $done
+            (loop $next
+              (br_if $done (i32.eqz (local.get $len)))
+              (i32.store8 (local.get $dst) (i32.load8_u (local.get $src)))
+              (local.set $dst (i32.add (local.get $dst) (i32.const 1)))
+              (local.set $src (i32.add (local.get $src) (i32.const 1)))
+              (local.set $len (i32.sub (local.get $len) (i32.const 1)))
+              (br $next))))
+        (func (export "run") (result i64)
+          (i64.store (i32.const 0) (i64.const 0x0102030405060708))
+          (call $copy (i32.const 8) (i32.const 0) (i32.const 8))
+          (drop (memory.grow (i32.const 4)))
+          (i64.store (i32.const 0x40000) (i64.load (i32.const 8)))
+          (call $grow (i32.const 8))
+          (i64.store (i32.const 0xc0000) (i64.load (i32.const 0x40000)))
+          (call $host_grow (i32.const 16))
+          (i64.store (i32.const 0x1c0000) (i64.load (i32.const 0xc0000)))
+          (i64.load (i32.const 0x1c0000))))"#;
+    let module = Module::new(&store, wat)?;
+
+    #[derive(Clone, Default, WasmerEnv)]
+    struct Env {
+        #[wasmer(export)]
+        memory: LazyInit<Memory>,
+    }
+    fn host_grow(env: &Env, delta: u32) {
+        env.memory_ref().unwrap().grow(delta).unwrap();
+    }
+    let imports = imports! {
+        "env" => {
+            "grow" => Function::new_native_with_env(&store, Env::default(), host_grow),
+        },
+    };
+    let instance = Instance::new(&module, &imports)?;
+    let run = instance.exports.get_native_function::<(), i64>("run")?;
+    assert_eq!(run.call()?, 0x0102030405060708);
+    let memory = instance.exports.get_memory("memory")?;
+    assert_eq!(memory.size(), Pages(1 + 4 + 8 + 16));
+    Ok(())
+}
+
+#[compiler_test(memory_access)]
+fn host_functions_grow_memory_mid_call(config: crate::Config) -> Result<()> {
+    let store = dynamic_memory_store(&config);
+    // The accesses around the call are in the same block, so Singlepass
+    // would reuse the base of the memory if it kept it across the call.
+    let wat = r#"(module
+        (import "env" "grow" (func $host_grow))
+        (memory (export "memory") 1)
+        (func (export "run") (param $n i32) (result i32)
+          (local $sum i32)
+          (loop $next
+            (i32.store (i32.const 0) (local.get $n))
+            (call $host_grow)
+            (local.set $sum
+              (i32.add
+                (local.get $sum)
+                (i32.add
+                  (i32.load (i32.const 0))
+                  (i32.load (i32.mul
+                    (i32.sub (memory.size) (i32.const 1))
+                    (i32.const 0x10000))))))
+            (br_if $next (local.tee $n (i32.sub (local.get $n) (i32.const 1)))))
+          (local.get $sum)))"#;
+    let module = Module::new(&store, wat)?;
+
+    #[derive(Clone, Default, WasmerEnv)]
+    struct Env {
+        #[wasmer(export)]
+        memory: LazyInit<Memory>,
+    }
+    // Copies the value stored at the start of the memory to the start of
+    // a new page.
+    fn host_grow(env: &Env) {
+        let memory = env.memory_ref().unwrap();
+        let mut view = memory.view::<u32>();
+        let value = view[0].get();
+        let previous = memory.grow(1).unwrap();
+        view.refresh();
+        assert_eq!(view.len(), memory.size().bytes().0 / 4);
+        view[previous.bytes().0 / 4].set(value);
+    }
+    let imports = imports! {
+        "env" => {
+            "grow" => Function::new_native_with_env(&store, Env::default(), host_grow),
+        },
+    };
+    let instance = Instance::new(&module, &imports)?;
+    let run = instance.exports.get_native_function::<i32, i32>("run")?;
+    assert_eq!(run.call(3)?, 2 * (3 + 2 + 1));
+    let memory = instance.exports.get_memory("memory")?;
+    assert_eq!(memory.size(), Pages(1 + 3));
+    Ok(())
+}
+
+#[compiler_test(memory_access)]
+fn constant_addresses_around_the_minimum_size(config: crate::Config) -> Result<()> {
+    let store = dynamic_memory_store(&config);
+    let wat = r#"(module
+        (memory (export "memory") 1)
+        (func (export "last") (result i64)
+          (i64.store (i32.const 65528) (i64.const 42))
+          (i64.load offset=65520 (i32.const 8)))
+        (func (export "past") (result i64)
+          (i64.load (i32.const 65529)))
+        (func (export "past_offset") (result i32)
+          (i32.load8_u offset=65536 (i32.const 0)))
+        (func (export "overflow") (result i32)
+          (i32.load offset=0xffffffff (i32.const 4)))
+        (func (export "grow")
+          (drop (memory.grow (i32.const 1)))))"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let last = instance.exports.get_native_function::<(), i64>("last")?;
+    let past = instance.exports.get_native_function::<(), i64>("past")?;
+    let past_offset = instance
+        .exports
+        .get_native_function::<(), i32>("past_offset")?;
+    let overflow = instance
+        .exports
+        .get_native_function::<(), i32>("overflow")?;
+    let grow = instance.exports.get_native_function::<(), ()>("grow")?;
+
+    assert_eq!(last.call()?, 42);
+    for error in [past.call().unwrap_err(), past_offset.call().unwrap_err()] {
+        assert_eq!(error.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    }
+    // Past the minimum size, accesses are still checked against the
+    // current size.
+    grow.call()?;
+    assert_eq!(past.call()?, 0);
+    assert_eq!(past_offset.call()?, 0);
+    assert_eq!(
+        overflow.call().unwrap_err().to_trap(),
+        Some(TrapCode::HeapAccessOutOfBounds)
+    );
+    Ok(())
+}
@@ -437,3 +437,37 @@ fn dynamic_host_function_with_env(config: crate::Config) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(unix)]
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(native_functions)]
+fn native_function_call_on_stack(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let wat = r#"(module
+        (func $sum (export "sum") (param i32) (result i32)
+           (if (result i32) (i32.eqz (local.get 0))
+             (then (i32.const 0))
+             (else (i32.add (local.get 0)
+                            (call $sum (i32.sub (local.get 0) (i32.const 1)))))))
+        (func $overflow (export "overflow")
+           (call $overflow))
+)"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let sum: NativeFunc<i32, i32> = instance.exports.get_native_function("sum")?;
+    let overflow: NativeFunc<(), ()> = instance.exports.get_native_function("overflow")?;
+
+    let mut stack = vec![0u8; 256 * 1024];
+    assert_eq!(sum.call_on_stack(&mut stack, 100)?, 5050);
+
+    let e = overflow.call_on_stack(&mut stack).unwrap_err();
+    assert!(e.message().contains("call stack exhausted"));
+
+    // The guard page is accessible again once the call returned.
+    stack.fill(0xff);
+    assert_eq!(sum.call_on_stack(&mut stack, 10)?, 55);
+
+    assert!(sum.call_on_stack(&mut [0u8; 16], 1).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+#![cfg(all(
+    feature = "singlepass",
+    feature = "universal",
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use wasmer::*;
+use wasmer_compiler_singlepass::Singlepass;
+use wasmer_engine_universal::Universal;
+
+#[test]
+fn probes_are_patched_in_and_out() -> Result<()> {
+    let mut compiler = Singlepass::new();
+    compiler.patch_points(true, true);
+    let store = Store::new(&Universal::new(compiler).engine());
+    let wat = r#"(module
+        (func (export "count") (param $n i32) (result i32)
+          (local $i i32)
+          (loop $next
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br_if $next (i32.lt_u (local.get $i) (local.get $n))))
+          (local.get $i)))"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let function = instance.exports.get_function("count")?;
+    let count: NativeFunc<i32, i32> = function.native()?;
+
+    let probes = Arc::new(Mutex::new(Vec::new()));
+    let recorded = probes.clone();
+    set_probe_callback(Some(Box::new(move |probe: &Probe| {
+        recorded.lock().unwrap().push(probe.clone())
+    })));
+    let take_kinds = || {
+        probes
+            .lock()
+            .unwrap()
+            .drain(..)
+            .map(|probe| probe.kind)
+            .collect::<Vec<_>>()
+    };
+
+    // The patch points are nops until their probes are enabled.
+    assert_eq!(count.call(3)?, 3);
+    assert!(take_kinds().is_empty());
+
+    assert_eq!(function.set_probes(PatchPointKind::FunctionEntry, true)?, 1);
+    assert_eq!(count.call(3)?, 3);
+    assert_eq!(take_kinds(), vec![PatchPointKind::FunctionEntry]);
+
+    assert_eq!(function.set_probes(PatchPointKind::LoopHeader, true)?, 1);
+    assert_eq!(count.call(3)?, 3);
+    assert_eq!(
+        take_kinds(),
+        vec![
+            PatchPointKind::FunctionEntry,
+            PatchPointKind::LoopHeader,
+            PatchPointKind::LoopHeader,
+            PatchPointKind::LoopHeader,
+        ]
+    );
+
+    function.set_probes(PatchPointKind::FunctionEntry, false)?;
+    function.set_probes(PatchPointKind::LoopHeader, false)?;
+    assert_eq!(count.call(3)?, 3);
+    set_probe_callback(None);
+    assert!(take_kinds().is_empty());
+
+    // Host functions have no patch points.
+    let host = Function::new_native(&store, |a: i32| a);
+    assert_eq!(host.set_probes(PatchPointKind::FunctionEntry, true)?, 0);
+    Ok(())
+}
+
+#[test]
+fn patch_points_are_not_reserved_by_default() -> Result<()> {
+    let store = Store::new(&Universal::new(Singlepass::new()).engine());
+    let module = Module::new(&store, r#"(module (func (export "f")))"#)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let function = instance.exports.get_function("f")?;
+    assert_eq!(function.set_probes(PatchPointKind::FunctionEntry, true)?, 0);
+    Ok(())
+}
@@ -0,0 +1,114 @@
+#![cfg(feature = "universal")]
+
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use wasmer::*;
+use wasmer_compiler::{Relocation, RelocationTarget};
+use wasmer_engine_universal::{LinkedCode, PatchError, RelocationHook, Universal};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::LocalFunctionIndex;
+
+const WAT: &str = r#"(module
+    (func $one (result i32) (i32.const 1))
+    (func $two (result i32) (i32.const 2))
+    (func (export "run") (result i32) (call $one)))"#;
+
+/// Redirects the calls to `$one` to `$two`, then tries a few patches
+/// of the code of `run`.
+#[derive(Default)]
+struct Hook {
+    call_offsets: Mutex<Vec<usize>>,
+    patches: Arc<Mutex<Vec<Result<(), PatchError>>>>,
+}
+
+impl RelocationHook for Hook {
+    fn relocate(
+        &self,
+        code: &LinkedCode,
+        relocation: &Relocation,
+        site: usize,
+        _target: usize,
+    ) -> Option<usize> {
+        match relocation.reloc_target {
+            RelocationTarget::LocalFunc(index) if index == LocalFunctionIndex::new(0) => {
+                // The unwind info points at `$one` too, leave it be.
+                let run = LocalFunctionIndex::new(2);
+                let start = code.function_address(run).unwrap();
+                let len = code.function_code(run).unwrap().len();
+                if !(start..start + len).contains(&site) {
+                    return None;
+                }
+                self.call_offsets.lock().unwrap().push(site - start);
+                code.function_address(LocalFunctionIndex::new(1))
+            }
+            _ => None,
+        }
+    }
+
+    fn patch(&self, code: &mut LinkedCode) -> Result<(), PatchError> {
+        let run = LocalFunctionIndex::new(2);
+        let bytes = code.function_code(run).unwrap().to_vec();
+        let mut patches = self.patches.lock().unwrap();
+        patches.push(code.patch(run, 0, &bytes[..1]));
+        patches.push(code.patch(run, bytes.len(), &[0]));
+        patches.push(code.patch(LocalFunctionIndex::new(3), 0, &[0]));
+        for &offset in self.call_offsets.lock().unwrap().iter() {
+            patches.push(code.patch(run, offset, &bytes[offset..offset + 1]));
+        }
+        Ok(())
+    }
+}
+
+#[compiler_test(relocation_hook)]
+fn relocation_hook_redirects_and_patches(config: crate::Config) -> Result<()> {
+    // LLVM inlines the call to `$one`, leaving nothing to relocate.
+    if config.engine != crate::Engine::Universal || config.compiler == crate::Compiler::LLVM {
+        return Ok(());
+    }
+    let hook = Hook::default();
+    let patches = hook.patches.clone();
+    let engine = Universal::new(config.compiler_config(false))
+        .relocation_hook(hook)
+        .engine();
+    let store = Store::new(&engine);
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let run: NativeFunc<(), i32> = instance.exports.get_native_function("run")?;
+    assert_eq!(run.call()?, 2);
+
+    let patches = patches.lock().unwrap();
+    assert!(patches.len() > 3, "the call to `$one` wasn't relocated");
+    assert_eq!(patches[0], Ok(()));
+    assert!(matches!(patches[1], Err(PatchError::OutOfBounds { .. })));
+    assert_eq!(
+        patches[2],
+        Err(PatchError::UnknownFunction(LocalFunctionIndex::new(3)))
+    );
+    assert!(patches[3..]
+        .iter()
+        .all(|patch| matches!(patch, Err(PatchError::OverlapsRelocation(_)))));
+    Ok(())
+}
+
+/// Fails to patch the code.
+struct Failing;
+
+impl RelocationHook for Failing {
+    fn patch(&self, _code: &mut LinkedCode) -> Result<(), PatchError> {
+        Err(PatchError::Other("no patch for you".to_string()))
+    }
+}
+
+#[compiler_test(relocation_hook)]
+fn relocation_hook_fails_loading(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let engine = Universal::new(config.compiler_config(false))
+        .relocation_hook(Failing)
+        .engine();
+    let store = Store::new(&engine);
+    let error = Module::new(&store, WAT).unwrap_err();
+    assert!(error.to_string().contains("no patch for you"));
+    Ok(())
+}
@@ -0,0 +1,49 @@
+#![cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+
+use anyhow::Result;
+use std::time::{Duration, Instant};
+use wasmer::*;
+
+#[compiler_test(sampling)]
+fn hot_functions_are_sampled(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"(module $sampled
+        ;; xorshift, which compilers can't fold
+        (func $spin (export "spin") (param $n i32) (result i32)
+          (local $x i32)
+          (local.set $x (i32.const 1))
+          (loop $next
+            (local.set $x (i32.xor (local.get $x) (i32.shl (local.get $x) (i32.const 13))))
+            (local.set $x (i32.xor (local.get $x) (i32.shr_u (local.get $x) (i32.const 17))))
+            (local.set $x (i32.xor (local.get $x) (i32.shl (local.get $x) (i32.const 5))))
+            (br_if $next
+              (local.tee $n (i32.sub (local.get $n) (i32.const 1)))))
+          (local.get $x))
+        (func (export "idle")))"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let spin: NativeFunc<i32, i32> = instance.exports.get_native_function("spin")?;
+
+    let sampled = || {
+        hot_functions()
+            .into_iter()
+            .find(|function| function.module_name == "sampled")
+    };
+    start_sampling(Duration::from_millis(1))?;
+    // The profiling timer counts the CPU time of the whole process, so
+    // the samples may land on other threads for a while.
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while sampled().is_none() && Instant::now() < deadline {
+        spin.call(10_000_000)?;
+    }
+    stop_sampling()?;
+
+    let hot = sampled().expect("no samples were taken in the module");
+    assert_eq!(hot.func_index, 0);
+    assert_eq!(hot.function_name.as_deref(), Some("spin"));
+    assert!(hot.samples > 0);
+    Ok(())
+}
@@ -0,0 +1,113 @@
+#![cfg(all(feature = "universal", unix))]
+
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use wasmer::*;
+use wasmer_engine_universal::Universal;
+
+const WAT: &str = r#"(module
+    (func $double (param i32) (result i32)
+      (i32.mul (local.get 0) (i32.const 2)))
+    (func (export "run") (param i32) (result i32)
+      (call $double
+        (i32.add
+          (local.get 0)
+          (i32.trunc_f32_s (f32.ceil (f32.const 1.5)))))))"#;
+
+fn run(config: &crate::Config, dir: &Path) -> Result<i32> {
+    let engine = Universal::new(config.compiler_config(false))
+        .shared_code_dir(dir)
+        .engine();
+    let store = Store::new(&engine);
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let run: NativeFunc<i32, i32> = instance.exports.get_native_function("run")?;
+    Ok(run.call(3)?)
+}
+
+fn shared_files(dir: &Path) -> Result<Vec<fs::DirEntry>> {
+    Ok(fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?)
+}
+
+#[compiler_test(shared_code)]
+fn shared_code_is_saved_and_reused(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let dir = tempfile::tempdir()?;
+
+    assert_eq!(run(&config, dir.path())?, 10);
+    let files = shared_files(dir.path())?;
+    assert_eq!(files.len(), 1);
+    assert!(files[0].file_name().to_string_lossy().ends_with(".code"));
+
+    // The first engine is gone, so its code can be mapped at the same
+    // address; otherwise the code is linked again.
+    assert_eq!(run(&config, dir.path())?, 10);
+    assert_eq!(shared_files(dir.path())?.len(), 1);
+    Ok(())
+}
+
+#[compiler_test(shared_code)]
+fn shared_code_ignores_invalid_files(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let dir = tempfile::tempdir()?;
+    run(&config, dir.path())?;
+    let path = shared_files(dir.path())?[0].path();
+
+    fs::write(&path, b"not code")?;
+    assert_eq!(run(&config, dir.path())?, 10);
+    assert_eq!(fs::read(&path)?, b"not code");
+    Ok(())
+}
+
+/// Zeroes the code saved to `path`, which follows the 40-byte header and
+/// the inputs of the linking, so that it fails if it's ever mapped.
+fn clobber_code(path: &Path, change_inputs: bool) -> Result<()> {
+    let mut contents = fs::read(path)?;
+    let mut inputs_len = [0u8; 8];
+    inputs_len.copy_from_slice(&contents[32..40]);
+    let code_start = 40 + u64::from_le_bytes(inputs_len) as usize;
+    for byte in &mut contents[code_start..] {
+        *byte = 0;
+    }
+    if change_inputs {
+        contents[40] ^= 0xff;
+    }
+    fs::write(path, &contents)?;
+    Ok(())
+}
+
+#[compiler_test(shared_code)]
+fn shared_code_ignores_files_from_other_inputs(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let dir = tempfile::tempdir()?;
+    run(&config, dir.path())?;
+    let path = shared_files(dir.path())?[0].path();
+
+    clobber_code(&path, true)?;
+    assert_eq!(run(&config, dir.path())?, 10);
+    Ok(())
+}
+
+#[compiler_test(shared_code)]
+fn shared_code_ignores_files_writable_by_others(config: crate::Config) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let dir = tempfile::tempdir()?;
+    run(&config, dir.path())?;
+    let path = shared_files(dir.path())?[0].path();
+
+    clobber_code(&path, false)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o666))?;
+    assert_eq!(run(&config, dir.path())?, 10);
+    Ok(())
+}
@@ -0,0 +1,58 @@
+#![cfg(all(
+    feature = "singlepass",
+    feature = "universal",
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+
+use anyhow::Result;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasmer::*;
+use wasmer_compiler_singlepass::Singlepass;
+use wasmer_engine_universal::Universal;
+
+#[test]
+fn single_step_reports_locals_and_stack() -> Result<()> {
+    let mut compiler = Singlepass::new();
+    compiler.single_step(true);
+    let store = Store::new(&Universal::new(compiler).engine());
+    let wat = r#"(module
+        (func (export "add") (param i32 i32) (result i32)
+           local.get 0
+           local.get 1
+           i32.add))"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let add: NativeFunc<(i32, i32), i32> = instance.exports.get_native_function("add")?;
+
+    let steps = Rc::new(RefCell::new(Vec::new()));
+    let recorded = steps.clone();
+    set_step_callback(Some(Box::new(move |step: &Step| {
+        recorded.borrow_mut().push(step.clone())
+    })));
+    let result = add.call(3, 4);
+    set_step_callback(None);
+    assert_eq!(result?, 7);
+
+    // The `end` of the function returns, so it has no step.
+    let steps = steps.borrow();
+    assert_eq!(steps.len(), 3);
+    let low_bits = |values: &[Option<u64>]| {
+        values
+            .iter()
+            .map(|value| value.map(|value| value as u32))
+            .collect::<Vec<_>>()
+    };
+    for step in steps.iter() {
+        assert_eq!(step.func_index, 0);
+        assert_eq!(low_bits(&step.locals), vec![Some(3), Some(4)]);
+    }
+    assert_eq!(low_bits(&steps[0].stack), vec![Some(3)]);
+    assert_eq!(low_bits(&steps[1].stack), vec![Some(3), Some(4)]);
+    assert_eq!(low_bits(&steps[2].stack), vec![Some(7)]);
+    assert!(steps
+        .windows(2)
+        .all(|pair| pair[0].module_offset < pair[1].module_offset));
+    Ok(())
+}
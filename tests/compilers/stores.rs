@@ -0,0 +1,90 @@
+//! Many independent stores living in the same process, each with its own
+//! engine, created and dropped concurrently.
+
+use anyhow::Result;
+use std::thread;
+use wasmer::*;
+
+const THREADS: usize = 8;
+const STORES_PER_THREAD: usize = 32;
+
+const WAT: &str = r#"(module
+  (type $binary (func (param i32 i32) (result i32)))
+  (table 2 funcref)
+  (elem (i32.const 0) $add $sub)
+  (func $add (type $binary) (i32.add (local.get 0) (local.get 1)))
+  (func $sub (type $binary) (i32.sub (local.get 0) (local.get 1)))
+  (func (export "apply") (param i32 i32 i32) (result i32)
+    (call_indirect (type $binary) (local.get 1) (local.get 2) (local.get 0)))
+  (func $fail (export "fail") (unreachable)))"#;
+
+/// Compiles and runs `WAT` in a new store, then drops it.
+fn run_in_new_store(config: &crate::Config, canonicalize_nans: bool) -> Result<()> {
+    let engine = config.engine(config.compiler_config(canonicalize_nans));
+    let store = Store::new(&*engine);
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    let apply = instance
+        .exports
+        .get_native_function::<(i32, i32, i32), i32>("apply")?;
+    assert_eq!(apply.call(0, 7, 5)?, 12);
+    assert_eq!(apply.call(1, 7, 5)?, 2);
+    assert!(apply.call(2, 7, 5).is_err());
+
+    // The trace is symbolicated with the frame information of this store's
+    // module, even while others are registered and unregistered.
+    let fail = instance.exports.get_native_function::<(), ()>("fail")?;
+    let trace = fail.call().unwrap_err().trace().to_vec();
+    assert_eq!(trace.len(), 1);
+    assert_eq!(trace[0].function_name(), Some("fail"));
+    Ok(())
+}
+
+#[compiler_test(stores)]
+fn create_and_drop_stores_concurrently(config: crate::Config) -> Result<()> {
+    let handles = (0..THREADS)
+        .map(|thread| {
+            let config = config.clone();
+            thread::spawn(move || -> Result<()> {
+                for store in 0..STORES_PER_THREAD {
+                    // Alternate between two compiler configurations.
+                    run_in_new_store(&config, (thread + store) % 2 == 0)?;
+                }
+                Ok(())
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().unwrap()?;
+    }
+    Ok(())
+}
+
+#[compiler_test(stores)]
+fn stores_outlive_others(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let apply = instance
+        .exports
+        .get_native_function::<(i32, i32, i32), i32>("apply")?;
+
+    for _ in 0..STORES_PER_THREAD {
+        let other = config.store();
+        let other_module = Module::new(&other, WAT)?;
+        let other_instance = Instance::new(&other_module, &imports! {})?;
+        let other_apply = other_instance
+            .exports
+            .get_native_function::<(i32, i32, i32), i32>("apply")?;
+        assert_eq!(other_apply.call(1, 3, 2)?, 1);
+        assert_eq!(apply.call(0, 3, 2)?, 5);
+    }
+
+    // The first store is unaffected by the others having been dropped.
+    assert_eq!(apply.call(1, 3, 2)?, 1);
+    let fail = instance.exports.get_native_function::<(), ()>("fail")?;
+    let trace = fail.call().unwrap_err().trace().to_vec();
+    assert_eq!(trace[0].function_name(), Some("fail"));
+    Ok(())
+}
@@ -36,6 +36,159 @@ fn test_trap_return(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+#[compiler_test(traps)]
+fn test_custom_trap_from_import(config: crate::Config) -> Result<()> {
+    const POLICY_VIOLATION: u32 = 7;
+
+    let store = config.store();
+    let wat = r#"
+        (module
+        (func $check (import "" "check"))
+        (func (export "run") (call $check))
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let check_func = Function::new_native(&store, || -> Result<(), RuntimeError> {
+        Err(CustomTrap::new(POLICY_VIOLATION, "sandbox policy violated")
+            .with_payload(String::from("open"))
+            .into())
+    });
+
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "" => {
+                "check" => check_func
+            }
+        },
+    )?;
+    let run_func = instance
+        .exports
+        .get_function("run")
+        .expect("expected function export");
+
+    let e = run_func.call(&[]).err().expect("error calling function");
+
+    assert_eq!(e.message(), "sandbox policy violated");
+    let trap = e.custom_trap().expect("expected a custom trap");
+    assert_eq!(trap.code(), POLICY_VIOLATION);
+    assert_eq!(trap.payload::<String>().map(String::as_str), Some("open"));
+    assert_eq!(trap.payload::<u32>(), None);
+    assert_eq!(e.to_trap(), Some(TrapCode::Custom));
+
+    Ok(())
+}
+
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(traps)]
+fn test_full_trace_interleaves_host_frames(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let callee = Module::new(
+        &store,
+        r#"
+        (module $callee
+            (func (export "die") (unreachable))
+        )
+    "#,
+    )?;
+    let callee = Instance::new(&callee, &imports! {})?;
+    let die = callee.exports.get_function("die")?.clone();
+
+    let caller = Module::new(
+        &store,
+        r#"
+        (module $caller
+            (func $host (import "" "host"))
+            (func (export "run") (call $host))
+        )
+    "#,
+    )?;
+    // Dynamic functions are called through trampolines, which can't be
+    // unwound through on every platform.
+    #[derive(WasmerEnv, Clone)]
+    struct Env {
+        die: Function,
+    }
+    fn host(env: &Env) -> Result<(), RuntimeError> {
+        env.die.call(&[])?;
+        Ok(())
+    }
+    let host = Function::new_native_with_env(&store, Env { die }, host);
+    let caller = Instance::new(
+        &caller,
+        &imports! {
+            "" => {
+                "host" => host
+            }
+        },
+    )?;
+    let run_func = caller.exports.get_function("run")?;
+
+    let e = run_func.call(&[]).err().expect("error calling function");
+    let trace = e.full_trace();
+    assert_eq!(e.to_trap(), Some(TrapCode::UnreachableCodeReached));
+
+    let wasm_modules = trace
+        .iter()
+        .filter_map(|frame| match frame {
+            TraceFrame::Wasm(frame) => Some(frame.module_name()),
+            TraceFrame::Host(_) => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(wasm_modules, vec!["callee", "caller"]);
+
+    // The host function is between the two wasm frames
+    let callee_position = trace
+        .iter()
+        .position(|frame| matches!(frame, TraceFrame::Wasm(_)))
+        .unwrap();
+    assert!(trace[callee_position + 1..]
+        .iter()
+        .take_while(|frame| matches!(frame, TraceFrame::Host(_)))
+        .any(|frame| frame
+            .to_string()
+            .contains("test_full_trace_interleaves_host_frames")));
+
+    Ok(())
+}
+
+#[compiler_test(traps)]
+fn test_trace_id_is_stable(config: crate::Config) -> Result<()> {
+    let wat = r#"
+        (module $a
+            (func $die (param i32)
+                (if (local.get 0) (then unreachable))
+                unreachable)
+            (func (export "run") (param i32) (call $die (local.get 0)))
+        )
+    "#;
+    let trap = |store: &Store, arg: i32| -> Result<RuntimeError> {
+        let module = Module::new(store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let run_func = instance.exports.get_function("run")?;
+        Ok(run_func
+            .call(&[Val::I32(arg)])
+            .err()
+            .expect("error calling function"))
+    };
+
+    // The identifier doesn't depend on where the code was loaded
+    let first = trap(&config.store(), 1)?;
+    let second = trap(&config.store(), 1)?;
+    assert!(first.trace_id().is_some());
+    assert_eq!(first.trace_id(), second.trace_id());
+
+    // A trap at another instruction has another identifier
+    let other = trap(&config.store(), 0)?;
+    assert_ne!(first.trace_id(), other.trace_id());
+
+    // Errors not raised from WebAssembly have none
+    assert_eq!(RuntimeError::new("host").trace_id(), None);
+
+    Ok(())
+}
+
 #[cfg_attr(target_env = "musl", ignore)]
 #[compiler_test(traps)]
 fn test_trap_trace(config: crate::Config) -> Result<()> {
@@ -469,3 +622,29 @@ fn present_after_module_drop(config: crate::Config) -> Result<()> {
         // assert_eq!(t.trace()[0].func_index(), 0);
     }
 }
+
+#[compiler_test(traps)]
+fn out_of_bounds_access_fault_address(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "load") (param i32) (result i32)
+                (i32.load offset=8 (local.get 0)))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let memory = instance.exports.get_memory("memory")?;
+    let load: NativeFunc<i32, i32> = instance.exports.get_native_function("load")?;
+
+    let e = load.call(0x10000).unwrap_err();
+    assert_eq!(e.clone().to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    // Only accesses caught by the hardware, rather than by explicit bounds
+    // checks, know the faulting address.
+    if let Some(address) = e.fault_address() {
+        assert_eq!(address - memory.data_ptr() as usize, 0x10008);
+    }
+
+    Ok(())
+}
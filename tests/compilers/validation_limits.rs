@@ -0,0 +1,87 @@
+#![cfg(feature = "universal")]
+
+use anyhow::Result;
+use wasmer::*;
+use wasmer_engine_universal::Universal;
+
+fn store(config: &crate::Config, limits: ValidationLimits) -> Store {
+    let engine = Universal::new(config.compiler_config(false))
+        .validation_limits(limits)
+        .engine();
+    Store::new(&engine)
+}
+
+fn exceeded(store: &Store, wat: &str) -> Option<LimitExceeded> {
+    match Module::new(store, wat) {
+        Err(CompileError::LimitExceeded(exceeded)) => Some(exceeded),
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => None,
+    }
+}
+
+#[compiler_test(validation_limits)]
+fn modules_within_limits_compile(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let mut limits = ValidationLimits::new();
+    limits
+        .max_function_body_size(64)
+        .max_locals(2)
+        .max_br_table_targets(2)
+        .max_nesting_depth(2);
+    let store = store(&config, limits);
+
+    let wat = r#"(module
+        (func (param i32) (local i32 i32)
+          (block (block
+            (br_table 0 1 1 (local.get 0))))))"#;
+    assert_eq!(exceeded(&store, wat), None);
+    Ok(())
+}
+
+#[compiler_test(validation_limits)]
+fn modules_over_limits_are_rejected(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let mut limits = ValidationLimits::new();
+    limits.max_locals(2);
+    let exceeded_limit = exceeded(
+        &store(&config, limits),
+        "(module (func (local i32 i64) (local f32)))",
+    )
+    .expect("the module has too many locals");
+    assert_eq!(exceeded_limit.limit, ValidationLimit::Locals);
+    assert_eq!(exceeded_limit.value, 3);
+    assert_eq!(exceeded_limit.max, 2);
+
+    let mut limits = ValidationLimits::new();
+    limits.max_nesting_depth(2);
+    let exceeded_limit = exceeded(
+        &store(&config, limits),
+        "(module (func (block (loop (if (i32.const 0) (then))))))",
+    )
+    .expect("the function is nested too deeply");
+    assert_eq!(exceeded_limit.limit, ValidationLimit::NestingDepth);
+
+    let mut limits = ValidationLimits::new();
+    limits.max_br_table_targets(1);
+    let exceeded_limit = exceeded(
+        &store(&config, limits),
+        "(module (func (block (block (br_table 0 1 0 (i32.const 0))))))",
+    )
+    .expect("the br_table has too many targets");
+    assert_eq!(exceeded_limit.limit, ValidationLimit::BrTableTargets);
+    assert_eq!(exceeded_limit.value, 2);
+
+    let mut limits = ValidationLimits::new();
+    limits.max_function_body_size(4);
+    let exceeded_limit = exceeded(
+        &store(&config, limits),
+        "(module (func (drop (i32.add (i32.const 1) (i32.const 2)))))",
+    )
+    .expect("the function is too large");
+    assert_eq!(exceeded_limit.limit, ValidationLimit::FunctionBodySize);
+    Ok(())
+}
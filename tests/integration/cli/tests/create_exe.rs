@@ -26,6 +26,8 @@ struct WasmerCreateExe {
     native_executable_path: PathBuf,
     /// Compiler with which to compile the Wasm.
     compiler: Compiler,
+    /// Additional flags given to the command.
+    extra_cli_flags: Vec<&'static str>,
 }
 
 impl Default for WasmerCreateExe {
@@ -40,6 +42,7 @@ impl Default for WasmerCreateExe {
             wasm_path: PathBuf::from(create_exe_test_wasm_path()),
             native_executable_path,
             compiler: Compiler::Cranelift,
+            extra_cli_flags: vec![],
         }
     }
 }
@@ -53,6 +56,7 @@ impl WasmerCreateExe {
             .arg(&self.compiler.to_flag())
             .arg("-o")
             .arg(&self.native_executable_path)
+            .args(&self.extra_cli_flags)
             .output()?;
 
         if !output.status.success() {
@@ -101,6 +105,40 @@ fn create_exe_works() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn create_exe_works_with_function_sections() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let operating_dir: PathBuf = temp_dir.path().to_owned();
+
+    let wasm_path = operating_dir.join(create_exe_test_wasm_path());
+    #[cfg(not(windows))]
+    let executable_path = operating_dir.join("wasm.out");
+    #[cfg(windows)]
+    let executable_path = operating_dir.join("wasm.exe");
+
+    WasmerCreateExe {
+        current_dir: operating_dir.clone(),
+        wasm_path: wasm_path,
+        native_executable_path: executable_path.clone(),
+        compiler: Compiler::Cranelift,
+        extra_cli_flags: vec!["--function-sections"],
+        ..Default::default()
+    }
+    .run()
+    .context("Failed to create-exe wasm with Wasmer")?;
+
+    let result = run_code(
+        &operating_dir,
+        &executable_path,
+        &["--eval".to_string(), "function greet(name) { return JSON.stringify('Hello, ' + name); }; print(greet('World'));".to_string()],
+    )
+    .context("Failed to run generated executable")?;
+    let result_lines = result.lines().collect::<Vec<&str>>();
+    assert_eq!(result_lines, vec!["\"Hello, World\""],);
+
+    Ok(())
+}
+
 #[test]
 fn create_exe_works_with_file() -> anyhow::Result<()> {
     let temp_dir = tempfile::tempdir()?;
@@ -75,3 +75,52 @@ fn run_no_start_wasm_report_error() -> anyhow::Result<()> {
     assert_eq!(result.contains("Can not find any export functions."), true);
     Ok(())
 }
+
+#[test]
+fn run_bundled_module_works() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let bundle_path = temp_dir.path().join("modules.wasmu-bundle");
+
+    let output = Command::new(WASMER_PATH)
+        .arg("bundle")
+        .arg(test_no_imports_wat_path())
+        .arg(test_no_start_wat_path())
+        .arg("-o")
+        .arg(&bundle_path)
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "bundling failed with: stdout: {}\n\nstderr: {}",
+            std::str::from_utf8(&output.stdout)
+                .expect("stdout is not utf8! need to handle arbitrary bytes"),
+            std::str::from_utf8(&output.stderr)
+                .expect("stderr is not utf8! need to handle arbitrary bytes")
+        );
+    }
+
+    let output = Command::new(WASMER_PATH)
+        .arg("run")
+        .arg(&bundle_path)
+        .arg("--bundle-module")
+        .arg("fib")
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "running the bundled module failed with: stdout: {}\n\nstderr: {}",
+            std::str::from_utf8(&output.stdout)
+                .expect("stdout is not utf8! need to handle arbitrary bytes"),
+            std::str::from_utf8(&output.stderr)
+                .expect("stderr is not utf8! need to handle arbitrary bytes")
+        );
+    }
+
+    // The bundle holds two modules, so one must be selected.
+    let output = Command::new(WASMER_PATH)
+        .arg("run")
+        .arg(&bundle_path)
+        .output()?;
+    assert_eq!(output.status.success(), false);
+    let result = std::str::from_utf8(&output.stderr).unwrap().to_string();
+    assert_eq!(result.contains("--bundle-module"), true);
+    Ok(())
+}
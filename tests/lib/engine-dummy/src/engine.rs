@@ -72,6 +72,11 @@ impl Engine for DummyEngine {
         self.signatures.lookup(sig)
     }
 
+    /// The shared signature registry
+    fn signature_registry(&self) -> Arc<SignatureRegistry> {
+        self.signatures.clone()
+    }
+
     #[cfg(feature = "compiler")]
     /// Validates a WebAssembly module
     fn validate(&self, binary: &[u8]) -> Result<(), CompileError> {